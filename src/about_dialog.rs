@@ -0,0 +1,232 @@
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{HBRUSH, HFONT, COLOR_BTNFACE},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Input::KeyboardAndMouse::{EnableWindow, SetFocus},
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::everything_sdk::EverythingSDK;
+use crate::lang::{fill, get_strings};
+use crate::logger::log_error;
+use crate::thumbnail::to_wide;
+use crate::update_check::{Release, CURRENT_VERSION, check_for_updates};
+
+const ABOUT_BOX_CLASS: &str = "EverythingLikeAboutBox";
+
+// Class, text, style, id and the x, y, width and height of a control
+type ControlSpec = (PCWSTR, String, WINDOW_STYLE, i32, i32, i32, i32, i32);
+
+const ID_ABOUT_STATUS: i32 = 9311;
+const ID_ABOUT_CHECK_UPDATES: i32 = 9312;
+
+// Posted by the update check with a boxed Result<Release, String> in WPARAM
+const WM_UPDATE_CHECKED: u32 = WM_USER + 116;
+
+// Posted to the owner with a boxed Release in WPARAM when a newer release
+// was found; the owner offers to install it
+pub const WM_UPDATE_FOUND: u32 = WM_USER + 117;
+
+// Set when the dialog is closed. It is modal, so there is at most one at
+// a time.
+static ABOUT_BOX_DONE: AtomicBool = AtomicBool::new(false);
+
+pub fn register_about_box_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(about_box_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as isize),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeAboutBox"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// Help → About: the version, when it was built and whether the Everything
+// SDK can reach Everything, with a button that looks for a newer release
+pub fn show_about_dialog(owner: HWND, font: HFONT) {
+    unsafe {
+        let strings = get_strings();
+        let instance = match GetModuleHandleW(None) {
+            Ok(module) => HINSTANCE(module.0),
+            Err(_) => return,
+        };
+        let (width, height) = (440, 250);
+
+        let mut owner_rect = RECT::default();
+        let _ = GetWindowRect(owner, &mut owner_rect);
+        let x = owner_rect.left + ((owner_rect.right - owner_rect.left) - width) / 2;
+        let y = owner_rect.top + ((owner_rect.bottom - owner_rect.top) - height) / 2;
+
+        let window = CreateWindowExW(
+            WS_EX_DLGMODALFRAME,
+            PCWSTR::from_raw(to_wide(ABOUT_BOX_CLASS).as_ptr()),
+            PCWSTR::from_raw(to_wide(&strings.help_about).as_ptr()),
+            WS_POPUP | WS_CAPTION | WS_SYSMENU | WS_VISIBLE,
+            x.max(0),
+            y.max(0),
+            width,
+            height,
+            owner,
+            None,
+            instance,
+            None,
+        );
+
+        if window.0 == 0 {
+            log_error("Failed to create about box");
+            return;
+        }
+
+        let sdk_status = match EverythingSDK::new() {
+            Ok(sdk) => match sdk.everything_version() {
+                Some(version) => fill(&strings.about_sdk_running, &[("version", &version)]),
+                None => strings.about_sdk_not_running.clone(),
+            },
+            Err(e) => fill(&strings.about_sdk_missing, &[("error", &e.to_string())]),
+        };
+
+        let mut client_rect = RECT::default();
+        let _ = GetClientRect(window, &mut client_rect);
+        let (client_width, client_height) = (client_rect.right, client_rect.bottom);
+
+        let lines = [
+            "EverythingLike".to_string(),
+            fill(&strings.about_version, &[("version", CURRENT_VERSION)]),
+            fill(&strings.about_build_date, &[("date", env!("BUILD_DATE"))]),
+            sdk_status,
+            env!("CARGO_PKG_HOMEPAGE").to_string(),
+        ];
+        let mut controls: Vec<ControlSpec> = lines
+            .into_iter()
+            .enumerate()
+            .map(|(index, line)| (w!("STATIC"), line, WINDOW_STYLE(0), -1, 14, 12 + index as i32 * 24, client_width - 28, 20))
+            .collect();
+        controls.extend([
+            (w!("STATIC"), String::new(), WINDOW_STYLE(0), ID_ABOUT_STATUS, 14, client_height - 78, client_width - 28, 20),
+            (w!("BUTTON"), strings.about_check_updates.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), ID_ABOUT_CHECK_UPDATES, 14, client_height - 40, 180, 26),
+            (w!("BUTTON"), strings.settings_ok.clone(), WS_TABSTOP | WINDOW_STYLE(BS_DEFPUSHBUTTON as u32), IDOK.0, client_width - 94, client_height - 40, 80, 26),
+        ]);
+        for (class, text, style, id, x, y, width, height) in controls {
+            let control = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                class,
+                PCWSTR::from_raw(to_wide(&text).as_ptr()),
+                WS_CHILD | WS_VISIBLE | style,
+                x, y, width, height,
+                window,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(control, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        }
+
+        let _ = SetFocus(GetDlgItem(window, IDOK.0));
+
+        ABOUT_BOX_DONE.store(false, Ordering::Relaxed);
+        EnableWindow(owner, FALSE);
+
+        // Local message loop; IsDialogMessage maps Enter/Esc to IDOK/IDCANCEL
+        let mut message = MSG::default();
+        while !ABOUT_BOX_DONE.load(Ordering::Relaxed) {
+            if !GetMessageW(&mut message, None, 0, 0).as_bool() {
+                // Let the main loop see the quit message too
+                PostQuitMessage(message.wParam.0 as i32);
+                break;
+            }
+            if !IsDialogMessageW(window, &message).as_bool() {
+                TranslateMessage(&message);
+                DispatchMessageW(&message);
+            }
+        }
+
+        EnableWindow(owner, TRUE);
+        if IsWindow(window).as_bool() {
+            let _ = DestroyWindow(window);
+        }
+        let _ = SetForegroundWindow(owner);
+    }
+}
+
+fn set_status(window: HWND, text: &str) {
+    unsafe {
+        let _ = SetWindowTextW(GetDlgItem(window, ID_ABOUT_STATUS), PCWSTR::from_raw(to_wide(text).as_ptr()));
+    }
+}
+
+fn show_update_result(window: HWND, result: std::result::Result<Release, String>) {
+    let strings = get_strings();
+    match result {
+        Ok(release) if release.is_newer() => {
+            set_status(window, &fill(&strings.about_update_available, &[("version", &release.version)]));
+            
+            // The owner takes it from here, since a download outlives the dialog
+            let release_ptr = Box::into_raw(Box::new(release)) as usize;
+            unsafe {
+                let owner = GetWindow(window, GW_OWNER);
+                if PostMessageW(owner, WM_UPDATE_FOUND, WPARAM(release_ptr), LPARAM(0)).is_ok() {
+                    ABOUT_BOX_DONE.store(true, Ordering::Relaxed);
+                } else {
+                    drop(Box::from_raw(release_ptr as *mut Release));
+                }
+            }
+        }
+        Ok(_) => set_status(window, &strings.about_up_to_date),
+        Err(e) => set_status(window, &fill(&strings.error_update_check, &[("error", &e)])),
+    }
+}
+
+extern "system" fn about_box_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_COMMAND => {
+                let id = (wparam.0 & 0xFFFF) as i32;
+                if id == IDOK.0 || id == IDCANCEL.0 {
+                    ABOUT_BOX_DONE.store(true, Ordering::Relaxed);
+                } else if id == ID_ABOUT_CHECK_UPDATES {
+                    EnableWindow(GetDlgItem(window, ID_ABOUT_CHECK_UPDATES), FALSE);
+                    set_status(window, &get_strings().about_checking);
+                    check_for_updates(window, WM_UPDATE_CHECKED);
+                }
+                LRESULT(0)
+            }
+            WM_UPDATE_CHECKED => {
+                let result = *Box::from_raw(wparam.0 as *mut std::result::Result<Release, String>);
+                EnableWindow(GetDlgItem(window, ID_ABOUT_CHECK_UPDATES), TRUE);
+                show_update_result(window, result);
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                ABOUT_BOX_DONE.store(true, Ordering::Relaxed);
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
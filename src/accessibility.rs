@@ -0,0 +1,563 @@
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{ClientToScreen, ScreenToClient},
+        System::{
+            Com::{IDispatch, IDispatch_Vtbl, DISPATCH_FLAGS, DISPPARAMS, EXCEPINFO},
+            Variant::{VARIANT, VT_EMPTY, VT_I4},
+        },
+        UI::{
+            Accessibility::*,
+            Controls::{STATE_SYSTEM_FOCUSABLE, STATE_SYSTEM_INVISIBLE, STATE_SYSTEM_OFFSCREEN},
+            Input::KeyboardAndMouse::GetFocus,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use std::ffi::c_void;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// An element of the owner-drawn list that screen readers can visit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AccessibleChild {
+    Header(usize),
+    Row(usize),
+}
+
+// What the accessible object asks the list view about. Rectangles and
+// points are in client coordinates.
+pub trait AccessibleList {
+    fn list_name(&self) -> String;
+    // Column headers, only while they are on screen
+    fn header_count(&self) -> usize;
+    fn header_name(&self, column: usize) -> String;
+    fn row_count(&self) -> usize;
+    fn row_name(&self, row: usize) -> String;
+    // The other columns of the row, as "Column: value" pairs
+    fn row_description(&self, row: usize) -> String;
+    // None while the element is scrolled out of view
+    fn child_rect(&self, child: AccessibleChild) -> Option<RECT>;
+    fn child_at(&self, x: i32, y: i32) -> Option<AccessibleChild>;
+    fn focused_row(&self) -> Option<usize>;
+    fn is_row_selected(&self, row: usize) -> bool;
+    fn selected_count(&self) -> usize;
+    // `flags` are the SELFLAG_* values of IAccessible::accSelect
+    fn select_row(&mut self, row: usize, flags: u32);
+    fn default_action(&self, child: AccessibleChild) -> Option<String>;
+    fn do_default_action(&mut self, child: AccessibleChild);
+}
+
+// Finds the list behind a window; None once the window is gone
+pub type ListLookup = unsafe fn(HWND) -> Option<&'static mut dyn AccessibleList>;
+
+// Children are numbered from 1, the column headers before the rows
+pub fn child_id(list: &dyn AccessibleList, child: AccessibleChild) -> i32 {
+    match child {
+        AccessibleChild::Header(column) => column as i32 + 1,
+        AccessibleChild::Row(row) => (list.header_count() + row) as i32 + 1,
+    }
+}
+
+fn child_from_id(list: &dyn AccessibleList, id: i32) -> Option<AccessibleChild> {
+    let index = usize::try_from(id).ok()?.checked_sub(1)?;
+    let headers = list.header_count();
+    if index < headers {
+        Some(AccessibleChild::Header(index))
+    } else if index - headers < list.row_count() {
+        Some(AccessibleChild::Row(index - headers))
+    } else {
+        None
+    }
+}
+
+// Answers WM_GETOBJECT for the client area with an IAccessible that
+// describes the list. Other objects are left to DefWindowProc.
+pub fn handle_get_object(window: HWND, wparam: WPARAM, lparam: LPARAM, lookup: ListLookup) -> Option<LRESULT> {
+    if lparam.0 as i32 != OBJID_CLIENT.0 {
+        return None;
+    }
+
+    unsafe {
+        let mut standard: *mut c_void = std::ptr::null_mut();
+        CreateStdAccessibleObject(window, OBJID_CLIENT.0, &IAccessible::IID, &mut standard).ok()?;
+
+        let object = Box::new(ListAccessible {
+            vtable: &LIST_ACCESSIBLE_VTBL,
+            references: AtomicU32::new(1),
+            window,
+            lookup,
+            standard: IAccessible::from_raw(standard),
+        });
+        // LresultFromObject takes its own reference
+        let unknown = IUnknown::from_raw(Box::into_raw(object) as *mut c_void);
+        Some(LresultFromObject(&IAccessible::IID, wparam, &unknown))
+    }
+}
+
+// Tells screen readers which row has the focus and how the selection
+// changed. Call after every selection change.
+pub fn notify_selection_changed(window: HWND, list: &dyn AccessibleList) {
+    let Some(row) = list.focused_row() else {
+        return;
+    };
+    let child = child_id(list, AccessibleChild::Row(row));
+    unsafe {
+        if list.is_row_selected(row) && list.selected_count() == 1 {
+            NotifyWinEvent(EVENT_OBJECT_SELECTION, window, OBJID_CLIENT.0, child);
+        } else {
+            NotifyWinEvent(EVENT_OBJECT_SELECTIONWITHIN, window, OBJID_CLIENT.0, CHILDID_SELF as i32);
+        }
+        if GetFocus() == window {
+            NotifyWinEvent(EVENT_OBJECT_FOCUS, window, OBJID_CLIENT.0, child);
+        }
+    }
+}
+
+// Announces the focused row when the list gets the keyboard focus
+pub fn notify_focus(window: HWND, list: &dyn AccessibleList) {
+    let child = list
+        .focused_row()
+        .map_or(CHILDID_SELF as i32, |row| child_id(list, AccessibleChild::Row(row)));
+    unsafe {
+        NotifyWinEvent(EVENT_OBJECT_FOCUS, window, OBJID_CLIENT.0, child);
+    }
+}
+
+// The rows were replaced, by new search results say
+pub fn notify_rows_changed(window: HWND) {
+    unsafe {
+        NotifyWinEvent(EVENT_OBJECT_REORDER, window, OBJID_CLIENT.0, CHILDID_SELF as i32);
+    }
+}
+
+// IAccessible for the list view, with a hand-written vtable. The rows and
+// headers are simple elements identified by child ID, so this is the only
+// object handed out.
+#[repr(C)]
+struct ListAccessible {
+    vtable: *const IAccessible_Vtbl,
+    references: AtomicU32,
+    window: HWND,
+    lookup: ListLookup,
+    // The system's object for the client area; it answers for the list
+    // itself: its parent, position and window state
+    standard: IAccessible,
+}
+
+// What a VARIANT child argument refers to
+enum Target {
+    List,
+    Child(AccessibleChild),
+}
+
+impl ListAccessible {
+    unsafe fn from_this<'a>(this: *mut c_void) -> &'a Self {
+        &*(this as *const Self)
+    }
+
+    unsafe fn list(&self) -> Option<&'static mut dyn AccessibleList> {
+        (self.lookup)(self.window)
+    }
+
+    // The list and the target named by `child`, or the HRESULT to return
+    unsafe fn target(&self, child: &VARIANT) -> std::result::Result<(&'static mut dyn AccessibleList, Target), HRESULT> {
+        let list = self.list().ok_or(E_FAIL)?;
+        let id = variant_i4(child).ok_or(E_INVALIDARG)?;
+        if id == CHILDID_SELF as i32 {
+            return Ok((list, Target::List));
+        }
+        let child = child_from_id(list, id).ok_or(E_INVALIDARG)?;
+        Ok((list, Target::Child(child)))
+    }
+}
+
+fn variant_i4(variant: &VARIANT) -> Option<i32> {
+    unsafe {
+        let inner = &variant.Anonymous.Anonymous;
+        if inner.vt == VT_I4 {
+            Some(inner.Anonymous.lVal)
+        } else {
+            None
+        }
+    }
+}
+
+unsafe fn write_i4(out: *mut VARIANT, value: i32) {
+    let mut variant = VARIANT::default();
+    (*variant.Anonymous.Anonymous).vt = VT_I4;
+    (*variant.Anonymous.Anonymous).Anonymous.lVal = value;
+    out.write(variant);
+}
+
+unsafe fn write_empty(out: *mut VARIANT) {
+    let mut variant = VARIANT::default();
+    (*variant.Anonymous.Anonymous).vt = VT_EMPTY;
+    out.write(variant);
+}
+
+unsafe fn write_string(out: *mut MaybeUninit<BSTR>, text: &str) -> HRESULT {
+    (*out).write(BSTR::from(text));
+    S_OK
+}
+
+static LIST_ACCESSIBLE_VTBL: IAccessible_Vtbl = IAccessible_Vtbl {
+    base__: IDispatch_Vtbl {
+        base__: IUnknown_Vtbl {
+            QueryInterface: query_interface,
+            AddRef: add_ref,
+            Release: release,
+        },
+        GetTypeInfoCount: get_type_info_count,
+        GetTypeInfo: get_type_info,
+        GetIDsOfNames: get_ids_of_names,
+        Invoke: invoke,
+    },
+    accParent: acc_parent,
+    accChildCount: acc_child_count,
+    get_accChild: get_acc_child,
+    get_accName: get_acc_name,
+    get_accValue: get_acc_value,
+    get_accDescription: get_acc_description,
+    get_accRole: get_acc_role,
+    get_accState: get_acc_state,
+    get_accHelp: get_acc_help,
+    get_accHelpTopic: get_acc_help_topic,
+    get_accKeyboardShortcut: get_acc_keyboard_shortcut,
+    accFocus: acc_focus,
+    accSelection: acc_selection,
+    get_accDefaultAction: get_acc_default_action,
+    accSelect: acc_select,
+    accLocation: acc_location,
+    accNavigate: acc_navigate,
+    accHitTest: acc_hit_test,
+    accDoDefaultAction: acc_do_default_action,
+    put_accName: put_acc_name,
+    put_accValue: put_acc_value,
+};
+
+unsafe extern "system" fn query_interface(this: *mut c_void, iid: *const GUID, interface: *mut *mut c_void) -> HRESULT {
+    if interface.is_null() {
+        return E_POINTER;
+    }
+    if [IUnknown::IID, IDispatch::IID, IAccessible::IID].contains(&*iid) {
+        add_ref(this);
+        *interface = this;
+        S_OK
+    } else {
+        *interface = std::ptr::null_mut();
+        E_NOINTERFACE
+    }
+}
+
+unsafe extern "system" fn add_ref(this: *mut c_void) -> u32 {
+    ListAccessible::from_this(this).references.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+unsafe extern "system" fn release(this: *mut c_void) -> u32 {
+    let remaining = ListAccessible::from_this(this).references.fetch_sub(1, Ordering::Release) - 1;
+    if remaining == 0 {
+        std::sync::atomic::fence(Ordering::Acquire);
+        drop(Box::from_raw(this as *mut ListAccessible));
+    }
+    remaining
+}
+
+// Screen readers call IAccessible directly, so there is no type library
+unsafe extern "system" fn get_type_info_count(_this: *mut c_void, count: *mut u32) -> HRESULT {
+    *count = 0;
+    S_OK
+}
+
+unsafe extern "system" fn get_type_info(_this: *mut c_void, _index: u32, _lcid: u32, _info: *mut *mut c_void) -> HRESULT {
+    E_NOTIMPL
+}
+
+unsafe extern "system" fn get_ids_of_names(
+    _this: *mut c_void,
+    _iid: *const GUID,
+    _names: *const PCWSTR,
+    _count: u32,
+    _lcid: u32,
+    _ids: *mut i32,
+) -> HRESULT {
+    E_NOTIMPL
+}
+
+unsafe extern "system" fn invoke(
+    _this: *mut c_void,
+    _member: i32,
+    _iid: *const GUID,
+    _lcid: u32,
+    _flags: DISPATCH_FLAGS,
+    _params: *const DISPPARAMS,
+    _result: *mut VARIANT,
+    _exception: *mut EXCEPINFO,
+    _argument_error: *mut u32,
+) -> HRESULT {
+    E_NOTIMPL
+}
+
+unsafe extern "system" fn acc_parent(this: *mut c_void, parent: *mut *mut c_void) -> HRESULT {
+    match ListAccessible::from_this(this).standard.accParent() {
+        Ok(dispatch) => {
+            *parent = dispatch.into_raw();
+            S_OK
+        }
+        Err(e) => e.code(),
+    }
+}
+
+unsafe extern "system" fn acc_child_count(this: *mut c_void, count: *mut i32) -> HRESULT {
+    match ListAccessible::from_this(this).list() {
+        Some(list) => {
+            *count = (list.header_count() + list.row_count()) as i32;
+            S_OK
+        }
+        None => E_FAIL,
+    }
+}
+
+// Rows and headers have no objects of their own
+unsafe extern "system" fn get_acc_child(this: *mut c_void, child: VARIANT, dispatch: *mut *mut c_void) -> HRESULT {
+    *dispatch = std::ptr::null_mut();
+    match ListAccessible::from_this(this).target(&child) {
+        Ok(_) => S_FALSE,
+        Err(e) => e,
+    }
+}
+
+unsafe extern "system" fn get_acc_name(this: *mut c_void, child: VARIANT, name: *mut MaybeUninit<BSTR>) -> HRESULT {
+    match ListAccessible::from_this(this).target(&child) {
+        Ok((list, Target::List)) => write_string(name, &list.list_name()),
+        Ok((list, Target::Child(AccessibleChild::Header(column)))) => write_string(name, &list.header_name(column)),
+        Ok((list, Target::Child(AccessibleChild::Row(row)))) => write_string(name, &list.row_name(row)),
+        Err(e) => e,
+    }
+}
+
+unsafe extern "system" fn get_acc_value(_this: *mut c_void, _child: VARIANT, _value: *mut MaybeUninit<BSTR>) -> HRESULT {
+    DISP_E_MEMBERNOTFOUND
+}
+
+unsafe extern "system" fn get_acc_description(this: *mut c_void, child: VARIANT, description: *mut MaybeUninit<BSTR>) -> HRESULT {
+    match ListAccessible::from_this(this).target(&child) {
+        Ok((list, Target::Child(AccessibleChild::Row(row)))) => write_string(description, &list.row_description(row)),
+        Ok(_) => DISP_E_MEMBERNOTFOUND,
+        Err(e) => e,
+    }
+}
+
+unsafe extern "system" fn get_acc_role(this: *mut c_void, child: VARIANT, role: *mut VARIANT) -> HRESULT {
+    let value = match ListAccessible::from_this(this).target(&child) {
+        Ok((_, Target::List)) => ROLE_SYSTEM_LIST,
+        Ok((_, Target::Child(AccessibleChild::Header(_)))) => ROLE_SYSTEM_COLUMNHEADER,
+        Ok((_, Target::Child(AccessibleChild::Row(_)))) => ROLE_SYSTEM_LISTITEM,
+        Err(e) => return e,
+    };
+    write_i4(role, value as i32);
+    S_OK
+}
+
+unsafe extern "system" fn get_acc_state(this: *mut c_void, child: VARIANT, state: *mut VARIANT) -> HRESULT {
+    let object = ListAccessible::from_this(this);
+    let value = match object.target(&child) {
+        Ok((_, Target::List)) => {
+            let standard = object.standard.get_accState(child).ok().and_then(|state| variant_i4(&state)).unwrap_or(0) as u32;
+            standard | STATE_SYSTEM_MULTISELECTABLE | STATE_SYSTEM_EXTSELECTABLE
+        }
+        Ok((list, Target::Child(child))) => {
+            let mut value = if list.child_rect(child).is_some() { 0 } else { STATE_SYSTEM_OFFSCREEN.0 | STATE_SYSTEM_INVISIBLE.0 };
+            if let AccessibleChild::Row(row) = child {
+                value |= STATE_SYSTEM_SELECTABLE | STATE_SYSTEM_FOCUSABLE.0 | STATE_SYSTEM_MULTISELECTABLE | STATE_SYSTEM_EXTSELECTABLE;
+                if list.is_row_selected(row) {
+                    value |= STATE_SYSTEM_SELECTED;
+                }
+                if list.focused_row() == Some(row) && GetFocus() == object.window {
+                    value |= STATE_SYSTEM_FOCUSED;
+                }
+            }
+            value
+        }
+        Err(e) => return e,
+    };
+    write_i4(state, value as i32);
+    S_OK
+}
+
+unsafe extern "system" fn get_acc_help(_this: *mut c_void, _child: VARIANT, _help: *mut MaybeUninit<BSTR>) -> HRESULT {
+    DISP_E_MEMBERNOTFOUND
+}
+
+unsafe extern "system" fn get_acc_help_topic(_this: *mut c_void, _file: *mut MaybeUninit<BSTR>, _child: VARIANT, _topic: *mut i32) -> HRESULT {
+    DISP_E_MEMBERNOTFOUND
+}
+
+unsafe extern "system" fn get_acc_keyboard_shortcut(_this: *mut c_void, _child: VARIANT, _shortcut: *mut MaybeUninit<BSTR>) -> HRESULT {
+    DISP_E_MEMBERNOTFOUND
+}
+
+unsafe extern "system" fn acc_focus(this: *mut c_void, child: *mut VARIANT) -> HRESULT {
+    let object = ListAccessible::from_this(this);
+    let Some(list) = object.list() else {
+        return E_FAIL;
+    };
+    if GetFocus() != object.window {
+        write_empty(child);
+        return S_FALSE;
+    }
+    match list.focused_row() {
+        Some(row) => write_i4(child, child_id(list, AccessibleChild::Row(row))),
+        None => write_i4(child, CHILDID_SELF as i32),
+    }
+    S_OK
+}
+
+// A single row is reported even when several are selected; IEnumVARIANT
+// for the rest is more than screen readers need to read the selection
+unsafe extern "system" fn acc_selection(this: *mut c_void, children: *mut VARIANT) -> HRESULT {
+    let Some(list) = ListAccessible::from_this(this).list() else {
+        return E_FAIL;
+    };
+    match list.focused_row().filter(|&row| list.is_row_selected(row)) {
+        Some(row) => {
+            write_i4(children, child_id(list, AccessibleChild::Row(row)));
+            S_OK
+        }
+        None => {
+            write_empty(children);
+            S_FALSE
+        }
+    }
+}
+
+unsafe extern "system" fn get_acc_default_action(this: *mut c_void, child: VARIANT, action: *mut MaybeUninit<BSTR>) -> HRESULT {
+    match ListAccessible::from_this(this).target(&child) {
+        Ok((list, Target::Child(child))) => match list.default_action(child) {
+            Some(name) => write_string(action, &name),
+            None => DISP_E_MEMBERNOTFOUND,
+        },
+        Ok(_) => DISP_E_MEMBERNOTFOUND,
+        Err(e) => e,
+    }
+}
+
+unsafe extern "system" fn acc_select(this: *mut c_void, flags: i32, child: VARIANT) -> HRESULT {
+    match ListAccessible::from_this(this).target(&child) {
+        Ok((list, Target::Child(AccessibleChild::Row(row)))) => {
+            list.select_row(row, flags as u32);
+            S_OK
+        }
+        Ok(_) => DISP_E_MEMBERNOTFOUND,
+        Err(e) => e,
+    }
+}
+
+unsafe extern "system" fn acc_location(
+    this: *mut c_void,
+    left: *mut i32,
+    top: *mut i32,
+    width: *mut i32,
+    height: *mut i32,
+    child: VARIANT,
+) -> HRESULT {
+    let object = ListAccessible::from_this(this);
+    match object.target(&child) {
+        Ok((_, Target::List)) => match object.standard.accLocation(left, top, width, height, child) {
+            Ok(_) => S_OK,
+            Err(e) => e.code(),
+        },
+        Ok((list, Target::Child(child))) => {
+            let Some(rect) = list.child_rect(child) else {
+                return S_FALSE;
+            };
+            let mut origin = POINT { x: rect.left, y: rect.top };
+            ClientToScreen(object.window, &mut origin);
+            *left = origin.x;
+            *top = origin.y;
+            *width = rect.right - rect.left;
+            *height = rect.bottom - rect.top;
+            S_OK
+        }
+        Err(e) => e,
+    }
+}
+
+// Moves between the children in ID order. From the list itself only the
+// first and last child are handled here.
+unsafe extern "system" fn acc_navigate(this: *mut c_void, direction: i32, start: VARIANT, end: *mut VARIANT) -> HRESULT {
+    let object = ListAccessible::from_this(this);
+    let (list, target) = match object.target(&start) {
+        Ok(found) => found,
+        Err(e) => return e,
+    };
+    let count = (list.header_count() + list.row_count()) as i32;
+    let next = match (target, direction as u32) {
+        (Target::List, NAVDIR_FIRSTCHILD) => Some(1),
+        (Target::List, NAVDIR_LASTCHILD) => Some(count),
+        (Target::List, _) => {
+            return match object.standard.accNavigate(direction, start) {
+                Ok(variant) => {
+                    end.write(variant);
+                    S_OK
+                }
+                Err(e) => e.code(),
+            };
+        }
+        (Target::Child(child), NAVDIR_NEXT | NAVDIR_DOWN) => Some(child_id(list, child) + 1),
+        (Target::Child(child), NAVDIR_PREVIOUS | NAVDIR_UP) => Some(child_id(list, child) - 1),
+        (Target::Child(_), _) => None,
+    };
+    match next.filter(|&id| id >= 1 && id <= count) {
+        Some(id) => {
+            write_i4(end, id);
+            S_OK
+        }
+        None => {
+            write_empty(end);
+            S_FALSE
+        }
+    }
+}
+
+unsafe extern "system" fn acc_hit_test(this: *mut c_void, x: i32, y: i32, child: *mut VARIANT) -> HRESULT {
+    let object = ListAccessible::from_this(this);
+    let Some(list) = object.list() else {
+        return E_FAIL;
+    };
+
+    let mut point = POINT { x, y };
+    ScreenToClient(object.window, &mut point);
+    let mut client = RECT::default();
+    let _ = GetClientRect(object.window, &mut client);
+    if point.x < client.left || point.x >= client.right || point.y < client.top || point.y >= client.bottom {
+        write_empty(child);
+        return S_FALSE;
+    }
+
+    match list.child_at(point.x, point.y) {
+        Some(found) => write_i4(child, child_id(list, found)),
+        None => write_i4(child, CHILDID_SELF as i32),
+    }
+    S_OK
+}
+
+unsafe extern "system" fn acc_do_default_action(this: *mut c_void, child: VARIANT) -> HRESULT {
+    match ListAccessible::from_this(this).target(&child) {
+        Ok((list, Target::Child(child))) if list.default_action(child).is_some() => {
+            list.do_default_action(child);
+            S_OK
+        }
+        Ok(_) => DISP_E_MEMBERNOTFOUND,
+        Err(e) => e,
+    }
+}
+
+unsafe extern "system" fn put_acc_name(_this: *mut c_void, _child: VARIANT, _name: MaybeUninit<BSTR>) -> HRESULT {
+    E_NOTIMPL
+}
+
+unsafe extern "system" fn put_acc_value(_this: *mut c_void, _child: VARIANT, _value: MaybeUninit<BSTR>) -> HRESULT {
+    E_NOTIMPL
+}
@@ -0,0 +1,495 @@
+use std::time::{Duration, UNIX_EPOCH};
+use chrono::{DateTime, Local};
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{COLOR_BTNFACE, HBRUSH, HFONT},
+        Storage::EnhancedStorage::{PKEY_DateModified, PKEY_Size},
+        System::{
+            Com::*,
+            LibraryLoader::GetModuleHandleW,
+            SystemServices::{SFGAO_FOLDER, SS_NOPREFIX, SS_PATHELLIPSIS},
+        },
+        UI::{
+            Controls::*,
+            Input::KeyboardAndMouse::{EnableWindow, SetFocus, VK_BACK},
+            Shell::*,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::lang::{fill, get_strings};
+use crate::list_stats::format_bytes;
+use crate::logger::{log_error, log_info, log_warn};
+use crate::thumbnail::to_wide;
+
+const ARCHIVE_WINDOW_CLASS: &str = "EverythingLikeArchiveBrowser";
+
+const ARCHIVE_WINDOW_WIDTH: i32 = 760;
+const ARCHIVE_WINDOW_HEIGHT: i32 = 520;
+const MARGIN: i32 = 10;
+const BUTTON_WIDTH: i32 = 80;
+const WIDE_BUTTON_WIDTH: i32 = 130;
+const BUTTON_HEIGHT: i32 = 26;
+
+// Control IDs
+const ID_ARCHIVE_PATH: i32 = 9891;
+const ID_ARCHIVE_UP: i32 = 9892;
+const ID_ARCHIVE_LIST: i32 = 9893;
+const ID_ARCHIVE_STATUS: i32 = 9894;
+const ID_ARCHIVE_EXTRACT_SELECTED: i32 = 9895;
+const ID_ARCHIVE_EXTRACT_ALL: i32 = 9896;
+const ID_ARCHIVE_CLOSE: i32 = 9897;
+
+// List view columns
+const COLUMN_SIZE: i32 = 1;
+const COLUMN_MODIFIED: i32 = 2;
+
+// Archives Explorer opens as folders. Zip and cab always work; 7z, rar and
+// tar need Windows 11.
+const ARCHIVE_EXTENSIONS: [&str; 6] = ["zip", "7z", "rar", "tar", "tgz", "cab"];
+
+pub fn is_archive(extension: &str) -> bool {
+    ARCHIVE_EXTENSIONS.iter().any(|known| known.eq_ignore_ascii_case(extension))
+}
+
+struct ArchiveEntry {
+    item: IShellItem,
+    name: String,
+    is_folder: bool,
+    size: Option<u64>,
+    modified: Option<FILETIME>,
+}
+
+struct ArchiveBrowser {
+    archive: String,
+    list: HWND,
+    // The archive itself and then each folder opened inside it
+    folders: Vec<(String, IShellItem)>,
+    // Contents of the last folder, in list order
+    entries: Vec<ArchiveEntry>,
+    // CoUninitialize is owed when the window closes
+    com_initialized: bool,
+}
+
+// The window's ArchiveBrowser, kept in GWLP_USERDATA
+unsafe fn archive_browser(window: HWND) -> Option<&'static mut ArchiveBrowser> {
+    let browser = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut ArchiveBrowser;
+    browser.as_mut()
+}
+
+pub fn register_archive_window_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(archive_window_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as isize),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeArchiveBrowser"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// Lists what is inside `archive` through the shell's archive folders, so
+// whatever Explorer can open works here. Double-clicking a folder opens it
+// and the extract buttons copy entries out with the usual progress dialog.
+pub fn show_archive_browser(owner: HWND, font: HFONT, archive: &str) {
+    unsafe {
+        let strings = get_strings();
+        let com_initialized = CoInitializeEx(None, COINIT_APARTMENTTHREADED | COINIT_DISABLE_OLE1DDE).is_ok();
+        let root: IShellItem = match SHCreateItemFromParsingName(PCWSTR::from_raw(to_wide(archive).as_ptr()), None) {
+            Ok(item) => item,
+            Err(e) => {
+                log_warn(&format!("Can't open archive {}: {}", archive, e));
+                show_error(owner, &fill(&strings.archive_open_failed, &[("error", &e.message().to_string())]));
+                if com_initialized {
+                    CoUninitialize();
+                }
+                return;
+            }
+        };
+
+        let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+        let name = std::path::Path::new(archive)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(archive)
+            .to_string();
+        let window = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            PCWSTR::from_raw(to_wide(ARCHIVE_WINDOW_CLASS).as_ptr()),
+            PCWSTR::from_raw(to_wide(&fill(&strings.archive_title, &[("name", &name)])).as_ptr()),
+            WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX | WS_VISIBLE,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            ARCHIVE_WINDOW_WIDTH,
+            ARCHIVE_WINDOW_HEIGHT,
+            owner,
+            None,
+            instance,
+            None,
+        );
+
+        if window.0 == 0 {
+            log_error("Failed to create archive window");
+            if com_initialized {
+                CoUninitialize();
+            }
+            return;
+        }
+
+        let mut client_rect = RECT::default();
+        let _ = GetClientRect(window, &mut client_rect);
+        let list_y = MARGIN * 2 + BUTTON_HEIGHT;
+        let list_width = client_rect.right - MARGIN * 2;
+        let button_y = client_rect.bottom - MARGIN - BUTTON_HEIGHT;
+
+        let list = CreateWindowExW(
+            WS_EX_CLIENTEDGE,
+            WC_LISTVIEWW,
+            PCWSTR::null(),
+            WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(LVS_REPORT | LVS_SHOWSELALWAYS),
+            MARGIN,
+            list_y,
+            list_width,
+            button_y - MARGIN - list_y,
+            window,
+            HMENU(ID_ARCHIVE_LIST as isize),
+            instance,
+            None,
+        );
+        SendMessageW(list, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        SendMessageW(list, LVM_SETEXTENDEDLISTVIEWSTYLE, WPARAM(0), LPARAM((LVS_EX_FULLROWSELECT | LVS_EX_DOUBLEBUFFER) as isize));
+
+        let columns = [
+            (&strings.column_name, list_width - 280),
+            (&strings.column_size, 110),
+            (&strings.column_date_modified, 140),
+        ];
+        for (index, (name, column_width)) in columns.iter().enumerate() {
+            let mut name_wide = to_wide(name);
+            let column = LVCOLUMNW {
+                mask: LVCF_TEXT | LVCF_WIDTH,
+                cx: *column_width,
+                pszText: PWSTR::from_raw(name_wide.as_mut_ptr()),
+                ..Default::default()
+            };
+            SendMessageW(list, LVM_INSERTCOLUMNW, WPARAM(index), LPARAM(&column as *const _ as isize));
+        }
+
+        let status_x = MARGIN * 3 + WIDE_BUTTON_WIDTH * 2;
+        let controls = [
+            (w!("BUTTON"), strings.archive_up.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), ID_ARCHIVE_UP, MARGIN, MARGIN, BUTTON_WIDTH, BUTTON_HEIGHT),
+            (w!("STATIC"), String::new(), WINDOW_STYLE(SS_PATHELLIPSIS.0 | SS_NOPREFIX.0), ID_ARCHIVE_PATH, MARGIN * 2 + BUTTON_WIDTH, MARGIN + 5, client_rect.right - MARGIN * 3 - BUTTON_WIDTH, 20),
+            (w!("BUTTON"), strings.archive_extract_selected.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), ID_ARCHIVE_EXTRACT_SELECTED, MARGIN, button_y, WIDE_BUTTON_WIDTH, BUTTON_HEIGHT),
+            (w!("BUTTON"), strings.archive_extract_all.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), ID_ARCHIVE_EXTRACT_ALL, MARGIN * 2 + WIDE_BUTTON_WIDTH, button_y, WIDE_BUTTON_WIDTH, BUTTON_HEIGHT),
+            (w!("STATIC"), String::new(), WINDOW_STYLE(SS_NOPREFIX.0), ID_ARCHIVE_STATUS, status_x, button_y + 5, client_rect.right - MARGIN * 2 - BUTTON_WIDTH - status_x, 20),
+            (w!("BUTTON"), strings.stats_close.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), ID_ARCHIVE_CLOSE, client_rect.right - MARGIN - BUTTON_WIDTH, button_y, BUTTON_WIDTH, BUTTON_HEIGHT),
+        ];
+        for (class, text, style, id, x, y, width, height) in controls {
+            let control = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                class,
+                PCWSTR::from_raw(to_wide(&text).as_ptr()),
+                WS_CHILD | WS_VISIBLE | style,
+                x, y, width, height,
+                window,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(control, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        }
+
+        let browser = Box::new(ArchiveBrowser {
+            archive: archive.to_string(),
+            list,
+            folders: vec![(name, root)],
+            entries: Vec::new(),
+            com_initialized,
+        });
+        SetWindowLongPtrW(window, GWLP_USERDATA, Box::into_raw(browser) as isize);
+
+        log_info(&format!("Browsing archive {}", archive));
+        show_folder(window);
+        SetFocus(list);
+    }
+}
+
+unsafe fn show_error(window: HWND, message: &str) {
+    MessageBoxW(
+        window,
+        PCWSTR::from_raw(to_wide(message).as_ptr()),
+        PCWSTR::from_raw(to_wide(&get_strings().warning_title).as_ptr()),
+        MB_OK | MB_ICONWARNING,
+    );
+}
+
+// Reads a shell string and frees it
+unsafe fn take_string(text: PWSTR) -> String {
+    let result = text.to_string().unwrap_or_default();
+    CoTaskMemFree(Some(text.0 as *const std::ffi::c_void));
+    result
+}
+
+// Folders first, then files, each by name
+unsafe fn read_entries(folder: &IShellItem) -> Result<Vec<ArchiveEntry>> {
+    let enumerator: IEnumShellItems = folder.BindToHandler(None, &BHID_EnumItems)?;
+    let mut entries = Vec::new();
+    loop {
+        let mut items = [None];
+        let mut fetched = 0u32;
+        if enumerator.Next(&mut items, Some(&mut fetched)).is_err() || fetched == 0 {
+            break;
+        }
+        let Some(item) = items[0].take() else { break };
+
+        let name = item.GetDisplayName(SIGDN_PARENTRELATIVEEDITING).map(|name| take_string(name)).unwrap_or_default();
+        let is_folder = item.GetAttributes(SFGAO_FOLDER).is_ok_and(|attributes| attributes.0 & SFGAO_FOLDER.0 != 0);
+        let properties = item.cast::<IShellItem2>().ok();
+        let size = properties.as_ref().and_then(|properties| properties.GetUInt64(&PKEY_Size).ok());
+        let modified = properties.as_ref().and_then(|properties| properties.GetFileTime(&PKEY_DateModified).ok());
+        entries.push(ArchiveEntry { item, name, is_folder, size, modified });
+    }
+    entries.sort_by(|a, b| b.is_folder.cmp(&a.is_folder).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+    Ok(entries)
+}
+
+fn format_filetime(time: FILETIME) -> String {
+    // FILETIME counts 100 ns ticks from 1601
+    let ticks = ((time.dwHighDateTime as u64) << 32) | time.dwLowDateTime as u64;
+    let Some(unix_ticks) = ticks.checked_sub(116_444_736_000_000_000) else { return String::new() };
+    let modified = UNIX_EPOCH + Duration::from_nanos(unix_ticks.saturating_mul(100));
+    DateTime::<Local>::from(modified).format("%Y-%m-%d %H:%M").to_string()
+}
+
+unsafe fn set_cell(list: HWND, row: usize, column: i32, text: &str) {
+    let mut text_wide = to_wide(text);
+    let item = LVITEMW {
+        iSubItem: column,
+        pszText: PWSTR::from_raw(text_wide.as_mut_ptr()),
+        ..Default::default()
+    };
+    SendMessageW(list, LVM_SETITEMTEXTW, WPARAM(row), LPARAM(&item as *const _ as isize));
+}
+
+// Fills the list with the innermost open folder
+unsafe fn show_folder(window: HWND) {
+    let Some(browser) = archive_browser(window) else { return };
+    let strings = get_strings();
+    let Some((_, folder)) = browser.folders.last() else { return };
+
+    let previous_cursor = SetCursor(LoadCursorW(None, IDC_WAIT).unwrap_or_default());
+    let entries = read_entries(folder);
+    SetCursor(previous_cursor);
+    browser.entries = match entries {
+        Ok(entries) => entries,
+        Err(e) => {
+            log_warn(&format!("Can't read {}: {}", browser.archive, e));
+            show_error(window, &fill(&strings.archive_open_failed, &[("error", &e.message().to_string())]));
+            Vec::new()
+        }
+    };
+
+    SendMessageW(browser.list, WM_SETREDRAW, WPARAM(0), LPARAM(0));
+    SendMessageW(browser.list, LVM_DELETEALLITEMS, WPARAM(0), LPARAM(0));
+    for (row, entry) in browser.entries.iter().enumerate() {
+        let name = if entry.is_folder { format!("{}\\", entry.name) } else { entry.name.clone() };
+        let mut name_wide = to_wide(&name);
+        let item = LVITEMW {
+            mask: LVIF_TEXT,
+            iItem: row as i32,
+            pszText: PWSTR::from_raw(name_wide.as_mut_ptr()),
+            ..Default::default()
+        };
+        SendMessageW(browser.list, LVM_INSERTITEMW, WPARAM(0), LPARAM(&item as *const _ as isize));
+        if let Some(size) = entry.size.filter(|_| !entry.is_folder) {
+            set_cell(browser.list, row, COLUMN_SIZE, &format_bytes(size, &strings));
+        }
+        if let Some(modified) = entry.modified {
+            set_cell(browser.list, row, COLUMN_MODIFIED, &format_filetime(modified));
+        }
+    }
+    SendMessageW(browser.list, WM_SETREDRAW, WPARAM(1), LPARAM(0));
+
+    let path = browser.folders.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join("\\");
+    let _ = SetWindowTextW(GetDlgItem(window, ID_ARCHIVE_PATH), PCWSTR::from_raw(to_wide(&path).as_ptr()));
+    let _ = EnableWindow(GetDlgItem(window, ID_ARCHIVE_UP), BOOL::from(browser.folders.len() > 1));
+    let status = fill(&strings.archive_items, &[("count", &browser.entries.len().to_string())]);
+    let _ = SetWindowTextW(GetDlgItem(window, ID_ARCHIVE_STATUS), PCWSTR::from_raw(to_wide(&status).as_ptr()));
+}
+
+unsafe fn go_up(window: HWND) {
+    let Some(browser) = archive_browser(window) else { return };
+    if browser.folders.len() > 1 {
+        browser.folders.pop();
+        show_folder(window);
+    }
+}
+
+unsafe fn selected_rows(list: HWND) -> Vec<usize> {
+    let mut rows = Vec::new();
+    let mut row = -1isize;
+    loop {
+        row = SendMessageW(list, LVM_GETNEXTITEM, WPARAM(row as usize), LPARAM(LVNI_SELECTED as isize)).0;
+        if row < 0 {
+            break;
+        }
+        rows.push(row as usize);
+    }
+    rows
+}
+
+// Copies `items` into `folder`; the shell shows progress and asks about
+// files that already exist
+unsafe fn copy_items(window: HWND, items: &[&IShellItem], folder: &str, silent: bool) -> Result<()> {
+    let destination: IShellItem = SHCreateItemFromParsingName(PCWSTR::from_raw(to_wide(folder).as_ptr()), None)?;
+    let operation: IFileOperation = CoCreateInstance(&FileOperation, None, CLSCTX_ALL)?;
+    operation.SetOwnerWindow(window)?;
+    let flags = if silent { FOF_NOCONFIRMMKDIR | FOF_SILENT | FOF_NOCONFIRMATION } else { FOF_NOCONFIRMMKDIR };
+    operation.SetOperationFlags(flags)?;
+    for item in items {
+        operation.CopyItem(*item, &destination, PCWSTR::null(), None)?;
+    }
+    operation.PerformOperations()
+}
+
+unsafe fn extract(window: HWND, selected_only: bool) {
+    let Some(browser) = archive_browser(window) else { return };
+    let strings = get_strings();
+    let items: Vec<&IShellItem> = if selected_only {
+        selected_rows(browser.list).into_iter().filter_map(|row| browser.entries.get(row)).map(|entry| &entry.item).collect()
+    } else {
+        browser.entries.iter().map(|entry| &entry.item).collect()
+    };
+    if items.is_empty() {
+        return;
+    }
+    let Some(folder) = crate::show_folder_dialog(window, &strings.archive_extract_title) else { return };
+
+    let status = match copy_items(window, &items, &folder, false) {
+        Ok(()) => {
+            log_info(&format!("Extracted {} items from {} to {}", items.len(), browser.archive, folder));
+            fill(&strings.archive_extracted, &[("count", &items.len().to_string()), ("folder", &folder)])
+        }
+        Err(e) => {
+            log_warn(&format!("Failed to extract from {}: {}", browser.archive, e));
+            fill(&strings.archive_extract_failed, &[("error", &e.message().to_string())])
+        }
+    };
+    let _ = SetWindowTextW(GetDlgItem(window, ID_ARCHIVE_STATUS), PCWSTR::from_raw(to_wide(&status).as_ptr()));
+}
+
+// Opens a folder, or extracts a file to the temp folder and opens it there
+unsafe fn open_row(window: HWND, row: usize) {
+    let Some(browser) = archive_browser(window) else { return };
+    let Some(entry) = browser.entries.get(row) else { return };
+
+    if entry.is_folder {
+        let folder = (entry.name.clone(), entry.item.clone());
+        browser.folders.push(folder);
+        show_folder(window);
+        return;
+    }
+
+    let temp_folder = std::env::temp_dir().join("EverythingLike").join("Archives");
+    if let Err(e) = std::fs::create_dir_all(&temp_folder) {
+        log_warn(&format!("Failed to create {}: {}", temp_folder.display(), e));
+        return;
+    }
+    let temp_folder = temp_folder.to_string_lossy().to_string();
+    match copy_items(window, &[&entry.item], &temp_folder, true) {
+        Ok(()) => {
+            let path = to_wide(&format!("{}\\{}", temp_folder, entry.name));
+            ShellExecuteW(window, w!("open"), PCWSTR::from_raw(path.as_ptr()), None, None, SW_SHOWNORMAL);
+        }
+        Err(e) => {
+            log_warn(&format!("Failed to extract {} from {}: {}", entry.name, browser.archive, e));
+            show_error(window, &fill(&get_strings().archive_extract_failed, &[("error", &e.message().to_string())]));
+        }
+    }
+}
+
+extern "system" fn archive_window_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_NOTIFY => {
+                let header = &*(lparam.0 as *const NMHDR);
+                if header.idFrom == ID_ARCHIVE_LIST as usize {
+                    match header.code {
+                        NM_DBLCLK => {
+                            let activate = &*(lparam.0 as *const NMITEMACTIVATE);
+                            if activate.iItem >= 0 {
+                                open_row(window, activate.iItem as usize);
+                            }
+                        }
+                        LVN_KEYDOWN => {
+                            let key = &*(lparam.0 as *const NMLVKEYDOWN);
+                            if key.wVKey == VK_BACK.0 {
+                                go_up(window);
+                            } else if key.wVKey == 0x0D { // VK_RETURN
+                                if let Some(browser) = archive_browser(window) {
+                                    if let Some(&row) = selected_rows(browser.list).first() {
+                                        open_row(window, row);
+                                    }
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                match (wparam.0 & 0xFFFF) as i32 {
+                    ID_ARCHIVE_UP => go_up(window),
+                    ID_ARCHIVE_EXTRACT_SELECTED => extract(window, true),
+                    ID_ARCHIVE_EXTRACT_ALL => extract(window, false),
+                    ID_ARCHIVE_CLOSE => {
+                        let _ = DestroyWindow(window);
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(window);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                let browser = SetWindowLongPtrW(window, GWLP_USERDATA, 0) as *mut ArchiveBrowser;
+                if !browser.is_null() {
+                    let browser = Box::from_raw(browser);
+                    let com_initialized = browser.com_initialized;
+                    // The shell items go before COM does
+                    drop(browser);
+                    if com_initialized {
+                        CoUninitialize();
+                    }
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use windows::{
+    core::PCWSTR,
+    Win32::System::Registry::*,
+};
+use crate::logger::log_info;
+use crate::thumbnail::to_wide;
+
+const RUN_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Run";
+const RUN_VALUE: &str = "EverythingLike";
+
+// True when the per-user Run key has an entry for this app
+pub fn is_autostart_enabled() -> bool {
+    unsafe {
+        let Some(key) = open_run_key(KEY_QUERY_VALUE) else {
+            return false;
+        };
+
+        let value_wide = to_wide(RUN_VALUE);
+        let enabled = RegQueryValueExW(key, PCWSTR::from_raw(value_wide.as_ptr()), None, None, None, None).is_ok();
+
+        let _ = RegCloseKey(key);
+        enabled
+    }
+}
+
+// Adds or removes the Run key entry pointing at the running executable
+pub fn set_autostart(enabled: bool) -> Result<(), Box<dyn std::error::Error>> {
+    unsafe {
+        let key = open_run_key(KEY_SET_VALUE).ok_or("Failed to open the Run registry key")?;
+        let value_wide = to_wide(RUN_VALUE);
+
+        let result = if enabled {
+            let exe_path = std::env::current_exe()?;
+            let command = format!("\"{}\"", exe_path.display());
+            let command_wide = to_wide(&command);
+            let data = std::slice::from_raw_parts(command_wide.as_ptr() as *const u8, command_wide.len() * 2);
+            RegSetValueExW(key, PCWSTR::from_raw(value_wide.as_ptr()), 0, REG_SZ, Some(data))
+        } else {
+            match RegDeleteValueW(key, PCWSTR::from_raw(value_wide.as_ptr())) {
+                // Already gone is what we wanted
+                Err(e) if e.code() == windows::Win32::Foundation::ERROR_FILE_NOT_FOUND.to_hresult() => Ok(()),
+                other => other,
+            }
+        };
+
+        let _ = RegCloseKey(key);
+        result?;
+        log_info(&format!("Start with Windows {}", if enabled { "enabled" } else { "disabled" }));
+        Ok(())
+    }
+}
+
+unsafe fn open_run_key(access: REG_SAM_FLAGS) -> Option<HKEY> {
+    let key_wide = to_wide(RUN_KEY);
+    let mut key = HKEY::default();
+    RegOpenKeyExW(HKEY_CURRENT_USER, PCWSTR::from_raw(key_wide.as_ptr()), 0, access, &mut key).ok()?;
+    Some(key)
+}
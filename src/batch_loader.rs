@@ -0,0 +1,108 @@
+use windows::Win32::{
+    Foundation::*,
+    UI::WindowsAndMessaging::PostMessageW,
+};
+use std::collections::HashSet;
+use std::marker::PhantomData;
+use std::sync::{Arc, mpsc, atomic::{AtomicU64, Ordering}};
+
+// One row of a request. The path is what the UI matches results against.
+pub trait BatchItem: Send + 'static {
+    fn path(&self) -> &str;
+}
+
+impl BatchItem for String {
+    fn path(&self) -> &str {
+        self
+    }
+}
+
+// A row index with its path, for results that go back to a fixed row
+impl BatchItem for (usize, String) {
+    fn path(&self) -> &str {
+        &self.1
+    }
+}
+
+struct BatchRequest<I> {
+    generation: u64,
+    items: Vec<I>,
+}
+
+// Works out one entry per item on a worker thread and posts `message` with
+// a boxed Vec<E> in WPARAM for each batch of `batch_size` entries. Only the
+// newest request is worked on; older ones are dropped once the view has
+// moved on.
+pub struct BatchLoader<I, E> {
+    sender: mpsc::Sender<BatchRequest<I>>,
+    latest_generation: Arc<AtomicU64>,
+    // Paths of the last request, so repaints of the same rows don't queue them again
+    requested: HashSet<String>,
+    entries: PhantomData<fn() -> E>,
+}
+
+impl<I: BatchItem, E: Send + 'static> BatchLoader<I, E> {
+    // `compute` runs on the worker thread, so it can keep caches of its own
+    // between requests
+    pub fn spawn(window: HWND, message: u32, batch_size: usize, mut compute: impl FnMut(&I) -> E + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel::<BatchRequest<I>>();
+        let latest_generation = Arc::new(AtomicU64::new(0));
+        let thread_generation = latest_generation.clone();
+
+        std::thread::spawn(move || {
+            while let Ok(mut request) = receiver.recv() {
+                // Skip straight to the newest request
+                while let Ok(newer) = receiver.try_recv() {
+                    request = newer;
+                }
+
+                for chunk in request.items.chunks(batch_size) {
+                    if request.generation != thread_generation.load(Ordering::Relaxed) {
+                        break;
+                    }
+
+                    let entries: Vec<E> = chunk.iter().map(&mut compute).collect();
+
+                    let entries_ptr = Box::into_raw(Box::new(entries)) as usize;
+                    unsafe {
+                        if PostMessageW(window, message, WPARAM(entries_ptr), LPARAM(0)).is_err() {
+                            // The window is gone, so nobody will free the batch
+                            drop(Box::from_raw(entries_ptr as *mut Vec<E>));
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+
+        Self {
+            sender,
+            latest_generation,
+            requested: HashSet::new(),
+            entries: PhantomData,
+        }
+    }
+
+    // Queues the items that still need an entry. Nothing is sent when every
+    // item is already part of the last request.
+    pub fn request(&mut self, items: Vec<I>) {
+        if items.iter().all(|item| self.requested.contains(item.path())) {
+            return;
+        }
+
+        let generation = self.latest_generation.fetch_add(1, Ordering::Relaxed) + 1;
+        self.requested = items.iter().map(|item| item.path().to_string()).collect();
+        let _ = self.sender.send(BatchRequest { generation, items });
+    }
+
+    // Items of the last request that haven't come back yet
+    pub fn pending(&self) -> usize {
+        self.requested.len()
+    }
+
+    // Called for each entry the UI has received, so an item that didn't
+    // land where it was expected can be requested again
+    pub fn finished(&mut self, path: &str) {
+        self.requested.remove(path);
+    }
+}
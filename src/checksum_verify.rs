@@ -0,0 +1,512 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use rayon::prelude::*;
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{HBRUSH, HFONT, COLOR_BTNFACE},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Controls::*,
+            Input::KeyboardAndMouse::EnableWindow,
+            Shell::ShellExecuteW,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::file_hash::{hash_file, to_hex, HashAlgorithm};
+use crate::lang::{fill, get_strings};
+use crate::logger::{log_error, log_info, log_warn};
+use crate::thumbnail::to_wide;
+
+// Posted by the workers for each checked file, with a boxed (usize, Outcome)
+// in WPARAM
+const WM_CHECKSUM_RESULT: u32 = WM_USER + 131;
+
+const CHECKSUM_WINDOW_CLASS: &str = "EverythingLikeChecksumVerifier";
+
+const CHECKSUM_WINDOW_WIDTH: i32 = 860;
+const CHECKSUM_WINDOW_HEIGHT: i32 = 520;
+const MARGIN: i32 = 10;
+const BUTTON_WIDTH: i32 = 150;
+const BUTTON_HEIGHT: i32 = 26;
+
+// Control IDs
+const ID_CHECKSUM_STATUS: i32 = 9901;
+const ID_CHECKSUM_LIST: i32 = 9902;
+const ID_CHECKSUM_EXPORT: i32 = 9903;
+const ID_CHECKSUM_CLOSE: i32 = 9904;
+
+// List view columns
+const COLUMN_ALGORITHM: i32 = 1;
+const COLUMN_EXPECTED: i32 = 2;
+const COLUMN_RESULT: i32 = 3;
+
+const MANIFEST_EXTENSIONS: [&str; 5] = ["sfv", "md5", "sha1", "sha256", "sha512"];
+
+pub fn is_checksum_manifest(extension: &str) -> bool {
+    MANIFEST_EXTENSIONS.iter().any(|known| known.eq_ignore_ascii_case(extension))
+}
+
+// One file named by the manifest
+struct ChecksumEntry {
+    // As written in the manifest
+    name: String,
+    path: PathBuf,
+    algorithm: HashAlgorithm,
+    // Lowercase hex
+    expected: String,
+}
+
+enum Outcome {
+    Passed,
+    Mismatch(String),
+    Missing,
+    Error(String),
+}
+
+impl Outcome {
+    fn text(&self) -> String {
+        let strings = get_strings();
+        match self {
+            Outcome::Passed => strings.checksum_passed.clone(),
+            Outcome::Mismatch(_) => strings.checksum_mismatch.clone(),
+            Outcome::Missing => strings.checksum_missing.clone(),
+            Outcome::Error(error) => fill(&strings.checksum_error, &[("error", error)]),
+        }
+    }
+}
+
+struct ChecksumVerifier {
+    manifest: String,
+    list: HWND,
+    entries: Vec<ChecksumEntry>,
+    outcomes: Vec<Option<Outcome>>,
+    // Set when the window closes, so the workers stop hashing
+    cancel: Arc<AtomicBool>,
+}
+
+// The window's ChecksumVerifier, kept in GWLP_USERDATA
+unsafe fn verifier(window: HWND) -> Option<&'static mut ChecksumVerifier> {
+    let verifier = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut ChecksumVerifier;
+    verifier.as_mut()
+}
+
+pub fn register_checksum_window_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(checksum_window_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as isize),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeChecksumVerifier"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// Reads a .sfv, .md5, .sha1, .sha256 or .sha512 file. SFV lines are
+// `name CRC32`; the others are `digest *name`, `digest  name` or the BSD
+// `ALGORITHM (name) = digest`. Relative names are resolved against the
+// manifest's folder and lines that don't parse are skipped.
+fn parse_manifest(manifest: &Path) -> std::result::Result<Vec<ChecksumEntry>, String> {
+    let bytes = std::fs::read(manifest).map_err(|e| e.to_string())?;
+    let text = String::from_utf8_lossy(bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&bytes)).into_owned();
+    let folder = manifest.parent().unwrap_or(Path::new(""));
+    let is_sfv = manifest
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("sfv"));
+
+    let mut entries = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+
+        let parsed = if is_sfv {
+            line.rsplit_once(char::is_whitespace)
+                .map(|(name, digest)| (name.trim(), digest, Some(HashAlgorithm::Crc32)))
+        } else if let Some((tagged, digest)) = line.rsplit_once(") = ") {
+            tagged.split_once(" (").map(|(tag, name)| {
+                let algorithm = match tag.trim().to_ascii_uppercase().replace('-', "").as_str() {
+                    "MD5" => Some(HashAlgorithm::Md5),
+                    "SHA1" => Some(HashAlgorithm::Sha1),
+                    "SHA256" => Some(HashAlgorithm::Sha256),
+                    "SHA512" => Some(HashAlgorithm::Sha512),
+                    _ => None,
+                };
+                (name, digest, algorithm)
+            })
+        } else {
+            line.split_once(char::is_whitespace).map(|(digest, rest)| {
+                let name = rest.strip_prefix([' ', '*']).unwrap_or(rest);
+                let algorithm = [HashAlgorithm::Md5, HashAlgorithm::Sha1, HashAlgorithm::Sha256, HashAlgorithm::Sha512]
+                    .into_iter()
+                    .find(|algorithm| algorithm.size() * 2 == digest.len());
+                (name, digest, algorithm)
+            })
+        };
+
+        let Some((name, digest, Some(algorithm))) = parsed else {
+            continue;
+        };
+        let digest = digest.trim();
+        if name.is_empty()
+            || digest.len() != algorithm.size() * 2
+            || !digest.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            continue;
+        }
+
+        let relative = name.replace('/', "\\");
+        entries.push(ChecksumEntry {
+            name: name.to_string(),
+            path: folder.join(relative),
+            algorithm,
+            expected: digest.to_ascii_lowercase(),
+        });
+    }
+    Ok(entries)
+}
+
+fn verify_file(path: &Path, algorithm: HashAlgorithm, expected: &str) -> Outcome {
+    if !path.is_file() {
+        return Outcome::Missing;
+    }
+    match hash_file(path, algorithm) {
+        Ok(digest) => {
+            let actual = to_hex(&digest);
+            if actual == expected {
+                Outcome::Passed
+            } else {
+                Outcome::Mismatch(actual)
+            }
+        }
+        Err(e) => Outcome::Error(e),
+    }
+}
+
+unsafe fn show_error(window: HWND, message: &str) {
+    MessageBoxW(
+        window,
+        PCWSTR::from_raw(to_wide(message).as_ptr()),
+        PCWSTR::from_raw(to_wide(&get_strings().warning_title).as_ptr()),
+        MB_OK | MB_ICONWARNING,
+    );
+}
+
+// Verify Checksums: lists every file `manifest` names and hashes them on
+// worker threads, filling in pass or fail as each one finishes. Failures
+// can be exported to a text file once the run is done.
+pub fn show_checksum_verifier(owner: HWND, font: HFONT, manifest: &str) {
+    unsafe {
+        let strings = get_strings();
+        let manifest_name = Path::new(manifest)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| manifest.to_string());
+
+        let entries = match parse_manifest(Path::new(manifest)) {
+            Ok(entries) if entries.is_empty() => {
+                show_error(owner, &fill(&strings.checksum_no_entries, &[("name", &manifest_name)]));
+                return;
+            }
+            Ok(entries) => entries,
+            Err(e) => {
+                log_warn(&format!("Can't read checksum file {}: {}", manifest, e));
+                show_error(owner, &fill(&strings.checksum_read_failed, &[("name", &manifest_name), ("error", &e)]));
+                return;
+            }
+        };
+
+        let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+        let title = fill(&strings.checksum_title, &[("name", &manifest_name)]);
+        let window = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            PCWSTR::from_raw(to_wide(CHECKSUM_WINDOW_CLASS).as_ptr()),
+            PCWSTR::from_raw(to_wide(&title).as_ptr()),
+            WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX | WS_VISIBLE,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            CHECKSUM_WINDOW_WIDTH,
+            CHECKSUM_WINDOW_HEIGHT,
+            owner,
+            None,
+            instance,
+            None,
+        );
+
+        if window.0 == 0 {
+            log_error("Failed to create checksum verifier window");
+            return;
+        }
+
+        let mut client_rect = RECT::default();
+        let _ = GetClientRect(window, &mut client_rect);
+        let width = client_rect.right - MARGIN * 2;
+        let button_y = client_rect.bottom - MARGIN - BUTTON_HEIGHT;
+        let list_y = MARGIN * 2 + 20;
+        let list_height = button_y - MARGIN - list_y;
+
+        let list = CreateWindowExW(
+            WS_EX_CLIENTEDGE,
+            WC_LISTVIEWW,
+            PCWSTR::null(),
+            WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(LVS_REPORT | LVS_SHOWSELALWAYS),
+            MARGIN,
+            list_y,
+            width,
+            list_height,
+            window,
+            HMENU(ID_CHECKSUM_LIST as isize),
+            instance,
+            None,
+        );
+        SendMessageW(list, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        SendMessageW(
+            list,
+            LVM_SETEXTENDEDLISTVIEWSTYLE,
+            WPARAM(0),
+            LPARAM((LVS_EX_FULLROWSELECT | LVS_EX_GRIDLINES) as isize),
+        );
+
+        let columns = [
+            (&strings.checksum_column_file, width - 520),
+            (&strings.checksum_column_algorithm, 80),
+            (&strings.checksum_column_expected, 280),
+            (&strings.checksum_column_result, 140),
+        ];
+        for (index, (name, column_width)) in columns.iter().enumerate() {
+            let mut name_wide = to_wide(name);
+            let column = LVCOLUMNW {
+                mask: LVCF_TEXT | LVCF_WIDTH,
+                cx: *column_width,
+                pszText: PWSTR::from_raw(name_wide.as_mut_ptr()),
+                ..Default::default()
+            };
+            SendMessageW(list, LVM_INSERTCOLUMNW, WPARAM(index), LPARAM(&column as *const _ as isize));
+        }
+
+        for (row, entry) in entries.iter().enumerate() {
+            let mut name_wide = to_wide(&entry.name);
+            let item = LVITEMW {
+                mask: LVIF_TEXT,
+                iItem: row as i32,
+                pszText: PWSTR::from_raw(name_wide.as_mut_ptr()),
+                ..Default::default()
+            };
+            SendMessageW(list, LVM_INSERTITEMW, WPARAM(0), LPARAM(&item as *const _ as isize));
+            set_cell(list, row, COLUMN_ALGORITHM, entry.algorithm.name());
+            set_cell(list, row, COLUMN_EXPECTED, &entry.expected);
+            set_cell(list, row, COLUMN_RESULT, &strings.checksum_pending);
+        }
+
+        let controls = [
+            (w!("STATIC"), String::new(), WINDOW_STYLE(0), ID_CHECKSUM_STATUS, MARGIN, MARGIN, width, 20),
+            (w!("BUTTON"), strings.checksum_export_failures.clone(), WS_TABSTOP | WS_DISABLED | WINDOW_STYLE(BS_PUSHBUTTON as u32), ID_CHECKSUM_EXPORT, MARGIN, button_y, BUTTON_WIDTH, BUTTON_HEIGHT),
+            (w!("BUTTON"), strings.stats_close.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), ID_CHECKSUM_CLOSE, client_rect.right - MARGIN - 80, button_y, 80, BUTTON_HEIGHT),
+        ];
+        for (class, text, style, id, x, y, width, height) in controls {
+            let control = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                class,
+                PCWSTR::from_raw(to_wide(&text).as_ptr()),
+                WS_CHILD | WS_VISIBLE | style,
+                x, y, width, height,
+                window,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(control, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        }
+
+        log_info(&format!("Verifying {} entries of {}", entries.len(), manifest));
+        let jobs: Vec<(usize, PathBuf, HashAlgorithm, String)> = entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| (index, entry.path.clone(), entry.algorithm, entry.expected.clone()))
+            .collect();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let verifier = Box::new(ChecksumVerifier {
+            manifest: manifest.to_string(),
+            list,
+            outcomes: entries.iter().map(|_| None).collect(),
+            entries,
+            cancel: cancel.clone(),
+        });
+        SetWindowLongPtrW(window, GWLP_USERDATA, Box::into_raw(verifier) as isize);
+        update_status(window);
+
+        std::thread::spawn(move || {
+            jobs.into_par_iter().for_each(|(index, path, algorithm, expected)| {
+                if cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                let outcome = verify_file(&path, algorithm, &expected);
+                let result = Box::into_raw(Box::new((index, outcome))) as usize;
+                if PostMessageW(window, WM_CHECKSUM_RESULT, WPARAM(result), LPARAM(0)).is_err() {
+                    // The window is gone, so nobody will free the result
+                    drop(Box::from_raw(result as *mut (usize, Outcome)));
+                }
+            });
+        });
+    }
+}
+
+unsafe fn set_cell(list: HWND, row: usize, column: i32, text: &str) {
+    let mut text_wide = to_wide(text);
+    let item = LVITEMW {
+        iSubItem: column,
+        pszText: PWSTR::from_raw(text_wide.as_mut_ptr()),
+        ..Default::default()
+    };
+    SendMessageW(list, LVM_SETITEMTEXTW, WPARAM(row), LPARAM(&item as *const _ as isize));
+}
+
+unsafe fn update_status(window: HWND) {
+    let Some(verifier) = verifier(window) else {
+        return;
+    };
+    let done = verifier.outcomes.iter().flatten().count();
+    let passed = verifier.outcomes.iter().flatten().filter(|outcome| matches!(outcome, Outcome::Passed)).count();
+    let missing = verifier.outcomes.iter().flatten().filter(|outcome| matches!(outcome, Outcome::Missing)).count();
+    let failed = done - passed - missing;
+    let status = fill(&get_strings().checksum_progress, &[
+        ("done", &done.to_string()),
+        ("total", &verifier.entries.len().to_string()),
+        ("passed", &passed.to_string()),
+        ("failed", &failed.to_string()),
+        ("missing", &missing.to_string()),
+    ]);
+    let _ = SetWindowTextW(GetDlgItem(window, ID_CHECKSUM_STATUS), PCWSTR::from_raw(to_wide(&status).as_ptr()));
+
+    if done == verifier.entries.len() {
+        log_info(&format!(
+            "Verified {}: {} passed, {} failed, {} missing",
+            verifier.manifest, passed, failed, missing
+        ));
+        EnableWindow(GetDlgItem(window, ID_CHECKSUM_EXPORT), BOOL::from(passed < done));
+    }
+}
+
+// Writes every entry that didn't pass to a tab-separated text file: the
+// result, the path and, for mismatches, the expected and actual digests
+unsafe fn export_failures(window: HWND) {
+    let Some(verifier) = verifier(window) else {
+        return;
+    };
+    let strings = get_strings();
+    let filters = [("Text Files (*.txt)", "txt")];
+    let Some(result) = crate::show_save_file_dialog_with_filters(window, &strings.checksum_export_failures, "failed_checksums", &filters, 1, None) else {
+        return;
+    };
+
+    let mut report = String::new();
+    for (entry, outcome) in verifier.entries.iter().zip(&verifier.outcomes) {
+        let Some(outcome) = outcome else {
+            continue;
+        };
+        if matches!(outcome, Outcome::Passed) {
+            continue;
+        }
+        report.push_str(&format!("{}\t{}", outcome.text(), entry.path.display()));
+        if let Outcome::Mismatch(actual) = outcome {
+            report.push_str(&format!("\t{}\t{}", entry.expected, actual));
+        }
+        report.push_str("\r\n");
+    }
+
+    if let Err(e) = std::fs::write(&result.path, report) {
+        log_warn(&format!("Failed to export checksum failures to {}: {}", result.path, e));
+        show_error(window, &fill(&strings.checksum_export_failed, &[("error", &e.to_string())]));
+    }
+}
+
+// Shows the file of `row` selected in Explorer
+unsafe fn open_row_location(window: HWND, row: usize) {
+    let Some(entry) = verifier(window).and_then(|verifier| verifier.entries.get(row)) else {
+        return;
+    };
+    let arguments = format!("/select,\"{}\"", entry.path.display());
+    ShellExecuteW(
+        None,
+        w!("open"),
+        w!("explorer.exe"),
+        PCWSTR::from_raw(to_wide(&arguments).as_ptr()),
+        None,
+        SW_SHOWNORMAL,
+    );
+}
+
+extern "system" fn checksum_window_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_CHECKSUM_RESULT => {
+                let (index, outcome) = *Box::from_raw(wparam.0 as *mut (usize, Outcome));
+                if let Some(verifier) = verifier(window) {
+                    if let Some(slot) = verifier.outcomes.get_mut(index) {
+                        set_cell(verifier.list, index, COLUMN_RESULT, &outcome.text());
+                        *slot = Some(outcome);
+                        update_status(window);
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_NOTIFY => {
+                let header = &*(lparam.0 as *const NMHDR);
+                if header.idFrom == ID_CHECKSUM_LIST as usize && header.code == NM_DBLCLK {
+                    let activate = &*(lparam.0 as *const NMITEMACTIVATE);
+                    if activate.iItem >= 0 {
+                        open_row_location(window, activate.iItem as usize);
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                match (wparam.0 & 0xFFFF) as i32 {
+                    ID_CHECKSUM_EXPORT => export_failures(window),
+                    ID_CHECKSUM_CLOSE => {
+                        let _ = DestroyWindow(window);
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(window);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                let verifier = SetWindowLongPtrW(window, GWLP_USERDATA, 0) as *mut ChecksumVerifier;
+                if !verifier.is_null() {
+                    let verifier = Box::from_raw(verifier);
+                    verifier.cancel.store(true, Ordering::Relaxed);
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
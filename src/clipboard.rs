@@ -0,0 +1,220 @@
+use windows::Win32::{
+    Foundation::*,
+    System::{
+        DataExchange::*,
+        Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE},
+        Ole::{CF_HDROP, CF_UNICODETEXT},
+    },
+    UI::Shell::{DragQueryFileW, HDROP},
+};
+use windows::core::w;
+use crate::logger::log_error;
+use crate::thumbnail::to_wide;
+
+// Reads file paths from the clipboard. Files copied in Explorer (CF_HDROP)
+// take precedence over text, which is split into one path per line.
+pub fn read_clipboard_paths(owner: HWND) -> Vec<String> {
+    unsafe {
+        if OpenClipboard(owner).is_err() {
+            log_error("Failed to open clipboard");
+            return Vec::new();
+        }
+
+        let paths = if IsClipboardFormatAvailable(CF_HDROP.0 as u32).is_ok() {
+            read_dropped_files()
+        } else if IsClipboardFormatAvailable(CF_UNICODETEXT.0 as u32).is_ok() {
+            read_text().map(|text| parse_path_lines(&text)).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        let _ = CloseClipboard();
+        paths
+    }
+}
+
+unsafe fn read_dropped_files() -> Vec<String> {
+    let handle = match GetClipboardData(CF_HDROP.0 as u32) {
+        Ok(handle) => handle,
+        Err(_) => return Vec::new(),
+    };
+    let hdrop = HDROP(handle.0);
+
+    // Index 0xFFFFFFFF returns the number of files
+    let count = DragQueryFileW(hdrop, 0xFFFFFFFF, None);
+    let mut paths = Vec::with_capacity(count as usize);
+
+    for index in 0..count {
+        let len = DragQueryFileW(hdrop, index, None) as usize;
+        let mut buffer = vec![0u16; len + 1];
+        let copied = DragQueryFileW(hdrop, index, Some(&mut buffer)) as usize;
+        if copied > 0 {
+            paths.push(String::from_utf16_lossy(&buffer[..copied]));
+        }
+    }
+
+    paths
+}
+
+unsafe fn read_text() -> Option<String> {
+    let handle = GetClipboardData(CF_UNICODETEXT.0 as u32).ok()?;
+    let hglobal = HGLOBAL(handle.0 as *mut std::ffi::c_void);
+
+    let ptr = GlobalLock(hglobal) as *const u16;
+    if ptr.is_null() {
+        return None;
+    }
+
+    let mut len = 0;
+    while *ptr.add(len) != 0 {
+        len += 1;
+    }
+    let text = String::from_utf16_lossy(std::slice::from_raw_parts(ptr, len));
+
+    let _ = GlobalUnlock(hglobal);
+    Some(text)
+}
+
+// Keeps lines that look like absolute paths, so stray text is ignored
+fn parse_path_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.trim().trim_matches('"'))
+        .filter(|line| !line.is_empty())
+        .filter(|line| std::path::Path::new(line).is_absolute() || line.starts_with("\\\\"))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+// Starts or stops sending WM_CLIPBOARDUPDATE to `window` whenever the
+// clipboard changes
+pub fn set_clipboard_listener(window: HWND, enabled: bool) -> bool {
+    unsafe {
+        if enabled {
+            AddClipboardFormatListener(window).is_ok()
+        } else {
+            RemoveClipboardFormatListener(window).is_ok()
+        }
+    }
+}
+
+// True when `window` put the current contents on the clipboard
+pub fn clipboard_owned_by(window: HWND) -> bool {
+    unsafe { GetClipboardOwner() == window }
+}
+
+// Replaces the clipboard contents with the given text
+pub fn set_clipboard_text(owner: HWND, text: &str) -> bool {
+    let text_wide = to_wide(text);
+    set_clipboard_formats(owner, &[(CF_UNICODETEXT.0 as u32, wide_bytes(&text_wide))])
+}
+
+// Puts `rows` on the clipboard as a table: tab-separated text, which
+// spreadsheets split into columns, and an HTML table for programs that
+// prefer it. The first row is the header.
+pub fn set_clipboard_table(owner: HWND, rows: &[Vec<String>]) -> bool {
+    let tsv = rows
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.replace(['\t', '\r', '\n'], " ")).collect::<Vec<_>>().join("\t"))
+        .collect::<Vec<_>>()
+        .join("\r\n");
+    let text_wide = to_wide(&tsv);
+
+    let html = html_clipboard_data(&html_table(rows));
+    let html_format = unsafe { RegisterClipboardFormatW(w!("HTML Format")) };
+
+    let mut formats = vec![(CF_UNICODETEXT.0 as u32, wide_bytes(&text_wide))];
+    if html_format != 0 {
+        formats.push((html_format, html.as_bytes()));
+    }
+    set_clipboard_formats(owner, &formats)
+}
+
+fn wide_bytes(text_wide: &[u16]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(text_wide.as_ptr() as *const u8, std::mem::size_of_val(text_wide)) }
+}
+
+fn html_table(rows: &[Vec<String>]) -> String {
+    let escape = |text: &str| text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    let mut html = String::from("<table>");
+    for (index, row) in rows.iter().enumerate() {
+        let tag = if index == 0 { "th" } else { "td" };
+        html.push_str("<tr>");
+        for cell in row {
+            html.push_str(&format!("<{tag}>{}</{tag}>", escape(cell)));
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</table>");
+    html
+}
+
+// The CF_HTML layout: a header giving the byte offsets of the document and
+// of the copied fragment within it, which programs use to find the table
+fn html_clipboard_data(fragment: &str) -> String {
+    const PREFIX: &str = "<html><body>\r\n<!--StartFragment-->";
+    const SUFFIX: &str = "<!--EndFragment-->\r\n</body></html>";
+    let header = |start_html: usize, end_html: usize, start_fragment: usize, end_fragment: usize| {
+        format!(
+            "Version:0.9\r\nStartHTML:{:010}\r\nEndHTML:{:010}\r\nStartFragment:{:010}\r\nEndFragment:{:010}\r\n",
+            start_html, end_html, start_fragment, end_fragment
+        )
+    };
+
+    // The offsets are padded to ten digits, so the header's length is known
+    // before they are
+    let start_html = header(0, 0, 0, 0).len();
+    let start_fragment = start_html + PREFIX.len();
+    let end_fragment = start_fragment + fragment.len();
+    let end_html = end_fragment + SUFFIX.len();
+    format!("{}{}{}{}", header(start_html, end_html, start_fragment, end_fragment), PREFIX, fragment, SUFFIX)
+}
+
+// Replaces the clipboard contents with `formats`, each the format and its
+// bytes. A null byte goes after each.
+fn set_clipboard_formats(owner: HWND, formats: &[(u32, &[u8])]) -> bool {
+    unsafe {
+        let mut handles = Vec::with_capacity(formats.len());
+        for (format, bytes) in formats {
+            let Ok(hglobal) = GlobalAlloc(GMEM_MOVEABLE, bytes.len() + 1) else {
+                free_handles(&handles);
+                return false;
+            };
+            let ptr = GlobalLock(hglobal) as *mut u8;
+            if ptr.is_null() {
+                let _ = GlobalFree(hglobal);
+                free_handles(&handles);
+                return false;
+            }
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len());
+            *ptr.add(bytes.len()) = 0;
+            let _ = GlobalUnlock(hglobal);
+            handles.push((*format, hglobal));
+        }
+
+        if OpenClipboard(owner).is_err() {
+            log_error("Failed to open clipboard");
+            free_handles(&handles);
+            return false;
+        }
+
+        let _ = EmptyClipboard();
+
+        // The clipboard owns the memory once SetClipboardData succeeds
+        let mut copied = true;
+        for (format, hglobal) in handles {
+            if SetClipboardData(format, HANDLE(hglobal.0 as isize)).is_err() {
+                let _ = GlobalFree(hglobal);
+                copied = false;
+            }
+        }
+
+        let _ = CloseClipboard();
+        copied
+    }
+}
+
+unsafe fn free_handles(handles: &[(u32, HGLOBAL)]) {
+    for (_, hglobal) in handles {
+        let _ = GlobalFree(*hglobal);
+    }
+}
@@ -0,0 +1,30 @@
+use std::cmp::Ordering;
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::LPARAM;
+use windows::Win32::Globalization::{
+    CompareStringEx, CSTR_GREATER_THAN, CSTR_LESS_THAN, LINGUISTIC_IGNORECASE, SORT_DIGITSASNUMBERS,
+};
+
+// Compares two UTF-16 strings the way the given locale orders them
+// (e.g. pinyin for zh-CN, accents after base letters for fr-FR).
+// Case is ignored and digit runs compare as numbers, like Explorer.
+pub fn compare_by_locale(locale: &[u16], a: &[u16], b: &[u16]) -> Ordering {
+    let result = unsafe {
+        CompareStringEx(
+            PCWSTR::from_raw(locale.as_ptr()),
+            LINGUISTIC_IGNORECASE | SORT_DIGITSASNUMBERS,
+            a,
+            b,
+            None,
+            None,
+            LPARAM(0),
+        )
+    };
+
+    match result {
+        CSTR_LESS_THAN => Ordering::Less,
+        CSTR_GREATER_THAN => Ordering::Greater,
+        // CSTR_EQUAL, or 0 when the locale is not available
+        _ => Ordering::Equal,
+    }
+}
@@ -0,0 +1,306 @@
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{HBRUSH, HFONT, COLOR_BTNFACE},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Controls::*,
+            Input::KeyboardAndMouse::{EnableWindow, SetFocus},
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::lang::get_strings;
+use crate::logger::log_error;
+use crate::thumbnail::to_wide;
+
+const COLUMN_CHOOSER_CLASS: &str = "EverythingLikeColumnChooser";
+
+const ID_CHOOSER_LIST: i32 = 9321;
+const ID_CHOOSER_WIDTH: i32 = 9322;
+
+// Narrowest width the chooser accepts, the same as dragging allows
+const MIN_COLUMN_WIDTH: i32 = 20;
+
+// One Details column as the chooser shows and edits it
+#[derive(Clone)]
+pub struct ColumnChoice {
+    pub label: String,
+    pub visible: bool,
+    pub width: i32,
+}
+
+struct ColumnChooserState {
+    done: bool,
+    accepted: bool,
+    list: HWND,
+    choices: Vec<ColumnChoice>,
+    // Row whose width the edit shows
+    selected: Option<usize>,
+    // Set while the edit is filled in, so its EN_CHANGE is ignored
+    filling: bool,
+}
+
+// The window's ColumnChooserState, kept in GWLP_USERDATA while the
+// chooser's modal loop runs
+unsafe fn column_chooser(window: HWND) -> Option<&'static mut ColumnChooserState> {
+    let state = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut ColumnChooserState;
+    state.as_mut()
+}
+
+pub fn register_column_chooser_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(column_chooser_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as isize),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeColumnChooser"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// Lets the user tick the columns to show and set their widths, like
+// Explorer's Choose Details. Returns the edited choices in the same
+// order, or None when cancelled.
+pub fn choose_columns(owner: HWND, choices: Vec<ColumnChoice>, font: HFONT) -> Option<Vec<ColumnChoice>> {
+    unsafe {
+        let strings = get_strings();
+        let instance = HINSTANCE(GetModuleHandleW(None).ok()?.0);
+        let (width, height) = (340, 400);
+
+        let mut owner_rect = RECT::default();
+        let _ = GetWindowRect(owner, &mut owner_rect);
+        let x = owner_rect.left + ((owner_rect.right - owner_rect.left) - width) / 2;
+        let y = owner_rect.top + ((owner_rect.bottom - owner_rect.top) - height) / 2;
+
+        let window = CreateWindowExW(
+            WS_EX_DLGMODALFRAME,
+            PCWSTR::from_raw(to_wide(COLUMN_CHOOSER_CLASS).as_ptr()),
+            PCWSTR::from_raw(to_wide(&strings.column_chooser_title).as_ptr()),
+            WS_POPUP | WS_CAPTION | WS_SYSMENU | WS_VISIBLE,
+            x.max(0),
+            y.max(0),
+            width,
+            height,
+            owner,
+            None,
+            instance,
+            None,
+        );
+
+        if window.0 == 0 {
+            log_error("Failed to create column chooser");
+            return None;
+        }
+
+        let mut client_rect = RECT::default();
+        let _ = GetClientRect(window, &mut client_rect);
+        let (client_width, client_height) = (client_rect.right, client_rect.bottom);
+        let button_y = client_height - 36;
+        let width_y = button_y - 40;
+        let list_height = width_y - 44;
+
+        let controls = [
+            (w!("STATIC"), strings.column_chooser_label.clone(), WINDOW_STYLE(0), WINDOW_EX_STYLE::default(), -1, 10, 10, client_width - 20, 20),
+            (w!("STATIC"), strings.column_chooser_width.clone(), WINDOW_STYLE(0), WINDOW_EX_STYLE::default(), -1, 10, width_y + 3, client_width - 100, 20),
+            (w!("EDIT"), String::new(), WS_TABSTOP | WS_DISABLED | WINDOW_STYLE((ES_NUMBER | ES_AUTOHSCROLL) as u32), WS_EX_CLIENTEDGE, ID_CHOOSER_WIDTH, client_width - 80, width_y, 70, 24),
+            (w!("BUTTON"), strings.settings_ok.clone(), WS_TABSTOP | WINDOW_STYLE(BS_DEFPUSHBUTTON as u32), WINDOW_EX_STYLE::default(), IDOK.0, client_width - 180, button_y, 80, 26),
+            (w!("BUTTON"), strings.settings_cancel.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), WINDOW_EX_STYLE::default(), IDCANCEL.0, client_width - 90, button_y, 80, 26),
+        ];
+        for (class, text, style, ex_style, id, x, y, width, height) in controls {
+            let control = CreateWindowExW(
+                ex_style,
+                class,
+                PCWSTR::from_raw(to_wide(&text).as_ptr()),
+                WS_CHILD | WS_VISIBLE | style,
+                x, y, width, height,
+                window,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(control, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        }
+
+        let list = CreateWindowExW(
+            WS_EX_CLIENTEDGE,
+            WC_LISTVIEWW,
+            PCWSTR::null(),
+            WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(LVS_REPORT | LVS_SINGLESEL | LVS_SHOWSELALWAYS | LVS_NOCOLUMNHEADER),
+            10,
+            34,
+            client_width - 20,
+            list_height,
+            window,
+            HMENU(ID_CHOOSER_LIST as isize),
+            instance,
+            None,
+        );
+        SendMessageW(list, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        SendMessageW(list, LVM_SETEXTENDEDLISTVIEWSTYLE, WPARAM(0), LPARAM((LVS_EX_FULLROWSELECT | LVS_EX_CHECKBOXES) as isize));
+        let column = LVCOLUMNW {
+            mask: LVCF_WIDTH,
+            cx: client_width - 50,
+            ..Default::default()
+        };
+        SendMessageW(list, LVM_INSERTCOLUMNW, WPARAM(0), LPARAM(&column as *const _ as isize));
+        for (row, choice) in choices.iter().enumerate() {
+            let mut label_wide = to_wide(&choice.label);
+            let item = LVITEMW {
+                mask: LVIF_TEXT,
+                iItem: row as i32,
+                pszText: PWSTR::from_raw(label_wide.as_mut_ptr()),
+                ..Default::default()
+            };
+            SendMessageW(list, LVM_INSERTITEMW, WPARAM(0), LPARAM(&item as *const _ as isize));
+            set_checked(list, row, choice.visible);
+        }
+        let _ = SetFocus(list);
+
+        let state = Box::into_raw(Box::new(ColumnChooserState { done: false, accepted: false, list, choices, selected: None, filling: false }));
+        SetWindowLongPtrW(window, GWLP_USERDATA, state as isize);
+        EnableWindow(owner, FALSE);
+
+        // Local message loop; IsDialogMessage maps Enter/Esc to IDOK/IDCANCEL
+        let mut message = MSG::default();
+        while column_chooser(window).is_some_and(|state| !state.done) {
+            if !GetMessageW(&mut message, None, 0, 0).as_bool() {
+                // Let the main loop see the quit message too
+                PostQuitMessage(message.wParam.0 as i32);
+                break;
+            }
+            if !IsDialogMessageW(window, &message).as_bool() {
+                TranslateMessage(&message);
+                DispatchMessageW(&message);
+            }
+        }
+
+        // The boxes are read while the list is still there
+        let state = Box::from_raw(state);
+        let result = Some(state).filter(|state| state.accepted).map(|mut state| {
+            for (row, choice) in state.choices.iter_mut().enumerate() {
+                choice.visible = is_checked(state.list, row);
+            }
+            state.choices
+        });
+
+        EnableWindow(owner, TRUE);
+        if IsWindow(window).as_bool() {
+            SetWindowLongPtrW(window, GWLP_USERDATA, 0);
+            let _ = DestroyWindow(window);
+        }
+        let _ = SetForegroundWindow(owner);
+
+        result
+    }
+}
+
+unsafe fn is_checked(list: HWND, row: usize) -> bool {
+    let state = SendMessageW(list, LVM_GETITEMSTATE, WPARAM(row), LPARAM(LVIS_STATEIMAGEMASK.0 as isize)).0 as u32;
+    // State image 2 is the checked box, 1 the empty one
+    (state >> 12) == 2
+}
+
+unsafe fn set_checked(list: HWND, row: usize, checked: bool) {
+    let item = LVITEMW {
+        stateMask: LVIS_STATEIMAGEMASK,
+        state: LIST_VIEW_ITEM_STATE_FLAGS(if checked { 2 << 12 } else { 1 << 12 }),
+        ..Default::default()
+    };
+    SendMessageW(list, LVM_SETITEMSTATE, WPARAM(row), LPARAM(&item as *const _ as isize));
+}
+
+// Shows the width of the newly selected row in the edit. The state isn't
+// held on to while the text is set, as that sends EN_CHANGE right away.
+unsafe fn select_row(window: HWND, row: usize) {
+    let Some(state) = column_chooser(window) else {
+        return;
+    };
+    let Some(width) = state.choices.get(row).map(|choice| choice.width) else {
+        return;
+    };
+    state.selected = Some(row);
+    state.filling = true;
+
+    let edit = GetDlgItem(window, ID_CHOOSER_WIDTH);
+    let _ = SetWindowTextW(edit, PCWSTR::from_raw(to_wide(&width.to_string()).as_ptr()));
+    EnableWindow(edit, TRUE);
+    if let Some(state) = column_chooser(window) {
+        state.filling = false;
+    }
+}
+
+// Keeps the width typed for the selected row
+unsafe fn read_width(window: HWND) {
+    let mut buffer: [u16; 16] = [0; 16];
+    let len = GetWindowTextW(GetDlgItem(window, ID_CHOOSER_WIDTH), &mut buffer);
+    let Ok(width) = String::from_utf16_lossy(&buffer[..len.max(0) as usize]).trim().parse::<i32>() else {
+        return;
+    };
+    let Some(state) = column_chooser(window).filter(|state| !state.filling) else {
+        return;
+    };
+    if let Some(choice) = state.selected.and_then(|row| state.choices.get_mut(row)) {
+        choice.width = width.max(MIN_COLUMN_WIDTH);
+    }
+}
+
+extern "system" fn column_chooser_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_NOTIFY => {
+                let header = &*(lparam.0 as *const NMHDR);
+                if header.idFrom == ID_CHOOSER_LIST as usize && header.code == LVN_ITEMCHANGED {
+                    let change = &*(lparam.0 as *const NMLISTVIEW);
+                    let now_selected = change.uNewState & LVIS_SELECTED.0 != 0;
+                    let was_selected = change.uOldState & LVIS_SELECTED.0 != 0;
+                    if now_selected && !was_selected && change.iItem >= 0 {
+                        select_row(window, change.iItem as usize);
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                let id = (wparam.0 & 0xFFFF) as i32;
+                let notification = ((wparam.0 >> 16) & 0xFFFF) as u32;
+                if id == ID_CHOOSER_WIDTH && notification == EN_CHANGE {
+                    read_width(window);
+                } else if id == IDOK.0 || id == IDCANCEL.0 {
+                    if let Some(state) = column_chooser(window) {
+                        state.accepted = id == IDOK.0;
+                        state.done = true;
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                if let Some(state) = column_chooser(window) {
+                    state.done = true;
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use windows::Win32::UI::Shell::{SHGetFolderPathW, CSIDL_APPDATA};
 use windows::Win32::Foundation::{MAX_PATH, HWND};
+use crate::logger::{LogLevel, log_debug, log_error, log_info, log_warn};
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum ThumbnailStrategy {
@@ -30,6 +32,60 @@ impl ThumbnailStrategy {
     }
 }
 
+// Where the Path column cuts a path that doesn't fit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum PathEllipsis {
+    #[default]
+    #[serde(rename = "End")]
+    End,
+    // Keeps the last folder of the path visible
+    #[serde(rename = "Middle")]
+    Middle,
+}
+
+// How the Size column and the status bar show file sizes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum SizeFormat {
+    // KB, MB or GB, whichever fits
+    #[default]
+    #[serde(rename = "Auto")]
+    Auto,
+    // Always whole KB with thousands separators, like Explorer
+    #[serde(rename = "Kilobytes")]
+    Kilobytes,
+    // The exact number of bytes
+    #[serde(rename = "Bytes")]
+    Bytes,
+}
+
+// What is written under each thumbnail of the icon views
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ThumbnailCaption {
+    #[default]
+    #[serde(rename = "Name")]
+    Name,
+    #[serde(rename = "NameAndSize")]
+    NameAndSize,
+    // Width and height of images and videos
+    #[serde(rename = "NameAndDimensions")]
+    NameAndDimensions,
+    // Nothing, so the thumbnails make a plain grid
+    #[serde(rename = "None")]
+    Hidden,
+}
+
+impl ThumbnailCaption {
+    // Lines of text reserved under each thumbnail: two for the file name
+    // and one for its size or dimensions
+    pub fn line_count(self) -> i32 {
+        match self {
+            ThumbnailCaption::Name => 2,
+            ThumbnailCaption::NameAndSize | ThumbnailCaption::NameAndDimensions => 3,
+            ThumbnailCaption::Hidden => 0,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum ThumbnailBackground {
     #[serde(rename = "Transparent")]
@@ -84,6 +140,12 @@ impl ThumbnailBackground {
 pub enum LanguageCode {
     English,
     Chinese,
+    Japanese,
+    German,
+    French,
+    Korean,
+    Spanish,
+    Russian,
 }
 
 impl Default for LanguageCode {
@@ -97,35 +159,652 @@ impl LanguageCode {
         match self {
             LanguageCode::English => "en".to_string(),
             LanguageCode::Chinese => "zh".to_string(),
+            LanguageCode::Japanese => "ja".to_string(),
+            LanguageCode::German => "de".to_string(),
+            LanguageCode::French => "fr".to_string(),
+            LanguageCode::Korean => "ko".to_string(),
+            LanguageCode::Spanish => "es".to_string(),
+            LanguageCode::Russian => "ru".to_string(),
         }
     }
     
     pub fn from_string(s: &str) -> Self {
         match s.to_lowercase().as_str() {
             "zh" | "zh-cn" | "chinese" => LanguageCode::Chinese,
+            "ja" | "japanese" => LanguageCode::Japanese,
+            "de" | "german" => LanguageCode::German,
+            "fr" | "french" => LanguageCode::French,
+            "ko" | "korean" => LanguageCode::Korean,
+            "es" | "spanish" => LanguageCode::Spanish,
+            "ru" | "russian" => LanguageCode::Russian,
             _ => LanguageCode::English,
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+// Maximum number of unpinned entries kept in the recent lists menu
+pub const MAX_RECENT_LISTS: usize = 10;
+
+// An Everything ETP server that searches can be sent to instead of the
+// local index. The password is kept as typed, like Everything's own
+// ETP connections.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteServer {
+    pub host: String,
+    #[serde(default = "default_etp_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+}
+
+impl RemoteServer {
+    // Shown in the Remote Server menu and the status bar
+    pub fn display_name(&self) -> String {
+        let host = if self.port == default_etp_port() {
+            self.host.clone()
+        } else {
+            format!("{}:{}", self.host, self.port)
+        };
+        if self.username.is_empty() {
+            host
+        } else {
+            format!("{}@{}", self.username, host)
+        }
+    }
+}
+
+fn default_etp_port() -> u16 {
+    21
+}
+
+// Maximum number of servers shown in the Remote Server menu
+pub const MAX_REMOTE_SERVERS: usize = 20;
+
+// An entry of the Tools menu. `command` is a command line with
+// placeholders for the selected items, see external_tools.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExternalTool {
+    pub name: String,
+    pub command: String,
+}
+
+// Maximum number of entries in the Tools menu
+pub const MAX_EXTERNAL_TOOLS: usize = 20;
+
+// A saved search from the Bookmarks menu, imported from Everything.
+// `filter` names the entry of the Filters menu it turns on, if any.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub search: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub filter: String,
+}
+
+// A search that is run again on a schedule and written to `path`, as EFU
+// or CSV by its extension. `filter` names an entry of the Filters menu.
+// The outcome of the last run is kept for the Bookmarks menu.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledExport {
+    pub name: String,
+    pub search: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub filter: String,
+    pub path: String,
+    // Hours between runs; 0 runs it once each time the app starts
+    pub interval_hours: u32,
+    // Unix seconds when the last run finished, 0 before the first
+    #[serde(default)]
+    pub last_run: u64,
+    // Files written by the last run, or why it failed
+    #[serde(default)]
+    pub last_count: usize,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub last_error: String,
+}
+
+pub const MAX_SCHEDULED_EXPORTS: usize = 10;
+
+// Choices offered for ScheduledExport::interval_hours
+pub const SCHEDULED_EXPORT_INTERVALS: [u32; 5] = [0, 1, 6, 24, 168];
+
+// An entry of the Filters menu; its search is combined with every query
+// while it is selected
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchFilter {
+    pub name: String,
+    pub search: String,
+}
+
+// What a Favorites entry of the sidebar opens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FavoriteKind {
+    // Limits searches to the folder in `target`
+    Folder,
+    // Runs the query in `target`
+    Search,
+    // Opens the file list at `target`
+    List,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Favorite {
+    pub kind: FavoriteKind,
+    pub name: String,
+    pub target: String,
+}
+
+pub const MAX_FAVORITES: usize = 100;
+
+// Maximum number of entries in the Bookmarks and Filters menus
+pub const MAX_BOOKMARKS: usize = 100;
+pub const MAX_SEARCH_FILTERS: usize = 50;
+
+// Maximum number of folders left out of the results; each one adds a term
+// to every search
+pub const MAX_EXCLUDED_FOLDERS: usize = 20;
+
+// Maximum number of queries kept in the tray's recent searches menu
+pub const MAX_RECENT_SEARCHES: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentList {
+    pub path: String,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+// Choices offered in the contact sheet menu
+pub const CONTACT_SHEET_COLUMN_CHOICES: [u32; 4] = [4, 6, 8, 10];
+pub const CONTACT_SHEET_CELL_SIZE_CHOICES: [u32; 3] = [128, 192, 256];
+
+fn default_contact_sheet_columns() -> u32 {
+    6
+}
+
+fn default_contact_sheet_cell_size() -> u32 {
+    192
+}
+
+// Limits for the numeric options in the Settings dialog
+pub const SEARCH_DELAY_RANGE: (u32, u32) = (0, 5000);
+pub const MAX_RESULTS_RANGE: (u32, u32) = (100, 1_000_000);
+pub const THUMBNAIL_THREADS_RANGE: (u32, u32) = (1, 32);
+pub const THUMBNAIL_CACHE_SIZE_RANGE: (u32, u32) = (50, 10_000);
+pub const HTTP_SERVER_PORT_RANGE: (u32, u32) = (1, 65535);
+pub const MEMORY_BUDGET_RANGE: (u32, u32) = (0, 65536);
+
+fn default_http_server_port() -> u32 {
+    8765
+}
+
+fn default_search_delay_ms() -> u32 {
+    500
+}
+
+fn default_max_results() -> u32 {
+    50000
+}
+
+fn default_memory_budget_mb() -> u32 {
+    1024
+}
+
+// Default search that lists every file Everything knows about
+pub const SHOW_ALL_SEARCH: &str = "everything:";
+
+fn default_search() -> String {
+    "*.png".to_string()
+}
+
+fn default_thumbnail_threads() -> u32 {
+    4
+}
+
+fn default_thumbnail_cache_size() -> u32 {
+    500
+}
+
+// Width and visibility of one Details column, keyed by column name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnLayout {
+    pub column: String,
+    pub width: i32,
+    pub visible: bool,
+    // None keeps the column's own alignment
+    #[serde(default)]
+    pub align_right: Option<bool>,
+}
+
+// Named set of settings that can be switched to from the Profiles menu.
+// Recent lists, the saved session and other profiles are not part of it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub settings: AppConfig,
+}
+
+// Maximum number of profiles shown in the Profiles menu
+pub const MAX_PROFILES: usize = 50;
+
+// View state saved on exit and restored on the next launch
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionState {
+    #[serde(default)]
+    pub query: String,
+    // Set when a list file was open instead of an Everything search
+    #[serde(default)]
+    pub list_path: Option<String>,
+    #[serde(default)]
+    pub sort_column: Option<String>,
+    #[serde(default)]
+    pub sort_descending: bool,
+    #[serde(default)]
+    pub zoom_level: i32,
+    // Details or icons; missing in sessions saved before it was added
+    #[serde(default)]
+    pub view_mode: Option<String>,
+    #[serde(default)]
+    pub scroll_pos: i32,
+    // Query of each open search tab; the fields above belong to the active one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tabs: Vec<String>,
+    #[serde(default)]
+    pub active_tab: usize,
+}
+
+// Schema version written to config.json. Bump it when a field changes
+// meaning or is renamed, and add a step to migrate_config.
+pub const CONFIG_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
+    // Missing in files written before versioning, which count as version 0
+    #[serde(default)]
+    pub version: u32,
     pub thumbnail_strategy: ThumbnailStrategy,
     pub thumbnail_background: ThumbnailBackground,
+    // Leave files on network shares without thumbnails
+    #[serde(default)]
+    pub skip_network_thumbnails: bool,
     pub language: LanguageCode,
+    #[serde(default)]
+    pub recent_lists: Vec<RecentList>,
+    #[serde(default = "default_contact_sheet_columns")]
+    pub contact_sheet_columns: u32,
+    #[serde(default = "default_contact_sheet_cell_size")]
+    pub contact_sheet_cell_size: u32,
+    #[serde(default)]
+    pub restore_session: bool,
+    // Hide to the notification area instead of showing the window on launch
+    #[serde(default)]
+    pub start_minimized: bool,
+    // Mirrors the Run key entry, which is the source of truth
+    #[serde(default)]
+    pub start_with_windows: bool,
+    // Mirrors the "Search here" registry keys, which are the source of truth
+    #[serde(default)]
+    pub explorer_verb: bool,
+    // Mirrors the es:// protocol keys, which are the source of truth
+    #[serde(default)]
+    pub url_protocol: bool,
+    // Closing the main window hides it to the notification area instead
+    #[serde(default)]
+    pub close_to_tray: bool,
+    // Ctrl+Alt+Space opens the quick search bar from anywhere
+    #[serde(default)]
+    pub quick_search_hotkey: bool,
+    // Ctrl+Shift+Space opens the launcher from anywhere
+    #[serde(default)]
+    pub launcher_hotkey: bool,
+    // Offer to reveal paths copied in other programs
+    #[serde(default)]
+    pub clipboard_watcher: bool,
+    // Look for a newer release at startup, at most once a day
+    #[serde(default)]
+    pub auto_check_updates: bool,
+    // Unix time of the last automatic update check
+    #[serde(default)]
+    pub last_update_check: u64,
+    // Queries submitted with Enter, most recent first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recent_searches: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remote_servers: Vec<RemoteServer>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub external_tools: Vec<ExternalTool>,
+    // Windows Terminal profile for Open Terminal Here; empty uses the default
+    #[serde(default)]
+    pub terminal_profile: String,
+    // Imported from Everything's Bookmarks.csv and Filters.csv
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bookmarks: Vec<Bookmark>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub search_filters: Vec<SearchFilter>,
+    // Listed under Bookmarks > Scheduled Exports
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scheduled_exports: Vec<ScheduledExport>,
+    // Pinned to the sidebar, in the order shown
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub favorites: Vec<Favorite>,
+    // Folders whose contents are left out of the results
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded_folders: Vec<String>,
+    // Where to import them from; empty looks in Everything's settings folder
+    #[serde(default)]
+    pub everything_bookmarks_path: String,
+    #[serde(default)]
+    pub everything_filters_path: String,
+    // Distribution for WSL shells; empty uses the default one
+    #[serde(default)]
+    pub wsl_distro: String,
+    // Answers GET /search on 127.0.0.1 for browsers and scripts
+    #[serde(default)]
+    pub http_server: bool,
+    #[serde(default = "default_http_server_port")]
+    pub http_server_port: u32,
+    #[serde(default)]
+    pub last_session: Option<SessionState>,
+    // Delay after the last keystroke before an Everything search starts
+    #[serde(default = "default_search_delay_ms")]
+    pub search_delay_ms: u32,
+    #[serde(default = "default_max_results")]
+    pub max_results: u32,
+    // Megabytes a search's results may take before asking whether to load
+    // them all; 0 for no limit
+    #[serde(default = "default_memory_budget_mb")]
+    pub memory_budget_mb: u32,
+    // Query run at startup and whenever the search box is empty.
+    // An empty string shows nothing; SHOW_ALL_SEARCH lists every file.
+    #[serde(default = "default_search")]
+    pub default_search: String,
+    // Sort Name, Path and Type with the UI language's collation
+    #[serde(default)]
+    pub locale_sort: bool,
+    // Order results by name match, run count and recency instead of a column
+    #[serde(default)]
+    pub rank_by_use: bool,
+    // Drive and folder tree left of the results
+    #[serde(default)]
+    pub show_folder_tree: bool,
+    // Preview of the selected file right of the results
+    #[serde(default)]
+    pub show_preview_pane: bool,
+    // Open images in the built-in viewer instead of their usual program
+    #[serde(default)]
+    pub use_image_viewer: bool,
+    #[serde(default)]
+    pub path_ellipsis: PathEllipsis,
+    #[serde(default)]
+    pub size_format: SizeFormat,
+    #[serde(default)]
+    pub thumbnail_caption: ThumbnailCaption,
+    // Keep the Name column in place when the other columns scroll sideways
+    #[serde(default)]
+    pub freeze_name_column: bool,
+    // Show the details view's results under a header per drive
+    #[serde(default)]
+    pub group_by_drive: bool,
+    // Add the files opened from the app to open_history.jsonl
+    #[serde(default)]
+    pub record_open_history: bool,
+    #[serde(default = "default_thumbnail_threads")]
+    pub thumbnail_threads: u32,
+    // Number of thumbnails kept in memory
+    #[serde(default = "default_thumbnail_cache_size")]
+    pub thumbnail_cache_size: u32,
+    #[serde(default)]
+    pub columns: Vec<ColumnLayout>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<Profile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_profile: Option<String>,
+    // Diagnostics; changed from the log window (Ctrl+Shift+L)
+    #[serde(default)]
+    pub log_level: LogLevel,
+    #[serde(default)]
+    pub log_to_file: bool,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             thumbnail_strategy: ThumbnailStrategy::default(),
             thumbnail_background: ThumbnailBackground::default(),
+            skip_network_thumbnails: false,
             language: LanguageCode::default(),
+            recent_lists: Vec::new(),
+            contact_sheet_columns: default_contact_sheet_columns(),
+            contact_sheet_cell_size: default_contact_sheet_cell_size(),
+            restore_session: false,
+            start_minimized: false,
+            start_with_windows: false,
+            explorer_verb: false,
+            url_protocol: false,
+            close_to_tray: false,
+            quick_search_hotkey: false,
+            launcher_hotkey: false,
+            clipboard_watcher: false,
+            auto_check_updates: false,
+            last_update_check: 0,
+            recent_searches: Vec::new(),
+            remote_servers: Vec::new(),
+            external_tools: Vec::new(),
+            terminal_profile: String::new(),
+            bookmarks: Vec::new(),
+            search_filters: Vec::new(),
+            scheduled_exports: Vec::new(),
+            favorites: Vec::new(),
+            excluded_folders: Vec::new(),
+            everything_bookmarks_path: String::new(),
+            everything_filters_path: String::new(),
+            wsl_distro: String::new(),
+            http_server: false,
+            http_server_port: default_http_server_port(),
+            last_session: None,
+            search_delay_ms: default_search_delay_ms(),
+            max_results: default_max_results(),
+            memory_budget_mb: default_memory_budget_mb(),
+            default_search: default_search(),
+            locale_sort: false,
+            rank_by_use: false,
+            show_folder_tree: false,
+            show_preview_pane: false,
+            use_image_viewer: false,
+            path_ellipsis: PathEllipsis::default(),
+            size_format: SizeFormat::default(),
+            thumbnail_caption: ThumbnailCaption::default(),
+            freeze_name_column: false,
+            group_by_drive: false,
+            record_open_history: false,
+            thumbnail_threads: default_thumbnail_threads(),
+            thumbnail_cache_size: default_thumbnail_cache_size(),
+            columns: Vec::new(),
+            profiles: Vec::new(),
+            active_profile: None,
+            log_level: LogLevel::default(),
+            log_to_file: false,
         }
     }
 }
 
+impl AppConfig {
+    // Move a list file to the top of the recent lists, keeping its pin state
+    pub fn add_recent_list(&mut self, path: &str) {
+        let pinned = self.recent_lists
+            .iter()
+            .find(|entry| entry.path.eq_ignore_ascii_case(path))
+            .map(|entry| entry.pinned)
+            .unwrap_or(false);
+        
+        self.recent_lists.retain(|entry| !entry.path.eq_ignore_ascii_case(path));
+        self.recent_lists.insert(0, RecentList {
+            path: path.to_string(),
+            pinned,
+        });
+        
+        // Drop the oldest unpinned entries once over the limit
+        let mut unpinned = 0;
+        self.recent_lists.retain(|entry| {
+            if entry.pinned {
+                true
+            } else {
+                unpinned += 1;
+                unpinned <= MAX_RECENT_LISTS
+            }
+        });
+    }
+    
+    pub fn remove_recent_list(&mut self, path: &str) {
+        self.recent_lists.retain(|entry| !entry.path.eq_ignore_ascii_case(path));
+    }
+    
+    // Move a query to the top of the recent searches
+    pub fn add_recent_search(&mut self, query: &str) {
+        let query = query.trim();
+        if query.is_empty() {
+            return;
+        }
+        
+        self.recent_searches.retain(|entry| entry != query);
+        self.recent_searches.insert(0, query.to_string());
+        self.recent_searches.truncate(MAX_RECENT_SEARCHES);
+    }
+    
+    // Returns the new pin state, or None if the path is not in the recent lists
+    pub fn toggle_recent_list_pin(&mut self, path: &str) -> Option<bool> {
+        let entry = self.recent_lists
+            .iter_mut()
+            .find(|entry| entry.path.eq_ignore_ascii_case(path))?;
+        entry.pinned = !entry.pinned;
+        Some(entry.pinned)
+    }
+    
+    pub fn clear_recent_lists(&mut self) {
+        // Pinned entries survive a clear
+        self.recent_lists.retain(|entry| entry.pinned);
+    }
+    
+    // Pins a favorite at the end of the sidebar's list. Returns false when
+    // the same target is already pinned or the list is full.
+    pub fn add_favorite(&mut self, kind: FavoriteKind, name: &str, target: &str) -> bool {
+        let pinned = self.favorites
+            .iter()
+            .any(|favorite| favorite.kind == kind && favorite.target.eq_ignore_ascii_case(target));
+        if pinned || self.favorites.len() >= MAX_FAVORITES {
+            return false;
+        }
+        
+        self.favorites.push(Favorite {
+            kind,
+            name: name.to_string(),
+            target: target.to_string(),
+        });
+        true
+    }
+    
+    // Adds a folder to the exclusions. Returns false when it is already
+    // excluded, by itself or by a folder above it, or the list is full.
+    pub fn add_excluded_folder(&mut self, folder: &str) -> bool {
+        let folder = folder.trim_end_matches(['\\', '/']);
+        if self.is_excluded(&format!("{}\\", folder)) || self.excluded_folders.len() >= MAX_EXCLUDED_FOLDERS {
+            return false;
+        }
+        
+        self.excluded_folders.push(folder.to_string());
+        true
+    }
+    
+    // Whether `path` lies inside one of the excluded folders
+    pub fn is_excluded(&self, path: &str) -> bool {
+        let path = path.to_lowercase();
+        self.excluded_folders
+            .iter()
+            .any(|folder| path.starts_with(&format!("{}\\", folder.to_lowercase())))
+    }
+    
+    // Copy of the settings without the per-machine state, stored in a profile
+    pub fn profile_settings(&self) -> AppConfig {
+        let mut settings = self.clone();
+        settings.recent_lists.clear();
+        settings.recent_searches.clear();
+        settings.remote_servers.clear();
+        settings.external_tools.clear();
+        settings.bookmarks.clear();
+        settings.search_filters.clear();
+        settings.scheduled_exports.clear();
+        settings.favorites.clear();
+        settings.excluded_folders.clear();
+        settings.last_session = None;
+        settings.profiles.clear();
+        settings.active_profile = None;
+        settings
+    }
+    
+    // Adds a profile, replacing any existing one with the same name
+    pub fn save_profile(&mut self, name: &str) {
+        let settings = self.profile_settings();
+        match self.profiles.iter_mut().find(|profile| profile.name.eq_ignore_ascii_case(name)) {
+            Some(profile) => {
+                profile.name = name.to_string();
+                profile.settings = settings;
+            }
+            None => self.profiles.push(Profile {
+                name: name.to_string(),
+                settings,
+            }),
+        }
+        self.active_profile = Some(name.to_string());
+    }
+    
+    pub fn delete_profile(&mut self, name: &str) {
+        self.profiles.retain(|profile| !profile.name.eq_ignore_ascii_case(name));
+        if self.active_profile.as_deref().map_or(false, |active| active.eq_ignore_ascii_case(name)) {
+            self.active_profile = None;
+        }
+    }
+    
+    // Recent lists in menu order: pinned first, then most recently opened
+    pub fn recent_lists_for_menu(&self) -> Vec<&RecentList> {
+        let mut entries: Vec<&RecentList> = self.recent_lists.iter().filter(|entry| entry.pinned).collect();
+        entries.extend(self.recent_lists.iter().filter(|entry| !entry.pinned));
+        entries
+    }
+}
+
+// Marker file that keeps all settings next to the executable
+const PORTABLE_MARKER: &str = "portable.ini";
+
+fn get_exe_dir() -> Option<PathBuf> {
+    std::env::current_exe().ok()?.parent().map(|dir| dir.to_path_buf())
+}
+
+// Portable mode is on when portable.ini or config.json sits beside the exe,
+// so the app can run from a USB stick without touching %APPDATA%
+pub fn get_portable_dir() -> Option<PathBuf> {
+    let exe_dir = get_exe_dir()?;
+    if exe_dir.join(PORTABLE_MARKER).exists() || exe_dir.join("config.json").exists() {
+        Some(exe_dir)
+    } else {
+        None
+    }
+}
+
+pub fn get_languages_dir() -> PathBuf {
+    match get_portable_dir() {
+        Some(portable_dir) => portable_dir.join("languages"),
+        None => PathBuf::from("languages"),
+    }
+}
+
 pub fn get_config_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if let Some(portable_dir) = get_portable_dir() {
+        return Ok(portable_dir);
+    }
+    
     unsafe {
         let mut path: [u16; MAX_PATH as usize] = [0; MAX_PATH as usize];
         let result = SHGetFolderPathW(
@@ -160,32 +839,108 @@ pub fn get_config_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
     Ok(config_dir)
 }
 
+// Set once config.json has been backed up in this run
+static CONFIG_BACKED_UP: AtomicBool = AtomicBool::new(false);
+
+// Upgrades a parsed config file one version at a time
+fn migrate_config(value: &mut serde_json::Value) {
+    let Some(object) = value.as_object_mut() else {
+        return;
+    };
+    
+    let version = object.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+    if version > CONFIG_VERSION {
+        // Written by a newer build; unknown fields are ignored and the
+        // original is kept as a backup before it is rewritten
+        log_warn(&format!("Config version {} is newer than {}, loading known fields only", version, CONFIG_VERSION));
+    }
+    
+    if version < 1 {
+        // Version 0 predates the version field. Every field added since then
+        // has a serde default, so only the version needs to be stamped.
+        log_info("Migrating config from version 0 to 1");
+    }
+    
+    object.insert("version".to_string(), serde_json::Value::from(CONFIG_VERSION));
+    
+    // Profiles hold a full set of settings written in the same format
+    if let Some(profiles) = object.get_mut("profiles").and_then(|p| p.as_array_mut()) {
+        for profile in profiles {
+            if let Some(settings) = profile.get_mut("settings") {
+                migrate_config(settings);
+            }
+        }
+    }
+}
+
+// Parses config.json content of any known version into the current schema
+fn parse_config(content: &str) -> Result<AppConfig, Box<dyn std::error::Error>> {
+    let mut value: serde_json::Value = serde_json::from_str(content.trim_start_matches('\u{feff}'))?;
+    migrate_config(&mut value);
+    Ok(serde_json::from_value(value)?)
+}
+
+fn file_version(content: &str) -> u32 {
+    serde_json::from_str::<serde_json::Value>(content.trim_start_matches('\u{feff}'))
+        .ok()
+        .and_then(|value| value.get("version").and_then(|v| v.as_u64()))
+        .unwrap_or(0) as u32
+}
+
+// Copies config.json aside before it is first rewritten in this run, so a
+// bad save or a downgrade can be undone by hand
+fn backup_config_file(config_path: &PathBuf) {
+    if CONFIG_BACKED_UP.swap(true, Ordering::Relaxed) || !config_path.exists() {
+        return;
+    }
+    
+    // Keep files from other versions under their own name so they are not
+    // overwritten by backups of this version
+    let version = fs::read_to_string(config_path).map(|content| file_version(&content)).unwrap_or(0);
+    let backup_name = if version == CONFIG_VERSION {
+        "config.json.bak".to_string()
+    } else {
+        format!("config.v{}.json.bak", version)
+    };
+    let backup_path = config_path.with_file_name(backup_name);
+    
+    match fs::copy(config_path, &backup_path) {
+        Ok(_) => log_info(&format!("Backed up config to: {}", backup_path.display())),
+        Err(e) => log_error(&format!("Failed to back up config: {}", e)),
+    }
+}
+
 pub fn load_config() -> AppConfig {
     match get_config_path() {
         Ok(config_path) => {
             if config_path.exists() {
                 match fs::read_to_string(&config_path) {
                     Ok(content) => {
-                        match serde_json::from_str::<AppConfig>(&content) {
+                        match parse_config(&content) {
                             Ok(config) => {
-                                println!("Loaded config: {:?}", config);
+                                log_debug(&format!("Loaded config: {:?}", config));
                                 return config;
                             }
                             Err(e) => {
-                                println!("Failed to parse config file: {}", e);
+                                log_warn(&format!("Failed to parse config file: {}", e));
                             }
                         }
                     }
                     Err(e) => {
-                        println!("Failed to read config file: {}", e);
+                        log_warn(&format!("Failed to read config file: {}", e));
                     }
                 }
             } else {
-                println!("Config file not found, using defaults");
+                // First run: follow the Windows display language
+                log_info("Config file not found, using defaults");
+                return AppConfig {
+                    language: LanguageCode::from_string(crate::lang::get_system_language().to_code()),
+                    ..AppConfig::default()
+                };
             }
         }
         Err(e) => {
-            println!("Failed to get config path: {}", e);
+            log_warn(&format!("Failed to get config path: {}", e));
         }
     }
     
@@ -194,8 +949,24 @@ pub fn load_config() -> AppConfig {
 
 pub fn save_config(config: &AppConfig) -> Result<(), Box<dyn std::error::Error>> {
     let config_path = get_config_path()?;
+    backup_config_file(&config_path);
     let content = serde_json::to_string_pretty(config)?;
     fs::write(&config_path, content)?;
-    println!("Saved config: {:?}", config);
+    log_debug(&format!("Saved config: {:?}", config));
     Ok(())
-} 
\ No newline at end of file
+} 
+
+// Writes the whole configuration to a file chosen by the user
+pub fn export_config(config: &AppConfig, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let content = serde_json::to_string_pretty(config)?;
+    fs::write(path, content)?;
+    log_info(&format!("Exported config to: {}", path));
+    Ok(())
+}
+
+pub fn import_config(path: &str) -> Result<AppConfig, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+    let config = parse_config(&content)?;
+    log_info(&format!("Imported config from: {}", path));
+    Ok(config)
+}
@@ -0,0 +1,212 @@
+use windows::{
+    core::{w, PCWSTR},
+    Win32::{
+        Foundation::*,
+        Graphics::{Gdi::*, Imaging::*},
+        System::Com::*,
+    },
+};
+use crate::config::ThumbnailBackground;
+use crate::everything_sdk::FileResult;
+use crate::logger::log_info;
+use crate::thumbnail::{get_shell_thumbnail, to_wide};
+
+// Keeps the sheet bitmap within what GDI and image viewers handle comfortably
+pub const MAX_SHEET_HEIGHT: i32 = 16384;
+
+const CELL_PADDING: i32 = 8;
+const LABEL_HEIGHT: i32 = 36;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ContactSheetOptions {
+    pub columns: u32,
+    pub cell_size: u32,
+    pub background: ThumbnailBackground,
+}
+
+// Sent back to the UI thread once rendering has finished
+pub struct ContactSheetResult {
+    pub file_path: String,
+    pub rendered: usize,
+    pub total: usize,
+    pub error: Option<String>,
+}
+
+// Renders thumbnails and file names into a grid and writes it as a PNG.
+// Must be called on a thread with COM initialized. Returns the number of
+// items drawn, which is less than items.len() when the height limit is hit.
+pub fn render_contact_sheet(items: &[FileResult], options: &ContactSheetOptions, file_path: &str) -> Result<usize, Box<dyn std::error::Error>> {
+    if items.is_empty() {
+        return Err("No items to export".into());
+    }
+
+    let thumbnail_size = options.cell_size as i32;
+    let cell_width = thumbnail_size + CELL_PADDING * 2;
+    let cell_height = cell_width + LABEL_HEIGHT;
+
+    // Don't leave empty columns when there are only a few items
+    let columns = (options.columns.max(1) as usize).min(items.len()) as i32;
+    let needed_rows = (items.len() as i32 + columns - 1) / columns;
+    let rows = needed_rows.min((MAX_SHEET_HEIGHT / cell_height).max(1));
+    let rendered = items.len().min((rows * columns) as usize);
+
+    let width = columns * cell_width;
+    let height = rows * cell_height;
+
+    log_info(&format!("Rendering contact sheet: {} of {} items, {}x{} px", rendered, items.len(), width, height));
+
+    unsafe {
+        let screen_dc = GetDC(HWND(0));
+        let sheet_dc = CreateCompatibleDC(screen_dc);
+        let thumbnail_dc = CreateCompatibleDC(screen_dc);
+        let sheet_bitmap = CreateCompatibleBitmap(screen_dc, width, height);
+        ReleaseDC(HWND(0), screen_dc);
+
+        if sheet_bitmap.is_invalid() {
+            DeleteDC(sheet_dc);
+            DeleteDC(thumbnail_dc);
+            return Err("Failed to allocate the contact sheet bitmap".into());
+        }
+
+        let old_bitmap = SelectObject(sheet_dc, sheet_bitmap);
+
+        let sheet_rect = RECT {
+            left: 0,
+            top: 0,
+            right: width,
+            bottom: height,
+        };
+        let bg_brush = CreateSolidBrush(COLORREF(0x00FFFFFF));
+        FillRect(sheet_dc, &sheet_rect, bg_brush);
+        DeleteObject(bg_brush);
+
+        let font = CreateFontW(
+            14, 0, 0, 0,
+            400,  // FW_NORMAL
+            0, 0, 0,
+            1,    // DEFAULT_CHARSET
+            0,    // OUT_DEFAULT_PRECIS
+            0,    // CLIP_DEFAULT_PRECIS
+            0,    // DEFAULT_QUALITY
+            0,    // DEFAULT_PITCH | FF_DONTCARE
+            w!("Segoe UI"),
+        );
+        let old_font = SelectObject(sheet_dc, font);
+        SetBkMode(sheet_dc, TRANSPARENT);
+        SetTextColor(sheet_dc, COLORREF(0x00000000));
+
+        for (index, item) in items.iter().take(rendered).enumerate() {
+            let x = (index as i32 % columns) * cell_width;
+            let y = (index as i32 / columns) * cell_height;
+
+            if let Some(thumbnail) = get_shell_thumbnail(item.path(), options.cell_size, options.background) {
+                draw_thumbnail(sheet_dc, thumbnail_dc, thumbnail, x + CELL_PADDING, y + CELL_PADDING, thumbnail_size, options.background);
+                DeleteObject(thumbnail);
+            }
+
+            let mut text_rect = RECT {
+                left: x + 2,
+                top: y + CELL_PADDING + thumbnail_size + 4,
+                right: x + cell_width - 2,
+                bottom: y + cell_height - 2,
+            };
+            let mut name_utf16: Vec<u16> = item.name().encode_utf16().collect();
+            DrawTextW(sheet_dc, &mut name_utf16, &mut text_rect, DT_CENTER | DT_WORDBREAK | DT_END_ELLIPSIS | DT_NOPREFIX);
+        }
+
+        SelectObject(sheet_dc, old_font);
+        DeleteObject(font);
+
+        // The bitmap must not be selected into a DC while WIC reads it
+        SelectObject(sheet_dc, old_bitmap);
+        DeleteDC(sheet_dc);
+        DeleteDC(thumbnail_dc);
+
+        let result = encode_png(sheet_bitmap, width as u32, height as u32, file_path);
+        DeleteObject(sheet_bitmap);
+        result?;
+    }
+
+    Ok(rendered)
+}
+
+// Centers a thumbnail inside its cell; shell thumbnails may be smaller than requested
+fn draw_thumbnail(sheet_dc: HDC, thumbnail_dc: HDC, thumbnail: HBITMAP, x: i32, y: i32, size: i32, background: ThumbnailBackground) {
+    unsafe {
+        let mut bitmap_info = BITMAP::default();
+        GetObjectW(
+            thumbnail,
+            std::mem::size_of::<BITMAP>() as i32,
+            Some(&mut bitmap_info as *mut _ as *mut std::ffi::c_void)
+        );
+
+        let src_width = bitmap_info.bmWidth.min(size);
+        let src_height = bitmap_info.bmHeight.abs().min(size);
+        let dest_x = x + (size - src_width) / 2;
+        let dest_y = y + (size - src_height) / 2;
+
+        let old_thumbnail = SelectObject(thumbnail_dc, thumbnail);
+
+        match background {
+            ThumbnailBackground::Transparent => {
+                // Unmodified shell thumbnails carry an alpha channel
+                let blend_func = BLENDFUNCTION {
+                    BlendOp: 0, // AC_SRC_OVER
+                    BlendFlags: 0,
+                    SourceConstantAlpha: 255, // Opaque
+                    AlphaFormat: 1, // AC_SRC_ALPHA
+                };
+
+                let _ = AlphaBlend(
+                    sheet_dc,
+                    dest_x,
+                    dest_y,
+                    src_width,
+                    src_height,
+                    thumbnail_dc,
+                    0,
+                    0,
+                    src_width,
+                    src_height,
+                    blend_func,
+                );
+            }
+            _ => {
+                let _ = BitBlt(sheet_dc, dest_x, dest_y, src_width, src_height, thumbnail_dc, 0, 0, SRCCOPY);
+            }
+        }
+
+        SelectObject(thumbnail_dc, old_thumbnail);
+    }
+}
+
+fn encode_png(bitmap: HBITMAP, width: u32, height: u32, file_path: &str) -> windows::core::Result<()> {
+    unsafe {
+        let factory: IWICImagingFactory = CoCreateInstance(&CLSID_WICImagingFactory, None, CLSCTX_INPROC_SERVER)?;
+        let source = factory.CreateBitmapFromHBITMAP(bitmap, HPALETTE(0), WICBitmapIgnoreAlpha)?;
+
+        let path_wide = to_wide(file_path);
+        let stream = factory.CreateStream()?;
+        stream.InitializeFromFilename(PCWSTR::from_raw(path_wide.as_ptr()), GENERIC_WRITE.0)?;
+
+        let encoder = factory.CreateEncoder(&GUID_ContainerFormatPng, std::ptr::null())?;
+        encoder.Initialize(&stream, WICBitmapEncoderNoCache)?;
+
+        let mut frame = None;
+        let mut frame_options = None;
+        encoder.CreateNewFrame(&mut frame, &mut frame_options)?;
+        let frame = frame.ok_or_else(|| windows::core::Error::from(E_FAIL))?;
+
+        frame.Initialize(frame_options.as_ref())?;
+        frame.SetSize(width, height)?;
+
+        let mut pixel_format = GUID_WICPixelFormat24bppBGR;
+        frame.SetPixelFormat(&mut pixel_format)?;
+        frame.WriteSource(&source, std::ptr::null())?;
+
+        frame.Commit()?;
+        encoder.Commit()?;
+    }
+
+    Ok(())
+}
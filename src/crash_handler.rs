@@ -0,0 +1,162 @@
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Storage::FileSystem::*,
+        System::{
+            Diagnostics::Debug::*,
+            Threading::{GetCurrentProcess, GetCurrentProcessId, GetCurrentThreadId},
+        },
+        UI::{
+            Shell::ShellExecuteW,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::lang::{fill, try_get_strings};
+use crate::logger::try_recent_log_lines;
+use crate::thumbnail::to_wide;
+
+// A panic that ends in an abort reaches the exception filter as well, so
+// only the first report is written
+static CRASH_REPORTED: AtomicBool = AtomicBool::new(false);
+
+// Crash reports go to a "crashes" folder next to config.json
+fn crash_dir() -> Option<PathBuf> {
+    crate::config::get_config_dir().ok().map(|dir| dir.join("crashes"))
+}
+
+// Installs a panic hook and an unhandled-exception filter. Both write a
+// minidump plus the recent log lines and then offer to open the folder.
+pub fn install_crash_handler() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let thread = std::thread::current();
+        let reason = format!(
+            "Panic on thread '{}': {}\r\n\r\n{}",
+            thread.name().unwrap_or("unnamed"),
+            info,
+            std::backtrace::Backtrace::force_capture(),
+        );
+        report_crash(&reason, None);
+        default_hook(info);
+    }));
+
+    unsafe {
+        SetUnhandledExceptionFilter(Some(unhandled_exception_filter));
+    }
+}
+
+unsafe extern "system" fn unhandled_exception_filter(exception: *const EXCEPTION_POINTERS) -> i32 {
+    let code = exception
+        .as_ref()
+        .and_then(|pointers| pointers.ExceptionRecord.as_ref())
+        .map_or(0, |record| record.ExceptionCode.0 as u32);
+    report_crash(&format!("Unhandled exception 0x{:08X}", code), Some(exception));
+
+    // EXCEPTION_EXECUTE_HANDLER: let the process end without the system dialog
+    1
+}
+
+fn report_crash(reason: &str, exception: Option<*const EXCEPTION_POINTERS>) {
+    if CRASH_REPORTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let Some(dir) = crash_dir() else {
+        return;
+    };
+    if std::fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let dump_path = dir.join(format!("crash-{}.dmp", stamp));
+    let log_path = dir.join(format!("crash-{}.log", stamp));
+
+    let dump_written = unsafe { write_minidump(&dump_path, exception) };
+
+    let mut report = String::new();
+    let _ = write!(report, "Everything-like {}\r\n", env!("CARGO_PKG_VERSION"));
+    let _ = write!(report, "{}\r\n\r\n", reason);
+    if !dump_written {
+        report.push_str("The minidump could not be written.\r\n\r\n");
+    }
+    report.push_str("Recent log:\r\n");
+    match try_recent_log_lines() {
+        Some(lines) => {
+            for line in lines {
+                let _ = write!(report, "{}\r\n", line);
+            }
+        }
+        None => report.push_str("(the log was locked by the crashing thread)\r\n"),
+    }
+    let _ = std::fs::write(&log_path, report);
+
+    show_crash_dialog(&dir);
+}
+
+unsafe fn write_minidump(path: &Path, exception: Option<*const EXCEPTION_POINTERS>) -> bool {
+    let path_wide = to_wide(&path.to_string_lossy());
+    let file = match CreateFileW(
+        PCWSTR::from_raw(path_wide.as_ptr()),
+        FILE_GENERIC_WRITE.0,
+        FILE_SHARE_NONE,
+        None,
+        CREATE_ALWAYS,
+        FILE_ATTRIBUTE_NORMAL,
+        HANDLE(0),
+    ) {
+        Ok(file) => file,
+        Err(_) => return false,
+    };
+
+    let exception_info = exception.map(|pointers| MINIDUMP_EXCEPTION_INFORMATION {
+        ThreadId: GetCurrentThreadId(),
+        ExceptionPointers: pointers as *mut EXCEPTION_POINTERS,
+        ClientPointers: FALSE,
+    });
+
+    let result = MiniDumpWriteDump(
+        GetCurrentProcess(),
+        GetCurrentProcessId(),
+        file,
+        MiniDumpNormal,
+        exception_info.as_ref().map(|info| info as *const MINIDUMP_EXCEPTION_INFORMATION),
+        None,
+        None,
+    );
+    let _ = CloseHandle(file);
+
+    result.is_ok()
+}
+
+fn show_crash_dialog(dir: &Path) {
+    let strings = try_get_strings();
+    let dir_text = dir.to_string_lossy().to_string();
+    let message = fill(&strings.crash_message, &[("path", &dir_text)]);
+
+    unsafe {
+        let answer = MessageBoxW(
+            None,
+            PCWSTR::from_raw(to_wide(&message).as_ptr()),
+            PCWSTR::from_raw(to_wide(&strings.crash_title).as_ptr()),
+            MB_YESNO | MB_ICONERROR | MB_TOPMOST,
+        );
+
+        if answer == IDYES {
+            let dir_wide = to_wide(&dir_text);
+            ShellExecuteW(
+                None,
+                w!("open"),
+                PCWSTR::from_raw(dir_wide.as_ptr()),
+                None,
+                None,
+                SW_SHOWNORMAL,
+            );
+        }
+    }
+}
@@ -0,0 +1,488 @@
+use std::collections::HashMap;
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::*,
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Controls::*,
+            Input::KeyboardAndMouse::EnableWindow,
+            Shell::ShellExecuteW,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::everything_sdk::EverythingSDK;
+use crate::lang::{fill, get_strings};
+use crate::list_stats::format_bytes;
+use crate::logger::{log_error, log_info, log_warn};
+use crate::shell_verb::scoped_search_query;
+use crate::thumbnail::to_wide;
+
+// Posted by the worker with a boxed Result<DiskUsageScan, String> in WPARAM
+const WM_DISK_USAGE_SCANNED: u32 = WM_USER + 124;
+
+const DISK_USAGE_WINDOW_CLASS: &str = "EverythingLikeDiskUsage";
+
+const DISK_USAGE_WINDOW_WIDTH: i32 = 900;
+const DISK_USAGE_WINDOW_HEIGHT: i32 = 600;
+const MARGIN: i32 = 10;
+const BUTTON_WIDTH: i32 = 80;
+const BUTTON_HEIGHT: i32 = 26;
+
+// Control IDs
+const ID_DISK_USAGE_FOLDER: i32 = 9821;
+const ID_DISK_USAGE_BROWSE: i32 = 9822;
+const ID_DISK_USAGE_MODE: i32 = 9823;
+const ID_DISK_USAGE_SCAN: i32 = 9824;
+const ID_DISK_USAGE_STATUS: i32 = 9825;
+const ID_DISK_USAGE_LIST: i32 = 9826;
+const ID_DISK_USAGE_CLOSE: i32 = 9827;
+
+// List view columns
+const COLUMN_SIZE: i32 = 1;
+const COLUMN_SHARE: i32 = 2;
+const COLUMN_FILES: i32 = 3;
+
+// How many files the "Largest files" view asks for
+const LARGEST_FILES_COUNT: u32 = 1000;
+
+// Light blue, so the percentage stays readable on top of it
+const BAR_COLOR: COLORREF = COLORREF(0x00F0C080);
+
+// One row: a file, or a folder with everything below it added up
+struct UsageRow {
+    path: String,
+    size: u64,
+    // Number of files, for folder rows
+    files: Option<usize>,
+    // The row for files lying directly in the scanned folder
+    loose: bool,
+}
+
+struct DiskUsageScan {
+    folders: bool,
+    rows: Vec<UsageRow>,
+    total: u64,
+    file_count: usize,
+}
+
+struct DiskUsage {
+    list: HWND,
+    scan: Option<DiskUsageScan>,
+}
+
+// The window's DiskUsage, kept in GWLP_USERDATA
+unsafe fn disk_usage(window: HWND) -> Option<&'static mut DiskUsage> {
+    let usage = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut DiskUsage;
+    usage.as_mut()
+}
+
+pub fn register_disk_usage_window_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(disk_usage_window_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as isize),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeDiskUsage"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// Tools → Biggest Files: asks Everything for the largest files under
+// `folder`, or for the size of each folder in it, and draws a bar per row.
+// An empty folder means every indexed drive.
+pub fn show_disk_usage(owner: HWND, font: HFONT, folder: &str) {
+    unsafe {
+        let strings = get_strings();
+        let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+
+        let window = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            PCWSTR::from_raw(to_wide(DISK_USAGE_WINDOW_CLASS).as_ptr()),
+            PCWSTR::from_raw(to_wide(&strings.disk_usage_title).as_ptr()),
+            WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX | WS_VISIBLE,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            DISK_USAGE_WINDOW_WIDTH,
+            DISK_USAGE_WINDOW_HEIGHT,
+            owner,
+            None,
+            instance,
+            None,
+        );
+
+        if window.0 == 0 {
+            log_error("Failed to create disk usage window");
+            return;
+        }
+
+        let mut client_rect = RECT::default();
+        let _ = GetClientRect(window, &mut client_rect);
+        let width = client_rect.right - MARGIN * 2;
+        let label_width = 70;
+        let mode_width = 160;
+        let folder_width = width - label_width - mode_width - BUTTON_WIDTH * 2 - MARGIN * 3;
+        let mode_x = MARGIN + label_width + folder_width + BUTTON_WIDTH + MARGIN * 2;
+        let status_y = MARGIN * 2 + BUTTON_HEIGHT;
+        let list_y = status_y + 20 + MARGIN;
+        let button_y = client_rect.bottom - MARGIN - BUTTON_HEIGHT;
+
+        let list = CreateWindowExW(
+            WS_EX_CLIENTEDGE,
+            WC_LISTVIEWW,
+            PCWSTR::null(),
+            WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(LVS_REPORT | LVS_SINGLESEL | LVS_SHOWSELALWAYS),
+            MARGIN,
+            list_y,
+            width,
+            button_y - MARGIN - list_y,
+            window,
+            HMENU(ID_DISK_USAGE_LIST as isize),
+            instance,
+            None,
+        );
+        SendMessageW(list, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        SendMessageW(list, LVM_SETEXTENDEDLISTVIEWSTYLE, WPARAM(0), LPARAM((LVS_EX_FULLROWSELECT | LVS_EX_GRIDLINES) as isize));
+
+        let columns = [
+            (&strings.column_path, width - 480),
+            (&strings.column_size, 100),
+            (&strings.disk_usage_share, 280),
+            (&strings.disk_usage_column_files, 80),
+        ];
+        for (index, (name, column_width)) in columns.iter().enumerate() {
+            let mut name_wide = to_wide(name);
+            let column = LVCOLUMNW {
+                mask: LVCF_TEXT | LVCF_WIDTH,
+                cx: *column_width,
+                pszText: PWSTR::from_raw(name_wide.as_mut_ptr()),
+                ..Default::default()
+            };
+            SendMessageW(list, LVM_INSERTCOLUMNW, WPARAM(index), LPARAM(&column as *const _ as isize));
+        }
+
+        let controls = [
+            (w!("STATIC"), strings.disk_usage_folder.clone(), WINDOW_STYLE(0), WINDOW_EX_STYLE::default(), -1, MARGIN, MARGIN + 4, label_width, 20),
+            (w!("EDIT"), folder.to_string(), WS_TABSTOP | WINDOW_STYLE(ES_AUTOHSCROLL as u32), WS_EX_CLIENTEDGE, ID_DISK_USAGE_FOLDER, MARGIN + label_width, MARGIN, folder_width, BUTTON_HEIGHT),
+            (w!("BUTTON"), strings.disk_usage_browse.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), WINDOW_EX_STYLE::default(), ID_DISK_USAGE_BROWSE, MARGIN * 2 + label_width + folder_width, MARGIN, BUTTON_WIDTH, BUTTON_HEIGHT),
+            (w!("COMBOBOX"), String::new(), WS_TABSTOP | WS_VSCROLL | WINDOW_STYLE(CBS_DROPDOWNLIST as u32), WINDOW_EX_STYLE::default(), ID_DISK_USAGE_MODE, mode_x, MARGIN, mode_width, 200),
+            (w!("BUTTON"), strings.disk_usage_scan.clone(), WS_TABSTOP | WINDOW_STYLE(BS_DEFPUSHBUTTON as u32), WINDOW_EX_STYLE::default(), ID_DISK_USAGE_SCAN, client_rect.right - MARGIN - BUTTON_WIDTH, MARGIN, BUTTON_WIDTH, BUTTON_HEIGHT),
+            (w!("STATIC"), String::new(), WINDOW_STYLE(0), WINDOW_EX_STYLE::default(), ID_DISK_USAGE_STATUS, MARGIN, status_y, width, 20),
+            (w!("BUTTON"), strings.stats_close.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), WINDOW_EX_STYLE::default(), ID_DISK_USAGE_CLOSE, client_rect.right - MARGIN - BUTTON_WIDTH, button_y, BUTTON_WIDTH, BUTTON_HEIGHT),
+        ];
+        for (class, text, style, ex_style, id, x, y, width, height) in controls {
+            let control = CreateWindowExW(
+                ex_style,
+                class,
+                PCWSTR::from_raw(to_wide(&text).as_ptr()),
+                WS_CHILD | WS_VISIBLE | style,
+                x, y, width, height,
+                window,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(control, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        }
+
+        let combo = GetDlgItem(window, ID_DISK_USAGE_MODE);
+        for label in [&strings.disk_usage_files, &strings.disk_usage_folders] {
+            SendMessageW(combo, CB_ADDSTRING, WPARAM(0), LPARAM(to_wide(label).as_ptr() as isize));
+        }
+        SendMessageW(combo, CB_SETCURSEL, WPARAM(0), LPARAM(0));
+
+        let usage = Box::new(DiskUsage { list, scan: None });
+        SetWindowLongPtrW(window, GWLP_USERDATA, Box::into_raw(usage) as isize);
+
+        start_scan(window);
+    }
+}
+
+unsafe fn set_status(window: HWND, text: &str) {
+    let _ = SetWindowTextW(GetDlgItem(window, ID_DISK_USAGE_STATUS), PCWSTR::from_raw(to_wide(text).as_ptr()));
+}
+
+unsafe fn folder_text(window: HWND) -> String {
+    let edit = GetDlgItem(window, ID_DISK_USAGE_FOLDER);
+    let mut buffer = vec![0u16; GetWindowTextLengthW(edit) as usize + 1];
+    let copied = GetWindowTextW(edit, &mut buffer);
+    String::from_utf16_lossy(&buffer[..copied as usize]).trim().to_string()
+}
+
+// Runs the query for the folder and view in the controls on a worker thread
+unsafe fn start_scan(window: HWND) {
+    let folder = folder_text(window);
+    let folders = SendMessageW(GetDlgItem(window, ID_DISK_USAGE_MODE), CB_GETCURSEL, WPARAM(0), LPARAM(0)).0 == 1;
+
+    EnableWindow(GetDlgItem(window, ID_DISK_USAGE_SCAN), FALSE);
+    set_status(window, &get_strings().disk_usage_scanning);
+    log_info(&format!("Scanning sizes under '{}'", folder));
+
+    std::thread::spawn(move || {
+        let result = Box::new(scan(&folder, folders));
+        let result_ptr = Box::into_raw(result) as usize;
+        if PostMessageW(window, WM_DISK_USAGE_SCANNED, WPARAM(result_ptr), LPARAM(0)).is_err() {
+            // The window is gone, so nobody will free the result
+            drop(Box::from_raw(result_ptr as *mut std::result::Result<DiskUsageScan, String>));
+        }
+    });
+}
+
+fn scan(folder: &str, folders: bool) -> std::result::Result<DiskUsageScan, String> {
+    let query = if folder.is_empty() { "file:".to_string() } else { format!("{}file:", scoped_search_query(folder)) };
+    let files = {
+        // The SDK keeps one result list per process, so searches take turns
+        // with the main search thread
+        let _guard = crate::EVERYTHING_SDK_MUTEX.lock().unwrap();
+        let max_results = if folders { None } else { Some(LARGEST_FILES_COUNT) };
+        EverythingSDK::new()
+            .and_then(|sdk| sdk.search_by_size(&query, max_results))
+            .map_err(|e| e.to_string())?
+    };
+
+    let file_count = files.len();
+    let total = files.iter().map(|(_, size)| size).sum();
+    let rows = if folders {
+        size_per_folder(folder, files)
+    } else {
+        files
+            .into_iter()
+            .map(|(path, size)| UsageRow { path, size, files: None, loose: false })
+            .collect()
+    };
+    Ok(DiskUsageScan { folders, rows, total, file_count })
+}
+
+// Adds the files up per folder directly below `folder`, or per drive when
+// it is empty, largest first
+fn size_per_folder(folder: &str, files: Vec<(String, u64)>) -> Vec<UsageRow> {
+    let folder = folder.trim_end_matches(['\\', '/']);
+    let prefix = if folder.is_empty() { String::new() } else { format!("{}\\", folder) };
+
+    // Keyed by the lowercased path, since Windows paths ignore case
+    let mut totals: HashMap<String, UsageRow> = HashMap::new();
+    for (path, size) in files {
+        let Some(rest) = path.get(prefix.len()..).filter(|_| path[..prefix.len()].eq_ignore_ascii_case(&prefix)) else {
+            continue;
+        };
+        let (child, loose) = match rest.find('\\') {
+            // A drive keeps its backslash: "C:\"
+            Some(slash) if prefix.is_empty() => (path[..=slash].to_string(), false),
+            Some(slash) => (path[..prefix.len() + slash].to_string(), false),
+            None => (folder.to_string(), true),
+        };
+        let row = totals.entry(child.to_lowercase()).or_insert_with(|| UsageRow { path: child, size: 0, files: Some(0), loose });
+        row.size += size;
+        row.files = row.files.map(|count| count + 1);
+    }
+
+    let mut rows: Vec<UsageRow> = totals.into_values().collect();
+    rows.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    rows
+}
+
+unsafe fn set_cell(list: HWND, row: usize, column: i32, text: &str) {
+    let mut text_wide = to_wide(text);
+    let item = LVITEMW {
+        iSubItem: column,
+        pszText: PWSTR::from_raw(text_wide.as_mut_ptr()),
+        ..Default::default()
+    };
+    SendMessageW(list, LVM_SETITEMTEXTW, WPARAM(row), LPARAM(&item as *const _ as isize));
+}
+
+unsafe fn show_scan(window: HWND, scan: DiskUsageScan) {
+    let Some(usage) = disk_usage(window) else {
+        return;
+    };
+    let strings = get_strings();
+
+    SendMessageW(usage.list, LVM_DELETEALLITEMS, WPARAM(0), LPARAM(0));
+    for (row, entry) in scan.rows.iter().enumerate() {
+        let label = if entry.loose { strings.disk_usage_loose_files.clone() } else { entry.path.clone() };
+        let mut label_wide = to_wide(&label);
+        let item = LVITEMW {
+            mask: LVIF_TEXT,
+            iItem: row as i32,
+            pszText: PWSTR::from_raw(label_wide.as_mut_ptr()),
+            ..Default::default()
+        };
+        SendMessageW(usage.list, LVM_INSERTITEMW, WPARAM(0), LPARAM(&item as *const _ as isize));
+        set_cell(usage.list, row, COLUMN_SIZE, &format_bytes(entry.size, &strings));
+        if let Some(files) = entry.files {
+            set_cell(usage.list, row, COLUMN_FILES, &files.to_string());
+        }
+    }
+
+    let summary = if scan.folders {
+        fill(&strings.disk_usage_folders_summary, &[
+            ("files", &scan.file_count.to_string()),
+            ("count", &scan.rows.len().to_string()),
+            ("size", &format_bytes(scan.total, &strings)),
+        ])
+    } else {
+        fill(&strings.disk_usage_files_summary, &[
+            ("count", &scan.file_count.to_string()),
+            ("size", &format_bytes(scan.total, &strings)),
+        ])
+    };
+    set_status(window, &summary);
+    usage.scan = Some(scan);
+}
+
+// Draws the share column: a bar as long as the row's size compared with
+// the largest row, and its percentage of the total
+unsafe fn draw_share_cell(usage: &DiskUsage, draw: &NMLVCUSTOMDRAW) {
+    let Some(scan) = &usage.scan else {
+        return;
+    };
+    let Some(entry) = scan.rows.get(draw.nmcd.dwItemSpec) else {
+        return;
+    };
+
+    let mut cell = RECT { top: COLUMN_SHARE, left: LVIR_BOUNDS as i32, ..Default::default() };
+    SendMessageW(usage.list, LVM_GETSUBITEMRECT, WPARAM(draw.nmcd.dwItemSpec), LPARAM(&mut cell as *mut RECT as isize));
+
+    let hdc = draw.nmcd.hdc;
+    FillRect(hdc, &cell, GetSysColorBrush(COLOR_WINDOW));
+
+    let largest = scan.rows.first().map_or(1, |first| first.size.max(1));
+    let inner = RECT { left: cell.left + 3, top: cell.top + 3, right: cell.right - 3, bottom: cell.bottom - 3 };
+    let bar_width = ((inner.right - inner.left).max(0) as u64 * entry.size / largest) as i32;
+    if bar_width > 0 {
+        let bar_brush = CreateSolidBrush(BAR_COLOR);
+        FillRect(hdc, &RECT { right: inner.left + bar_width, ..inner }, bar_brush);
+        DeleteObject(bar_brush);
+    }
+
+    let percent = entry.size as f64 * 100.0 / scan.total.max(1) as f64;
+    let mut text: Vec<u16> = format!("{:.1}%", percent).encode_utf16().collect();
+    let mut text_rect = RECT { left: inner.left + 4, ..inner };
+    SetBkMode(hdc, TRANSPARENT);
+    SetTextColor(hdc, COLORREF(GetSysColor(COLOR_WINDOWTEXT)));
+    DrawTextW(hdc, &mut text, &mut text_rect, DT_LEFT | DT_VCENTER | DT_SINGLELINE);
+}
+
+unsafe fn handle_custom_draw(window: HWND, draw: &NMLVCUSTOMDRAW) -> LRESULT {
+    let stage = draw.nmcd.dwDrawStage;
+    if stage == CDDS_PREPAINT {
+        LRESULT(CDRF_NOTIFYITEMDRAW as isize)
+    } else if stage == CDDS_ITEMPREPAINT {
+        LRESULT(CDRF_NOTIFYSUBITEMDRAW as isize)
+    } else if stage.0 == CDDS_ITEMPREPAINT.0 | CDDS_SUBITEM.0 && draw.iSubItem == COLUMN_SHARE {
+        if let Some(usage) = disk_usage(window) {
+            draw_share_cell(usage, draw);
+        }
+        LRESULT(CDRF_SKIPDEFAULT as isize)
+    } else {
+        LRESULT(CDRF_DODEFAULT as isize)
+    }
+}
+
+// Opens a folder row as the next scan; shows a file in Explorer
+unsafe fn open_row(window: HWND, row: usize) {
+    let Some(usage) = disk_usage(window) else {
+        return;
+    };
+    let Some(entry) = usage.scan.as_ref().and_then(|scan| scan.rows.get(row)) else {
+        return;
+    };
+
+    if entry.files.is_some() && !entry.loose {
+        let _ = SetWindowTextW(GetDlgItem(window, ID_DISK_USAGE_FOLDER), PCWSTR::from_raw(to_wide(&entry.path).as_ptr()));
+        start_scan(window);
+    } else if !entry.loose {
+        let arguments = format!("/select,\"{}\"", entry.path);
+        ShellExecuteW(
+            None,
+            w!("open"),
+            w!("explorer.exe"),
+            PCWSTR::from_raw(to_wide(&arguments).as_ptr()),
+            None,
+            SW_SHOWNORMAL,
+        );
+    }
+}
+
+extern "system" fn disk_usage_window_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_DISK_USAGE_SCANNED => {
+                let result = *Box::from_raw(wparam.0 as *mut std::result::Result<DiskUsageScan, String>);
+                EnableWindow(GetDlgItem(window, ID_DISK_USAGE_SCAN), TRUE);
+                match result {
+                    Ok(scan) => show_scan(window, scan),
+                    Err(e) => {
+                        log_warn(&format!("Disk usage scan failed: {}", e));
+                        set_status(window, &fill(&get_strings().error_disk_usage, &[("error", &e)]));
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_NOTIFY => {
+                let header = &*(lparam.0 as *const NMHDR);
+                if header.idFrom == ID_DISK_USAGE_LIST as usize {
+                    if header.code == NM_CUSTOMDRAW {
+                        return handle_custom_draw(window, &*(lparam.0 as *const NMLVCUSTOMDRAW));
+                    } else if header.code == NM_DBLCLK {
+                        let activate = &*(lparam.0 as *const NMITEMACTIVATE);
+                        if activate.iItem >= 0 {
+                            open_row(window, activate.iItem as usize);
+                        }
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                match (wparam.0 & 0xFFFF) as i32 {
+                    ID_DISK_USAGE_BROWSE => {
+                        if let Some(folder) = crate::show_folder_dialog(window, &get_strings().disk_usage_title) {
+                            let _ = SetWindowTextW(GetDlgItem(window, ID_DISK_USAGE_FOLDER), PCWSTR::from_raw(to_wide(&folder).as_ptr()));
+                            start_scan(window);
+                        }
+                    }
+                    ID_DISK_USAGE_SCAN => start_scan(window),
+                    ID_DISK_USAGE_CLOSE => {
+                        let _ = DestroyWindow(window);
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(window);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                let usage = SetWindowLongPtrW(window, GWLP_USERDATA, 0) as *mut DiskUsage;
+                if !usage.is_null() {
+                    drop(Box::from_raw(usage));
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
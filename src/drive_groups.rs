@@ -0,0 +1,110 @@
+use std::collections::HashSet;
+use crate::everything_sdk::FileResult;
+
+// A run of results on the same volume while the details view is grouped by
+// drive. The results are sorted drive first, so each volume is one run.
+pub struct DriveGroup {
+    // "C:" or "\\server\share"
+    pub label: String,
+    // Index of the first result of the run
+    pub start: usize,
+    pub len: usize,
+    pub total_size: u64,
+    pub collapsed: bool,
+}
+
+// What a row of the grouped details view shows
+#[derive(Clone, Copy, PartialEq)]
+pub enum DetailsRow {
+    Header(usize),
+    Item(usize),
+}
+
+// The volume `path` is on: its drive letter or the server and share of a
+// UNC path. Empty for anything else.
+pub fn drive_of(path: &str) -> &str {
+    if let Some(rest) = path.strip_prefix("\\\\") {
+        let mut end = 2;
+        for (count, part) in rest.splitn(3, '\\').take(2).enumerate() {
+            end += part.len() + count;
+        }
+        return &path[..end.min(path.len())];
+    }
+    match path.as_bytes() {
+        [letter, b':', ..] if letter.is_ascii_alphabetic() => &path[..2],
+        _ => "",
+    }
+}
+
+// What results are ordered by ahead of the sort column, so that each
+// volume's results are together
+pub fn drive_sort_key(path: &str) -> String {
+    drive_of(path).to_uppercase()
+}
+
+// The runs of `results` on the same volume, those labelled in `collapsed`
+// showing their header only
+pub fn group_by_drive(results: &[FileResult], collapsed: &HashSet<String>) -> Vec<DriveGroup> {
+    let mut groups: Vec<DriveGroup> = Vec::new();
+    for (index, result) in results.iter().enumerate() {
+        let drive = drive_of(result.path());
+        match groups.last_mut() {
+            Some(group) if group.label.eq_ignore_ascii_case(drive) => {
+                group.len += 1;
+                group.total_size += result.size;
+            }
+            _ => groups.push(DriveGroup {
+                label: drive.to_string(),
+                start: index,
+                len: 1,
+                total_size: result.size,
+                collapsed: collapsed.contains(drive),
+            }),
+        }
+    }
+    groups
+}
+
+fn shown_rows(group: &DriveGroup) -> usize {
+    if group.collapsed { 1 } else { 1 + group.len }
+}
+
+// Rows the grouped view has, headers included
+pub fn row_count(groups: &[DriveGroup]) -> usize {
+    groups.iter().map(shown_rows).sum()
+}
+
+// What row `row` of the grouped view shows
+pub fn row_at(groups: &[DriveGroup], row: usize) -> Option<DetailsRow> {
+    let mut first = 0;
+    for (group_index, group) in groups.iter().enumerate() {
+        let rows = shown_rows(group);
+        if row < first + rows {
+            return Some(if row == first {
+                DetailsRow::Header(group_index)
+            } else {
+                DetailsRow::Item(group.start + row - first - 1)
+            });
+        }
+        first += rows;
+    }
+    None
+}
+
+// The row showing result `index`, or the header of its group when that is
+// collapsed
+pub fn row_of(groups: &[DriveGroup], index: usize) -> usize {
+    let mut first = 0;
+    for group in groups {
+        if index < group.start + group.len {
+            return if group.collapsed { first } else { first + 1 + index - group.start };
+        }
+        first += shown_rows(group);
+    }
+    first
+}
+
+// The group result `index` belongs to
+pub fn group_of(groups: &[DriveGroup], index: usize) -> Option<usize> {
+    groups.iter().position(|group| index >= group.start && index < group.start + group.len)
+}
@@ -0,0 +1,568 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use rayon::prelude::*;
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{HBRUSH, HFONT, COLOR_BTNFACE},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Controls::*,
+            Input::KeyboardAndMouse::EnableWindow,
+            Shell::ShellExecuteW,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::file_hash::sha256_file;
+use crate::lang::{fill, get_strings};
+use crate::list_stats::format_bytes;
+use crate::elevation::WM_ELEVATED_RECYCLE_DONE;
+use crate::logger::{log_error, log_info, log_warn};
+use crate::recycle_bin::{move_to_recycle_bin, notify_recycled};
+use crate::thumbnail::to_wide;
+
+// Posted by the worker with the number of files hashed in WPARAM and the
+// number to hash in LPARAM
+const WM_DUPLICATES_PROGRESS: u32 = WM_USER + 121;
+
+// Posted by the worker with a boxed Vec<DuplicateGroup> in WPARAM
+const WM_DUPLICATES_FOUND: u32 = WM_USER + 122;
+
+const DUPLICATE_WINDOW_CLASS: &str = "EverythingLikeDuplicateFinder";
+
+const DUPLICATE_WINDOW_WIDTH: i32 = 860;
+const DUPLICATE_WINDOW_HEIGHT: i32 = 560;
+const MARGIN: i32 = 10;
+const BUTTON_WIDTH: i32 = 190;
+const BUTTON_HEIGHT: i32 = 26;
+
+// Control IDs
+const ID_DUPLICATES_STATUS: i32 = 9801;
+const ID_DUPLICATES_LIST: i32 = 9802;
+const ID_DUPLICATES_KEEP_FIRST: i32 = 9803;
+const ID_DUPLICATES_DELETE: i32 = 9804;
+const ID_DUPLICATES_CLOSE: i32 = 9805;
+
+// List view columns
+const COLUMN_PATH: i32 = 1;
+const COLUMN_SIZE: i32 = 2;
+
+// Progress is posted after this many files, so a large search doesn't
+// flood the message queue
+const PROGRESS_STEP: usize = 32;
+
+// Files with the same size and SHA-256
+struct DuplicateGroup {
+    size: u64,
+    paths: Vec<String>,
+}
+
+impl DuplicateGroup {
+    // What deleting all copies but one would free
+    fn reclaimable(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+struct DuplicateFinder {
+    owner: HWND,
+    list: HWND,
+    groups: Vec<DuplicateGroup>,
+    // Group and path index of each list row
+    rows: Vec<(usize, usize)>,
+    // Set when the window closes, so the worker stops hashing
+    cancel: Arc<AtomicBool>,
+    // Set while the list is filled, so check changes aren't counted one by one
+    filling: bool,
+}
+
+// The window's DuplicateFinder, kept in GWLP_USERDATA
+unsafe fn duplicate_finder(window: HWND) -> Option<&'static mut DuplicateFinder> {
+    let finder = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut DuplicateFinder;
+    finder.as_mut()
+}
+
+pub fn register_duplicate_window_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(duplicate_window_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as isize),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeDuplicateFinder"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// Tools → Find Duplicates: groups `paths` by size, hashes the files that
+// share one on worker threads and lists the true duplicates. Every copy
+// but the first of each group is checked, ready to go to the Recycle Bin.
+pub fn show_duplicate_finder(owner: HWND, font: HFONT, paths: Vec<String>) {
+    unsafe {
+        let strings = get_strings();
+        let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+
+        let window = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            PCWSTR::from_raw(to_wide(DUPLICATE_WINDOW_CLASS).as_ptr()),
+            PCWSTR::from_raw(to_wide(&strings.duplicates_title).as_ptr()),
+            WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX | WS_VISIBLE,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            DUPLICATE_WINDOW_WIDTH,
+            DUPLICATE_WINDOW_HEIGHT,
+            owner,
+            None,
+            instance,
+            None,
+        );
+
+        if window.0 == 0 {
+            log_error("Failed to create duplicate finder window");
+            return;
+        }
+
+        let mut client_rect = RECT::default();
+        let _ = GetClientRect(window, &mut client_rect);
+        let width = client_rect.right - MARGIN * 2;
+        let button_y = client_rect.bottom - MARGIN - BUTTON_HEIGHT;
+        let list_y = MARGIN * 2 + 20;
+        let list_height = button_y - MARGIN - list_y;
+
+        let list = CreateWindowExW(
+            WS_EX_CLIENTEDGE,
+            WC_LISTVIEWW,
+            PCWSTR::null(),
+            WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(LVS_REPORT | LVS_SHOWSELALWAYS),
+            MARGIN,
+            list_y,
+            width,
+            list_height,
+            window,
+            HMENU(ID_DUPLICATES_LIST as isize),
+            instance,
+            None,
+        );
+        SendMessageW(list, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        SendMessageW(
+            list,
+            LVM_SETEXTENDEDLISTVIEWSTYLE,
+            WPARAM(0),
+            LPARAM((LVS_EX_FULLROWSELECT | LVS_EX_GRIDLINES | LVS_EX_CHECKBOXES) as isize),
+        );
+
+        let columns = [(&strings.duplicates_column_group, 70), (&strings.column_path, width - 200), (&strings.column_size, 100)];
+        for (index, (name, column_width)) in columns.iter().enumerate() {
+            let mut name_wide = to_wide(name);
+            let column = LVCOLUMNW {
+                mask: LVCF_TEXT | LVCF_WIDTH,
+                cx: *column_width,
+                pszText: PWSTR::from_raw(name_wide.as_mut_ptr()),
+                ..Default::default()
+            };
+            SendMessageW(list, LVM_INSERTCOLUMNW, WPARAM(index), LPARAM(&column as *const _ as isize));
+        }
+
+        let status = fill(&strings.duplicates_comparing, &[("count", &paths.len().to_string())]);
+        let controls = [
+            (w!("STATIC"), status, WINDOW_STYLE(0), ID_DUPLICATES_STATUS, MARGIN, MARGIN, width, 20),
+            (w!("BUTTON"), strings.duplicates_keep_first.clone(), WS_TABSTOP | WS_DISABLED | WINDOW_STYLE(BS_PUSHBUTTON as u32), ID_DUPLICATES_KEEP_FIRST, MARGIN, button_y, BUTTON_WIDTH, BUTTON_HEIGHT),
+            (w!("BUTTON"), strings.duplicates_delete.clone(), WS_TABSTOP | WS_DISABLED | WINDOW_STYLE(BS_PUSHBUTTON as u32), ID_DUPLICATES_DELETE, MARGIN * 2 + BUTTON_WIDTH, button_y, BUTTON_WIDTH, BUTTON_HEIGHT),
+            (w!("BUTTON"), strings.stats_close.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), ID_DUPLICATES_CLOSE, client_rect.right - MARGIN - 80, button_y, 80, BUTTON_HEIGHT),
+        ];
+        for (class, text, style, id, x, y, width, height) in controls {
+            let control = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                class,
+                PCWSTR::from_raw(to_wide(&text).as_ptr()),
+                WS_CHILD | WS_VISIBLE | style,
+                x, y, width, height,
+                window,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(control, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        }
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let finder = Box::new(DuplicateFinder {
+            owner,
+            list,
+            groups: Vec::new(),
+            rows: Vec::new(),
+            cancel: cancel.clone(),
+            filling: false,
+        });
+        SetWindowLongPtrW(window, GWLP_USERDATA, Box::into_raw(finder) as isize);
+
+        log_info(&format!("Looking for duplicates among {} files", paths.len()));
+        std::thread::spawn(move || {
+            let groups = find_duplicates(paths, &cancel, |done, total| {
+                let _ = PostMessageW(window, WM_DUPLICATES_PROGRESS, WPARAM(done), LPARAM(total as isize));
+            });
+
+            let groups_ptr = Box::into_raw(Box::new(groups)) as usize;
+            if PostMessageW(window, WM_DUPLICATES_FOUND, WPARAM(groups_ptr), LPARAM(0)).is_err() {
+                // The window is gone, so nobody will free the result
+                drop(Box::from_raw(groups_ptr as *mut Vec<DuplicateGroup>));
+            }
+        });
+    }
+}
+
+// Only files that share a size can be equal, so only those are hashed.
+// Groups come back with the most space to reclaim first.
+fn find_duplicates<F>(mut paths: Vec<String>, cancel: &AtomicBool, on_progress: F) -> Vec<DuplicateGroup>
+where
+    F: Fn(usize, usize) + Sync,
+{
+    // A list can name the same file twice, which isn't a duplicate
+    paths.sort_by_key(|path| path.to_lowercase());
+    paths.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+
+    let sized: Vec<(u64, String)> = paths
+        .into_par_iter()
+        .filter_map(|path| {
+            let metadata = std::fs::metadata(&path).ok()?;
+            (metadata.is_file() && metadata.len() > 0).then_some((metadata.len(), path))
+        })
+        .collect();
+
+    let mut by_size: HashMap<u64, Vec<String>> = HashMap::new();
+    for (size, path) in sized {
+        by_size.entry(size).or_default().push(path);
+    }
+    let candidates: Vec<(u64, String)> = by_size
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .flat_map(|(size, paths)| paths.into_iter().map(move |path| (size, path)))
+        .collect();
+
+    let total = candidates.len();
+    let hashed = AtomicUsize::new(0);
+    on_progress(0, total);
+    let digests: Vec<((u64, [u8; 32]), String)> = candidates
+        .into_par_iter()
+        .filter_map(|(size, path)| {
+            if cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+            let digest = sha256_file(Path::new(&path));
+            let done = hashed.fetch_add(1, Ordering::Relaxed) + 1;
+            if done.is_multiple_of(PROGRESS_STEP) || done == total {
+                on_progress(done, total);
+            }
+            match digest {
+                Ok(digest) => Some(((size, digest), path)),
+                Err(e) => {
+                    log_warn(&format!("Could not hash {}: {}", path, e));
+                    None
+                }
+            }
+        })
+        .collect();
+
+    let mut by_digest: HashMap<(u64, [u8; 32]), Vec<String>> = HashMap::new();
+    for (key, path) in digests {
+        by_digest.entry(key).or_default().push(path);
+    }
+    let mut groups: Vec<DuplicateGroup> = by_digest
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .map(|((size, _), mut paths)| {
+            paths.sort_by_key(|path| path.to_lowercase());
+            DuplicateGroup { size, paths }
+        })
+        .collect();
+    groups.sort_by(|a, b| b.reclaimable().cmp(&a.reclaimable()).then_with(|| a.paths.cmp(&b.paths)));
+    groups
+}
+
+unsafe fn set_cell(list: HWND, row: usize, column: i32, text: &str) {
+    let mut text_wide = to_wide(text);
+    let item = LVITEMW {
+        iSubItem: column,
+        pszText: PWSTR::from_raw(text_wide.as_mut_ptr()),
+        ..Default::default()
+    };
+    SendMessageW(list, LVM_SETITEMTEXTW, WPARAM(row), LPARAM(&item as *const _ as isize));
+}
+
+unsafe fn is_checked(list: HWND, row: usize) -> bool {
+    let state = SendMessageW(list, LVM_GETITEMSTATE, WPARAM(row), LPARAM(LVIS_STATEIMAGEMASK.0 as isize)).0 as u32;
+    // State image 2 is the checked box, 1 the empty one
+    (state >> 12) == 2
+}
+
+unsafe fn set_checked(list: HWND, row: usize, checked: bool) {
+    let item = LVITEMW {
+        stateMask: LVIS_STATEIMAGEMASK,
+        state: LIST_VIEW_ITEM_STATE_FLAGS(if checked { 2 << 12 } else { 1 << 12 }),
+        ..Default::default()
+    };
+    SendMessageW(list, LVM_SETITEMSTATE, WPARAM(row), LPARAM(&item as *const _ as isize));
+}
+
+// Fills the list from the groups, one row per file
+unsafe fn fill_list(window: HWND) {
+    let Some(finder) = duplicate_finder(window) else {
+        return;
+    };
+    let strings = get_strings();
+
+    finder.filling = true;
+    SendMessageW(finder.list, LVM_DELETEALLITEMS, WPARAM(0), LPARAM(0));
+    finder.rows.clear();
+    for (group_index, group) in finder.groups.iter().enumerate() {
+        for (path_index, path) in group.paths.iter().enumerate() {
+            let row = finder.rows.len();
+            let mut group_wide = to_wide(&(group_index + 1).to_string());
+            let item = LVITEMW {
+                mask: LVIF_TEXT,
+                iItem: row as i32,
+                pszText: PWSTR::from_raw(group_wide.as_mut_ptr()),
+                ..Default::default()
+            };
+            SendMessageW(finder.list, LVM_INSERTITEMW, WPARAM(0), LPARAM(&item as *const _ as isize));
+            set_cell(finder.list, row, COLUMN_PATH, path);
+            set_cell(finder.list, row, COLUMN_SIZE, &format_bytes(group.size, &strings));
+            finder.rows.push((group_index, path_index));
+        }
+    }
+    finder.filling = false;
+
+    let has_groups = !finder.groups.is_empty();
+    EnableWindow(GetDlgItem(window, ID_DUPLICATES_KEEP_FIRST), BOOL::from(has_groups));
+    EnableWindow(GetDlgItem(window, ID_DUPLICATES_DELETE), BOOL::from(has_groups));
+
+    keep_first_of_each(window);
+}
+
+// Checks every copy but the first of each group
+unsafe fn keep_first_of_each(window: HWND) {
+    let Some(finder) = duplicate_finder(window) else {
+        return;
+    };
+    finder.filling = true;
+    for (row, &(_, path_index)) in finder.rows.iter().enumerate() {
+        set_checked(finder.list, row, path_index > 0);
+    }
+    finder.filling = false;
+    update_summary(window);
+}
+
+// The rows checked for deletion
+unsafe fn checked_rows(finder: &DuplicateFinder) -> Vec<usize> {
+    (0..finder.rows.len()).filter(|&row| is_checked(finder.list, row)).collect()
+}
+
+unsafe fn set_status(window: HWND, text: &str) {
+    let _ = SetWindowTextW(GetDlgItem(window, ID_DUPLICATES_STATUS), PCWSTR::from_raw(to_wide(text).as_ptr()));
+}
+
+unsafe fn update_summary(window: HWND) {
+    let Some(finder) = duplicate_finder(window) else {
+        return;
+    };
+    let strings = get_strings();
+    if finder.groups.is_empty() {
+        set_status(window, &strings.duplicates_none);
+        return;
+    }
+
+    let reclaimable: u64 = finder.groups.iter().map(DuplicateGroup::reclaimable).sum();
+    let checked = checked_rows(finder);
+    let checked_size: u64 = checked.iter().map(|&row| finder.groups[finder.rows[row].0].size).sum();
+    let summary = fill(&strings.duplicates_summary, &[
+        ("groups", &finder.groups.len().to_string()),
+        ("reclaimable", &format_bytes(reclaimable, &strings)),
+        ("count", &checked.len().to_string()),
+        ("size", &format_bytes(checked_size, &strings)),
+    ]);
+    set_status(window, &summary);
+}
+
+// Moves the checked files to the Recycle Bin, refusing when that would take
+// every copy of a group
+unsafe fn delete_checked(window: HWND) {
+    let Some(finder) = duplicate_finder(window) else {
+        return;
+    };
+    let strings = get_strings();
+    let checked = checked_rows(finder);
+    if checked.is_empty() {
+        return;
+    }
+
+    for (group_index, group) in finder.groups.iter().enumerate() {
+        let checked_in_group = checked.iter().filter(|&&row| finder.rows[row].0 == group_index).count();
+        if checked_in_group == group.paths.len() {
+            let message = fill(&strings.duplicates_all_copies, &[("group", &(group_index + 1).to_string())]);
+            MessageBoxW(
+                window,
+                PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                PCWSTR::from_raw(to_wide(&strings.duplicates_title).as_ptr()),
+                MB_ICONEXCLAMATION | MB_OK,
+            );
+            return;
+        }
+    }
+
+    let paths: Vec<String> = checked
+        .iter()
+        .map(|&row| {
+            let (group_index, path_index) = finder.rows[row];
+            finder.groups[group_index].paths[path_index].clone()
+        })
+        .collect();
+    let size: u64 = checked.iter().map(|&row| finder.groups[finder.rows[row].0].size).sum();
+    let message = fill(&strings.duplicates_confirm_delete, &[
+        ("count", &paths.len().to_string()),
+        ("size", &format_bytes(size, &strings)),
+    ]);
+    let answer = MessageBoxW(
+        window,
+        PCWSTR::from_raw(to_wide(&message).as_ptr()),
+        PCWSTR::from_raw(to_wide(&strings.confirm_title).as_ptr()),
+        MB_ICONQUESTION | MB_YESNO | MB_DEFBUTTON2,
+    );
+    if answer != IDYES {
+        return;
+    }
+
+    let deleted = move_to_recycle_bin(window, paths);
+    forget_deleted(window, deleted);
+}
+
+// Drops the files in `deleted` from the groups and tells the main window
+unsafe fn forget_deleted(window: HWND, deleted: Vec<String>) {
+    let Some(finder) = duplicate_finder(window) else {
+        return;
+    };
+    if deleted.is_empty() {
+        return;
+    }
+
+    for group in &mut finder.groups {
+        group.paths.retain(|path| !deleted.contains(path));
+    }
+    finder.groups.retain(|group| group.paths.len() > 1);
+    let owner = finder.owner;
+    fill_list(window);
+
+    notify_recycled(owner, deleted);
+}
+
+// Shows the file of `row` selected in Explorer
+unsafe fn open_row_location(window: HWND, row: usize) {
+    let Some(finder) = duplicate_finder(window) else {
+        return;
+    };
+    let Some(&(group_index, path_index)) = finder.rows.get(row) else {
+        return;
+    };
+    let arguments = format!("/select,\"{}\"", finder.groups[group_index].paths[path_index]);
+    ShellExecuteW(
+        None,
+        w!("open"),
+        w!("explorer.exe"),
+        PCWSTR::from_raw(to_wide(&arguments).as_ptr()),
+        None,
+        SW_SHOWNORMAL,
+    );
+}
+
+extern "system" fn duplicate_window_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_DUPLICATES_PROGRESS => {
+                let status = fill(&get_strings().duplicates_hashing, &[
+                    ("done", &wparam.0.to_string()),
+                    ("total", &lparam.0.to_string()),
+                ]);
+                set_status(window, &status);
+                LRESULT(0)
+            }
+            WM_DUPLICATES_FOUND => {
+                let groups = Box::from_raw(wparam.0 as *mut Vec<DuplicateGroup>);
+                if let Some(finder) = duplicate_finder(window) {
+                    log_info(&format!("Found {} groups of duplicates", groups.len()));
+                    finder.groups = *groups;
+                    fill_list(window);
+                }
+                LRESULT(0)
+            }
+            WM_ELEVATED_RECYCLE_DONE => {
+                let deleted = Box::from_raw(wparam.0 as *mut Vec<String>);
+                forget_deleted(window, *deleted);
+                LRESULT(0)
+            }
+            WM_NOTIFY => {
+                let header = &*(lparam.0 as *const NMHDR);
+                if header.idFrom == ID_DUPLICATES_LIST as usize {
+                    if header.code == LVN_ITEMCHANGED {
+                        let change = &*(lparam.0 as *const NMLISTVIEW);
+                        let check_changed = (change.uNewState ^ change.uOldState) & LVIS_STATEIMAGEMASK.0 != 0;
+                        if check_changed && duplicate_finder(window).is_some_and(|finder| !finder.filling) {
+                            update_summary(window);
+                        }
+                    } else if header.code == NM_DBLCLK {
+                        let activate = &*(lparam.0 as *const NMITEMACTIVATE);
+                        if activate.iItem >= 0 {
+                            open_row_location(window, activate.iItem as usize);
+                        }
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                match (wparam.0 & 0xFFFF) as i32 {
+                    ID_DUPLICATES_KEEP_FIRST => keep_first_of_each(window),
+                    ID_DUPLICATES_DELETE => delete_checked(window),
+                    ID_DUPLICATES_CLOSE => {
+                        let _ = DestroyWindow(window);
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(window);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                let finder = SetWindowLongPtrW(window, GWLP_USERDATA, 0) as *mut DuplicateFinder;
+                if !finder.is_null() {
+                    let finder = Box::from_raw(finder);
+                    finder.cancel.store(true, Ordering::Relaxed);
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
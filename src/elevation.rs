@@ -0,0 +1,201 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use windows::{
+    core::{w, PCWSTR},
+    Win32::{
+        Foundation::*,
+        System::Threading::{GetExitCodeProcess, WaitForSingleObject, INFINITE},
+        UI::{
+            Shell::{IsUserAnAdmin, ShellExecuteExW, ShellExecuteW, SHELLEXECUTEINFOW, SEE_MASK_NOASYNC, SEE_MASK_NOCLOSEPROCESS},
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::lang::get_strings;
+use crate::logger::{log_info, log_warn};
+use crate::long_paths::shell_path;
+use crate::recycle_bin::recycle_paths;
+use crate::shell_verb::{argument_value, quote_argument};
+use crate::thumbnail::to_wide;
+
+// This executable is started elevated with one of these to repeat an
+// action that was refused for lack of rights
+const ELEVATED_OPEN_ARG: &str = "--elevated-open";
+const ELEVATED_RECYCLE_ARG: &str = "--elevated-recycle";
+
+// Posted to the window that asked for an elevated Recycle Bin move once
+// the helper is done, with a boxed Vec<String> in WPARAM of the paths that
+// are gone
+pub const WM_ELEVATED_RECYCLE_DONE: u32 = WM_USER + 147;
+
+// Numbers the path lists of elevated Recycle Bin moves, which may overlap
+static RECYCLE_LIST_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// What the elevated helper was started to do
+pub enum ElevatedAction {
+    Open(String),
+    // A temporary file listing the paths, one per line
+    Recycle(PathBuf),
+}
+
+// The action to run when this process was started as the elevated helper
+pub fn elevated_action_argument() -> Option<ElevatedAction> {
+    argument_value(ELEVATED_OPEN_ARG)
+        .map(ElevatedAction::Open)
+        .or_else(|| argument_value(ELEVATED_RECYCLE_ARG).map(|list| ElevatedAction::Recycle(PathBuf::from(list))))
+}
+
+// Runs as the helper. Returns the exit code, which tells the app waiting
+// on a Recycle Bin move whether everything went.
+pub fn run_elevated_action(action: ElevatedAction) -> u32 {
+    match action {
+        ElevatedAction::Open(path) => {
+            if shell_open(&path) <= 32 {
+                show_error(HWND(0), &get_strings().error_open_file, &[path]);
+                return 1;
+            }
+            0
+        }
+        ElevatedAction::Recycle(list) => {
+            let paths: Vec<String> = std::fs::read_to_string(&list)
+                .unwrap_or_default()
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect();
+            let result = recycle_paths(HWND(0), &paths);
+            let failed: Vec<String> = paths.into_iter().filter(|path| Path::new(path).exists()).collect();
+            if !failed.is_empty() && result.failed() {
+                show_error(HWND(0), &get_strings().error_delete_files, &failed);
+            }
+            u32::from(!failed.is_empty())
+        }
+    }
+}
+
+// Opens `path` with its associated program, as ShellExecute returns it
+pub fn shell_open(path: &str) -> isize {
+    let path_wide = to_wide(&shell_path(path));
+    unsafe {
+        ShellExecuteW(None, w!("open"), PCWSTR::from_raw(path_wide.as_ptr()), None, None, SW_SHOWNORMAL).0
+    }
+}
+
+// Whether a ShellExecute result or a Win32 error code means the user
+// lacks the rights, SE_ERR_ACCESSDENIED and ERROR_ACCESS_DENIED both being 5
+pub fn is_access_denied(code: u32) -> bool {
+    code == ERROR_ACCESS_DENIED.0
+}
+
+// Tells the user `paths` couldn't be acted on for lack of rights and asks
+// whether to try again as administrator. Always false when this process
+// already runs elevated, as there is nothing more to gain.
+pub fn confirm_elevated_retry(window: HWND, paths: &[String]) -> bool {
+    if unsafe { IsUserAnAdmin().as_bool() } {
+        return false;
+    }
+    let strings = get_strings();
+    let message = format!("{}\n{}\n\n{}", strings.error_access_denied, paths.join("\n"), strings.retry_elevated_prompt);
+    let title = to_wide(&strings.retry_elevated_title);
+    unsafe {
+        MessageBoxW(
+            window,
+            PCWSTR::from_raw(to_wide(&message).as_ptr()),
+            PCWSTR::from_raw(title.as_ptr()),
+            MB_ICONWARNING | MB_YESNO,
+        ) == IDYES
+    }
+}
+
+// Opens `path` from an elevated copy of this executable
+pub fn open_elevated(path: &str) {
+    let arguments = format!("{} {}", ELEVATED_OPEN_ARG, quote_argument(path));
+    if let Err(e) = launch_elevated(&arguments, false) {
+        log_warn(&format!("Could not open {} elevated: {}", path, e));
+    }
+}
+
+// Moves `paths` to the Recycle Bin from an elevated copy of this
+// executable. The helper is waited for on a worker thread, which then
+// tells `window` what went with WM_ELEVATED_RECYCLE_DONE.
+pub fn recycle_elevated(window: HWND, paths: Vec<String>) {
+    let number = RECYCLE_LIST_COUNT.fetch_add(1, Ordering::Relaxed);
+    let list = std::env::temp_dir().join(format!("EverythingLike-recycle-{}-{}.txt", std::process::id(), number));
+    if let Err(e) = std::fs::write(&list, paths.join("\n")) {
+        log_warn(&format!("Could not write {}: {}", list.display(), e));
+        return;
+    }
+    let arguments = format!("{} {}", ELEVATED_RECYCLE_ARG, quote_argument(&list.to_string_lossy()));
+    let process = match launch_elevated(&arguments, true) {
+        Ok(process) => process,
+        Err(e) => {
+            log_warn(&format!("Could not move the files elevated: {}", e));
+            let _ = std::fs::remove_file(&list);
+            return;
+        }
+    };
+
+    std::thread::spawn(move || {
+        match wait_for_exit(process) {
+            Ok(0) => log_info(&format!("Moved {} items to the Recycle Bin elevated", paths.len())),
+            Ok(code) => log_warn(&format!("The elevated Recycle Bin move ended with {}", code)),
+            Err(e) => log_warn(&format!("Could not wait for the elevated Recycle Bin move: {}", e)),
+        }
+        let _ = std::fs::remove_file(&list);
+
+        let deleted: Vec<String> = paths.into_iter().filter(|path| !Path::new(path).exists()).collect();
+        let deleted_ptr = Box::into_raw(Box::new(deleted)) as usize;
+        unsafe {
+            if PostMessageW(window, WM_ELEVATED_RECYCLE_DONE, WPARAM(deleted_ptr), LPARAM(0)).is_err() {
+                // The window is gone, so nobody will free the list
+                drop(Box::from_raw(deleted_ptr as *mut Vec<String>));
+            }
+        }
+    });
+}
+
+// Starts this executable with `arguments` through the UAC prompt. With
+// `wait` the helper's process is returned to be waited on; the prompt
+// being declined is an error.
+fn launch_elevated(arguments: &str, wait: bool) -> Result<HANDLE, String> {
+    let current_exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let program_wide = to_wide(&current_exe.to_string_lossy());
+    let arguments_wide = to_wide(arguments);
+    let mut info = SHELLEXECUTEINFOW {
+        cbSize: std::mem::size_of::<SHELLEXECUTEINFOW>() as u32,
+        fMask: SEE_MASK_NOASYNC | if wait { SEE_MASK_NOCLOSEPROCESS } else { 0 },
+        lpVerb: w!("runas"),
+        lpFile: PCWSTR::from_raw(program_wide.as_ptr()),
+        lpParameters: PCWSTR::from_raw(arguments_wide.as_ptr()),
+        nShow: SW_HIDE.0,
+        ..Default::default()
+    };
+    unsafe {
+        ShellExecuteExW(&mut info).map_err(|e| e.to_string())?;
+    }
+    Ok(info.hProcess)
+}
+
+// Waits for a process from launch_elevated and closes it. An invalid
+// handle, when the shell gave none, counts as having succeeded.
+fn wait_for_exit(process: HANDLE) -> Result<u32, String> {
+    if process.is_invalid() {
+        return Ok(0);
+    }
+    unsafe {
+        WaitForSingleObject(process, INFINITE);
+        let mut code = 0;
+        let result = GetExitCodeProcess(process, &mut code);
+        let _ = CloseHandle(process);
+        result.map_err(|e| e.to_string())?;
+        Ok(code)
+    }
+}
+
+// `message` followed by the paths it is about
+pub fn show_error(window: HWND, message: &str, paths: &[String]) {
+    let message = format!("{}\n{}", message, paths.join("\n"));
+    unsafe {
+        MessageBoxW(window, PCWSTR::from_raw(to_wide(&message).as_ptr()), w!("Error"), MB_ICONERROR | MB_OK);
+    }
+}
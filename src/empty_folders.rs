@@ -0,0 +1,432 @@
+use rayon::prelude::*;
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{HBRUSH, HFONT, COLOR_BTNFACE},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Controls::*,
+            Input::KeyboardAndMouse::{EnableWindow, SetFocus},
+            Shell::ShellExecuteW,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::elevation::WM_ELEVATED_RECYCLE_DONE;
+use crate::everything_sdk::EverythingSDK;
+use crate::lang::{fill, get_strings};
+use crate::logger::{log_error, log_info, log_warn};
+use crate::recycle_bin::{move_to_recycle_bin, notify_recycled};
+use crate::shell_verb::scoped_search_query;
+use crate::thumbnail::to_wide;
+
+// Posted by the worker with the number of folders to check in WPARAM
+const WM_EMPTY_FOLDERS_CHECKING: u32 = WM_USER + 125;
+
+// Posted by the worker with a boxed Result<Vec<String>, String> in WPARAM
+const WM_EMPTY_FOLDERS_FOUND: u32 = WM_USER + 126;
+
+const EMPTY_FOLDERS_WINDOW_CLASS: &str = "EverythingLikeEmptyFolders";
+
+const EMPTY_FOLDERS_WINDOW_WIDTH: i32 = 760;
+const EMPTY_FOLDERS_WINDOW_HEIGHT: i32 = 560;
+const MARGIN: i32 = 10;
+const BUTTON_WIDTH: i32 = 80;
+const BUTTON_HEIGHT: i32 = 26;
+
+// Control IDs
+const ID_EMPTY_FOLDERS_ROOT: i32 = 9841;
+const ID_EMPTY_FOLDERS_BROWSE: i32 = 9842;
+const ID_EMPTY_FOLDERS_SCAN: i32 = 9843;
+const ID_EMPTY_FOLDERS_STATUS: i32 = 9844;
+const ID_EMPTY_FOLDERS_LIST: i32 = 9845;
+const ID_EMPTY_FOLDERS_SELECT_ALL: i32 = 9846;
+const ID_EMPTY_FOLDERS_DELETE: i32 = 9847;
+const ID_EMPTY_FOLDERS_CLOSE: i32 = 9848;
+
+struct EmptyFolders {
+    owner: HWND,
+    list: HWND,
+    folders: Vec<String>,
+}
+
+// The window's EmptyFolders, kept in GWLP_USERDATA
+unsafe fn empty_folders(window: HWND) -> Option<&'static mut EmptyFolders> {
+    let finder = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut EmptyFolders;
+    finder.as_mut()
+}
+
+pub fn register_empty_folders_window_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(empty_folders_window_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as isize),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeEmptyFolders"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// Tools → Find Empty Folders: asks Everything for the folders under `root`
+// and lists the ones without anything in them. An empty root means every
+// indexed drive.
+pub fn show_empty_folders(owner: HWND, font: HFONT, root: &str) {
+    unsafe {
+        let strings = get_strings();
+        let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+
+        let window = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            PCWSTR::from_raw(to_wide(EMPTY_FOLDERS_WINDOW_CLASS).as_ptr()),
+            PCWSTR::from_raw(to_wide(&strings.empty_folders_title).as_ptr()),
+            WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX | WS_VISIBLE,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            EMPTY_FOLDERS_WINDOW_WIDTH,
+            EMPTY_FOLDERS_WINDOW_HEIGHT,
+            owner,
+            None,
+            instance,
+            None,
+        );
+
+        if window.0 == 0 {
+            log_error("Failed to create empty folders window");
+            return;
+        }
+
+        let mut client_rect = RECT::default();
+        let _ = GetClientRect(window, &mut client_rect);
+        let width = client_rect.right - MARGIN * 2;
+        let label_width = 70;
+        let root_width = width - label_width - BUTTON_WIDTH * 2 - MARGIN * 2;
+        let status_y = MARGIN * 2 + BUTTON_HEIGHT;
+        let list_y = status_y + 20 + MARGIN;
+        let button_y = client_rect.bottom - MARGIN - BUTTON_HEIGHT;
+
+        let list = CreateWindowExW(
+            WS_EX_CLIENTEDGE,
+            WC_LISTVIEWW,
+            PCWSTR::null(),
+            WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(LVS_REPORT | LVS_SHOWSELALWAYS | LVS_NOCOLUMNHEADER),
+            MARGIN,
+            list_y,
+            width,
+            button_y - MARGIN - list_y,
+            window,
+            HMENU(ID_EMPTY_FOLDERS_LIST as isize),
+            instance,
+            None,
+        );
+        SendMessageW(list, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        SendMessageW(list, LVM_SETEXTENDEDLISTVIEWSTYLE, WPARAM(0), LPARAM(LVS_EX_FULLROWSELECT as isize));
+
+        let mut name_wide = to_wide(&strings.column_path);
+        let column = LVCOLUMNW {
+            mask: LVCF_TEXT | LVCF_WIDTH,
+            cx: width - 24,
+            pszText: PWSTR::from_raw(name_wide.as_mut_ptr()),
+            ..Default::default()
+        };
+        SendMessageW(list, LVM_INSERTCOLUMNW, WPARAM(0), LPARAM(&column as *const _ as isize));
+
+        let delete_width = 220;
+        let controls = [
+            (w!("STATIC"), strings.disk_usage_folder.clone(), WINDOW_STYLE(0), WINDOW_EX_STYLE::default(), -1, MARGIN, MARGIN + 4, label_width, 20),
+            (w!("EDIT"), root.to_string(), WS_TABSTOP | WINDOW_STYLE(ES_AUTOHSCROLL as u32), WS_EX_CLIENTEDGE, ID_EMPTY_FOLDERS_ROOT, MARGIN + label_width, MARGIN, root_width, BUTTON_HEIGHT),
+            (w!("BUTTON"), strings.disk_usage_browse.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), WINDOW_EX_STYLE::default(), ID_EMPTY_FOLDERS_BROWSE, MARGIN * 2 + label_width + root_width, MARGIN, BUTTON_WIDTH, BUTTON_HEIGHT),
+            (w!("BUTTON"), strings.disk_usage_scan.clone(), WS_TABSTOP | WINDOW_STYLE(BS_DEFPUSHBUTTON as u32), WINDOW_EX_STYLE::default(), ID_EMPTY_FOLDERS_SCAN, client_rect.right - MARGIN - BUTTON_WIDTH, MARGIN, BUTTON_WIDTH, BUTTON_HEIGHT),
+            (w!("STATIC"), String::new(), WINDOW_STYLE(0), WINDOW_EX_STYLE::default(), ID_EMPTY_FOLDERS_STATUS, MARGIN, status_y, width, 20),
+            (w!("BUTTON"), strings.empty_folders_select_all.clone(), WS_TABSTOP | WS_DISABLED | WINDOW_STYLE(BS_PUSHBUTTON as u32), WINDOW_EX_STYLE::default(), ID_EMPTY_FOLDERS_SELECT_ALL, MARGIN, button_y, 120, BUTTON_HEIGHT),
+            (w!("BUTTON"), strings.empty_folders_delete.clone(), WS_TABSTOP | WS_DISABLED | WINDOW_STYLE(BS_PUSHBUTTON as u32), WINDOW_EX_STYLE::default(), ID_EMPTY_FOLDERS_DELETE, MARGIN * 2 + 120, button_y, delete_width, BUTTON_HEIGHT),
+            (w!("BUTTON"), strings.stats_close.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), WINDOW_EX_STYLE::default(), ID_EMPTY_FOLDERS_CLOSE, client_rect.right - MARGIN - BUTTON_WIDTH, button_y, BUTTON_WIDTH, BUTTON_HEIGHT),
+        ];
+        for (class, text, style, ex_style, id, x, y, width, height) in controls {
+            let control = CreateWindowExW(
+                ex_style,
+                class,
+                PCWSTR::from_raw(to_wide(&text).as_ptr()),
+                WS_CHILD | WS_VISIBLE | style,
+                x, y, width, height,
+                window,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(control, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        }
+
+        let finder = Box::new(EmptyFolders { owner, list, folders: Vec::new() });
+        SetWindowLongPtrW(window, GWLP_USERDATA, Box::into_raw(finder) as isize);
+
+        start_scan(window);
+    }
+}
+
+unsafe fn set_status(window: HWND, text: &str) {
+    let _ = SetWindowTextW(GetDlgItem(window, ID_EMPTY_FOLDERS_STATUS), PCWSTR::from_raw(to_wide(text).as_ptr()));
+}
+
+unsafe fn root_text(window: HWND) -> String {
+    let edit = GetDlgItem(window, ID_EMPTY_FOLDERS_ROOT);
+    let mut buffer = vec![0u16; GetWindowTextLengthW(edit) as usize + 1];
+    let copied = GetWindowTextW(edit, &mut buffer);
+    String::from_utf16_lossy(&buffer[..copied as usize]).trim().to_string()
+}
+
+// Looks for empty folders under the root in the edit box on a worker thread
+unsafe fn start_scan(window: HWND) {
+    let root = root_text(window);
+    EnableWindow(GetDlgItem(window, ID_EMPTY_FOLDERS_SCAN), FALSE);
+    set_status(window, &get_strings().disk_usage_scanning);
+    log_info(&format!("Looking for empty folders under '{}'", root));
+
+    std::thread::spawn(move || {
+        let result = Box::new(find_empty_folders(&root, |count| {
+            let _ = PostMessageW(window, WM_EMPTY_FOLDERS_CHECKING, WPARAM(count), LPARAM(0));
+        }));
+        let result_ptr = Box::into_raw(result) as usize;
+        if PostMessageW(window, WM_EMPTY_FOLDERS_FOUND, WPARAM(result_ptr), LPARAM(0)).is_err() {
+            // The window is gone, so nobody will free the result
+            drop(Box::from_raw(result_ptr as *mut std::result::Result<Vec<String>, String>));
+        }
+    });
+}
+
+// Everything names the folders; whether one is empty is checked on disk,
+// in parallel, since the index doesn't keep child counts
+fn find_empty_folders<F: Fn(usize)>(root: &str, on_checking: F) -> std::result::Result<Vec<String>, String> {
+    let query = if root.is_empty() { "folder:".to_string() } else { format!("{}folder:", scoped_search_query(root)) };
+    let mut folders: Vec<String> = Vec::new();
+    {
+        // The SDK keeps one result list per process, so searches take turns
+        // with the main search thread
+        let _guard = crate::EVERYTHING_SDK_MUTEX.lock().unwrap();
+        EverythingSDK::new()
+            .and_then(|sdk| {
+                sdk.search_files_in_batches(&query, usize::MAX, |batch, _| {
+                    folders.extend(batch.iter().map(|folder| folder.path().to_string()));
+                    true
+                })
+            })
+            .map_err(|e| e.to_string())?;
+    }
+
+    on_checking(folders.len());
+    let mut empty: Vec<String> = folders
+        .into_par_iter()
+        .filter(|folder| match std::fs::read_dir(folder) {
+            Ok(mut entries) => entries.next().is_none(),
+            Err(e) => {
+                log_warn(&format!("Could not read {}: {}", folder, e));
+                false
+            }
+        })
+        .collect();
+    empty.sort_by_key(|folder| folder.to_lowercase());
+    Ok(empty)
+}
+
+unsafe fn fill_list(window: HWND) {
+    let Some(finder) = empty_folders(window) else {
+        return;
+    };
+    let strings = get_strings();
+
+    SendMessageW(finder.list, LVM_DELETEALLITEMS, WPARAM(0), LPARAM(0));
+    for (row, folder) in finder.folders.iter().enumerate() {
+        let mut folder_wide = to_wide(folder);
+        let item = LVITEMW {
+            mask: LVIF_TEXT,
+            iItem: row as i32,
+            pszText: PWSTR::from_raw(folder_wide.as_mut_ptr()),
+            ..Default::default()
+        };
+        SendMessageW(finder.list, LVM_INSERTITEMW, WPARAM(0), LPARAM(&item as *const _ as isize));
+    }
+
+    let found = !finder.folders.is_empty();
+    EnableWindow(GetDlgItem(window, ID_EMPTY_FOLDERS_SELECT_ALL), BOOL::from(found));
+    EnableWindow(GetDlgItem(window, ID_EMPTY_FOLDERS_DELETE), BOOL::from(found));
+    if found {
+        set_status(window, &fill(&strings.empty_folders_summary, &[("count", &finder.folders.len().to_string())]));
+    } else {
+        set_status(window, &strings.empty_folders_none);
+    }
+}
+
+unsafe fn selected_rows(list: HWND) -> Vec<usize> {
+    let mut rows = Vec::new();
+    let mut row = -1isize;
+    loop {
+        row = SendMessageW(list, LVM_GETNEXTITEM, WPARAM(row as usize), LPARAM(LVNI_SELECTED as isize)).0;
+        if row < 0 {
+            break;
+        }
+        rows.push(row as usize);
+    }
+    rows
+}
+
+unsafe fn select_all(list: HWND) {
+    let item = LVITEMW {
+        stateMask: LVIS_SELECTED,
+        state: LVIS_SELECTED,
+        ..Default::default()
+    };
+    // Row -1 applies the state to every row
+    SendMessageW(list, LVM_SETITEMSTATE, WPARAM(usize::MAX), LPARAM(&item as *const _ as isize));
+    let _ = SetFocus(list);
+}
+
+unsafe fn delete_selected(window: HWND) {
+    let Some(finder) = empty_folders(window) else {
+        return;
+    };
+    let strings = get_strings();
+    let paths: Vec<String> = selected_rows(finder.list)
+        .into_iter()
+        .filter_map(|row| finder.folders.get(row).cloned())
+        .collect();
+    if paths.is_empty() {
+        return;
+    }
+
+    let message = fill(&strings.empty_folders_confirm_delete, &[("count", &paths.len().to_string())]);
+    let answer = MessageBoxW(
+        window,
+        PCWSTR::from_raw(to_wide(&message).as_ptr()),
+        PCWSTR::from_raw(to_wide(&strings.confirm_title).as_ptr()),
+        MB_ICONQUESTION | MB_YESNO | MB_DEFBUTTON2,
+    );
+    if answer != IDYES {
+        return;
+    }
+
+    let deleted = move_to_recycle_bin(window, paths);
+    forget_deleted(window, deleted);
+}
+
+// Drops the folders in `deleted` from the list and tells the main window
+unsafe fn forget_deleted(window: HWND, deleted: Vec<String>) {
+    let Some(finder) = empty_folders(window) else {
+        return;
+    };
+    finder.folders.retain(|folder| !deleted.contains(folder));
+    let owner = finder.owner;
+    fill_list(window);
+    notify_recycled(owner, deleted);
+}
+
+unsafe fn open_folder(window: HWND, row: usize) {
+    let Some(folder) = empty_folders(window).and_then(|finder| finder.folders.get(row).cloned()) else {
+        return;
+    };
+    ShellExecuteW(
+        None,
+        w!("open"),
+        PCWSTR::from_raw(to_wide(&folder).as_ptr()),
+        None,
+        None,
+        SW_SHOWNORMAL,
+    );
+}
+
+extern "system" fn empty_folders_window_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_EMPTY_FOLDERS_CHECKING => {
+                set_status(window, &fill(&get_strings().empty_folders_checking, &[("count", &wparam.0.to_string())]));
+                LRESULT(0)
+            }
+            WM_EMPTY_FOLDERS_FOUND => {
+                let result = *Box::from_raw(wparam.0 as *mut std::result::Result<Vec<String>, String>);
+                EnableWindow(GetDlgItem(window, ID_EMPTY_FOLDERS_SCAN), TRUE);
+                match result {
+                    Ok(folders) => {
+                        log_info(&format!("Found {} empty folders", folders.len()));
+                        if let Some(finder) = empty_folders(window) {
+                            finder.folders = folders;
+                        }
+                        fill_list(window);
+                    }
+                    Err(e) => {
+                        log_warn(&format!("Empty folder search failed: {}", e));
+                        set_status(window, &fill(&get_strings().error_empty_folders, &[("error", &e)]));
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_ELEVATED_RECYCLE_DONE => {
+                let deleted = Box::from_raw(wparam.0 as *mut Vec<String>);
+                forget_deleted(window, *deleted);
+                LRESULT(0)
+            }
+            WM_NOTIFY => {
+                let header = &*(lparam.0 as *const NMHDR);
+                if header.idFrom == ID_EMPTY_FOLDERS_LIST as usize && header.code == NM_DBLCLK {
+                    let activate = &*(lparam.0 as *const NMITEMACTIVATE);
+                    if activate.iItem >= 0 {
+                        open_folder(window, activate.iItem as usize);
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                match (wparam.0 & 0xFFFF) as i32 {
+                    ID_EMPTY_FOLDERS_BROWSE => {
+                        if let Some(folder) = crate::show_folder_dialog(window, &get_strings().empty_folders_title) {
+                            let _ = SetWindowTextW(GetDlgItem(window, ID_EMPTY_FOLDERS_ROOT), PCWSTR::from_raw(to_wide(&folder).as_ptr()));
+                            start_scan(window);
+                        }
+                    }
+                    ID_EMPTY_FOLDERS_SCAN => start_scan(window),
+                    ID_EMPTY_FOLDERS_SELECT_ALL => {
+                        if let Some(finder) = empty_folders(window) {
+                            select_all(finder.list);
+                        }
+                    }
+                    ID_EMPTY_FOLDERS_DELETE => delete_selected(window),
+                    ID_EMPTY_FOLDERS_CLOSE => {
+                        let _ = DestroyWindow(window);
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(window);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                let finder = SetWindowLongPtrW(window, GWLP_USERDATA, 0) as *mut EmptyFolders;
+                if !finder.is_null() {
+                    drop(Box::from_raw(finder));
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
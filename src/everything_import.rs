@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+use crate::config::{Bookmark, SearchFilter, MAX_BOOKMARKS, MAX_SEARCH_FILTERS};
+use crate::csv::parse_csv;
+
+const BOOKMARKS_FILE: &str = "Bookmarks.csv";
+const FILTERS_FILE: &str = "Filters.csv";
+
+// Everything keeps its settings in %APPDATA%\Everything, or next to
+// Everything.exe when it is set up not to use the AppData folder
+fn default_locations(file_name: &str) -> Vec<PathBuf> {
+    let mut locations = Vec::new();
+    if let Some(appdata) = std::env::var_os("APPDATA") {
+        locations.push(PathBuf::from(appdata).join("Everything").join(file_name));
+    }
+    for program_files in ["ProgramFiles", "ProgramFiles(x86)"] {
+        if let Some(dir) = std::env::var_os(program_files) {
+            locations.push(PathBuf::from(dir).join("Everything").join(file_name));
+        }
+    }
+    locations
+}
+
+// The configured file, or the first of Everything's usual locations that exists
+fn find_file(configured: &str, file_name: &str) -> Result<PathBuf, String> {
+    if !configured.is_empty() {
+        return Ok(PathBuf::from(configured));
+    }
+    default_locations(file_name)
+        .into_iter()
+        .find(|path| path.exists())
+        .ok_or_else(|| format!("{} not found", file_name))
+}
+
+// Reads Everything's Bookmarks.csv. Bookmarks without a search, such as
+// bookmark folders, are skipped.
+pub fn import_bookmarks(configured_path: &str) -> Result<Vec<Bookmark>, String> {
+    let path = find_file(configured_path, BOOKMARKS_FILE)?;
+    let table = read_table(&path)?;
+    Ok(table
+        .rows
+        .iter()
+        .filter_map(|row| {
+            let name = table.field(row, "Name")?;
+            let search = table.field(row, "Search")?;
+            if name.is_empty() || search.is_empty() {
+                return None;
+            }
+            Some(Bookmark {
+                name: name.to_string(),
+                search: search.to_string(),
+                filter: table.field(row, "Filter").unwrap_or("").to_string(),
+            })
+        })
+        .take(MAX_BOOKMARKS)
+        .collect())
+}
+
+// Reads Everything's Filters.csv. The built-in "Everything" filter has an
+// empty search and is left out; the Filters menu has its own entry for that.
+pub fn import_filters(configured_path: &str) -> Result<Vec<SearchFilter>, String> {
+    let path = find_file(configured_path, FILTERS_FILE)?;
+    let table = read_table(&path)?;
+    Ok(table
+        .rows
+        .iter()
+        .filter_map(|row| {
+            let name = table.field(row, "Name")?;
+            let search = table.field(row, "Search")?;
+            if name.is_empty() || search.is_empty() {
+                return None;
+            }
+            Some(SearchFilter {
+                name: name.to_string(),
+                search: search.to_string(),
+            })
+        })
+        .take(MAX_SEARCH_FILTERS)
+        .collect())
+}
+
+// A CSV file whose first row names the columns
+struct Table {
+    columns: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    // Columns are looked up by name, since Everything versions differ in
+    // which columns they write
+    fn field<'a>(&self, row: &'a [String], column: &str) -> Option<&'a str> {
+        let index = self.columns.iter().position(|name| name.eq_ignore_ascii_case(column))?;
+        Some(row.get(index).map_or("", |value| value.trim()))
+    }
+}
+
+fn read_table(path: &PathBuf) -> Result<Table, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let text = String::from_utf8_lossy(&bytes);
+    let mut rows = parse_csv(text.trim_start_matches('\u{feff}'));
+    if rows.is_empty() {
+        return Err(format!("{}: empty file", path.display()));
+    }
+    let columns = rows.remove(0);
+    Ok(Table { columns, rows })
+}
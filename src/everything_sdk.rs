@@ -1,6 +1,26 @@
 use libloading::{Library, Symbol};
+use std::sync::{Arc, OnceLock};
 use windows::core::PCWSTR;
 use windows::Win32::Foundation::BOOL;
+use crate::config::SizeFormat;
+use crate::file_icons::{file_type_name, folder_type_name};
+use crate::lang::LanguageStrings;
+use crate::list_stats::format_size;
+
+// The first batch is small so the list fills in right away; later batches
+// are larger to keep the number of merges on the UI thread down
+pub const FIRST_BATCH_SIZE: usize = 500;
+pub const BATCH_SIZE: usize = 10_000;
+
+// Rows fetched at a time while browsing the whole index
+pub const BROWSE_PAGE_SIZE: usize = 1000;
+
+// Everything's sort orders; each descending order is the ascending one + 1
+pub const SORT_NAME_ASCENDING: u32 = 1;
+pub const SORT_PATH_ASCENDING: u32 = 3;
+pub const SORT_SIZE_ASCENDING: u32 = 5;
+pub const SORT_TYPE_NAME_ASCENDING: u32 = 9;
+pub const SORT_DATE_MODIFIED_ASCENDING: u32 = 13;
 
 // Everything SDK function signatures
 type EverythingSetSearchW = extern "system" fn(search: PCWSTR);
@@ -77,6 +97,55 @@ impl EverythingSDK {
         }
     }
     
+    // Version of the running Everything, e.g. "1.4.1.1026". None when it
+    // isn't running or the DLL is too old to ask.
+    pub fn everything_version(&self) -> Option<String> {
+        type EverythingGetVersionPart = extern "system" fn() -> u32;
+        
+        unsafe {
+            let mut parts = Vec::new();
+            for name in [
+                &b"Everything_GetMajorVersion"[..],
+                b"Everything_GetMinorVersion",
+                b"Everything_GetRevision",
+                b"Everything_GetBuildNumber",
+            ] {
+                let get_part: Symbol<EverythingGetVersionPart> = self._lib.get(name).ok()?;
+                parts.push(get_part());
+            }
+            
+            // Every part is 0 when Everything can't be reached
+            if parts.iter().all(|&part| part == 0) {
+                return None;
+            }
+            Some(parts.iter().map(|part| part.to_string()).collect::<Vec<_>>().join("."))
+        }
+    }
+    
+    // Asks Everything to rebuild its whole database
+    pub fn rebuild_database(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.send_command(b"Everything_RebuildDB")
+    }
+    
+    // Asks Everything to rescan the folders it indexes without NTFS journals
+    pub fn update_all_folder_indexes(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.send_command(b"Everything_UpdateAllFolderIndexes")
+    }
+    
+    // The IPC commands only fail when Everything isn't running
+    fn send_command(&self, name: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        type EverythingCommand = extern "system" fn() -> BOOL;
+        
+        unsafe {
+            let command: Symbol<EverythingCommand> = self._lib.get(name)?;
+            if command().as_bool() {
+                Ok(())
+            } else {
+                Err("Everything is not running".into())
+            }
+        }
+    }
+    
     pub fn get_result_full_path(&self, index: u32) -> Result<String, Box<dyn std::error::Error>> {
         const MAX_PATH_SIZE: u32 = 32768; // Large buffer for long paths
         let mut buffer: Vec<u16> = vec![0; MAX_PATH_SIZE as usize];
@@ -97,28 +166,182 @@ impl EverythingSDK {
         }
     }
     
-    pub fn search_files(&self, query: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        // Set the search query
+    // Runs the query sorted by size, largest first, and returns the paths
+    // with their sizes: at most `max_results` of them, or all with None.
+    // Everything is asked for the sizes, so no file is touched. The sort
+    // and request settings are reset afterwards for the next search.
+    pub fn search_by_size(&self, query: &str, max_results: Option<u32>) -> Result<Vec<(String, u64)>, Box<dyn std::error::Error>> {
+        type EverythingSetNumber = extern "system" fn(value: u32);
+        type EverythingGetResultSize = extern "system" fn(index: u32, size: *mut i64) -> BOOL;
+        type EverythingReset = extern "system" fn();
+        
+        const EVERYTHING_SORT_SIZE_DESCENDING: u32 = 6;
+        const EVERYTHING_REQUEST_FULL_PATH_AND_FILE_NAME: u32 = 0x0000_0004;
+        const EVERYTHING_REQUEST_SIZE: u32 = 0x0000_0010;
+        
+        unsafe {
+            // Missing from DLLs older than Everything 1.4
+            let set_sort: Symbol<EverythingSetNumber> = self._lib.get(b"Everything_SetSort")?;
+            let set_request_flags: Symbol<EverythingSetNumber> = self._lib.get(b"Everything_SetRequestFlags")?;
+            let set_max: Symbol<EverythingSetNumber> = self._lib.get(b"Everything_SetMax")?;
+            let get_result_size: Symbol<EverythingGetResultSize> = self._lib.get(b"Everything_GetResultSize")?;
+            let reset: Symbol<EverythingReset> = self._lib.get(b"Everything_Reset")?;
+            
+            self.set_search(query)?;
+            set_sort(EVERYTHING_SORT_SIZE_DESCENDING);
+            set_request_flags(EVERYTHING_REQUEST_FULL_PATH_AND_FILE_NAME | EVERYTHING_REQUEST_SIZE);
+            set_max(max_results.unwrap_or(u32::MAX));
+            
+            let result = if self.query(true)? {
+                let num_results = self.get_num_results();
+                let mut results = Vec::with_capacity(num_results as usize);
+                for i in 0..num_results {
+                    if let Ok(path) = self.get_result_full_path(i) {
+                        let mut size: i64 = 0;
+                        let known = get_result_size(i, &mut size).as_bool();
+                        results.push((path, if known { size.max(0) as u64 } else { 0 }));
+                    }
+                }
+                Ok(results)
+            } else {
+                Err("Query failed".into())
+            };
+            
+            reset();
+            result
+        }
+    }
+    
+    // Runs the query and hands the results to `on_batch` while they are
+    // read, with a flag that is set on the last batch. At most `max_results`
+    // results are read; reading stops early when `on_batch` returns false.
+    // An empty result still produces one (empty, last) batch.
+    pub fn search_files_in_batches<F>(&self, query: &str, max_results: usize, on_batch: F) -> Result<(), Box<dyn std::error::Error>>
+    where
+        F: FnMut(Vec<FileResult>, bool) -> bool,
+    {
+        self.search_files_in_batches_limited(query, |found| found.min(max_results), on_batch)
+    }
+    
+    // search_files_in_batches, except that `limit` is told how many results
+    // the query found before any are read and returns how many to read
+    pub fn search_files_in_batches_limited<L, F>(&self, query: &str, limit: L, mut on_batch: F) -> Result<(), Box<dyn std::error::Error>>
+    where
+        L: FnOnce(usize) -> usize,
+        F: FnMut(Vec<FileResult>, bool) -> bool,
+    {
+        type EverythingIsFolderResult = extern "system" fn(index: u32) -> BOOL;
+        
         self.set_search(query)?;
         
-        // Execute the search
         if !self.query(true)? {
             return Err("Query failed".into());
         }
         
-        // Get number of results
-        let num_results = self.get_num_results();
-        let mut results = Vec::new();
+        // Everything knows which results are folders, so no disk access is needed
+        let is_folder_result: Option<EverythingIsFolderResult> = unsafe {
+            self._lib.get::<EverythingIsFolderResult>(b"Everything_IsFolderResult").ok().map(|symbol| *symbol)
+        };
+        
+        let num_results = limit(self.get_num_results() as usize);
+        let mut batch = ResultBatch::default();
+        let mut batch_size = FIRST_BATCH_SIZE;
         
-        // Collect all results
         for i in 0..num_results {
-            match self.get_result_full_path(i) {
-                Ok(path) => results.push(path),
-                Err(_) => continue, // Skip failed entries
+            if let Ok(path) = self.get_result_full_path(i as u32) {
+                batch.push(&path, is_folder_result.is_some_and(|is_folder| is_folder(i as u32).as_bool()));
+            }
+            
+            let last = i + 1 == num_results;
+            if batch.len() >= batch_size && !last {
+                if !on_batch(std::mem::take(&mut batch).finish(), false) {
+                    return Ok(());
+                }
+                batch_size = BATCH_SIZE;
             }
         }
         
-        Ok(results)
+        on_batch(batch.finish(), true);
+        Ok(())
+    }
+    
+    // Reads `count` results of the query from `offset` on, in Everything's
+    // `sort` order, along with the number of results in all. Everything
+    // reports the sizes and dates, so the rows need no metadata loading.
+    pub fn search_page(&self, query: &str, sort: u32, offset: usize, count: usize) -> Result<(usize, Vec<FileResult>), Box<dyn std::error::Error>> {
+        type EverythingSetNumber = extern "system" fn(value: u32);
+        type EverythingGetNumber = extern "system" fn() -> u32;
+        type EverythingIsFolderResult = extern "system" fn(index: u32) -> BOOL;
+        type EverythingGetResultSize = extern "system" fn(index: u32, size: *mut i64) -> BOOL;
+        type EverythingGetResultDate = extern "system" fn(index: u32, date: *mut i64) -> BOOL;
+        type EverythingReset = extern "system" fn();
+        
+        const EVERYTHING_REQUEST_FULL_PATH_AND_FILE_NAME: u32 = 0x0000_0004;
+        const EVERYTHING_REQUEST_SIZE: u32 = 0x0000_0010;
+        const EVERYTHING_REQUEST_DATE_MODIFIED: u32 = 0x0000_0040;
+        // FILETIMEs count 100 ns intervals from 1601
+        const FILETIME_UNIX_EPOCH: i64 = 116_444_736_000_000_000;
+        
+        unsafe {
+            // Missing from DLLs older than Everything 1.4
+            let set_sort: Symbol<EverythingSetNumber> = self._lib.get(b"Everything_SetSort")?;
+            let set_request_flags: Symbol<EverythingSetNumber> = self._lib.get(b"Everything_SetRequestFlags")?;
+            let set_offset: Symbol<EverythingSetNumber> = self._lib.get(b"Everything_SetOffset")?;
+            let set_max: Symbol<EverythingSetNumber> = self._lib.get(b"Everything_SetMax")?;
+            let get_total_results: Symbol<EverythingGetNumber> = self._lib.get(b"Everything_GetTotResults")?;
+            let is_folder_result: Symbol<EverythingIsFolderResult> = self._lib.get(b"Everything_IsFolderResult")?;
+            let get_result_size: Symbol<EverythingGetResultSize> = self._lib.get(b"Everything_GetResultSize")?;
+            let get_result_date: Symbol<EverythingGetResultDate> = self._lib.get(b"Everything_GetResultDateModified")?;
+            let reset: Symbol<EverythingReset> = self._lib.get(b"Everything_Reset")?;
+            
+            self.set_search(query)?;
+            set_sort(sort);
+            set_request_flags(EVERYTHING_REQUEST_FULL_PATH_AND_FILE_NAME | EVERYTHING_REQUEST_SIZE | EVERYTHING_REQUEST_DATE_MODIFIED);
+            set_offset(offset as u32);
+            set_max(count as u32);
+            
+            let result = if self.query(true)? {
+                let num_results = self.get_num_results();
+                let mut batch = ResultBatch::default();
+                // Size and date of each row, None for rows whose path couldn't be read
+                let mut rows = Vec::with_capacity(num_results as usize);
+                for i in 0..num_results {
+                    let Ok(path) = self.get_result_full_path(i) else {
+                        rows.push(None);
+                        continue;
+                    };
+                    batch.push(&path, is_folder_result(i).as_bool());
+                    let mut size: i64 = 0;
+                    let mut date: i64 = 0;
+                    let size = if get_result_size(i, &mut size).as_bool() { size.max(0) as u64 } else { 0 };
+                    let modified_time = if get_result_date(i, &mut date).as_bool() && date > FILETIME_UNIX_EPOCH {
+                        std::time::UNIX_EPOCH + std::time::Duration::from_nanos((date - FILETIME_UNIX_EPOCH) as u64 * 100)
+                    } else {
+                        std::time::UNIX_EPOCH
+                    };
+                    rows.push(Some((size, modified_time)));
+                }
+                
+                let mut paths = batch.finish().into_iter();
+                let results = rows
+                    .into_iter()
+                    .map(|row| match row.and_then(|metadata| Some((paths.next()?, metadata))) {
+                        Some((mut result, (size, modified_time))) => {
+                            // Folders still get their entries counted by the loader
+                            result.set_metadata(if result.is_folder { 0 } else { size }, modified_time);
+                            result
+                        }
+                        None => FileResult::placeholder(),
+                    })
+                    .collect();
+                Ok((get_total_results() as usize, results))
+            } else {
+                Err("Query failed".into())
+            };
+            
+            reset();
+            result
+        }
     }
 }
 
@@ -130,72 +353,235 @@ impl Drop for EverythingSDK {
     }
 }
 
-#[derive(Debug, Clone)]
+// Collects the paths of a set of results into one buffer that the results
+// made from it share, so reading a batch costs one allocation instead of
+// one per result
+#[derive(Default)]
+pub struct ResultBatch {
+    paths: String,
+    // End of each path in `paths` and whether it is a folder
+    entries: Vec<(usize, bool)>,
+}
+
+impl ResultBatch {
+    // `is_folder` is what Everything reports; a trailing separator marks a
+    // folder too
+    pub fn push(&mut self, path: &str, is_folder: bool) {
+        let (path, trailing_separator) = trim_folder_path(path);
+        self.paths.push_str(path);
+        self.entries.push((self.paths.len(), is_folder || trailing_separator));
+    }
+    
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    
+    pub fn finish(self) -> Vec<FileResult> {
+        let paths: Arc<str> = Arc::from(self.paths);
+        let mut start = 0;
+        self.entries
+            .into_iter()
+            .map(|(end, is_folder)| {
+                let result = FileResult::in_buffer(paths.clone(), start, end, is_folder);
+                start = end;
+                result
+            })
+            .collect()
+    }
+}
+
+// A trailing separator marks a folder, as in pasted or saved lists. It is
+// dropped so the path matches the one Everything returns; a drive root
+// keeps it.
+fn trim_folder_path(path: &str) -> (&str, bool) {
+    let trimmed = path.trim_end_matches(['\\', '/']);
+    let is_folder = trimmed.len() < path.len();
+    let path = if is_folder && !trimmed.is_empty() && !trimmed.ends_with(':') { trimmed } else { path };
+    (path, is_folder)
+}
+
+// One search result. Only the full path is stored, as a range of a buffer
+// shared with the rest of its batch; the name and extension are ranges
+// into it and the type is derived when needed, so large result sets stay
+// small.
+#[derive(Clone)]
 pub struct FileResult {
-    pub name: String,
-    pub path: String,
+    paths: Arc<str>,
+    path_start: u32,
+    path_end: u32,
+    name_start: u32,
+    name_end: u32,
+    extension_start: u32,
     pub size: u64,
     pub modified_time: std::time::SystemTime,
-    pub file_type: String,
-    pub extension: String,
+    pub missing: bool,  // Set by list validation when the file no longer exists
+    pub metadata_loaded: bool,  // Size and modified time have been read from disk
+    pub is_folder: bool,
+    pub item_count: Option<u32>,  // Entries in a folder, once counted; shown instead of its size
+}
+
+impl std::fmt::Debug for FileResult {
+    // Only this result's part of the shared buffer
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter
+            .debug_struct("FileResult")
+            .field("path", &self.path())
+            .field("size", &self.size)
+            .field("modified_time", &self.modified_time)
+            .field("missing", &self.missing)
+            .field("metadata_loaded", &self.metadata_loaded)
+            .field("is_folder", &self.is_folder)
+            .field("item_count", &self.item_count)
+            .finish()
+    }
 }
 
 impl FileResult {
+    // A result with a buffer of its own. Sets of results are read through
+    // ResultBatch instead.
     pub fn from_path(path: &str) -> Self {
-        let name = std::path::Path::new(path)
+        let (path, is_folder) = trim_folder_path(path);
+        Self::in_buffer(Arc::from(path), 0, path.len(), is_folder)
+    }
+    
+    // Sets of paths, e.g. from a list file, sharing one buffer
+    pub fn from_paths<'a>(paths: impl IntoIterator<Item = &'a str>) -> Vec<Self> {
+        let mut batch = ResultBatch::default();
+        for path in paths {
+            batch.push(path, false);
+        }
+        batch.finish()
+    }
+    
+    // The result for paths[start..end]
+    fn in_buffer(paths: Arc<str>, start: usize, end: usize, is_folder: bool) -> Self {
+        let path = &paths[start..end];
+        
+        // The last component, as Path::file_name finds it (trailing
+        // separators and "." are skipped)
+        let (name_start, name_end) = std::path::Path::new(path)
             .file_name()
             .and_then(|n| n.to_str())
-            .unwrap_or("Unknown")
-            .to_string();
-            
-        let extension = std::path::Path::new(path)
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("")
-            .to_string();
-            
-        let file_type = if extension.is_empty() {
-            "File".to_string()
-        } else {
-            format!("{} File", extension.to_uppercase())
-        };
-
+            .and_then(|name| path.rfind(name).map(|start| (start, start + name.len())))
+            .unwrap_or((path.len(), path.len()));
+        
+        // A leading dot starts a hidden name, not an extension. Folders
+        // have no extension.
+        let extension_start = path[name_start..name_end]
+            .rfind('.')
+            .filter(|&dot| dot > 0 && !is_folder)
+            .map_or(name_end, |dot| name_start + dot + 1);
+        
         Self {
-            name,
-            path: path.to_string(),
+            path_start: start as u32,
+            path_end: end as u32,
+            name_start: (start + name_start) as u32,
+            name_end: (start + name_end) as u32,
+            extension_start: (start + extension_start) as u32,
+            paths,
             size: 0,  // Lazy load when needed
             modified_time: std::time::UNIX_EPOCH,  // Lazy load when needed
-            file_type,
-            extension,
+            missing: false,
+            metadata_loaded: false,
+            is_folder,
+            item_count: None,
+        }
+    }
+    
+    // Stands in for a row of a page that hasn't been read yet. Pages of a
+    // large index start out as placeholders, so they share one empty buffer.
+    pub fn placeholder() -> Self {
+        static EMPTY: OnceLock<Arc<str>> = OnceLock::new();
+        let mut result = Self::in_buffer(EMPTY.get_or_init(|| Arc::from("")).clone(), 0, 0, false);
+        result.metadata_loaded = true;
+        result
+    }
+    
+    pub fn is_placeholder(&self) -> bool {
+        self.path_start == self.path_end
+    }
+    
+    fn mark_folder(&mut self) {
+        self.is_folder = true;
+        self.extension_start = self.name_end;
+    }
+    
+    pub fn path(&self) -> &str {
+        &self.paths[self.path_start as usize..self.path_end as usize]
+    }
+    
+    pub fn name(&self) -> &str {
+        if self.name_start == self.name_end {
+            return "Unknown";
+        }
+        &self.paths[self.name_start as usize..self.name_end as usize]
+    }
+    
+    pub fn extension(&self) -> &str {
+        &self.paths[self.extension_start as usize..self.name_end as usize]
+    }
+    
+    // The registered type name, as in Explorer; "PNG File" style names
+    // stand in for extensions without one
+    pub fn file_type(&self) -> String {
+        if self.is_folder {
+            return folder_type_name().unwrap_or_else(|| "File folder".to_string());
+        }
+        let extension = self.extension();
+        if let Some(name) = file_type_name(extension) {
+            return name;
+        }
+        if extension.is_empty() {
+            "File".to_string()
+        } else {
+            format!("{} File", extension.to_uppercase())
         }
     }
     
     pub fn load_metadata(&mut self) {
-        if self.size == 0 && self.modified_time == std::time::UNIX_EPOCH {
-            if let Ok(metadata) = std::fs::metadata(&self.path) {
+        if !self.metadata_loaded {
+            if let Ok(metadata) = std::fs::metadata(self.path()) {
                 self.size = metadata.len();
                 self.modified_time = metadata.modified().unwrap_or(std::time::UNIX_EPOCH);
+                if metadata.is_dir() {
+                    self.set_folder_items(count_folder_items(self.path()));
+                }
             }
+            self.metadata_loaded = true;
         }
     }
     
-    pub fn format_size(&self) -> String {
+    // Marks the result as a folder holding `count` entries
+    pub fn set_folder_items(&mut self, count: u32) {
+        self.mark_folder();
+        self.size = 0;
+        self.item_count = Some(count);
+    }
+    
+    // Whether the metadata loader still has something to read for this row
+    pub fn needs_metadata(&self) -> bool {
+        !self.metadata_loaded || (self.is_folder && self.item_count.is_none())
+    }
+    
+    // Stores metadata read elsewhere (the background metadata loader)
+    pub fn set_metadata(&mut self, size: u64, modified_time: std::time::SystemTime) {
+        self.size = size;
+        self.modified_time = modified_time;
+        self.metadata_loaded = true;
+    }
+    
+    pub fn format_size(&self, format: SizeFormat, strings: &LanguageStrings) -> String {
+        if self.is_folder {
+            return self.item_count.map_or(String::new(), |count| strings.folder_items.format(count as u64));
+        }
         if self.size == 0 {
             return String::new();
         }
         
-        if self.size > 1024 * 1024 * 1024 {
-            format!("{:.1} GB", self.size as f64 / (1024.0 * 1024.0 * 1024.0))
-        } else if self.size > 1024 * 1024 {
-            format!("{:.1} MB", self.size as f64 / (1024.0 * 1024.0))
-        } else if self.size > 1024 {
-            format!("{:.1} KB", self.size as f64 / 1024.0)
-        } else {
-            format!("{} bytes", self.size)
-        }
+        format_size(self.size, format, strings)
     }
     
-    pub fn format_modified_time(&self) -> String {
+    pub fn format_modified_time(&self, strings: &LanguageStrings) -> String {
         if self.modified_time == std::time::UNIX_EPOCH {
             return String::new();
         }
@@ -211,17 +597,16 @@ impl FileResult {
                 let diff_secs = now.saturating_sub(secs);
                 let diff_days = diff_secs / (24 * 3600);
                 
-                // Use a simple fallback if we can't get language strings
                 if diff_days == 0 {
-                    "Today".to_string()
+                    strings.time_today.clone()
                 } else if diff_days == 1 {
-                    "Yesterday".to_string()
+                    strings.time_yesterday.clone()
                 } else if diff_days < 7 {
-                    format!("{} days ago", diff_days)
+                    strings.time_days_ago.format(diff_days)
                 } else if diff_days < 30 {
-                    format!("{} weeks ago", diff_days / 7)
+                    strings.time_weeks_ago.format(diff_days / 7)
                 } else if diff_days < 365 {
-                    format!("{} months ago", diff_days / 30)
+                    strings.time_months_ago.format(diff_days / 30)
                 } else {
                     // For files older than a year, show actual date
                     let days_since_epoch = secs / (24 * 3600);
@@ -240,4 +625,9 @@ impl FileResult {
             Err(_) => String::new(),
         }
     }
-} 
\ No newline at end of file
+} 
+
+// Files and folders directly inside `folder`; 0 when it can't be read
+pub fn count_folder_items(folder: &str) -> u32 {
+    std::fs::read_dir(folder).map_or(0, |entries| entries.count() as u32)
+}
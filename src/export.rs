@@ -0,0 +1,276 @@
+use crate::everything_sdk::FileResult;
+use crate::csv::csv_field;
+use crate::list_notes::ListNotes;
+use crate::logger::log_info;
+use crate::playlist::format_m3u;
+use serde::Serialize;
+use std::fs;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Text,
+    Csv,
+    Json,
+    Xml,
+    Html,
+    M3u,
+    Efu,
+}
+
+impl ExportFormat {
+    // Order matches the filter list shown in the export dialog
+    pub const ALL: [ExportFormat; 7] = [
+        ExportFormat::Text,
+        ExportFormat::Csv,
+        ExportFormat::Json,
+        ExportFormat::Xml,
+        ExportFormat::Html,
+        ExportFormat::M3u,
+        ExportFormat::Efu,
+    ];
+
+    pub fn filter_name(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "Text Files (*.txt)",
+            ExportFormat::Csv => "CSV Files (*.csv)",
+            ExportFormat::Json => "JSON Files (*.json)",
+            ExportFormat::Xml => "XML Files (*.xml)",
+            ExportFormat::Html => "HTML Report (*.html)",
+            ExportFormat::M3u => "M3U Playlists (*.m3u8)",
+            ExportFormat::Efu => "Everything File Lists (*.efu)",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Xml => "xml",
+            ExportFormat::Html => "html",
+            ExportFormat::M3u => "m3u8",
+            ExportFormat::Efu => "efu",
+        }
+    }
+
+    // Save dialog filter indices are 1-based
+    pub fn from_filter_index(index: u32) -> Option<Self> {
+        Self::ALL.get((index as usize).checked_sub(1)?).copied()
+    }
+
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        match extension.as_str() {
+            "txt" => Some(ExportFormat::Text),
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            "xml" => Some(ExportFormat::Xml),
+            "htm" | "html" => Some(ExportFormat::Html),
+            "m3u" | "m3u8" => Some(ExportFormat::M3u),
+            "efu" => Some(ExportFormat::Efu),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ExportEntry {
+    path: String,
+    name: String,
+    size: u64,
+    mtime: String,
+}
+
+fn to_entries(items: &[FileResult]) -> Vec<ExportEntry> {
+    items
+        .iter()
+        .map(|item| {
+            // Load metadata if not already loaded
+            let mut item_clone = item.clone();
+            if item_clone.size == 0 && item_clone.modified_time == std::time::UNIX_EPOCH {
+                item_clone.load_metadata();
+            }
+
+            ExportEntry {
+                path: item_clone.path().to_string(),
+                name: item_clone.name().to_string(),
+                size: item_clone.size,
+                mtime: format_mtime(item_clone.modified_time),
+            }
+        })
+        .collect()
+}
+
+// RFC 3339 timestamp in local time, empty when the time is unknown
+pub fn format_mtime(time: std::time::SystemTime) -> String {
+    if time == std::time::UNIX_EPOCH {
+        return String::new();
+    }
+
+    let datetime: chrono::DateTime<chrono::Local> = time.into();
+    datetime.to_rfc3339_opts(chrono::SecondsFormat::Secs, false)
+}
+
+pub fn export_results(items: &[FileResult], format: ExportFormat, file_path: &str, notes: Option<&ListNotes>) -> Result<(), Box<dyn std::error::Error>> {
+    log_info(&format!("Exporting {} items as {:?} to: {}", items.len(), format, file_path));
+
+    let content = match format {
+        ExportFormat::Text => export_text(items),
+        ExportFormat::Csv => export_csv(&to_entries(items)),
+        ExportFormat::Json => serde_json::to_string_pretty(&to_entries(items))?,
+        ExportFormat::Xml => export_xml(&to_entries(items)),
+        ExportFormat::Html => export_html(&to_entries(items)),
+        ExportFormat::M3u => format_m3u(items),
+        ExportFormat::Efu => export_efu(items, notes),
+    };
+
+    fs::write(file_path, content)?;
+    Ok(())
+}
+
+fn export_text(items: &[FileResult]) -> String {
+    // Simple text format - one path per line
+    let mut content = String::new();
+    for item in items {
+        content.push_str(item.path());
+        content.push('\n');
+    }
+    content
+}
+
+// 100 ns intervals between 1601-01-01, where FILETIME counts from, and 1970-01-01
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+
+// Everything's file list format, with dates as FILETIME values, so the
+// file opens in Everything as well as here. Notes on list entries go in a
+// Note column, which Everything ignores.
+fn export_efu(items: &[FileResult], notes: Option<&ListNotes>) -> String {
+    let mut content = String::from(if notes.is_some() { "Filename,Size,Date Modified,Note\n" } else { "Filename,Size,Date Modified\n" });
+    for item in items {
+        let mut item = item.clone();
+        if item.size == 0 && item.modified_time == std::time::UNIX_EPOCH {
+            item.load_metadata();
+        }
+        let modified = match item.modified_time.duration_since(std::time::UNIX_EPOCH) {
+            Ok(elapsed) if !elapsed.is_zero() => (elapsed.as_nanos() / 100) as u64 + FILETIME_UNIX_EPOCH,
+            _ => 0,
+        };
+        content.push_str(&format!("{},{},{}", csv_field(item.path()), item.size, modified));
+        if let Some(notes) = notes {
+            content.push(',');
+            content.push_str(&csv_field(notes.note_of(item.path()).unwrap_or_default()));
+        }
+        content.push('\n');
+    }
+    content
+}
+
+fn export_csv(entries: &[ExportEntry]) -> String {
+    let mut content = String::from("Path,Name,Size,Modified\n");
+    for entry in entries {
+        content.push_str(&format!(
+            "{},{},{},{}\n",
+            csv_field(&entry.path),
+            csv_field(&entry.name),
+            entry.size,
+            csv_field(&entry.mtime)
+        ));
+    }
+    content
+}
+
+fn escape_markup(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn export_xml(entries: &[ExportEntry]) -> String {
+    let mut content = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<files>\n");
+    for entry in entries {
+        content.push_str(&format!(
+            "  <file>\n    <path>{}</path>\n    <name>{}</name>\n    <size>{}</size>\n    <mtime>{}</mtime>\n  </file>\n",
+            escape_markup(&entry.path),
+            escape_markup(&entry.name),
+            entry.size,
+            escape_markup(&entry.mtime)
+        ));
+    }
+    content.push_str("</files>\n");
+    content
+}
+
+// Standalone report; clicking a column header sorts the table
+const HTML_HEADER: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>File Report</title>
+<style>
+body { font-family: "Segoe UI", sans-serif; font-size: 13px; margin: 16px; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border: 1px solid #d0d0d0; padding: 4px 8px; text-align: left; }
+th { background: #e0e0e0; cursor: pointer; user-select: none; }
+tr:nth-child(even) td { background: #f8f8f8; }
+td.size { text-align: right; }
+</style>
+</head>
+<body>
+"#;
+
+const HTML_FOOTER: &str = r#"<script>
+document.querySelectorAll("th").forEach(function (th, column) {
+  var ascending = true;
+  th.addEventListener("click", function () {
+    var body = th.closest("table").tBodies[0];
+    var rows = Array.from(body.rows);
+    var numeric = th.dataset.type === "number";
+    rows.sort(function (a, b) {
+      var x = a.cells[column].dataset.value || a.cells[column].textContent;
+      var y = b.cells[column].dataset.value || b.cells[column].textContent;
+      var result = numeric ? Number(x) - Number(y) : x.localeCompare(y);
+      return ascending ? result : -result;
+    });
+    ascending = !ascending;
+    rows.forEach(function (row) { body.appendChild(row); });
+  });
+});
+</script>
+</body>
+</html>
+"#;
+
+fn export_html(entries: &[ExportEntry]) -> String {
+    let mut content = String::from(HTML_HEADER);
+    content.push_str(&format!("<h1>File Report</h1>\n<p>{} files</p>\n", entries.len()));
+    content.push_str("<table>\n<thead><tr><th>Name</th><th data-type=\"number\">Size</th><th>Modified</th><th>Path</th></tr></thead>\n<tbody>\n");
+
+    for entry in entries {
+        content.push_str(&format!(
+            "<tr><td>{}</td><td class=\"size\" data-value=\"{}\">{}</td><td>{}</td><td>{}</td></tr>\n",
+            escape_markup(&entry.name),
+            entry.size,
+            entry.size,
+            escape_markup(&entry.mtime),
+            escape_markup(&entry.path)
+        ));
+    }
+
+    content.push_str("</tbody>\n</table>\n");
+    content.push_str(HTML_FOOTER);
+    content
+}
@@ -0,0 +1,147 @@
+use std::path::Path;
+use windows::{
+    core::{w, PCWSTR},
+    Win32::UI::{Shell::ShellExecuteW, WindowsAndMessaging::SW_SHOWNORMAL},
+};
+use crate::thumbnail::to_wide;
+
+// Replaced for each selected item, which gets a command of its own
+const PATH_PLACEHOLDER: &str = "%path%";
+const DIR_PLACEHOLDER: &str = "%dir%";
+const NAME_PLACEHOLDER: &str = "%name%";
+// Replaced with every selected path, quoted, so the tool runs once; for
+// tools that take several files, like a diff
+const PATHS_PLACEHOLDER: &str = "%paths%";
+
+// Whether `command` runs once on all the selected paths
+pub fn takes_all_paths(command: &str) -> bool {
+    command.contains(PATHS_PLACEHOLDER)
+}
+
+// The command lines to run for `command` on the selected `paths`
+pub fn expand_tool_command(command: &str, paths: &[&str]) -> Vec<String> {
+    if command.contains(PATHS_PLACEHOLDER) {
+        let quoted: Vec<String> = paths.iter().map(|path| format!("\"{}\"", path)).collect();
+        let first = paths.first().copied().unwrap_or("");
+        return vec![expand_for_path(&command.replace(PATHS_PLACEHOLDER, &quoted.join(" ")), first)];
+    }
+
+    paths.iter().map(|path| expand_for_path(command, path)).collect()
+}
+
+fn expand_for_path(command: &str, path: &str) -> String {
+    let path_ref = Path::new(path);
+    let dir = path_ref.parent().map(|dir| dir.to_string_lossy().to_string()).unwrap_or_default();
+    let name = path_ref.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    command
+        .replace(PATH_PLACEHOLDER, path)
+        .replace(DIR_PLACEHOLDER, &dir)
+        .replace(NAME_PLACEHOLDER, &name)
+}
+
+// Starts one expanded command line in `working_dir`. The program is looked
+// up the way the Run dialog does, so "code" or "notepad" are enough.
+pub fn run_tool_command(command_line: &str, working_dir: &str) -> Result<(), String> {
+    let (program, arguments) = split_program(command_line);
+    if program.is_empty() {
+        return Err("Empty command line".to_string());
+    }
+
+    let program_wide = to_wide(&program);
+    let arguments_wide = to_wide(arguments);
+    let dir_wide = to_wide(working_dir);
+    let result = unsafe {
+        ShellExecuteW(
+            None,
+            w!("open"),
+            PCWSTR::from_raw(program_wide.as_ptr()),
+            PCWSTR::from_raw(arguments_wide.as_ptr()),
+            if working_dir.is_empty() { PCWSTR::null() } else { PCWSTR::from_raw(dir_wide.as_ptr()) },
+            SW_SHOWNORMAL,
+        )
+    };
+
+    // Values up to 32 are errors
+    if result.0 <= 32 {
+        return Err(format!("{} (error {})", program, result.0));
+    }
+    Ok(())
+}
+
+// The first token of a command line, unquoted, and the rest as written
+fn split_program(command_line: &str) -> (String, &str) {
+    let command_line = command_line.trim_start();
+    if let Some(quoted) = command_line.strip_prefix('"') {
+        return match quoted.find('"') {
+            Some(end) => (quoted[..end].to_string(), quoted[end + 1..].trim_start()),
+            None => (quoted.to_string(), ""),
+        };
+    }
+    match command_line.split_once(char::is_whitespace) {
+        Some((program, arguments)) => (program.to_string(), arguments.trim_start()),
+        None => (command_line.to_string(), ""),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Terminal {
+    CommandPrompt,
+    PowerShell,
+    // Windows Terminal, started through wt.exe
+    Wt,
+    Wsl,
+}
+
+// Opens `terminal` in `folder`. Windows Terminal starts with `profile` and
+// WSL with `distro`; empty means the default.
+pub fn open_terminal(terminal: Terminal, folder: &str, profile: &str, distro: &str) -> Result<(), String> {
+    let command_line = match terminal {
+        Terminal::CommandPrompt => "cmd.exe".to_string(),
+        Terminal::PowerShell => "powershell.exe -NoLogo".to_string(),
+        Terminal::Wt => {
+            // A backslash before the closing quote would escape it
+            let folder = if folder.ends_with('\\') { format!("{}.", folder) } else { folder.to_string() };
+            let mut command_line = format!("wt.exe -d \"{}\"", folder);
+            if !profile.is_empty() {
+                command_line.push_str(&format!(" -p \"{}\"", profile));
+            }
+            command_line
+        }
+        Terminal::Wsl => {
+            let folder = to_wsl_path(folder).unwrap_or_else(|| "~".to_string());
+            let mut command_line = format!("wsl.exe --cd \"{}\"", folder);
+            if !distro.is_empty() {
+                command_line.push_str(&format!(" -d \"{}\"", distro));
+            }
+            command_line
+        }
+    };
+    run_tool_command(&command_line, folder)
+}
+
+// The path as WSL sees it: C:\foo\bar is /mnt/c/foo/bar, and files inside
+// a distribution (\\wsl$\Ubuntu\home or \\wsl.localhost\Ubuntu\home) keep
+// their Linux path. None for other network paths, which WSL can't reach.
+pub fn to_wsl_path(path: &str) -> Option<String> {
+    if let Some(share) = path.strip_prefix("\\\\") {
+        let (server, rest) = share.split_once('\\')?;
+        if !server.eq_ignore_ascii_case("wsl$") && !server.eq_ignore_ascii_case("wsl.localhost") {
+            return None;
+        }
+        let linux_path = rest.split_once('\\').map_or("", |(_, linux_path)| linux_path);
+        return Some(format!("/{}", linux_path.replace('\\', "/")));
+    }
+
+    let mut chars = path.chars();
+    let drive = chars.next().filter(|c| c.is_ascii_alphabetic())?;
+    if chars.next() != Some(':') {
+        return None;
+    }
+    let rest = chars.as_str().trim_start_matches('\\').trim_end_matches('\\');
+    let mut wsl_path = format!("/mnt/{}", drive.to_ascii_lowercase());
+    if !rest.is_empty() {
+        wsl_path.push('/');
+        wsl_path.push_str(&rest.replace('\\', "/"));
+    }
+    Some(wsl_path)
+}
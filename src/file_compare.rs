@@ -0,0 +1,48 @@
+use std::path::Path;
+use windows::Win32::{
+    Foundation::*,
+    UI::WindowsAndMessaging::{PostMessageW, WM_USER},
+};
+use crate::file_hash::sha256_file;
+use crate::logger::log_info;
+
+// Posted to the window with a boxed CompareResult in WPARAM when a
+// comparison is done
+pub const WM_COMPARE_DONE: u32 = WM_USER + 146;
+
+pub struct CompareResult {
+    pub first: String,
+    pub second: String,
+    // Whether the files are byte for byte the same, or why they couldn't
+    // be read
+    pub outcome: Result<bool, String>,
+}
+
+// Compares the two files on a background thread. Files of different sizes
+// differ without being read; others are hashed, which can take a while.
+pub fn start_compare(window: HWND, first: String, second: String) {
+    log_info(&format!("Comparing {} with {}", first, second));
+    std::thread::spawn(move || {
+        let outcome = files_identical(Path::new(&first), Path::new(&second));
+        let result = Box::into_raw(Box::new(CompareResult { first, second, outcome })) as usize;
+        unsafe {
+            if PostMessageW(window, WM_COMPARE_DONE, WPARAM(result), LPARAM(0)).is_err() {
+                // The window is gone, so nobody will free the result
+                drop(Box::from_raw(result as *mut CompareResult));
+            }
+        }
+    });
+}
+
+fn files_identical(first: &Path, second: &Path) -> Result<bool, String> {
+    let size_of = |path: &Path| std::fs::metadata(path).map(|metadata| metadata.len()).map_err(|e| read_error(path, e.to_string()));
+    if size_of(first)? != size_of(second)? {
+        return Ok(false);
+    }
+    let digest_of = |path: &Path| sha256_file(path).map_err(|e| read_error(path, e));
+    Ok(digest_of(first)? == digest_of(second)?)
+}
+
+fn read_error(path: &Path, error: String) -> String {
+    format!("{}: {}", path.display(), error)
+}
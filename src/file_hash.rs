@@ -0,0 +1,166 @@
+use std::io::Read;
+use std::path::Path;
+use windows::Win32::Security::Cryptography::*;
+
+const SHA256_SIZE: usize = 32;
+
+// The digests checksum files are written with
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HashAlgorithm {
+    Crc32,
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    // Digest length in bytes
+    pub fn size(self) -> usize {
+        match self {
+            HashAlgorithm::Crc32 => 4,
+            HashAlgorithm::Md5 => 16,
+            HashAlgorithm::Sha1 => 20,
+            HashAlgorithm::Sha256 => SHA256_SIZE,
+            HashAlgorithm::Sha512 => 64,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgorithm::Crc32 => "CRC32",
+            HashAlgorithm::Md5 => "MD5",
+            HashAlgorithm::Sha1 => "SHA1",
+            HashAlgorithm::Sha256 => "SHA256",
+            HashAlgorithm::Sha512 => "SHA512",
+        }
+    }
+}
+
+// A hash fed in pieces so large files never sit in memory. CRC-32 isn't
+// offered by CNG, so it is computed here.
+pub struct Hasher {
+    algorithm: HashAlgorithm,
+    hash: BCRYPT_HASH_HANDLE,
+    crc: u32,
+}
+
+impl Hasher {
+    pub fn new(algorithm: HashAlgorithm) -> Result<Self, String> {
+        let provider = match algorithm {
+            HashAlgorithm::Crc32 => {
+                return Ok(Self { algorithm, hash: BCRYPT_HASH_HANDLE::default(), crc: !0 });
+            }
+            HashAlgorithm::Md5 => BCRYPT_MD5_ALG_HANDLE,
+            HashAlgorithm::Sha1 => BCRYPT_SHA1_ALG_HANDLE,
+            HashAlgorithm::Sha256 => BCRYPT_SHA256_ALG_HANDLE,
+            HashAlgorithm::Sha512 => BCRYPT_SHA512_ALG_HANDLE,
+        };
+        let mut hash = BCRYPT_HASH_HANDLE::default();
+        unsafe {
+            BCryptCreateHash(provider, &mut hash, None, None, 0)
+                .ok()
+                .map_err(|e| format!("BCryptCreateHash failed: {}", e))?;
+        }
+        Ok(Self { algorithm, hash, crc: 0 })
+    }
+
+    pub fn update(&mut self, data: &[u8]) -> Result<(), String> {
+        if self.algorithm == HashAlgorithm::Crc32 {
+            self.crc = crc32_update(self.crc, data);
+            return Ok(());
+        }
+        unsafe {
+            BCryptHashData(self.hash, data, 0)
+                .ok()
+                .map_err(|e| format!("BCryptHashData failed: {}", e))
+        }
+    }
+
+    pub fn finish(self) -> Result<Vec<u8>, String> {
+        if self.algorithm == HashAlgorithm::Crc32 {
+            return Ok((!self.crc).to_be_bytes().to_vec());
+        }
+        let mut digest = vec![0u8; self.algorithm.size()];
+        unsafe {
+            BCryptFinishHash(self.hash, &mut digest, 0)
+                .ok()
+                .map_err(|e| format!("BCryptFinishHash failed: {}", e))?;
+        }
+        Ok(digest)
+    }
+}
+
+impl Drop for Hasher {
+    fn drop(&mut self) {
+        if !self.hash.is_invalid() {
+            unsafe {
+                let _ = BCryptDestroyHash(self.hash);
+            }
+        }
+    }
+}
+
+// Reflected CRC-32 (polynomial 0xEDB88320), as used by .sfv files
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    static TABLE: std::sync::OnceLock<[u32; 256]> = std::sync::OnceLock::new();
+    let table = TABLE.get_or_init(|| {
+        let mut table = [0u32; 256];
+        for (index, entry) in table.iter_mut().enumerate() {
+            let mut value = index as u32;
+            for _ in 0..8 {
+                value = if value & 1 != 0 { (value >> 1) ^ 0xEDB8_8320 } else { value >> 1 };
+            }
+            *entry = value;
+        }
+        table
+    });
+    for &byte in data {
+        crc = table[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    crc
+}
+
+// SHA-256 through CNG
+pub struct Sha256 {
+    hasher: Hasher,
+}
+
+impl Sha256 {
+    pub fn new() -> Result<Self, String> {
+        Ok(Self { hasher: Hasher::new(HashAlgorithm::Sha256)? })
+    }
+
+    pub fn update(&mut self, data: &[u8]) -> Result<(), String> {
+        self.hasher.update(data)
+    }
+
+    pub fn finish(self) -> Result<[u8; SHA256_SIZE], String> {
+        let digest = self.hasher.finish()?;
+        digest.try_into().map_err(|_| "Unexpected SHA-256 digest length".to_string())
+    }
+}
+
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm) -> Result<Vec<u8>, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut hasher = Hasher::new(algorithm)?;
+    let mut buffer = vec![0u8; 256 * 1024];
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| e.to_string())?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read])?;
+    }
+    hasher.finish()
+}
+
+pub fn sha256_file(path: &Path) -> Result<[u8; SHA256_SIZE], String> {
+    let digest = hash_file(path, HashAlgorithm::Sha256)?;
+    digest.try_into().map_err(|_| "Unexpected SHA-256 digest length".to_string())
+}
+
+// Lowercase hex, the way checksum files write digests
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
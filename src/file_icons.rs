@@ -1,8 +1,10 @@
 use windows::{
     core::*,
     Win32::{
+        Foundation::*,
         Storage::FileSystem::*,
         Graphics::Gdi::*,
+        System::Com::*,
         UI::{
             WindowsAndMessaging::*,
             Shell::*,
@@ -10,42 +12,210 @@ use windows::{
     },
 };
 use lru::LruCache;
+use std::collections::{HashMap, HashSet};
 use std::num::NonZeroUsize;
 use std::path::Path;
+use std::sync::{mpsc, Mutex};
+use crate::long_paths::shell_path;
+use crate::network_paths::throttled;
+use crate::perf::ICON_LOOKUPS;
+
+// Posted to the list view with a boxed cache key (String) in WPARAM when
+// an icon has been loaded in the background
+pub const WM_ICON_READY: u32 = WM_USER + 111;
 
 // Icon cache for file extensions
-static mut ICON_CACHE: Option<LruCache<String, HICON>> = None;
+static ICON_CACHE: Mutex<Option<LruCache<String, HICON>>> = Mutex::new(None);
+
+// Icon cache for files that carry their own icon, keyed by full path
+static PATH_ICON_CACHE: Mutex<Option<LruCache<String, HICON>>> = Mutex::new(None);
+
+// Executables, shortcuts, icon files and internet shortcuts each have
+// their own icon, so these are looked up per file instead of per extension
+const PER_FILE_ICON_EXTENSIONS: [&str; 4] = ["exe", "lnk", "ico", "url"];
+
+const EXTENSION_CACHE_SIZE: usize = 200;
+const PATH_CACHE_SIZE: usize = 500;
+
+struct IconRequest {
+    cache_key: String,
+    file_path: String,
+    small: bool,
+}
+
+// SHGetFileInfoW can block for seconds on network paths, so icons are
+// loaded on a worker thread and painting uses a generic icon meanwhile
+struct IconLoader {
+    sender: mpsc::Sender<IconRequest>,
+    // Cache keys queued but not loaded yet
+    pending: HashSet<String>,
+}
+
+static ICON_LOADER: Mutex<Option<IconLoader>> = Mutex::new(None);
+
+// Registered type names by lowercase extension; there are few enough
+// extensions to keep every one
+static TYPE_NAME_CACHE: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+
+// The generic icons are loaded once and kept for the lifetime of the app
+static DEFAULT_ICONS: Mutex<[Option<HICON>; 2]> = Mutex::new([None, None]);
+static FOLDER_ICONS: Mutex<[Option<HICON>; 2]> = Mutex::new([None, None]);
 
 // Initialize the icon cache
 pub fn init_icon_cache() {
-    unsafe {
-        ICON_CACHE = Some(LruCache::new(NonZeroUsize::new(200).unwrap()));
+    if let Ok(mut cache) = ICON_CACHE.lock() {
+        *cache = Some(LruCache::new(NonZeroUsize::new(EXTENSION_CACHE_SIZE).unwrap()));
+    }
+    if let Ok(mut cache) = PATH_ICON_CACHE.lock() {
+        *cache = Some(LruCache::new(NonZeroUsize::new(PATH_CACHE_SIZE).unwrap()));
     }
 }
 
-// Get file icon by file path
-pub fn get_file_icon(file_path: &str, small: bool) -> Option<HICON> {
-    unsafe {
-        // Get file extension for caching
+// Starts the worker that loads icons for `window`
+pub fn start_icon_loader(window: HWND) {
+    let (sender, receiver) = mpsc::channel::<IconRequest>();
+    let window_handle = window.0;
+    
+    std::thread::spawn(move || {
+        // Shell icon extraction needs COM on this thread
+        unsafe {
+            let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+        }
+        
+        while let Ok(request) = receiver.recv() {
+            let cache = if is_per_file_icon(&request.file_path) { &PATH_ICON_CACHE } else { &ICON_CACHE };
+            
+            // Queued twice if the first copy landed between the UI's cache
+            // check and its request
+            let cached = cache.lock().map_or(false, |cache| {
+                cache.as_ref().map_or(false, |cache| cache.contains(&request.cache_key))
+            });
+            // Files on an offline share keep the generic icon
+            let icon = if cached { None } else { throttled(&request.file_path, || load_file_icon(&request.file_path, request.small)).flatten() };
+            if let Some(icon) = icon {
+                cache_icon(cache, request.cache_key.clone(), icon);
+            }
+            
+            if let Ok(mut loader) = ICON_LOADER.lock() {
+                if let Some(loader) = loader.as_mut() {
+                    loader.pending.remove(&request.cache_key);
+                }
+            }
+            
+            // Files without an icon keep the generic one; nothing to repaint
+            if icon.is_some() {
+                let key_ptr = Box::into_raw(Box::new(request.cache_key)) as usize;
+                unsafe {
+                    if PostMessageW(HWND(window_handle), WM_ICON_READY, WPARAM(key_ptr), LPARAM(0)).is_err() {
+                        drop(Box::from_raw(key_ptr as *mut String));
+                    }
+                }
+            }
+        }
+        
+        unsafe {
+            CoUninitialize();
+        }
+    });
+    
+    if let Ok(mut loader) = ICON_LOADER.lock() {
+        *loader = Some(IconLoader {
+            sender,
+            pending: HashSet::new(),
+        });
+    }
+}
+
+fn is_per_file_icon(file_path: &str) -> bool {
+    let extension = Path::new(file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    PER_FILE_ICON_EXTENSIONS.contains(&extension.as_str())
+}
+
+// Files sharing a key share an icon: the extension for most files, the
+// full path for executables and shortcuts
+pub fn icon_cache_key(file_path: &str, small: bool) -> String {
+    let size = if small { "small" } else { "large" };
+    if is_per_file_icon(file_path) {
+        format!("{}_{}", file_path.to_lowercase(), size)
+    } else {
         let extension = Path::new(file_path)
             .extension()
             .and_then(|ext| ext.to_str())
             .unwrap_or("")
             .to_lowercase();
-        
-        // Create cache key based on extension and size
-        let cache_key = format!("{}_{}", extension, if small { "small" } else { "large" });
-        
-        // Check cache first
-        if let Some(ref mut cache) = ICON_CACHE {
-            if let Some(&cached_icon) = cache.get(&cache_key) {
-                return Some(cached_icon);
+        format!("{}_{}", extension, size)
+    }
+}
+
+// SHGetFileInfoW hands out a new icon each time, so whatever falls out of
+// a cache has to be destroyed
+fn cache_icon(cache: &Mutex<Option<LruCache<String, HICON>>>, key: String, icon: HICON) {
+    if let Ok(mut cache) = cache.lock() {
+        if let Some(cache) = cache.as_mut() {
+            if let Some((_, evicted)) = cache.push(key, icon) {
+                if evicted != icon {
+                    unsafe {
+                        let _ = DestroyIcon(evicted);
+                    }
+                }
             }
         }
-        
+    }
+}
+
+// Get file icon by file path. Returns the cached icon, or queues it on the
+// icon loader and returns None so the caller can draw a generic icon until
+// WM_ICON_READY arrives.
+pub fn get_file_icon(file_path: &str, small: bool) -> Option<HICON> {
+    let cache_key = icon_cache_key(file_path, small);
+    let cache = if is_per_file_icon(file_path) { &PATH_ICON_CACHE } else { &ICON_CACHE };
+    
+    // Check cache first
+    if let Ok(mut cache) = cache.lock() {
+        let cached = cache.as_mut().and_then(|cache| cache.get(&cache_key)).copied();
+        ICON_LOOKUPS.record(cached.is_some());
+        if cached.is_some() {
+            return cached;
+        }
+    }
+    
+    if let Ok(mut loader) = ICON_LOADER.lock() {
+        if let Some(loader) = loader.as_mut() {
+            if loader.pending.insert(cache_key.clone()) {
+                let _ = loader.sender.send(IconRequest {
+                    cache_key,
+                    file_path: file_path.to_string(),
+                    small,
+                });
+            }
+            return None;
+        }
+    }
+    
+    // No loader running; load it here
+    let icon = load_file_icon(file_path, small)?;
+    cache_icon(cache, cache_key, icon);
+    Some(icon)
+}
+
+// Icons requested from the loader thread that haven't arrived yet
+pub fn pending_icon_count() -> usize {
+    ICON_LOADER
+        .lock()
+        .ok()
+        .and_then(|loader| loader.as_ref().map(|loader| loader.pending.len()))
+        .unwrap_or(0)
+}
+
+fn load_file_icon(file_path: &str, small: bool) -> Option<HICON> {
+    unsafe {
         // Get icon using SHGetFileInfoW
         let mut file_info = SHFILEINFOW::default();
-        let file_path_wide: Vec<u16> = file_path.encode_utf16().chain(std::iter::once(0)).collect();
+        let file_path_wide: Vec<u16> = shell_path(file_path).encode_utf16().chain(std::iter::once(0)).collect();
         
         let flags = SHGFI_ICON | if small { SHGFI_SMALLICON } else { SHGFI_LARGEICON };
         
@@ -58,14 +228,53 @@ pub fn get_file_icon(file_path: &str, small: bool) -> Option<HICON> {
         );
         
         if result != 0 && !file_info.hIcon.is_invalid() {
-            let icon = file_info.hIcon;
-            
-            // Cache the icon
-            if let Some(ref mut cache) = ICON_CACHE {
-                cache.put(cache_key, icon);
-            }
-            
-            Some(icon)
+            Some(file_info.hIcon)
+        } else {
+            None
+        }
+    }
+}
+
+// The type name Explorer shows for files with `extension`, such as
+// "Microsoft Word Document". Only the registry is read, never the file.
+pub fn file_type_name(extension: &str) -> Option<String> {
+    let key = extension.to_lowercase();
+    if let Ok(cache) = TYPE_NAME_CACHE.lock() {
+        if let Some(name) = cache.as_ref().and_then(|cache| cache.get(&key)) {
+            return Some(name.clone()).filter(|name| !name.is_empty());
+        }
+    }
+    
+    let name = load_type_name(&format!("file.{}", key), FILE_ATTRIBUTE_NORMAL);
+    if let Ok(mut cache) = TYPE_NAME_CACHE.lock() {
+        // Unregistered extensions are remembered too, as an empty name
+        cache.get_or_insert_with(HashMap::new).insert(key, name.clone().unwrap_or_default());
+    }
+    name
+}
+
+// "File folder" in the UI language
+pub fn folder_type_name() -> Option<String> {
+    static FOLDER_TYPE_NAME: std::sync::OnceLock<Option<String>> = std::sync::OnceLock::new();
+    FOLDER_TYPE_NAME.get_or_init(|| load_type_name("folder", FILE_ATTRIBUTE_DIRECTORY)).clone()
+}
+
+fn load_type_name(file_name: &str, attributes: FILE_FLAGS_AND_ATTRIBUTES) -> Option<String> {
+    unsafe {
+        let mut file_info = SHFILEINFOW::default();
+        let file_name: Vec<u16> = file_name.encode_utf16().chain(std::iter::once(0)).collect();
+        
+        let result = SHGetFileInfoW(
+            PCWSTR::from_raw(file_name.as_ptr()),
+            attributes,
+            Some(&mut file_info),
+            std::mem::size_of::<SHFILEINFOW>() as u32,
+            SHGFI_TYPENAME | SHGFI_USEFILEATTRIBUTES,
+        );
+        
+        let length = file_info.szTypeName.iter().position(|&c| c == 0).unwrap_or(file_info.szTypeName.len());
+        if result != 0 && length > 0 {
+            Some(String::from_utf16_lossy(&file_info.szTypeName[..length]))
         } else {
             None
         }
@@ -74,6 +283,21 @@ pub fn get_file_icon(file_path: &str, small: bool) -> Option<HICON> {
 
 // Get default file icon for unknown types
 pub fn get_default_file_icon(small: bool) -> Option<HICON> {
+    let slot = if small { 0 } else { 1 };
+    if let Ok(icons) = DEFAULT_ICONS.lock() {
+        if let Some(icon) = icons[slot] {
+            return Some(icon);
+        }
+    }
+    
+    let icon = load_default_file_icon(small)?;
+    if let Ok(mut icons) = DEFAULT_ICONS.lock() {
+        icons[slot] = Some(icon);
+    }
+    Some(icon)
+}
+
+fn load_default_file_icon(small: bool) -> Option<HICON> {
     unsafe {
         let mut file_info = SHFILEINFOW::default();
         let flags = SHGFI_ICON | SHGFI_USEFILEATTRIBUTES | if small { SHGFI_SMALLICON } else { SHGFI_LARGEICON };
@@ -94,8 +318,23 @@ pub fn get_default_file_icon(small: bool) -> Option<HICON> {
     }
 }
 
-// Get folder icon
+// The folder icon, loaded once like the generic file icons
 pub fn get_folder_icon(small: bool) -> Option<HICON> {
+    let slot = if small { 0 } else { 1 };
+    if let Ok(icons) = FOLDER_ICONS.lock() {
+        if let Some(icon) = icons[slot] {
+            return Some(icon);
+        }
+    }
+    
+    let icon = load_folder_icon(small)?;
+    if let Ok(mut icons) = FOLDER_ICONS.lock() {
+        icons[slot] = Some(icon);
+    }
+    Some(icon)
+}
+
+fn load_folder_icon(small: bool) -> Option<HICON> {
     unsafe {
         let mut file_info = SHFILEINFOW::default();
         let flags = SHGFI_ICON | SHGFI_USEFILEATTRIBUTES | if small { SHGFI_SMALLICON } else { SHGFI_LARGEICON };
@@ -125,11 +364,16 @@ pub fn draw_icon(hdc: HDC, icon: HICON, x: i32, y: i32, size: i32) {
 
 // Cleanup icon cache
 pub fn cleanup_icon_cache() {
-    unsafe {
-        if let Some(ref mut cache) = ICON_CACHE {
-            // Icons are system resources, Windows manages them
-            // We don't need to explicitly destroy them
-            cache.clear();
+    for cache in [&ICON_CACHE, &PATH_ICON_CACHE] {
+        if let Ok(mut cache) = cache.lock() {
+            if let Some(cache) = cache.as_mut() {
+                for (_, icon) in cache.iter() {
+                    unsafe {
+                        let _ = DestroyIcon(*icon);
+                    }
+                }
+                cache.clear();
+            }
         }
     }
 } 
\ No newline at end of file
@@ -0,0 +1,96 @@
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::*,
+        Storage::FileSystem::*,
+    },
+};
+use crate::batch_loader::BatchLoader;
+use crate::long_paths::extended_path;
+use crate::network_paths::throttled;
+use crate::thumbnail::to_wide;
+
+// Results are posted in small batches so the first rows fill in quickly
+const RESULT_BATCH_SIZE: usize = 32;
+
+// NTFS details of one file that Everything doesn't index
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkInfo {
+    // Names the file has on its volume; more than 1 for hard-linked files
+    pub hard_links: u32,
+    // Named streams besides the file's contents
+    pub alternate_streams: u32,
+}
+
+// Details of one row. None when the file couldn't be opened.
+pub struct LinkInfoEntry {
+    pub path: String,
+    pub info: Option<LinkInfo>,
+}
+
+// Reads hard-link counts and streams for the rows on screen on a worker
+// thread and posts `message` with a boxed Vec<LinkInfoEntry> in WPARAM for
+// each batch
+pub type LinkInfoLoader = BatchLoader<String, LinkInfoEntry>;
+
+impl LinkInfoLoader {
+    pub fn start(window: HWND, message: u32) -> Self {
+        BatchLoader::spawn(window, message, RESULT_BATCH_SIZE, |path: &String| LinkInfoEntry {
+            path: path.clone(),
+            info: throttled(path, || read_link_info(path)).flatten(),
+        })
+    }
+}
+
+fn read_link_info(path: &str) -> Option<LinkInfo> {
+    let path_wide = to_wide(&extended_path(path));
+    unsafe {
+        // Only the attributes are read, so files locked by other programs work too
+        let file = CreateFileW(
+            PCWSTR::from_raw(path_wide.as_ptr()),
+            FILE_READ_ATTRIBUTES.0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            None,
+            OPEN_EXISTING,
+            FILE_FLAG_BACKUP_SEMANTICS,
+            HANDLE(0),
+        )
+        .ok()?;
+        let mut information = BY_HANDLE_FILE_INFORMATION::default();
+        let read = GetFileInformationByHandle(file, &mut information);
+        let _ = CloseHandle(file);
+        read.ok()?;
+
+        Some(LinkInfo {
+            hard_links: information.nNumberOfLinks,
+            alternate_streams: count_alternate_streams(&path_wide),
+        })
+    }
+}
+
+// Streams other than the unnamed one holding the contents. Volumes
+// without streams (FAT, most network shares) report none.
+unsafe fn count_alternate_streams(path_wide: &[u16]) -> u32 {
+    let mut data = WIN32_FIND_STREAM_DATA::default();
+    let Ok(find) = FindFirstStreamW(
+        PCWSTR::from_raw(path_wide.as_ptr()),
+        FindStreamInfoStandard,
+        &mut data as *mut _ as *mut std::ffi::c_void,
+        0,
+    ) else {
+        return 0;
+    };
+
+    let mut count = 0;
+    loop {
+        let length = data.cStreamName.iter().position(|&c| c == 0).unwrap_or(data.cStreamName.len());
+        if String::from_utf16_lossy(&data.cStreamName[..length]) != "::$DATA" {
+            count += 1;
+        }
+        if FindNextStreamW(find, &mut data as *mut _ as *mut std::ffi::c_void).is_err() {
+            break;
+        }
+    }
+    let _ = FindClose(find);
+    count
+}
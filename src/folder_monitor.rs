@@ -0,0 +1,218 @@
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::*,
+        Storage::FileSystem::*,
+        System::IO::CancelIoEx,
+        UI::WindowsAndMessaging::PostMessageW,
+    },
+};
+use std::path::Path;
+use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
+use std::time::Duration;
+use crate::everything_sdk::FileResult;
+use crate::logger::log_error;
+use crate::thumbnail::to_wide;
+
+// A render or download writes many times a second; gathering changes for a
+// moment keeps the list from being refiltered for each one
+const CHANGE_BATCH_DELAY: Duration = Duration::from_millis(300);
+
+// What happened under the monitored folder
+pub enum FolderChange {
+    // A new file, or one moved in; metadata is already loaded
+    Added(FileResult),
+    // A file that was written to
+    Modified(FileResult),
+    // A file or a whole folder that was deleted or moved away
+    Removed(String),
+    // Too many changes to track; the folder has to be read again
+    Rescan,
+}
+
+// Watches a folder tree and posts `message` with a boxed
+// (generation, Vec<FolderChange>) in WPARAM for every batch of changes
+pub struct FolderMonitor {
+    stop: Arc<AtomicBool>,
+    directory: Arc<Mutex<Option<HANDLE>>>,
+}
+
+impl FolderMonitor {
+    pub fn start(folder: &str, window: HWND, message: u32, generation: u64) -> Option<Self> {
+        let root = folder.trim_end_matches('\\').to_string();
+
+        let directory = unsafe {
+            let folder_wide = to_wide(folder);
+            match CreateFileW(
+                PCWSTR::from_raw(folder_wide.as_ptr()),
+                FILE_LIST_DIRECTORY.0,
+                FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+                None,
+                OPEN_EXISTING,
+                FILE_FLAG_BACKUP_SEMANTICS,
+                HANDLE(0),
+            ) {
+                Ok(handle) => handle,
+                Err(e) => {
+                    log_error(&format!("Failed to monitor folder {}: {}", folder, e));
+                    return None;
+                }
+            }
+        };
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let shared_directory = Arc::new(Mutex::new(Some(directory)));
+
+        let thread_stop = stop.clone();
+        let thread_directory = shared_directory.clone();
+
+        std::thread::spawn(move || {
+            // DWORD-aligned buffer as required by ReadDirectoryChangesW
+            let mut buffer = vec![0u32; 16384];
+
+            while !thread_stop.load(Ordering::Relaxed) {
+                let mut bytes_returned = 0u32;
+                let result = unsafe {
+                    ReadDirectoryChangesW(
+                        directory,
+                        buffer.as_mut_ptr() as *mut std::ffi::c_void,
+                        (buffer.len() * std::mem::size_of::<u32>()) as u32,
+                        TRUE,
+                        FILE_NOTIFY_CHANGE_FILE_NAME
+                            | FILE_NOTIFY_CHANGE_DIR_NAME
+                            | FILE_NOTIFY_CHANGE_SIZE
+                            | FILE_NOTIFY_CHANGE_LAST_WRITE,
+                        Some(&mut bytes_returned),
+                        None,
+                        None,
+                    )
+                };
+
+                // Cancelled by stop(), or the folder went away
+                if result.is_err() || thread_stop.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                // A zero-length result means the buffer overflowed
+                let changes = if bytes_returned == 0 {
+                    vec![FolderChange::Rescan]
+                } else {
+                    unsafe { read_changes(&buffer, &root) }
+                };
+                if !changes.is_empty() {
+                    let batch = Box::into_raw(Box::new((generation, changes))) as usize;
+                    unsafe {
+                        if PostMessageW(window, message, WPARAM(batch), LPARAM(0)).is_err() {
+                            drop(Box::from_raw(batch as *mut (u64, Vec<FolderChange>)));
+                        }
+                    }
+                }
+
+                // Changes made meanwhile are queued by the system for the next read
+                std::thread::sleep(CHANGE_BATCH_DELAY);
+            }
+
+            if let Ok(mut directory) = thread_directory.lock() {
+                if let Some(handle) = directory.take() {
+                    unsafe {
+                        let _ = CloseHandle(handle);
+                    }
+                }
+            }
+        });
+
+        Some(Self {
+            stop,
+            directory: shared_directory,
+        })
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        // Wake the monitor thread out of its blocking read
+        if let Ok(directory) = self.directory.lock() {
+            if let Some(handle) = *directory {
+                unsafe {
+                    let _ = CancelIoEx(handle, None);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for FolderMonitor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// Turns a buffer of notifications into changes to files. A folder that
+// appears is listed, since the files inside it don't notify on their own.
+unsafe fn read_changes(buffer: &[u32], root: &str) -> Vec<FolderChange> {
+    let base = buffer.as_ptr() as *const u8;
+    let mut offset = 0usize;
+    let mut changes = Vec::new();
+
+    loop {
+        let info = &*(base.add(offset) as *const FILE_NOTIFY_INFORMATION);
+        let name_len = info.FileNameLength as usize / 2;
+        let name = String::from_utf16_lossy(std::slice::from_raw_parts(info.FileName.as_ptr(), name_len));
+        let path = format!("{}\\{}", root, name);
+
+        match info.Action {
+            FILE_ACTION_REMOVED | FILE_ACTION_RENAMED_OLD_NAME => changes.push(FolderChange::Removed(path)),
+            FILE_ACTION_ADDED | FILE_ACTION_RENAMED_NEW_NAME => {
+                if Path::new(&path).is_dir() {
+                    changes.extend(scan_folder(&path).into_iter().map(FolderChange::Added));
+                } else if Path::new(&path).is_file() {
+                    changes.push(FolderChange::Added(loaded(&path)));
+                }
+            }
+            FILE_ACTION_MODIFIED if Path::new(&path).is_file() => {
+                changes.push(FolderChange::Modified(loaded(&path)));
+            }
+            _ => {}
+        }
+
+        if info.NextEntryOffset == 0 {
+            return changes;
+        }
+        offset += info.NextEntryOffset as usize;
+    }
+}
+
+fn loaded(path: &str) -> FileResult {
+    let mut file = FileResult::from_path(path);
+    file.load_metadata();
+    file
+}
+
+// Every file under `folder` with its size and date, most recently modified
+// first. Links to other folders aren't followed.
+pub fn scan_folder(folder: &str) -> Vec<FileResult> {
+    let mut files = Vec::new();
+    let mut pending = vec![folder.trim_end_matches('\\').to_string()];
+    while let Some(directory) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&directory) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = format!("{}\\{}", directory, entry.file_name().to_string_lossy());
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if file_type.is_file() {
+                let mut file = FileResult::from_path(&path);
+                if let Ok(metadata) = entry.metadata() {
+                    file.set_metadata(metadata.len(), metadata.modified().unwrap_or(std::time::UNIX_EPOCH));
+                }
+                files.push(file);
+            }
+        }
+    }
+    files.sort_by_key(|file| std::cmp::Reverse(file.modified_time));
+    files
+}
@@ -0,0 +1,282 @@
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{ScreenToClient, HFONT},
+        Storage::FileSystem::GetLogicalDrives,
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Controls::*,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::config::{Favorite, FavoriteKind};
+use crate::lang::get_strings;
+use crate::logger::log_warn;
+use crate::thumbnail::to_wide;
+
+// What an item of the sidebar stands for
+#[derive(Debug, Clone, PartialEq)]
+pub enum SidebarItem {
+    // A drive or folder; the path is empty for All Drives
+    Folder(String),
+    Favorites,
+    // Index into the configured favorites
+    Favorite(usize, FavoriteKind),
+}
+
+// One item of the tree. Subfolders are listed the first time it expands.
+struct FolderNode {
+    item: SidebarItem,
+    loaded: bool,
+}
+
+// The sidebar: pinned favorites above a drive and folder tree. Item
+// lParams index into `nodes`.
+pub struct FolderTree {
+    pub window: HWND,
+    nodes: Vec<FolderNode>,
+    favorites: HTREEITEM,
+    root: HTREEITEM,
+}
+
+impl FolderTree {
+    pub fn create(parent: HWND, font: HFONT, id: i32, favorites: &[Favorite]) -> Self {
+        let window = unsafe {
+            let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+            let window = CreateWindowExW(
+                WS_EX_CLIENTEDGE,
+                WC_TREEVIEWW,
+                PCWSTR::null(),
+                WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(TVS_HASBUTTONS | TVS_HASLINES | TVS_LINESATROOT | TVS_SHOWSELALWAYS),
+                0, 0, 0, 0,
+                parent,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(window, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+            SendMessageW(window, TVM_SETEXTENDEDSTYLE, WPARAM(TVS_EX_DOUBLEBUFFER as usize), LPARAM(TVS_EX_DOUBLEBUFFER as isize));
+            window
+        };
+
+        let strings = get_strings();
+        let mut tree = FolderTree { window, nodes: Vec::new(), favorites: HTREEITEM(0), root: HTREEITEM(0) };
+        tree.favorites = tree.insert(TVI_ROOT, &strings.sidebar_favorites, SidebarItem::Favorites, true);
+        tree.root = tree.insert(TVI_ROOT, &strings.folder_tree_all_drives, SidebarItem::Folder(String::new()), true);
+        tree.nodes[1].loaded = true;
+
+        let drives = unsafe { GetLogicalDrives() };
+        for letter in 0..26u8 {
+            if drives & (1 << letter) != 0 {
+                let drive = format!("{}:", (b'A' + letter) as char);
+                tree.insert(tree.root, &drive, SidebarItem::Folder(format!("{}\\", drive)), true);
+            }
+        }
+        tree.set_favorites(favorites);
+
+        unsafe {
+            SendMessageW(window, TVM_EXPAND, WPARAM(TVE_EXPAND.0 as usize), LPARAM(tree.root.0));
+            SendMessageW(window, TVM_SELECTITEM, WPARAM(TVGN_CARET as usize), LPARAM(tree.root.0));
+        }
+        tree
+    }
+
+    pub fn destroy(self) {
+        unsafe {
+            let _ = DestroyWindow(self.window);
+        }
+    }
+
+    // Adds an item below `parent`. An expandable folder keeps its button
+    // until listing it shows there is nothing inside.
+    fn insert(&mut self, parent: HTREEITEM, label: &str, item: SidebarItem, expandable: bool) -> HTREEITEM {
+        let mut label_wide = to_wide(label);
+        let insert = TVINSERTSTRUCTW {
+            hParent: parent,
+            hInsertAfter: TVI_LAST,
+            Anonymous: TVINSERTSTRUCTW_0 {
+                item: TVITEMW {
+                    mask: TVIF_TEXT | TVIF_PARAM | TVIF_CHILDREN,
+                    pszText: PWSTR::from_raw(label_wide.as_mut_ptr()),
+                    cChildren: TVITEMEXW_CHILDREN(expandable as i32),
+                    lParam: LPARAM(self.nodes.len() as isize),
+                    ..Default::default()
+                },
+            },
+        };
+        self.nodes.push(FolderNode { item, loaded: false });
+        unsafe {
+            HTREEITEM(SendMessageW(self.window, TVM_INSERTITEMW, WPARAM(0), LPARAM(&insert as *const _ as isize)).0)
+        }
+    }
+
+    fn node_index(&self, item: HTREEITEM) -> Option<usize> {
+        let mut tree_item = TVITEMW {
+            mask: TVIF_PARAM,
+            hItem: item,
+            ..Default::default()
+        };
+        let found = unsafe { SendMessageW(self.window, TVM_GETITEMW, WPARAM(0), LPARAM(&mut tree_item as *mut _ as isize)).0 != 0 };
+        found.then_some(tree_item.lParam.0 as usize).filter(|&index| index < self.nodes.len())
+    }
+
+    fn children(&self, item: HTREEITEM) -> Vec<HTREEITEM> {
+        let mut children = Vec::new();
+        unsafe {
+            let mut child = SendMessageW(self.window, TVM_GETNEXTITEM, WPARAM(TVGN_CHILD as usize), LPARAM(item.0)).0;
+            while child != 0 {
+                children.push(HTREEITEM(child));
+                child = SendMessageW(self.window, TVM_GETNEXTITEM, WPARAM(TVGN_NEXT as usize), LPARAM(child)).0;
+            }
+        }
+        children
+    }
+
+    fn sidebar_item(&self, item: HTREEITEM) -> Option<&SidebarItem> {
+        self.node_index(item).map(|index| &self.nodes[index].item)
+    }
+
+    // Lists the subfolders of `item` the first time it is needed
+    fn load_children(&mut self, item: HTREEITEM) {
+        let Some(index) = self.node_index(item) else {
+            return;
+        };
+        let path = match &self.nodes[index].item {
+            SidebarItem::Folder(path) if !self.nodes[index].loaded => path.clone(),
+            _ => return,
+        };
+        self.nodes[index].loaded = true;
+
+        let mut folders: Vec<String> = match std::fs::read_dir(&path) {
+            Ok(entries) => entries
+                .flatten()
+                .filter(|entry| entry.file_type().map_or(false, |file_type| file_type.is_dir()))
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .collect(),
+            Err(e) => {
+                log_warn(&format!("Failed to list folders in {}: {}", path, e));
+                Vec::new()
+            }
+        };
+        folders.sort_by_key(|name| name.to_lowercase());
+
+        for name in &folders {
+            let child_path = std::path::Path::new(&path).join(name).to_string_lossy().to_string();
+            self.insert(item, name, SidebarItem::Folder(child_path), true);
+        }
+
+        if folders.is_empty() {
+            // Nothing to expand, so drop the button
+            let tree_item = TVITEMW {
+                mask: TVIF_CHILDREN,
+                hItem: item,
+                cChildren: TVITEMEXW_CHILDREN(0),
+                ..Default::default()
+            };
+            unsafe {
+                SendMessageW(self.window, TVM_SETITEMW, WPARAM(0), LPARAM(&tree_item as *const _ as isize));
+            }
+        }
+    }
+
+    // Replaces the items under Favorites
+    pub fn set_favorites(&mut self, favorites: &[Favorite]) {
+        for child in self.children(self.favorites) {
+            unsafe {
+                SendMessageW(self.window, TVM_DELETEITEM, WPARAM(0), LPARAM(child.0));
+            }
+        }
+        for (index, favorite) in favorites.iter().enumerate() {
+            self.insert(self.favorites, &favorite.name, SidebarItem::Favorite(index, favorite.kind), false);
+        }
+        unsafe {
+            SendMessageW(self.window, TVM_EXPAND, WPARAM(TVE_EXPAND.0 as usize), LPARAM(self.favorites.0));
+        }
+    }
+
+    // The item under the mouse cursor, for the context menu
+    pub fn item_at_cursor(&self) -> Option<SidebarItem> {
+        let mut hit_test = TVHITTESTINFO::default();
+        unsafe {
+            let _ = GetCursorPos(&mut hit_test.pt);
+            ScreenToClient(self.window, &mut hit_test.pt);
+            let item = HTREEITEM(SendMessageW(self.window, TVM_HITTEST, WPARAM(0), LPARAM(&mut hit_test as *mut _ as isize)).0);
+            self.sidebar_item(item).cloned()
+        }
+    }
+
+    // Whether the screen point is over the sidebar, for dropping results
+    pub fn contains_point(&self, point: POINT) -> bool {
+        let mut rect = RECT::default();
+        unsafe {
+            let _ = GetWindowRect(self.window, &mut rect);
+        }
+        point.x >= rect.left && point.x < rect.right && point.y >= rect.top && point.y < rect.bottom
+    }
+
+    // Handles the tree's WM_NOTIFY. Returns the item the user picked:
+    // folders as soon as they are selected, favorites when clicked or on
+    // Enter, since opening one while arrowing past would be slow.
+    pub unsafe fn handle_notify(&mut self, code: u32, lparam: LPARAM) -> Option<SidebarItem> {
+        match code {
+            TVN_ITEMEXPANDINGW => {
+                let info = &*(lparam.0 as *const NMTREEVIEWW);
+                if info.action.0 & TVE_EXPAND.0 != 0 {
+                    self.load_children(info.itemNew.hItem);
+                }
+                None
+            }
+            TVN_SELCHANGEDW => {
+                // Selections made in code aren't the user's choice
+                let info = &*(lparam.0 as *const NMTREEVIEWW);
+                if info.action == TVC_UNKNOWN {
+                    return None;
+                }
+                match &self.nodes.get(info.itemNew.lParam.0 as usize)?.item {
+                    SidebarItem::Folder(path) => Some(SidebarItem::Folder(path.clone())),
+                    SidebarItem::Favorite(index, kind) if info.action == TVC_BYMOUSE => Some(SidebarItem::Favorite(*index, *kind)),
+                    _ => None,
+                }
+            }
+            TVN_KEYDOWN if { (*(lparam.0 as *const NMTVKEYDOWN)).wVKey } == 0x0D => { // VK_RETURN
+                let caret = HTREEITEM(SendMessageW(self.window, TVM_GETNEXTITEM, WPARAM(TVGN_CARET as usize), LPARAM(0)).0);
+                self.sidebar_item(caret).filter(|item| matches!(item, SidebarItem::Favorite(..))).cloned()
+            }
+            _ => None,
+        }
+    }
+
+    // Expands the tree down to `folder` and highlights it, leaving the
+    // selection, which is the search scope, alone
+    pub fn reveal(&mut self, folder: &str) {
+        let mut item = self.root;
+        loop {
+            self.load_children(item);
+            let next = self.children(item).into_iter().find(|&child| {
+                matches!(self.sidebar_item(child), Some(SidebarItem::Folder(path)) if contains_path(path, folder))
+            });
+            match next {
+                Some(child) => item = child,
+                None => break,
+            }
+        }
+
+        unsafe {
+            if item == self.root {
+                SendMessageW(self.window, TVM_SELECTITEM, WPARAM(TVGN_DROPHILITE as usize), LPARAM(0));
+            } else {
+                SendMessageW(self.window, TVM_SELECTITEM, WPARAM(TVGN_DROPHILITE as usize), LPARAM(item.0));
+                SendMessageW(self.window, TVM_ENSUREVISIBLE, WPARAM(0), LPARAM(item.0));
+            }
+        }
+    }
+}
+
+// Whether `path` is `folder` or one of its subfolders, ignoring case
+fn contains_path(folder: &str, path: &str) -> bool {
+    let folder = format!("{}\\", folder.trim_end_matches('\\').to_lowercase());
+    let path = format!("{}\\", path.trim_end_matches('\\').to_lowercase());
+    path.starts_with(&folder)
+}
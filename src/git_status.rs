@@ -0,0 +1,175 @@
+use windows::Win32::Foundation::HWND;
+use git2::{Repository, Status, StatusOptions};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use crate::batch_loader::BatchLoader;
+use crate::lang::LanguageStrings;
+
+// Statuses are posted in small batches so the first rows fill in quickly
+const RESULT_BATCH_SIZE: usize = 32;
+
+// A repository's status is read again once it is this old, so edits made
+// while the app is open show up on the next repaint after it
+const REPO_STATUS_TTL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GitFileStatus {
+    Modified,
+    Staged,
+    Untracked,
+    Ignored,
+    Conflicted,
+}
+
+impl GitFileStatus {
+    pub fn label(self, strings: &LanguageStrings) -> String {
+        match self {
+            GitFileStatus::Modified => strings.git_modified.clone(),
+            GitFileStatus::Staged => strings.git_staged.clone(),
+            GitFileStatus::Untracked => strings.git_untracked.clone(),
+            GitFileStatus::Ignored => strings.git_ignored.clone(),
+            GitFileStatus::Conflicted => strings.git_conflicted.clone(),
+        }
+    }
+
+    // The working tree state wins over the index, since that is what the
+    // file on disk shows
+    fn from_status(status: Status) -> Option<Self> {
+        if status.is_conflicted() {
+            Some(GitFileStatus::Conflicted)
+        } else if status.is_ignored() {
+            Some(GitFileStatus::Ignored)
+        } else if status.is_wt_new() {
+            Some(GitFileStatus::Untracked)
+        } else if status.intersects(Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_RENAMED | Status::WT_TYPECHANGE) {
+            Some(GitFileStatus::Modified)
+        } else if status.intersects(Status::INDEX_NEW | Status::INDEX_MODIFIED | Status::INDEX_DELETED | Status::INDEX_RENAMED | Status::INDEX_TYPECHANGE) {
+            Some(GitFileStatus::Staged)
+        } else {
+            None
+        }
+    }
+}
+
+// Status of one row. None when the file is unchanged or not in a
+// repository; both show an empty cell.
+pub struct GitStatusEntry {
+    pub path: String,
+    pub status: Option<GitFileStatus>,
+}
+
+// Every status of one working tree, keyed by the lowercase path relative
+// to it with '/' separators. Untracked and ignored folders are listed once,
+// with a trailing '/', instead of file by file.
+struct RepoStatus {
+    statuses: HashMap<String, GitFileStatus>,
+    read_at: Instant,
+}
+
+// Looks up Git statuses for the rows around the viewport on a worker
+// thread and posts `message` with a boxed Vec<GitStatusEntry> in WPARAM
+// for each batch
+pub type GitStatusLoader = BatchLoader<String, GitStatusEntry>;
+
+impl GitStatusLoader {
+    pub fn start(window: HWND, message: u32) -> Self {
+        // Working tree of each folder looked at (None outside a repository)
+        // and the status of each working tree
+        let mut folder_repos: HashMap<String, Option<String>> = HashMap::new();
+        let mut repos: HashMap<String, RepoStatus> = HashMap::new();
+
+        BatchLoader::spawn(window, message, RESULT_BATCH_SIZE, move |path: &String| GitStatusEntry {
+            path: path.clone(),
+            status: file_status(path, &mut folder_repos, &mut repos),
+        })
+    }
+}
+
+fn file_status(
+    path: &str,
+    folder_repos: &mut HashMap<String, Option<String>>,
+    repos: &mut HashMap<String, RepoStatus>,
+) -> Option<GitFileStatus> {
+    let path_ref = Path::new(path);
+    let folder = path_ref.parent()?.to_string_lossy().to_lowercase();
+
+    let workdir = folder_repos
+        .entry(folder.clone())
+        .or_insert_with(|| {
+            let repo = Repository::discover(&folder).ok()?;
+            let workdir = repo.workdir()?;
+            Some(normalize(&workdir.to_string_lossy()))
+        })
+        .clone()?;
+
+    let fresh = repos.get(&workdir).is_some_and(|repo| repo.read_at.elapsed() <= REPO_STATUS_TTL);
+    if !fresh {
+        match read_repo_status(&workdir) {
+            Some(repo_status) => {
+                repos.insert(workdir.clone(), repo_status);
+            }
+            None => {
+                repos.remove(&workdir);
+                return None;
+            }
+        }
+    }
+    let repo_status = repos.get(&workdir)?;
+
+    let relative = normalize(path).strip_prefix(&workdir)?.trim_matches('/').to_string();
+    if relative.is_empty() || relative == ".git" || relative.starts_with(".git/") {
+        return None;
+    }
+
+    if let Some(&status) = repo_status.statuses.get(&relative) {
+        return Some(status);
+    }
+    if let Some(&status) = repo_status.statuses.get(&format!("{}/", relative)) {
+        return Some(status);
+    }
+
+    // Inside an untracked or ignored folder, which is listed as a whole
+    let mut end = relative.len();
+    while let Some(slash) = relative[..end].rfind('/') {
+        if let Some(&status) = repo_status.statuses.get(&relative[..=slash]) {
+            return Some(status);
+        }
+        end = slash;
+    }
+    None
+}
+
+fn read_repo_status(workdir: &str) -> Option<RepoStatus> {
+    let repo = Repository::open(workdir).ok()?;
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .include_ignored(true)
+        .recurse_untracked_dirs(false)
+        .recurse_ignored_dirs(false)
+        .exclude_submodules(true);
+    let statuses = repo.statuses(Some(&mut options)).ok()?;
+
+    let statuses = statuses
+        .iter()
+        .filter_map(|entry| {
+            let status = GitFileStatus::from_status(entry.status())?;
+            Some((entry.path()?.to_lowercase(), status))
+        })
+        .collect();
+    Some(RepoStatus {
+        statuses,
+        read_at: Instant::now(),
+    })
+}
+
+// Lowercase with '/' separators and a trailing '/', so a working tree is a
+// plain prefix of the paths inside it
+fn normalize(path: &str) -> String {
+    let mut normalized = path.replace('\\', "/").to_lowercase();
+    if !normalized.ends_with('/') {
+        normalized.push('/');
+    }
+    normalized
+}
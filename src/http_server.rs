@@ -0,0 +1,291 @@
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, SocketAddrV4, TcpListener, TcpStream};
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use crate::everything_sdk::{EverythingSDK, FileResult};
+use crate::export::format_mtime;
+use crate::logger::{log_debug, log_info, log_warn};
+
+// A client that stops sending is dropped after this long
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Requests are a single line plus headers; anything longer is refused
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+// Results per response unless the request asks for a different count
+const DEFAULT_COUNT: usize = 100;
+const MAX_COUNT: usize = 10_000;
+
+// Search page served at "/". It calls /search and lists what comes back.
+const SEARCH_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>EverythingLike</title>
+<style>
+body { font-family: "Segoe UI", sans-serif; font-size: 13px; margin: 16px; }
+input { width: 60%; font-size: 14px; padding: 4px; }
+table { border-collapse: collapse; width: 100%; margin-top: 12px; }
+th, td { border: 1px solid #d0d0d0; padding: 4px 8px; text-align: left; }
+th { background: #e0e0e0; }
+td.size { text-align: right; }
+</style>
+</head>
+<body>
+<form id="search"><input id="query" autofocus> <button>Search</button></form>
+<p id="status"></p>
+<table><thead><tr><th>Name</th><th>Size</th><th>Modified</th><th>Path</th></tr></thead><tbody id="results"></tbody></table>
+<script>
+document.getElementById("search").addEventListener("submit", function (event) {
+  event.preventDefault();
+  var query = document.getElementById("query").value;
+  fetch("/search?q=" + encodeURIComponent(query))
+    .then(function (response) { return response.json(); })
+    .then(function (reply) {
+      var body = document.getElementById("results");
+      body.textContent = "";
+      reply.results.forEach(function (result) {
+        var row = body.insertRow();
+        [result.name, result.size, result.modified, result.path].forEach(function (value, column) {
+          var cell = row.insertCell();
+          cell.textContent = value;
+          if (column === 1) cell.className = "size";
+        });
+      });
+      document.getElementById("status").textContent =
+        reply.results.length + " of " + reply.total + " results";
+    })
+    .catch(function (error) {
+      document.getElementById("status").textContent = error;
+    });
+});
+</script>
+</body>
+</html>
+"#;
+
+// Answers searches on 127.0.0.1 like Everything's HTTP server: GET
+// /search?q=... returns JSON and GET / a page that uses it. Dropping the
+// server stops it.
+pub struct HttpServer {
+    port: u16,
+    stopping: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HttpServer {
+    pub fn start(port: u16) -> io::Result<Self> {
+        // Loopback only; the results include every path on the machine
+        let listener = TcpListener::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, port))?;
+        let stopping = Arc::new(AtomicBool::new(false));
+
+        let thread_stopping = stopping.clone();
+        let thread = std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if thread_stopping.load(Ordering::Relaxed) {
+                    break;
+                }
+                match stream {
+                    // Browsers open connections they may never use, so a
+                    // quiet client must not hold up the next one
+                    Ok(stream) => {
+                        std::thread::spawn(move || handle_connection(stream));
+                    }
+                    Err(e) => log_warn(&format!("HTTP server accept failed: {}", e)),
+                }
+            }
+            log_debug("HTTP server stopped");
+        });
+
+        log_info(&format!("HTTP server listening on http://127.0.0.1:{}/", port));
+        Ok(Self {
+            port,
+            stopping,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::Relaxed);
+        // accept() only returns for a connection, so make one
+        let _ = TcpStream::connect(SocketAddrV4::new(Ipv4Addr::LOCALHOST, self.port));
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn new(status: &'static str, content_type: &'static str, body: String) -> Self {
+        Self { status, content_type, body }
+    }
+
+    fn text(status: &'static str) -> Self {
+        Self::new(status, "text/plain; charset=utf-8", format!("{}\n", status))
+    }
+}
+
+fn handle_connection(mut stream: TcpStream) {
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+
+    let response = match read_request_head(&mut stream) {
+        Some(head) => respond(&head),
+        None => return,
+    };
+
+    let header = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+    let _ = stream
+        .write_all(header.as_bytes())
+        .and_then(|_| stream.write_all(response.body.as_bytes()));
+}
+
+// Reads up to the blank line that ends the headers. None when the client
+// went away, was too slow or sent too much.
+fn read_request_head(stream: &mut TcpStream) -> Option<String> {
+    let mut head = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).ok()?;
+        if read == 0 || head.len() + read > MAX_REQUEST_SIZE {
+            return None;
+        }
+        head.extend_from_slice(&buffer[..read]);
+    }
+    Some(String::from_utf8_lossy(&head).to_string())
+}
+
+fn respond(head: &str) -> Response {
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or("").split(' ');
+    let (method, target) = (request_line.next().unwrap_or(""), request_line.next().unwrap_or(""));
+
+    // A web page can point a name it controls at 127.0.0.1; checking the
+    // Host header keeps such pages from reading the results
+    let host = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("host"))
+        .map(|(_, value)| value.trim())
+        .unwrap_or("");
+    if !is_local_host(host) {
+        return Response::text("403 Forbidden");
+    }
+
+    if method != "GET" {
+        return Response::text("405 Method Not Allowed");
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    match path {
+        "/" => Response::new("200 OK", "text/html; charset=utf-8", SEARCH_PAGE.to_string()),
+        "/search" => search(query),
+        _ => Response::text("404 Not Found"),
+    }
+}
+
+fn is_local_host(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    matches!(name.to_ascii_lowercase().as_str(), "localhost" | "127.0.0.1")
+}
+
+fn search(query_string: &str) -> Response {
+    let mut query = String::new();
+    let mut count = DEFAULT_COUNT;
+    for parameter in query_string.split('&') {
+        let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+        match name {
+            "q" | "s" | "search" => query = percent_decode(value),
+            "count" => count = value.parse().unwrap_or(DEFAULT_COUNT).min(MAX_COUNT),
+            _ => {}
+        }
+    }
+
+    let mut results: Vec<FileResult> = Vec::new();
+    let search_result = {
+        // The SDK keeps one result list per process, so searches take turns
+        // with the main search thread
+        let _guard = crate::EVERYTHING_SDK_MUTEX.lock().unwrap();
+        EverythingSDK::new().and_then(|sdk| {
+            sdk.search_files_in_batches(&query, count, |paths, _| {
+                results.extend(paths);
+                true
+            })?;
+            Ok(sdk.get_num_results())
+        })
+    };
+    let total = match search_result {
+        Ok(total) => total,
+        Err(e) => {
+            log_warn(&format!("HTTP search for '{}' failed: {}", query, e));
+            return Response::text("503 Service Unavailable");
+        }
+    };
+
+    let entries: Vec<serde_json::Value> = results
+        .iter_mut()
+        .map(|result| {
+            result.load_metadata();
+            serde_json::json!({
+                "name": result.name(),
+                "path": result.path(),
+                "size": result.size,
+                "modified": format_mtime(result.modified_time),
+            })
+        })
+        .collect();
+    let body = serde_json::json!({
+        "query": query,
+        "total": total,
+        "results": entries,
+    });
+
+    Response::new("200 OK", "application/json; charset=utf-8", body.to_string())
+}
+
+// Decodes a URL query value: "+" is a space and "%XX" a byte of UTF-8
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => match (hex_digit(bytes[i + 1]), hex_digit(bytes[i + 2])) {
+                (Some(high), Some(low)) => {
+                    decoded.push(high << 4 | low);
+                    i += 2;
+                }
+                _ => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn hex_digit(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|digit| digit as u8)
+}
@@ -0,0 +1,190 @@
+use std::ffi::c_void;
+use std::io::Write;
+use std::path::Path;
+use windows::{
+    core::{w, PCWSTR},
+    Win32::Networking::WinHttp::*,
+};
+use crate::thumbnail::to_wide;
+
+// Replies read into memory are refused beyond this; a release description
+// is a few KB
+const MAX_REPLY_SIZE: usize = 4 * 1024 * 1024;
+
+// Milliseconds for name resolution, connecting, sending and each read
+const TIMEOUT_MS: i32 = 15_000;
+
+// Closes a WinHTTP session, connection or request handle
+struct Handle(*mut c_void);
+
+impl Handle {
+    fn new(handle: *mut c_void, what: &str) -> Result<Self, String> {
+        if handle.is_null() {
+            Err(format!("{} failed: {}", what, windows::core::Error::from_win32()))
+        } else {
+            Ok(Self(handle))
+        }
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = WinHttpCloseHandle(self.0);
+        }
+    }
+}
+
+// An open 200 reply whose body is read with `read`. The handles close in
+// field order, request first.
+struct Reply {
+    request: Handle,
+    _connection: Handle,
+    _session: Handle,
+    // From Content-Length; None when the server didn't send one
+    length: Option<u64>,
+}
+
+impl Reply {
+    // Sends a GET for an https:// URL. WinHTTP uses the system's proxy
+    // settings and certificate store and follows redirects.
+    fn open(url: &str, accept: &str) -> Result<Self, String> {
+        let rest = url.strip_prefix("https://").ok_or("Only https:// addresses are supported")?;
+        let (host_port, path) = match rest.find('/') {
+            Some(slash) => (&rest[..slash], &rest[slash..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match host_port.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| format!("Bad port in {}", url))?),
+            None => (host_port, INTERNET_DEFAULT_HTTPS_PORT),
+        };
+
+        unsafe {
+            let user_agent = format!("EverythingLike/{}", env!("CARGO_PKG_VERSION"));
+            let session = Handle::new(
+                WinHttpOpen(
+                    PCWSTR::from_raw(to_wide(&user_agent).as_ptr()),
+                    WINHTTP_ACCESS_TYPE_AUTOMATIC_PROXY,
+                    PCWSTR::null(),
+                    PCWSTR::null(),
+                    0,
+                ),
+                "WinHttpOpen",
+            )?;
+            let _ = WinHttpSetTimeouts(session.0, TIMEOUT_MS, TIMEOUT_MS, TIMEOUT_MS, TIMEOUT_MS);
+
+            let connection = Handle::new(
+                WinHttpConnect(session.0, PCWSTR::from_raw(to_wide(host).as_ptr()), port, 0),
+                "WinHttpConnect",
+            )?;
+            let request = Handle::new(
+                WinHttpOpenRequest(
+                    connection.0,
+                    w!("GET"),
+                    PCWSTR::from_raw(to_wide(path).as_ptr()),
+                    PCWSTR::null(),
+                    PCWSTR::null(),
+                    std::ptr::null(),
+                    WINHTTP_FLAG_SECURE,
+                ),
+                "WinHttpOpenRequest",
+            )?;
+
+            let headers: Vec<u16> = format!("Accept: {}\r\n", accept).encode_utf16().collect();
+            WinHttpSendRequest(request.0, Some(&headers), None, 0, 0, 0).map_err(|e| e.to_string())?;
+            WinHttpReceiveResponse(request.0, std::ptr::null_mut()).map_err(|e| e.to_string())?;
+
+            let status = query_number(&request, WINHTTP_QUERY_STATUS_CODE).ok_or("The reply has no status")?;
+            if status != 200 {
+                return Err(format!("The server answered HTTP {}", status));
+            }
+            let length = query_number(&request, WINHTTP_QUERY_CONTENT_LENGTH).map(u64::from);
+
+            Ok(Self {
+                request,
+                _connection: connection,
+                _session: session,
+                length,
+            })
+        }
+    }
+
+    // Reads the next part of the body; 0 at the end
+    fn read(&self, buffer: &mut [u8]) -> Result<usize, String> {
+        let mut read: u32 = 0;
+        unsafe {
+            WinHttpReadData(self.request.0, buffer.as_mut_ptr() as *mut c_void, buffer.len() as u32, &mut read)
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(read as usize)
+    }
+}
+
+fn query_number(request: &Handle, info: u32) -> Option<u32> {
+    let mut value: u32 = 0;
+    let mut size = std::mem::size_of::<u32>() as u32;
+    unsafe {
+        WinHttpQueryHeaders(
+            request.0,
+            info | WINHTTP_QUERY_FLAG_NUMBER,
+            PCWSTR::null(),
+            Some(&mut value as *mut u32 as *mut c_void),
+            &mut size,
+            std::ptr::null_mut(),
+        )
+        .ok()?;
+    }
+    Some(value)
+}
+
+// GETs an https:// URL and returns the body of a 200 reply
+pub fn https_get(url: &str, accept: &str) -> Result<Vec<u8>, String> {
+    let reply = Reply::open(url, accept)?;
+    let mut body = Vec::new();
+    let mut buffer = vec![0u8; 16 * 1024];
+    loop {
+        let read = reply.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        body.extend_from_slice(&buffer[..read]);
+        if body.len() > MAX_REPLY_SIZE {
+            return Err("The reply is too large".to_string());
+        }
+    }
+    Ok(body)
+}
+
+// Streams the body of a 200 reply into `path`. `on_data` sees every piece
+// written along with the bytes so far and the total, when the server gave
+// one. A partly written file is removed on failure.
+pub fn https_download<F>(url: &str, path: &Path, mut on_data: F) -> Result<u64, String>
+where
+    F: FnMut(&[u8], u64, Option<u64>) -> Result<(), String>,
+{
+    let reply = Reply::open(url, "application/octet-stream")?;
+    let mut file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+
+    let mut written: u64 = 0;
+    let mut buffer = vec![0u8; 64 * 1024];
+    let result = loop {
+        let read = match reply.read(&mut buffer) {
+            Ok(0) => break Ok(written),
+            Ok(read) => read,
+            Err(e) => break Err(e),
+        };
+        if let Err(e) = file.write_all(&buffer[..read]) {
+            break Err(e.to_string());
+        }
+        written += read as u64;
+        if let Err(e) = on_data(&buffer[..read], written, reply.length) {
+            break Err(e);
+        }
+    };
+
+    if result.is_err() {
+        drop(file);
+        let _ = std::fs::remove_file(path);
+    }
+    result
+}
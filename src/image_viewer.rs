@@ -0,0 +1,547 @@
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::{Gdi::*, Imaging::*},
+        System::{Com::*, LibraryLoader::GetModuleHandleW},
+        UI::{
+            Input::KeyboardAndMouse::*,
+            Shell::ShellExecuteW,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::lang::{fill, get_strings};
+use crate::logger::{log_error, log_info, log_warn};
+use crate::thumbnail::to_wide;
+
+// Posted by the decoder with a boxed DecodeResult in WPARAM
+const WM_IMAGE_LOADED: u32 = WM_USER + 129;
+
+const VIEWER_WINDOW_CLASS: &str = "EverythingLikeImageViewer";
+
+const VIEWER_WINDOW_WIDTH: i32 = 1000;
+const VIEWER_WINDOW_HEIGHT: i32 = 750;
+
+// Dark gray behind the image, also blended into transparent pixels (0x00BBGGRR)
+const BACKGROUND_COLOR: u32 = 0x00282828;
+const TEXT_COLOR: u32 = 0x00C0C0C0;
+
+const MIN_ZOOM: f64 = 0.02;
+const MAX_ZOOM: f64 = 16.0;
+const ZOOM_STEP: f64 = 1.25;
+
+// Extensions Windows ships a WIC codec for. HEIC, AVIF and WebP need the
+// codecs from the Store; without them the viewer says it can't show the file.
+const IMAGE_EXTENSIONS: [&str; 18] = [
+    "bmp", "dib", "gif", "ico", "jpg", "jpeg", "jpe", "jfif", "png", "tif",
+    "tiff", "wdp", "jxr", "dds", "webp", "heic", "heif", "avif",
+];
+
+pub fn is_viewable_image(extension: &str) -> bool {
+    IMAGE_EXTENSIONS.iter().any(|known| known.eq_ignore_ascii_case(extension))
+}
+
+// A decoded frame, 32bpp BGR top-down with transparency already blended
+// into the background
+struct DecodedImage {
+    width: i32,
+    height: i32,
+    pixels: Vec<u8>,
+}
+
+// (load number, image or error) as sent back by the decoder
+type DecodeResult = (u64, std::result::Result<DecodedImage, String>);
+
+struct ImageViewer {
+    paths: Vec<String>,
+    index: usize,
+    image: Option<DecodedImage>,
+    // Set when the current file couldn't be decoded
+    failed: bool,
+    // Bumped for every file shown, so a slow decode of a file the user has
+    // already moved past is dropped
+    load_id: u64,
+    // None fits the image to the window, never enlarging it
+    zoom: Option<f64>,
+    // Offset of the image from the centered position, in screen pixels
+    pan_x: i32,
+    pan_y: i32,
+    // Cursor position and pan when a drag started
+    drag: Option<(POINT, i32, i32)>,
+}
+
+// The window's ImageViewer, kept in GWLP_USERDATA
+unsafe fn viewer(window: HWND) -> Option<&'static mut ImageViewer> {
+    let viewer = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut ImageViewer;
+    viewer.as_mut()
+}
+
+pub fn register_image_viewer_window_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW | CS_DBLCLKS,
+            lpfnWndProc: Some(image_viewer_window_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH(0),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeImageViewer"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// Shows paths[index] in the viewer window, opening it if it isn't open yet.
+// Left/Right step through `paths`, the wheel zooms and dragging pans.
+pub fn show_image_viewer(owner: HWND, paths: Vec<String>, index: usize) {
+    if index >= paths.len() {
+        return;
+    }
+
+    unsafe {
+        let class_wide = to_wide(VIEWER_WINDOW_CLASS);
+        let mut window = FindWindowW(PCWSTR::from_raw(class_wide.as_ptr()), PCWSTR::null());
+
+        if window.0 == 0 {
+            let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+            window = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                PCWSTR::from_raw(class_wide.as_ptr()),
+                PCWSTR::null(),
+                WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+                CW_USEDEFAULT,
+                CW_USEDEFAULT,
+                VIEWER_WINDOW_WIDTH,
+                VIEWER_WINDOW_HEIGHT,
+                owner,
+                None,
+                instance,
+                None,
+            );
+
+            if window.0 == 0 {
+                log_error("Failed to create image viewer window");
+                return;
+            }
+
+            let viewer = Box::new(ImageViewer {
+                paths: Vec::new(),
+                index: 0,
+                image: None,
+                failed: false,
+                load_id: 0,
+                zoom: None,
+                pan_x: 0,
+                pan_y: 0,
+                drag: None,
+            });
+            SetWindowLongPtrW(window, GWLP_USERDATA, Box::into_raw(viewer) as isize);
+        } else if IsIconic(window).as_bool() {
+            let _ = ShowWindow(window, SW_RESTORE);
+        }
+
+        if let Some(viewer) = viewer(window) {
+            viewer.paths = paths;
+        }
+        show_index(window, index);
+        let _ = SetForegroundWindow(window);
+    }
+}
+
+// Switches to paths[index] and starts decoding it. The previous image stays
+// up until the new one is ready, so stepping through doesn't flash.
+unsafe fn show_index(window: HWND, index: usize) {
+    let Some(viewer) = viewer(window) else { return };
+    let Some(path) = viewer.paths.get(index).cloned() else { return };
+
+    viewer.index = index;
+    viewer.load_id += 1;
+    viewer.zoom = None;
+    viewer.pan_x = 0;
+    viewer.pan_y = 0;
+    update_title(window);
+
+    let load_id = viewer.load_id;
+    std::thread::spawn(move || {
+        let result = Box::new((load_id, decode_image(&path)));
+        let result_ptr = Box::into_raw(result) as usize;
+        if PostMessageW(window, WM_IMAGE_LOADED, WPARAM(result_ptr), LPARAM(0)).is_err() {
+            // The window is gone, so nobody will free the image
+            drop(Box::from_raw(result_ptr as *mut DecodeResult));
+        }
+    });
+}
+
+fn decode_image(path: &str) -> std::result::Result<DecodedImage, String> {
+    unsafe {
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+        let result = decode_frame(path).map_err(|e| e.message().to_string());
+        CoUninitialize();
+        result
+    }
+}
+
+unsafe fn decode_frame(path: &str) -> Result<DecodedImage> {
+    let factory: IWICImagingFactory = CoCreateInstance(&CLSID_WICImagingFactory, None, CLSCTX_INPROC_SERVER)?;
+    let path_wide = to_wide(path);
+    let decoder = factory.CreateDecoderFromFilename(
+        PCWSTR::from_raw(path_wide.as_ptr()),
+        None,
+        GENERIC_READ,
+        WICDecodeMetadataCacheOnDemand,
+    )?;
+    let frame = decoder.GetFrame(0)?;
+    let source = WICConvertBitmapSource(&GUID_WICPixelFormat32bppPBGRA, &frame)?;
+
+    let (mut width, mut height) = (0u32, 0u32);
+    source.GetSize(&mut width, &mut height)?;
+    let stride = width * 4;
+    let mut pixels = vec![0u8; stride as usize * height as usize];
+    source.CopyPixels(std::ptr::null(), stride, &mut pixels)?;
+
+    // Premultiplied, so blending in the background is one multiply per channel
+    let background = [
+        (BACKGROUND_COLOR >> 16) as u8 as u32,
+        (BACKGROUND_COLOR >> 8) as u8 as u32,
+        BACKGROUND_COLOR as u8 as u32,
+    ];
+    for pixel in pixels.chunks_exact_mut(4) {
+        let transparency = 255 - pixel[3] as u32;
+        if transparency > 0 {
+            for channel in 0..3 {
+                pixel[channel] = (pixel[channel] as u32 + background[channel] * transparency / 255).min(255) as u8;
+            }
+        }
+        pixel[3] = 0;
+    }
+
+    Ok(DecodedImage { width: width as i32, height: height as i32, pixels })
+}
+
+unsafe fn update_title(window: HWND) {
+    let Some(viewer) = viewer(window) else { return };
+    let Some(path) = viewer.paths.get(viewer.index) else { return };
+
+    let name = std::path::Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path);
+    let zoom = match &viewer.image {
+        Some(image) => format!("{:.0}", current_zoom(window, viewer, image) * 100.0),
+        None => "-".to_string(),
+    };
+    let title = fill(
+        &get_strings().image_viewer_title,
+        &[
+            ("name", name),
+            ("index", &(viewer.index + 1).to_string()),
+            ("count", &viewer.paths.len().to_string()),
+            ("zoom", &zoom),
+        ],
+    );
+    let _ = SetWindowTextW(window, PCWSTR::from_raw(to_wide(&title).as_ptr()));
+}
+
+unsafe fn client_size(window: HWND) -> (i32, i32) {
+    let mut rect = RECT::default();
+    let _ = GetClientRect(window, &mut rect);
+    (rect.right.max(1), rect.bottom.max(1))
+}
+
+unsafe fn current_zoom(window: HWND, viewer: &ImageViewer, image: &DecodedImage) -> f64 {
+    viewer.zoom.unwrap_or_else(|| {
+        let (client_width, client_height) = client_size(window);
+        (client_width as f64 / image.width.max(1) as f64)
+            .min(client_height as f64 / image.height.max(1) as f64)
+            .min(1.0)
+    })
+}
+
+// Where the image goes in the client area: x, y, width and height. Keeps
+// the pan within the image, and centers a side that fits the window.
+unsafe fn image_rect(window: HWND, viewer: &mut ImageViewer, image: &DecodedImage) -> (i32, i32, i32, i32) {
+    let (client_width, client_height) = client_size(window);
+    let zoom = current_zoom(window, viewer, image);
+    let width = ((image.width as f64 * zoom).round() as i32).max(1);
+    let height = ((image.height as f64 * zoom).round() as i32).max(1);
+
+    let limit_x = ((width - client_width) / 2).max(0);
+    let limit_y = ((height - client_height) / 2).max(0);
+    viewer.pan_x = viewer.pan_x.clamp(-limit_x, limit_x);
+    viewer.pan_y = viewer.pan_y.clamp(-limit_y, limit_y);
+
+    (
+        (client_width - width) / 2 + viewer.pan_x,
+        (client_height - height) / 2 + viewer.pan_y,
+        width,
+        height,
+    )
+}
+
+// Sets the zoom, keeping the image point under `anchor` (client
+// coordinates) where it is. None fits the image again.
+unsafe fn set_zoom(window: HWND, zoom: Option<f64>, anchor: Option<POINT>) {
+    let Some(viewer) = viewer(window) else { return };
+    let Some(image) = viewer.image.take() else { return };
+
+    let (x, y, width, _) = image_rect(window, viewer, &image);
+    let old_zoom = width as f64 / image.width.max(1) as f64;
+    match zoom {
+        Some(zoom) => {
+            let zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+            let (client_width, client_height) = client_size(window);
+            let anchor = anchor.unwrap_or(POINT { x: client_width / 2, y: client_height / 2 });
+            let image_x = (anchor.x - x) as f64 / old_zoom;
+            let image_y = (anchor.y - y) as f64 / old_zoom;
+            let new_x = anchor.x - (image_x * zoom).round() as i32;
+            let new_y = anchor.y - (image_y * zoom).round() as i32;
+            viewer.pan_x = new_x - (client_width - (image.width as f64 * zoom).round() as i32) / 2;
+            viewer.pan_y = new_y - (client_height - (image.height as f64 * zoom).round() as i32) / 2;
+            viewer.zoom = Some(zoom);
+        }
+        None => {
+            viewer.zoom = None;
+            viewer.pan_x = 0;
+            viewer.pan_y = 0;
+        }
+    }
+    viewer.image = Some(image);
+
+    update_title(window);
+    InvalidateRect(window, None, FALSE);
+}
+
+unsafe fn zoom_by(window: HWND, factor: f64, anchor: Option<POINT>) {
+    let Some(viewer) = viewer(window) else { return };
+    let Some(image) = &viewer.image else { return };
+    let zoom = current_zoom(window, viewer, image) * factor;
+    set_zoom(window, Some(zoom), anchor);
+}
+
+unsafe fn step(window: HWND, delta: isize) {
+    let Some(viewer) = viewer(window) else { return };
+    let last = viewer.paths.len().saturating_sub(1) as isize;
+    let index = (viewer.index as isize).saturating_add(delta).clamp(0, last) as usize;
+    if index != viewer.index {
+        show_index(window, index);
+    }
+}
+
+// Drawn into a memory bitmap first so panning doesn't flicker
+unsafe fn paint(window: HWND) {
+    let mut paint_struct = PAINTSTRUCT::default();
+    let hdc = BeginPaint(window, &mut paint_struct);
+    let (client_width, client_height) = client_size(window);
+
+    let memory_dc = CreateCompatibleDC(hdc);
+    let bitmap = CreateCompatibleBitmap(hdc, client_width, client_height);
+    let old_bitmap = SelectObject(memory_dc, bitmap);
+
+    let client_rect = RECT { left: 0, top: 0, right: client_width, bottom: client_height };
+    let background = CreateSolidBrush(COLORREF(BACKGROUND_COLOR));
+    FillRect(memory_dc, &client_rect, background);
+    DeleteObject(background);
+
+    if let Some(viewer) = viewer(window) {
+        if let Some(image) = viewer.image.take() {
+            let (x, y, width, height) = image_rect(window, viewer, &image);
+            let bitmap_info = BITMAPINFO {
+                bmiHeader: BITMAPINFOHEADER {
+                    biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                    biWidth: image.width,
+                    biHeight: -image.height,
+                    biPlanes: 1,
+                    biBitCount: 32,
+                    biCompression: BI_RGB.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            SetStretchBltMode(memory_dc, HALFTONE);
+            let _ = SetBrushOrgEx(memory_dc, 0, 0, None);
+            StretchDIBits(
+                memory_dc,
+                x, y, width, height,
+                0, 0, image.width, image.height,
+                Some(image.pixels.as_ptr() as *const std::ffi::c_void),
+                &bitmap_info,
+                DIB_RGB_COLORS,
+                SRCCOPY,
+            );
+            viewer.image = Some(image);
+        }
+
+        if viewer.failed || viewer.image.is_none() {
+            let strings = get_strings();
+            let message = if viewer.failed { &strings.image_viewer_failed } else { &strings.image_viewer_loading };
+            let mut text: Vec<u16> = message.encode_utf16().collect();
+            let mut text_rect = client_rect;
+            SetBkMode(memory_dc, TRANSPARENT);
+            SetTextColor(memory_dc, COLORREF(TEXT_COLOR));
+            let old_font = SelectObject(memory_dc, GetStockObject(DEFAULT_GUI_FONT));
+            DrawTextW(memory_dc, &mut text, &mut text_rect, DT_CENTER | DT_VCENTER | DT_SINGLELINE | DT_NOPREFIX);
+            SelectObject(memory_dc, old_font);
+        }
+    }
+
+    let _ = BitBlt(hdc, 0, 0, client_width, client_height, memory_dc, 0, 0, SRCCOPY);
+
+    SelectObject(memory_dc, old_bitmap);
+    DeleteObject(bitmap);
+    DeleteDC(memory_dc);
+    EndPaint(window, &paint_struct);
+}
+
+fn point_from_lparam(lparam: LPARAM) -> POINT {
+    POINT {
+        x: (lparam.0 & 0xFFFF) as i16 as i32,
+        y: ((lparam.0 >> 16) & 0xFFFF) as i16 as i32,
+    }
+}
+
+extern "system" fn image_viewer_window_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_IMAGE_LOADED => {
+                let (load_id, result) = *Box::from_raw(wparam.0 as *mut DecodeResult);
+                if let Some(viewer) = viewer(window) {
+                    if load_id == viewer.load_id {
+                        match result {
+                            Ok(image) => {
+                                log_info(&format!("Viewing {}x{} image", image.width, image.height));
+                                viewer.image = Some(image);
+                                viewer.failed = false;
+                            }
+                            Err(e) => {
+                                log_warn(&format!("Failed to decode {}: {}", viewer.paths[viewer.index], e));
+                                viewer.image = None;
+                                viewer.failed = true;
+                            }
+                        }
+                        update_title(window);
+                        InvalidateRect(window, None, FALSE);
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_PAINT => {
+                paint(window);
+                LRESULT(0)
+            }
+            WM_ERASEBKGND => LRESULT(1),
+            WM_SIZE => {
+                update_title(window);
+                InvalidateRect(window, None, FALSE);
+                LRESULT(0)
+            }
+            WM_KEYDOWN => {
+                let key = VIRTUAL_KEY(wparam.0 as u16);
+                match key {
+                    VK_LEFT | VK_PRIOR | VK_BACK => step(window, -1),
+                    VK_RIGHT | VK_NEXT | VK_SPACE => step(window, 1),
+                    VK_HOME => step(window, isize::MIN),
+                    VK_END => step(window, isize::MAX),
+                    VK_ADD | VK_OEM_PLUS => zoom_by(window, ZOOM_STEP, None),
+                    VK_SUBTRACT | VK_OEM_MINUS => zoom_by(window, 1.0 / ZOOM_STEP, None),
+                    VK_0 | VK_NUMPAD0 => set_zoom(window, None, None),
+                    VK_1 | VK_NUMPAD1 => set_zoom(window, Some(1.0), None),
+                    VK_RETURN => {
+                        // Hands the file to its usual program
+                        if let Some(viewer) = viewer(window) {
+                            let path_wide = to_wide(&viewer.paths[viewer.index]);
+                            ShellExecuteW(None, w!("open"), PCWSTR::from_raw(path_wide.as_ptr()), None, None, SW_SHOWNORMAL);
+                        }
+                    }
+                    VK_ESCAPE => {
+                        let _ = DestroyWindow(window);
+                    }
+                    _ => return DefWindowProcW(window, message, wparam, lparam),
+                }
+                LRESULT(0)
+            }
+            WM_MOUSEWHEEL => {
+                let delta = ((wparam.0 >> 16) & 0xFFFF) as i16;
+                let mut anchor = point_from_lparam(lparam);
+                let _ = ScreenToClient(window, &mut anchor);
+                let factor = if delta > 0 { ZOOM_STEP } else { 1.0 / ZOOM_STEP };
+                zoom_by(window, factor, Some(anchor));
+                LRESULT(0)
+            }
+            WM_LBUTTONDBLCLK => {
+                // Toggles between fitting and actual size
+                if let Some(viewer) = viewer(window) {
+                    let zoom = if viewer.zoom.is_some() { None } else { Some(1.0) };
+                    set_zoom(window, zoom, Some(point_from_lparam(lparam)));
+                }
+                LRESULT(0)
+            }
+            WM_LBUTTONDOWN => {
+                if let Some(viewer) = viewer(window) {
+                    viewer.drag = Some((point_from_lparam(lparam), viewer.pan_x, viewer.pan_y));
+                    SetCapture(window);
+                }
+                LRESULT(0)
+            }
+            WM_MOUSEMOVE => {
+                if let Some(viewer) = viewer(window) {
+                    if let Some((start, pan_x, pan_y)) = viewer.drag {
+                        let point = point_from_lparam(lparam);
+                        viewer.pan_x = pan_x + point.x - start.x;
+                        viewer.pan_y = pan_y + point.y - start.y;
+                        InvalidateRect(window, None, FALSE);
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_LBUTTONUP => {
+                if let Some(viewer) = viewer(window) {
+                    if viewer.drag.take().is_some() {
+                        let _ = ReleaseCapture();
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_CAPTURECHANGED => {
+                if let Some(viewer) = viewer(window) {
+                    viewer.drag = None;
+                }
+                LRESULT(0)
+            }
+            WM_XBUTTONUP => {
+                // Mouse back and forward buttons
+                let button = ((wparam.0 >> 16) & 0xFFFF) as u16;
+                step(window, if button == XBUTTON1 { -1 } else { 1 });
+                LRESULT(1)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(window);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                let viewer = SetWindowLongPtrW(window, GWLP_USERDATA, 0) as *mut ImageViewer;
+                if !viewer.is_null() {
+                    drop(Box::from_raw(viewer));
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
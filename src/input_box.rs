@@ -0,0 +1,182 @@
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{HBRUSH, HFONT, COLOR_BTNFACE},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Controls::EM_SETSEL,
+            Input::KeyboardAndMouse::{EnableWindow, SetFocus},
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::lang::get_strings;
+use crate::logger::log_error;
+use crate::thumbnail::to_wide;
+
+const INPUT_BOX_CLASS: &str = "EverythingLikeInputBox";
+
+const ID_INPUT_TEXT: i32 = 9301;
+
+struct InputBoxState {
+    done: bool,
+    result: Option<String>,
+}
+
+// The window's InputBoxState, kept in GWLP_USERDATA while the prompt's
+// modal loop runs
+unsafe fn input_box(window: HWND) -> Option<&'static mut InputBoxState> {
+    let state = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut InputBoxState;
+    state.as_mut()
+}
+
+pub fn register_input_box_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(input_box_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as isize),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeInputBox"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// Asks for a single line of text. Returns None when cancelled or left empty.
+pub fn prompt_text(owner: HWND, title: &str, label: &str, initial: &str, font: HFONT) -> Option<String> {
+    unsafe {
+        let strings = get_strings();
+        let instance = HINSTANCE(GetModuleHandleW(None).ok()?.0);
+        let (width, height) = (380, 150);
+
+        let mut owner_rect = RECT::default();
+        let _ = GetWindowRect(owner, &mut owner_rect);
+        let x = owner_rect.left + ((owner_rect.right - owner_rect.left) - width) / 2;
+        let y = owner_rect.top + ((owner_rect.bottom - owner_rect.top) - height) / 2;
+
+        let window = CreateWindowExW(
+            WS_EX_DLGMODALFRAME,
+            PCWSTR::from_raw(to_wide(INPUT_BOX_CLASS).as_ptr()),
+            PCWSTR::from_raw(to_wide(title).as_ptr()),
+            WS_POPUP | WS_CAPTION | WS_SYSMENU | WS_VISIBLE,
+            x.max(0),
+            y.max(0),
+            width,
+            height,
+            owner,
+            None,
+            instance,
+            None,
+        );
+
+        if window.0 == 0 {
+            log_error("Failed to create input box");
+            return None;
+        }
+
+        let mut client_rect = RECT::default();
+        let _ = GetClientRect(window, &mut client_rect);
+        let client_width = client_rect.right;
+
+        let controls = [
+            (w!("STATIC"), label.to_string(), WINDOW_STYLE(0), WINDOW_EX_STYLE::default(), -1, 10, 10, client_width - 20, 20),
+            (w!("EDIT"), initial.to_string(), WS_TABSTOP | WINDOW_STYLE(ES_AUTOHSCROLL as u32), WS_EX_CLIENTEDGE, ID_INPUT_TEXT, 10, 34, client_width - 20, 24),
+            (w!("BUTTON"), strings.settings_ok.clone(), WS_TABSTOP | WINDOW_STYLE(BS_DEFPUSHBUTTON as u32), WINDOW_EX_STYLE::default(), IDOK.0, client_width - 180, 72, 80, 26),
+            (w!("BUTTON"), strings.settings_cancel.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), WINDOW_EX_STYLE::default(), IDCANCEL.0, client_width - 90, 72, 80, 26),
+        ];
+        for (class, text, style, ex_style, id, x, y, width, height) in controls {
+            let control = CreateWindowExW(
+                ex_style,
+                class,
+                PCWSTR::from_raw(to_wide(&text).as_ptr()),
+                WS_CHILD | WS_VISIBLE | style,
+                x, y, width, height,
+                window,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(control, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        }
+
+        let edit = GetDlgItem(window, ID_INPUT_TEXT);
+        SendMessageW(edit, EM_SETSEL, WPARAM(0), LPARAM(-1));
+        let _ = SetFocus(edit);
+
+        let state = Box::into_raw(Box::new(InputBoxState { done: false, result: None }));
+        SetWindowLongPtrW(window, GWLP_USERDATA, state as isize);
+        EnableWindow(owner, FALSE);
+
+        // Local message loop; IsDialogMessage maps Enter/Esc to IDOK/IDCANCEL
+        let mut message = MSG::default();
+        while input_box(window).is_some_and(|state| !state.done) {
+            if !GetMessageW(&mut message, None, 0, 0).as_bool() {
+                // Let the main loop see the quit message too
+                PostQuitMessage(message.wParam.0 as i32);
+                break;
+            }
+            if !IsDialogMessageW(window, &message).as_bool() {
+                TranslateMessage(&message);
+                DispatchMessageW(&message);
+            }
+        }
+
+        EnableWindow(owner, TRUE);
+        if IsWindow(window).as_bool() {
+            SetWindowLongPtrW(window, GWLP_USERDATA, 0);
+            let _ = DestroyWindow(window);
+        }
+        let _ = SetForegroundWindow(owner);
+
+        Box::from_raw(state).result
+    }
+}
+
+extern "system" fn input_box_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_COMMAND => {
+                let id = (wparam.0 & 0xFFFF) as i32;
+                if id == IDOK.0 || id == IDCANCEL.0 {
+                    if let Some(state) = input_box(window) {
+                        if id == IDOK.0 {
+                            let mut buffer: [u16; 260] = [0; 260];
+                            let len = GetWindowTextW(GetDlgItem(window, ID_INPUT_TEXT), &mut buffer);
+                            let text = String::from_utf16_lossy(&buffer[..len.max(0) as usize]).trim().to_string();
+                            state.result = if text.is_empty() { None } else { Some(text) };
+                        }
+                        state.done = true;
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                if let Some(state) = input_box(window) {
+                    state.done = true;
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::E_FAIL,
+        Storage::EnhancedStorage::PKEY_Title,
+        System::{
+            Com::*,
+            Com::StructuredStorage::{PROPVARIANT, PropVariantClear},
+            Variant::VT_LPWSTR,
+        },
+        UI::Shell::{
+            Common::{IObjectArray, IObjectCollection},
+            PropertiesSystem::IPropertyStore,
+            *,
+        },
+    },
+};
+use crate::logger::log_error;
+use crate::shell_verb::{argument_value, quote_argument};
+use crate::thumbnail::to_wide;
+
+// Command line arguments the jump list entries start the app with
+const SEARCH_ARG: &str = "--search";
+const OPEN_LIST_ARG: &str = "--open-list";
+
+// dwData of the WM_COPYDATA that hands a jump list entry to the running
+// instance, next to shell_verb's COPYDATA_SEARCH_IN
+pub const COPYDATA_SEARCH: usize = 2;
+pub const COPYDATA_OPEN_LIST: usize = 3;
+
+// Entries per category; the taskbar shows fewer when space is short
+const MAX_CATEGORY_ITEMS: usize = 10;
+
+// The query passed with --search, if the app was started from the jump list
+pub fn search_argument() -> Option<String> {
+    argument_value(SEARCH_ARG)
+}
+
+// The list file passed with --open-list
+pub fn open_list_argument() -> Option<String> {
+    argument_value(OPEN_LIST_ARG)
+}
+
+pub struct JumpListCategory {
+    pub title: String,
+    pub items: Vec<JumpListItem>,
+}
+
+// A link that starts the app with `arguments`
+pub struct JumpListItem {
+    title: String,
+    tooltip: String,
+    arguments: String,
+}
+
+impl JumpListItem {
+    // Entry that reruns `query`
+    pub fn search(query: &str) -> Self {
+        Self {
+            title: query.to_string(),
+            tooltip: query.to_string(),
+            arguments: format!("{} {}", SEARCH_ARG, quote_argument(query)),
+        }
+    }
+
+    // Entry that opens the list file at `path`
+    pub fn open_list(path: &str) -> Self {
+        let title = std::path::Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string());
+        Self {
+            title,
+            tooltip: path.to_string(),
+            arguments: format!("{} {}", OPEN_LIST_ARG, quote_argument(path)),
+        }
+    }
+}
+
+// Replaces the taskbar jump list with `categories`. Entries the user
+// removed from the jump list are left out, since the shell refuses a list
+// that brings them back.
+pub fn update_jump_list(categories: &[JumpListCategory]) -> Result<()> {
+    unsafe {
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+
+        let list: ICustomDestinationList = CoCreateInstance(&DestinationList, None, CLSCTX_INPROC_SERVER)?;
+        let mut min_slots = 0;
+        let removed: IObjectArray = list.BeginList(&mut min_slots)?;
+        let removed_arguments = link_arguments(&removed);
+
+        let exe_path = std::env::current_exe().map_err(|_| Error::from(E_FAIL))?;
+        let exe_path = exe_path.to_string_lossy().to_string();
+
+        for category in categories {
+            let collection: IObjectCollection = CoCreateInstance(&EnumerableObjectCollection, None, CLSCTX_INPROC_SERVER)?;
+            let mut count = 0;
+            for item in category.items.iter().take(MAX_CATEGORY_ITEMS) {
+                if removed_arguments.contains(&item.arguments) {
+                    continue;
+                }
+                collection.AddObject(&create_link(&exe_path, item)?)?;
+                count += 1;
+            }
+            if count == 0 {
+                continue;
+            }
+
+            let objects: IObjectArray = collection.cast()?;
+            let title_wide = to_wide(&category.title);
+            // Fails when the user turned off recent items for Start and the
+            // taskbar; the other categories can still go in
+            if let Err(e) = list.AppendCategory(PCWSTR::from_raw(title_wide.as_ptr()), &objects) {
+                log_error(&format!("Failed to add jump list category '{}': {}", category.title, e));
+            }
+        }
+
+        list.CommitList()
+    }
+}
+
+unsafe fn create_link(exe_path: &str, item: &JumpListItem) -> Result<IShellLinkW> {
+    let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+    link.SetPath(PCWSTR::from_raw(to_wide(exe_path).as_ptr()))?;
+    link.SetArguments(PCWSTR::from_raw(to_wide(&item.arguments).as_ptr()))?;
+    link.SetDescription(PCWSTR::from_raw(to_wide(&item.tooltip).as_ptr()))?;
+    link.SetIconLocation(PCWSTR::from_raw(to_wide(exe_path).as_ptr()), 0)?;
+
+    // The jump list shows the link's title property, not its file name
+    let store: IPropertyStore = link.cast()?;
+    let mut value = PROPVARIANT::default();
+    let inner = &mut *value.Anonymous.Anonymous;
+    inner.vt = VT_LPWSTR;
+    inner.Anonymous.pwszVal = SHStrDupW(PCWSTR::from_raw(to_wide(&item.title).as_ptr()))?;
+    let result = store.SetValue(&PKEY_Title, &value).and_then(|_| store.Commit());
+    let _ = PropVariantClear(&mut value);
+    result?;
+
+    Ok(link)
+}
+
+// Arguments of the links in `objects`, used to recognise removed entries
+unsafe fn link_arguments(objects: &IObjectArray) -> Vec<String> {
+    let count = objects.GetCount().unwrap_or(0);
+    (0..count)
+        .filter_map(|index| objects.GetAt::<IShellLinkW>(index).ok())
+        .filter_map(|link| {
+            let mut buffer: [u16; 1024] = [0; 1024];
+            link.GetArguments(&mut buffer).ok()?;
+            let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+            Some(String::from_utf16_lossy(&buffer[..len]))
+        })
+        .collect()
+}
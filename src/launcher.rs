@@ -0,0 +1,410 @@
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::*,
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Controls::{DRAWITEMSTRUCT, ODS_SELECTED},
+            Input::KeyboardAndMouse::*,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::everything_sdk::FileResult;
+use crate::file_icons::{draw_icon, get_default_file_icon, get_file_icon, get_folder_icon};
+use crate::logger::log_error;
+use crate::thumbnail::to_wide;
+
+// Sent to the owner window whenever the launcher's text changes; WPARAM is
+// the query as a boxed String. The owner answers with set_launcher_results.
+pub const WM_LAUNCHER_QUERY: u32 = WM_USER + 137;
+// Sent to the owner window to open a result; WPARAM is its path as a boxed String
+pub const WM_LAUNCHER_OPEN: u32 = WM_USER + 138;
+
+// Results shown below the search box
+pub const LAUNCHER_RESULTS: usize = 10;
+
+const LAUNCHER_CLASS: &str = "EverythingLikeLauncher";
+
+const LAUNCHER_WIDTH: i32 = 600;
+const EDIT_HEIGHT: i32 = 30;
+const ROW_HEIGHT: i32 = 28;
+const ICON_SIZE: i32 = 16;
+const MARGIN: i32 = 6;
+
+const ID_LAUNCHER_TEXT: i32 = 9711;
+const ID_LAUNCHER_LIST: i32 = 9712;
+
+struct Launcher {
+    edit: HWND,
+    list: HWND,
+    owner: HWND,
+    // The rows of the list, best first
+    rows: Vec<FileResult>,
+}
+
+// The window's Launcher, kept in GWLP_USERDATA
+unsafe fn launcher(window: HWND) -> Option<&'static mut Launcher> {
+    let launcher = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut Launcher;
+    launcher.as_mut()
+}
+
+// The launcher is created on first use and hidden, not destroyed, afterwards
+unsafe fn existing_launcher() -> Option<HWND> {
+    let existing = FindWindowW(PCWSTR::from_raw(to_wide(LAUNCHER_CLASS).as_ptr()), None);
+    (existing.0 != 0).then_some(existing)
+}
+
+pub fn register_launcher_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(launcher_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH((COLOR_WINDOW.0 + 1) as isize),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeLauncher"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// Borderless search box with the best few results below it, centered on
+// the screen the cursor is on. Opened and closed from the tray menu or the
+// launcher hotkey; Escape or clicking elsewhere hides it too.
+pub fn toggle_launcher(owner: HWND, font: HFONT) {
+    unsafe {
+        let window = match existing_launcher() {
+            Some(window) if IsWindowVisible(window).as_bool() => {
+                ShowWindow(window, SW_HIDE);
+                return;
+            }
+            Some(window) => window,
+            None => match create_launcher(owner, font) {
+                Some(window) => window,
+                None => return,
+            },
+        };
+        let Some(edit) = launcher(window).map(|launcher| launcher.edit) else {
+            return;
+        };
+
+        let mut cursor = POINT::default();
+        let _ = GetCursorPos(&mut cursor);
+        let mut monitor_info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        let _ = GetMonitorInfoW(MonitorFromPoint(cursor, MONITOR_DEFAULTTONEAREST), &mut monitor_info);
+        let work_area = monitor_info.rcWork;
+        let x = work_area.left + (work_area.right - work_area.left - LAUNCHER_WIDTH) / 2;
+        let y = work_area.top + (work_area.bottom - work_area.top) / 4;
+
+        // Start empty; the edit notification asks the owner to drop its search
+        set_launcher_results(Vec::new());
+        let _ = SetWindowTextW(edit, w!(""));
+        let _ = SetWindowPos(window, HWND_TOPMOST, x, y, 0, 0, SWP_NOSIZE | SWP_SHOWWINDOW);
+        let _ = SetForegroundWindow(window);
+        SetFocus(edit);
+    }
+}
+
+// Replaces the rows with `results`, already in order, and fits the window
+// around them
+pub fn set_launcher_results(results: Vec<FileResult>) {
+    unsafe {
+        let Some(window) = existing_launcher() else {
+            return;
+        };
+        let Some(launcher) = launcher(window) else {
+            return;
+        };
+        let count = results.len().min(LAUNCHER_RESULTS);
+
+        // The rows are in place before the list asks to draw them
+        launcher.rows = results.into_iter().take(count).collect();
+        SendMessageW(launcher.list, LB_RESETCONTENT, WPARAM(0), LPARAM(0));
+        for row in &launcher.rows {
+            // The name is what screen readers announce
+            let name = to_wide(row.name());
+            SendMessageW(launcher.list, LB_ADDSTRING, WPARAM(0), LPARAM(name.as_ptr() as isize));
+        }
+        if count > 0 {
+            SendMessageW(launcher.list, LB_SETCURSEL, WPARAM(0), LPARAM(0));
+        }
+
+        fit_to_rows(window, launcher.list, count as i32);
+    }
+}
+
+unsafe fn fit_to_rows(window: HWND, list: HWND, rows: i32) {
+    let list_height = rows * ROW_HEIGHT;
+    let client_height = MARGIN + EDIT_HEIGHT + MARGIN + if rows > 0 { list_height + MARGIN } else { 0 };
+    let border = GetSystemMetrics(SM_CYBORDER) * 2;
+
+    ShowWindow(list, if rows > 0 { SW_SHOWNA } else { SW_HIDE });
+    let _ = SetWindowPos(list, None, MARGIN, MARGIN * 2 + EDIT_HEIGHT, LAUNCHER_WIDTH - MARGIN * 2 - border, list_height, SWP_NOZORDER | SWP_NOACTIVATE);
+    let _ = SetWindowPos(window, None, 0, 0, LAUNCHER_WIDTH, client_height + border, SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE);
+}
+
+unsafe fn create_launcher(owner: HWND, font: HFONT) -> Option<HWND> {
+    let instance = HINSTANCE(GetModuleHandleW(None).ok()?.0);
+
+    let window = CreateWindowExW(
+        WS_EX_TOOLWINDOW | WS_EX_TOPMOST,
+        PCWSTR::from_raw(to_wide(LAUNCHER_CLASS).as_ptr()),
+        w!(""),
+        WS_POPUP | WS_BORDER,
+        0,
+        0,
+        LAUNCHER_WIDTH,
+        EDIT_HEIGHT + MARGIN * 2,
+        None,
+        None,
+        instance,
+        None,
+    );
+    if window.0 == 0 {
+        log_error("Failed to create launcher window");
+        return None;
+    }
+
+    let mut client_rect = RECT::default();
+    let _ = GetClientRect(window, &mut client_rect);
+    let edit = CreateWindowExW(
+        WS_EX_CLIENTEDGE,
+        w!("EDIT"),
+        w!(""),
+        WS_CHILD | WS_VISIBLE | WINDOW_STYLE(ES_AUTOHSCROLL as u32),
+        MARGIN,
+        MARGIN,
+        client_rect.right - MARGIN * 2,
+        EDIT_HEIGHT,
+        window,
+        HMENU(ID_LAUNCHER_TEXT as isize),
+        instance,
+        None,
+    );
+    SendMessageW(edit, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+
+    let list = CreateWindowExW(
+        WINDOW_EX_STYLE::default(),
+        w!("LISTBOX"),
+        w!(""),
+        WS_CHILD | WINDOW_STYLE((LBS_NOTIFY | LBS_NOINTEGRALHEIGHT | LBS_OWNERDRAWFIXED | LBS_HASSTRINGS) as u32),
+        MARGIN,
+        MARGIN * 2 + EDIT_HEIGHT,
+        client_rect.right - MARGIN * 2,
+        0,
+        window,
+        HMENU(ID_LAUNCHER_LIST as isize),
+        instance,
+        None,
+    );
+    SendMessageW(list, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+    SendMessageW(list, LB_SETITEMHEIGHT, WPARAM(0), LPARAM(ROW_HEIGHT as isize));
+
+    // Arrow keys, Enter and Escape need handling in the edit itself; the
+    // original procedure is kept in its GWLP_USERDATA
+    let original_proc = SetWindowLongPtrW(edit, GWLP_WNDPROC, launcher_edit_proc as *const () as isize);
+    SetWindowLongPtrW(edit, GWLP_USERDATA, original_proc);
+
+    let launcher = Box::new(Launcher { edit, list, owner, rows: Vec::new() });
+    SetWindowLongPtrW(window, GWLP_USERDATA, Box::into_raw(launcher) as isize);
+    Some(window)
+}
+
+extern "system" fn launcher_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_ACTIVATE => {
+                if (wparam.0 & 0xFFFF) as u32 == WA_INACTIVE {
+                    ShowWindow(window, SW_HIDE);
+                }
+                LRESULT(0)
+            }
+            WM_SETFOCUS => {
+                if let Some(launcher) = launcher(window) {
+                    SetFocus(launcher.edit);
+                }
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                let id = (wparam.0 & 0xFFFF) as i32;
+                let code = ((wparam.0 >> 16) & 0xFFFF) as u32;
+                match (id, code) {
+                    (ID_LAUNCHER_TEXT, EN_CHANGE) => send_query(window),
+                    (ID_LAUNCHER_LIST, LBN_DBLCLK) => open_selected(window),
+                    // Keep typing in the search box after clicking a row
+                    (ID_LAUNCHER_LIST, LBN_SELCHANGE) => {
+                        if let Some(launcher) = launcher(window) {
+                            SetFocus(launcher.edit);
+                        }
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_DRAWITEM => {
+                let draw_item = &*(lparam.0 as *const DRAWITEMSTRUCT);
+                draw_row(window, draw_item);
+                LRESULT(1)
+            }
+            WM_DESTROY => {
+                let launcher = SetWindowLongPtrW(window, GWLP_USERDATA, 0) as *mut Launcher;
+                if !launcher.is_null() {
+                    drop(Box::from_raw(launcher));
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
+
+// The icon and name, with the folder in gray after it
+unsafe fn draw_row(window: HWND, draw_item: &DRAWITEMSTRUCT) {
+    let Some(row) = launcher(window).and_then(|launcher| launcher.rows.get(draw_item.itemID as usize)) else {
+        return;
+    };
+    let hdc = draw_item.hDC;
+    let mut rect = draw_item.rcItem;
+    let selected = draw_item.itemState.0 & ODS_SELECTED.0 != 0;
+
+    let (background, text_color) = if selected {
+        (COLOR_HIGHLIGHT, GetSysColor(COLOR_HIGHLIGHTTEXT))
+    } else {
+        (COLOR_WINDOW, GetSysColor(COLOR_WINDOWTEXT))
+    };
+    FillRect(hdc, &rect, GetSysColorBrush(background));
+    SetBkMode(hdc, TRANSPARENT);
+
+    let icon = if row.is_folder {
+        get_folder_icon(true)
+    } else {
+        get_file_icon(row.path(), true).or_else(|| get_default_file_icon(true))
+    };
+    if let Some(icon) = icon {
+        draw_icon(hdc, icon, rect.left + MARGIN, rect.top + (ROW_HEIGHT - ICON_SIZE) / 2, ICON_SIZE);
+    }
+    rect.left += MARGIN * 2 + ICON_SIZE;
+    rect.right -= MARGIN;
+
+    // The name gets the room it needs, the folder whatever is left
+    let mut name = row.name().encode_utf16().collect::<Vec<u16>>();
+    let mut name_rect = rect;
+    DrawTextW(hdc, &mut name, &mut name_rect, DT_SINGLELINE | DT_CALCRECT | DT_NOPREFIX);
+    name_rect.right = name_rect.right.min(rect.right);
+    name_rect.top = rect.top;
+    name_rect.bottom = rect.bottom;
+    SetTextColor(hdc, COLORREF(text_color));
+    DrawTextW(hdc, &mut name, &mut name_rect, DT_SINGLELINE | DT_VCENTER | DT_NOPREFIX | DT_END_ELLIPSIS);
+
+    let folder = row.path().strip_suffix(row.name()).unwrap_or("").trim_end_matches('\\');
+    let mut folder: Vec<u16> = folder.encode_utf16().collect();
+    let mut folder_rect = RECT { left: name_rect.right + MARGIN * 2, ..rect };
+    if folder_rect.left < folder_rect.right {
+        let gray = if selected { text_color } else { GetSysColor(COLOR_GRAYTEXT) };
+        SetTextColor(hdc, COLORREF(gray));
+        DrawTextW(hdc, &mut folder, &mut folder_rect, DT_SINGLELINE | DT_VCENTER | DT_NOPREFIX | DT_PATH_ELLIPSIS);
+    }
+}
+
+extern "system" fn launcher_edit_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_KEYDOWN if wparam.0 == VK_RETURN.0 as usize => {
+                open_selected(GetParent(window));
+                return LRESULT(0);
+            }
+            WM_KEYDOWN if wparam.0 == VK_ESCAPE.0 as usize => {
+                ShowWindow(GetParent(window), SW_HIDE);
+                return LRESULT(0);
+            }
+            WM_KEYDOWN if wparam.0 == VK_UP.0 as usize || wparam.0 == VK_DOWN.0 as usize => {
+                move_selection(GetParent(window), if wparam.0 == VK_UP.0 as usize { -1 } else { 1 });
+                return LRESULT(0);
+            }
+            // Swallow the characters of Enter and Escape so the edit doesn't beep
+            WM_CHAR if wparam.0 == 0x0D || wparam.0 == 0x1B => {
+                return LRESULT(0);
+            }
+            _ => {}
+        }
+
+        let original_proc: WNDPROC = std::mem::transmute(GetWindowLongPtrW(window, GWLP_USERDATA));
+        if original_proc.is_some() {
+            CallWindowProcW(original_proc, window, message, wparam, lparam)
+        } else {
+            DefWindowProcW(window, message, wparam, lparam)
+        }
+    }
+}
+
+unsafe fn move_selection(window: HWND, step: i32) {
+    let Some(launcher) = launcher(window) else {
+        return;
+    };
+    let count = SendMessageW(launcher.list, LB_GETCOUNT, WPARAM(0), LPARAM(0)).0 as i32;
+    if count <= 0 {
+        return;
+    }
+    let current = SendMessageW(launcher.list, LB_GETCURSEL, WPARAM(0), LPARAM(0)).0 as i32;
+    let next = (current + step).clamp(0, count - 1);
+    SendMessageW(launcher.list, LB_SETCURSEL, WPARAM(next as usize), LPARAM(0));
+}
+
+unsafe fn send_query(window: HWND) {
+    let Some(launcher) = launcher(window) else {
+        return;
+    };
+
+    let mut buffer: [u16; 1024] = [0; 1024];
+    let len = GetWindowTextW(launcher.edit, &mut buffer);
+    let query = String::from_utf16_lossy(&buffer[..len.max(0) as usize]).trim().to_string();
+
+    let query_ptr = Box::into_raw(Box::new(query)) as usize;
+    if PostMessageW(launcher.owner, WM_LAUNCHER_QUERY, WPARAM(query_ptr), LPARAM(0)).is_err() {
+        drop(Box::from_raw(query_ptr as *mut String));
+    }
+}
+
+// Hands the selected row, or the first one, to the owner to open
+unsafe fn open_selected(window: HWND) {
+    let Some(launcher) = launcher(window) else {
+        return;
+    };
+    let selected = SendMessageW(launcher.list, LB_GETCURSEL, WPARAM(0), LPARAM(0)).0.max(0) as usize;
+    let Some(path) = launcher.rows.get(selected).map(|row| row.path().to_string()) else {
+        return;
+    };
+
+    ShowWindow(window, SW_HIDE);
+    let path_ptr = Box::into_raw(Box::new(path)) as usize;
+    if PostMessageW(launcher.owner, WM_LAUNCHER_OPEN, WPARAM(path_ptr), LPARAM(0)).is_err() {
+        drop(Box::from_raw(path_ptr as *mut String));
+    }
+}
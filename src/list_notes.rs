@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+use crate::csv::parse_csv_line;
+
+// Comment at the top of saved lists naming their columns. The "Note"
+// column is what tells a list with notes from an older one.
+pub const LIST_FORMAT_HEADER: &str = "# Format: \"Path\",Size,Modified,\"Note\"";
+
+// Free-text notes written on the entries of the open list. They belong to
+// the list rather than the files, so they are saved with it as an extra
+// column instead of going in a database like tags.
+#[derive(Default, Clone)]
+pub struct ListNotes {
+    // (path as noted, note) per lowercased path
+    notes: HashMap<String, (String, String)>,
+}
+
+impl ListNotes {
+    pub fn note_of(&self, path: &str) -> Option<&str> {
+        self.notes.get(&path.to_lowercase()).map(|(_, note)| note.as_str())
+    }
+
+    // Gives `path` the note, or takes its note away when the note is empty
+    pub fn set(&mut self, path: &str, note: &str) {
+        // One line, so the note fits in its cell and its CSV field
+        let note = note.split_whitespace().collect::<Vec<_>>().join(" ");
+        if note.is_empty() {
+            self.notes.remove(&path.to_lowercase());
+        } else {
+            self.notes.insert(path.to_lowercase(), (path.to_string(), note));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.notes.is_empty()
+    }
+}
+
+// Which field of a list's lines holds the note, going by its header: the
+// format comment of lists saved here, or the column names of an EFU file
+pub fn note_column(header: &str) -> Option<usize> {
+    let columns = header.strip_prefix("# Format:").unwrap_or(header);
+    parse_csv_line(columns.trim())
+        .iter()
+        .position(|column| column.trim().eq_ignore_ascii_case("note"))
+}
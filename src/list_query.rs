@@ -0,0 +1,553 @@
+use crate::everything_sdk::FileResult;
+use crate::list_notes::ListNotes;
+use crate::tags::{TagStore, MAX_RATING};
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone};
+use std::time::SystemTime;
+
+// Filtering for list mode, following Everything's search syntax closely
+// enough that the same query gives the same results in both modes:
+//
+//   foo bar        both terms (AND)          foo|bar      either term (OR)
+//   !foo, NOT foo  exclude term              <foo|bar> x  grouping
+//   "a b"          phrase with spaces        *.jpg, a?c   wildcards (whole name)
+//   ext:jpg;png    extension list            path:dir     substring of full path
+//   size:>1mb      size with < <= > >= = and ranges (1mb..5mb)
+//   dm:today       date modified: today, yesterday, thisweek, thismonth,
+//                  thisyear, lastweek, lastmonth, lastyear, 2024, 2024-05,
+//                  2024-05-17, with the same comparisons and ranges as size:
+//   tag:work;todo  any of the tags           tag:         any tag at all
+//   note:keeper    note of the entry         note:        any note at all
+//   rating:>=4     stars given, 0 to 5, with the same comparisons as size:
+//
+// As in Everything, OR binds tighter than AND, and terms containing a
+// path separator are matched against the full path instead of the name.
+pub struct ListQuery {
+    root: Option<Node>,
+}
+
+enum Node {
+    And(Vec<Node>),
+    Or(Vec<Node>),
+    Not(Box<Node>),
+    // The term and how it was written, to pass on to Everything
+    Term(Term, String),
+}
+
+enum Term {
+    Text { text: String, match_path: bool },
+    Wildcard { pattern: Vec<char>, match_path: bool },
+    Path(String),
+    Extension(Vec<String>),
+    Size(Comparison<u64>),
+    Modified(Comparison<SystemTime>),
+    Tag(Vec<String>),
+    Note(String),
+    Rating(Comparison<u8>),
+}
+
+// Values are half-open ranges [start, end) so "dm:2024" or "size:1kb" can
+// be compared the same way as single points
+enum Comparison<T> {
+    Within(T, T),
+    Less(T),
+    LessOrEqual(T),
+    Greater(T),
+    GreaterOrEqual(T),
+}
+
+impl<T: PartialOrd + Copy> Comparison<T> {
+    fn matches(&self, value: T) -> bool {
+        match *self {
+            Comparison::Within(start, end) => value >= start && value < end,
+            Comparison::Less(start) => value < start,
+            Comparison::LessOrEqual(end) => value < end,
+            Comparison::Greater(end) => value >= end,
+            Comparison::GreaterOrEqual(start) => value >= start,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Phrase(String),
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl ListQuery {
+    pub fn parse(query: &str) -> Self {
+        let tokens = tokenize(query);
+        let mut position = 0;
+        let mut parts = Vec::new();
+
+        while position < tokens.len() {
+            if let Some(node) = parse_and(&tokens, &mut position) {
+                parts.push(node);
+            }
+            // Step over the unmatched '>' that ended parse_and early
+            position += 1;
+        }
+
+        let root = if parts.len() > 1 { Some(Node::And(parts)) } else { parts.pop() };
+        Self { root }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    // size: and dm: need file metadata, which list entries load lazily
+    pub fn needs_metadata(&self) -> bool {
+        fn visit(node: &Node) -> bool {
+            match node {
+                Node::And(children) | Node::Or(children) => children.iter().any(visit),
+                Node::Not(child) => visit(child),
+                Node::Term(Term::Size(_) | Term::Modified(_), _) => true,
+                Node::Term(..) => false,
+            }
+        }
+        self.root.as_ref().is_some_and(visit)
+    }
+
+    // tag: and rating: aren't Everything syntax, so searches using them are
+    // filtered here instead
+    pub fn mentions_tags(&self) -> bool {
+        self.root.as_ref().is_some_and(mentions_tags)
+    }
+
+    // Whether a file without tags or stars can match, as with rating:0 or
+    // !tag:work. When none can, only the tagged and rated files need looking
+    // at.
+    pub fn can_match_unmarked(&self) -> bool {
+        // Some(matches) where tags and ratings decide, None where the rest
+        // of the file does
+        fn visit(node: &Node) -> Option<bool> {
+            match node {
+                Node::And(children) => {
+                    let results: Vec<Option<bool>> = children.iter().map(visit).collect();
+                    if results.contains(&Some(false)) {
+                        Some(false)
+                    } else if results.iter().all(|result| *result == Some(true)) {
+                        Some(true)
+                    } else {
+                        None
+                    }
+                }
+                Node::Or(children) => {
+                    let results: Vec<Option<bool>> = children.iter().map(visit).collect();
+                    if results.contains(&Some(true)) {
+                        Some(true)
+                    } else if results.iter().all(|result| *result == Some(false)) {
+                        Some(false)
+                    } else {
+                        None
+                    }
+                }
+                Node::Not(child) => visit(child).map(|matches| !matches),
+                Node::Term(Term::Tag(_), _) => Some(false),
+                Node::Term(Term::Rating(comparison), _) => Some(comparison.matches(0)),
+                Node::Term(..) => None,
+            }
+        }
+        self.root.as_ref().is_none_or(|root| visit(root) != Some(false))
+    }
+
+    // The query for Everything with the tag: and rating: terms left out, so
+    // that it finds every file that can match and then some; the results
+    // still need `matches`. Empty when that is every file.
+    pub fn everything_query(&self) -> String {
+        fn visit(node: &Node) -> Option<String> {
+            match node {
+                Node::And(children) => {
+                    let parts: Vec<String> = children.iter().filter_map(visit).collect();
+                    (!parts.is_empty()).then(|| format!("<{}>", parts.join(" ")))
+                }
+                // One part that could be anything makes all of them so
+                Node::Or(children) => children
+                    .iter()
+                    .map(visit)
+                    .collect::<Option<Vec<String>>>()
+                    .map(|parts| format!("<{}>", parts.join("|"))),
+                Node::Not(child) if mentions_tags(child) => None,
+                Node::Not(child) => visit(child).map(|text| format!("!{}", text)),
+                Node::Term(Term::Tag(_) | Term::Rating(_), _) => None,
+                Node::Term(_, source) => Some(source.clone()),
+            }
+        }
+        self.root.as_ref().and_then(visit).unwrap_or_default()
+    }
+
+    pub fn matches(&self, file: &FileResult, tags: &TagStore, notes: &ListNotes) -> bool {
+        match &self.root {
+            Some(root) => matches_node(root, file, tags, notes),
+            None => true,
+        }
+    }
+}
+
+fn tokenize(query: &str) -> Vec<Token> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '|' {
+            tokens.push(Token::Or);
+            i += 1;
+        } else if c == '!' {
+            tokens.push(Token::Not);
+            i += 1;
+        } else if c == '<' {
+            tokens.push(Token::Open);
+            i += 1;
+        } else if c == '>' {
+            tokens.push(Token::Close);
+            i += 1;
+        } else if c == '"' {
+            let end = chars[i + 1..].iter().position(|&c| c == '"').map_or(chars.len(), |p| i + 1 + p);
+            let phrase: String = chars[i + 1..end].iter().collect();
+            if !phrase.is_empty() {
+                tokens.push(Token::Phrase(phrase));
+            }
+            i = end + 1;
+        } else {
+            let mut word = String::new();
+            while i < chars.len() {
+                let c = chars[i];
+                if c.is_whitespace() || c == '|' {
+                    break;
+                }
+                // In "size:>1mb" the '>' is an operator, elsewhere '<' and '>' are groups
+                if (c == '<' || c == '>') && !word.ends_with(':') {
+                    break;
+                }
+                if c == '"' {
+                    // Quoted part inside a word, e.g. path:"program files"
+                    let end = chars[i + 1..].iter().position(|&c| c == '"').map_or(chars.len(), |p| i + 1 + p);
+                    word.extend(&chars[i + 1..end]);
+                    i = end + 1;
+                    continue;
+                }
+                word.push(c);
+                i += 1;
+            }
+
+            match word.as_str() {
+                "AND" => {}
+                "OR" => tokens.push(Token::Or),
+                "NOT" => tokens.push(Token::Not),
+                _ => tokens.push(Token::Word(word)),
+            }
+        }
+    }
+
+    tokens
+}
+
+fn parse_and(tokens: &[Token], position: &mut usize) -> Option<Node> {
+    let mut children = Vec::new();
+
+    while *position < tokens.len() {
+        if tokens[*position] == Token::Close {
+            break;
+        }
+        match parse_or(tokens, position) {
+            Some(node) => children.push(node),
+            // Skip stray operators such as a leading '|'
+            None => *position += 1,
+        }
+    }
+
+    match children.len() {
+        0 => None,
+        1 => children.pop(),
+        _ => Some(Node::And(children)),
+    }
+}
+
+fn parse_or(tokens: &[Token], position: &mut usize) -> Option<Node> {
+    let mut children = vec![parse_unary(tokens, position)?];
+
+    while *position < tokens.len() && tokens[*position] == Token::Or {
+        *position += 1;
+        if let Some(node) = parse_unary(tokens, position) {
+            children.push(node);
+        }
+    }
+
+    if children.len() == 1 {
+        children.pop()
+    } else {
+        Some(Node::Or(children))
+    }
+}
+
+fn parse_unary(tokens: &[Token], position: &mut usize) -> Option<Node> {
+    match tokens.get(*position)? {
+        Token::Not => {
+            *position += 1;
+            parse_unary(tokens, position).map(|node| Node::Not(Box::new(node)))
+        }
+        Token::Open => {
+            *position += 1;
+            let node = parse_and(tokens, position);
+            // Tolerate a missing '>' at the end of the query
+            if tokens.get(*position) == Some(&Token::Close) {
+                *position += 1;
+            }
+            node
+        }
+        Token::Word(word) => {
+            *position += 1;
+            // The tokenizer took the quotes off path:"program files"
+            let source = match word.split_once(':') {
+                Some((function, value)) if value.contains(char::is_whitespace) => format!("{}:\"{}\"", function, value),
+                _ if word.contains(char::is_whitespace) => format!("\"{}\"", word),
+                _ => word.clone(),
+            };
+            Some(Node::Term(parse_term(word), source))
+        }
+        Token::Phrase(phrase) => {
+            *position += 1;
+            Some(Node::Term(text_term(phrase), format!("\"{}\"", phrase)))
+        }
+        Token::Or | Token::Close => None,
+    }
+}
+
+fn parse_term(word: &str) -> Term {
+    if let Some((function, value)) = word.split_once(':') {
+        let value = value.trim();
+        let parsed = match function.to_lowercase().as_str() {
+            "ext" => Some(Term::Extension(
+                value
+                    .split(';')
+                    .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+                    .filter(|e| !e.is_empty())
+                    .collect(),
+            )),
+            "path" => Some(Term::Path(value.to_lowercase())),
+            "size" => parse_comparison(value, parse_size_range).map(Term::Size),
+            "dm" | "datemodified" => parse_comparison(value, parse_date_range).map(Term::Modified),
+            "tag" => Some(Term::Tag(
+                value
+                    .split(';')
+                    .map(|t| t.trim().to_lowercase())
+                    .filter(|t| !t.is_empty())
+                    .collect(),
+            )),
+            "note" => Some(Term::Note(value.to_lowercase())),
+            "rating" => parse_comparison(value, parse_rating_range).map(Term::Rating),
+            _ => None,
+        };
+
+        // Unknown functions and unparsable values fall back to plain text,
+        // which also keeps drive letters like "c:\" working
+        if let Some(term) = parsed {
+            return term;
+        }
+    }
+
+    text_term(word)
+}
+
+fn text_term(text: &str) -> Term {
+    let text = text.to_lowercase();
+    let match_path = text.contains('\\') || text.contains('/');
+    if text.contains('*') || text.contains('?') {
+        Term::Wildcard { pattern: text.chars().collect(), match_path }
+    } else {
+        Term::Text { text, match_path }
+    }
+}
+
+fn parse_comparison<T: Copy>(value: &str, parse_range: fn(&str) -> Option<(T, T)>) -> Option<Comparison<T>> {
+    if let Some((low, high)) = value.split_once("..") {
+        let (start, _) = parse_range(low)?;
+        let (_, end) = parse_range(high)?;
+        return Some(Comparison::Within(start, end));
+    }
+
+    let (operator, operand) = if let Some(rest) = value.strip_prefix(">=") {
+        (">=", rest)
+    } else if let Some(rest) = value.strip_prefix("<=") {
+        ("<=", rest)
+    } else if let Some(rest) = value.strip_prefix('>') {
+        (">", rest)
+    } else if let Some(rest) = value.strip_prefix('<') {
+        ("<", rest)
+    } else {
+        ("=", value.strip_prefix('=').unwrap_or(value))
+    };
+
+    let (start, end) = parse_range(operand.trim())?;
+    Some(match operator {
+        ">=" => Comparison::GreaterOrEqual(start),
+        "<=" => Comparison::LessOrEqual(end),
+        ">" => Comparison::Greater(end),
+        "<" => Comparison::Less(start),
+        _ => Comparison::Within(start, end),
+    })
+}
+
+// "10mb" -> [10 MiB, 10 MiB + 1); units are binary like Everything's
+fn parse_size_range(value: &str) -> Option<(u64, u64)> {
+    let value = value.to_lowercase();
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+
+    let multiplier = match unit.trim() {
+        "" | "b" => 1u64,
+        "k" | "kb" => 1024,
+        "m" | "mb" => 1024 * 1024,
+        "g" | "gb" => 1024 * 1024 * 1024,
+        "t" | "tb" => 1024 * 1024 * 1024 * 1024,
+        _ => return None,
+    };
+
+    let bytes = (number * multiplier as f64) as u64;
+    Some((bytes, bytes + 1))
+}
+
+// A number of stars, 0 for none
+fn parse_rating_range(value: &str) -> Option<(u8, u8)> {
+    let stars: u8 = value.parse().ok()?;
+    (stars <= MAX_RATING).then_some((stars, stars + 1))
+}
+
+// Resolves a date keyword or (partial) date to the local-time range it covers
+fn parse_date_range(value: &str) -> Option<(SystemTime, SystemTime)> {
+    let today = Local::now().date_naive();
+    let value = value.to_lowercase();
+
+    let (start, end) = match value.as_str() {
+        "today" => (today, today + Duration::days(1)),
+        "yesterday" => (today - Duration::days(1), today),
+        "thisweek" | "lastweek" => {
+            let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+            if value == "thisweek" {
+                (monday, today + Duration::days(1))
+            } else {
+                (monday - Duration::days(7), monday)
+            }
+        }
+        "thismonth" => (today.with_day(1)?, today + Duration::days(1)),
+        "lastmonth" => {
+            let first = today.with_day(1)?;
+            let previous = first - Duration::days(1);
+            (previous.with_day(1)?, first)
+        }
+        "thisyear" => (NaiveDate::from_ymd_opt(today.year(), 1, 1)?, today + Duration::days(1)),
+        "lastyear" => (
+            NaiveDate::from_ymd_opt(today.year() - 1, 1, 1)?,
+            NaiveDate::from_ymd_opt(today.year(), 1, 1)?,
+        ),
+        _ => {
+            let parts: Vec<&str> = value.split(|c| c == '-' || c == '/').collect();
+            let year: i32 = parts.first()?.parse().ok()?;
+            match parts.len() {
+                1 => (NaiveDate::from_ymd_opt(year, 1, 1)?, NaiveDate::from_ymd_opt(year + 1, 1, 1)?),
+                2 => {
+                    let month: u32 = parts[1].parse().ok()?;
+                    let start = NaiveDate::from_ymd_opt(year, month, 1)?;
+                    let end = if month == 12 {
+                        NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+                    } else {
+                        NaiveDate::from_ymd_opt(year, month + 1, 1)?
+                    };
+                    (start, end)
+                }
+                3 => {
+                    let day = NaiveDate::from_ymd_opt(year, parts[1].parse().ok()?, parts[2].parse().ok()?)?;
+                    (day, day + Duration::days(1))
+                }
+                _ => return None,
+            }
+        }
+    };
+
+    Some((local_midnight(start)?, local_midnight(end)?))
+}
+
+fn local_midnight(date: NaiveDate) -> Option<SystemTime> {
+    let datetime = Local.from_local_datetime(&date.and_hms_opt(0, 0, 0)?).earliest()?;
+    Some(datetime.into())
+}
+
+fn mentions_tags(node: &Node) -> bool {
+    match node {
+        Node::And(children) | Node::Or(children) => children.iter().any(mentions_tags),
+        Node::Not(child) => mentions_tags(child),
+        Node::Term(Term::Tag(_) | Term::Rating(_), _) => true,
+        Node::Term(..) => false,
+    }
+}
+
+fn matches_node(node: &Node, file: &FileResult, tags: &TagStore, notes: &ListNotes) -> bool {
+    match node {
+        Node::And(children) => children.iter().all(|child| matches_node(child, file, tags, notes)),
+        Node::Or(children) => children.iter().any(|child| matches_node(child, file, tags, notes)),
+        Node::Not(child) => !matches_node(child, file, tags, notes),
+        Node::Term(term, _) => matches_term(term, file, tags, notes),
+    }
+}
+
+fn matches_term(term: &Term, file: &FileResult, tags: &TagStore, notes: &ListNotes) -> bool {
+    match term {
+        Term::Text { text, match_path } => {
+            let subject = if *match_path { file.path() } else { file.name() };
+            subject.to_lowercase().contains(text.as_str())
+        }
+        Term::Wildcard { pattern, match_path } => {
+            let subject = if *match_path { file.path() } else { file.name() };
+            let subject: Vec<char> = subject.to_lowercase().chars().collect();
+            wildcard_match(pattern, &subject)
+        }
+        Term::Path(text) => file.path().to_lowercase().contains(text.as_str()),
+        Term::Extension(extensions) => {
+            let extension = file.extension().to_lowercase();
+            extensions.iter().any(|e| *e == extension)
+        }
+        Term::Size(comparison) => comparison.matches(file.size),
+        Term::Modified(comparison) => {
+            file.modified_time != std::time::UNIX_EPOCH && comparison.matches(file.modified_time)
+        }
+        Term::Tag(names) => {
+            let file_tags = tags.tags_of(file.path());
+            !file_tags.is_empty() && (names.is_empty() || file_tags.iter().any(|tag| names.contains(&tag.to_lowercase())))
+        }
+        Term::Rating(comparison) => comparison.matches(tags.rating_of(file.path())),
+        Term::Note(text) => notes.note_of(file.path()).is_some_and(|note| note.to_lowercase().contains(text.as_str())),
+    }
+}
+
+// '*' matches any run of characters, '?' exactly one; the whole subject must match
+fn wildcard_match(pattern: &[char], subject: &[char]) -> bool {
+    let (mut p, mut s) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while s < subject.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == subject[s]) {
+            p += 1;
+            s += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, s));
+            p += 1;
+        } else if let Some((star_p, star_s)) = backtrack {
+            p = star_p + 1;
+            s = star_s + 1;
+            backtrack = Some((star_p, star_s + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
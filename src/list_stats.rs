@@ -0,0 +1,255 @@
+use crate::config::SizeFormat;
+use crate::everything_sdk::FileResult;
+use crate::lang::LanguageStrings;
+use chrono::Datelike;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use windows::core::PCWSTR;
+use windows::Win32::Globalization::{GetLocaleInfoEx, LOCALE_STHOUSAND};
+
+const TOP_EXTENSIONS: usize = 15;
+const LARGEST_FILES: usize = 10;
+const HISTOGRAM_WIDTH: usize = 40;
+
+// Upper bounds of the size histogram buckets; one more bucket takes
+// everything from 1 GB up
+pub const SIZE_BUCKET_LIMITS: [u64; 6] = [
+    1024,
+    64 * 1024,
+    1024 * 1024,
+    16 * 1024 * 1024,
+    128 * 1024 * 1024,
+    1024 * 1024 * 1024,
+];
+
+// Above this many months the timeline counts whole years
+const TIMELINE_MAX_MONTHS: i32 = 36;
+
+pub struct ListStatistics {
+    pub total_files: usize,
+    pub missing_files: usize,
+    pub total_size: u64,
+    // (extension, file count, total size), most common first
+    pub extensions: Vec<(String, usize, u64)>,
+    // (path, size), largest first
+    pub largest_files: Vec<(String, u64)>,
+    // Files per year of last modification
+    pub years: BTreeMap<i32, usize>,
+    // (file count, total size) per size bucket, see SIZE_BUCKET_LIMITS
+    pub size_buckets: Vec<(usize, u64)>,
+    // (file count, total size) per (year, month) of last modification
+    pub months: BTreeMap<(i32, u32), (usize, u64)>,
+}
+
+pub fn compute_statistics(items: &[FileResult]) -> ListStatistics {
+    // Lists don't carry metadata, so load it in parallel for the missing entries
+    let items: Vec<FileResult> = items
+        .par_iter()
+        .map(|item| {
+            let mut item = item.clone();
+            if !item.missing {
+                item.load_metadata();
+            }
+            item
+        })
+        .collect();
+
+    let mut extension_map: HashMap<String, (usize, u64)> = HashMap::new();
+    let mut years = BTreeMap::new();
+    let mut size_buckets = vec![(0, 0); SIZE_BUCKET_LIMITS.len() + 1];
+    let mut months = BTreeMap::new();
+
+    for item in &items {
+        let entry = extension_map.entry(item.extension().to_lowercase()).or_insert((0, 0));
+        entry.0 += 1;
+        entry.1 += item.size;
+
+        if !item.missing {
+            let bucket = &mut size_buckets[SIZE_BUCKET_LIMITS.iter().take_while(|&&limit| item.size >= limit).count()];
+            bucket.0 += 1;
+            bucket.1 += item.size;
+        }
+
+        if item.modified_time != std::time::UNIX_EPOCH {
+            let datetime: chrono::DateTime<chrono::Local> = item.modified_time.into();
+            *years.entry(datetime.year()).or_insert(0) += 1;
+            let month = months.entry((datetime.year(), datetime.month())).or_insert((0, 0));
+            month.0 += 1;
+            month.1 += item.size;
+        }
+    }
+
+    let mut extensions: Vec<(String, usize, u64)> = extension_map
+        .into_iter()
+        .map(|(extension, (count, size))| (extension, count, size))
+        .collect();
+    extensions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut largest_files: Vec<(String, u64)> = items
+        .iter()
+        .filter(|item| item.size > 0)
+        .map(|item| (item.path().to_string(), item.size))
+        .collect();
+    largest_files.sort_by(|a, b| b.1.cmp(&a.1));
+    largest_files.truncate(LARGEST_FILES);
+
+    ListStatistics {
+        total_files: items.len(),
+        missing_files: items.iter().filter(|item| item.missing).count(),
+        total_size: items.iter().map(|item| item.size).sum(),
+        extensions,
+        largest_files,
+        years,
+        size_buckets,
+        months,
+    }
+}
+
+// "< 64.0 KB" for a size histogram bucket, "≥ 1.0 GB" for the last one
+pub fn size_bucket_label(bucket: usize, strings: &LanguageStrings) -> String {
+    match SIZE_BUCKET_LIMITS.get(bucket) {
+        Some(&limit) => format!("< {}", format_bytes(limit, strings)),
+        None => format!("≥ {}", format_bytes(SIZE_BUCKET_LIMITS[SIZE_BUCKET_LIMITS.len() - 1], strings)),
+    }
+}
+
+impl ListStatistics {
+    // Plain-text report, shown in the statistics window and copied as-is
+    pub fn to_report(&self, strings: &LanguageStrings) -> String {
+        let mut report = String::new();
+
+        report.push_str(&format!("{}: {}\r\n", strings.stats_total_files, self.total_files));
+        report.push_str(&format!("{}: {}\r\n", strings.stats_missing_files, self.missing_files));
+        report.push_str(&format!("{}: {} ({})\r\n", strings.stats_total_size, format_bytes(self.total_size, strings), strings.size_bytes.format(self.total_size)));
+
+        report.push_str(&format!("\r\n{}\r\n", strings.stats_by_extension));
+        for (extension, count, size) in self.extensions.iter().take(TOP_EXTENSIONS) {
+            let extension = if extension.is_empty() {
+                strings.stats_no_extension.clone()
+            } else {
+                format!(".{}", extension)
+            };
+            report.push_str(&format!("  {:<12} {:>8}  {:>10}\r\n", extension, count, format_bytes(*size, strings)));
+        }
+        if self.extensions.len() > TOP_EXTENSIONS {
+            let other_count: usize = self.extensions[TOP_EXTENSIONS..].iter().map(|e| e.1).sum();
+            let other_size: u64 = self.extensions[TOP_EXTENSIONS..].iter().map(|e| e.2).sum();
+            report.push_str(&format!("  {:<12} {:>8}  {:>10}\r\n", strings.stats_other, other_count, format_bytes(other_size, strings)));
+        }
+
+        report.push_str(&format!("\r\n{}\r\n", strings.stats_by_size));
+        let max_count = self.size_buckets.iter().map(|bucket| bucket.0).max().unwrap_or(0);
+        for (bucket, (count, size)) in self.size_buckets.iter().enumerate() {
+            let bar_len = if *count > 0 { ((count * HISTOGRAM_WIDTH) / max_count.max(1)).max(1) } else { 0 };
+            report.push_str(&format!("  {:<12} {:>8}  {:>10}  {}\r\n", size_bucket_label(bucket, strings), count, format_bytes(*size, strings), "#".repeat(bar_len)));
+        }
+
+        report.push_str(&format!("\r\n{}\r\n", strings.stats_largest_files));
+        for (path, size) in &self.largest_files {
+            report.push_str(&format!("  {:>10}  {}\r\n", format_bytes(*size, strings), path));
+        }
+
+        report.push_str(&format!("\r\n{}\r\n", strings.stats_by_year));
+        let max_count = self.years.values().copied().max().unwrap_or(0);
+        for (year, count) in &self.years {
+            // Scale bars to the busiest year, but always show at least one mark
+            let bar_len = ((count * HISTOGRAM_WIDTH) / max_count.max(1)).max(1);
+            report.push_str(&format!("  {}  {:>8}  {}\r\n", year, count, "#".repeat(bar_len)));
+        }
+
+        report
+    }
+
+    // Files per month modified, or per year when that would make more than
+    // TIMELINE_MAX_MONTHS bars. Empty months and years in between are kept,
+    // so the bars are evenly spaced in time. Returns whether the entries
+    // are months, and (label, file count, total size) per entry.
+    pub fn timeline(&self) -> (bool, Vec<(String, usize, u64)>) {
+        let (Some(&(first_year, first_month)), Some(&(last_year, last_month))) = (self.months.keys().next(), self.months.keys().next_back()) else {
+            return (false, Vec::new());
+        };
+
+        let span = (last_year - first_year) * 12 + last_month as i32 - first_month as i32 + 1;
+        if span <= TIMELINE_MAX_MONTHS {
+            let entries = (0..span)
+                .map(|offset| {
+                    let index = first_year * 12 + first_month as i32 - 1 + offset;
+                    let (year, month) = (index / 12, (index % 12) as u32 + 1);
+                    let (count, size) = self.months.get(&(year, month)).copied().unwrap_or((0, 0));
+                    (format!("{}-{:02}", year, month), count, size)
+                })
+                .collect();
+            (true, entries)
+        } else {
+            let mut years: BTreeMap<i32, (usize, u64)> = (first_year..=last_year).map(|year| (year, (0, 0))).collect();
+            for (&(year, _), (count, size)) in &self.months {
+                let entry = years.entry(year).or_insert((0, 0));
+                entry.0 += count;
+                entry.1 += size;
+            }
+            (false, years.into_iter().map(|(year, (count, size))| (year.to_string(), count, size)).collect())
+        }
+    }
+
+    // Every table of the statistics in one CSV: a section column says
+    // which one a row belongs to. Sizes are in bytes.
+    pub fn to_csv(&self) -> String {
+        let mut content = String::from("Section,Value,Files,Size\n");
+        for (extension, count, size) in &self.extensions {
+            content.push_str(&format!("extension,\"{}\",{},{}\n", extension.replace('"', "\"\""), count, size));
+        }
+        for (bucket, (count, size)) in self.size_buckets.iter().enumerate() {
+            let value = match SIZE_BUCKET_LIMITS.get(bucket) {
+                Some(limit) => format!("<{}", limit),
+                None => format!(">={}", SIZE_BUCKET_LIMITS[SIZE_BUCKET_LIMITS.len() - 1]),
+            };
+            content.push_str(&format!("size,{},{},{}\n", value, count, size));
+        }
+        let (by_month, timeline) = self.timeline();
+        let section = if by_month { "month" } else { "year" };
+        for (label, count, size) in timeline {
+            content.push_str(&format!("{},{},{},{}\n", section, label, count, size));
+        }
+        content
+    }
+}
+
+pub fn format_bytes(size: u64, strings: &LanguageStrings) -> String {
+    if size >= 1024 * 1024 * 1024 {
+        format!("{:.1} GB", size as f64 / (1024.0 * 1024.0 * 1024.0))
+    } else if size >= 1024 * 1024 {
+        format!("{:.1} MB", size as f64 / (1024.0 * 1024.0))
+    } else if size >= 1024 {
+        format!("{:.1} KB", size as f64 / 1024.0)
+    } else {
+        strings.size_bytes.format(size)
+    }
+}
+
+// `size` the way the Size column and the status bar are set to show it
+pub fn format_size(size: u64, format: SizeFormat, strings: &LanguageStrings) -> String {
+    match format {
+        SizeFormat::Auto => format_bytes(size, strings),
+        // Explorer rounds up, so no file with data in it shows as 0 KB
+        SizeFormat::Kilobytes => format!("{} KB", group_thousands(size.div_ceil(1024))),
+        SizeFormat::Bytes => strings.size_bytes.format_as(size, &group_thousands(size)),
+    }
+}
+
+// `value` with the user's thousands separator between each group of three
+// digits
+pub fn group_thousands(value: u64) -> String {
+    let mut buffer = [0u16; 8];
+    let length = unsafe { GetLocaleInfoEx(PCWSTR::null(), LOCALE_STHOUSAND, Some(&mut buffer)) };
+    let separator = if length > 1 { String::from_utf16_lossy(&buffer[..length as usize - 1]) } else { ",".to_string() };
+
+    let digits = value.to_string();
+    let mut grouped = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push_str(&separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
@@ -0,0 +1,149 @@
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::*,
+        Storage::FileSystem::*,
+        System::IO::CancelIoEx,
+        UI::WindowsAndMessaging::PostMessageW,
+    },
+};
+use std::path::Path;
+use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
+use std::time::Duration;
+use crate::logger::log_error;
+use crate::thumbnail::to_wide;
+
+// Editors and scripts often write a file in several steps, so wait a bit
+// after the first change before telling the UI
+const CHANGE_SETTLE_DELAY: Duration = Duration::from_millis(500);
+
+// Watches the folder of an opened list file and posts `message` with the
+// watcher's generation in WPARAM whenever that file is written or replaced.
+pub struct ListWatcher {
+    stop: Arc<AtomicBool>,
+    directory: Arc<Mutex<Option<HANDLE>>>,
+}
+
+impl ListWatcher {
+    pub fn start(list_path: &str, window: HWND, message: u32, generation: u64) -> Option<Self> {
+        let path = Path::new(list_path);
+        let file_name = path.file_name()?.to_string_lossy().to_string();
+        let directory_path = path.parent()?.to_string_lossy().to_string();
+
+        let directory = unsafe {
+            let directory_wide = to_wide(&directory_path);
+            match CreateFileW(
+                PCWSTR::from_raw(directory_wide.as_ptr()),
+                FILE_LIST_DIRECTORY.0,
+                FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+                None,
+                OPEN_EXISTING,
+                FILE_FLAG_BACKUP_SEMANTICS,
+                HANDLE(0),
+            ) {
+                Ok(handle) => handle,
+                Err(e) => {
+                    log_error(&format!("Failed to watch list folder {}: {}", directory_path, e));
+                    return None;
+                }
+            }
+        };
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let shared_directory = Arc::new(Mutex::new(Some(directory)));
+
+        let thread_stop = stop.clone();
+        let thread_directory = shared_directory.clone();
+
+        std::thread::spawn(move || {
+            // DWORD-aligned buffer as required by ReadDirectoryChangesW
+            let mut buffer = vec![0u32; 4096];
+
+            while !thread_stop.load(Ordering::Relaxed) {
+                let mut bytes_returned = 0u32;
+                let result = unsafe {
+                    ReadDirectoryChangesW(
+                        directory,
+                        buffer.as_mut_ptr() as *mut std::ffi::c_void,
+                        (buffer.len() * std::mem::size_of::<u32>()) as u32,
+                        FALSE,
+                        FILE_NOTIFY_CHANGE_FILE_NAME | FILE_NOTIFY_CHANGE_LAST_WRITE,
+                        Some(&mut bytes_returned),
+                        None,
+                        None,
+                    )
+                };
+
+                // Cancelled by stop(), or the folder went away
+                if result.is_err() || thread_stop.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                // A zero-length result means the buffer overflowed; assume our file changed
+                let changed = bytes_returned == 0 || unsafe { contains_change_to(&buffer, &file_name) };
+                if changed {
+                    std::thread::sleep(CHANGE_SETTLE_DELAY);
+                    if !thread_stop.load(Ordering::Relaxed) {
+                        unsafe {
+                            let _ = PostMessageW(window, message, WPARAM(generation as usize), LPARAM(0));
+                        }
+                    }
+                }
+            }
+
+            if let Ok(mut directory) = thread_directory.lock() {
+                if let Some(handle) = directory.take() {
+                    unsafe {
+                        let _ = CloseHandle(handle);
+                    }
+                }
+            }
+        });
+
+        Some(Self {
+            stop,
+            directory: shared_directory,
+        })
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        // Wake the watcher thread out of its blocking read
+        if let Ok(directory) = self.directory.lock() {
+            if let Some(handle) = *directory {
+                unsafe {
+                    let _ = CancelIoEx(handle, None);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ListWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+unsafe fn contains_change_to(buffer: &[u32], file_name: &str) -> bool {
+    let base = buffer.as_ptr() as *const u8;
+    let mut offset = 0usize;
+
+    loop {
+        let info = &*(base.add(offset) as *const FILE_NOTIFY_INFORMATION);
+        let name_len = info.FileNameLength as usize / 2;
+        let name = String::from_utf16_lossy(std::slice::from_raw_parts(info.FileName.as_ptr(), name_len));
+
+        // Removals and the old half of a rename don't leave a new file behind
+        let produces_file = info.Action != FILE_ACTION_REMOVED && info.Action != FILE_ACTION_RENAMED_OLD_NAME;
+        if produces_file && name.eq_ignore_ascii_case(file_name) {
+            return true;
+        }
+
+        if info.NextEntryOffset == 0 {
+            return false;
+        }
+        offset += info.NextEntryOffset as usize;
+    }
+}
@@ -0,0 +1,332 @@
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{HBRUSH, HFONT, COLOR_BTNFACE},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Controls::*,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::clipboard::set_clipboard_text;
+use crate::lang::get_strings;
+use crate::logger::{
+    clear_log_lines, log_error, log_level, log_revision, log_to_file, recent_log_lines, set_log_level, set_log_to_file, LogLevel,
+};
+use crate::thumbnail::to_wide;
+
+// Sent to the owner window when the level or file output was changed,
+// so the choice can be saved to the config
+pub const WM_LOG_SETTINGS_CHANGED: u32 = WM_USER + 108;
+
+const LOG_WINDOW_CLASS: &str = "EverythingLikeLogWindow";
+
+const LOG_WINDOW_WIDTH: i32 = 820;
+const LOG_WINDOW_HEIGHT: i32 = 560;
+const MARGIN: i32 = 10;
+const BUTTON_WIDTH: i32 = 80;
+const BUTTON_HEIGHT: i32 = 26;
+
+// Control IDs
+const ID_LOG_LEVEL: i32 = 9601;
+const ID_LOG_TO_FILE: i32 = 9602;
+const ID_LOG_TEXT: i32 = 9603;
+const ID_LOG_COPY: i32 = 9604;
+const ID_LOG_CLEAR: i32 = 9605;
+const ID_LOG_CLOSE: i32 = 9606;
+const ID_LOG_SHOW: i32 = 9607;
+const ID_LOG_FIND: i32 = 9608;
+
+// Hidden switch that opens the window at startup, for troubleshooting
+// problems that happen before a shortcut can be pressed
+const DEBUG_CONSOLE_ARG: &str = "--debug-console";
+
+// New lines are picked up on this timer
+const LOG_REFRESH_TIMER_ID: usize = 1;
+const LOG_REFRESH_INTERVAL_MS: u32 = 500;
+
+struct LogWindow {
+    owner: HWND,
+    text: HWND,
+    // log_revision() when the text was last filled
+    revision: Option<u64>,
+}
+
+// The window's LogWindow, kept in GWLP_USERDATA
+unsafe fn log_window(window: HWND) -> Option<&'static mut LogWindow> {
+    let log_window = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut LogWindow;
+    log_window.as_mut()
+}
+
+// Only one log window is open at a time
+unsafe fn existing_log_window() -> Option<HWND> {
+    let existing = FindWindowW(PCWSTR::from_raw(to_wide(LOG_WINDOW_CLASS).as_ptr()), None);
+    (existing.0 != 0).then_some(existing)
+}
+
+pub fn debug_console_argument() -> bool {
+    std::env::args().skip(1).any(|arg| arg.eq_ignore_ascii_case(DEBUG_CONSOLE_ARG))
+}
+
+pub fn register_log_window_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(log_window_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as isize),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeLogWindow"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// Ctrl+Shift+L opens the window, or closes it when it is already open
+pub fn toggle_log_window(owner: HWND, font: HFONT) {
+    match unsafe { existing_log_window() } {
+        Some(window) => unsafe {
+            let _ = DestroyWindow(window);
+        },
+        None => show_log_window(owner, font),
+    }
+}
+
+// Diagnostics window showing the in-memory log. It has no menu entry and
+// is opened with Ctrl+Shift+L or --debug-console.
+pub fn show_log_window(owner: HWND, font: HFONT) {
+    unsafe {
+        if let Some(existing) = existing_log_window() {
+            let _ = SetForegroundWindow(existing);
+            return;
+        }
+
+        let strings = get_strings();
+        let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+
+        let window = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            PCWSTR::from_raw(to_wide(LOG_WINDOW_CLASS).as_ptr()),
+            PCWSTR::from_raw(to_wide(&strings.log_window_title).as_ptr()),
+            WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX | WS_VISIBLE,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            LOG_WINDOW_WIDTH,
+            LOG_WINDOW_HEIGHT,
+            owner,
+            None,
+            instance,
+            None,
+        );
+
+        if window.0 == 0 {
+            log_error("Failed to create log window");
+            return;
+        }
+
+        let mut client_rect = RECT::default();
+        let _ = GetClientRect(window, &mut client_rect);
+        let width = client_rect.right - MARGIN * 2;
+        let button_y = client_rect.bottom - MARGIN - BUTTON_HEIGHT;
+        let filter_y = MARGIN * 2 + 24;
+        let text_y = filter_y + 24 + MARGIN;
+        let text_height = button_y - MARGIN - text_y;
+
+        let controls = [
+            (w!("STATIC"), strings.log_level.clone(), WINDOW_STYLE(0), WINDOW_EX_STYLE::default(), -1, MARGIN, MARGIN + 4, 90, 20),
+            (w!("COMBOBOX"), String::new(), WS_TABSTOP | WS_VSCROLL | WINDOW_STYLE(CBS_DROPDOWNLIST as u32), WINDOW_EX_STYLE::default(), ID_LOG_LEVEL, MARGIN + 95, MARGIN, 120, 200),
+            (w!("BUTTON"), strings.log_write_file.clone(), WS_TABSTOP | WINDOW_STYLE(BS_AUTOCHECKBOX as u32), WINDOW_EX_STYLE::default(), ID_LOG_TO_FILE, MARGIN + 235, MARGIN, width - 235, 24),
+            (w!("STATIC"), strings.log_show.clone(), WINDOW_STYLE(0), WINDOW_EX_STYLE::default(), -1, MARGIN, filter_y + 4, 90, 20),
+            (w!("COMBOBOX"), String::new(), WS_TABSTOP | WS_VSCROLL | WINDOW_STYLE(CBS_DROPDOWNLIST as u32), WINDOW_EX_STYLE::default(), ID_LOG_SHOW, MARGIN + 95, filter_y, 120, 200),
+            (w!("STATIC"), strings.log_find.clone(), WINDOW_STYLE(0), WINDOW_EX_STYLE::default(), -1, MARGIN + 235, filter_y + 4, 60, 20),
+            (w!("EDIT"), String::new(), WS_TABSTOP | WINDOW_STYLE(ES_AUTOHSCROLL as u32), WS_EX_CLIENTEDGE, ID_LOG_FIND, MARGIN + 300, filter_y, width - 300, 24),
+            (w!("EDIT"), String::new(), WS_VSCROLL | WS_HSCROLL | WINDOW_STYLE((ES_MULTILINE | ES_READONLY | ES_AUTOVSCROLL | ES_AUTOHSCROLL) as u32), WS_EX_CLIENTEDGE, ID_LOG_TEXT, MARGIN, text_y, width, text_height),
+            (w!("BUTTON"), strings.stats_copy.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), WINDOW_EX_STYLE::default(), ID_LOG_COPY, client_rect.right - (MARGIN + BUTTON_WIDTH) * 3, button_y, BUTTON_WIDTH, BUTTON_HEIGHT),
+            (w!("BUTTON"), strings.log_clear.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), WINDOW_EX_STYLE::default(), ID_LOG_CLEAR, client_rect.right - (MARGIN + BUTTON_WIDTH) * 2, button_y, BUTTON_WIDTH, BUTTON_HEIGHT),
+            (w!("BUTTON"), strings.stats_close.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), WINDOW_EX_STYLE::default(), ID_LOG_CLOSE, client_rect.right - (MARGIN + BUTTON_WIDTH), button_y, BUTTON_WIDTH, BUTTON_HEIGHT),
+        ];
+        for (class, text, style, ex_style, id, x, y, width, height) in controls {
+            let control = CreateWindowExW(
+                ex_style,
+                class,
+                PCWSTR::from_raw(to_wide(&text).as_ptr()),
+                WS_CHILD | WS_VISIBLE | style,
+                x, y, width, height,
+                window,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(control, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        }
+
+        let combo = GetDlgItem(window, ID_LOG_LEVEL);
+        for level in LogLevel::ALL {
+            SendMessageW(combo, CB_ADDSTRING, WPARAM(0), LPARAM(to_wide(level.label()).as_ptr() as isize));
+        }
+        let selected = LogLevel::ALL.iter().position(|level| *level == log_level()).unwrap_or(0);
+        SendMessageW(combo, CB_SETCURSEL, WPARAM(selected), LPARAM(0));
+
+        // Everything captured is shown until a level is picked
+        let show = GetDlgItem(window, ID_LOG_SHOW);
+        for level in LogLevel::ALL {
+            SendMessageW(show, CB_ADDSTRING, WPARAM(0), LPARAM(to_wide(level.label()).as_ptr() as isize));
+        }
+        SendMessageW(show, CB_SETCURSEL, WPARAM(LogLevel::ALL.len() - 1), LPARAM(0));
+
+        if log_to_file() {
+            SendMessageW(GetDlgItem(window, ID_LOG_TO_FILE), BM_SETCHECK, WPARAM(BST_CHECKED.0 as usize), LPARAM(0));
+        }
+
+        let log_window = Box::new(LogWindow {
+            owner,
+            text: GetDlgItem(window, ID_LOG_TEXT),
+            revision: None,
+        });
+        SetWindowLongPtrW(window, GWLP_USERDATA, Box::into_raw(log_window) as isize);
+
+        refresh_log_text(window);
+        SetTimer(window, LOG_REFRESH_TIMER_ID, LOG_REFRESH_INTERVAL_MS, None);
+    }
+}
+
+// Reloads the text when the log has changed and scrolls to the newest line
+unsafe fn refresh_log_text(window: HWND) {
+    let Some(log_window) = log_window(window) else {
+        return;
+    };
+
+    let revision = log_revision();
+    if log_window.revision == Some(revision) {
+        return;
+    }
+    log_window.revision = Some(revision);
+
+    let text = filtered_log_lines(window).join("\r\n");
+    let _ = SetWindowTextW(log_window.text, PCWSTR::from_raw(to_wide(&text).as_ptr()));
+    let length = GetWindowTextLengthW(log_window.text);
+    SendMessageW(log_window.text, EM_SETSEL, WPARAM(length as usize), LPARAM(length as isize));
+    SendMessageW(log_window.text, EM_SCROLLCARET, WPARAM(0), LPARAM(0));
+}
+
+// The buffered lines at or above the level picked under Show that contain
+// the Find text, ignoring case
+unsafe fn filtered_log_lines(window: HWND) -> Vec<String> {
+    let index = SendMessageW(GetDlgItem(window, ID_LOG_SHOW), CB_GETCURSEL, WPARAM(0), LPARAM(0)).0;
+    let shown = LogLevel::ALL.get(index.max(0) as usize).copied().unwrap_or(LogLevel::Trace);
+
+    let find = GetDlgItem(window, ID_LOG_FIND);
+    let mut buffer = vec![0u16; GetWindowTextLengthW(find) as usize + 1];
+    let length = GetWindowTextW(find, &mut buffer);
+    let needle = String::from_utf16_lossy(&buffer[..length.max(0) as usize]).to_lowercase();
+
+    recent_log_lines()
+        .into_iter()
+        .filter(|line| line_level(line).is_none_or(|level| level <= shown))
+        .filter(|line| needle.is_empty() || line.to_lowercase().contains(&needle))
+        .collect()
+}
+
+// Lines are written as "time LEVEL message"
+fn line_level(line: &str) -> Option<LogLevel> {
+    let label = line.split_whitespace().nth(1)?;
+    LogLevel::ALL.into_iter().find(|level| level.label() == label)
+}
+
+// Refills the text on the next refresh even though the log is unchanged
+unsafe fn invalidate_log_text(window: HWND) {
+    if let Some(log_window) = log_window(window) {
+        log_window.revision = None;
+    }
+}
+
+extern "system" fn log_window_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_TIMER => {
+                if wparam.0 == LOG_REFRESH_TIMER_ID {
+                    refresh_log_text(window);
+                }
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                let id = (wparam.0 & 0xFFFF) as i32;
+                let notification = ((wparam.0 >> 16) & 0xFFFF) as u32;
+                match id {
+                    ID_LOG_LEVEL if notification == CBN_SELCHANGE => {
+                        let index = SendMessageW(GetDlgItem(window, ID_LOG_LEVEL), CB_GETCURSEL, WPARAM(0), LPARAM(0)).0;
+                        if let Some(level) = LogLevel::ALL.get(index.max(0) as usize) {
+                            set_log_level(*level);
+                        }
+                        if let Some(log_window) = log_window(window) {
+                            let _ = PostMessageW(log_window.owner, WM_LOG_SETTINGS_CHANGED, WPARAM(0), LPARAM(0));
+                        }
+                    }
+                    ID_LOG_TO_FILE => {
+                        let checked = SendMessageW(GetDlgItem(window, ID_LOG_TO_FILE), BM_GETCHECK, WPARAM(0), LPARAM(0)).0 == BST_CHECKED.0 as isize;
+                        set_log_to_file(checked);
+                        if checked && !log_to_file() {
+                            // The file couldn't be opened
+                            SendMessageW(GetDlgItem(window, ID_LOG_TO_FILE), BM_SETCHECK, WPARAM(BST_UNCHECKED.0 as usize), LPARAM(0));
+                        }
+                        if let Some(log_window) = log_window(window) {
+                            let _ = PostMessageW(log_window.owner, WM_LOG_SETTINGS_CHANGED, WPARAM(0), LPARAM(0));
+                        }
+                    }
+                    ID_LOG_SHOW if notification == CBN_SELCHANGE => {
+                        invalidate_log_text(window);
+                        refresh_log_text(window);
+                    }
+                    ID_LOG_FIND if notification == EN_CHANGE => {
+                        invalidate_log_text(window);
+                        refresh_log_text(window);
+                    }
+                    ID_LOG_COPY => {
+                        set_clipboard_text(window, &filtered_log_lines(window).join("\r\n"));
+                    }
+                    ID_LOG_CLEAR => {
+                        clear_log_lines();
+                        refresh_log_text(window);
+                    }
+                    ID_LOG_CLOSE => {
+                        let _ = DestroyWindow(window);
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(window);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                let _ = KillTimer(window, LOG_REFRESH_TIMER_ID);
+                let log_window = SetWindowLongPtrW(window, GWLP_USERDATA, 0) as *mut LogWindow;
+                if !log_window.is_null() {
+                    drop(Box::from_raw(log_window));
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum LogLevel {
+    Error = 1,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 5] = [
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Trace,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LogLevel::Error => "ERROR",
+            LogLevel::Warn => "WARN",
+            LogLevel::Info => "INFO",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Trace => "TRACE",
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => LogLevel::Error,
+            2 => LogLevel::Warn,
+            3 => LogLevel::Info,
+            4 => LogLevel::Debug,
+            _ => LogLevel::Trace,
+        }
+    }
+}
+
+// Lines kept in memory for the log window
+const RING_BUFFER_SIZE: usize = 2000;
+
+struct LogSink {
+    file: Option<File>,
+    lines: VecDeque<String>,
+    total: u64,
+}
+
+// The level is checked without taking the lock, so disabled messages
+// (paint and scroll traces, usually) cost next to nothing
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+static LOG_TO_FILE: AtomicBool = AtomicBool::new(false);
+static LOG_SINK: Mutex<LogSink> = Mutex::new(LogSink {
+    file: None,
+    lines: VecDeque::new(),
+    total: 0,
+});
+
+// debug.log sits next to config.json
+pub fn log_file_path() -> Option<PathBuf> {
+    crate::config::get_config_dir().ok().map(|dir| dir.join("debug.log"))
+}
+
+pub fn configure_logger(level: LogLevel, to_file: bool) {
+    set_log_level(level);
+    set_log_to_file(to_file);
+}
+
+pub fn log_level() -> LogLevel {
+    LogLevel::from_u8(LOG_LEVEL.load(Ordering::Relaxed))
+}
+
+pub fn set_log_level(level: LogLevel) {
+    LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn log_to_file() -> bool {
+    LOG_TO_FILE.load(Ordering::Relaxed)
+}
+
+// Starts or stops writing to debug.log. The file is truncated each time
+// it is opened, like the old always-on log.
+pub fn set_log_to_file(enabled: bool) {
+    let mut sink = match LOG_SINK.lock() {
+        Ok(sink) => sink,
+        Err(_) => return,
+    };
+
+    if !enabled {
+        sink.file = None;
+        LOG_TO_FILE.store(false, Ordering::Relaxed);
+        return;
+    }
+    if sink.file.is_some() {
+        return;
+    }
+
+    let file = log_file_path().and_then(|path| {
+        OpenOptions::new().create(true).write(true).truncate(true).open(path).ok()
+    });
+    match file {
+        Some(mut file) => {
+            let _ = writeln!(file, "=== Application Debug Log Started ===");
+            for line in &sink.lines {
+                let _ = writeln!(file, "{}", line);
+            }
+            sink.file = Some(file);
+            LOG_TO_FILE.store(true, Ordering::Relaxed);
+        }
+        None => {
+            // log() takes the lock too
+            drop(sink);
+            log_warn("Failed to open debug.log");
+        }
+    }
+}
+
+pub fn log_enabled(level: LogLevel) -> bool {
+    level as u8 <= LOG_LEVEL.load(Ordering::Relaxed)
+}
+
+pub fn log(level: LogLevel, message: &str) {
+    if !log_enabled(level) {
+        return;
+    }
+
+    let line = format!("{} {:<5} {}", chrono::Local::now().format("%H:%M:%S%.3f"), level.label(), message);
+
+    if let Ok(mut sink) = LOG_SINK.lock() {
+        if let Some(file) = sink.file.as_mut() {
+            let _ = writeln!(file, "{}", line);
+            let _ = file.flush();
+        }
+        if sink.lines.len() == RING_BUFFER_SIZE {
+            sink.lines.pop_front();
+        }
+        sink.lines.push_back(line);
+        sink.total += 1;
+    }
+}
+
+pub fn log_error(message: &str) {
+    log(LogLevel::Error, message);
+}
+
+pub fn log_warn(message: &str) {
+    log(LogLevel::Warn, message);
+}
+
+pub fn log_info(message: &str) {
+    log(LogLevel::Info, message);
+}
+
+pub fn log_debug(message: &str) {
+    log(LogLevel::Debug, message);
+}
+
+pub fn log_trace(message: &str) {
+    log(LogLevel::Trace, message);
+}
+
+// Changes whenever the ring buffer does, so the log window can tell
+// when to refresh
+pub fn log_revision() -> u64 {
+    LOG_SINK.lock().map_or(0, |sink| sink.total)
+}
+
+// The lines still in the ring buffer, oldest first
+pub fn recent_log_lines() -> Vec<String> {
+    LOG_SINK.lock().map_or_else(|_| Vec::new(), |sink| sink.lines.iter().cloned().collect())
+}
+
+// For the crash handler, which can't wait on a lock the crashing thread
+// may be holding
+pub fn try_recent_log_lines() -> Option<Vec<String>> {
+    LOG_SINK.try_lock().ok().map(|sink| sink.lines.iter().cloned().collect())
+}
+
+pub fn clear_log_lines() {
+    if let Ok(mut sink) = LOG_SINK.lock() {
+        sink.lines.clear();
+        sink.total += 1;
+    }
+}
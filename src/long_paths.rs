@@ -0,0 +1,66 @@
+use std::borrow::Cow;
+use windows::{
+    core::PCWSTR,
+    Win32::Storage::FileSystem::GetShortPathNameW,
+};
+use crate::thumbnail::to_wide;
+
+// Windows' classic path limit, terminating null included. Longer paths
+// need the \\?\ form for raw Win32 file calls and the 8.3 form for the
+// shell; std::fs adds the prefix by itself.
+const MAX_PATH: usize = 260;
+
+fn is_long(path: &str) -> bool {
+    path.encode_utf16().count() >= MAX_PATH
+}
+
+// `path` in the \\?\ form raw Win32 file calls such as CreateFileW accept
+// past MAX_PATH: C:\… turns into \\?\C:\… and \\server\share\… into
+// \\?\UNC\server\share\…. Paths within the limit, relative paths, paths
+// with . or .. parts, which that form takes literally, and paths already in
+// it come back as they are. std::fs calls take `path` itself.
+pub fn extended_path(path: &str) -> Cow<'_, str> {
+    if !is_long(path) || path.starts_with(r"\\?\") || path.starts_with(r"\\.\") {
+        return Cow::Borrowed(path);
+    }
+    if path.split(['\\', '/']).any(|part| part == "." || part == "..") {
+        return Cow::Borrowed(path);
+    }
+
+    // The prefix turns off the normalization that would accept slashes
+    if let Some(rest) = path.strip_prefix(r"\\").or_else(|| path.strip_prefix("//")) {
+        Cow::Owned(format!(r"\\?\UNC\{}", rest.replace('/', "\\")))
+    } else if path.get(1..3).is_some_and(|drive| drive == ":\\" || drive == ":/") {
+        Cow::Owned(format!(r"\\?\{}", path.replace('/', "\\")))
+    } else {
+        Cow::Borrowed(path)
+    }
+}
+
+// `path` for the shell (ShellExecute, SHGetFileInfo, shell items and file
+// operations), which doesn't take \\?\ paths. Past MAX_PATH the 8.3 short
+// form is used where the volume keeps short names; otherwise the path is
+// passed on as it is and left to the shell.
+pub fn shell_path(path: &str) -> Cow<'_, str> {
+    if !is_long(path) {
+        return Cow::Borrowed(path);
+    }
+
+    let long = to_wide(&extended_path(path));
+    let mut buffer = vec![0u16; long.len()];
+    let length = unsafe { GetShortPathNameW(PCWSTR::from_raw(long.as_ptr()), Some(&mut buffer)) } as usize;
+    if length == 0 || length >= buffer.len() {
+        return Cow::Borrowed(path);
+    }
+
+    let short = String::from_utf16_lossy(&buffer[..length]);
+    let short = match short.strip_prefix(r"\\?\UNC\") {
+        Some(rest) => format!(r"\\{}", rest),
+        None => short.strip_prefix(r"\\?\").map_or(short.clone(), str::to_string),
+    };
+    if is_long(&short) {
+        Cow::Borrowed(path)
+    } else {
+        Cow::Owned(short)
+    }
+}
@@ -3,12 +3,16 @@
     Win32::{
         Foundation::*,
         Graphics::Gdi::*,
-        System::LibraryLoader::GetModuleHandleW,
+        System::{
+            DataExchange::COPYDATASTRUCT,
+            LibraryLoader::GetModuleHandleW,
+        },
         UI::{
+            Accessibility::{SELFLAG_ADDSELECTION, SELFLAG_EXTENDSELECTION, SELFLAG_REMOVESELECTION, SELFLAG_TAKEFOCUS, SELFLAG_TAKESELECTION},
             Controls::*,
             Input::KeyboardAndMouse::*,
             WindowsAndMessaging::*,
-            Shell::ShellExecuteW,
+            Shell::{ShellExecuteExW, ShellExecuteW, SHELLEXECUTEINFOW, SEE_MASK_INVOKEIDLIST, NIN_BALLOONTIMEOUT, NIN_BALLOONUSERCLICK},
         },
     },
 };
@@ -18,64 +22,388 @@ mod thumbnail;
 mod config;
 mod lang;
 mod file_icons;
+mod export;
+mod contact_sheet;
+mod clipboard;
+mod playlist;
+mod list_watcher;
+mod folder_monitor;
+mod scheduled_export;
+mod list_stats;
+mod list_query;
+mod list_notes;
+mod csv;
+mod settings_dialog;
+mod input_box;
+mod autostart;
+mod tray;
+mod collation;
+mod translation_editor;
+mod logger;
+mod log_window;
+mod batch_loader;
+mod metadata_loader;
+mod git_status;
+mod file_links;
+mod media_info;
+mod run_history;
+mod sort_task;
+mod crash_handler;
+mod perf;
+mod shell_verb;
+mod quick_search;
+mod launcher;
+mod jump_list;
+mod etp_client;
+mod http_server;
+mod url_protocol;
+mod external_tools;
+mod everything_import;
+mod accessibility;
+mod menu_mnemonics;
+mod https_client;
+mod update_check;
+mod about_dialog;
+mod file_hash;
+mod file_compare;
+mod update_install;
+mod recycle_bin;
+mod duplicate_finder;
+mod disk_usage;
+mod empty_folders;
+mod stats_dashboard;
+mod tags;
+mod timeline_view;
+mod image_viewer;
+mod quick_view;
+mod video_preview;
+mod archive_browser;
+mod checksum_verify;
+mod folder_tree;
+mod preview_pane;
+mod network_paths;
+mod long_paths;
+mod elevation;
+mod column_chooser;
+mod memory_usage;
+mod open_history;
+mod syntax_help;
+mod drive_groups;
+mod search_tabs;
+mod print_results;
 
-use everything_sdk::{EverythingSDK, FileResult};
+use everything_sdk::{EverythingSDK, FileResult, ResultBatch, BROWSE_PAGE_SIZE, SORT_NAME_ASCENDING, SORT_PATH_ASCENDING, SORT_SIZE_ASCENDING, SORT_TYPE_NAME_ASCENDING, SORT_DATE_MODIFIED_ASCENDING};
 use thumbnail::{ThumbnailTaskManager, WM_THUMBNAIL_READY, WM_RECOMPUTE_THUMBS, create_placeholder_bitmap, to_wide};
-use config::{ThumbnailStrategy, ThumbnailBackground, LanguageCode, AppConfig, load_config, save_config};
-use lang::{Language, init_language_manager, set_language, get_strings, get_current_language};
-use file_icons::{init_icon_cache, get_file_icon, get_default_file_icon, draw_icon};
+use config::{ThumbnailStrategy, ThumbnailBackground, ThumbnailCaption, PathEllipsis, SizeFormat, LanguageCode, AppConfig, load_config, save_config, RecentList, SessionState, ColumnLayout, export_config, import_config, MAX_PROFILES, MAX_RECENT_SEARCHES, MAX_REMOTE_SERVERS, RemoteServer, FavoriteKind, ExternalTool, MAX_EXTERNAL_TOOLS, SearchFilter, ScheduledExport, MAX_BOOKMARKS, MAX_SCHEDULED_EXPORTS, SCHEDULED_EXPORT_INTERVALS, MAX_SEARCH_FILTERS, MAX_EXCLUDED_FOLDERS, SHOW_ALL_SEARCH, CONTACT_SHEET_COLUMN_CHOICES, CONTACT_SHEET_CELL_SIZE_CHOICES};
+use lang::{Language, LanguageStrings, init_language_manager, set_language, get_strings, get_current_language, fill, validate_translations, TranslationReport};
+use file_icons::{init_icon_cache, start_icon_loader, pending_icon_count, get_file_icon, get_default_file_icon, get_folder_icon, draw_icon, icon_cache_key, WM_ICON_READY};
+use export::{ExportFormat, export_results};
+use contact_sheet::{ContactSheetOptions, ContactSheetResult, render_contact_sheet};
+use clipboard::{read_clipboard_paths, set_clipboard_table, set_clipboard_text, set_clipboard_listener, clipboard_owned_by};
+use playlist::{is_playlist_path, parse_playlist};
+use list_watcher::ListWatcher;
+use folder_monitor::{scan_folder, FolderChange, FolderMonitor};
+use scheduled_export::{interval_label, is_due, last_run_status, run_scheduled_export, unix_now, ScheduledExportDone, SCHEDULE_CHECK_INTERVAL_MS};
+use metadata_loader::{MetadataEntry, MetadataLoader};
+use git_status::{GitFileStatus, GitStatusEntry, GitStatusLoader};
+use file_links::{LinkInfo, LinkInfoEntry, LinkInfoLoader};
+use media_info::{has_dimensions, is_media, MediaInfo, MediaInfoEntry, MediaInfoLoader};
+use run_history::RunHistory;
+use sort_task::{SortItem, SortKey, SortResult, SortTask, SortText, StreamSorter};
+use crash_handler::install_crash_handler;
+use perf::{MessageTimer, THUMBNAIL_LOOKUPS, hud_lines, record_paint, start_watchdog, time_stage};
+use list_stats::{compute_statistics, format_size};
+use memory_usage::{budget_bytes, predicted_results_bytes, results_bytes, results_within_budget, thumbnail_cache_bytes};
+use list_query::ListQuery;
+use list_notes::{ListNotes, LIST_FORMAT_HEADER, note_column};
+use csv::{csv_field, parse_csv_line};
+use settings_dialog::{WM_APPLY_SETTINGS, register_settings_window_class, show_settings_dialog};
+use input_box::{register_input_box_class, prompt_text};
+use column_chooser::{ColumnChoice, choose_columns, register_column_chooser_class};
+use autostart::{is_autostart_enabled, set_autostart};
+use shell_verb::{COPYDATA_SEARCH_IN, forward_search_in, forward_to_running_instance, is_explorer_verb_registered, browse_folder_query, excluded_search_query, scoped_search_query, search_in_argument, set_explorer_verb};
+use etp_client::{EtpConnection, parse_server_address};
+use http_server::HttpServer;
+use url_protocol::{is_url_protocol_registered, set_url_protocol, url_search_argument};
+use external_tools::{Terminal, expand_tool_command, open_terminal, run_tool_command, takes_all_paths, to_wsl_path};
+use everything_import::{import_bookmarks, import_filters};
+use menu_mnemonics::assign_menu_mnemonics;
+use duplicate_finder::{register_duplicate_window_class, show_duplicate_finder};
+use recycle_bin::WM_FILES_RECYCLED;
+use disk_usage::{register_disk_usage_window_class, show_disk_usage};
+use empty_folders::{register_empty_folders_window_class, show_empty_folders};
+use stats_dashboard::{register_stats_dashboard_class, show_stats_dashboard};
+use tags::{TagStore, MAX_TAGS, MAX_RATING};
+use timeline_view::{register_timeline_window_class, show_timeline};
+use syntax_help::{register_syntax_help_class, toggle_syntax_help, WM_SYNTAX_INSERT};
+use file_compare::{start_compare, CompareResult, WM_COMPARE_DONE};
+use drive_groups::{group_by_drive, DetailsRow, DriveGroup};
+use search_tabs::{SearchTabs, TAB_STRIP_HEIGHT};
+use print_results::{print_table, register_print_preview_class, show_print_preview, PrintColumn, PrintTable};
+use open_history::{record_opened, register_open_history_window_class, show_open_history, WM_OPEN_HISTORY_OPEN, WM_OPEN_HISTORY_RECORD};
+use image_viewer::{is_viewable_image, register_image_viewer_window_class, show_image_viewer};
+use quick_view::{register_quick_view_window_class, show_quick_view};
+use video_preview::{is_video, register_video_preview_window_class, show_video_preview};
+use archive_browser::{is_archive, register_archive_window_class, show_archive_browser};
+use checksum_verify::{is_checksum_manifest, register_checksum_window_class, show_checksum_verifier};
+use folder_tree::{FolderTree, SidebarItem};
+use preview_pane::PreviewPane;
+use network_paths::{WM_SHARE_STATUS_CHANGED, is_offline};
+use long_paths::shell_path;
+use elevation::{confirm_elevated_retry, elevated_action_argument, is_access_denied, open_elevated, run_elevated_action, shell_open};
+use about_dialog::{WM_UPDATE_FOUND, register_about_box_class, show_about_dialog};
+use update_check::{Release, check_for_updates};
+use update_install::{apply_update, apply_update_argument, launch_update_helper, remove_update_files, start_update_download};
+use accessibility::{AccessibleChild, AccessibleList, handle_get_object, notify_focus, notify_rows_changed, notify_selection_changed};
+use jump_list::{COPYDATA_OPEN_LIST, COPYDATA_SEARCH, JumpListCategory, JumpListItem, open_list_argument, search_argument, update_jump_list};
+use tray::{WM_TRAY_ICON, add_tray_icon, remove_tray_icon, show_tray_balloon};
+use translation_editor::{WM_TRANSLATIONS_CHANGED, register_translation_editor_class, show_translation_editor};
+use logger::{configure_logger, log_debug, log_error, log_info, log_level, log_to_file, log_trace, log_warn};
+use log_window::{WM_LOG_SETTINGS_CHANGED, debug_console_argument, register_log_window_class, show_log_window, toggle_log_window};
+use quick_search::{WM_QUICK_SEARCH, register_quick_search_class, show_quick_search};
+use launcher::{WM_LAUNCHER_OPEN, WM_LAUNCHER_QUERY, LAUNCHER_RESULTS, register_launcher_class, set_launcher_results, toggle_launcher};
 use lru::LruCache;
 use std::fs;
-use std::fs::OpenOptions;
-use std::io::Write;
+use std::collections::{BTreeSet, HashSet};
 use std::num::NonZeroUsize;
+use std::path::PathBuf;
 use std::sync::{Arc, atomic::{AtomicBool, AtomicU64, Ordering}, Mutex, mpsc};
 use std::time::{Duration, Instant};
 use rayon::prelude::*;
 
-// Global logger for debugging
-static mut LOG_FILE: Option<std::fs::File> = None;
-
 // Global Everything SDK synchronization
 static EVERYTHING_SDK_MUTEX: Mutex<()> = Mutex::new(());
 
-// Store original search edit window procedure
-static mut ORIGINAL_SEARCH_EDIT_PROC: Option<WNDPROC> = None;
-
 // Search request structure
-#[derive(Debug)]
 struct SearchRequest {
     query: String,
     generation: u64,
     window: HWND,
+    // Posted with each SearchBatch; WM_SEARCH_RESULTS for the result list
+    message: u32,
     cancel_flag: Arc<AtomicBool>,
+    max_results: usize,
+    // Sorts the batches into the active sort order, if there is one
+    sorter: Option<StreamSorter>,
+    // Reads one page of the results instead of all of them, for browsing
+    // the whole index
+    page: Option<PageRequest>,
+    // Bytes the results may be expected to take before the user is asked
+    // whether to load the rest
+    memory_budget: Option<u64>,
+    // Picks the results when Everything can only run part of the query
+    filter: Option<ResultFilter>,
+}
+
+// A tag: or rating: search that files without tags or stars can match,
+// such as rating:0. Everything runs the rest of the query and this picks
+// the matches from what it finds.
+struct ResultFilter {
+    query: ListQuery,
+    tags: TagStore,
+    notes: ListNotes,
+}
+
+impl ResultFilter {
+    fn matches(&self, file: &mut FileResult) -> bool {
+        if self.query.needs_metadata() {
+            file.load_metadata();
+        }
+        self.query.matches(file, &self.tags, &self.notes)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PageRequest {
+    offset: usize,
+    // Everything's sort order
+    sort: u32,
+}
+
+// A page of the whole index after the first, posted boxed in WPARAM of
+// WM_INDEX_PAGE
+struct IndexPage {
+    offset: usize,
+    results: Vec<FileResult>,
+    generation: u64,
 }
 
-fn init_logger() {
+// The show-all search while it is on screen: every file Everything knows,
+// read a page at a time as the rows are scrolled to
+struct IndexBrowse {
+    // The query sent to Everything, with the filter and scope
+    query: String,
+    // Everything's sort order the pages are read in
+    sort: u32,
+    // Pages asked for so far, by number
+    requested: std::collections::HashSet<usize>,
+}
+
+// One batch of search results, posted boxed in WPARAM of WM_SEARCH_RESULTS.
+// The first batch replaces the list and later ones are merged into it.
+struct SearchBatch {
+    results: Vec<FileResult>,
+    // Index of each result in the sorted list once merged, when the search
+    // thread has sorted the batch
+    positions: Option<Vec<usize>>,
+    // Results sent for this search so far, this batch included
+    total: usize,
+    generation: u64,
+    first: bool,
+    last: bool,
+}
+
+impl SearchBatch {
+    // The whole result set in one unsorted batch
+    fn complete(results: Vec<FileResult>, generation: u64) -> Self {
+        Self {
+            total: results.len(),
+            results,
+            positions: None,
+            generation,
+            first: true,
+            last: true,
+        }
+    }
+}
+
+fn post_search_batch(window: HWND, message: u32, batch: SearchBatch) {
+    let batch_ptr = Box::into_raw(Box::new(batch)) as usize;
     unsafe {
-        if let Ok(mut file) = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open("debug.log") {
-            let _ = writeln!(file, "=== Application Debug Log Started ===");
-            LOG_FILE = Some(file);
+        if PostMessageW(window, message, WPARAM(batch_ptr), LPARAM(0)).is_err() {
+            drop(Box::from_raw(batch_ptr as *mut SearchBatch));
+        }
+    }
+}
+
+// Reads a page of the show-all search. The first page goes out as the
+// complete result list, padded with placeholders to the size of the index;
+// later pages replace the placeholders.
+fn read_index_page(sdk: &EverythingSDK, request: &SearchRequest, page: PageRequest) {
+    let result = {
+        let _stage = time_stage("query");
+        let _guard = EVERYTHING_SDK_MUTEX.lock().unwrap();
+        sdk.search_page(&request.query, page.sort, page.offset, BROWSE_PAGE_SIZE)
+    };
+    
+    match result {
+        Ok((total, mut results)) if page.offset == 0 => {
+            log_debug(&format!("Browsing {} indexed files", total));
+            let shown = results_within_budget(total.max(results.len()), request.memory_budget);
+            results.resize_with(shown, FileResult::placeholder);
+            post_search_batch(request.window, request.message, SearchBatch::complete(results, request.generation));
+            if shown < total {
+                post_over_memory_budget(request, total);
+            }
+        }
+        Ok((_, results)) => {
+            let page_ptr = Box::into_raw(Box::new(IndexPage {
+                offset: page.offset,
+                results,
+                generation: request.generation,
+            })) as usize;
+            unsafe {
+                if PostMessageW(request.window, WM_INDEX_PAGE, WPARAM(page_ptr), LPARAM(0)).is_err() {
+                    drop(Box::from_raw(page_ptr as *mut IndexPage));
+                }
+            }
+        }
+        Err(e) => {
+            log_error(&format!("Failed to read the index page at {}: {}", page.offset, e));
+            if page.offset == 0 {
+                post_search_batch(request.window, request.message, SearchBatch::complete(Vec::new(), request.generation));
+            }
         }
     }
 }
 
-fn log_debug(message: &str) {
+// Tells the window a search found `found` results, more than its memory
+// budget allows
+fn post_over_memory_budget(request: &SearchRequest, found: usize) {
+    log_info(&format!("{} results are over the memory budget", found));
     unsafe {
-        if let Some(ref mut file) = LOG_FILE {
-            let timestamp = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_millis();
-            let _ = writeln!(file, "[{}] {}", timestamp, message);
-            let _ = file.flush();
+        let _ = PostMessageW(request.window, WM_OVER_MEMORY_BUDGET, WPARAM(found), LPARAM(request.generation as isize));
+    }
+}
+
+// Runs the searches sent while an ETP server is selected. The connection is
+// opened by the first search and again after an error; the thread ends when
+// the sender is dropped.
+fn remote_search_thread(server: RemoteServer, receiver: mpsc::Receiver<SearchRequest>) {
+    let mut connection: Option<EtpConnection> = None;
+    
+    while let Ok(mut request) = receiver.recv() {
+        // Same debounce as the Everything SDK thread
+        std::thread::sleep(Duration::from_millis(150));
+        if request.cancel_flag.load(Ordering::Relaxed) {
+            continue;
+        }
+        
+        if connection.is_none() {
+            match EtpConnection::connect(&server) {
+                Ok(opened) => connection = Some(opened),
+                Err(e) => {
+                    log_error(&format!("Failed to connect to {}: {}", server.display_name(), e));
+                    post_search_batch(request.window, request.message, SearchBatch::complete(Vec::new(), request.generation));
+                    
+                    // WPARAM is the server and the error, boxed
+                    let failure_ptr = Box::into_raw(Box::new((server.clone(), e.to_string()))) as usize;
+                    unsafe {
+                        if PostMessageW(request.window, WM_REMOTE_FAILED, WPARAM(failure_ptr), LPARAM(0)).is_err() {
+                            drop(Box::from_raw(failure_ptr as *mut (RemoteServer, String)));
+                        }
+                    }
+                    continue;
+                }
+            }
+        }
+        let Some(current) = connection.as_mut() else {
+            continue;
+        };
+        
+        let mut first = true;
+        let mut total = 0;
+        let search_result = {
+            let _stage = time_stage("query");
+            current.search_in_batches(&request.query, request.max_results, |mut results, last| {
+                if request.cancel_flag.load(Ordering::Relaxed) {
+                    return false;
+                }
+                
+                let positions = request.sorter.as_mut().map(|sorter| sorter.insert_batch(&mut results));
+                total += results.len();
+                post_search_batch(request.window, request.message, SearchBatch {
+                    results,
+                    positions,
+                    total,
+                    generation: request.generation,
+                    first,
+                    last,
+                });
+                first = false;
+                true
+            })
+        };
+        
+        if let Err(e) = search_result {
+            log_error(&format!("ETP search failed: {}", e));
+            // Start over with a new connection next time
+            connection = None;
+            post_search_batch(request.window, request.message, SearchBatch::complete(Vec::new(), request.generation));
         }
     }
-    println!("{}", message); // Also print to console
+    
+    log_debug("ETP search thread ended");
+}
+
+// Outcome of downloading remote files, posted boxed in WPARAM of
+// WM_DOWNLOAD_DONE
+struct DownloadDone {
+    downloaded: Vec<PathBuf>,
+    // "path: error" for each file that couldn't be fetched
+    failed: Vec<String>,
+    // Open the files once they are here
+    open: bool,
 }
 
 // Helper macros for Win32
@@ -98,27 +426,100 @@ fn GET_WHEEL_DELTA_WPARAM(wparam: WPARAM) -> i16 {
 // Custom window messages
 const WM_SEARCH_RESULTS: u32 = WM_USER + 100;
 const WM_SEARCH_DEBOUNCE: u32 = WM_USER + 101;
+const WM_LIST_VALIDATED: u32 = WM_USER + 102;
+const WM_CONTACT_SHEET_DONE: u32 = WM_USER + 103;
+const WM_LIST_FILE_CHANGED: u32 = WM_USER + 104;
+// Posted by the folder monitor with a boxed (generation, Vec<FolderChange>)
+const WM_MONITORED_FOLDER_CHANGED: u32 = WM_USER + 132;
+// Posted when the monitored folder has been read, with a boxed
+// (generation, Vec<FileResult>)
+const WM_MONITORED_FOLDER_SCANNED: u32 = WM_USER + 133;
+// Posted when a scheduled export has run, with a boxed ScheduledExportDone
+const WM_SCHEDULED_EXPORT_DONE: u32 = WM_USER + 134;
+const WM_METADATA_LOADED: u32 = WM_USER + 109;
+const WM_SORT_DONE: u32 = WM_USER + 110;
+const WM_REMOTE_FAILED: u32 = WM_USER + 113;
+const WM_DOWNLOAD_DONE: u32 = WM_USER + 114;
+const WM_GIT_STATUS_LOADED: u32 = WM_USER + 115;
+const WM_LINK_INFO_LOADED: u32 = WM_USER + 135;
+const WM_MEDIA_INFO_LOADED: u32 = WM_USER + 136;
+// Search batches for the launcher; 137 and 138 are the launcher's own
+const WM_LAUNCHER_RESULTS: u32 = WM_USER + 139;
+// Posted with a boxed IndexPage while browsing the whole index
+const WM_INDEX_PAGE: u32 = WM_USER + 140;
+// Posted when a search found more results than fit in the memory budget,
+// with their number in WPARAM and the search generation in LPARAM
+const WM_OVER_MEMORY_BUDGET: u32 = WM_USER + 142;
+const WM_UPDATE_CHECK_DONE: u32 = WM_USER + 118;
+const WM_UPDATE_PROGRESS: u32 = WM_USER + 119;
+const WM_UPDATE_DOWNLOADED: u32 = WM_USER + 120;
+
+// Automatic update checks run at most this often, in seconds
+const UPDATE_CHECK_INTERVAL: u64 = 24 * 60 * 60;
 
 // Timer IDs
 const SEARCH_TIMER_ID: usize = 1001;
+const SORT_PROGRESS_TIMER_ID: usize = 1002;
+const PERF_HUD_TIMER_ID: usize = 1003;
+// Waits for the selection to settle before the folder tree follows it
+const FOLDER_TREE_TIMER_ID: usize = 1004;
+// Looks for scheduled exports that are due
+const SCHEDULE_TIMER_ID: usize = 1005;
+// Waits for the selection to settle before the preview pane loads it
+const PREVIEW_TIMER_ID: usize = 1006;
+// Scrolls the list view while entries are dragged near its top or bottom
+const REORDER_SCROLL_TIMER_ID: usize = 1007;
+// How near, in pixels
+const REORDER_SCROLL_MARGIN: i32 = 20;
+
+// Performance HUD overlay in the top right corner of the list view
+const PERF_HUD_WIDTH: i32 = 420;
+const PERF_HUD_LINE_HEIGHT: i32 = 18;
+const PERF_HUD_LINES: i32 = 6;
+const PERF_HUD_MARGIN: i32 = 8;
 
 // Window class names
 const MAIN_WINDOW_CLASS: &str = "EverythingLikeMainWindow";
 const LIST_VIEW_CLASS: &str = "EverythingLikeListView";
+const STATS_WINDOW_CLASS: &str = "EverythingLikeStatsWindow";
 
 // Control IDs
 const ID_SEARCH_EDIT: i32 = 1001;
 const ID_LIST_VIEW: i32 = 1002;
 const ID_STATUS_BAR: i32 = 1003;
+const ID_FOLDER_TREE: i32 = 1004;
+const ID_PREVIEW_PANE: i32 = 1005;
+const ID_SYNTAX_HELP_BUTTON: i32 = 1006;
+const ID_SEARCH_TABS: i32 = 1007;
 
 // Header height for details view
 const HEADER_HEIGHT: i32 = 25;
 
+// Width of the folder tree sidebar, at most a third of the window
+const FOLDER_TREE_WIDTH: i32 = 240;
+
+// Width of the preview pane, at most a third of the window
+const PREVIEW_PANE_WIDTH: i32 = 360;
+
 // Menu IDs for view modes
 const ID_VIEW_DETAILS: i32 = 2001;
 const ID_VIEW_MEDIUM_ICONS: i32 = 2002;
 const ID_VIEW_LARGE_ICONS: i32 = 2003;
 const ID_VIEW_EXTRALARGE_ICONS: i32 = 2004;
+const ID_VIEW_FOLDER_TREE: i32 = 2005;
+
+// Sidebar context menu
+const ID_SIDEBAR_PIN_FOLDER: i32 = 2006;
+const ID_SIDEBAR_UNPIN: i32 = 2007;
+const ID_SIDEBAR_PIN_SEARCH: i32 = 2008;
+const ID_SIDEBAR_PIN_LIST: i32 = 2009;
+
+const ID_VIEW_IMAGE_VIEWER: i32 = 2010;
+const ID_VIEW_PREVIEW_PANE: i32 = 2011;
+const ID_VIEW_GROUP_BY_DRIVE: i32 = 2012;
+
+// Tab strip context menu
+const ID_CLOSE_SEARCH_TAB: i32 = 2013;
 
 // Menu IDs for thumbnail strategies
 const ID_THUMB_DEFAULT: i32 = 3001;
@@ -134,11 +535,41 @@ const ID_BG_GRAY: i32 = 3105;
 const ID_BG_LIGHT_GRAY: i32 = 3106;
 const ID_BG_DARK_GRAY: i32 = 3107;
 
+// Menu IDs for thumbnail captions
+const ID_CAPTION_NAME: i32 = 3201;
+const ID_CAPTION_NAME_SIZE: i32 = 3202;
+const ID_CAPTION_NAME_DIMENSIONS: i32 = 3203;
+const ID_CAPTION_NONE: i32 = 3204;
+
 // Menu IDs for file context menu
 const ID_OPEN_FILE: i32 = 4001;
 const ID_OPEN_FILE_LOCATION: i32 = 4002;
 const ID_COPY_PATH: i32 = 4003;
 const ID_COPY_NAME: i32 = 4004;
+const ID_DOWNLOAD_FILE: i32 = 4005;
+const ID_OPEN_CMD: i32 = 4006;
+const ID_OPEN_POWERSHELL: i32 = 4007;
+const ID_OPEN_WINDOWS_TERMINAL: i32 = 4008;
+const ID_OPEN_WSL: i32 = 4009;
+const ID_COPY_WSL_PATH: i32 = 4010;
+const ID_EXCLUDE_FOLDER: i32 = 4011;
+// Tags submenu; one ID per tag from ID_TAG_FIRST
+const ID_TAG_NEW: i32 = 4012;
+const ID_TAG_CLEAR: i32 = 4013;
+const ID_QUICK_VIEW: i32 = 4014;
+const ID_PREVIEW_VIDEO: i32 = 4015;
+const ID_BROWSE_ARCHIVE: i32 = 4016;
+const ID_VERIFY_CHECKSUMS: i32 = 4017;
+const ID_BROWSE_FOLDER_IN_TAB: i32 = 4018;
+const ID_COPY_AS_TABLE: i32 = 4019;
+const ID_TAG_FIRST: i32 = 4020;
+// After the tag IDs
+const ID_EDIT_NOTE: i32 = 4050;
+const ID_REMOVE_NOTE: i32 = 4051;
+// Rating submenu, from no stars to MAX_RATING
+const ID_RATING_FIRST: i32 = 4052;
+const ID_COMPARE_CONTENTS: i32 = 4058;
+const ID_COMPARE_IN_DIFF_TOOL: i32 = 4059;
 
 // Menu IDs for column management
 const ID_COLUMN_NAME: i32 = 5001;
@@ -146,16 +577,110 @@ const ID_COLUMN_SIZE: i32 = 5002;
 const ID_COLUMN_TYPE: i32 = 5003;
 const ID_COLUMN_MODIFIED: i32 = 5004;
 const ID_COLUMN_PATH: i32 = 5005;
+const ID_COLUMN_GIT_STATUS: i32 = 5006;
+const ID_COLUMN_TAGS: i32 = 5007;
+const ID_PATH_ELLIPSIS_END: i32 = 5008;
+const ID_PATH_ELLIPSIS_MIDDLE: i32 = 5009;
+const ID_COLUMN_HARD_LINKS: i32 = 5010;
+const ID_COLUMN_STREAMS: i32 = 5011;
+const ID_COLUMN_DURATION: i32 = 5012;
+const ID_COLUMN_BITRATE: i32 = 5013;
+const ID_SIZE_FORMAT_AUTO: i32 = 5014;
+const ID_SIZE_FORMAT_KILOBYTES: i32 = 5015;
+const ID_SIZE_FORMAT_BYTES: i32 = 5016;
+const ID_COLUMN_SIZE_TO_FIT: i32 = 5017;
+const ID_COLUMN_RESET_LAYOUT: i32 = 5018;
+const ID_COLUMN_MORE: i32 = 5019;
+const ID_FREEZE_NAME_COLUMN: i32 = 5020;
+const ID_COLUMN_NOTES: i32 = 5021;
+const ID_COLUMN_RATING: i32 = 5022;
+// Align Right items, one per column in the order of AppState::columns
+const ID_ALIGN_RIGHT_FIRST: i32 = 5101;
+const ID_ALIGN_RIGHT_LAST: i32 = 5199;
 
 // Menu IDs for language management
-const ID_LANG_ENGLISH: i32 = 6001;
-const ID_LANG_CHINESE: i32 = 6002;
+// One item per entry in Language::ALL
+const ID_LANG_FIRST: i32 = 6001;
+const ID_LANG_EDIT_TRANSLATIONS: i32 = 6100;
+const ID_LANG_VALIDATE_TRANSLATIONS: i32 = 6101;
 
 // Menu IDs for file operations
 const ID_FILE_OPEN_LIST: i32 = 7001;
 const ID_FILE_SAVE_LIST: i32 = 7002;
 const ID_FILE_EXPORT_LIST: i32 = 7003;
 const ID_FILE_CLOSE_LIST: i32 = 7004;
+const ID_FILE_PIN_CURRENT_LIST: i32 = 7005;
+const ID_FILE_CLEAR_RECENT_LISTS: i32 = 7006;
+const ID_FILE_REMOVE_MISSING: i32 = 7007;
+const ID_FILE_EXPORT_SELECTION: i32 = 7008;
+const ID_FILE_EXPORT_CONTACT_SHEET: i32 = 7009;
+const ID_FILE_PASTE_PATHS: i32 = 7010;
+const ID_FILE_EXPORT_M3U: i32 = 7011;
+const ID_FILE_LIST_STATISTICS: i32 = 7012;
+const ID_FILE_RESTORE_SESSION: i32 = 7013;
+const ID_FILE_SETTINGS: i32 = 7014;
+const ID_FILE_EXPORT_SETTINGS: i32 = 7015;
+const ID_FILE_IMPORT_SETTINGS: i32 = 7016;
+const ID_PROFILE_SAVE: i32 = 7017;
+const ID_PROFILE_DELETE: i32 = 7018;
+const ID_FILE_MONITOR_FOLDER: i32 = 7019;
+const ID_FILE_PRINT_PREVIEW: i32 = 7020;
+const ID_FILE_PRINT: i32 = 7021;
+
+// Menu ID range for recent list entries (one ID per entry)
+const ID_FILE_RECENT_LIST_FIRST: i32 = 7100;
+const ID_FILE_RECENT_LIST_LAST: i32 = 7199;
+
+// Contact sheet options; offsets index CONTACT_SHEET_*_CHOICES
+const ID_CONTACT_SHEET_COLUMNS_FIRST: i32 = 7200;
+const ID_CONTACT_SHEET_CELL_SIZE_FIRST: i32 = 7210;
+
+// Menu ID range for profile entries (one ID per profile)
+const ID_PROFILE_FIRST: i32 = 7300;
+
+// Remote Server submenu; servers take IDs from ID_REMOTE_FIRST on
+const ID_REMOTE_LOCAL: i32 = 7400;
+const ID_REMOTE_ADD: i32 = 7401;
+const ID_REMOTE_REMOVE: i32 = 7402;
+const ID_REMOTE_FIRST: i32 = 7410;
+
+// Tools menu; tools run from ID_TOOL_FIRST and are removed from
+// ID_TOOL_REMOVE_FIRST, one ID per tool
+const ID_TOOL_ADD: i32 = 7500;
+const ID_TOOL_FIND_DUPLICATES: i32 = 7501;
+const ID_TOOL_DISK_USAGE: i32 = 7502;
+const ID_TOOL_EMPTY_FOLDERS: i32 = 7503;
+const ID_TOOL_STATISTICS: i32 = 7504;
+const ID_TOOL_TIMELINE: i32 = 7505;
+const ID_TOOL_REBUILD_DATABASE: i32 = 7506;
+const ID_TOOL_UPDATE_FOLDER_INDEXES: i32 = 7507;
+const ID_TOOL_OPEN_HISTORY: i32 = 7508;
+const ID_TOOL_RECORD_HISTORY: i32 = 7509;
+const ID_TOOL_FIRST: i32 = 7510;
+const ID_TOOL_REMOVE_FIRST: i32 = 7540;
+
+// Bookmarks and Filters menus; one ID per bookmark from ID_BOOKMARK_FIRST
+// and per filter from ID_FILTER_FIRST
+const ID_IMPORT_EVERYTHING: i32 = 7600;
+const ID_BOOKMARK_FIRST: i32 = 7610;
+// Scheduled Exports submenu of Bookmarks; each export takes
+// SCHEDULED_EXPORT_ID_STRIDE IDs from ID_SCHEDULED_EXPORT_FIRST: Run Now,
+// Remove, then one per SCHEDULED_EXPORT_INTERVALS choice
+const ID_SCHEDULED_EXPORT_ADD: i32 = 7710;
+const ID_SCHEDULED_EXPORT_FIRST: i32 = 7720;
+const SCHEDULED_EXPORT_ID_STRIDE: i32 = 8;
+const SCHEDULED_EXPORT_RUN: i32 = 0;
+const SCHEDULED_EXPORT_REMOVE: i32 = 1;
+const SCHEDULED_EXPORT_INTERVAL_FIRST: i32 = 2;
+const ID_FILTER_NONE: i32 = 7800;
+const ID_FILTER_FIRST: i32 = 7810;
+// Excluded Folders submenu of Filters; one ID per folder from
+// ID_EXCLUDED_FOLDER_FIRST
+const ID_EXCLUDED_FOLDERS_CLEAR: i32 = 7870;
+const ID_EXCLUDED_FOLDER_FIRST: i32 = 7871;
+
+// Help menu
+const ID_HELP_ABOUT: i32 = 7900;
 
 // Menu IDs for sort operations
 const ID_SORT_NAME: i32 = 8001;
@@ -165,6 +690,30 @@ const ID_SORT_DATE: i32 = 8004;
 const ID_SORT_PATH: i32 = 8005;
 const ID_SORT_ASCENDING: i32 = 8006;
 const ID_SORT_DESCENDING: i32 = 8007;
+const ID_SORT_LOCALE: i32 = 8008;
+const ID_SORT_RANK_BY_USE: i32 = 8009;
+
+// Control IDs for the statistics window
+const ID_STATS_TEXT: i32 = 9101;
+const ID_STATS_COPY: i32 = 9102;
+const ID_STATS_CLOSE: i32 = 9103;
+
+// Tray icon menu
+const ID_TRAY_SHOW: i32 = 9401;
+const ID_TRAY_EXIT: i32 = 9402;
+const ID_TRAY_NEW_SEARCH: i32 = 9403;
+const ID_TRAY_QUICK_SEARCH: i32 = 9404;
+const ID_TRAY_REVEAL_COPIED: i32 = 9405;
+const ID_TRAY_ADD_COPIED: i32 = 9406;
+const ID_TRAY_LAUNCHER: i32 = 9407;
+// Recent searches take IDs from here up to MAX_RECENT_SEARCHES
+const ID_TRAY_RECENT_FIRST: i32 = 9410;
+
+// Global hotkey that opens the quick search bar
+const QUICK_SEARCH_HOTKEY_ID: i32 = 1;
+const LAUNCHER_HOTKEY_ID: i32 = 2;
+// Results asked from Everything for the launcher, of which the best ranked are shown
+const LAUNCHER_CANDIDATES: usize = 200;
 
 #[derive(Clone, PartialEq, Debug)]
 enum ViewMode {
@@ -174,6 +723,28 @@ enum ViewMode {
     ExtraLargeIcons,
 }
 
+impl ViewMode {
+    // Name used for the view mode in the saved session
+    fn config_key(&self) -> &'static str {
+        match self {
+            ViewMode::Details => "details",
+            ViewMode::MediumIcons => "medium_icons",
+            ViewMode::LargeIcons => "large_icons",
+            ViewMode::ExtraLargeIcons => "extra_large_icons",
+        }
+    }
+
+    fn from_config_key(key: &str) -> Option<Self> {
+        match key {
+            "details" => Some(ViewMode::Details),
+            "medium_icons" => Some(ViewMode::MediumIcons),
+            "large_icons" => Some(ViewMode::LargeIcons),
+            "extra_large_icons" => Some(ViewMode::ExtraLargeIcons),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum ColumnType {
     Name,
@@ -181,8 +752,24 @@ enum ColumnType {
     Type,
     Modified,
     Path,
+    GitStatus,
+    Tags,
+    HardLinks,
+    Streams,
+    Duration,
+    Bitrate,
+    Notes,
+    Rating,
 }
 
+// Git statuses of the rows seen recently, including rows outside a
+// repository (None), so each path is only looked up once per search
+const GIT_STATUS_CACHE_SIZE: usize = 10_000;
+// Hard-link counts and streams of the rows seen recently, the same way
+const LINK_INFO_CACHE_SIZE: usize = 10_000;
+// Durations and bitrates of the media files seen recently
+const MEDIA_INFO_CACHE_SIZE: usize = 10_000;
+
 impl ColumnType {
     fn display_name(&self) -> &'static str {
         match self {
@@ -191,6 +778,14 @@ impl ColumnType {
             ColumnType::Type => "Type",
             ColumnType::Modified => "Date Modified",
             ColumnType::Path => "Path",
+            ColumnType::GitStatus => "Git",
+            ColumnType::Tags => "Tags",
+            ColumnType::HardLinks => "Links",
+            ColumnType::Streams => "Streams",
+            ColumnType::Duration => "Duration",
+            ColumnType::Bitrate => "Bitrate",
+            ColumnType::Notes => "Notes",
+            ColumnType::Rating => "Rating",
         }
     }
     
@@ -201,6 +796,111 @@ impl ColumnType {
             ColumnType::Type => 100,
             ColumnType::Modified => 120,
             ColumnType::Path => 300,
+            ColumnType::GitStatus => 90,
+            ColumnType::Tags => 120,
+            ColumnType::HardLinks => 50,
+            ColumnType::Streams => 60,
+            ColumnType::Duration => 70,
+            ColumnType::Bitrate => 80,
+            ColumnType::Notes => 200,
+            ColumnType::Rating => RATING_STAR_LEFT * 2 + RATING_STAR_WIDTH * MAX_RATING as i32,
+        }
+    }
+    
+    // Numbers and dates line up on the right so they are easy to compare;
+    // text stays on the left
+    fn default_align_right(&self) -> bool {
+        matches!(
+            self,
+            ColumnType::Size | ColumnType::Modified | ColumnType::HardLinks | ColumnType::Streams | ColumnType::Duration | ColumnType::Bitrate
+        )
+    }
+    
+    fn menu_label<'a>(&self, strings: &'a LanguageStrings) -> &'a str {
+        match self {
+            ColumnType::Name => &strings.column_name,
+            ColumnType::Size => &strings.column_size,
+            ColumnType::Type => &strings.column_type,
+            ColumnType::Modified => &strings.column_date_modified,
+            ColumnType::Path => &strings.column_path,
+            ColumnType::GitStatus => &strings.column_git,
+            ColumnType::Tags => &strings.column_tags,
+            ColumnType::HardLinks => &strings.column_hard_links,
+            ColumnType::Streams => &strings.column_streams,
+            ColumnType::Duration => &strings.column_duration,
+            ColumnType::Bitrate => &strings.column_bitrate,
+            ColumnType::Notes => &strings.column_notes,
+            ColumnType::Rating => &strings.column_rating,
+        }
+    }
+    
+    // Stable name used when the sort column is saved with the session
+    fn config_key(&self) -> &'static str {
+        match self {
+            ColumnType::Name => "name",
+            ColumnType::Size => "size",
+            ColumnType::Type => "type",
+            ColumnType::Modified => "modified",
+            ColumnType::Path => "path",
+            ColumnType::GitStatus => "git",
+            ColumnType::Tags => "tags",
+            ColumnType::HardLinks => "hard_links",
+            ColumnType::Streams => "streams",
+            ColumnType::Duration => "duration",
+            ColumnType::Bitrate => "bitrate",
+            ColumnType::Notes => "notes",
+            ColumnType::Rating => "rating",
+        }
+    }
+    
+    fn from_config_key(key: &str) -> Option<Self> {
+        match key {
+            "name" => Some(ColumnType::Name),
+            "size" => Some(ColumnType::Size),
+            "type" => Some(ColumnType::Type),
+            "modified" => Some(ColumnType::Modified),
+            "path" => Some(ColumnType::Path),
+            "git" => Some(ColumnType::GitStatus),
+            "tags" => Some(ColumnType::Tags),
+            "hard_links" => Some(ColumnType::HardLinks),
+            "streams" => Some(ColumnType::Streams),
+            "duration" => Some(ColumnType::Duration),
+            "bitrate" => Some(ColumnType::Bitrate),
+            "notes" => Some(ColumnType::Notes),
+            "rating" => Some(ColumnType::Rating),
+            _ => None,
+        }
+    }
+    
+    // The Git status, hard links and streams are looked up for the visible
+    // rows only, so there is nothing to sort the whole list by. Tags, notes
+    // and ratings aren't sorted by either.
+    fn is_sortable(&self) -> bool {
+        !matches!(
+            self,
+            ColumnType::GitStatus | ColumnType::Tags | ColumnType::HardLinks | ColumnType::Streams | ColumnType::Notes | ColumnType::Rating
+        )
+    }
+    
+    // Columns filled in by the link info loader
+    fn needs_link_info(&self) -> bool {
+        matches!(self, ColumnType::HardLinks | ColumnType::Streams)
+    }
+    
+    // Columns filled in by the media info loader
+    fn needs_media_info(&self) -> bool {
+        matches!(self, ColumnType::Duration | ColumnType::Bitrate)
+    }
+    
+    // Everything's ascending sort by this column, for browsing the index
+    fn everything_sort(&self) -> Option<u32> {
+        match self {
+            ColumnType::Name => Some(SORT_NAME_ASCENDING),
+            ColumnType::Path => Some(SORT_PATH_ASCENDING),
+            ColumnType::Size => Some(SORT_SIZE_ASCENDING),
+            ColumnType::Type => Some(SORT_TYPE_NAME_ASCENDING),
+            ColumnType::Modified => Some(SORT_DATE_MODIFIED_ASCENDING),
+            _ => None,
         }
     }
 }
@@ -210,6 +910,7 @@ struct ColumnInfo {
     column_type: ColumnType,
     width: i32,
     visible: bool,
+    align_right: bool,
 }
 
 impl ColumnInfo {
@@ -218,10 +919,87 @@ impl ColumnInfo {
             column_type,
             width: column_type.default_width(),
             visible: true,
+            align_right: column_type.default_align_right(),
+        }
+    }
+}
+
+// The columns in their fixed order, with their default widths and only
+// the common ones shown
+fn default_columns() -> Vec<ColumnInfo> {
+    let mut columns: Vec<ColumnInfo> = [
+        ColumnType::Name,
+        ColumnType::Size,
+        ColumnType::Type,
+        ColumnType::Modified,
+        ColumnType::Path,
+        ColumnType::GitStatus,
+        ColumnType::Tags,
+        ColumnType::HardLinks,
+        ColumnType::Streams,
+        ColumnType::Duration,
+        ColumnType::Bitrate,
+        ColumnType::Notes,
+        ColumnType::Rating,
+    ]
+    .into_iter()
+    .map(ColumnInfo::new)
+    .collect();
+    
+    // Hide some columns by default
+    columns[2].visible = false; // Type
+    columns[3].visible = false; // Modified
+    columns[5].visible = false; // Git status, which costs a repository scan
+    columns[6].visible = false; // Tags
+    columns[7].visible = false; // Hard links and streams, for cleanup and forensics
+    columns[8].visible = false;
+    columns[9].visible = false; // Duration and bitrate of audio and video files
+    columns[10].visible = false;
+    columns[11].visible = false; // Notes, which only lists have
+    columns[12].visible = false; // Star ratings
+    
+    columns
+}
+
+fn column_layout(columns: &[ColumnInfo]) -> Vec<ColumnLayout> {
+    columns
+        .iter()
+        .map(|column| ColumnLayout {
+            column: column.column_type.config_key().to_string(),
+            width: column.width,
+            visible: column.visible,
+            align_right: (column.align_right != column.column_type.default_align_right()).then_some(column.align_right),
+        })
+        .collect()
+}
+
+fn apply_column_layout(columns: &mut [ColumnInfo], layout: &[ColumnLayout]) {
+    for entry in layout {
+        let Some(column_type) = ColumnType::from_config_key(&entry.column) else {
+            continue;
+        };
+        if let Some(column) = columns.iter_mut().find(|column| column.column_type == column_type) {
+            column.width = entry.width.max(20);
+            column.visible = entry.visible;
+            column.align_right = entry.align_right.unwrap_or(column_type.default_align_right());
         }
     }
 }
 
+// Results being dragged: to the sidebar to pin them, or within an open
+// list to put them somewhere else in it. The drag starts once the mouse has
+// moved far enough from where the button went down.
+#[derive(Debug)]
+struct ResultDragState {
+    start: POINT,
+    is_dragging: bool,
+    // Where in the list the entries would go if dropped now
+    reorder_gap: Option<usize>,
+    // Item clicked inside a multi-selection; the selection becomes just
+    // this item if the button goes up without a drag
+    clicked_in_selection: Option<usize>,
+}
+
 #[derive(Debug)]
 struct ColumnDragState {
     is_dragging: bool,
@@ -230,6 +1008,56 @@ struct ColumnDragState {
     start_width: i32,
 }
 
+// Off-screen bitmap the list view is drawn into before being blitted to
+// the window. It is kept between paints and only recreated when the list
+// view changes size.
+struct Backbuffer {
+    dc: HDC,
+    bitmap: HBITMAP,
+    old_bitmap: HGDIOBJ,
+    width: i32,
+    height: i32,
+}
+
+impl Backbuffer {
+    unsafe fn new(window_dc: HDC, width: i32, height: i32) -> Self {
+        let dc = CreateCompatibleDC(window_dc);
+        let bitmap = CreateCompatibleBitmap(window_dc, width, height);
+        let old_bitmap = SelectObject(dc, bitmap);
+        Self { dc, bitmap, old_bitmap, width, height }
+    }
+}
+
+impl Drop for Backbuffer {
+    fn drop(&mut self) {
+        unsafe {
+            SelectObject(self.dc, self.old_bitmap);
+            DeleteObject(self.bitmap);
+            DeleteDC(self.dc);
+        }
+    }
+}
+
+// Selection and scroll position before an input event, so that only the
+// items that changed need repainting afterwards
+struct SelectionSnapshot {
+    items: BTreeSet<usize>,
+    focused: Option<usize>,
+    scroll_pos: i32,
+}
+
+// Selection and the first visible item, kept by path so they can be found
+// again after the list has been replaced by fresh results
+struct ViewAnchor {
+    selected_paths: std::collections::HashSet<String>,
+    focused_path: Option<String>,
+    anchor_path: Option<String>,
+    top_path: Option<String>,
+    // How far the top item was scrolled past the top edge
+    top_offset: i32,
+    scroll_pos: i32,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum SortOrder {
     None,
@@ -247,7 +1075,13 @@ struct SortState {
 struct AppState {
     main_window: HWND,
     search_edit: HWND,
+    // The "?" button beside the search box opening the syntax cheat sheet
+    syntax_button: HWND,
+    search_tabs: Option<SearchTabs>,
     list_view: HWND,
+    // The search box or the list, whichever had the keyboard when the
+    // window was last deactivated
+    last_focus: HWND,
     status_bar: HWND,
     list_data: Vec<FileResult>,
     visible_start: usize,
@@ -257,24 +1091,88 @@ struct AppState {
     total_height: i32,
     client_height: i32,
     client_width: i32,
+    // How far the details columns are scrolled to the left, in pixels
+    h_scroll_pos: i32,
+    // Width of the columns and of the page the horizontal scrollbar was
+    // last set to
+    h_scroll_range: (i32, i32),
+    // The volumes of the results while the details view is grouped by drive
+    drive_groups: Option<Vec<DriveGroup>>,
+    // Row count and search the drive groups were worked out for
+    drive_groups_key: (usize, u64),
+    // Labels of the drive groups showing their header only
+    collapsed_drives: HashSet<String>,
     font: HFONT,
+    strikeout_font: HFONT,
     everything_sdk: Option<EverythingSDK>,
     selected_index: Option<usize>,
+    // Multi-selection; selected_index remains the focused item
+    selected_items: BTreeSet<usize>,
+    selection_anchor: Option<usize>,
     view_mode: ViewMode,
     selected_view_size: u32,
     zoom_level: i32, // 0-14: 0=Details, 1-14=Icon sizes
     thumbnail_cache: LruCache<(String, u32), HBITMAP>,
     thumbnail_task_manager: Option<ThumbnailTaskManager>,
+    // Reads sizes and dates for the rows around the viewport
+    metadata_loader: Option<MetadataLoader>,
+    // Looks up Git statuses while the Git column is shown
+    git_status_loader: Option<GitStatusLoader>,
+    git_statuses: LruCache<String, Option<GitFileStatus>>,
+    link_info_loader: Option<LinkInfoLoader>,
+    link_infos: LruCache<String, Option<LinkInfo>>,
+    media_info_loader: Option<MediaInfoLoader>,
+    media_infos: LruCache<String, Option<MediaInfo>>,
+    // User tags of files, shown as colored dots and in the Tags column
+    tags: TagStore,
+    run_history: RunHistory,
+    // Reused by paint_list_view until the list view is resized
+    backbuffer: Option<Backbuffer>,
     grid_cols: i32,
     cell_size: i32,
     config: AppConfig,
     // Async search state
     search_cancel_flag: Arc<AtomicBool>,
+    // The launcher's search, separate from the result list's
+    launcher_generation: u64,
+    launcher_cancel_flag: Arc<AtomicBool>,
+    launcher_query: String,
+    launcher_candidates: Vec<FileResult>,
     search_generation: Arc<AtomicU64>,
     last_search_time: Instant,
     pending_search_query: String,
+    // Query of the latest search sent, and of the results on screen; when
+    // they match, the search was re-run and the view is kept in place
+    searched_query: String,
+    displayed_query: Option<String>,
+    // The search last found over the memory budget and whether the user
+    // chose to load all of it; either way they aren't asked again for it
+    memory_budget_answer: Option<(String, bool)>,
+    // The rows of the running search are in sort order, so its batches can
+    // be merged at the positions the search thread sends
+    stream_sorted: bool,
     // Search channel for thread-safe Everything SDK access
     search_sender: Option<mpsc::Sender<SearchRequest>>,
+    // ETP server searched instead of the local index, and the channel to
+    // the thread that holds its connection
+    remote_server: Option<RemoteServer>,
+    remote_sender: Option<mpsc::Sender<SearchRequest>>,
+    // Filter from the Filters menu, combined with every search
+    active_filter: Option<SearchFilter>,
+    // Sidebar shown with View > Folder Tree
+    folder_tree: Option<FolderTree>,
+    preview_pane: Option<PreviewPane>,
+    index_browse: Option<IndexBrowse>,
+    // What the sidebar's context menu was opened on
+    sidebar_menu_item: Option<SidebarItem>,
+    // The tab the tab strip's context menu was opened on
+    search_tab_menu_index: Option<usize>,
+    // Column whose header the header menu was opened on
+    header_menu_column: Option<ColumnType>,
+    // Folder picked in the folder tree; searches are limited to it
+    search_scope: Option<String>,
+    // Localhost HTTP endpoint, running while enabled in Settings
+    http_server: Option<HttpServer>,
     // Search debounce timer
     search_timer_active: bool,
     // Scrollbar dragging state
@@ -282,78 +1180,158 @@ struct AppState {
     // Column configuration
     columns: Vec<ColumnInfo>,
     column_drag_state: Option<ColumnDragState>,
+    result_drag_state: Option<ResultDragState>,
     // Sorting state
     sort_state: Option<SortState>,
+    // Sort running in the background and the generation of the latest one
+    sort_task: Option<SortTask>,
+    sort_generation: u64,
+    // Hidden performance overlay, toggled with Ctrl+Shift+P
+    perf_hud_visible: bool,
     // File list mode state
     is_list_mode: bool,
     current_list_name: Option<String>,
+    current_list_path: Option<String>,
     original_list_data: Vec<FileResult>,
+    // Notes on the entries of the open list, saved with it
+    list_notes: ListNotes,
+    list_validation_generation: u64,
+    missing_count: usize,
+    // Contact sheet export running in the background
+    contact_sheet_running: bool,
+    // Watches the open list file for external changes
+    list_watcher: Option<ListWatcher>,
+    list_watch_generation: u64,
+    list_file_modified: Option<std::time::SystemTime>,
+    list_reload_prompt_active: bool,
+    // Folder whose files are shown as a live list, and its watcher
+    monitored_folder: Option<String>,
+    folder_monitor: Option<FolderMonitor>,
+    folder_monitor_generation: u64,
+    // Restored session whose sort and scroll wait for the first search results
+    pending_session: Option<SessionState>,
+    // Window is hidden with only the notification area icon showing
+    tray_icon_visible: bool,
+    // Paths last copied in another program, offered by the clipboard
+    // watcher; the tray icon may be up only to show that offer
+    copied_paths: Vec<String>,
+    tray_icon_for_balloon: bool,
+    // Percentage of an update being downloaded
+    update_progress: Option<u32>,
+    // A verified update that is installed when the app exits
+    pending_update: Option<PathBuf>,
+    // Output paths of the scheduled exports running right now
+    running_exports: std::collections::HashSet<String>,
 }
 
-static mut APP_STATE: Option<AppState> = None;
+// The state is owned by the main window: main() boxes it and passes it as
+// the creation parameter, WM_NCCREATE keeps the pointer in GWLP_USERDATA and
+// WM_NCDESTROY frees it. Child windows reach it through their top-level
+// parent, so window procedures look it up from the HWND they are given and
+// pass it down to the functions they call.
+unsafe fn app_state<'a>(window: HWND) -> Option<&'a mut AppState> {
+    let main_window = GetAncestor(window, GA_ROOT);
+    let state = GetWindowLongPtrW(main_window, GWLP_USERDATA) as *mut AppState;
+    state.as_mut()
+}
 
 impl AppState {
     fn new() -> Self {
-        let config = load_config();
+        let mut config = load_config();
+        
+        // The Run key can be edited outside the app, so trust it over the config
+        config.start_with_windows = is_autostart_enabled();
+        config.explorer_verb = is_explorer_verb_registered();
+        config.url_protocol = is_url_protocol_registered();
         
         // Initialize language manager
         init_language_manager();
         
         // Set language from config
-        let language = match config.language {
-            LanguageCode::English => Language::English,
-            LanguageCode::Chinese => Language::Chinese,
-        };
+        let language = Language::from_code(&config.language.to_string());
         if let Err(e) = set_language(language) {
-            println!("Failed to set language: {}", e);
+            log_warn(&format!("Failed to set language: {}", e));
         }
         
         // Initialize icon cache
         init_icon_cache();
         
-        // Initialize default columns
-        let mut columns = Vec::new();
-        columns.push(ColumnInfo::new(ColumnType::Name));
-        columns.push(ColumnInfo::new(ColumnType::Size));
-        columns.push(ColumnInfo::new(ColumnType::Type));
-        columns.push(ColumnInfo::new(ColumnType::Modified));
-        columns.push(ColumnInfo::new(ColumnType::Path));
+        let mut columns = default_columns();
         
-        // Hide some columns by default
-        columns[2].visible = false; // Type
-        columns[3].visible = false; // Modified
+        // Widths and visibility saved from the last run
+        apply_column_layout(&mut columns, &config.columns);
         
         Self {
             main_window: HWND(0),
             search_edit: HWND(0),
+            syntax_button: HWND(0),
+            search_tabs: None,
             list_view: HWND(0),
+            last_focus: HWND(0),
             status_bar: HWND(0),
             list_data: Vec::new(),
             visible_start: 0,
             visible_count: 0,
             item_height: 20,
             scroll_pos: 0,
+            h_scroll_pos: 0,
+            h_scroll_range: (0, 0),
+            drive_groups: None,
+            drive_groups_key: (0, 0),
+            collapsed_drives: HashSet::new(),
             total_height: 0,
             client_height: 0,
             client_width: 0,
             font: HFONT(0),
+            strikeout_font: HFONT(0),
             everything_sdk: None,
             selected_index: None,
+            selected_items: BTreeSet::new(),
+            selection_anchor: None,
             view_mode: ViewMode::Details,
             selected_view_size: 0,
             zoom_level: 0, // Start at Details view
-            thumbnail_cache: LruCache::new(NonZeroUsize::new(500).unwrap()),
+            thumbnail_cache: LruCache::new(NonZeroUsize::new(config.thumbnail_cache_size.max(1) as usize).unwrap()),
             thumbnail_task_manager: None,
+            metadata_loader: None,
+            git_status_loader: None,
+            git_statuses: LruCache::new(NonZeroUsize::new(GIT_STATUS_CACHE_SIZE).unwrap()),
+            link_info_loader: None,
+            link_infos: LruCache::new(NonZeroUsize::new(LINK_INFO_CACHE_SIZE).unwrap()),
+            media_info_loader: None,
+            media_infos: LruCache::new(NonZeroUsize::new(MEDIA_INFO_CACHE_SIZE).unwrap()),
+            tags: TagStore::open(),
+            run_history: RunHistory::open(),
+            backbuffer: None,
             grid_cols: 1,
             cell_size: 20,
             config,
             // Async search state
             search_cancel_flag: Arc::new(AtomicBool::new(false)),
+            launcher_generation: 0,
+            launcher_cancel_flag: Arc::new(AtomicBool::new(false)),
+            launcher_query: String::new(),
+            launcher_candidates: Vec::new(),
             search_generation: Arc::new(AtomicU64::new(0)),
             last_search_time: Instant::now(),
             pending_search_query: String::new(),
+            searched_query: String::new(),
+            memory_budget_answer: None,
+            displayed_query: None,
+            stream_sorted: false,
             // Search channel for thread-safe Everything SDK access
             search_sender: None,
+            remote_server: None,
+            remote_sender: None,
+            active_filter: None,
+            folder_tree: None,
+            preview_pane: None,
+            index_browse: None,
+            sidebar_menu_item: None,
+            search_tab_menu_index: None,
+            header_menu_column: None,
+            search_scope: None,
+            http_server: None,
             // Search debounce timer
             search_timer_active: false,
             // Scrollbar dragging state
@@ -361,12 +1339,35 @@ impl AppState {
             // Column configuration
             columns,
             column_drag_state: None,
+            result_drag_state: None,
             // Sorting state
             sort_state: None,
+            sort_task: None,
+            sort_generation: 0,
+            perf_hud_visible: false,
             // File list mode state
             is_list_mode: false,
             current_list_name: None,
+            current_list_path: None,
             original_list_data: Vec::new(),
+            list_notes: ListNotes::default(),
+            list_validation_generation: 0,
+            missing_count: 0,
+            contact_sheet_running: false,
+            list_watcher: None,
+            list_watch_generation: 0,
+            list_file_modified: None,
+            list_reload_prompt_active: false,
+            monitored_folder: None,
+            folder_monitor: None,
+            folder_monitor_generation: 0,
+            pending_session: None,
+            tray_icon_visible: false,
+            copied_paths: Vec::new(),
+            tray_icon_for_balloon: false,
+            update_progress: None,
+            pending_update: None,
+            running_exports: std::collections::HashSet::new(),
         }
     }
 
@@ -402,10 +1403,45 @@ impl AppState {
         }
     }
 
+    // Tells Everything to rebuild its database, or to rescan its folder
+    // indexes, once the user has confirmed
+    fn run_everything_command(&self, rebuild: bool) {
+        let strings = get_strings();
+        let confirmed = unsafe {
+            MessageBoxW(
+                self.main_window,
+                PCWSTR::from_raw(to_wide(&strings.confirm_clear_index).as_ptr()),
+                PCWSTR::from_raw(to_wide(&strings.confirm_title).as_ptr()),
+                MB_ICONQUESTION | MB_YESNO | MB_DEFBUTTON2,
+            )
+        } == IDYES;
+        if !confirmed {
+            return;
+        }
+        
+        let result = EverythingSDK::new().and_then(|sdk| {
+            if rebuild {
+                sdk.rebuild_database()
+            } else {
+                sdk.update_all_folder_indexes()
+            }
+        });
+        match result {
+            Ok(()) => log_info(if rebuild { "Asked Everything to rebuild its database" } else { "Asked Everything to update its folder indexes" }),
+            Err(e) => {
+                log_warn(&format!("Everything command failed: {}", e));
+                let message = fill(&strings.error_everything_command, &[("error", &e.to_string())]);
+                unsafe {
+                    MessageBoxW(self.main_window, PCWSTR::from_raw(to_wide(&message).as_ptr()), w!("Error"), MB_ICONERROR | MB_OK);
+                }
+            }
+        }
+    }
+    
     fn initialize_everything_sdk(&mut self) {
         match EverythingSDK::new() {
             Ok(sdk) => {
-                log_debug("Everything SDK loaded successfully");
+                log_info("Everything SDK loaded successfully");
                 
                 // Create a channel for search requests
                 let (sender, receiver) = mpsc::channel::<SearchRequest>();
@@ -416,7 +1452,7 @@ impl AppState {
                 std::thread::spawn(move || {
                     log_debug("Everything SDK search thread started");
                     
-                    while let Ok(request) = receiver.recv() {
+                    while let Ok(mut request) = receiver.recv() {
                         log_debug(&format!("Processing search request: {:?}", request.query));
                         
                         // Check if cancelled before starting
@@ -425,8 +1461,11 @@ impl AppState {
                             continue;
                         }
                         
-                        // Add debounce delay
-                        std::thread::sleep(Duration::from_millis(150));
+                        // Add debounce delay; later pages of the index are
+                        // asked for by scrolling, not typing
+                        if request.page.map_or(true, |page| page.offset == 0) {
+                            std::thread::sleep(Duration::from_millis(150));
+                        }
                         
                         // Check if cancelled after delay
                         if request.cancel_flag.load(Ordering::Relaxed) {
@@ -434,55 +1473,76 @@ impl AppState {
                             continue;
                         }
                         
-                        log_debug("Performing Everything SDK search");
-                        
-                        // Perform the search with mutex protection
-                        let search_result = {
-                            let _guard = EVERYTHING_SDK_MUTEX.lock().unwrap();
-                            if request.query.trim().is_empty() {
-                                sdk.search_files("*.png")
-                            } else {
-                                sdk.search_files(&request.query)
-                            }
-                        };
-                        
-                        // Check if cancelled after search
-                        if request.cancel_flag.load(Ordering::Relaxed) {
-                            log_debug("Search request was cancelled after SDK search");
+                        if let Some(page) = request.page {
+                            read_index_page(&sdk, &request, page);
                             continue;
                         }
                         
-                        log_debug("Everything SDK search completed, sending results");
+                        log_debug("Performing Everything SDK search");
                         
-                        // Send results back to UI thread
-                        match search_result {
-                            Ok(file_paths) => {
-                                log_debug(&format!("Converting {} file paths to FileResult objects", file_paths.len()));
-                                
-                                let results: Vec<crate::everything_sdk::FileResult> = file_paths
-                                    .into_iter()
-                                    .map(|path| crate::everything_sdk::FileResult::from_path(&path))
-                                    .collect();
-                                
-                                // Allocate results in a Box and send the pointer
-                                let boxed_results = Box::new((results, request.generation));
-                                let results_ptr = Box::into_raw(boxed_results) as isize;
-                                
-                                log_debug(&format!("Posting WM_SEARCH_RESULTS message with ptr: {}", results_ptr));
-                                
-                                unsafe {
-                                    let _ = PostMessageW(request.window, WM_SEARCH_RESULTS, WPARAM(results_ptr as usize), LPARAM(0));
+                        // Read the results with mutex protection and post each
+                        // batch as soon as it is ready
+                        let mut first = true;
+                        let mut total = 0;
+                        let mut over_budget = None;
+                        let (max_results, memory_budget) = (request.max_results, request.memory_budget);
+                        let filtered = request.filter.is_some();
+                        let search_result = {
+                            let _stage = time_stage("query");
+                            let _guard = EVERYTHING_SDK_MUTEX.lock().unwrap();
+                            let limit = |found: usize| {
+                                // Filtered searches read on until enough results pass
+                                if filtered {
+                                    return found;
+                                }
+                                let wanted = found.min(max_results);
+                                let shown = results_within_budget(wanted, memory_budget);
+                                if shown < wanted {
+                                    over_budget = Some(wanted);
+                                }
+                                shown
+                            };
+                            sdk.search_files_in_batches_limited(&request.query, limit, |mut results, last| {
+                                // Check if cancelled between batches
+                                if request.cancel_flag.load(Ordering::Relaxed) {
+                                    log_debug("Search request was cancelled while reading results");
+                                    return false;
                                 }
-                            }
-                            Err(e) => {
-                                log_debug(&format!("Everything SDK search failed: {}", e));
-                                // Send empty results on error
-                                let boxed_results = Box::new((Vec::<crate::everything_sdk::FileResult>::new(), request.generation));
-                                let results_ptr = Box::into_raw(boxed_results) as isize;
                                 
-                                unsafe {
-                                    let _ = PostMessageW(request.window, WM_SEARCH_RESULTS, WPARAM(results_ptr as usize), LPARAM(0));
+                                let mut last = last;
+                                if let Some(filter) = &request.filter {
+                                    results.retain_mut(|file| filter.matches(file));
+                                    results.truncate(max_results - total);
+                                    last |= total + results.len() >= max_results;
+                                    if results.is_empty() && !first && !last {
+                                        return true;
+                                    }
                                 }
+                                
+                                let positions = request.sorter.as_mut().map(|sorter| sorter.insert_batch(&mut results));
+                                total += results.len();
+                                
+                                log_debug(&format!("Posting {} results ({} so far, last batch: {})", results.len(), total, last));
+                                post_search_batch(request.window, request.message, SearchBatch {
+                                    results,
+                                    positions,
+                                    total,
+                                    generation: request.generation,
+                                    first,
+                                    last,
+                                });
+                                first = false;
+                                !last
+                            })
+                        };
+                        
+                        if let Err(e) = search_result {
+                            log_error(&format!("Everything SDK search failed: {}", e));
+                            // Send empty results on error
+                            post_search_batch(request.window, request.message, SearchBatch::complete(Vec::new(), request.generation));
+                        } else if let Some(found) = over_budget {
+                            if !request.cancel_flag.load(Ordering::Relaxed) {
+                                post_over_memory_budget(&request, found);
                             }
                         }
                         
@@ -492,12 +1552,12 @@ impl AppState {
                     log_debug("Everything SDK search thread terminated");
                 });
                 
-                // Start initial async search with PNG files
-                self.start_async_search("*.png".to_string());
+                // Start initial async search with the configured default
+                self.start_async_search(self.config.default_search.clone());
             }
             Err(e) => {
-                log_debug(&format!("Failed to load Everything SDK: {}", e));
-                log_debug("Falling back to sample data");
+                log_warn(&format!("Failed to load Everything SDK: {}", e));
+                log_info("Falling back to sample data");
                 self.everything_sdk = None;
                 
                 // For sample data, use the old rayon approach since it's thread-safe
@@ -505,1188 +1565,5203 @@ impl AppState {
             }
         }
     }
-
-    fn initialize_thumbnail_task_manager(&mut self, window: HWND) {
-        self.thumbnail_task_manager = Some(ThumbnailTaskManager::new(window));
+    
+    // True while the rows are search results from an ETP server
+    fn showing_remote_results(&self) -> bool {
+        self.remote_server.is_some() && !self.is_list_mode
     }
-
-    fn load_from_everything_sdk(&mut self, query: &str) -> std::result::Result<(), String> {
-        if let Some(ref sdk) = self.everything_sdk {
-            println!("Searching for: {}", query);
-            
-            // Search for files
-            match sdk.search_files(query) {
-                Ok(file_paths) => {
-                    println!("Found {} results", file_paths.len());
-                    
-                    // Convert paths to FileResult objects
-                    self.list_data = file_paths
-                        .into_iter()
-                        .map(|path| FileResult::from_path(&path))
-                        .collect();
-                    
-                    // Limit results to prevent UI slowdown during testing
-                    if self.list_data.len() > 50000 {
-                        self.list_data.truncate(50000);
-                        println!("Truncated results to 50000 items for performance");
-                    }
-                    
-                    // Reset selection when new data loads
-                    self.selected_index = if !self.list_data.is_empty() { Some(0) } else { None };
-                    
-                    // Clear thumbnail cache when loading new data
-                    self.thumbnail_cache.clear();
-                    
-                    self.calculate_layout();
-                    Ok(())
-                }
-                Err(e) => Err(format!("Search failed: {}", e))
-            }
+    
+    // Sends searches to the server at `index` of the Remote Server menu. The
+    // connection is opened on the search thread, which posts
+    // WM_REMOTE_FAILED if it can't be.
+    fn connect_remote_server(&mut self, index: usize) {
+        let Some(server) = self.config.remote_servers.get(index).cloned() else {
+            return;
+        };
+        log_info(&format!("Searching ETP server {}", server.display_name()));
+        
+        let (sender, receiver) = mpsc::channel::<SearchRequest>();
+        let thread_server = server.clone();
+        std::thread::spawn(move || remote_search_thread(thread_server, receiver));
+        
+        self.remote_server = Some(server);
+        self.remote_sender = Some(sender);
+        self.search_again_after_source_change();
+    }
+    
+    fn disconnect_remote_server(&mut self) {
+        if self.remote_server.take().is_none() {
+            return;
+        }
+        
+        // Dropping the sender ends the search thread and its connection
+        self.remote_sender = None;
+        log_info("Searching this computer again");
+        self.search_again_after_source_change();
+    }
+    
+    // Shows the current query's results from the newly chosen search source
+    fn search_again_after_source_change(&mut self) {
+        if self.is_list_mode {
+            // Closing the list searches again by itself
+            self.close_file_list();
         } else {
-            Err("Everything SDK not initialized".to_string())
+            handle_immediate_search(self);
         }
+        
+        let _ = recreate_menus_with_language(self.main_window, self);
+        update_status_bar(self);
     }
-
-    fn calculate_layout(&mut self) {
-        log_debug(&format!("calculate_layout called, current scroll_pos: {}", self.scroll_pos));
+    
+    fn add_remote_server(&mut self) {
+        let strings = get_strings();
+        let Some(address) = prompt_text(self.main_window, &strings.remote_add_server, &strings.remote_server_prompt, "etp://", self.font) else {
+            return;
+        };
         
-        match self.view_mode {
-            ViewMode::Details => {
-                self.item_height = 20;
-                self.grid_cols = 1;
-                self.cell_size = self.item_height;
-                
-                // Account for header height in details view
-                let available_height = self.client_height - HEADER_HEIGHT;
-                self.visible_start = (self.scroll_pos / self.item_height) as usize;
-                self.visible_count = ((available_height / self.item_height) + 2) as usize;
-                self.total_height = self.list_data.len() as i32 * self.item_height;
+        let Some(server) = parse_server_address(&address) else {
+            unsafe {
+                MessageBoxW(
+                    self.main_window,
+                    PCWSTR::from_raw(to_wide(&strings.error_remote_address).as_ptr()),
+                    w!("Error"),
+                    MB_ICONERROR | MB_OK,
+                );
             }
-            _ => {
-                // Icon modes - add extra height for file name display
-                let padding = 8;
-                let filename_height = 40; // Reserve space for 2 lines of filename text
-                self.cell_size = self.selected_view_size as i32 + padding * 2 + filename_height;
-                self.grid_cols = if self.client_width > 0 && self.cell_size > 0 {
-                    (self.client_width / self.cell_size).max(1)
-                } else {
-                    1
-                };
-                
-                let total_rows = if self.grid_cols > 0 {
-                    (self.list_data.len() as i32 + self.grid_cols - 1) / self.grid_cols
-                } else {
-                    0
-                };
-                
-                self.total_height = total_rows * self.cell_size;
-                
-                // Calculate visible range for grid
-                let first_visible_row = self.scroll_pos / self.cell_size;
-                let visible_rows = (self.client_height / self.cell_size) + 2;
-                
-                self.visible_start = (first_visible_row * self.grid_cols) as usize;
-                self.visible_count = (visible_rows * self.grid_cols) as usize;
-            }
-        }
-        
-        // IMPORTANT: Clamp scroll_pos to valid range after layout changes
-        // This prevents losing view position when window size changes dramatically
-        let max_scroll = (self.total_height - self.client_height).max(0);
-        if self.scroll_pos > max_scroll {
-            log_debug(&format!("Clamping scroll_pos from {} to {} (max_scroll) due to layout change", 
-                self.scroll_pos, max_scroll));
-            self.scroll_pos = max_scroll;
-            
-            // Recalculate visible range with corrected scroll_pos
-            match self.view_mode {
-                ViewMode::Details => {
-                    let available_height = self.client_height - HEADER_HEIGHT;
-                    self.visible_start = (self.scroll_pos / self.item_height) as usize;
-                    self.visible_count = ((available_height / self.item_height) + 2) as usize;
-                }
-                _ => {
-                    let first_visible_row = self.scroll_pos / self.cell_size;
-                    let visible_rows = (self.client_height / self.cell_size) + 2;
-                    
-                    self.visible_start = (first_visible_row * self.grid_cols) as usize;
-                    self.visible_count = (visible_rows * self.grid_cols) as usize;
-                }
-            }
-        }
+            return;
+        };
         
-        // Bounds checking
-        if self.visible_start >= self.list_data.len() {
-            self.visible_start = if self.list_data.len() > 0 { self.list_data.len() - 1 } else { 0 };
+        // Adding a server again replaces its saved password
+        self.config.remote_servers.retain(|existing| {
+            !(existing.host.eq_ignore_ascii_case(&server.host) && existing.port == server.port && existing.username == server.username)
+        });
+        self.config.remote_servers.push(server);
+        if self.config.remote_servers.len() > MAX_REMOTE_SERVERS {
+            self.config.remote_servers.remove(0);
         }
         
-        if self.visible_start + self.visible_count > self.list_data.len() {
-            self.visible_count = self.list_data.len().saturating_sub(self.visible_start);
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
         }
         
-        log_debug(&format!("calculate_layout completed, scroll_pos: {}, total_height: {}, visible_start: {}, visible_count: {}", 
-            self.scroll_pos, self.total_height, self.visible_start, self.visible_count));
-    }
-
-    fn populate_sample_data(&mut self) {
-        self.list_data.clear();
-        for i in 0..100000 {
-            let path = format!("C:\\Users\\Example\\Documents\\File_{:06}.txt", i);
-            self.list_data.push(FileResult::from_path(&path));
-        }
-        self.selected_index = if !self.list_data.is_empty() { Some(0) } else { None };
-        self.calculate_layout();
+        self.connect_remote_server(self.config.remote_servers.len() - 1);
     }
-
-    fn search_everything(&mut self, query: &str) {
-        if query.trim().is_empty() {
-            // If empty query, reload default search
-            if let Err(e) = self.load_from_everything_sdk("*.png") {
-                println!("Search failed: {}", e);
-            }
-        } else {
-            // Search with user query
-            if let Err(e) = self.load_from_everything_sdk(query) {
-                println!("Search failed: {}", e);
-            }
-        }
+    
+    fn remove_current_remote_server(&mut self) {
+        let Some(server) = self.remote_server.clone() else {
+            return;
+        };
         
-        // Only reset scroll position if we're not currently dragging the scrollbar
-        // This prevents the scrollbar from jumping back to the top during scroll operations
-        if !self.is_scrollbar_dragging {
-        self.scroll_pos = 0;
+        self.config.remote_servers.retain(|existing| *existing != server);
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
         }
-        self.calculate_layout();
         
-        // Cancel all thumbnail tasks and recompute
-        if let Some(ref task_manager) = self.thumbnail_task_manager {
-            task_manager.cancel_all_tasks();
+        self.disconnect_remote_server();
+    }
+    
+    // The search thread couldn't reach `server`; search this computer again
+    fn handle_remote_failed(&mut self, server: &RemoteServer, error: &str) {
+        // A server that was already left behind doesn't matter any more
+        if self.remote_server.as_ref() != Some(server) {
+            return;
         }
         
-        // Clear thumbnail cache
-        self.thumbnail_cache.clear();
-        
-        // Post message to recompute thumbnails
-        unsafe {
-            let _ = PostMessageW(self.main_window, WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
-        }
+        self.disconnect_remote_server();
         
-        // Update UI
+        let strings = get_strings();
+        let message = format!("{}\n{}: {}", strings.error_remote_connect, server.display_name(), error);
         unsafe {
-            if let Some(state) = &APP_STATE {
-                update_scrollbar(state.list_view);
-                InvalidateRect(state.list_view, None, TRUE);
-                update_status_bar();
-            }
-        }
-    }
-
-    fn set_selection(&mut self, index: usize) {
-        if index < self.list_data.len() {
-            self.selected_index = Some(index);
-            self.ensure_selection_visible();
+            MessageBoxW(
+                self.main_window,
+                PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                w!("Error"),
+                MB_ICONERROR | MB_OK,
+            );
         }
     }
-
-    fn move_selection(&mut self, direction: i32) {
-        if self.list_data.is_empty() {
+    
+    // Fetches the selected remote files into `folder` on a background
+    // thread, which reports back with WM_DOWNLOAD_DONE
+    fn download_selected(&self, folder: PathBuf, open: bool) {
+        let Some(server) = self.remote_server.clone() else {
+            return;
+        };
+        let paths: Vec<String> = self.get_selected_results().iter().map(|file| file.path().to_string()).collect();
+        if paths.is_empty() {
             return;
         }
-
-        let new_index = match self.selected_index {
-            Some(current) => {
-                match self.view_mode {
-                    ViewMode::Details => {
-                        let new = current as i32 + direction;
-                        if new < 0 {
-                            0
-                        } else if new >= self.list_data.len() as i32 {
-                            self.list_data.len() - 1
-                        } else {
-                            new as usize
-                        }
-                    }
-                    _ => {
-                        // Grid navigation
-                        if direction == -1 && current > 0 {
-                            current - 1
-                        } else if direction == 1 && current < self.list_data.len() - 1 {
-                            current + 1
-                        } else if direction < 0 {
-                            // Page up or similar - move by grid_cols
-                            ((current as i32 + direction * self.grid_cols).max(0)) as usize
-                        } else {
-                            // Page down or similar
-                            ((current as i32 + direction * self.grid_cols) as usize).min(self.list_data.len() - 1)
+        
+        log_info(&format!("Downloading {} files from {}", paths.len(), server.display_name()));
+        let window = self.main_window;
+        std::thread::spawn(move || {
+            let mut done = DownloadDone {
+                downloaded: Vec::new(),
+                failed: Vec::new(),
+                open,
+            };
+            
+            let connection = std::fs::create_dir_all(&folder)
+                .map_err(|e| e.to_string())
+                .and_then(|_| EtpConnection::connect(&server).map_err(|e| e.to_string()));
+            match connection {
+                Ok(mut connection) => {
+                    for path in paths {
+                        let name = std::path::Path::new(&path).file_name().unwrap_or_default().to_os_string();
+                        let local_path = folder.join(name);
+                        match connection.download(&path, &local_path) {
+                            Ok(_) => done.downloaded.push(local_path),
+                            Err(e) => done.failed.push(format!("{}: {}", path, e)),
                         }
                     }
                 }
+                Err(e) => done.failed.extend(paths.iter().map(|path| format!("{}: {}", path, e))),
             }
-            None => 0,
-        };
-
-        self.selected_index = Some(new_index);
-        self.ensure_selection_visible();
-    }
-
-    fn ensure_selection_visible(&mut self) {
-        log_debug(&format!("ensure_selection_visible called, current scroll_pos: {}, selected_index: {:?}", 
-            self.scroll_pos, self.selected_index));
             
-        if let Some(selected) = self.selected_index {
-            match self.view_mode {
-                ViewMode::Details => {
-                    let selected_y = selected as i32 * self.item_height;
-                    
-                    if selected_y < self.scroll_pos {
-                        log_debug(&format!("Adjusting scroll_pos from {} to {} (selection above visible area)", 
-                            self.scroll_pos, selected_y));
-                        self.scroll_pos = selected_y;
-                        self.calculate_layout();
-                    } else if selected_y >= self.scroll_pos + self.client_height - self.item_height {
-                        let new_pos = selected_y - self.client_height + self.item_height;
-                        log_debug(&format!("Adjusting scroll_pos from {} to {} (selection below visible area)", 
-                            self.scroll_pos, new_pos));
-                        self.scroll_pos = new_pos;
-                        self.calculate_layout();
-                    }
-                }
-                _ => {
-                    // Grid mode
-                    let row = selected as i32 / self.grid_cols;
-                    let selected_y = row * self.cell_size;
-                    
-                    if selected_y < self.scroll_pos {
-                        log_debug(&format!("Grid: Adjusting scroll_pos from {} to {} (selection above visible area)", 
-                            self.scroll_pos, selected_y));
-                        self.scroll_pos = selected_y;
-                        self.calculate_layout();
-                    } else if selected_y >= self.scroll_pos + self.client_height - self.cell_size {
-                        let new_pos = selected_y - self.client_height + self.cell_size;
-                        log_debug(&format!("Grid: Adjusting scroll_pos from {} to {} (selection below visible area)", 
-                            self.scroll_pos, new_pos));
-                        self.scroll_pos = new_pos;
-                        self.calculate_layout();
-                    }
+            let done_ptr = Box::into_raw(Box::new(done)) as usize;
+            unsafe {
+                if PostMessageW(window, WM_DOWNLOAD_DONE, WPARAM(done_ptr), LPARAM(0)).is_err() {
+                    drop(Box::from_raw(done_ptr as *mut DownloadDone));
                 }
             }
+        });
+    }
+    
+    fn handle_download_done(&self, done: DownloadDone) {
+        log_info(&format!("Downloaded {} files, {} failed", done.downloaded.len(), done.failed.len()));
+        if done.open {
+            for path in &done.downloaded {
+                open_file(self.main_window, &path.to_string_lossy());
+            }
         }
         
-        log_debug(&format!("ensure_selection_visible completed, final scroll_pos: {}", self.scroll_pos));
+        if !done.failed.is_empty() {
+            let strings = get_strings();
+            let message = format!("{}\n{}", strings.error_download, done.failed.join("\n"));
+            unsafe {
+                MessageBoxW(
+                    self.main_window,
+                    PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                    w!("Error"),
+                    MB_ICONERROR | MB_OK,
+                );
+            }
+        }
     }
-
-    fn get_item_at_point(&self, x: i32, y: i32) -> Option<usize> {
-        if self.list_data.is_empty() {
-            return None;
+    
+    // Looks for a newer release in the background when automatic checks are
+    // on and the last one was a day or more ago
+    fn check_for_updates_at_startup(&mut self) {
+        if !self.config.auto_check_updates {
+            return;
         }
-
-        match self.view_mode {
-            ViewMode::Details => {
-                // Account for header height - clicks in header area return None
-                if y < HEADER_HEIGHT {
-                    return None;
-                }
-                
-                let adjusted_y = y - HEADER_HEIGHT + (self.scroll_pos % self.item_height);
-                let item_index = (self.scroll_pos + adjusted_y) / self.item_height;
-                
-                if item_index >= 0 && (item_index as usize) < self.list_data.len() {
-                    Some(item_index as usize)
-                } else {
-                    None
-                }
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        if now.saturating_sub(self.config.last_update_check) < UPDATE_CHECK_INTERVAL {
+            return;
+        }
+        self.config.last_update_check = now;
+        if let Err(e) = save_config(&self.config) {
+            log_warn(&format!("Failed to save config: {}", e));
+        }
+        check_for_updates(self.main_window, WM_UPDATE_CHECK_DONE);
+    }
+    
+    fn handle_update_check_done(&mut self, result: std::result::Result<Release, String>) {
+        match result {
+            Ok(release) if release.is_newer() => self.offer_update(release),
+            Ok(release) => log_info(&format!("Up to date; the latest release is {}", release.version)),
+            // Nobody asked for this check, so a failure isn't worth a dialog
+            Err(e) => log_warn(&format!("Update check failed: {}", e)),
+        }
+    }
+    
+    // Asks whether to download and install `release`. Without an executable
+    // to install, offers its download page instead.
+    fn offer_update(&mut self, release: Release) {
+        if self.update_progress.is_some() || self.pending_update.is_some() {
+            return;
+        }
+        let strings = get_strings();
+        let prompt = if release.asset.is_some() { &strings.update_install_prompt } else { &strings.about_open_download };
+        let message = fill(prompt, &[("version", &release.version)]);
+        let answer = unsafe {
+            MessageBoxW(
+                self.main_window,
+                PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                PCWSTR::from_raw(to_wide(&strings.help_about).as_ptr()),
+                MB_ICONQUESTION | MB_YESNO,
+            )
+        };
+        if answer != IDYES {
+            return;
+        }
+        
+        match release.asset {
+            Some(asset) => {
+                log_info(&format!("Downloading update {} from {}", release.version, asset.url));
+                self.update_progress = Some(0);
+                update_status_bar(self);
+                start_update_download(self.main_window, asset, WM_UPDATE_PROGRESS, WM_UPDATE_DOWNLOADED);
             }
-            _ => {
-                // Grid mode
-                if self.cell_size <= 0 || self.grid_cols <= 0 {
-                    return None;
-                }
-                
-                let row = (y + self.scroll_pos) / self.cell_size;
-                let col = x / self.cell_size;
-                
-                if col >= 0 && col < self.grid_cols && row >= 0 {
-                    let index = (row * self.grid_cols + col) as usize;
-                    if index < self.list_data.len() {
-                        Some(index)
-                    } else {
-                        None
+            None => unsafe {
+                ShellExecuteW(
+                    None,
+                    w!("open"),
+                    PCWSTR::from_raw(to_wide(&release.page_url).as_ptr()),
+                    None,
+                    None,
+                    SW_SHOWNORMAL,
+                );
+            },
+        }
+    }
+    
+    fn handle_update_downloaded(&mut self, result: std::result::Result<PathBuf, String>) {
+        self.update_progress = None;
+        update_status_bar(self);
+        
+        let strings = get_strings();
+        match result {
+            Ok(path) => {
+                self.pending_update = Some(path);
+                let answer = unsafe {
+                    MessageBoxW(
+                        self.main_window,
+                        PCWSTR::from_raw(to_wide(&strings.update_ready).as_ptr()),
+                        PCWSTR::from_raw(to_wide(&strings.help_about).as_ptr()),
+                        MB_ICONQUESTION | MB_YESNO,
+                    )
+                };
+                if answer == IDYES {
+                    // Exits the way the tray's Exit does; WM_DESTROY starts
+                    // the installer
+                    unsafe {
+                        let _ = PostMessageW(self.main_window, WM_COMMAND, WPARAM(ID_TRAY_EXIT as usize), LPARAM(0));
                     }
-                } else {
-                    None
+                }
+            }
+            Err(e) => {
+                let message = format!("{}\n{}", strings.error_update_download, e);
+                unsafe {
+                    MessageBoxW(
+                        self.main_window,
+                        PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                        w!("Error"),
+                        MB_ICONERROR | MB_OK,
+                    );
                 }
             }
         }
     }
-
-    fn open_selected_file(&self) {
-        if let Some(selected) = self.selected_index {
-            if selected < self.list_data.len() {
-                let file_path = &self.list_data[selected].path;
-                open_file(file_path);
+    
+    // Hands a downloaded update to the helper, which replaces this
+    // executable once the process has exited
+    fn install_pending_update(&mut self) {
+        if let Some(path) = self.pending_update.take() {
+            if let Err(e) = launch_update_helper(&path) {
+                log_error(&format!("Failed to start the update helper: {}", e));
             }
         }
     }
-
-    fn set_view_mode(&mut self, new_mode: ViewMode) {
-        // Convert old view mode to zoom level for backward compatibility
-        let new_zoom_level = match new_mode {
-            ViewMode::Details => 0,
-            ViewMode::MediumIcons => 5,  // 64px
-            ViewMode::LargeIcons => 10,  // 128px  
-            ViewMode::ExtraLargeIcons => 13, // 256px
-        };
-        
-        self.set_zoom_level(new_zoom_level);
+    
+    // Runs the tool at `index` of the Tools menu on the selected rows
+    // The two files selected, when that is all that is selected
+    fn compared_pair(&self) -> Option<(String, String)> {
+        let selected = self.get_selected_results();
+        match selected.as_slice() {
+            [first, second] if !first.is_folder && !second.is_folder && !self.showing_remote_results() => {
+                Some((first.path().to_string(), second.path().to_string()))
+            }
+            _ => None,
+        }
     }
-
-    fn set_zoom_level(&mut self, zoom_level: i32) {
-        // Clamp zoom level to valid range
-        let zoom_level = zoom_level.max(0).min(14);
+    
+    // Compare → Check If Identical: the answer comes as a notification
+    fn compare_selected_files(&mut self) {
+        if let Some((first, second)) = self.compared_pair() {
+            start_compare(self.main_window, first, second);
+        }
+    }
+    
+    fn handle_compare_done(&mut self, result: CompareResult) {
+        let strings = get_strings();
+        let name_of = |path: &str| std::path::Path::new(path).file_name().map_or(path.to_string(), |name| name.to_string_lossy().to_string());
+        let (first, second) = (name_of(&result.first), name_of(&result.second));
+        let text = match &result.outcome {
+            Ok(true) => fill(&strings.compare_identical, &[("first", &first), ("second", &second)]),
+            Ok(false) => fill(&strings.compare_different, &[("first", &first), ("second", &second)]),
+            Err(e) => fill(&strings.compare_failed, &[("error", e)]),
+        };
+        log_info(&format!("Compared {} with {}: {:?}", result.first, result.second, result.outcome));
+        
+        // Shown in a message box when notifications can't be
+        if !self.show_notification(&strings.compare_title, &text) {
+            unsafe {
+                let icon = if result.outcome.is_err() { MB_ICONERROR } else { MB_ICONINFORMATION };
+                MessageBoxW(
+                    self.main_window,
+                    PCWSTR::from_raw(to_wide(&text).as_ptr()),
+                    PCWSTR::from_raw(to_wide(&strings.compare_title).as_ptr()),
+                    icon | MB_OK,
+                );
+            }
+        }
+    }
+    
+    // Compare → Compare in Diff Tool runs the first tool that takes all the
+    // selected paths at once
+    fn compare_in_diff_tool(&self) {
+        if self.compared_pair().is_none() {
+            return;
+        }
+        match self.config.external_tools.iter().position(|tool| takes_all_paths(&tool.command)) {
+            Some(index) => self.run_external_tool(index),
+            None => unsafe {
+                let strings = get_strings();
+                MessageBoxW(
+                    self.main_window,
+                    PCWSTR::from_raw(to_wide(&strings.compare_no_diff_tool).as_ptr()),
+                    PCWSTR::from_raw(to_wide(&strings.compare_title).as_ptr()),
+                    MB_ICONINFORMATION | MB_OK,
+                );
+            },
+        }
+    }
+    
+    fn run_external_tool(&self, index: usize) {
+        let Some(tool) = self.config.external_tools.get(index) else {
+            return;
+        };
+        let selected = self.get_selected_results();
+        let paths: Vec<&str> = selected.iter().map(|file| file.path()).collect();
+        if paths.is_empty() || self.showing_remote_results() {
+            return;
+        }
         
-        if self.zoom_level == zoom_level {
-            return; // No change needed
+        let working_dir = std::path::Path::new(paths[0])
+            .parent()
+            .map(|dir| dir.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let failed: Vec<String> = expand_tool_command(&tool.command, &paths)
+            .iter()
+            .filter_map(|command_line| {
+                log_info(&format!("Running tool '{}': {}", tool.name, command_line));
+                run_tool_command(command_line, &working_dir).err()
+            })
+            .collect();
+        
+        if !failed.is_empty() {
+            let strings = get_strings();
+            let message = format!("{}\n{}", strings.error_run_tool, failed.join("\n"));
+            unsafe {
+                MessageBoxW(
+                    self.main_window,
+                    PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                    w!("Error"),
+                    MB_ICONERROR | MB_OK,
+                );
+            }
         }
+    }
+    
+    // Opens a terminal in the selected folder, or in the folder that holds
+    // the selected file
+    fn open_terminal_here(&self, terminal: Terminal) {
+        let Some(file) = self.selected_index.and_then(|index| self.list_data.get(index)) else {
+            return;
+        };
         
-        log_debug(&format!("set_zoom_level: changing from {} to {}", self.zoom_level, zoom_level));
+        let path = std::path::Path::new(file.path());
+        let folder = if path.is_dir() { Some(path) } else { path.parent() };
+        let Some(folder) = folder else {
+            return;
+        };
         
-        // Calculate the current visible item index to preserve relative position
-        let current_visible_item = if self.list_data.is_empty() {
-            0
-        } else {
-            match self.view_mode {
-                ViewMode::Details => {
-                    // In details view, calculate which item is at the top
-                    ((self.scroll_pos + HEADER_HEIGHT) / self.item_height) as usize
-                }
-                _ => {
-                    // In grid view, calculate which item is at the top
-                    if self.cell_size > 0 && self.grid_cols > 0 {
-                        let row = self.scroll_pos / self.cell_size;
-                        (row * self.grid_cols) as usize
-                    } else {
-                        0
-                    }
-                }
-            }.min(self.list_data.len().saturating_sub(1))
+        if let Err(e) = open_terminal(terminal, &folder.to_string_lossy(), &self.config.terminal_profile, &self.config.wsl_distro) {
+            let strings = get_strings();
+            let message = format!("{}\n{}", strings.error_run_tool, e);
+            unsafe {
+                MessageBoxW(
+                    self.main_window,
+                    PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                    w!("Error"),
+                    MB_ICONERROR | MB_OK,
+                );
+            }
+        }
+    }
+    
+    fn add_external_tool(&mut self) {
+        let strings = get_strings();
+        let Some(name) = prompt_text(self.main_window, &strings.tools_add, &strings.tools_name_prompt, "", self.font) else {
+            return;
+        };
+        let Some(command) = prompt_text(self.main_window, &strings.tools_add, &strings.tools_command_prompt, "\"%path%\"", self.font) else {
+            return;
         };
         
-        log_debug(&format!("Preserving visible item index: {} (from scroll_pos: {})", current_visible_item, self.scroll_pos));
+        // Adding a tool again under the same name replaces its command
+        self.config.external_tools.retain(|existing| !existing.name.eq_ignore_ascii_case(&name));
+        self.config.external_tools.push(ExternalTool { name, command });
+        if self.config.external_tools.len() > MAX_EXTERNAL_TOOLS {
+            self.config.external_tools.remove(0);
+        }
         
-        self.zoom_level = zoom_level;
-        self.view_mode = Self::get_view_mode_from_zoom_level(zoom_level);
-        self.selected_view_size = Self::get_icon_size_from_zoom_level(zoom_level);
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        let _ = recreate_menus_with_language(self.main_window, self);
+    }
+    
+    fn remove_external_tool(&mut self, index: usize) {
+        if index >= self.config.external_tools.len() {
+            return;
+        }
         
-        // Clear thumbnail cache when switching zoom levels
-        self.thumbnail_cache.clear();
+        self.config.external_tools.remove(index);
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        let _ = recreate_menus_with_language(self.main_window, self);
+    }
+    
+    // Replaces the Bookmarks and Filters menus with the ones in Everything's
+    // Bookmarks.csv and Filters.csv. A file that can't be read leaves its
+    // menu as it was.
+    fn import_from_everything(&mut self) {
+        let strings = get_strings();
+        let mut errors = Vec::new();
         
-        // Cancel all thumbnail tasks and recompute
-        if let Some(ref task_manager) = self.thumbnail_task_manager {
-            task_manager.cancel_all_tasks();
+        match import_bookmarks(&self.config.everything_bookmarks_path) {
+            Ok(bookmarks) => self.config.bookmarks = bookmarks,
+            Err(e) => errors.push(e),
         }
+        match import_filters(&self.config.everything_filters_path) {
+            Ok(filters) => self.config.search_filters = filters,
+            Err(e) => errors.push(e),
+        }
+        log_info(&format!(
+            "Imported {} bookmarks and {} filters from Everything",
+            self.config.bookmarks.len(),
+            self.config.search_filters.len()
+        ));
         
-        // Recalculate layout with new view mode
-        self.calculate_layout();
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        let _ = recreate_menus_with_language(self.main_window, self);
         
-        // Adjust scroll position to show the same item that was visible before
-        let new_scroll_pos = if self.list_data.is_empty() {
-            0
+        let bookmarks = self.config.bookmarks.len().to_string();
+        let filters = self.config.search_filters.len().to_string();
+        let mut message = fill(&strings.import_everything_done, &[("bookmarks", &bookmarks), ("filters", &filters)]);
+        let icon = if errors.is_empty() {
+            MB_ICONINFORMATION
         } else {
-            match self.view_mode {
-                ViewMode::Details => {
-                    // In details view, position to show the item at the top
-                    (current_visible_item as i32 * self.item_height) - HEADER_HEIGHT
-                }
-                _ => {
-                    // In grid view, position to show the item at the top
-                    if self.grid_cols > 0 {
-                        let row = current_visible_item as i32 / self.grid_cols;
-                        row * self.cell_size
-                    } else {
-                        0
-                    }
-                }
-            }.max(0).min((self.total_height - self.client_height).max(0))
+            message = format!("{}\n\n{}\n{}", message, strings.error_import_everything, errors.join("\n"));
+            MB_ICONWARNING
         };
-        
-        log_debug(&format!("Adjusting scroll_pos from {} to {} to preserve visible item", self.scroll_pos, new_scroll_pos));
-        self.scroll_pos = new_scroll_pos;
-        
-        // Recalculate layout again with the adjusted scroll position
-        self.calculate_layout();
-        
-        // Update menu checkmarks
-        update_view_menu_checkmarks(self.main_window, &self.view_mode);
-        
-        // Post message to recompute thumbnails
         unsafe {
-            let _ = PostMessageW(self.main_window, WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
+            MessageBoxW(
+                self.main_window,
+                PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                PCWSTR::from_raw(to_wide(&strings.import_everything).as_ptr()),
+                icon | MB_OK,
+            );
         }
-        
-        log_debug(&format!("set_zoom_level completed: final scroll_pos={}", self.scroll_pos));
     }
-
-    fn set_thumbnail_strategy(&mut self, strategy: ThumbnailStrategy) {
-        self.config.thumbnail_strategy = strategy;
+    
+    // Runs the bookmark's search and turns on the filter saved with it
+    fn open_bookmark(&mut self, index: usize) {
+        let Some(bookmark) = self.config.bookmarks.get(index).cloned() else {
+            return;
+        };
         
-        // Save configuration
-        if let Err(e) = save_config(&self.config) {
-            println!("Failed to save config: {}", e);
+        self.active_filter = self.config.search_filters
+            .iter()
+            .find(|filter| filter.name.eq_ignore_ascii_case(&bookmark.filter))
+            .cloned();
+        let _ = recreate_menus_with_language(self.main_window, self);
+        self.run_search(&bookmark.search);
+        update_status_bar(self);
+    }
+    
+    // Bookmarks > Scheduled Exports > Schedule Current Search: keeps the
+    // search and filter in use and writes their results to a chosen EFU or
+    // CSV file, daily until changed. The first run starts right away.
+    fn add_scheduled_export(&mut self) {
+        let strings = get_strings();
+        if self.config.scheduled_exports.len() >= MAX_SCHEDULED_EXPORTS {
+            return;
         }
         
-        // Cancel all thumbnail tasks and recompute
-        if let Some(ref task_manager) = self.thumbnail_task_manager {
-            task_manager.cancel_all_tasks();
-        }
+        let search = unsafe {
+            let mut buffer: [u16; 1024] = [0; 1024];
+            let len = GetWindowTextW(self.search_edit, &mut buffer);
+            String::from_utf16_lossy(&buffer[..len.max(0) as usize])
+        };
+        let filter = self.active_filter.as_ref().map_or(String::new(), |filter| filter.name.clone());
+        let initial = self.config.bookmarks
+            .iter()
+            .find(|bookmark| bookmark.search == search)
+            .map_or_else(|| search.clone(), |bookmark| bookmark.name.clone());
+        let Some(name) = prompt_text(self.main_window, &strings.scheduled_export_add, &strings.scheduled_export_name_prompt, &initial, self.font) else {
+            return;
+        };
         
-        // Clear thumbnail cache
-        self.thumbnail_cache.clear();
+        let filters = [("Everything File Lists (*.efu)", "efu"), ("CSV Files (*.csv)", "csv")];
+        let Some(result) = show_save_file_dialog_with_filters(self.main_window, &strings.scheduled_export_add, &name, &filters, 1, None) else {
+            return;
+        };
         
-        // Post message to recompute thumbnails
-        unsafe {
-            let _ = PostMessageW(self.main_window, WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
+        self.config.scheduled_exports.push(ScheduledExport {
+            name,
+            search,
+            filter,
+            path: result.path,
+            interval_hours: 24,
+            last_run: 0,
+            last_count: 0,
+            last_error: String::new(),
+        });
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
         }
-        
-        // Update menu checkmarks
-        update_thumbnail_menu_checkmarks(self.main_window, strategy);
-        
-        // Invalidate the list view
-        unsafe {
-            InvalidateRect(self.list_view, None, TRUE);
+        self.start_scheduled_export(self.config.scheduled_exports.len() - 1);
+    }
+    
+    fn start_scheduled_export(&mut self, index: usize) {
+        let Some(export) = self.config.scheduled_exports.get(index) else {
+            return;
+        };
+        if !self.running_exports.insert(export.path.clone()) {
+            return;
         }
         
-        println!("Switched to thumbnail strategy: {:?}", strategy);
+        let filter = self.config.search_filters
+            .iter()
+            .find(|filter| filter.name.eq_ignore_ascii_case(&export.filter));
+        let query = self.combined_query(&export.search, filter, None);
+        run_scheduled_export(self.main_window, WM_SCHEDULED_EXPORT_DONE, export, query);
+        let _ = recreate_menus_with_language(self.main_window, self);
     }
     
-    fn set_thumbnail_background(&mut self, background: ThumbnailBackground) {
-        self.config.thumbnail_background = background;
-        
-        // Save configuration
+    // Starts the exports that are due; `startup` also runs the ones set to
+    // run at every start
+    fn run_due_exports(&mut self, startup: bool) {
+        let now = unix_now();
+        let due: Vec<usize> = self.config.scheduled_exports
+            .iter()
+            .enumerate()
+            .filter(|(_, export)| is_due(export, now, startup))
+            .map(|(index, _)| index)
+            .collect();
+        for index in due {
+            self.start_scheduled_export(index);
+        }
+    }
+    
+    fn handle_scheduled_export_done(&mut self, done: ScheduledExportDone) {
+        self.running_exports.remove(&done.path);
+        if let Some(export) = self.config.scheduled_exports.iter_mut().find(|export| export.path == done.path) {
+            export.last_run = done.finished;
+            match done.result {
+                Ok(count) => {
+                    export.last_count = count;
+                    export.last_error.clear();
+                }
+                Err(e) => export.last_error = e,
+            }
+            if let Err(e) = save_config(&self.config) {
+                log_error(&format!("Failed to save config: {}", e));
+            }
+        }
+        let _ = recreate_menus_with_language(self.main_window, self);
+    }
+    
+    fn set_scheduled_export_interval(&mut self, index: usize, hours: u32) {
+        let Some(export) = self.config.scheduled_exports.get_mut(index) else {
+            return;
+        };
+        export.interval_hours = hours;
         if let Err(e) = save_config(&self.config) {
-            println!("Failed to save config: {}", e);
+            log_error(&format!("Failed to save config: {}", e));
         }
-        
-        // Cancel all thumbnail tasks and recompute
-        if let Some(ref task_manager) = self.thumbnail_task_manager {
-            task_manager.cancel_all_tasks();
+        let _ = recreate_menus_with_language(self.main_window, self);
+    }
+    
+    fn remove_scheduled_export(&mut self, index: usize) {
+        if index >= self.config.scheduled_exports.len() {
+            return;
+        }
+        self.config.scheduled_exports.remove(index);
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        let _ = recreate_menus_with_language(self.main_window, self);
+    }
+    
+    // Limits searches to `folder`, or lifts the limit for None
+    fn set_search_scope(&mut self, folder: Option<String>) {
+        if folder == self.search_scope {
+            return;
         }
         
-        // Clear thumbnail cache
-        self.thumbnail_cache.clear();
+        log_info(&format!("Search scope: {}", folder.as_deref().unwrap_or("all drives")));
+        self.search_scope = folder;
+        self.search_again_after_source_change();
+    }
+    
+    fn toggle_folder_tree(&mut self) {
+        self.config.show_folder_tree = !self.config.show_folder_tree;
         
-        // Post message to recompute thumbnails
-        unsafe {
-            let _ = PostMessageW(self.main_window, WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
         }
         
-        // Update menu checkmarks
-        update_background_menu_checkmarks(self.main_window, background);
+        if self.config.show_folder_tree {
+            self.folder_tree = Some(FolderTree::create(self.main_window, self.font, ID_FOLDER_TREE, &self.config.favorites));
+            self.reveal_selected_folder();
+        } else if let Some(folder_tree) = self.folder_tree.take() {
+            folder_tree.destroy();
+            self.set_search_scope(None);
+        }
         
-        // Invalidate the list view
         unsafe {
+            let mut rect = RECT::default();
+            let _ = GetClientRect(self.main_window, &mut rect);
+            resize_controls(self, rect.right, rect.bottom);
             InvalidateRect(self.list_view, None, TRUE);
         }
-        
-        println!("Switched to thumbnail background: {:?}", background);
+        update_folder_tree_menu_state(self.main_window, self);
     }
     
-    fn toggle_column(&mut self, column_type: ColumnType) {
-        for column in &mut self.columns {
-            if column.column_type == column_type {
-                column.visible = !column.visible;
-                break;
-            }
+    fn toggle_preview_pane(&mut self) {
+        self.config.show_preview_pane = !self.config.show_preview_pane;
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
         }
         
-        // Update menu checkmarks
-        update_column_menu_checkmarks(self.main_window, &self.columns);
+        if self.config.show_preview_pane {
+            self.preview_pane = Some(PreviewPane::create(self.main_window, self.font, ID_PREVIEW_PANE));
+        } else if let Some(preview_pane) = self.preview_pane.take() {
+            preview_pane.destroy();
+        }
         
-        // Invalidate the list view to redraw with new columns
         unsafe {
+            let mut rect = RECT::default();
+            let _ = GetClientRect(self.main_window, &mut rect);
+            resize_controls(self, rect.right, rect.bottom);
             InvalidateRect(self.list_view, None, TRUE);
         }
-        
-        println!("Toggled column visibility: {:?}", column_type);
+        self.preview_selected_file();
+        update_preview_pane_menu_state(self.main_window, self);
     }
     
-    fn get_visible_columns(&self) -> Vec<&ColumnInfo> {
-        self.columns.iter().filter(|col| col.visible).collect()
+    // Shows the selected file in the preview pane. Folders, several
+    // selected files and results from a remote server aren't previewed.
+    fn preview_selected_file(&mut self) {
+        let remote = self.showing_remote_results();
+        let Some(preview_pane) = self.preview_pane.as_mut() else {
+            return;
+        };
+        let path = if self.selected_items.len() == 1 && !remote {
+            self.selected_index
+                .and_then(|index| self.list_data.get(index))
+                .filter(|file| !file.is_folder && !is_offline(file.path()))
+                .map(|file| file.path().to_string())
+        } else {
+            None
+        };
+        preview_pane.show_file(path.as_deref());
     }
     
-    fn get_column_at_x(&self, x: i32) -> Option<usize> {
-        let visible_columns = self.get_visible_columns();
-        let mut current_x = 0;
-        
-        for (index, column) in visible_columns.iter().enumerate() {
-            if x >= current_x && x < current_x + column.width {
-                return Some(index);
-            }
-            current_x += column.width;
+    // Acts on what the user picked in the sidebar
+    fn open_sidebar_item(&mut self, item: SidebarItem) {
+        match item {
+            SidebarItem::Folder(path) => self.set_search_scope((!path.is_empty()).then_some(path)),
+            SidebarItem::Favorite(index, _) => self.open_favorite(index),
+            SidebarItem::Favorites => {}
         }
-        
-        None
     }
     
-    fn get_column_resize_cursor_x(&self, x: i32) -> Option<usize> {
-        let visible_columns = self.get_visible_columns();
-        let mut current_x = 0;
-        let resize_margin = 3; // 3 pixels margin for resize cursor
+    fn open_favorite(&mut self, index: usize) {
+        let Some(favorite) = self.config.favorites.get(index).cloned() else {
+            return;
+        };
         
-        for (index, column) in visible_columns.iter().enumerate() {
-            current_x += column.width;
-            if x >= current_x - resize_margin && x <= current_x + resize_margin {
-                return Some(index);
+        match favorite.kind {
+            FavoriteKind::Folder => self.set_search_scope(Some(favorite.target)),
+            FavoriteKind::Search => {
+                // A saved search runs as it was saved, not within a folder
+                self.search_scope = None;
+                self.run_search(&favorite.target);
+                update_status_bar(self);
             }
+            FavoriteKind::List if std::path::Path::new(&favorite.target).exists() => {
+                open_file_list(self.main_window, self, &favorite.target);
+            }
+            FavoriteKind::List => unsafe {
+                let message = format!("{}\n{}", get_strings().error_favorite_missing, favorite.target);
+                MessageBoxW(
+                    self.main_window,
+                    PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                    w!("Error"),
+                    MB_ICONERROR | MB_OK,
+                );
+            },
         }
-        
-        None
     }
     
-    fn sort_by_column(&mut self, column_type: ColumnType) {
-        // Determine new sort order
-        let new_order = match &self.sort_state {
-            Some(state) if state.column == column_type => {
-                match state.order {
-                    SortOrder::None | SortOrder::Descending => SortOrder::Ascending,
-                    SortOrder::Ascending => SortOrder::Descending,
-                }
-            }
-            _ => SortOrder::Ascending,
+    // Pins a folder, search or list to the sidebar, named after its last
+    // path component
+    fn pin_favorite(&mut self, kind: FavoriteKind, target: &str) {
+        let name = match kind {
+            FavoriteKind::Search => target.to_string(),
+            _ => std::path::Path::new(target)
+                .file_name()
+                .map_or(target.to_string(), |name| name.to_string_lossy().to_string()),
         };
+        if !self.config.add_favorite(kind, &name, target) {
+            return;
+        }
         
-        // Update sort state
-        self.sort_state = Some(SortState {
-            column: column_type,
-            order: new_order,
-        });
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        if let Some(folder_tree) = self.folder_tree.as_mut() {
+            folder_tree.set_favorites(&self.config.favorites);
+        }
+    }
+    
+    fn unpin_favorite(&mut self, index: usize) {
+        if index >= self.config.favorites.len() {
+            return;
+        }
         
-        // Perform the sort
-        match column_type {
-            ColumnType::Name => {
-                if new_order == SortOrder::Ascending {
-                    self.list_data.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
-                } else {
-                    self.list_data.sort_by(|a, b| b.name.to_lowercase().cmp(&a.name.to_lowercase()));
-                }
+        self.config.favorites.remove(index);
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        if let Some(folder_tree) = self.folder_tree.as_mut() {
+            folder_tree.set_favorites(&self.config.favorites);
+        }
+    }
+    
+    // Pins results dropped on the sidebar: folders as folders, file lists
+    // as lists and any other file by its folder
+    fn pin_selected_results(&mut self) {
+        if self.showing_remote_results() {
+            return;
+        }
+        
+        for file in self.get_selected_results() {
+            let path = std::path::Path::new(file.path());
+            if path.is_dir() {
+                self.pin_favorite(FavoriteKind::Folder, file.path());
+            } else if file.extension().eq_ignore_ascii_case("efu") || is_playlist_path(file.path()) {
+                self.pin_favorite(FavoriteKind::List, file.path());
+            } else if let Some(folder) = path.parent() {
+                self.pin_favorite(FavoriteKind::Folder, &folder.to_string_lossy());
             }
-            ColumnType::Size => {
-                // Load metadata for all items before sorting (only for visible items to keep performance)
-                for item in &mut self.list_data {
-                    if item.size == 0 && item.modified_time == std::time::UNIX_EPOCH {
-                        item.load_metadata();
+        }
+    }
+    
+    // Highlights the folder of the selected result in the folder tree
+    fn reveal_selected_folder(&mut self) {
+        if self.showing_remote_results() {
+            return;
+        }
+        let folder = self.selected_index
+            .and_then(|index| self.list_data.get(index))
+            .and_then(|file| std::path::Path::new(file.path()).parent())
+            .map(|folder| folder.to_string_lossy().to_string());
+        if let (Some(folder_tree), Some(folder)) = (self.folder_tree.as_mut(), folder) {
+            folder_tree.reveal(&folder);
+        }
+    }
+    
+    // Index into the imported filters, or None for no filter
+    fn set_search_filter(&mut self, index: Option<usize>) {
+        let filter = index.and_then(|index| self.config.search_filters.get(index).cloned());
+        if filter == self.active_filter {
+            return;
+        }
+        
+        self.active_filter = filter;
+        self.search_again_after_source_change();
+    }
+    
+    // Puts the full paths, or just the names, of the selected rows on the
+    // clipboard, one per line
+    fn copy_selected(&self, names_only: bool) {
+        let text = self.get_selected_results()
+            .iter()
+            .map(|file| if names_only { file.name() } else { file.path() })
+            .collect::<Vec<_>>()
+            .join("\r\n");
+        if !text.is_empty() {
+            set_clipboard_text(self.main_window, &text);
+        }
+    }
+    
+    // Puts the visible columns of the selected rows, or of all of them when
+    // no more than one is selected, on the clipboard as a table to paste
+    // into a spreadsheet
+    fn copy_as_table(&self) {
+        let strings = get_strings();
+        let columns = self.get_visible_columns();
+        let items: Vec<&FileResult> = if self.selected_items.len() > 1 {
+            self.selected_items.iter().filter_map(|&index| self.list_data.get(index)).collect()
+        } else {
+            self.list_data.iter().collect()
+        };
+        
+        let header: Vec<String> = columns.iter().map(|column| column.column_type.display_name().to_string()).collect();
+        let rows: Vec<Vec<String>> = std::iter::once(header)
+            .chain(
+                items
+                    .into_iter()
+                    .filter(|item| !item.is_placeholder())
+                    .map(|item| columns.iter().map(|column| self.cell_text(item, column.column_type, &strings)).collect()),
+            )
+            .collect();
+        
+        if rows.len() > 1 && set_clipboard_table(self.main_window, &rows) {
+            log_info(&format!("Copied {} rows as a table", rows.len() - 1));
+        }
+    }
+    
+    // Puts the selected paths on the clipboard as WSL sees them. Network
+    // paths outside WSL have no such path and are left out.
+    fn copy_selected_as_wsl_paths(&self) {
+        let text = self.get_selected_results()
+            .iter()
+            .filter_map(|file| to_wsl_path(file.path()))
+            .collect::<Vec<_>>()
+            .join("\r\n");
+        if !text.is_empty() {
+            set_clipboard_text(self.main_window, &text);
+        }
+    }
+    
+    fn open_selected_location(&self) {
+        let Some(file) = self.selected_index.and_then(|index| self.list_data.get(index)) else {
+            return;
+        };
+        
+        let arguments = format!("/select,\"{}\"", shell_path(file.path()));
+        unsafe {
+            ShellExecuteW(
+                None,
+                w!("open"),
+                w!("explorer.exe"),
+                PCWSTR::from_raw(to_wide(&arguments).as_ptr()),
+                None,
+                SW_SHOWNORMAL,
+            );
+        }
+    }
+
+    fn initialize_thumbnail_task_manager(&mut self, window: HWND) {
+        self.thumbnail_task_manager = Some(ThumbnailTaskManager::new(window, self.config.thumbnail_threads as usize));
+    }
+
+    fn load_from_everything_sdk(&mut self, query: &str) -> std::result::Result<(), String> {
+        if let Some(ref sdk) = self.everything_sdk {
+            log_debug(&format!("Searching for: {}", query));
+            
+            // Search for files
+            let mut results = Vec::new();
+            let search_result = sdk.search_files_in_batches(query, usize::MAX, |batch, _| {
+                results.extend(batch);
+                true
+            });
+            match search_result.map(|()| results) {
+                Ok(results) => {
+                    log_debug(&format!("Found {} results", results.len()));
+                    self.list_data = results;
+                    
+                    // Limit results to prevent UI slowdown during testing
+                    let max_results = self.config.max_results as usize;
+                    if self.list_data.len() > max_results {
+                        self.list_data.truncate(max_results);
+                        log_debug(&format!("Truncated results to {} items for performance", max_results));
                     }
+                    
+                    // Reset selection when new data loads
+                    self.reset_selection();
+                    
+                    // Clear thumbnail cache when loading new data
+                    self.thumbnail_cache.clear();
+                    
+                    self.calculate_layout();
+                    Ok(())
                 }
+                Err(e) => Err(format!("Search failed: {}", e))
+            }
+        } else {
+            Err("Everything SDK not initialized".to_string())
+        }
+    }
+
+    fn calculate_layout(&mut self) {
+        log_trace(&format!("calculate_layout called, current scroll_pos: {}", self.scroll_pos));
+        
+        match self.view_mode {
+            ViewMode::Details => {
+                self.item_height = 20;
+                self.grid_cols = 1;
+                self.cell_size = self.item_height;
                 
-                if new_order == SortOrder::Ascending {
-                    self.list_data.sort_by(|a, b| a.size.cmp(&b.size));
-                } else {
-                    self.list_data.sort_by(|a, b| b.size.cmp(&a.size));
-                }
+                self.update_drive_groups();
+                
+                // Account for header height in details view
+                (self.visible_start, self.visible_count) = self.details_visible_range();
+                self.total_height = self.details_row_count() as i32 * self.item_height;
             }
-            ColumnType::Type => {
-                if new_order == SortOrder::Ascending {
-                    self.list_data.sort_by(|a, b| a.file_type.cmp(&b.file_type));
+            _ => {
+                self.drive_groups = None;
+                
+                // Icon modes - add extra height for the caption lines
+                let padding = 8;
+                let caption_height = self.config.thumbnail_caption.line_count() * CAPTION_LINE_HEIGHT;
+                self.cell_size = self.selected_view_size as i32 + padding * 2 + caption_height;
+                self.grid_cols = if self.client_width > 0 && self.cell_size > 0 {
+                    (self.client_width / self.cell_size).max(1)
                 } else {
-                    self.list_data.sort_by(|a, b| b.file_type.cmp(&a.file_type));
-                }
-            }
-            ColumnType::Modified => {
-                // Load metadata for all items before sorting
-                for item in &mut self.list_data {
-                    if item.size == 0 && item.modified_time == std::time::UNIX_EPOCH {
-                        item.load_metadata();
-                    }
-                }
+                    1
+                };
                 
-                if new_order == SortOrder::Ascending {
-                    self.list_data.sort_by(|a, b| a.modified_time.cmp(&b.modified_time));
+                let total_rows = if self.grid_cols > 0 {
+                    (self.list_data.len() as i32 + self.grid_cols - 1) / self.grid_cols
                 } else {
-                    self.list_data.sort_by(|a, b| b.modified_time.cmp(&a.modified_time));
-                }
+                    0
+                };
+                
+                self.total_height = total_rows * self.cell_size;
+                
+                // Calculate visible range for grid
+                let first_visible_row = self.scroll_pos / self.cell_size;
+                let visible_rows = (self.client_height / self.cell_size) + 2;
+                
+                self.visible_start = (first_visible_row * self.grid_cols) as usize;
+                self.visible_count = (visible_rows * self.grid_cols) as usize;
             }
-            ColumnType::Path => {
-                if new_order == SortOrder::Ascending {
-                    self.list_data.sort_by(|a, b| a.path.to_lowercase().cmp(&b.path.to_lowercase()));
-                } else {
-                    self.list_data.sort_by(|a, b| b.path.to_lowercase().cmp(&a.path.to_lowercase()));
+        }
+        
+        // IMPORTANT: Clamp scroll_pos to valid range after layout changes
+        // This prevents losing view position when window size changes dramatically
+        let max_scroll = (self.total_height - self.client_height).max(0);
+        if self.scroll_pos > max_scroll {
+            log_trace(&format!("Clamping scroll_pos from {} to {} (max_scroll) due to layout change", 
+                self.scroll_pos, max_scroll));
+            self.scroll_pos = max_scroll;
+            
+            // Recalculate visible range with corrected scroll_pos
+            match self.view_mode {
+                ViewMode::Details => {
+                    (self.visible_start, self.visible_count) = self.details_visible_range();
+                }
+                _ => {
+                    let first_visible_row = self.scroll_pos / self.cell_size;
+                    let visible_rows = (self.client_height / self.cell_size) + 2;
+                    
+                    self.visible_start = (first_visible_row * self.grid_cols) as usize;
+                    self.visible_count = (visible_rows * self.grid_cols) as usize;
                 }
             }
         }
         
-        // Reset selection to first item
-        self.selected_index = if !self.list_data.is_empty() { Some(0) } else { None };
+        // Bounds checking
+        if self.visible_start >= self.list_data.len() {
+            self.visible_start = if self.list_data.len() > 0 { self.list_data.len() - 1 } else { 0 };
+        }
         
-        // Recalculate layout
-        self.calculate_layout();
+        if self.visible_start + self.visible_count > self.list_data.len() {
+            self.visible_count = self.list_data.len().saturating_sub(self.visible_start);
+        }
         
-        println!("Sorted by {:?} in {:?} order", column_type, new_order);
+        log_trace(&format!("calculate_layout completed, scroll_pos: {}, total_height: {}, visible_start: {}, visible_count: {}", 
+            self.scroll_pos, self.total_height, self.visible_start, self.visible_count));
+    }
+
+    // Whether the details view shows the results under a header per drive.
+    // Not while browsing the index, whose rows aren't all read.
+    fn groups_by_drive(&self) -> bool {
+        self.config.group_by_drive && self.view_mode == ViewMode::Details && !self.browsing_index()
     }
     
-    fn set_language(&mut self, language: Language) {
-        // Set the language
-        if let Err(e) = lang::set_language(language) {
-            println!("Failed to set language: {}", e);
+    // Works out the drive groups again when the rows may have changed since.
+    // A finished sort drops them, the row count staying the same.
+    fn update_drive_groups(&mut self) {
+        if !self.groups_by_drive() {
+            self.drive_groups = None;
             return;
         }
-        
-        // Update config
-        self.config.language = match language {
-            Language::English => LanguageCode::English,
-            Language::Chinese => LanguageCode::Chinese,
+        let key = (self.list_data.len(), self.search_generation.load(Ordering::Relaxed));
+        if self.drive_groups.is_none() || self.drive_groups_key != key {
+            self.drive_groups = Some(group_by_drive(&self.list_data, &self.collapsed_drives));
+            self.drive_groups_key = key;
+        }
+    }
+    
+    // Opens or closes the drive group at `group`
+    fn set_drive_group_collapsed(&mut self, group: usize, collapsed: bool) {
+        let Some(group) = self.drive_groups.as_mut().and_then(|groups| groups.get_mut(group)) else {
+            return;
         };
+        group.collapsed = collapsed;
+        if collapsed {
+            self.collapsed_drives.insert(group.label.clone());
+        } else {
+            self.collapsed_drives.remove(&group.label);
+        }
+        self.calculate_layout();
+        unsafe {
+            update_scrollbar(self);
+            InvalidateRect(self.list_view, None, TRUE);
+        }
+    }
+    
+    // Rows of the details view, drive headers included
+    fn details_row_count(&self) -> usize {
+        match &self.drive_groups {
+            Some(groups) => drive_groups::row_count(groups),
+            None => self.list_data.len(),
+        }
+    }
+    
+    // What row `row` of the details view shows
+    fn details_row(&self, row: usize) -> Option<DetailsRow> {
+        match &self.drive_groups {
+            Some(groups) => drive_groups::row_at(groups, row),
+            None => (row < self.list_data.len()).then_some(DetailsRow::Item(row)),
+        }
+    }
+    
+    // The row of the details view showing result `index`
+    fn details_row_of(&self, index: usize) -> usize {
+        match &self.drive_groups {
+            Some(groups) => drive_groups::row_of(groups, index),
+            None => index,
+        }
+    }
+    
+    // The first result shown at or below row `row` of the details view
+    fn first_item_from_row(&self, row: usize) -> usize {
+        let rows = self.details_row_count();
+        (row..rows)
+            .find_map(|row| match self.details_row(row) {
+                Some(DetailsRow::Item(index)) => Some(index),
+                _ => None,
+            })
+            .unwrap_or(self.list_data.len())
+    }
+    
+    // First result and count of results spanned by the visible rows of the
+    // details view
+    fn details_visible_range(&self) -> (usize, usize) {
+        let first_row = (self.scroll_pos / self.item_height) as usize;
+        let rows = ((self.client_height - HEADER_HEIGHT) / self.item_height + 2).max(0) as usize;
+        if self.drive_groups.is_none() {
+            return (first_row, rows);
+        }
+        let start = self.first_item_from_row(first_row);
+        let end = self.first_item_from_row(first_row + rows);
+        (start, end.saturating_sub(start))
+    }
+    
+    // Shows or hides the results under the drive header at `group`
+    fn toggle_drive_group(&mut self, group: usize) {
+        if let Some(collapsed) = self.drive_groups.as_ref().and_then(|groups| groups.get(group)).map(|group| group.collapsed) {
+            self.set_drive_group_collapsed(group, !collapsed);
+        }
+    }
+    
+    fn toggle_group_by_drive(&mut self) {
+        self.config.group_by_drive = !self.config.group_by_drive;
         
-        // Save configuration
         if let Err(e) = save_config(&self.config) {
-            println!("Failed to save config: {}", e);
+            log_error(&format!("Failed to save config: {}", e));
         }
         
-        // Update menu checkmarks
-        update_language_menu_checkmarks(self.main_window, language);
-        
-        // Recreate the entire menu with new language strings
-        recreate_menus_with_language(self.main_window);
-        
-        // Invalidate the list view to redraw with new language
+        // The results are put in drive order, or back in the sort's own
+        self.drive_groups = None;
+        self.apply_sort();
+        self.calculate_layout();
+        update_group_by_drive_menu_state(self.main_window, self);
         unsafe {
+            update_scrollbar(self);
             InvalidateRect(self.list_view, None, TRUE);
         }
-        
-        println!("Language switched to: {:?}", language);
+    }
+    
+    fn populate_sample_data(&mut self) {
+        self.list_data.clear();
+        for i in 0..100000 {
+            let path = format!("C:\\Users\\Example\\Documents\\File_{:06}.txt", i);
+            self.list_data.push(FileResult::from_path(&path));
+        }
+        self.reset_selection();
+        self.calculate_layout();
     }
 
-    fn load_file_list(&mut self, file_path: &str) -> Result<()> {
-        println!("Loading file list from: {}", file_path);
-        
-        // Read the file content
-        let content = match std::fs::read_to_string(file_path) {
-            Ok(content) => content,
-            Err(_) => return Err(Error::from_win32()),
-        };
-        
-        // Parse the file list
-        let mut file_results = Vec::new();
-        
-        // Support multiple formats:
-        // 1. Simple text list (one file path per line)
-        // 2. CSV format (path,size,modified_timestamp)
-        // 3. Basic EFU-like format
-        
-        for line in content.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-            
-            // Check if it's a CSV format (has commas)
-            if line.contains(',') {
-                let parts: Vec<&str> = line.split(',').collect();
-                if parts.len() >= 1 {
-                    let path = parts[0].trim().trim_matches('"');
-                    if std::path::Path::new(path).exists() {
-                        file_results.push(FileResult::from_path(path));
-                    } else {
-                        println!("Warning: File not found: {}", path);
-                    }
-                }
-            } else {
-                // Simple text format (one path per line)
-                let path = line.trim_matches('"');
-                if std::path::Path::new(path).exists() {
-                    file_results.push(FileResult::from_path(path));
-                } else {
-                    println!("Warning: File not found: {}", path);
+    fn search_everything(&mut self, query: &str) {
+        match self.resolve_search_query(query) {
+            Some(query) => {
+                if let Err(e) = self.load_from_everything_sdk(&query) {
+                    log_error(&format!("Search failed: {}", e));
                 }
             }
+            None => {
+                self.list_data.clear();
+                self.reset_selection();
+                self.calculate_layout();
+            }
         }
         
-        println!("Loaded {} files from list", file_results.len());
-        
-        // Update the app state
-        self.list_data = file_results.clone();
-        self.selected_index = if !self.list_data.is_empty() { Some(0) } else { None };
+        // Only reset scroll position if we're not currently dragging the scrollbar
+        // This prevents the scrollbar from jumping back to the top during scroll operations
+        if !self.is_scrollbar_dragging {
         self.scroll_pos = 0;
+        }
+        self.calculate_layout();
         
-        // Set list mode state
-        self.is_list_mode = true;
-        self.current_list_name = Some(
-            std::path::Path::new(file_path)
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string()
-        );
-        self.original_list_data = file_results.clone();
+        // Cancel all thumbnail tasks and recompute
+        if let Some(ref task_manager) = self.thumbnail_task_manager {
+            task_manager.cancel_all_tasks();
+        }
+        
+        // Clear thumbnail cache
+        self.thumbnail_cache.clear();
         
+        // Post message to recompute thumbnails
         unsafe {
-            self.calculate_layout();
-            update_scrollbar(self.list_view);
-            InvalidateRect(self.list_view, None, TRUE);
-            update_status_bar();
-            
-            // Clear the search edit box to indicate we're in list mode
-            SetWindowTextW(self.search_edit, w!(""));
+            let _ = PostMessageW(self.main_window, WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
         }
         
+        // Update UI
+        unsafe {
+            update_scrollbar(self);
+            InvalidateRect(self.list_view, None, TRUE);
+            update_status_bar(self);
+        }
+    }
+
+    fn set_selection(&mut self, index: usize) {
+        if index < self.list_data.len() {
+            self.selected_index = Some(index);
+            self.selected_items.clear();
+            self.selected_items.insert(index);
+            self.selection_anchor = Some(index);
+            self.ensure_selection_visible();
+        }
+    }
+
+    // Select the first item (if any) after list_data has been replaced
+    fn reset_selection(&mut self) {
+        self.selected_items.clear();
+        self.selected_index = if !self.list_data.is_empty() { Some(0) } else { None };
+        if let Some(index) = self.selected_index {
+            self.selected_items.insert(index);
+        }
+        self.selection_anchor = self.selected_index;
+    }
+
+    // Ctrl+click: add or remove a single item from the selection
+    fn toggle_selection(&mut self, index: usize) {
+        if index >= self.list_data.len() {
+            return;
+        }
+        
+        if !self.selected_items.remove(&index) {
+            self.selected_items.insert(index);
+        }
+        self.selected_index = Some(index);
+        self.selection_anchor = Some(index);
+        self.ensure_selection_visible();
+    }
+
+    // Shift+click / Shift+arrows: select everything between the anchor and index
+    fn extend_selection(&mut self, anchor: usize, index: usize) {
+        if index >= self.list_data.len() {
+            return;
+        }
+        
+        let anchor = anchor.min(self.list_data.len() - 1);
+        let (start, end) = if anchor <= index { (anchor, index) } else { (index, anchor) };
+        self.selected_items = (start..=end).collect();
+        self.selected_index = Some(index);
+        self.selection_anchor = Some(anchor);
+        self.ensure_selection_visible();
+    }
+
+    fn select_all(&mut self) {
+        self.selected_items = (0..self.list_data.len()).collect();
+        if self.selected_index.is_none() && !self.list_data.is_empty() {
+            self.selected_index = Some(0);
+        }
+    }
+
+    fn is_selected(&self, index: usize) -> bool {
+        self.selected_items.contains(&index)
+    }
+    
+    // Distance from the top of the content to the row holding `index`
+    fn item_offset(&self, index: usize) -> i32 {
+        match self.view_mode {
+            ViewMode::Details => self.details_row_of(index) as i32 * self.item_height,
+            _ => index as i32 / self.grid_cols.max(1) * self.cell_size,
+        }
+    }
+    
+    fn capture_view_anchor(&self) -> ViewAnchor {
+        let path_at = |index: usize| self.list_data.get(index).map(|item| item.path().to_string());
+        let top_index = match self.view_mode {
+            ViewMode::Details => self.first_item_from_row((self.scroll_pos / self.item_height.max(1)) as usize),
+            _ => (self.scroll_pos / self.cell_size.max(1) * self.grid_cols.max(1)) as usize,
+        };
+        
+        ViewAnchor {
+            selected_paths: self.selected_items.iter().filter_map(|&index| path_at(index)).collect(),
+            focused_path: self.selected_index.and_then(path_at),
+            anchor_path: self.selection_anchor.and_then(path_at),
+            top_path: path_at(top_index),
+            top_offset: self.scroll_pos - self.item_offset(top_index),
+            scroll_pos: self.scroll_pos,
+        }
+    }
+    
+    // Selects the anchored paths again and scrolls the old top item back to
+    // where it was. Needs an up to date layout.
+    fn restore_view_anchor(&mut self, anchor: ViewAnchor) {
+        let (selected_items, focused, selection_anchor, top) = {
+            let positions: std::collections::HashMap<&str, usize> = self.list_data
+                .iter()
+                .enumerate()
+                .map(|(index, item)| (item.path(), index))
+                .collect();
+            let position = |path: &Option<String>| path.as_deref().and_then(|path| positions.get(path).copied());
+            let selected_items: BTreeSet<usize> = anchor.selected_paths
+                .iter()
+                .filter_map(|path| positions.get(path.as_str()).copied())
+                .collect();
+            (selected_items, position(&anchor.focused_path), position(&anchor.anchor_path), position(&anchor.top_path))
+        };
+        
+        self.selected_items = selected_items;
+        self.selected_index = focused.or_else(|| self.selected_items.iter().next().copied());
+        let scroll_pos = match (self.selected_index, top) {
+            (Some(index), _) => {
+                self.selected_items.insert(index);
+                self.selection_anchor = selection_anchor.or(Some(index));
+                top.map_or(anchor.scroll_pos, |top| self.item_offset(top) + anchor.top_offset)
+            }
+            (None, Some(top)) => {
+                self.reset_selection();
+                self.item_offset(top) + anchor.top_offset
+            }
+            // Nothing left of the old view
+            (None, None) => {
+                self.reset_selection();
+                0
+            }
+        };
+        
+        self.scroll_pos = scroll_pos.min((self.total_height - self.client_height).max(0)).max(0);
+        self.calculate_layout();
+    }
+    
+    fn selection_snapshot(&self) -> SelectionSnapshot {
+        SelectionSnapshot {
+            items: self.selected_items.clone(),
+            focused: self.selected_index,
+            scroll_pos: self.scroll_pos,
+        }
+    }
+    
+    // Repaints the items whose selection changed since `before`, scrolling
+    // the existing pixels first if the selection moved the view
+    fn invalidate_selection_change(&self, before: &SelectionSnapshot) {
+        if self.scroll_pos != before.scroll_pos {
+            scroll_view_contents(self, before.scroll_pos);
+        }
+        
+        let changed: BTreeSet<usize> = before.items
+            .symmetric_difference(&self.selected_items)
+            .copied()
+            .chain(before.focused)
+            .chain(self.selected_index)
+            .collect();
+        self.invalidate_items(&changed);
+        
+        if before.items != self.selected_items || before.focused != self.selected_index {
+            notify_selection_changed(self.list_view, self);
+            if self.folder_tree.is_some() {
+                unsafe {
+                    SetTimer(self.main_window, FOLDER_TREE_TIMER_ID, 200, None);
+                }
+            }
+            if self.preview_pane.is_some() {
+                unsafe {
+                    SetTimer(self.main_window, PREVIEW_TIMER_ID, 250, None);
+                }
+            }
+        }
+    }
+    
+    // Invalidates the on-screen rectangles of the given items. Large sets
+    // (select all, say) repaint the whole view instead.
+    fn invalidate_items(&self, items: &BTreeSet<usize>) {
+        unsafe {
+            if items.len() > self.visible_count.max(1) * 2 {
+                InvalidateRect(self.list_view, None, FALSE);
+                return;
+            }
+            for &index in items {
+                if let Some(rect) = get_item_rect(index, self) {
+                    InvalidateRect(self.list_view, Some(&rect), FALSE);
+                }
+            }
+        }
+    }
+
+    fn get_selected_results(&self) -> Vec<FileResult> {
+        self.selected_items
+            .iter()
+            .filter_map(|&index| self.list_data.get(index))
+            .cloned()
+            .collect()
+    }
+
+    fn move_selection(&mut self, direction: i32) {
+        if self.list_data.is_empty() {
+            return;
+        }
+
+        let new_index = match self.selected_index {
+            Some(current) => {
+                match self.view_mode {
+                    ViewMode::Details => {
+                        let new = current as i32 + direction;
+                        if new < 0 {
+                            0
+                        } else if new >= self.list_data.len() as i32 {
+                            self.list_data.len() - 1
+                        } else {
+                            new as usize
+                        }
+                    }
+                    _ => {
+                        // Grid navigation
+                        if direction == -1 && current > 0 {
+                            current - 1
+                        } else if direction == 1 && current < self.list_data.len() - 1 {
+                            current + 1
+                        } else if direction < 0 {
+                            // Page up or similar - move by grid_cols
+                            ((current as i32 + direction * self.grid_cols).max(0)) as usize
+                        } else {
+                            // Page down or similar
+                            ((current as i32 + direction * self.grid_cols) as usize).min(self.list_data.len() - 1)
+                        }
+                    }
+                }
+            }
+            None => 0,
+        };
+
+        self.selected_index = Some(new_index);
+        self.selected_items.clear();
+        self.selected_items.insert(new_index);
+        self.selection_anchor = Some(new_index);
+        self.ensure_selection_visible();
+    }
+
+    fn ensure_selection_visible(&mut self) {
+        log_trace(&format!("ensure_selection_visible called, current scroll_pos: {}, selected_index: {:?}", 
+            self.scroll_pos, self.selected_index));
+            
+        if let Some(selected) = self.selected_index {
+            match self.view_mode {
+                ViewMode::Details => {
+                    // A selection in a collapsed drive group opens it
+                    let collapsed = self.drive_groups.as_ref().and_then(|groups| {
+                        drive_groups::group_of(groups, selected).filter(|&group| groups[group].collapsed)
+                    });
+                    if let Some(group) = collapsed {
+                        self.set_drive_group_collapsed(group, false);
+                    }
+                    let selected_y = self.item_offset(selected);
+                    
+                    if selected_y < self.scroll_pos {
+                        log_trace(&format!("Adjusting scroll_pos from {} to {} (selection above visible area)", 
+                            self.scroll_pos, selected_y));
+                        self.scroll_pos = selected_y;
+                        self.calculate_layout();
+                    } else if selected_y >= self.scroll_pos + self.client_height - self.item_height {
+                        let new_pos = selected_y - self.client_height + self.item_height;
+                        log_trace(&format!("Adjusting scroll_pos from {} to {} (selection below visible area)", 
+                            self.scroll_pos, new_pos));
+                        self.scroll_pos = new_pos;
+                        self.calculate_layout();
+                    }
+                }
+                _ => {
+                    // Grid mode
+                    let row = selected as i32 / self.grid_cols;
+                    let selected_y = row * self.cell_size;
+                    
+                    if selected_y < self.scroll_pos {
+                        log_trace(&format!("Grid: Adjusting scroll_pos from {} to {} (selection above visible area)", 
+                            self.scroll_pos, selected_y));
+                        self.scroll_pos = selected_y;
+                        self.calculate_layout();
+                    } else if selected_y >= self.scroll_pos + self.client_height - self.cell_size {
+                        let new_pos = selected_y - self.client_height + self.cell_size;
+                        log_trace(&format!("Grid: Adjusting scroll_pos from {} to {} (selection below visible area)", 
+                            self.scroll_pos, new_pos));
+                        self.scroll_pos = new_pos;
+                        self.calculate_layout();
+                    }
+                }
+            }
+        }
+        
+        log_trace(&format!("ensure_selection_visible completed, final scroll_pos: {}", self.scroll_pos));
+    }
+
+    // What the details view shows at `y`, below the column headers
+    fn details_row_at(&self, y: i32) -> Option<DetailsRow> {
+        let adjusted_y = y - HEADER_HEIGHT + (self.scroll_pos % self.item_height);
+        let row = (self.scroll_pos + adjusted_y) / self.item_height;
+        if y < HEADER_HEIGHT || row < 0 {
+            return None;
+        }
+        self.details_row(row as usize)
+    }
+    
+    fn get_item_at_point(&self, x: i32, y: i32) -> Option<usize> {
+        if self.list_data.is_empty() {
+            return None;
+        }
+
+        match self.view_mode {
+            ViewMode::Details => {
+                // Account for header height - clicks in header area return None
+                if y < HEADER_HEIGHT {
+                    return None;
+                }
+                
+                match self.details_row_at(y) {
+                    Some(DetailsRow::Item(index)) => Some(index),
+                    _ => None,
+                }
+            }
+            _ => {
+                // Grid mode
+                if self.cell_size <= 0 || self.grid_cols <= 0 {
+                    return None;
+                }
+                
+                let row = (y + self.scroll_pos) / self.cell_size;
+                let col = x / self.cell_size;
+                
+                if col >= 0 && col < self.grid_cols && row >= 0 {
+                    let index = (row * self.grid_cols + col) as usize;
+                    if index < self.list_data.len() {
+                        Some(index)
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    // Enter and double-click: folders scope the search to themselves, files
+    // are opened. The context menu's Open still opens a folder in Explorer.
+    fn activate_selected(&mut self) {
+        let folder = self.selected_index
+            .and_then(|selected| self.list_data.get(selected))
+            .filter(|item| item.is_folder && !self.showing_remote_results())
+            .map(|item| item.path().to_string());
+        match folder {
+            Some(folder) => self.search_in_folder(&folder),
+            None => self.open_selected_file(),
+        }
+    }
+    
+    // Counts a run of `path` for ranking and, unless paused, adds it to the
+    // open history
+    fn note_opened(&mut self, path: &str) {
+        self.run_history.record(path);
+        if self.config.record_open_history {
+            record_opened(path);
+        }
+    }
+    
+    fn set_record_open_history(&mut self, record: bool) {
+        self.config.record_open_history = record;
+        log_info(if record { "Recording opened files" } else { "Stopped recording opened files" });
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        
+        update_record_history_menu_state(self.main_window, self);
+    }
+    
+    fn open_selected_file(&mut self) {
+        // Remote files are fetched to the temp folder and opened from there
+        if self.showing_remote_results() {
+            self.download_selected(std::env::temp_dir().join("EverythingLike"), true);
+            return;
+        }
+        
+        if let Some(selected) = self.selected_index {
+            if selected < self.list_data.len() {
+                let path = self.list_data[selected].path().to_string();
+                self.note_opened(&path);
+                if self.config.use_image_viewer && is_viewable_image(self.list_data[selected].extension()) {
+                    self.view_images_from(selected);
+                    return;
+                }
+                let file_path = &self.list_data[selected].path();
+                open_file(self.main_window, file_path);
+            }
+        }
+    }
+    
+    fn show_selected_properties(&self) {
+        if self.showing_remote_results() {
+            return;
+        }
+        if let Some(file) = self.selected_index.and_then(|selected| self.list_data.get(selected)) {
+            show_file_properties(self.main_window, file.path());
+        }
+    }
+    
+    // F3: the selected file as text or hex. Folders and remote results
+    // have nothing to show.
+    fn quick_view_selected(&self) {
+        if self.showing_remote_results() {
+            return;
+        }
+        if let Some(file) = self.selected_index.and_then(|selected| self.list_data.get(selected)) {
+            if !std::path::Path::new(file.path()).is_dir() {
+                show_quick_view(self.main_window, file.path());
+            }
+        }
+    }
+    
+    // F4: plays the selected video in a popup under its row
+    fn preview_selected_video(&self) {
+        if self.showing_remote_results() {
+            return;
+        }
+        let Some(selected) = self.selected_index else { return };
+        let Some(file) = self.list_data.get(selected).filter(|file| is_video(file.extension())) else { return };
+        
+        let mut anchor = POINT::default();
+        unsafe {
+            match get_item_rect(selected, self) {
+                Some(rect) => {
+                    anchor = POINT { x: rect.left, y: rect.bottom };
+                    let _ = ClientToScreen(self.list_view, &mut anchor);
+                }
+                None => {
+                    let _ = GetCursorPos(&mut anchor);
+                }
+            }
+        }
+        show_video_preview(self.main_window, file.path(), anchor);
+    }
+    
+    // Opens list_data[selected] in the image viewer, which steps through
+    // the other images in the results
+    fn view_images_from(&self, selected: usize) {
+        let mut index = 0;
+        let mut paths = Vec::new();
+        for (row, file) in self.list_data.iter().enumerate() {
+            if row == selected {
+                index = paths.len();
+            } else if file.missing || !is_viewable_image(file.extension()) {
+                continue;
+            }
+            paths.push(file.path().to_string());
+        }
+        show_image_viewer(self.main_window, paths, index);
+    }
+    
+    fn set_size_format(&mut self, size_format: SizeFormat) {
+        self.config.size_format = size_format;
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        
+        update_size_format_menu_state(self.main_window, self);
+        update_status_bar(self);
+        unsafe {
+            InvalidateRect(self.list_view, None, TRUE);
+        }
+    }
+    
+    fn set_thumbnail_caption(&mut self, caption: ThumbnailCaption) {
+        self.config.thumbnail_caption = caption;
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        
+        update_caption_menu_state(self.main_window, self);
+        self.calculate_layout();
+        update_scrollbar(self);
+        unsafe {
+            // Cells changed size, so other thumbnails are on screen
+            let _ = PostMessageW(self.main_window, WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
+            InvalidateRect(self.list_view, None, TRUE);
+        }
+    }
+    
+    fn set_path_ellipsis(&mut self, path_ellipsis: PathEllipsis) {
+        self.config.path_ellipsis = path_ellipsis;
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        
+        update_path_ellipsis_menu_state(self.main_window, self);
+        unsafe {
+            InvalidateRect(self.list_view, None, TRUE);
+        }
+    }
+    
+    fn toggle_image_viewer(&mut self) {
+        self.config.use_image_viewer = !self.config.use_image_viewer;
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        
+        update_image_viewer_menu_state(self.main_window, self);
+    }
+
+    fn set_view_mode(&mut self, new_mode: ViewMode) {
+        // Convert old view mode to zoom level for backward compatibility
+        let new_zoom_level = match new_mode {
+            ViewMode::Details => 0,
+            ViewMode::MediumIcons => 5,  // 64px
+            ViewMode::LargeIcons => 10,  // 128px  
+            ViewMode::ExtraLargeIcons => 13, // 256px
+        };
+        
+        self.set_zoom_level(new_zoom_level);
+    }
+
+    fn set_zoom_level(&mut self, zoom_level: i32) {
+        // Clamp zoom level to valid range
+        let zoom_level = zoom_level.max(0).min(14);
+        
+        if self.zoom_level == zoom_level {
+            return; // No change needed
+        }
+        
+        log_trace(&format!("set_zoom_level: changing from {} to {}", self.zoom_level, zoom_level));
+        
+        // Calculate the current visible item index to preserve relative position
+        let current_visible_item = if self.list_data.is_empty() {
+            0
+        } else {
+            match self.view_mode {
+                ViewMode::Details => {
+                    // In details view, calculate which item is at the top
+                    self.first_item_from_row(((self.scroll_pos + HEADER_HEIGHT) / self.item_height) as usize)
+                }
+                _ => {
+                    // In grid view, calculate which item is at the top
+                    if self.cell_size > 0 && self.grid_cols > 0 {
+                        let row = self.scroll_pos / self.cell_size;
+                        (row * self.grid_cols) as usize
+                    } else {
+                        0
+                    }
+                }
+            }.min(self.list_data.len().saturating_sub(1))
+        };
+        
+        log_trace(&format!("Preserving visible item index: {} (from scroll_pos: {})", current_visible_item, self.scroll_pos));
+        
+        self.zoom_level = zoom_level;
+        self.view_mode = Self::get_view_mode_from_zoom_level(zoom_level);
+        self.selected_view_size = Self::get_icon_size_from_zoom_level(zoom_level);
+        
+        // Clear thumbnail cache when switching zoom levels
+        self.thumbnail_cache.clear();
+        
+        // Cancel all thumbnail tasks and recompute
+        if let Some(ref task_manager) = self.thumbnail_task_manager {
+            task_manager.cancel_all_tasks();
+        }
+        
+        // Recalculate layout with new view mode
+        self.calculate_layout();
+        
+        // Adjust scroll position to show the same item that was visible before
+        let new_scroll_pos = if self.list_data.is_empty() {
+            0
+        } else {
+            match self.view_mode {
+                ViewMode::Details => {
+                    // In details view, position to show the item at the top
+                    self.item_offset(current_visible_item) - HEADER_HEIGHT
+                }
+                _ => {
+                    // In grid view, position to show the item at the top
+                    if self.grid_cols > 0 {
+                        let row = current_visible_item as i32 / self.grid_cols;
+                        row * self.cell_size
+                    } else {
+                        0
+                    }
+                }
+            }.max(0).min((self.total_height - self.client_height).max(0))
+        };
+        
+        log_trace(&format!("Adjusting scroll_pos from {} to {} to preserve visible item", self.scroll_pos, new_scroll_pos));
+        self.scroll_pos = new_scroll_pos;
+        
+        // Recalculate layout again with the adjusted scroll position
+        self.calculate_layout();
+        
+        // Update menu checkmarks
+        update_view_menu_checkmarks(self.main_window, &self.view_mode);
+        update_contact_sheet_menu_state(self.main_window, self);
+        
+        // Post message to recompute thumbnails
+        unsafe {
+            let _ = PostMessageW(self.main_window, WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
+        }
+        
+        log_trace(&format!("set_zoom_level completed: final scroll_pos={}", self.scroll_pos));
+    }
+
+    fn set_thumbnail_strategy(&mut self, strategy: ThumbnailStrategy) {
+        self.config.thumbnail_strategy = strategy;
+        
+        // Save configuration
+        if let Err(e) = save_config(&self.config) {
+            log_warn(&format!("Failed to save config: {}", e));
+        }
+        
+        // Cancel all thumbnail tasks and recompute
+        if let Some(ref task_manager) = self.thumbnail_task_manager {
+            task_manager.cancel_all_tasks();
+        }
+        
+        // Clear thumbnail cache
+        self.thumbnail_cache.clear();
+        
+        // Post message to recompute thumbnails
+        unsafe {
+            let _ = PostMessageW(self.main_window, WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
+        }
+        
+        // Update menu checkmarks
+        update_thumbnail_menu_checkmarks(self.main_window, strategy);
+        
+        // Invalidate the list view
+        unsafe {
+            InvalidateRect(self.list_view, None, TRUE);
+        }
+        
+        log_info(&format!("Switched to thumbnail strategy: {:?}", strategy));
+    }
+    
+    fn set_thumbnail_background(&mut self, background: ThumbnailBackground) {
+        self.config.thumbnail_background = background;
+        
+        // Save configuration
+        if let Err(e) = save_config(&self.config) {
+            log_warn(&format!("Failed to save config: {}", e));
+        }
+        
+        // Cancel all thumbnail tasks and recompute
+        if let Some(ref task_manager) = self.thumbnail_task_manager {
+            task_manager.cancel_all_tasks();
+        }
+        
+        // Clear thumbnail cache
+        self.thumbnail_cache.clear();
+        
+        // Post message to recompute thumbnails
+        unsafe {
+            let _ = PostMessageW(self.main_window, WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
+        }
+        
+        // Update menu checkmarks
+        update_background_menu_checkmarks(self.main_window, background);
+        
+        // Invalidate the list view
+        unsafe {
+            InvalidateRect(self.list_view, None, TRUE);
+        }
+        
+        log_info(&format!("Switched to thumbnail background: {:?}", background));
+    }
+    
+    fn toggle_column(&mut self, column_type: ColumnType) {
+        for column in &mut self.columns {
+            if column.column_type == column_type {
+                column.visible = !column.visible;
+                break;
+            }
+        }
+        
+        // Statuses may have changed while the column was hidden
+        if column_type == ColumnType::GitStatus {
+            self.git_statuses.clear();
+        }
+        if column_type.needs_link_info() {
+            self.link_infos.clear();
+        }
+        if column_type.needs_media_info() {
+            self.media_infos.clear();
+        }
+        
+        // Update menu checkmarks
+        update_column_menu_checkmarks(self.main_window, &self.columns);
+        
+        // Invalidate the list view to redraw with new columns
+        unsafe {
+            InvalidateRect(self.list_view, None, TRUE);
+        }
+        
+        log_info(&format!("Toggled column visibility: {:?}", column_type));
+    }
+    
+    fn toggle_column_alignment(&mut self, index: usize) {
+        let Some(column) = self.columns.get_mut(index) else {
+            return;
+        };
+        column.align_right = !column.align_right;
+        
+        update_column_menu_checkmarks(self.main_window, &self.columns);
+        unsafe {
+            InvalidateRect(self.list_view, None, TRUE);
+        }
+    }
+    
+    // Widens or narrows the column to its header and the rows on screen
+    fn size_column_to_fit(&mut self, column_type: ColumnType) {
+        // The icon in front of the name and the margins around the text,
+        // as paint_details_view lays them out
+        const ICON_SPACE: i32 = 22;
+        const TEXT_MARGINS: i32 = 10;
+        
+        let strings = get_strings();
+        let end = (self.visible_start + self.visible_count + 1).min(self.list_data.len());
+        let start = self.visible_start.min(end);
+        let widest = unsafe {
+            let hdc = GetDC(self.list_view);
+            let old_font = SelectObject(hdc, self.font);
+            let measure = |text: &str| {
+                let mut size = SIZE::default();
+                GetTextExtentPoint32W(hdc, &text.encode_utf16().collect::<Vec<u16>>(), &mut size);
+                size.cx
+            };
+            // The header may carry a sort arrow
+            let header = measure(&format!("{} ↑", column_type.display_name()));
+            let cells = self.list_data[start..end]
+                .iter()
+                .filter(|item| !item.is_placeholder())
+                .map(|item| measure(&self.cell_text(item, column_type, &strings)))
+                .max()
+                .unwrap_or(0);
+            SelectObject(hdc, old_font);
+            ReleaseDC(self.list_view, hdc);
+            header.max(cells)
+        };
+        
+        let first = self.get_visible_columns().first().map(|column| column.column_type) == Some(column_type);
+        let icon_space = if first && column_type == ColumnType::Name { ICON_SPACE } else { 0 };
+        if let Some(column) = self.columns.iter_mut().find(|column| column.column_type == column_type) {
+            column.width = (widest + icon_space + TEXT_MARGINS).max(20);
+        }
+        unsafe {
+            InvalidateRect(self.list_view, None, TRUE);
+        }
+    }
+    
+    // Back to the default columns, widths and alignment
+    fn reset_column_layout(&mut self) {
+        self.columns = default_columns();
+        self.git_statuses.clear();
+        self.link_infos.clear();
+        self.media_infos.clear();
+        
+        update_column_menu_checkmarks(self.main_window, &self.columns);
+        unsafe {
+            InvalidateRect(self.list_view, None, TRUE);
+        }
+    }
+    
+    // Visibility and widths of all columns from the column chooser
+    fn choose_more_columns(&mut self) {
+        let strings = get_strings();
+        let choices = self
+            .columns
+            .iter()
+            .map(|column| ColumnChoice {
+                label: column.column_type.menu_label(&strings).to_string(),
+                visible: column.visible,
+                width: column.width,
+            })
+            .collect();
+        let Some(choices) = choose_columns(self.main_window, choices, self.font) else {
+            return;
+        };
+        
+        for (column, choice) in self.columns.iter_mut().zip(choices) {
+            // Values may have changed while the column was hidden
+            if choice.visible && !column.visible {
+                if column.column_type == ColumnType::GitStatus {
+                    self.git_statuses.clear();
+                }
+                if column.column_type.needs_link_info() {
+                    self.link_infos.clear();
+                }
+                if column.column_type.needs_media_info() {
+                    self.media_infos.clear();
+                }
+            }
+            column.visible = choice.visible;
+            column.width = choice.width;
+        }
+        
+        update_column_menu_checkmarks(self.main_window, &self.columns);
+        unsafe {
+            InvalidateRect(self.list_view, None, TRUE);
+        }
+    }
+    
+    fn cell_text(&self, item: &FileResult, column: ColumnType, strings: &LanguageStrings) -> String {
+        match column {
+            ColumnType::Name => item.name().to_string(),
+            // Left blank until the metadata loader has read the file
+            ColumnType::Size => item.format_size(self.config.size_format, strings),
+            ColumnType::Type => item.file_type(),
+            ColumnType::Modified => item.format_modified_time(strings),
+            ColumnType::Path => item.path().to_string(),
+            ColumnType::GitStatus => self.git_statuses
+                .peek(item.path())
+                .copied()
+                .flatten()
+                .map(|status| status.label(strings))
+                .unwrap_or_default(),
+            ColumnType::Tags => self.tags.tags_of(item.path()).join(", "),
+            ColumnType::Notes => self.list_notes.note_of(item.path()).unwrap_or_default().to_string(),
+            // Blank when unrated; the view draws the stars itself
+            ColumnType::Rating => {
+                let rating = self.tags.rating_of(item.path());
+                if rating == 0 {
+                    String::new()
+                } else {
+                    format!("{}{}", "★".repeat(rating as usize), "☆".repeat((MAX_RATING - rating) as usize))
+                }
+            }
+            ColumnType::HardLinks => self.link_infos
+                .peek(item.path())
+                .copied()
+                .flatten()
+                .map(|info| info.hard_links.to_string())
+                .unwrap_or_default(),
+            // Blank for the usual file without any
+            ColumnType::Streams => self.link_infos
+                .peek(item.path())
+                .copied()
+                .flatten()
+                .filter(|info| info.alternate_streams > 0)
+                .map(|info| info.alternate_streams.to_string())
+                .unwrap_or_default(),
+            ColumnType::Duration => self.media_infos
+                .peek(item.path())
+                .copied()
+                .flatten()
+                .map(|info| info.format_duration())
+                .unwrap_or_default(),
+            ColumnType::Bitrate => self.media_infos
+                .peek(item.path())
+                .copied()
+                .flatten()
+                .map(|info| info.format_bitrate())
+                .unwrap_or_default(),
+        }
+    }
+    
+    // A column's name with the sort indicator when the results are sorted by it
+    fn column_header_text(&self, column_type: ColumnType) -> String {
+        let base_text = column_type.display_name();
+        match self.sort_state {
+            Some(ref sort_state) if sort_state.column == column_type => match sort_state.order {
+                SortOrder::Ascending => format!("{} ↑", base_text),
+                SortOrder::Descending => format!("{} ↓", base_text),
+                SortOrder::None => base_text.to_string(),
+            },
+            _ => base_text.to_string(),
+        }
+    }
+    
+    // The results as they are shown, to print: the visible columns in the
+    // current sort, headed by the search or list they come from
+    fn results_print_table(&self) -> PrintTable {
+        let strings = get_strings();
+        let visible_columns = self.get_visible_columns();
+        let source = match self.current_list_path {
+            Some(ref list_path) if self.is_list_mode => list_path.clone(),
+            _ => self.searched_query.clone(),
+        };
+        let title = fill(&strings.print_title, &[
+            ("source", &source),
+            ("count", &self.list_data.len().to_string()),
+            ("date", &chrono::Local::now().format("%Y-%m-%d %H:%M").to_string()),
+        ]);
+        
+        PrintTable {
+            title,
+            columns: visible_columns
+                .iter()
+                .map(|column| PrintColumn {
+                    name: self.column_header_text(column.column_type),
+                    width: column.width,
+                    align_right: column.align_right,
+                })
+                .collect(),
+            rows: self.list_data
+                .iter()
+                .filter(|item| !item.is_placeholder())
+                .map(|item| visible_columns.iter().map(|column| self.cell_text(item, column.column_type, &strings)).collect())
+                .collect(),
+        }
+    }
+    
+    fn get_visible_columns(&self) -> Vec<&ColumnInfo> {
+        self.columns.iter().filter(|col| col.visible).collect()
+    }
+    
+    // Width of the Name column while it stays put as the other columns
+    // scroll sideways, or 0 when nothing is frozen
+    fn frozen_width(&self) -> i32 {
+        if !self.config.freeze_name_column || self.view_mode != ViewMode::Details {
+            return 0;
+        }
+        match self.get_visible_columns().first() {
+            Some(column) if column.column_type == ColumnType::Name => column.width,
+            _ => 0,
+        }
+    }
+    
+    // Left edge on screen of each visible column, scrolled sideways
+    fn column_lefts(&self) -> Vec<i32> {
+        let frozen = self.frozen_width();
+        let mut current_x = 0;
+        self.get_visible_columns()
+            .iter()
+            .enumerate()
+            .map(|(index, column)| {
+                let left = if index == 0 && frozen > 0 { 0 } else { current_x - self.h_scroll_pos };
+                current_x += column.width;
+                left
+            })
+            .collect()
+    }
+    
+    fn get_column_at_x(&self, x: i32) -> Option<usize> {
+        // The frozen column covers whatever scrolled under it
+        let frozen = self.frozen_width();
+        if frozen > 0 && (0..frozen).contains(&x) {
+            return Some(0);
+        }
+        
+        let visible_columns = self.get_visible_columns();
+        let lefts = self.column_lefts();
+        visible_columns
+            .iter()
+            .enumerate()
+            .position(|(index, column)| (frozen == 0 || index > 0) && x >= lefts[index] && x < lefts[index] + column.width)
+    }
+    
+    fn get_column_resize_cursor_x(&self, x: i32) -> Option<usize> {
+        let visible_columns = self.get_visible_columns();
+        let lefts = self.column_lefts();
+        let frozen = self.frozen_width();
+        let resize_margin = 3; // 3 pixels margin for resize cursor
+        
+        for (index, column) in visible_columns.iter().enumerate() {
+            let right = lefts[index] + column.width;
+            // Edges scrolled under the frozen column can't be grabbed
+            if index > 0 && frozen > 0 && right < frozen {
+                continue;
+            }
+            if x >= right - resize_margin && x <= right + resize_margin {
+                return Some(index);
+            }
+        }
+        
+        None
+    }
+    
+    // Width of the visible columns side by side
+    fn columns_width(&self) -> i32 {
+        self.get_visible_columns().iter().map(|column| column.width).sum()
+    }
+    
+    fn toggle_freeze_name_column(&mut self) {
+        self.config.freeze_name_column = !self.config.freeze_name_column;
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        
+        update_freeze_column_menu_state(self.main_window, self);
+        unsafe {
+            InvalidateRect(self.list_view, None, TRUE);
+        }
+    }
+    
+    fn toggle_rank_by_use(&mut self) {
+        self.config.rank_by_use = !self.config.rank_by_use;
+        
+        // Ranking takes the place of the sort column
+        if self.config.rank_by_use {
+            self.sort_state = None;
+        }
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        
+        self.apply_sort();
+        update_sort_menu_checkmarks(self.main_window, &self.sort_state);
+        update_rank_menu_state(self.main_window, self);
+    }
+    
+    // Sorting by a column ends ranking
+    fn stop_ranking(&mut self) {
+        if !self.config.rank_by_use {
+            return;
+        }
+        self.config.rank_by_use = false;
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        update_rank_menu_state(self.main_window, self);
+    }
+    
+    fn toggle_locale_sort(&mut self) {
+        self.config.locale_sort = !self.config.locale_sort;
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        
+        self.apply_sort();
+        update_locale_sort_menu_state(self.main_window, self);
+    }
+    
+    fn sort_by_column(&mut self, column_type: ColumnType) {
+        if !column_type.is_sortable() {
+            return;
+        }
+        // Everything sorts the index, by fewer columns than the list can
+        if self.browsing_index() && column_type.everything_sort().is_none() {
+            return;
+        }
+        self.stop_ranking();
+        
+        // Determine new sort order
+        let new_order = match &self.sort_state {
+            Some(state) if state.column == column_type => {
+                match state.order {
+                    SortOrder::None | SortOrder::Descending => SortOrder::Ascending,
+                    SortOrder::Ascending => SortOrder::Descending,
+                }
+            }
+            _ => SortOrder::Ascending,
+        };
+        
+        // Update sort state
+        self.sort_state = Some(SortState {
+            column: column_type,
+            order: new_order,
+        });
+        
+        // The new order is swapped in once the background sort finishes
+        self.start_sort();
+        
+        log_debug(&format!("Sorting by {:?} in {:?} order", column_type, new_order));
+    }
+    
+    fn set_language(&mut self, language: Language) {
+        // Set the language
+        if let Err(e) = lang::set_language(language) {
+            log_warn(&format!("Failed to set language: {}", e));
+            return;
+        }
+        
+        // Update config
+        self.config.language = LanguageCode::from_string(language.to_code());
+        
+        // Collation follows the UI language
+        if self.config.locale_sort {
+            self.apply_sort();
+        }
+        
+        // The Explorer verb's label is written in the UI language
+        if self.config.explorer_verb {
+            if let Err(e) = set_explorer_verb(true, &get_strings().explorer_verb_label) {
+                log_warn(&format!("Failed to relabel the Explorer verb: {}", e));
+            }
+        }
+        
+        // Save configuration
+        if let Err(e) = save_config(&self.config) {
+            log_warn(&format!("Failed to save config: {}", e));
+        }
+        
+        // Update menu checkmarks
+        update_language_menu_checkmarks(self.main_window, language);
+        
+        // Recreate the entire menu with new language strings
+        let _ = recreate_menus_with_language(self.main_window, self);
+        
+        // The jump list's category titles are translated too
+        self.refresh_jump_list();
+        
+        // Invalidate the list view to redraw with new language
+        unsafe {
+            InvalidateRect(self.list_view, None, TRUE);
+        }
+        
+        log_info(&format!("Language switched to: {:?}", language));
+    }
+
+    fn load_file_list(&mut self, file_path: &str) -> Result<()> {
+        log_info(&format!("Loading file list from: {}", file_path));
+        self.displayed_query = None;
+        
+        // Read the file content
+        let content = if is_playlist_path(file_path) {
+            // Plain .m3u files are often not UTF-8, so don't fail on them
+            match std::fs::read(file_path) {
+                Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
+                Err(_) => return Err(Error::from_win32()),
+            }
+        } else {
+            match std::fs::read_to_string(file_path) {
+                Ok(content) => content,
+                Err(_) => return Err(Error::from_win32()),
+            }
+        };
+        
+        // Parse the file list
+        let mut list_notes = ListNotes::default();
+        
+        // Support multiple formats:
+        // 1. Simple text list (one file path per line)
+        // 2. CSV format (path,size,modified_timestamp)
+        // 3. Basic EFU-like format
+        // 4. M3U/M3U8 playlists
+        
+        // Existence is checked later on a background thread, so missing
+        // entries are kept here and flagged once validation completes
+        let file_results = if is_playlist_path(file_path) {
+            FileResult::from_paths(parse_playlist(&content, file_path).iter().map(String::as_str))
+        } else {
+            let mut batch = ResultBatch::default();
+            // Lists saved with notes name a column for them in their header
+            let mut notes_at = None;
+            for line in content.lines() {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                
+                // The format comment and the EFU column header
+                if line.starts_with('#') || line.starts_with("Filename,") {
+                    if line.starts_with("# Format:") || line.starts_with("Filename,") {
+                        notes_at = note_column(line);
+                    }
+                    continue;
+                }
+                
+                // Check if it's a CSV format (has commas)
+                if line.contains(',') {
+                    let fields = parse_csv_line(line);
+                    let path = fields.first().map_or("", |path| path.trim());
+                    if !path.is_empty() {
+                        if let Some(note) = notes_at.and_then(|column| fields.get(column)) {
+                            list_notes.set(path, note);
+                        }
+                        batch.push(path, false);
+                    }
+                } else {
+                    // Simple text format (one path per line)
+                    batch.push(line.trim_matches('"'), false);
+                }
+            }
+            batch.finish()
+        };
+        
+        log_info(&format!("Loaded {} files from list", file_results.len()));
+        
+        // Update the app state
+        self.list_data = file_results.clone();
+        self.reset_selection();
+        self.scroll_pos = 0;
+        
+        // Set list mode state
+        self.stop_folder_monitor();
+        self.is_list_mode = true;
+        self.current_list_name = Some(
+            std::path::Path::new(file_path)
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        );
+        self.current_list_path = Some(file_path.to_string());
+        self.original_list_data = file_results.clone();
+        self.list_notes = list_notes;
+        self.missing_count = 0;
+        
+        self.start_list_validation();
+        self.watch_current_list();
+        
+        unsafe {
+            self.calculate_layout();
+            update_scrollbar(self);
+            InvalidateRect(self.list_view, None, TRUE);
+            update_status_bar(self);
+            
+            // Clear the search edit box to indicate we're in list mode
+            SetWindowTextW(self.search_edit, w!(""));
+        }
+        
+        Ok(())
+    }
+    
+    fn save_file_list(&self, file_path: &str) -> Result<()> {
+        log_info(&format!("Saving file list to: {}", file_path));
+        
+        // Create CSV format with file paths and metadata
+        let mut content = String::new();
+        content.push_str("# File List Export\n");
+        content.push_str(LIST_FORMAT_HEADER);
+        content.push('\n');
+        
+        for item in &self.list_data {
+            // Load metadata if not already loaded
+            let mut item_clone = item.clone();
+            if item_clone.size == 0 && item_clone.modified_time == std::time::UNIX_EPOCH {
+                item_clone.load_metadata();
+            }
+            
+            // Format: "path",size,modified_timestamp,"note"
+            let modified_timestamp = item_clone.modified_time
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            
+            content.push_str(&format!("{},{},{},{}\n", 
+                csv_field(item.path()), 
+                item_clone.size,
+                modified_timestamp,
+                csv_field(self.list_notes.note_of(item.path()).unwrap_or_default())
+            ));
+        }
+        
+        // Write to file
+        match std::fs::write(file_path, content) {
+            Ok(_) => {
+                log_info(&format!("Successfully saved {} files to list", self.list_data.len()));
+                Ok(())
+            }
+            Err(_) => Err(Error::from_win32()),
+        }
+    }
+    
+    fn export_list(&self, file_path: &str, format: ExportFormat, selected_only: bool) -> Result<()> {
+        let selected_results;
+        let items: &[FileResult] = if selected_only {
+            selected_results = self.get_selected_results();
+            &selected_results
+        } else {
+            &self.list_data
+        };
+        
+        // Notes go along into EFU files, as an extra column
+        let notes = (self.is_list_mode && !self.list_notes.is_empty()).then_some(&self.list_notes);
+        match export_results(items, format, file_path, notes) {
+            Ok(_) => {
+                log_info(&format!("Successfully exported {} files as {:?}", items.len(), format));
+                Ok(())
+            }
+            Err(e) => {
+                log_error(&format!("Export failed: {}", e));
+                Err(Error::from_win32())
+            }
+        }
+    }
+
+    fn recompute_thumbnail_queue(&self) {
+        log_trace("recompute_thumbnail_queue called");
+        
+        if let Some(ref task_manager) = self.thumbnail_task_manager {
+            log_trace(&format!("Thumbnail task manager available, view_mode: {:?}, selected_view_size: {}", 
+                self.view_mode, self.selected_view_size));
+            
+            // Remote paths would be looked up on this machine
+            if self.view_mode != ViewMode::Details && self.selected_view_size > 0 && !self.showing_remote_results() {
+                log_trace("Calling task_manager.recompute_thumbnail_queue");
+                
+                task_manager.recompute_thumbnail_queue(
+                    self.config.thumbnail_strategy,
+                    self.config.thumbnail_background,
+                    self.visible_start,
+                    self.visible_count,
+                    self.list_data.len(),
+                    &self.list_data,
+                    self.selected_view_size,
+                    self.config.skip_network_thumbnails,
+                );
+                
+                log_trace("task_manager.recompute_thumbnail_queue completed");
+            } else {
+                log_trace(&format!("Skipping thumbnail queue recomputation (view_mode: {:?}, size: {})", 
+                    self.view_mode, self.selected_view_size));
+            }
+        } else {
+            log_trace("No thumbnail task manager available");
+        }
+        
+        log_trace("recompute_thumbnail_queue completed");
+    }
+    
+    // Asks the metadata loader for the visible rows that have no size or
+    // date yet, plus a page above and below so scrolling finds them ready
+    fn recompute_metadata_queue(&mut self) {
+        let shows_metadata = if self.view_mode == ViewMode::Details {
+            self.columns.iter().any(|column| {
+                column.visible && matches!(column.column_type, ColumnType::Size | ColumnType::Modified)
+            })
+        } else {
+            self.config.thumbnail_caption == ThumbnailCaption::NameAndSize
+        };
+        if !shows_metadata {
+            return;
+        }
+        
+        let page = self.visible_count.max(1);
+        let visible_end = (self.visible_start + page).min(self.list_data.len());
+        let below_end = (visible_end + page).min(self.list_data.len());
+        let above_start = self.visible_start.saturating_sub(page);
+        
+        // Visible rows first, then the page below, then the page above
+        let rows: Vec<(usize, String)> = (self.visible_start..visible_end)
+            .chain(visible_end..below_end)
+            .chain(above_start..self.visible_start)
+            .filter(|&index| self.list_data[index].needs_metadata())
+            .map(|index| (index, self.list_data[index].path().to_string()))
+            .collect();
+        
+        if let Some(loader) = self.metadata_loader.as_mut() {
+            loader.request(rows);
+        }
+    }
+    
+    // Asks the Git status loader for the visible rows and the page below
+    // while the Git column is shown. Remote results have no working tree here.
+    fn recompute_git_status_queue(&mut self) {
+        if self.view_mode != ViewMode::Details || self.showing_remote_results() {
+            return;
+        }
+        let shows_git_status = self.columns.iter().any(|column| column.visible && column.column_type == ColumnType::GitStatus);
+        if !shows_git_status {
+            return;
+        }
+        
+        let page = self.visible_count.max(1);
+        let end = (self.visible_start + page * 2).min(self.list_data.len());
+        let paths: Vec<String> = self.list_data[self.visible_start.min(end)..end]
+            .iter()
+            .filter(|item| !self.git_statuses.contains(item.path()))
+            .map(|item| item.path().to_string())
+            .collect();
+        
+        if let Some(loader) = self.git_status_loader.as_mut() {
+            loader.request(paths);
+        }
+    }
+    
+    // Asks the link info loader for the visible rows and the page below
+    // while the Links or Streams column is shown
+    fn recompute_link_info_queue(&mut self) {
+        if self.view_mode != ViewMode::Details || self.showing_remote_results() {
+            return;
+        }
+        let shows_link_info = self.columns.iter().any(|column| column.visible && column.column_type.needs_link_info());
+        if !shows_link_info {
+            return;
+        }
+        
+        let page = self.visible_count.max(1);
+        let end = (self.visible_start + page * 2).min(self.list_data.len());
+        let paths: Vec<String> = self.list_data[self.visible_start.min(end)..end]
+            .iter()
+            .filter(|item| !self.link_infos.contains(item.path()))
+            .map(|item| item.path().to_string())
+            .collect();
+        
+        if let Some(loader) = self.link_info_loader.as_mut() {
+            loader.request(paths);
+        }
+    }
+    
+    // Asks the media info loader for the visible rows and the page below
+    // while the Duration or Bitrate column is shown, or the thumbnails'
+    // dimensions are
+    fn recompute_media_info_queue(&mut self) {
+        if self.showing_remote_results() {
+            return;
+        }
+        let (shows_media_info, readable): (bool, fn(&str) -> bool) = if self.view_mode == ViewMode::Details {
+            (self.columns.iter().any(|column| column.visible && column.column_type.needs_media_info()), is_media)
+        } else {
+            (self.config.thumbnail_caption == ThumbnailCaption::NameAndDimensions, has_dimensions)
+        };
+        if !shows_media_info {
+            return;
+        }
+        
+        let page = self.visible_count.max(1);
+        let end = (self.visible_start + page * 2).min(self.list_data.len());
+        let paths: Vec<String> = self.list_data[self.visible_start.min(end)..end]
+            .iter()
+            .filter(|item| readable(item.extension()) && !self.media_infos.contains(item.path()))
+            .map(|item| item.path().to_string())
+            .collect();
+        
+        if let Some(loader) = self.media_info_loader.as_mut() {
+            loader.request(paths);
+        }
+    }
+    
+    fn handle_media_info_loaded(&mut self, entries_ptr: usize) {
+        let entries = unsafe { Box::from_raw(entries_ptr as *mut Vec<MediaInfoEntry>) };
+        
+        for entry in entries.into_iter() {
+            if let Some(loader) = self.media_info_loader.as_mut() {
+                loader.finished(&entry.path);
+            }
+            self.media_infos.put(entry.path, entry.info);
+        }
+        
+        unsafe {
+            InvalidateRect(self.list_view, None, FALSE);
+        }
+    }
+    
+    fn handle_link_info_loaded(&mut self, entries_ptr: usize) {
+        let entries = unsafe { Box::from_raw(entries_ptr as *mut Vec<LinkInfoEntry>) };
+        
+        for entry in entries.into_iter() {
+            if let Some(loader) = self.link_info_loader.as_mut() {
+                loader.finished(&entry.path);
+            }
+            self.link_infos.put(entry.path, entry.info);
+        }
+        
+        unsafe {
+            InvalidateRect(self.list_view, None, FALSE);
+        }
+    }
+    
+    fn handle_git_status_loaded(&mut self, entries_ptr: usize) {
+        let entries = unsafe { Box::from_raw(entries_ptr as *mut Vec<GitStatusEntry>) };
+        
+        for entry in entries.into_iter() {
+            if let Some(loader) = self.git_status_loader.as_mut() {
+                loader.finished(&entry.path);
+            }
+            self.git_statuses.put(entry.path, entry.status);
+        }
+        
+        unsafe {
+            InvalidateRect(self.list_view, None, FALSE);
+        }
+    }
+    
+    fn handle_metadata_loaded(&mut self, entries_ptr: usize) {
+        let entries = unsafe { Box::from_raw(entries_ptr as *mut Vec<MetadataEntry>) };
+        
+        let mut moved = false;
+        for entry in entries.iter() {
+            if let Some(loader) = self.metadata_loader.as_mut() {
+                loader.finished(&entry.path);
+            }
+            
+            // The list may have been sorted, filtered or replaced since the
+            // request was made
+            match self.list_data.get_mut(entry.index) {
+                Some(item) if item.path() == entry.path => {
+                    let old_size = item.size;
+                    item.set_metadata(entry.size, entry.modified_time);
+                    // The total of its drive group goes up by what was read
+                    let group = self.drive_groups.as_mut().and_then(|groups| {
+                        drive_groups::group_of(groups, entry.index).map(|group| &mut groups[group])
+                    });
+                    if let Some(group) = group {
+                        group.total_size = group.total_size + item.size - old_size;
+                    }
+                    // A folder that couldn't be read counts as empty, so it
+                    // isn't requested again
+                    if entry.folder_items.is_some() || item.is_folder {
+                        item.set_folder_items(entry.folder_items.unwrap_or(0));
+                    }
+                    if let Some(rect) = get_item_rect(entry.index, self) {
+                        unsafe {
+                            InvalidateRect(self.list_view, Some(&rect), FALSE);
+                        }
+                    }
+                }
+                _ => moved = true,
+            }
+        }
+        
+        log_trace(&format!("Metadata loaded for {} rows", entries.len()));
+        
+        // Rows that moved are requested again at their new position
+        if moved {
+            unsafe {
+                InvalidateRect(self.list_view, None, FALSE);
+            }
+        }
+    }
+
+    // Async search methods
+    // An empty search box runs the configured default search. Returns the
+    // Everything query to run, or None when nothing should be shown.
+    // Whether `query` is the show-all search, typed or as the default
+    fn shows_whole_index(&self, query: &str) -> bool {
+        let query = if query.trim().is_empty() { self.config.default_search.trim() } else { query };
+        query.eq_ignore_ascii_case(SHOW_ALL_SEARCH)
+    }
+    
+    // True while the rows are the whole index, read as they are scrolled to
+    fn browsing_index(&self) -> bool {
+        self.index_browse.is_some() && !self.is_list_mode
+    }
+    
+    // Everything's order for the active sort while browsing the index.
+    // Columns Everything can't sort by fall back to Name.
+    fn browse_sort(&self) -> u32 {
+        let Some(sort_state) = self.sort_state.as_ref().filter(|sort_state| sort_state.order != SortOrder::None) else {
+            return SORT_NAME_ASCENDING;
+        };
+        let ascending = sort_state.column.everything_sort().unwrap_or(SORT_NAME_ASCENDING);
+        if sort_state.order == SortOrder::Descending { ascending + 1 } else { ascending }
+    }
+    
+    // Asks for the pages of the index around the visible rows that haven't
+    // been read yet, the page above and below included
+    fn recompute_index_page_queue(&mut self) {
+        if !self.browsing_index() {
+            return;
+        }
+        let (Some(sender), Some(browse)) = (self.search_sender.as_ref(), self.index_browse.as_mut()) else {
+            return;
+        };
+        
+        let page = self.visible_count.max(1);
+        let start = self.visible_start.saturating_sub(page);
+        let end = (self.visible_start + page * 2).min(self.list_data.len());
+        if start >= end {
+            return;
+        }
+        
+        let generation = self.search_generation.load(Ordering::Relaxed);
+        for number in start / BROWSE_PAGE_SIZE..=(end - 1) / BROWSE_PAGE_SIZE {
+            if !browse.requested.insert(number) {
+                continue;
+            }
+            let request = SearchRequest {
+                query: browse.query.clone(),
+                generation,
+                window: self.main_window,
+                message: WM_SEARCH_RESULTS,
+                cancel_flag: self.search_cancel_flag.clone(),
+                max_results: BROWSE_PAGE_SIZE,
+                sorter: None,
+                page: Some(PageRequest { offset: number * BROWSE_PAGE_SIZE, sort: browse.sort }),
+                memory_budget: None,
+                filter: None,
+            };
+            if let Err(e) = sender.send(request) {
+                log_error(&format!("Failed to send page request: {}", e));
+            }
+        }
+    }
+    
+    // Puts a page of the index in place of its placeholder rows
+    fn handle_index_page(&mut self, page_ptr: usize) {
+        let IndexPage { offset, results, generation } = *unsafe { Box::from_raw(page_ptr as *mut IndexPage) };
+        if generation != self.search_generation.load(Ordering::Relaxed) || !self.browsing_index() {
+            log_debug("Ignoring a page of an earlier search");
+            return;
+        }
+        
+        for (row, result) in self.list_data.iter_mut().skip(offset).zip(results) {
+            if row.is_placeholder() {
+                *row = result;
+            }
+        }
+        
+        unsafe {
+            let _ = PostMessageW(self.main_window, WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
+            InvalidateRect(self.list_view, None, FALSE);
+        }
+    }
+    
+    // The memory budget of a search for `query`, unless the user chose to
+    // load all its results
+    fn memory_budget_for(&self, query: &str) -> Option<u64> {
+        match self.memory_budget_answer {
+            Some((ref answered, true)) if answered == query => None,
+            _ => budget_bytes(self.config.memory_budget_mb),
+        }
+    }
+    
+    // The latest search found `found` results, more than the memory budget
+    // allows, and shows only the first of them. Asks once per search whether
+    // to load them all.
+    fn confirm_over_memory_budget(&mut self, found: usize, generation: u64) {
+        if generation != self.search_generation.load(Ordering::Relaxed) {
+            return;
+        }
+        let Some(query) = self.resolve_search_query(&self.searched_query) else {
+            return;
+        };
+        if self.memory_budget_answer.as_ref().is_some_and(|(answered, _)| *answered == query) {
+            return;
+        }
+        
+        let strings = get_strings();
+        let budget = budget_bytes(self.config.memory_budget_mb).unwrap_or(0);
+        let message = fill(&strings.memory_budget_prompt, &[
+            ("count", &found.to_string()),
+            ("size", &format_size(predicted_results_bytes(found), SizeFormat::Auto, &strings)),
+            ("budget", &format_size(budget, SizeFormat::Auto, &strings)),
+            ("shown", &self.list_data.len().to_string()),
+        ]);
+        let load_all = unsafe {
+            MessageBoxW(
+                self.main_window,
+                PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                PCWSTR::from_raw(to_wide(&strings.memory_budget_title).as_ptr()),
+                MB_ICONWARNING | MB_YESNO,
+            ) == IDYES
+        };
+        
+        self.memory_budget_answer = Some((query, load_all));
+        if load_all {
+            self.start_async_search(self.searched_query.clone());
+        }
+    }
+    
+    fn resolve_search_query(&self, query: &str) -> Option<String> {
+        let query = if query.trim().is_empty() { self.config.default_search.trim() } else { query };
+        if query.is_empty() && self.search_scope.is_none() {
+            None
+        } else if query.eq_ignore_ascii_case(SHOW_ALL_SEARCH) {
+            Some(self.filtered_query(""))
+        } else {
+            Some(self.filtered_query(query))
+        }
+    }
+    
+    // Groups the active filter's search so an OR inside it stays apart from
+    // the query, the way Everything combines them. The folder tree's scope
+    // and the excluded folders go in front of both.
+    fn filtered_query(&self, query: &str) -> String {
+        self.combined_query(query, self.active_filter.as_ref(), self.search_scope.as_deref())
+    }
+    
+    fn combined_query(&self, query: &str, filter: Option<&SearchFilter>, scope: Option<&str>) -> String {
+        let query = match filter {
+            Some(filter) if query.is_empty() => filter.search.clone(),
+            Some(filter) => format!("<{}> {}", filter.search, query),
+            None => query.to_string(),
+        };
+        let mut prefix = scope.map_or(String::new(), scoped_search_query);
+        for folder in &self.config.excluded_folders {
+            prefix.push_str(&excluded_search_query(folder));
+        }
+        if prefix.is_empty() {
+            query
+        } else {
+            format!("{}{}", prefix, query).trim_end().to_string()
+        }
+    }
+    
+    fn start_async_search(&mut self, query: String) {
+        log_debug(&format!("start_async_search called with query: '{}'", query));
+        
+        // Cancel any existing search
+        self.search_cancel_flag.store(true, Ordering::Relaxed);
+        log_debug("Cancelled existing search");
+        
+        // Increment generation counter and get new values
+        let generation = self.search_generation.fetch_add(1, Ordering::Relaxed) + 1;
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        self.search_cancel_flag = cancel_flag.clone();
+        
+        log_debug(&format!("New search generation: {}", generation));
+        
+        // Files may have been edited since their Git status was read
+        self.git_statuses.clear();
+        self.link_infos.clear();
+        self.media_infos.clear();
+        self.index_browse = None;
+        
+        // Store the pending search for debouncing
+        self.pending_search_query = query.clone();
+        self.searched_query = query.clone();
+        if let Some(ref mut search_tabs) = self.search_tabs {
+            search_tabs.set_active_query(&query);
+        }
+        self.stream_sorted = true;
+        self.last_search_time = Instant::now();
+        
+        // A connected ETP server takes the place of the local Everything
+        if let Some(sender) = self.remote_sender.as_ref().or(self.search_sender.as_ref()) {
+            let query = match self.resolve_search_query(&query) {
+                Some(query) => query,
+                None => {
+                    log_debug("Default search is empty, showing no results");
+                    post_search_batch(self.main_window, WM_SEARCH_RESULTS, SearchBatch::complete(Vec::new(), generation));
+                    return;
+                }
+            };
+            
+            // Everything knows nothing of tags and ratings, so tag: and
+            // rating: searches go through the tagged and rated files here
+            // instead, unless files without any can match too
+            let list_query = ListQuery::parse(&query);
+            let filters_marks = self.remote_sender.is_none() && list_query.mentions_tags();
+            if filters_marks && !list_query.can_match_unmarked() {
+                let results: Vec<FileResult> = self.tags
+                    .tagged_or_rated_paths()
+                    .iter()
+                    .map(|path| {
+                        let mut file = FileResult::from_path(path);
+                        file.load_metadata();
+                        file
+                    })
+                    .filter(|file| list_query.matches(file, &self.tags, &self.list_notes))
+                    .take(self.config.max_results as usize)
+                    .collect();
+                log_debug(&format!("Tag search found {} files", results.len()));
+                post_search_batch(self.main_window, WM_SEARCH_RESULTS, SearchBatch::complete(results, generation));
+                return;
+            }
+            
+            log_debug("Sending search request to Everything SDK thread");
+            
+            let mut request = SearchRequest {
+                query: query.clone(),
+                generation,
+                window: self.main_window,
+                message: WM_SEARCH_RESULTS,
+                cancel_flag: cancel_flag.clone(),
+                max_results: self.config.max_results as usize,
+                sorter: None,
+                page: None,
+                memory_budget: self.memory_budget_for(&query),
+                filter: None,
+            };
+            if filters_marks {
+                request.query = list_query.everything_query();
+                request.memory_budget = None;
+                request.filter = Some(ResultFilter {
+                    query: list_query,
+                    tags: self.tags.snapshot(),
+                    notes: self.list_notes.clone(),
+                });
+            }
+            
+            // The show-all search reads the index a page at a time as it is
+            // scrolled instead of stopping at the result limit
+            if self.remote_sender.is_none() && self.shows_whole_index(&self.searched_query) {
+                let sort = self.browse_sort();
+                self.index_browse = Some(IndexBrowse {
+                    query: query.clone(),
+                    sort,
+                    requested: std::collections::HashSet::from([0]),
+                });
+                request.page = Some(PageRequest { offset: 0, sort });
+            } else {
+                let by_drive = self.config.group_by_drive;
+                request.sorter = self.sort_spec().map(|(key, text, descending)| StreamSorter::new(key, text, descending, by_drive));
+            }
+            
+            if let Err(e) = sender.send(request) {
+                log_error(&format!("Failed to send search request: {}", e));
+            } else {
+                log_debug("Search request sent to Everything SDK thread successfully");
+            }
+        } else {
+            log_debug("No Everything SDK available, using sample data with rayon");
+            
+            // For sample data, use rayon (thread-safe)
+            let window = self.main_window;
+            let query_clone = query.clone();
+            let max_results = self.config.max_results as usize;
+            
+            rayon::spawn(move || {
+                log_debug(&format!("Sample data background thread started for query: '{}'", query_clone));
+                
+                // Small delay to allow for more keystrokes (debouncing)
+                std::thread::sleep(Duration::from_millis(150));
+                
+                // Check if we've been cancelled during the delay
+                if cancel_flag.load(Ordering::Relaxed) {
+                    log_debug("Sample data search cancelled during debounce delay");
+                    return;
+                }
+                
+                log_debug("Starting sample data filtering");
+                
+                // Use sample data filtering
+                let search_result: std::result::Result<Vec<String>, String> = if query_clone.trim().is_empty() {
+                    // Return all sample data
+                    let mut results = Vec::new();
+                    for i in 0..100000 {
+                        results.push(format!("C:\\Users\\Example\\Documents\\File_{:06}.txt", i));
+                    }
+                    Ok(results)
+                } else {
+                    // Filter sample data by query
+                    let query_lower = query_clone.to_lowercase();
+                    let mut results = Vec::new();
+                    for i in 0..100000 {
+                        let filename = format!("File_{:06}.txt", i);
+                        let path = format!("C:\\Users\\Example\\Documents\\File_{:06}.txt", i);
+                        
+                        // Simple string matching
+                        if filename.to_lowercase().contains(&query_lower) || 
+                           path.to_lowercase().contains(&query_lower) {
+                            results.push(path);
+                        }
+                    }
+                    Ok(results)
+                };
+                
+                // Check if we've been cancelled after the search
+                if cancel_flag.load(Ordering::Relaxed) {
+                    log_debug("Sample data search cancelled after filtering");
+                    return;
+                }
+                
+                log_debug("Sample data filtering completed, sending results to UI thread");
+                
+                // Send results back to UI thread
+                match search_result {
+                    Ok(file_paths) => {
+                        log_debug(&format!("Converting {} sample file paths to FileResult objects", file_paths.len()));
+                        
+                        let results: Vec<crate::everything_sdk::FileResult> = file_paths
+                            .into_iter()
+                            .take(max_results)
+                            .map(|path| crate::everything_sdk::FileResult::from_path(&path))
+                            .collect();
+                        
+                        log_debug("Posting WM_SEARCH_RESULTS message");
+                        post_search_batch(window, WM_SEARCH_RESULTS, SearchBatch::complete(results, generation));
+                    }
+                    Err(e) => {
+                        log_error(&format!("Sample data search failed: {}", e));
+                        // Send empty results on error
+                        post_search_batch(window, WM_SEARCH_RESULTS, SearchBatch::complete(Vec::new(), generation));
+                    }
+                }
+                
+                log_debug("Sample data background thread completed");
+            });
+        }
+        
+        log_debug("start_async_search completed");
+    }
+    
+    // Applies one batch of search results. The first batch replaces the list;
+    // later ones are merged in while the search is still reading.
+    fn handle_search_results(&mut self, batch_ptr: usize) {
+        log_debug(&format!("handle_search_results called with ptr: {}", batch_ptr));
+        
+        unsafe {
+            log_trace("Converting pointer back to Box");
+            // Convert pointer back to Box
+            let SearchBatch { results, positions, total, generation, first, last } = *Box::from_raw(batch_ptr as *mut SearchBatch);
+            
+            log_debug(&format!("Unpacked results: {} items, generation: {}", results.len(), generation));
+            
+            // Check if this result is from the current generation
+            let current_generation = self.search_generation.load(Ordering::Relaxed);
+            if generation != current_generation {
+                log_debug(&format!("Ignoring old search results (gen {} vs current {})", generation, current_generation));
+                // This is from an old search, ignore it
+                return;
+            }
+            
+            log_info(&format!("Received async search results: {} items ({} so far)", results.len(), total));
+            let _stage = time_stage("display");
+            
+            // The positions only fit if nothing has reordered the rows since
+            // the previous batch
+            let shown = if first { 0 } else { self.list_data.len() };
+            let positions = positions.filter(|_| self.stream_sorted && shown + results.len() == total);
+            self.stream_sorted = positions.is_some();
+            
+            if first {
+                // Re-running the query on screen keeps the selection and scroll position
+                let anchor = if self.displayed_query.as_deref() == Some(self.searched_query.as_str()) {
+                    Some(self.capture_view_anchor())
+                } else {
+                    None
+                };
+                self.displayed_query = Some(self.searched_query.clone());
+                
+                log_trace("About to update list_data");
+                // Update UI with results
+                self.list_data = results;
+                log_trace(&format!("Updated list_data, new size: {}", self.list_data.len()));
+                
+                if let Some(anchor) = anchor {
+                    self.calculate_layout();
+                    self.restore_view_anchor(anchor);
+                    log_debug(&format!("Restored selection and scroll position, scroll_pos: {}", self.scroll_pos));
+                } else {
+                    self.reset_selection();
+                    log_trace("Updated selected_index");
+                
+                    // Only reset scroll position if we're not currently dragging the scrollbar
+                    // This prevents the scrollbar from jumping back to the top during scroll operations
+                    if !self.is_scrollbar_dragging {
+                    self.scroll_pos = 0;
+                        log_debug("Reset scroll position (not dragging)");
+                    } else {
+                        log_debug("Preserving scroll position during scrollbar dragging");
+                    }
+                
+                    self.calculate_layout();
+                    log_trace("Calculated layout");
+                }
+                
+                if let Some(session) = self.pending_session.take() {
+                    log_debug("Applying restored session view");
+                    self.apply_session_view(&session);
+                }
+            } else {
+                self.merge_search_batch(results, positions);
+            }
+            
+            // Cancel all thumbnail tasks and recompute
+            if let Some(ref task_manager) = self.thumbnail_task_manager {
+                log_trace("Cancelling all thumbnail tasks");
+                task_manager.cancel_all_tasks();
+            }
+            
+            // Clear thumbnail cache; it is keyed by path, so later batches keep it
+            if first {
+                log_trace("Clearing thumbnail cache");
+                self.thumbnail_cache.clear();
+            }
+            
+            // Post message to recompute thumbnails
+            log_trace("Posting WM_RECOMPUTE_THUMBS message");
+            let _ = PostMessageW(self.main_window, WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
+            
+            // Update UI
+            log_trace("About to update UI components");
+            log_trace("Updating scrollbar");
+            update_scrollbar(self);
+            log_trace("Invalidating list view");
+            InvalidateRect(self.list_view, None, TRUE);
+            log_trace("Updating status bar");
+            update_status_bar(self);
+            log_trace("UI update completed");
+            
+            if last {
+                notify_rows_changed(self.list_view);
+            }
+            
+            // Sort what couldn't be merged in order once everything is here.
+            // Everything has sorted the index already when browsing it.
+            if last && !self.stream_sorted && !self.browsing_index() {
+                self.apply_sort();
+            }
+            
+            log_debug("handle_search_results completed successfully");
+        }
+    }
+    
+    // Adds a later batch of the running search. Sorted batches go in at the
+    // positions the search thread worked out, others are appended. The item
+    // at the top of the view stays where it is unless the view is at the top.
+    fn merge_search_batch(&mut self, results: Vec<FileResult>, positions: Option<Vec<usize>>) {
+        let at_top = self.scroll_pos == 0;
+        let anchor = self.capture_view_anchor();
+        
+        match positions {
+            Some(positions) => {
+                let mut existing = std::mem::take(&mut self.list_data).into_iter();
+                let mut merged = Vec::with_capacity(existing.len() + results.len());
+                for (item, position) in results.into_iter().zip(positions) {
+                    let before = position.saturating_sub(merged.len());
+                    merged.extend(existing.by_ref().take(before));
+                    merged.push(item);
+                }
+                merged.extend(existing);
+                self.list_data = merged;
+            }
+            None => self.list_data.extend(results),
+        }
+        
+        self.calculate_layout();
+        self.restore_view_anchor(anchor);
+        if at_top {
+            self.scroll_pos = 0;
+            self.calculate_layout();
+        }
+    }
+
+    fn search_local_list(&mut self, query: &str) {
+        if !self.is_list_mode || self.original_list_data.is_empty() {
+            return;
+        }
+
+        self.filter_local_list(query);
+
+        // Reset selection and scroll
+        self.reset_selection();
+        self.scroll_pos = 0;
+
+        unsafe {
+            self.calculate_layout();
+            update_scrollbar(self);
+            InvalidateRect(self.list_view, None, TRUE);
+            update_status_bar(self);
+        }
+    }
+
+    // Shows the entries of the open list that match `query`
+    fn filter_local_list(&mut self, query: &str) {
+        let list_query = ListQuery::parse(query);
+        
+        if list_query.is_empty() {
+            // Show all files when query is empty
+            self.list_data = self.original_list_data
+                .iter()
+                .filter(|file| !self.config.is_excluded(file.path()))
+                .cloned()
+                .collect();
+        } else {
+            // size: and dm: filters need metadata; load it once and keep it on the list
+            if list_query.needs_metadata() {
+                self.original_list_data.par_iter_mut().for_each(|file| {
+                    if !file.missing {
+                        file.load_metadata();
+                    }
+                });
+            }
+            
+            // Filter files based on query
+            self.list_data = self.original_list_data
+                .iter()
+                .filter(|file| list_query.matches(file, &self.tags, &self.list_notes) && !self.config.is_excluded(file.path()))
+                .cloned()
+                .collect();
+        }
+    }
+
+    // Starts an Everything search limited to `folder`, for the Explorer verb.
+    // The folder goes into the search box so the query can be refined.
+    // Opens a tab listing the folder result `index` is in, like a file
+    // browser would
+    fn browse_folder_in_new_tab(&mut self, index: usize) {
+        let Some(folder) = self.list_data
+            .get(index)
+            .and_then(|item| std::path::Path::new(item.path()).parent())
+            .map(|folder| folder.to_string_lossy().to_string())
+        else {
+            return;
+        };
+        let Some(ref mut search_tabs) = self.search_tabs else {
+            return;
+        };
+        log_info(&format!("Browsing {} in a new tab", folder));
+        let query = browse_folder_query(&folder);
+        search_tabs.open(&query);
+        self.set_search_text(&query);
+        self.layout_search_tabs();
+    }
+    
+    // Switches to tab `index`, searching for its query again
+    fn select_search_tab(&mut self, index: usize) {
+        if let Some(query) = self.search_tabs.as_mut().and_then(|search_tabs| search_tabs.select(index)) {
+            self.set_search_text(&query);
+        }
+    }
+    
+    fn close_search_tab(&mut self, index: usize) {
+        let Some(ref mut search_tabs) = self.search_tabs else {
+            return;
+        };
+        if let Some(query) = search_tabs.close(index) {
+            self.set_search_text(&query);
+        }
+        self.layout_search_tabs();
+    }
+    
+    // The strip shows or hides as tabs come and go
+    fn layout_search_tabs(&mut self) {
+        unsafe {
+            let mut rect = RECT::default();
+            let _ = GetClientRect(self.main_window, &mut rect);
+            resize_controls(self, rect.right, rect.bottom);
+            InvalidateRect(self.list_view, None, TRUE);
+        }
+    }
+    
+    fn search_in_folder(&mut self, folder: &str) {
+        log_info(&format!("Searching in folder: {}", folder));
+        // Changing the text starts the search through EN_CHANGE
+        self.set_search_text(&scoped_search_query(folder));
+    }
+    
+    // Puts `query` in the search box with the caret at the end, leaving list
+    // mode first since the search box only drives Everything searches
+    fn set_search_text(&mut self, query: &str) {
+        if self.is_list_mode {
+            self.close_file_list();
+        }
+        
+        let length = query.encode_utf16().count();
+        unsafe {
+            let _ = SetWindowTextW(self.search_edit, PCWSTR::from_raw(to_wide(query).as_ptr()));
+            SendMessageW(self.search_edit, EM_SETSEL, WPARAM(length), LPARAM(length as isize));
+            SetFocus(self.search_edit);
+        }
+    }
+    
+    // Puts an operator from the syntax cheat sheet in the search box at the
+    // caret, apart from the word before it. The caret ends up between the
+    // quotes or brackets of a pair.
+    fn insert_search_syntax(&mut self, syntax: &str) {
+        if self.is_list_mode {
+            self.close_file_list();
+        }
+        
+        unsafe {
+            let mut buffer = vec![0u16; GetWindowTextLengthW(self.search_edit) as usize + 1];
+            let copied = GetWindowTextW(self.search_edit, &mut buffer) as usize;
+            let mut start = 0u32;
+            SendMessageW(self.search_edit, EM_GETSEL, WPARAM(&mut start as *mut u32 as usize), LPARAM(0));
+            let before = buffer[..(start as usize).min(copied)].last().copied();
+            
+            let separated = match before {
+                Some(previous) if !char::from_u32(previous as u32).is_some_and(char::is_whitespace) => format!(" {}", syntax),
+                _ => syntax.to_string(),
+            };
+            SendMessageW(
+                self.search_edit,
+                EM_REPLACESEL,
+                WPARAM(1),
+                LPARAM(to_wide(&separated).as_ptr() as isize),
+            );
+            if syntax == "\"\"" || syntax == "<>" {
+                let mut caret = 0u32;
+                SendMessageW(self.search_edit, EM_GETSEL, WPARAM(&mut caret as *mut u32 as usize), LPARAM(0));
+                let caret = caret.saturating_sub(1) as usize;
+                SendMessageW(self.search_edit, EM_SETSEL, WPARAM(caret), LPARAM(caret as isize));
+            }
+            SetFocus(self.search_edit);
+        }
+    }
+    
+    // Runs `query` right away for the tray, the quick search bar or the
+    // jump list, bringing the window back first
+    // Searches for the launcher on the same Everything thread as the list.
+    // Results come back as WM_LAUNCHER_RESULTS and are ranked once all are in.
+    fn search_for_launcher(&mut self, query: String) {
+        self.launcher_cancel_flag.store(true, Ordering::Relaxed);
+        self.launcher_generation += 1;
+        self.launcher_candidates.clear();
+        
+        let sender = match self.search_sender.as_ref() {
+            Some(sender) if !query.is_empty() => sender,
+            _ => {
+                set_launcher_results(Vec::new());
+                return;
+            }
+        };
+        
+        self.launcher_cancel_flag = Arc::new(AtomicBool::new(false));
+        self.launcher_query = query.clone();
+        let request = SearchRequest {
+            query,
+            generation: self.launcher_generation,
+            window: self.main_window,
+            message: WM_LAUNCHER_RESULTS,
+            cancel_flag: self.launcher_cancel_flag.clone(),
+            max_results: LAUNCHER_CANDIDATES,
+            sorter: None,
+            page: None,
+            memory_budget: None,
+            filter: None,
+        };
+        if let Err(e) = sender.send(request) {
+            log_error(&format!("Failed to send launcher search request: {}", e));
+        }
+    }
+    
+    fn handle_launcher_results(&mut self, batch_ptr: usize) {
+        let batch = unsafe { Box::from_raw(batch_ptr as *mut SearchBatch) };
+        if batch.generation != self.launcher_generation {
+            return;
+        }
+        
+        self.launcher_candidates.extend(batch.results);
+        if !batch.last {
+            return;
+        }
+        
+        let ranker = self.run_history.ranker(&self.launcher_query);
+        let mut ranked: Vec<(f64, FileResult)> = std::mem::take(&mut self.launcher_candidates)
+            .into_iter()
+            .map(|file| (ranker.score(file.path(), file.name()), file))
+            .collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.name().to_lowercase().cmp(&b.1.name().to_lowercase())));
+        set_launcher_results(ranked.into_iter().take(LAUNCHER_RESULTS).map(|(_, file)| file).collect());
+    }
+    
+    // Registers or removes the global launcher hotkey. Returns false when
+    // another program already owns the key combination.
+    fn set_launcher_hotkey(&self, enabled: bool) -> bool {
+        unsafe {
+            if enabled {
+                RegisterHotKey(
+                    self.main_window,
+                    LAUNCHER_HOTKEY_ID,
+                    MOD_CONTROL | MOD_SHIFT | MOD_NOREPEAT,
+                    VK_SPACE.0 as u32,
+                ).is_ok()
+            } else {
+                let _ = UnregisterHotKey(self.main_window, LAUNCHER_HOTKEY_ID);
+                true
+            }
+        }
+    }
+    
+    fn run_search(&mut self, query: &str) {
+        restore_from_tray(self.main_window, self);
+        self.set_search_text(query);
+        handle_immediate_search(self);
+        self.add_recent_search(query);
+    }
+    
+    fn add_recent_search(&mut self, query: &str) {
+        self.config.add_recent_search(query);
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        self.refresh_jump_list();
+    }
+    
+    // Rebuilds the taskbar jump list from the recent searches and lists
+    fn refresh_jump_list(&self) {
+        let strings = get_strings();
+        let categories = [
+            JumpListCategory {
+                title: strings.tray_recent_searches.clone(),
+                items: self.config.recent_searches.iter().map(|query| JumpListItem::search(query)).collect(),
+            },
+            JumpListCategory {
+                title: strings.file_recent_lists.clone(),
+                items: self.config.recent_lists_for_menu().iter().map(|entry| JumpListItem::open_list(&entry.path)).collect(),
+            },
+        ];
+        if let Err(e) = update_jump_list(&categories) {
+            log_warn(&format!("Failed to update the jump list: {}", e));
+        }
+    }
+    
+    // Registers or removes the global quick search hotkey. Returns false
+    // when another program already owns the key combination.
+    fn set_quick_search_hotkey(&self, enabled: bool) -> bool {
+        unsafe {
+            if enabled {
+                RegisterHotKey(
+                    self.main_window,
+                    QUICK_SEARCH_HOTKEY_ID,
+                    MOD_CONTROL | MOD_ALT | MOD_NOREPEAT,
+                    VK_SPACE.0 as u32,
+                ).is_ok()
+            } else {
+                let _ = UnregisterHotKey(self.main_window, QUICK_SEARCH_HOTKEY_ID);
+                true
+            }
+        }
+    }
+
+    // Called for every clipboard change while the clipboard watcher is on.
+    // Paths copied in other programs are offered in a tray notification.
+    fn offer_copied_paths(&mut self) {
+        // Copy Path and friends in this window would only echo back
+        if clipboard_owned_by(self.main_window) {
+            return;
+        }
+        
+        let paths = read_clipboard_paths(self.main_window);
+        if paths.is_empty() || paths == self.copied_paths {
+            return;
+        }
+        log_debug(&format!("Clipboard holds {} paths", paths.len()));
+        
+        let strings = get_strings();
+        let text = match paths.len() {
+            1 => paths[0].clone(),
+            count => fill(&strings.clipboard_watch_paths, &[("path", &paths[0]), ("count", &(count - 1).to_string())]),
+        };
+        let text = format!("{}\n{}", text, strings.clipboard_watch_hint);
+        if !self.show_notification(&strings.clipboard_watch_title, &text) {
+            log_warn("Failed to show the clipboard notification");
+            return;
+        }
+        self.copied_paths = paths;
+    }
+    
+    // A notification from the tray icon, which is put up for it when the
+    // window is showing. Returns false when it couldn't be shown.
+    fn show_notification(&mut self, title: &str, text: &str) -> bool {
+        // Clicking it offers copied paths only while it is about them
+        self.copied_paths.clear();
+        if !self.tray_icon_visible {
+            self.tray_icon_visible = add_tray_icon(self.main_window, "Everything-like File Browser");
+            self.tray_icon_for_balloon = self.tray_icon_visible;
+        }
+        if self.tray_icon_visible && show_tray_balloon(self.main_window, title, text) {
+            return true;
+        }
+        self.remove_balloon_tray_icon();
+        false
+    }
+    
+    // Takes down a tray icon that was only put up for a notification, unless
+    // the window has been hidden to the tray since
+    fn remove_balloon_tray_icon(&mut self) {
+        if !std::mem::take(&mut self.tray_icon_for_balloon) {
+            return;
+        }
+        unsafe {
+            if self.tray_icon_visible && IsWindowVisible(self.main_window).as_bool() {
+                remove_tray_icon(self.main_window);
+                self.tray_icon_visible = false;
+            }
+        }
+    }
+    
+    // Searches for the copied paths so they show up in the results
+    fn reveal_copied_paths(&mut self) {
+        let paths = std::mem::take(&mut self.copied_paths);
+        if paths.is_empty() {
+            return;
+        }
+        
+        let query = paths
+            .iter()
+            .map(|path| format!("\"{}\"", path))
+            .collect::<Vec<_>>()
+            .join("|");
+        restore_from_tray(self.main_window, self);
+        self.set_search_text(&query);
+        handle_immediate_search(self);
+    }
+    
+    // Starts, restarts or stops the HTTP server. The running server is only
+    // replaced once the new one is listening.
+    fn set_http_server(&mut self, enabled: bool, port: u32) -> std::io::Result<()> {
+        if !enabled {
+            self.http_server = None;
+            return Ok(());
+        }
+        if self.http_server.as_ref().is_some_and(|server| server.port() as u32 == port) {
+            return Ok(());
+        }
+        
+        self.http_server = Some(HttpServer::start(port as u16)?);
         Ok(())
     }
+
+    fn close_file_list(&mut self) {
+        self.list_data.clear();
+        self.selected_index = None;
+        self.selected_items.clear();
+        self.selection_anchor = None;
+        self.scroll_pos = 0;
+        self.is_list_mode = false;
+        self.current_list_name = None;
+        self.current_list_path = None;
+        self.original_list_data.clear();
+        self.list_notes = ListNotes::default();
+        self.missing_count = 0;
+        self.stop_folder_monitor();
+        
+        // Discard any validation still running for the closed list
+        self.list_validation_generation += 1;
+        self.watch_current_list();
+
+        unsafe {
+            // Restore the default search
+            if let Err(e) = SetWindowTextW(self.search_edit, PCWSTR::from_raw(to_wide(&self.config.default_search).as_ptr())) {
+                log_warn(&format!("Could not restore the default search: {}", e));
+            }
+
+            self.calculate_layout();
+            update_scrollbar(self);
+            InvalidateRect(self.list_view, None, TRUE);
+            update_status_bar(self);
+
+            // Trigger a search to reload global data
+            handle_immediate_search(self);
+        }
+        
+        update_recent_list_menu_state(self.main_window, self);
+        update_list_menu_state(self.main_window, self);
+    }
+
+    // Adds clipboard paths to the open list, or starts an unsaved list from them
+    fn paste_paths_as_list(&mut self) {
+        let paths = read_clipboard_paths(self.main_window);
+        self.add_paths_to_list(&paths);
+    }
+    
+    fn add_paths_to_list(&mut self, paths: &[String]) {
+        if paths.is_empty() {
+            let strings = get_strings();
+            unsafe {
+                MessageBoxW(
+                    self.main_window,
+                    PCWSTR::from_raw(to_wide(&strings.error_clipboard_no_paths).as_ptr()),
+                    w!("Information"),
+                    MB_ICONINFORMATION | MB_OK,
+                );
+            }
+            return;
+        }
+        
+        // Pasted paths don't belong to a monitored folder, so they start a list of their own
+        let starting_new_list = !self.is_list_mode || self.monitored_folder.is_some();
+        if starting_new_list {
+            self.stop_folder_monitor();
+            self.is_list_mode = true;
+            self.current_list_name = Some(get_strings().clipboard_list_name.clone());
+            self.current_list_path = None;
+            self.list_notes = ListNotes::default();
+            self.watch_current_list();
+            self.original_list_data.clear();
+            self.list_data.clear();
+            self.missing_count = 0;
+        }
+        
+        // Skip paths that are already in the list or repeated in the clipboard
+        let mut known: std::collections::HashSet<String> = self.original_list_data
+            .iter()
+            .map(|item| item.path().to_lowercase())
+            .collect();
+        let new_items = FileResult::from_paths(
+            paths
+                .iter()
+                .map(String::as_str)
+                .filter(|path| known.insert(path.to_lowercase())),
+        );
+        
+        log_info(&format!("Pasted {} paths, {} new", paths.len(), new_items.len()));
+        
+        self.original_list_data.extend(new_items.iter().cloned());
+        self.list_data.extend(new_items);
+        
+        if starting_new_list {
+            self.reset_selection();
+            self.scroll_pos = 0;
+        }
+        
+        self.start_list_validation();
+        
+        unsafe {
+            self.calculate_layout();
+            update_scrollbar(self);
+            InvalidateRect(self.list_view, None, TRUE);
+            update_status_bar(self);
+            
+            if starting_new_list {
+                // Clear the search edit box to indicate we're in list mode
+                let _ = SetWindowTextW(self.search_edit, w!(""));
+            }
+        }
+        
+        update_recent_list_menu_state(self.main_window, self);
+        update_list_menu_state(self.main_window, self);
+    }
+    
+    // Restarts the file watcher for current_list_path, or stops it when no list file is open
+    fn watch_current_list(&mut self) {
+        self.list_watcher = None;
+        self.list_watch_generation += 1;
+        self.list_file_modified = None;
+        
+        if let Some(list_path) = &self.current_list_path {
+            self.list_file_modified = fs::metadata(list_path).and_then(|m| m.modified()).ok();
+            self.list_watcher = ListWatcher::start(list_path, self.main_window, WM_LIST_FILE_CHANGED, self.list_watch_generation);
+        }
+    }
+    
+    // File → Monitor Folder: lists the files under `folder` and keeps the
+    // list up to date as files appear, change or go away, most recently
+    // changed first. The search box filters it like any other list.
+    fn monitor_folder(&mut self, folder: &str) {
+        log_info(&format!("Monitoring folder: {}", folder));
+        self.stop_folder_monitor();
+        self.displayed_query = None;
+        
+        self.is_list_mode = true;
+        self.current_list_name = Some(fill(&get_strings().monitor_list_name, &[("folder", folder)]));
+        self.current_list_path = None;
+        self.watch_current_list();
+        self.list_validation_generation += 1;
+        self.original_list_data.clear();
+        self.list_notes = ListNotes::default();
+        self.list_data.clear();
+        self.missing_count = 0;
+        self.reset_selection();
+        self.scroll_pos = 0;
+        
+        self.monitored_folder = Some(folder.to_string());
+        self.folder_monitor_generation += 1;
+        self.folder_monitor = FolderMonitor::start(folder, self.main_window, WM_MONITORED_FOLDER_CHANGED, self.folder_monitor_generation);
+        if self.folder_monitor.is_none() {
+            let message = fill(&get_strings().error_monitor_folder, &[("folder", folder)]);
+            unsafe {
+                MessageBoxW(self.main_window, PCWSTR::from_raw(to_wide(&message).as_ptr()), w!("Error"), MB_ICONERROR | MB_OK);
+            }
+        }
+        self.scan_monitored_folder();
+        
+        unsafe {
+            self.calculate_layout();
+            update_scrollbar(self);
+            InvalidateRect(self.list_view, None, TRUE);
+            update_status_bar(self);
+            
+            // Clear the search edit box to indicate we're in list mode
+            let _ = SetWindowTextW(self.search_edit, w!(""));
+        }
+        
+        update_recent_list_menu_state(self.main_window, self);
+        update_list_menu_state(self.main_window, self);
+    }
+    
+    // Reads the whole monitored folder in the background
+    fn scan_monitored_folder(&self) {
+        let Some(folder) = self.monitored_folder.clone() else {
+            return;
+        };
+        let generation = self.folder_monitor_generation;
+        let window = self.main_window;
+        
+        std::thread::spawn(move || {
+            let files = scan_folder(&folder);
+            let scanned = Box::into_raw(Box::new((generation, files))) as usize;
+            unsafe {
+                if PostMessageW(window, WM_MONITORED_FOLDER_SCANNED, WPARAM(scanned), LPARAM(0)).is_err() {
+                    drop(Box::from_raw(scanned as *mut (u64, Vec<FileResult>)));
+                }
+            }
+        });
+    }
+    
+    fn stop_folder_monitor(&mut self) {
+        self.folder_monitor = None;
+        self.monitored_folder = None;
+        self.folder_monitor_generation += 1;
+    }
+    
+    fn handle_monitored_folder_scanned(&mut self, scanned_ptr: isize) {
+        let (generation, files) = unsafe { *Box::from_raw(scanned_ptr as *mut (u64, Vec<FileResult>)) };
+        if generation != self.folder_monitor_generation {
+            return;
+        }
+        
+        log_info(&format!("Monitored folder holds {} files", files.len()));
+        self.original_list_data = files;
+        self.refresh_monitored_list();
+    }
+    
+    fn handle_monitored_folder_changed(&mut self, changes_ptr: isize) {
+        let (generation, changes) = unsafe { *Box::from_raw(changes_ptr as *mut (u64, Vec<FolderChange>)) };
+        if generation != self.folder_monitor_generation {
+            return;
+        }
+        
+        for change in changes {
+            match change {
+                FolderChange::Added(file) | FolderChange::Modified(file) => {
+                    self.original_list_data.retain(|item| !item.path().eq_ignore_ascii_case(file.path()));
+                    self.original_list_data.insert(0, file);
+                }
+                FolderChange::Removed(path) => {
+                    // A removed folder takes everything under it along
+                    let path = path.to_lowercase();
+                    let prefix = format!("{}\\", path);
+                    self.original_list_data.retain(|item| {
+                        let item_path = item.path().to_lowercase();
+                        item_path != path && !item_path.starts_with(&prefix)
+                    });
+                }
+                FolderChange::Rescan => {
+                    log_debug("Folder monitor overflowed, reading the folder again");
+                    self.scan_monitored_folder();
+                }
+            }
+        }
+        self.refresh_monitored_list();
+    }
+    
+    // Refilters the monitored list with the current search, keeping the
+    // selection and the item at the top of the view unless the view is at
+    // the top, where new files should show up
+    fn refresh_monitored_list(&mut self) {
+        let at_top = self.scroll_pos == 0;
+        let anchor = self.capture_view_anchor();
+        
+        let query = unsafe {
+            let mut buffer: [u16; 1024] = [0; 1024];
+            let len = GetWindowTextW(self.search_edit, &mut buffer);
+            String::from_utf16_lossy(&buffer[..len.max(0) as usize])
+        };
+        self.filter_local_list(&query);
+        
+        self.calculate_layout();
+        self.restore_view_anchor(anchor);
+        if at_top {
+            self.scroll_pos = 0;
+            self.calculate_layout();
+        }
+        
+        unsafe {
+            update_scrollbar(self);
+            InvalidateRect(self.list_view, None, TRUE);
+            update_status_bar(self);
+        }
+    }
+    
+    // Called after saving, so our own writes don't trigger a reload prompt
+    fn remember_list_file_version(&mut self, file_path: &str) {
+        let is_current = self.current_list_path
+            .as_ref()
+            .map_or(false, |list_path| list_path.eq_ignore_ascii_case(file_path));
+        if is_current {
+            self.list_file_modified = fs::metadata(file_path).and_then(|m| m.modified()).ok();
+        }
+    }
+    
+    fn handle_list_file_changed(&mut self, generation: u64) {
+        if generation != self.list_watch_generation || self.list_reload_prompt_active {
+            return;
+        }
+        
+        let list_path = match &self.current_list_path {
+            Some(list_path) => list_path.clone(),
+            None => return,
+        };
+        
+        // Several notifications arrive for one save; only ask once per new version.
+        // A missing file is usually mid-replace, the rename will notify again.
+        let modified = match fs::metadata(&list_path).and_then(|m| m.modified()) {
+            Ok(modified) => modified,
+            Err(_) => return,
+        };
+        if self.list_file_modified == Some(modified) {
+            return;
+        }
+        self.list_file_modified = Some(modified);
+        
+        log_info(&format!("List file changed on disk: {}", list_path));
+        
+        let strings = get_strings();
+        let message = format!("{}\n{}", strings.confirm_reload_list, list_path);
+        self.list_reload_prompt_active = true;
+        let result = unsafe {
+            MessageBoxW(
+                self.main_window,
+                PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                PCWSTR::from_raw(to_wide(&strings.confirm_title).as_ptr()),
+                MB_ICONQUESTION | MB_YESNO,
+            )
+        };
+        self.list_reload_prompt_active = false;
+        
+        if result == IDYES {
+            self.reload_current_list();
+        }
+    }
+    
+    // Reloads the open list file, keeping the selection and scroll position where possible
+    fn reload_current_list(&mut self) {
+        let list_path = match &self.current_list_path {
+            Some(list_path) => list_path.clone(),
+            None => return,
+        };
+        
+        let anchor = self.capture_view_anchor();
+        
+        if let Err(e) = self.load_file_list(&list_path) {
+            log_error(&format!("Failed to reload file list: {}", e));
+            return;
+        }
+        
+        unsafe {
+            self.calculate_layout();
+            self.restore_view_anchor(anchor);
+            update_scrollbar(self);
+            InvalidateRect(self.list_view, None, TRUE);
+            update_status_bar(self);
+        }
+    }
+    
+    fn start_list_validation(&mut self) {
+        self.list_validation_generation += 1;
+        let generation = self.list_validation_generation;
+        let window = self.main_window;
+        let paths: Vec<String> = self.original_list_data.iter().map(|item| item.path().to_string()).collect();
+        
+        log_info(&format!("Starting background validation of {} list entries", paths.len()));
+        
+        std::thread::spawn(move || {
+            let missing: Vec<String> = paths
+                .into_iter()
+                .filter(|path| !std::path::Path::new(path).exists())
+                .collect();
+            
+            // Allocate results in a Box and send the pointer
+            let boxed_results = Box::new((missing, generation));
+            let results_ptr = Box::into_raw(boxed_results) as isize;
+            
+            unsafe {
+                let _ = PostMessageW(window, WM_LIST_VALIDATED, WPARAM(results_ptr as usize), LPARAM(0));
+            }
+        });
+    }
+    
+    fn handle_list_validated(&mut self, results_ptr: isize) {
+        let boxed_results = unsafe { Box::from_raw(results_ptr as *mut (Vec<String>, u64)) };
+        let (missing, generation) = *boxed_results;
+        
+        if generation != self.list_validation_generation || !self.is_list_mode {
+            log_debug("Ignoring validation results for a list that is no longer open");
+            return;
+        }
+        
+        log_info(&format!("List validation completed: {} missing entries", missing.len()));
+        
+        let missing: std::collections::HashSet<String> = missing.into_iter().collect();
+        for item in self.original_list_data.iter_mut().chain(self.list_data.iter_mut()) {
+            item.missing = missing.contains(item.path());
+        }
+        self.missing_count = self.original_list_data.iter().filter(|item| item.missing).count();
+        
+        unsafe {
+            InvalidateRect(self.list_view, None, TRUE);
+            update_status_bar(self);
+        }
+        update_list_menu_state(self.main_window, self);
+    }
+    
+    // Opens the duplicate finder on the current results
+    fn find_duplicates(&self) {
+        // Remote results can't be read from here
+        if self.showing_remote_results() {
+            return;
+        }
+        let paths: Vec<String> = self.list_data
+            .iter()
+            .filter(|item| !item.missing)
+            .map(|item| item.path().to_string())
+            .collect();
+        show_duplicate_finder(self.main_window, self.font, paths);
+    }
+    
+    // The selected folder, or the folder of the selected file, where the
+    // folder tools start. Empty, meaning every indexed drive, when nothing
+    // local is selected.
+    fn selected_folder(&self) -> String {
+        match self.selected_index.and_then(|index| self.list_data.get(index)) {
+            Some(file) if !self.showing_remote_results() => {
+                let path = std::path::Path::new(file.path());
+                let folder = if path.is_dir() { Some(path) } else { path.parent() };
+                folder.map(|folder| folder.to_string_lossy().to_string()).unwrap_or_default()
+            }
+            _ => String::new(),
+        }
+    }
+    
+    // Drops what a tool moved to the Recycle Bin
+    fn forget_deleted_files(&mut self, paths: &[String]) {
+        let deleted: std::collections::HashSet<&str> = paths.iter().map(|path| path.as_str()).collect();
+        self.retain_results(|item| !deleted.contains(item.path()));
+    }
+    
+    // Drops the results `keep` rejects from the list and the loaded file list
+    fn retain_results(&mut self, keep: impl Fn(&FileResult) -> bool) {
+        self.original_list_data.retain(|item| keep(item));
+        self.list_data.retain(|item| keep(item));
+        
+        self.reset_selection();
+        
+        unsafe {
+            self.calculate_layout();
+            self.scroll_pos = self.scroll_pos.min((self.total_height - self.client_height).max(0));
+            update_scrollbar(self);
+            InvalidateRect(self.list_view, None, TRUE);
+            update_status_bar(self);
+        }
+    }
+    
+    // Asks for a tag name and gives it to the selected results
+    fn add_new_tag(&mut self) {
+        let strings = get_strings();
+        let Some(name) = prompt_text(self.main_window, &strings.tags_new, &strings.tags_new_prompt, "", self.font) else {
+            return;
+        };
+        let paths: Vec<String> = self.get_selected_results().iter().map(|file| file.path().to_string()).collect();
+        let result = self.tags.add_tag(&paths, name.trim());
+        self.tags_changed(result);
+    }
+    
+    // Asks for a note and writes it on the selected entries of the open
+    // list, starting from the note of the focused one. The Notes column is
+    // shown for the first note.
+    fn edit_selected_note(&mut self) {
+        if !self.is_list_mode || self.selected_items.is_empty() {
+            return;
+        }
+        let strings = get_strings();
+        let initial = self.selected_index
+            .and_then(|index| self.list_data.get(index))
+            .and_then(|item| self.list_notes.note_of(item.path()))
+            .unwrap_or_default()
+            .to_string();
+        let Some(note) = prompt_text(self.main_window, &strings.ctx_edit_note, &strings.note_prompt, &initial, self.font) else {
+            return;
+        };
+        self.set_selected_note(&note);
+        
+        let notes_hidden = self.columns.iter().any(|column| column.column_type == ColumnType::Notes && !column.visible);
+        if notes_hidden && self.view_mode == ViewMode::Details {
+            self.toggle_column(ColumnType::Notes);
+        }
+    }
+    
+    // Writes `note` on the selected entries, or takes theirs away when empty
+    fn set_selected_note(&mut self, note: &str) {
+        for item in self.get_selected_results() {
+            self.list_notes.set(item.path(), note);
+        }
+        unsafe {
+            InvalidateRect(self.list_view, None, FALSE);
+        }
+    }
+    
+    // Gives the selected results `rating` stars, 0 taking their rating away.
+    // The Rating column is shown for the first stars.
+    fn rate_selected(&mut self, rating: u8) {
+        let paths: Vec<String> = self.get_selected_results().iter().map(|file| file.path().to_string()).collect();
+        let result = self.tags.set_rating(&paths, rating);
+        self.tags_changed(result);
+        
+        let rating_hidden = self.columns.iter().any(|column| column.column_type == ColumnType::Rating && !column.visible);
+        if rating > 0 && rating_hidden && self.view_mode == ViewMode::Details {
+            self.toggle_column(ColumnType::Rating);
+        }
+    }
+    
+    // The result and the number of stars under `x`, `y` when that is on the
+    // stars of the Rating column
+    fn rating_stars_at(&self, x: i32, y: i32) -> Option<(usize, u8)> {
+        let column_index = self.get_column_at_x(x)?;
+        if self.get_visible_columns()[column_index].column_type != ColumnType::Rating {
+            return None;
+        }
+        let offset = x - self.column_lefts()[column_index] - RATING_STAR_LEFT;
+        if offset < 0 || offset >= RATING_STAR_WIDTH * MAX_RATING as i32 {
+            return None;
+        }
+        let item_index = self.get_item_at_point(x, y)?;
+        Some((item_index, (offset / RATING_STAR_WIDTH + 1) as u8))
+    }
+    
+    // Clicking the stars of a result gives it that many, or takes them away
+    // when it already has them. Clicked within a multi-selection, it does so
+    // for the whole selection.
+    fn rate_clicked(&mut self, item_index: usize, stars: u8) {
+        let paths: Vec<String> = if self.is_selected(item_index) && self.selected_items.len() > 1 {
+            self.get_selected_results().iter().map(|file| file.path().to_string()).collect()
+        } else {
+            self.list_data.get(item_index).map(|file| file.path().to_string()).into_iter().collect()
+        };
+        let rating = if paths.iter().all(|path| self.tags.rating_of(path) == stars) { 0 } else { stars };
+        let result = self.tags.set_rating(&paths, rating);
+        self.tags_changed(result);
+    }
+    
+    // Tags menu: a tag every selected result has comes off them all,
+    // otherwise they all get it. None removes every tag.
+    fn toggle_tag(&mut self, index: Option<usize>) {
+        let paths: Vec<String> = self.get_selected_results().iter().map(|file| file.path().to_string()).collect();
+        let result = match index {
+            Some(index) => {
+                let Some(name) = self.tags.tags().get(index).map(|tag| tag.name.clone()) else {
+                    return;
+                };
+                let tagged = paths.iter().all(|path| {
+                    self.tags.tags_of(path).iter().any(|tag| tag.eq_ignore_ascii_case(&name))
+                });
+                if tagged {
+                    self.tags.remove_tag(&paths, Some(&name))
+                } else {
+                    self.tags.add_tag(&paths, &name)
+                }
+            }
+            None => self.tags.remove_tag(&paths, None),
+        };
+        self.tags_changed(result);
+    }
+    
+    fn tags_changed(&mut self, result: std::result::Result<(), String>) {
+        if let Err(e) = result {
+            log_warn(&format!("Failed to save tags: {}", e));
+            let message = fill(&get_strings().error_tags, &[("error", &e)]);
+            unsafe {
+                MessageBoxW(
+                    self.main_window,
+                    PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                    w!("Error"),
+                    MB_ICONERROR | MB_OK,
+                );
+            }
+        }
+        unsafe {
+            InvalidateRect(self.list_view, None, FALSE);
+        }
+    }
+    
+    // Leaves the folders of the selected results out of the results, now
+    // and in later searches
+    fn exclude_selected_folders(&mut self) {
+        let folders: Vec<String> = self.get_selected_results()
+            .iter()
+            .filter_map(|file| std::path::Path::new(file.path()).parent())
+            .map(|folder| folder.to_string_lossy().to_string())
+            .collect();
+        
+        let mut excluded = false;
+        for folder in &folders {
+            if self.config.excluded_folders.len() >= MAX_EXCLUDED_FOLDERS {
+                unsafe {
+                    MessageBoxW(
+                        self.main_window,
+                        PCWSTR::from_raw(to_wide(&get_strings().error_excluded_folders_full).as_ptr()),
+                        w!("Error"),
+                        MB_ICONERROR | MB_OK,
+                    );
+                }
+                break;
+            }
+            excluded |= self.config.add_excluded_folder(folder);
+        }
+        if !excluded {
+            return;
+        }
+        
+        log_info(&format!("Excluded folders: {:?}", self.config.excluded_folders));
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        let config = self.config.clone();
+        self.retain_results(|item| !config.is_excluded(item.path()));
+        let _ = recreate_menus_with_language(self.main_window, self);
+    }
+    
+    // Takes one folder, or all of them, off the exclusions. Searches show
+    // its results again; an open file list only once it is reopened, since
+    // they were dropped from it.
+    fn include_excluded_folders(&mut self, index: Option<usize>) {
+        match index {
+            Some(index) if index < self.config.excluded_folders.len() => {
+                self.config.excluded_folders.remove(index);
+            }
+            Some(_) => return,
+            None => self.config.excluded_folders.clear(),
+        }
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        if !self.is_list_mode {
+            handle_immediate_search(self);
+        }
+        let _ = recreate_menus_with_language(self.main_window, self);
+        update_status_bar(self);
+    }
+    
+    fn remove_missing_entries(&mut self) {
+        if !self.is_list_mode || self.missing_count == 0 {
+            return;
+        }
+        
+        self.original_list_data.retain(|item| !item.missing);
+        self.list_data.retain(|item| !item.missing);
+        self.missing_count = 0;
+        
+        // Reset selection and scroll
+        self.reset_selection();
+        self.scroll_pos = 0;
+        
+        unsafe {
+            self.calculate_layout();
+            update_scrollbar(self);
+            InvalidateRect(self.list_view, None, TRUE);
+            update_status_bar(self);
+        }
+        update_list_menu_state(self.main_window, self);
+    }
+    
+    // Whether the entries of the open list can be put in another order by
+    // hand. Ranking and grouping by drive decide the order themselves.
+    fn can_reorder_list(&self) -> bool {
+        self.is_list_mode && !self.config.rank_by_use && !self.config.group_by_drive
+    }
+    
+    // Moves the selected entries of the open list, in their order, to just
+    // before entry `gap` (the number of entries for the end). The list is no
+    // longer sorted afterwards, and saving it keeps the new order.
+    fn move_selected_entries_to(&mut self, gap: usize) {
+        if !self.can_reorder_list() || self.selected_items.is_empty() {
+            return;
+        }
+        
+        let gap = gap.min(self.list_data.len());
+        let start = gap - self.selected_items.range(..gap).count();
+        if self.selected_items.iter().enumerate().all(|(offset, &index)| index == start + offset) {
+            return;
+        }
+        
+        let mut moved = Vec::with_capacity(self.selected_items.len());
+        let mut rest = Vec::with_capacity(self.list_data.len());
+        for (index, item) in std::mem::take(&mut self.list_data).into_iter().enumerate() {
+            if self.selected_items.contains(&index) {
+                moved.push(item);
+            } else {
+                rest.push(item);
+            }
+        }
+        
+        // The whole list, filtered out entries included, gets the same move:
+        // the entries go in front of the one that now follows them, or after
+        // the one that now comes before them
+        let moved_paths: HashSet<&str> = moved.iter().map(|item| item.path()).collect();
+        let (original_moved, mut original_rest): (Vec<FileResult>, Vec<FileResult>) = std::mem::take(&mut self.original_list_data)
+            .into_iter()
+            .partition(|item| moved_paths.contains(item.path()));
+        let position_of = |path: &str| original_rest.iter().position(|item| item.path() == path);
+        let original_gap = match (rest.get(start), start.checked_sub(1).and_then(|before| rest.get(before))) {
+            (Some(next), _) => position_of(next.path()),
+            (None, Some(previous)) => position_of(previous.path()).map(|position| position + 1),
+            (None, None) => None,
+        }
+        .unwrap_or(original_rest.len());
+        original_rest.splice(original_gap..original_gap, original_moved);
+        self.original_list_data = original_rest;
+        
+        let moved_count = moved.len();
+        rest.splice(start..start, moved);
+        self.list_data = rest;
+        
+        let new_index = |index: usize| start + self.selected_items.range(..index).count();
+        self.selected_index = self.selected_index.filter(|index| self.selected_items.contains(index)).map(new_index).or(Some(start));
+        self.selection_anchor = Some(start);
+        self.selected_items = (start..start + moved_count).collect();
+        
+        // The hand-made order replaces the sort, a running one included
+        self.sort_state = None;
+        self.sort_task = None;
+        unsafe {
+            let _ = KillTimer(self.main_window, SORT_PROGRESS_TIMER_ID);
+        }
+        update_sort_menu_checkmarks(self.main_window, &self.sort_state);
+        
+        self.ensure_selection_visible();
+        unsafe {
+            update_scrollbar(self);
+            InvalidateRect(self.list_view, None, FALSE);
+            update_status_bar(self);
+        }
+        notify_selection_changed(self.list_view, self);
+    }
     
-    fn save_file_list(&self, file_path: &str) -> Result<()> {
-        println!("Saving file list to: {}", file_path);
+    // Ctrl+Up/Down: moves the selected entries of the open list one place
+    // up or down
+    fn nudge_selected_entries(&mut self, direction: i32) {
+        let (Some(&first), Some(&last)) = (self.selected_items.first(), self.selected_items.last()) else {
+            return;
+        };
+        if direction < 0 && first > 0 {
+            self.move_selected_entries_to(first - 1);
+        } else if direction > 0 && last + 1 < self.list_data.len() {
+            self.move_selected_entries_to(last + 2);
+        }
+    }
+    
+    // The place between entries nearest to `x`, `y` in the list view, where
+    // dragged entries would go
+    fn reorder_gap_at(&self, x: i32, y: i32) -> usize {
+        let count = self.list_data.len() as i32;
+        let gap = match self.view_mode {
+            ViewMode::Details => (y - HEADER_HEIGHT + self.scroll_pos + self.item_height / 2) / self.item_height.max(1),
+            _ => {
+                let cell_size = self.cell_size.max(1);
+                let row = ((y + self.scroll_pos) / cell_size).max(0);
+                let col = ((x + cell_size / 2) / cell_size).clamp(0, self.grid_cols.max(1));
+                row * self.grid_cols.max(1) + col
+            }
+        };
+        gap.clamp(0, count) as usize
+    }
+    
+    // The line drawn at `gap` while entries are dragged there
+    fn reorder_marker_rect(&self, gap: usize) -> RECT {
+        match self.view_mode {
+            ViewMode::Details => {
+                let y = HEADER_HEIGHT + gap as i32 * self.item_height - self.scroll_pos;
+                RECT { left: 0, top: y - 1, right: self.client_width, bottom: y + 1 }
+            }
+            _ => {
+                let grid_cols = self.grid_cols.max(1) as usize;
+                // The end of a full last row rather than the start of the next
+                let (row, col) = (gap / grid_cols, gap % grid_cols);
+                let (row, col) = if row > 0 && col == 0 && gap == self.list_data.len() {
+                    (row - 1, grid_cols)
+                } else {
+                    (row, col)
+                };
+                let x = col as i32 * self.cell_size;
+                let y = row as i32 * self.cell_size - self.scroll_pos;
+                RECT { left: x - 1, top: y, right: x + 1, bottom: y + self.cell_size }
+            }
+        }
+    }
+
+    fn start_contact_sheet_export(&mut self, file_path: String) {
+        if self.contact_sheet_running {
+            return;
+        }
         
-        // Create CSV format with file paths and metadata
-        let mut content = String::new();
-        content.push_str("# File List Export\n");
-        content.push_str("# Format: \"Path\",Size,Modified\n");
+        // Export the multi-selection if there is one, otherwise all current results
+        let items = if self.selected_items.len() > 1 {
+            self.get_selected_results()
+        } else {
+            self.list_data.clone()
+        };
         
-        for item in &self.list_data {
-            // Load metadata if not already loaded
-            let mut item_clone = item.clone();
-            if item_clone.size == 0 && item_clone.modified_time == std::time::UNIX_EPOCH {
-                item_clone.load_metadata();
+        let options = ContactSheetOptions {
+            columns: self.config.contact_sheet_columns,
+            cell_size: self.config.contact_sheet_cell_size,
+            background: self.config.thumbnail_background,
+        };
+        let window = self.main_window;
+        
+        self.contact_sheet_running = true;
+        update_contact_sheet_menu_state(self.main_window, self);
+        
+        log_info(&format!("Starting contact sheet export of {} items to {}", items.len(), file_path));
+        
+        std::thread::spawn(move || {
+            use windows::Win32::System::Com::{CoInitializeEx, CoUninitialize, COINIT_APARTMENTTHREADED};
+            
+            // Shell thumbnails and WIC both need COM on this thread
+            unsafe {
+                let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
             }
             
-            // Format: "path",size,modified_timestamp
-            let modified_timestamp = item_clone.modified_time
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs();
+            let (rendered, error) = match render_contact_sheet(&items, &options, &file_path) {
+                Ok(rendered) => (rendered, None),
+                Err(e) => (0, Some(e.to_string())),
+            };
             
-            content.push_str(&format!("\"{}\",{},{}\n", 
-                item.path, 
-                item_clone.size,
-                modified_timestamp
-            ));
+            unsafe {
+                CoUninitialize();
+            }
+            
+            let boxed_result = Box::new(ContactSheetResult {
+                file_path,
+                rendered,
+                total: items.len(),
+                error,
+            });
+            let result_ptr = Box::into_raw(boxed_result) as isize;
+            
+            unsafe {
+                let _ = PostMessageW(window, WM_CONTACT_SHEET_DONE, WPARAM(result_ptr as usize), LPARAM(0));
+            }
+        });
+    }
+    
+    fn handle_contact_sheet_done(&mut self, result_ptr: isize) {
+        let result = unsafe { Box::from_raw(result_ptr as *mut ContactSheetResult) };
+        
+        self.contact_sheet_running = false;
+        update_contact_sheet_menu_state(self.main_window, self);
+        
+        let strings = get_strings();
+        let (message, title, icon) = match &result.error {
+            Some(error) => {
+                log_error(&format!("Contact sheet export failed: {}", error));
+                (format!("{}\n{}", strings.contact_sheet_failed, error), "Error", MB_ICONERROR)
+            }
+            None if result.rendered < result.total => (
+                format!("{}\n{}\n\n{}", strings.contact_sheet_exported, result.file_path, strings.contact_sheet_truncated),
+                "Success",
+                MB_ICONWARNING,
+            ),
+            None => (
+                format!("{}\n{}", strings.contact_sheet_exported, result.file_path),
+                "Success",
+                MB_ICONINFORMATION,
+            ),
+        };
+        
+        unsafe {
+            MessageBoxW(
+                self.main_window,
+                PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                PCWSTR::from_raw(to_wide(title).as_ptr()),
+                icon | MB_OK,
+            );
+        }
+    }
+    
+    fn set_contact_sheet_options(&mut self, columns: u32, cell_size: u32) {
+        self.config.contact_sheet_columns = columns;
+        self.config.contact_sheet_cell_size = cell_size;
+        
+        // Save configuration
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
         }
         
-        // Write to file
-        match std::fs::write(file_path, content) {
-            Ok(_) => {
-                println!("Successfully saved {} files to list", self.list_data.len());
-                Ok(())
+        update_contact_sheet_menu_state(self.main_window, self);
+    }
+
+    fn add_recent_list(&mut self, file_path: &str) {
+        self.config.add_recent_list(file_path);
+        
+        // Save configuration
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        
+        // Rebuild the File menu so the Recent Lists submenu reflects the change
+        let _ = recreate_menus_with_language(self.main_window, self);
+        self.refresh_jump_list();
+    }
+    
+    fn toggle_current_list_pin(&mut self) {
+        let Some(list_path) = self.current_list_path.clone() else {
+            return;
+        };
+        
+        if self.config.toggle_recent_list_pin(&list_path).is_none() {
+            // The list was opened before recent lists existed; record and pin it
+            self.config.add_recent_list(&list_path);
+            self.config.toggle_recent_list_pin(&list_path);
+        }
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        
+        let _ = recreate_menus_with_language(self.main_window, self);
+        self.refresh_jump_list();
+    }
+    
+    fn clear_recent_lists(&mut self) {
+        self.config.clear_recent_lists();
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        
+        let _ = recreate_menus_with_language(self.main_window, self);
+        self.refresh_jump_list();
+    }
+    
+    fn toggle_restore_session(&mut self) {
+        self.config.restore_session = !self.config.restore_session;
+        
+        // Don't bring back an old session if the option is turned on again later
+        if !self.config.restore_session {
+            self.config.last_session = None;
+        }
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        
+        update_session_menu_state(self.main_window, self);
+    }
+    
+    fn export_settings(&mut self, path: &str) {
+        self.config.columns = column_layout(&self.columns);
+        
+        let strings = get_strings();
+        let (message, icon) = match export_config(&self.config, path) {
+            Ok(_) => (format!("{}\n{}", strings.settings_exported, path), MB_ICONINFORMATION),
+            Err(e) => (format!("Failed to export settings: {}", e), MB_ICONERROR),
+        };
+        
+        unsafe {
+            MessageBoxW(
+                self.main_window,
+                PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                PCWSTR::from_raw(to_wide(&strings.file_export_settings).as_ptr()),
+                icon | MB_OK,
+            );
+        }
+    }
+    
+    fn import_settings(&mut self, path: &str) {
+        let imported = match import_config(path) {
+            Ok(imported) => imported,
+            Err(e) => {
+                let strings = get_strings();
+                let message = format!("{}\n{}", strings.error_import_settings, e);
+                unsafe {
+                    MessageBoxW(
+                        self.main_window,
+                        PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                        w!("Error"),
+                        MB_ICONERROR | MB_OK,
+                    );
+                }
+                return;
             }
-            Err(_) => Err(Error::from_win32()),
+        };
+        
+        // The saved session and the startup entry belong to this machine,
+        // everything else is taken over
+        let mut settings = imported.clone();
+        settings.start_with_windows = self.config.start_with_windows;
+        settings.explorer_verb = self.config.explorer_verb;
+        settings.url_protocol = self.config.url_protocol;
+        self.apply_settings(settings);
+        self.config.recent_lists = imported.recent_lists;
+        self.config.recent_searches = imported.recent_searches;
+        self.config.remote_servers = imported.remote_servers;
+        self.config.external_tools = imported.external_tools;
+        self.config.bookmarks = imported.bookmarks;
+        self.config.search_filters = imported.search_filters;
+        self.config.scheduled_exports = imported.scheduled_exports;
+        self.config.profiles = imported.profiles;
+        self.config.active_profile = imported.active_profile;
+        self.config.path_ellipsis = imported.path_ellipsis;
+        self.config.size_format = imported.size_format;
+        self.config.thumbnail_caption = imported.thumbnail_caption;
+        self.config.freeze_name_column = imported.freeze_name_column;
+        self.config.group_by_drive = imported.group_by_drive;
+        self.config.record_open_history = imported.record_open_history;
+        self.config.rank_by_use = imported.rank_by_use;
+        self.set_column_layout(imported.columns);
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        
+        let _ = recreate_menus_with_language(self.main_window, self);
+        self.refresh_jump_list();
+    }
+    
+    fn set_column_layout(&mut self, layout: Vec<ColumnLayout>) {
+        apply_column_layout(&mut self.columns, &layout);
+        self.config.columns = layout;
+        
+        update_column_menu_checkmarks(self.main_window, &self.columns);
+        unsafe {
+            InvalidateRect(self.list_view, None, TRUE);
+        }
+    }
+    
+    fn save_current_profile(&mut self) {
+        let strings = get_strings();
+        let initial = self.config.active_profile.clone().unwrap_or_default();
+        let Some(name) = prompt_text(self.main_window, &strings.profile_save, &strings.profile_name_prompt, &initial, self.font) else {
+            return;
+        };
+        
+        self.config.columns = column_layout(&self.columns);
+        self.config.save_profile(&name);
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        
+        let _ = recreate_menus_with_language(self.main_window, self);
+        log_info(&format!("Saved profile: {}", name));
+    }
+    
+    fn switch_profile(&mut self, index: usize) {
+        let Some(profile) = self.config.profiles.get(index).cloned() else {
+            return;
+        };
+        
+        // Profiles don't add or remove the startup entry or the shell
+        // registrations
+        let mut settings = profile.settings.clone();
+        settings.start_with_windows = self.config.start_with_windows;
+        settings.explorer_verb = self.config.explorer_verb;
+        settings.url_protocol = self.config.url_protocol;
+        self.apply_settings(settings);
+        self.set_column_layout(profile.settings.columns);
+        self.config.active_profile = Some(profile.name.clone());
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        
+        let _ = recreate_menus_with_language(self.main_window, self);
+        log_info(&format!("Switched to profile: {}", profile.name));
+    }
+    
+    fn delete_active_profile(&mut self) {
+        let Some(name) = self.config.active_profile.clone() else {
+            return;
+        };
+        
+        let strings = get_strings();
+        let message = format!("{}\n{}", strings.confirm_delete_profile, name);
+        let answer = unsafe {
+            MessageBoxW(
+                self.main_window,
+                PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                PCWSTR::from_raw(to_wide(&strings.menu_profiles).as_ptr()),
+                MB_ICONQUESTION | MB_YESNO,
+            )
+        };
+        if answer != IDYES {
+            return;
+        }
+        
+        self.config.delete_profile(&name);
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        
+        let _ = recreate_menus_with_language(self.main_window, self);
+    }
+    
+    // Applies the values edited in the Settings dialog; everything else in the
+    // config (recent lists, saved session) is left alone
+    fn apply_settings(&mut self, settings: AppConfig) {
+        if settings.thumbnail_strategy != self.config.thumbnail_strategy {
+            self.set_thumbnail_strategy(settings.thumbnail_strategy);
+        }
+        
+        if settings.thumbnail_background != self.config.thumbnail_background {
+            self.set_thumbnail_background(settings.thumbnail_background);
+        }
+        
+        if settings.thumbnail_caption != self.config.thumbnail_caption {
+            self.set_thumbnail_caption(settings.thumbnail_caption);
+        }
+        
+        if settings.skip_network_thumbnails != self.config.skip_network_thumbnails {
+            self.config.skip_network_thumbnails = settings.skip_network_thumbnails;
+            if let Some(ref task_manager) = self.thumbnail_task_manager {
+                task_manager.cancel_all_tasks();
+            }
+            unsafe {
+                let _ = PostMessageW(self.main_window, WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
+            }
+        }
+        
+        if settings.language != self.config.language {
+            self.set_language(Language::from_code(&settings.language.to_string()));
+        }
+        
+        if settings.restore_session != self.config.restore_session {
+            self.config.restore_session = settings.restore_session;
+            if !settings.restore_session {
+                self.config.last_session = None;
+            }
+        }
+        
+        self.config.start_minimized = settings.start_minimized;
+        self.config.close_to_tray = settings.close_to_tray;
+        self.config.auto_check_updates = settings.auto_check_updates;
+        
+        if settings.quick_search_hotkey != self.config.quick_search_hotkey {
+            if self.set_quick_search_hotkey(settings.quick_search_hotkey) {
+                self.config.quick_search_hotkey = settings.quick_search_hotkey;
+            } else {
+                let strings = get_strings();
+                unsafe {
+                    MessageBoxW(
+                        self.main_window,
+                        PCWSTR::from_raw(to_wide(&strings.error_quick_search_hotkey).as_ptr()),
+                        w!("Error"),
+                        MB_ICONERROR | MB_OK,
+                    );
+                }
+            }
+        }
+        
+        if settings.launcher_hotkey != self.config.launcher_hotkey {
+            if self.set_launcher_hotkey(settings.launcher_hotkey) {
+                self.config.launcher_hotkey = settings.launcher_hotkey;
+            } else {
+                let strings = get_strings();
+                unsafe {
+                    MessageBoxW(
+                        self.main_window,
+                        PCWSTR::from_raw(to_wide(&strings.error_launcher_hotkey).as_ptr()),
+                        w!("Error"),
+                        MB_ICONERROR | MB_OK,
+                    );
+                }
+            }
+        }
+        
+        if settings.clipboard_watcher != self.config.clipboard_watcher {
+            if set_clipboard_listener(self.main_window, settings.clipboard_watcher) {
+                self.config.clipboard_watcher = settings.clipboard_watcher;
+            } else {
+                log_warn("Failed to change the clipboard listener");
+            }
+        }
+        
+        if settings.locale_sort != self.config.locale_sort {
+            self.config.locale_sort = settings.locale_sort;
+            self.apply_sort();
+            unsafe {
+                update_scrollbar(self);
+                InvalidateRect(self.list_view, None, TRUE);
+            }
+        }
+        
+        if settings.start_with_windows != self.config.start_with_windows {
+            match set_autostart(settings.start_with_windows) {
+                Ok(_) => self.config.start_with_windows = settings.start_with_windows,
+                Err(e) => {
+                    let strings = get_strings();
+                    let message = format!("{}\n{}", strings.error_autostart, e);
+                    unsafe {
+                        MessageBoxW(
+                            self.main_window,
+                            PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                            w!("Error"),
+                            MB_ICONERROR | MB_OK,
+                        );
+                    }
+                }
+            }
+        }
+        
+        if settings.explorer_verb != self.config.explorer_verb {
+            let strings = get_strings();
+            match set_explorer_verb(settings.explorer_verb, &strings.explorer_verb_label) {
+                Ok(_) => self.config.explorer_verb = settings.explorer_verb,
+                Err(e) => {
+                    let message = format!("{}\n{}", strings.error_explorer_verb, e);
+                    unsafe {
+                        MessageBoxW(
+                            self.main_window,
+                            PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                            w!("Error"),
+                            MB_ICONERROR | MB_OK,
+                        );
+                    }
+                }
+            }
+        }
+        
+        if settings.url_protocol != self.config.url_protocol {
+            match set_url_protocol(settings.url_protocol) {
+                Ok(_) => self.config.url_protocol = settings.url_protocol,
+                Err(e) => {
+                    let strings = get_strings();
+                    let message = format!("{}\n{}", strings.error_url_protocol, e);
+                    unsafe {
+                        MessageBoxW(
+                            self.main_window,
+                            PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                            w!("Error"),
+                            MB_ICONERROR | MB_OK,
+                        );
+                    }
+                }
+            }
+        }
+        
+        if settings.http_server != self.config.http_server || settings.http_server_port != self.config.http_server_port {
+            match self.set_http_server(settings.http_server, settings.http_server_port) {
+                Ok(_) => {
+                    self.config.http_server = settings.http_server;
+                    self.config.http_server_port = settings.http_server_port;
+                }
+                Err(e) => {
+                    let strings = get_strings();
+                    let message = format!("{}\n{}", strings.error_http_server, e);
+                    unsafe {
+                        MessageBoxW(
+                            self.main_window,
+                            PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                            w!("Error"),
+                            MB_ICONERROR | MB_OK,
+                        );
+                    }
+                }
+            }
+        }
+        
+        self.config.search_delay_ms = settings.search_delay_ms;
+        self.config.max_results = settings.max_results;
+        self.config.memory_budget_mb = settings.memory_budget_mb;
+        self.config.default_search = settings.default_search;
+        self.config.terminal_profile = settings.terminal_profile;
+        self.config.wsl_distro = settings.wsl_distro;
+        self.config.contact_sheet_columns = settings.contact_sheet_columns;
+        self.config.contact_sheet_cell_size = settings.contact_sheet_cell_size;
+        
+        if settings.thumbnail_cache_size != self.config.thumbnail_cache_size {
+            self.config.thumbnail_cache_size = settings.thumbnail_cache_size;
+            self.thumbnail_cache.resize(NonZeroUsize::new(settings.thumbnail_cache_size.max(1) as usize).unwrap());
+        }
+        
+        if settings.thumbnail_threads != self.config.thumbnail_threads {
+            self.config.thumbnail_threads = settings.thumbnail_threads;
+            
+            // The thread pool size is fixed, so start a new task manager
+            if let Some(ref task_manager) = self.thumbnail_task_manager {
+                task_manager.cancel_all_tasks();
+            }
+            self.initialize_thumbnail_task_manager(self.list_view);
+            unsafe {
+                let _ = PostMessageW(self.main_window, WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
+            }
+        }
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+        
+        update_session_menu_state(self.main_window, self);
+        update_contact_sheet_menu_state(self.main_window, self);
+        update_locale_sort_menu_state(self.main_window, self);
+        
+        log_info(&format!("Applied settings: {:?}", self.config));
+    }
+    
+    // Column layout, and the session when enabled, are written once on exit
+    fn save_on_exit(&mut self) {
+        self.config.columns = column_layout(&self.columns);
+        
+        if self.config.restore_session {
+            self.config.last_session = Some(self.current_session());
+        }
+        
+        if let Err(e) = save_config(&self.config) {
+            log_error(&format!("Failed to save config: {}", e));
+        }
+    }
+    
+    // The current query or list, sort, view, scroll position and open tabs
+    fn current_session(&self) -> SessionState {
+        let query = unsafe {
+            let mut buffer: [u16; 1024] = [0; 1024];
+            let len = GetWindowTextW(self.search_edit, &mut buffer);
+            String::from_utf16_lossy(&buffer[..len.max(0) as usize])
+        };
+        
+        // Unsaved lists (e.g. pasted from the clipboard) have no file to reopen
+        let list_path = if self.is_list_mode { self.current_list_path.clone() } else { None };
+        
+        let sort_state = self.sort_state.as_ref().filter(|sort_state| sort_state.order != SortOrder::None);
+        
+        // A single tab is just the search box
+        let (tabs, active_tab) = match self.search_tabs {
+            Some(ref search_tabs) if search_tabs.is_shown() => (search_tabs.queries().to_vec(), search_tabs.active_index()),
+            _ => (Vec::new(), 0),
+        };
+        
+        SessionState {
+            query,
+            list_path,
+            sort_column: sort_state.map(|sort_state| sort_state.column.config_key().to_string()),
+            sort_descending: sort_state.map_or(false, |sort_state| sort_state.order == SortOrder::Descending),
+            zoom_level: self.zoom_level,
+            view_mode: Some(self.view_mode.config_key().to_string()),
+            scroll_pos: self.scroll_pos,
+            tabs,
+            active_tab,
         }
     }
     
-    fn export_simple_list(&self, file_path: &str) -> Result<()> {
-        println!("Exporting simple file list to: {}", file_path);
+    fn restore_session(&mut self) {
+        if !self.config.restore_session {
+            return;
+        }
+        let Some(session) = self.config.last_session.clone() else {
+            return;
+        };
         
-        // Create simple text format - one path per line
-        let mut content = String::new();
-        for item in &self.list_data {
-            content.push_str(&format!("{}\n", item.path));
+        log_info(&format!("Restoring previous session: {:?}", session));
+        // The zoom level picks the icon size within the saved view mode
+        match session.view_mode.as_deref().and_then(ViewMode::from_config_key) {
+            Some(ViewMode::Details) => self.set_zoom_level(0),
+            Some(view_mode) if session.zoom_level == 0 => self.set_view_mode(view_mode),
+            _ => self.set_zoom_level(session.zoom_level),
         }
         
-        // Write to file
-        match std::fs::write(file_path, content) {
-            Ok(_) => {
-                println!("Successfully exported {} files to simple list", self.list_data.len());
-                Ok(())
+        // The active tab's query is session.query, which is searched below
+        if let Some(ref mut search_tabs) = self.search_tabs {
+            search_tabs.restore(session.tabs.clone(), session.active_tab);
+            self.layout_search_tabs();
+        }
+        
+        // A list file that was moved or deleted falls back to a normal search
+        if let Some(list_path) = session.list_path.as_deref().filter(|path| std::path::Path::new(path).exists()) {
+            if self.load_file_list(list_path).is_ok() {
+                unsafe {
+                    // The edit notification filters the list right away
+                    let _ = SetWindowTextW(self.search_edit, PCWSTR::from_raw(to_wide(&session.query).as_ptr()));
+                }
+                self.apply_session_view(&session);
+                return;
             }
-            Err(_) => Err(Error::from_win32()),
         }
-    }
-
-    fn recompute_thumbnail_queue(&self) {
-        log_debug("recompute_thumbnail_queue called");
         
-        if let Some(ref task_manager) = self.thumbnail_task_manager {
-            log_debug(&format!("Thumbnail task manager available, view_mode: {:?}, selected_view_size: {}", 
-                self.view_mode, self.selected_view_size));
+        unsafe {
+            let _ = SetWindowTextW(self.search_edit, PCWSTR::from_raw(to_wide(&session.query).as_ptr()));
             
-            if self.view_mode != ViewMode::Details && self.selected_view_size > 0 {
-                log_debug("Calling task_manager.recompute_thumbnail_queue");
-                
-                task_manager.recompute_thumbnail_queue(
-                    self.config.thumbnail_strategy,
-                    self.config.thumbnail_background,
-                    self.visible_start,
-                    self.visible_count,
-                    self.list_data.len(),
-                    &self.list_data,
-                    self.selected_view_size,
-                );
-                
-                log_debug("task_manager.recompute_thumbnail_queue completed");
-            } else {
-                log_debug(&format!("Skipping thumbnail queue recomputation (view_mode: {:?}, size: {})", 
-                    self.view_mode, self.selected_view_size));
+            // Search now instead of waiting for the debounce timer
+            if self.search_timer_active {
+                let _ = KillTimer(self.main_window, SEARCH_TIMER_ID);
+                self.search_timer_active = false;
             }
-        } else {
-            log_debug("No thumbnail task manager available");
         }
         
-        log_debug("recompute_thumbnail_queue completed");
+        // Sort and scroll are re-applied once the results arrive
+        self.pending_session = Some(session.clone());
+        self.start_async_search(session.query);
     }
-
-    // Async search methods
-    fn start_async_search(&mut self, query: String) {
-        log_debug(&format!("start_async_search called with query: '{}'", query));
-        
-        // Cancel any existing search
-        self.search_cancel_flag.store(true, Ordering::Relaxed);
-        log_debug("Cancelled existing search");
-        
-        // Increment generation counter and get new values
-        let generation = self.search_generation.fetch_add(1, Ordering::Relaxed) + 1;
-        let cancel_flag = Arc::new(AtomicBool::new(false));
-        self.search_cancel_flag = cancel_flag.clone();
+    
+    fn apply_session_view(&mut self, session: &SessionState) {
+        if let Some(column) = session.sort_column.as_deref().and_then(ColumnType::from_config_key).filter(ColumnType::is_sortable) {
+            let order = if session.sort_descending { SortOrder::Descending } else { SortOrder::Ascending };
+            self.sort_state = Some(SortState { column, order });
+            self.apply_sort();
+            update_sort_menu_checkmarks(self.main_window, &self.sort_state);
+        }
         
-        log_debug(&format!("New search generation: {}", generation));
+        // calculate_layout clamps this when there are fewer results than last time
+        self.scroll_pos = session.scroll_pos.max(0);
+        self.calculate_layout();
         
-        // Store the pending search for debouncing
-        self.pending_search_query = query.clone();
-        self.last_search_time = Instant::now();
+        unsafe {
+            update_scrollbar(self);
+            InvalidateRect(self.list_view, None, TRUE);
+        }
+    }
+
+    fn change_sort_order(&mut self, new_order: SortOrder) {
+        self.stop_ranking();
         
-        // Check if we have Everything SDK available
-        if let Some(ref sender) = self.search_sender {
-            log_debug("Sending search request to Everything SDK thread");
-            
-            let request = SearchRequest {
-                query: query.clone(),
-                generation,
-                window: self.main_window,
-                cancel_flag: cancel_flag.clone(),
-            };
+        if let Some(ref mut sort_state) = self.sort_state {
+            // If we have an existing sort state, just change the order
+            sort_state.order = new_order;
             
-            if let Err(e) = sender.send(request) {
-                log_debug(&format!("Failed to send search request: {}", e));
-            } else {
-                log_debug("Search request sent to Everything SDK thread successfully");
-            }
+            // Re-sort with the new order
+            self.apply_sort();
         } else {
-            log_debug("No Everything SDK available, using sample data with rayon");
-            
-            // For sample data, use rayon (thread-safe)
-            let window = self.main_window;
-            let query_clone = query.clone();
-            
-            rayon::spawn(move || {
-                log_debug(&format!("Sample data background thread started for query: '{}'", query_clone));
-                
-                // Small delay to allow for more keystrokes (debouncing)
-                std::thread::sleep(Duration::from_millis(150));
-                
-                // Check if we've been cancelled during the delay
-                if cancel_flag.load(Ordering::Relaxed) {
-                    log_debug("Sample data search cancelled during debounce delay");
-                    return;
-                }
-                
-                log_debug("Starting sample data filtering");
-                
-                // Use sample data filtering
-                let search_result: std::result::Result<Vec<String>, String> = if query_clone.trim().is_empty() {
-                    // Return all sample data
-                    let mut results = Vec::new();
-                    for i in 0..100000 {
-                        results.push(format!("C:\\Users\\Example\\Documents\\File_{:06}.txt", i));
-                    }
-                    Ok(results)
-                } else {
-                    // Filter sample data by query
-                    let query_lower = query_clone.to_lowercase();
-                    let mut results = Vec::new();
-                    for i in 0..100000 {
-                        let filename = format!("File_{:06}.txt", i);
-                        let path = format!("C:\\Users\\Example\\Documents\\File_{:06}.txt", i);
-                        
-                        // Simple string matching
-                        if filename.to_lowercase().contains(&query_lower) || 
-                           path.to_lowercase().contains(&query_lower) {
-                            results.push(path);
-                        }
-                    }
-                    Ok(results)
-                };
-                
-                // Check if we've been cancelled after the search
-                if cancel_flag.load(Ordering::Relaxed) {
-                    log_debug("Sample data search cancelled after filtering");
-                    return;
-                }
-                
-                log_debug("Sample data filtering completed, sending results to UI thread");
-                
-                // Send results back to UI thread
-                match search_result {
-                    Ok(file_paths) => {
-                        log_debug(&format!("Converting {} sample file paths to FileResult objects", file_paths.len()));
-                        
-                        let results: Vec<crate::everything_sdk::FileResult> = file_paths
-                            .into_iter()
-                            .map(|path| crate::everything_sdk::FileResult::from_path(&path))
-                            .collect();
-                        
-                        // Allocate results in a Box and send the pointer
-                        let boxed_results = Box::new((results, generation));
-                        let results_ptr = Box::into_raw(boxed_results) as isize;
-                        
-                        log_debug(&format!("Posting WM_SEARCH_RESULTS message with ptr: {}", results_ptr));
-                        
-                        unsafe {
-                            let _ = PostMessageW(window, WM_SEARCH_RESULTS, WPARAM(results_ptr as usize), LPARAM(0));
-                        }
-                    }
-                    Err(e) => {
-                        log_debug(&format!("Sample data search failed: {}", e));
-                        // Send empty results on error
-                        let boxed_results = Box::new((Vec::<crate::everything_sdk::FileResult>::new(), generation));
-                        let results_ptr = Box::into_raw(boxed_results) as isize;
-                        
-                        unsafe {
-                            let _ = PostMessageW(window, WM_SEARCH_RESULTS, WPARAM(results_ptr as usize), LPARAM(0));
-                        }
-                    }
-                }
-                
-                log_debug("Sample data background thread completed");
+            // If no sort state exists, create one with the default column (Name)
+            self.sort_state = Some(SortState {
+                column: ColumnType::Name,
+                order: new_order,
             });
+            self.apply_sort();
         }
-        
-        log_debug("start_async_search completed");
     }
-    
-    fn handle_search_results(&mut self, results_ptr: isize) {
-        log_debug(&format!("handle_search_results called with ptr: {}", results_ptr));
-        
-        unsafe {
-            log_debug("Converting pointer back to Box");
-            // Convert pointer back to Box
-            let boxed_results = Box::from_raw(results_ptr as *mut (Vec<crate::everything_sdk::FileResult>, u64));
-            let (mut results, generation) = *boxed_results;
-            
-            log_debug(&format!("Unpacked results: {} items, generation: {}", results.len(), generation));
-            
-            // Check if this result is from the current generation
-            let current_generation = self.search_generation.load(Ordering::Relaxed);
-            if generation != current_generation {
-                log_debug(&format!("Ignoring old search results (gen {} vs current {})", generation, current_generation));
-                // This is from an old search, ignore it
-                return;
-            }
-            
-            log_debug(&format!("Received async search results: {} items", results.len()));
-            
-            // Limit results to prevent UI slowdown
-            if results.len() > 50000 {
-                results.truncate(50000);
-                log_debug("Truncated results to 50000 items for performance");
-            }
-            
-            log_debug("About to update list_data");
-            // Update UI with results
-            self.list_data = results;
-            log_debug(&format!("Updated list_data, new size: {}", self.list_data.len()));
-            
-            self.selected_index = if !self.list_data.is_empty() { Some(0) } else { None };
-            log_debug("Updated selected_index");
-            
-            // Only reset scroll position if we're not currently dragging the scrollbar
-            // This prevents the scrollbar from jumping back to the top during scroll operations
-            if !self.is_scrollbar_dragging {
-            self.scroll_pos = 0;
-                log_debug("Reset scroll position (not dragging)");
-            } else {
-                log_debug("Preserving scroll position during scrollbar dragging");
-            }
-            
-            self.calculate_layout();
-            log_debug("Calculated layout");
-            
-            // Cancel all thumbnail tasks and recompute
-            if let Some(ref task_manager) = self.thumbnail_task_manager {
-                log_debug("Cancelling all thumbnail tasks");
-                task_manager.cancel_all_tasks();
-            }
-            
-            // Clear thumbnail cache
-            log_debug("Clearing thumbnail cache");
-            self.thumbnail_cache.clear();
-            
-            // Post message to recompute thumbnails
-            log_debug("Posting WM_RECOMPUTE_THUMBS message");
-            let _ = PostMessageW(self.main_window, WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
-            
-            // Update UI
-            log_debug("About to update UI components");
-            if let Some(state) = &APP_STATE {
-                log_debug("Updating scrollbar");
-                update_scrollbar(state.list_view);
-                log_debug("Invalidating list view");
-                InvalidateRect(state.list_view, None, TRUE);
-                log_debug("Updating status bar");
-                update_status_bar();
-                log_debug("UI update completed");
-            } else {
-                log_debug("WARNING: APP_STATE is None during UI update");
-            }
-            
-            log_debug("handle_search_results completed successfully");
+
+    fn apply_sort(&mut self) {
+        if self.config.rank_by_use {
+            self.start_sort();
+            log_debug(&format!("Ranking results by use for {:?}", self.searched_query));
+        } else if let Some(sort_state) = self.sort_state.clone() {
+            self.start_sort();
+            log_debug(&format!("Applying sort by {:?} in {:?} order", sort_state.column, sort_state.order));
+        } else if self.config.group_by_drive {
+            self.start_sort();
         }
     }
-
-    fn search_local_list(&mut self, query: &str) {
-        if !self.is_list_mode || self.original_list_data.is_empty() {
+    
+    // Sorts an index array on a background thread so that stat'ing tens of
+    // thousands of files for a Size or Date sort doesn't block the UI.
+    // A sort that is still running is cancelled.
+    fn start_sort(&mut self) {
+        // The index is read again in Everything's new order
+        if self.browsing_index() {
+            let query = self.searched_query.clone();
+            self.start_async_search(query);
             return;
         }
-
-        if query.trim().is_empty() {
-            // Show all files when query is empty
-            self.list_data = self.original_list_data.clone();
-        } else {
-            // Filter files based on query
-            let query_lower = query.to_lowercase();
-            self.list_data = self.original_list_data
-                .iter()
-                .filter(|file| {
-                    file.name.to_lowercase().contains(&query_lower) ||
-                    file.path.to_lowercase().contains(&query_lower)
-                })
-                .cloned()
-                .collect();
-        }
-
-        // Reset selection and scroll
-        self.selected_index = if !self.list_data.is_empty() { Some(0) } else { None };
-        self.scroll_pos = 0;
-
+        
+        let Some((key, text, descending)) = self.sort_spec() else {
+            return;
+        };
+        
+        let items: Vec<SortItem> = self.list_data
+            .iter()
+            .map(|item| SortItem {
+                path: item.path().to_string(),
+                text: text(item),
+                metadata: item.metadata_loaded.then(|| (item.size, item.modified_time)),
+            })
+            .collect();
+        
+        // Batches of a running search won't line up with the new order
+        self.stream_sorted = false;
+        
+        self.sort_generation += 1;
+        self.sort_task = Some(SortTask::start(
+            self.main_window,
+            WM_SORT_DONE,
+            self.sort_generation,
+            items,
+            key,
+            descending,
+            self.config.group_by_drive,
+        ));
+        
         unsafe {
-            self.calculate_layout();
-            update_scrollbar(self.list_view);
-            InvalidateRect(self.list_view, None, TRUE);
-            update_status_bar();
+            SetTimer(self.main_window, SORT_PROGRESS_TIMER_ID, 200, None);
+            update_status_bar(self);
         }
     }
-
-    fn close_file_list(&mut self) {
-        self.list_data.clear();
-        self.selected_index = None;
-        self.scroll_pos = 0;
-        self.is_list_mode = false;
-        self.current_list_name = None;
-        self.original_list_data.clear();
-
+    
+    // The key, the text compared for it and whether it is descending, for
+    // the active sort
+    fn sort_spec(&self) -> Option<(SortKey, SortText, bool)> {
+        if self.config.rank_by_use {
+            let ranker = self.run_history.ranker(&self.searched_query);
+            return Some((SortKey::Rank(ranker), |item| item.name().to_string(), false));
+        }
+        
+        let Some(sort_state) = self.sort_state.as_ref().filter(|sort_state| sort_state.order != SortOrder::None) else {
+            // Grouping by drive alone keeps the order within each drive
+            if !self.config.group_by_drive {
+                return None;
+            }
+            return Some((SortKey::Unsorted, |item| item.name().to_string(), false));
+        };
+        
+        let key = match sort_state.column {
+            ColumnType::Size => SortKey::Size,
+            ColumnType::Modified => SortKey::Modified,
+            ColumnType::Duration => SortKey::Duration,
+            ColumnType::Bitrate => SortKey::Bitrate,
+            _ if self.config.locale_sort => SortKey::Locale(get_current_language().locale_name().to_string()),
+            ColumnType::Type => SortKey::Text { ignore_case: false },
+            _ => SortKey::Text { ignore_case: true },
+        };
+        let text: SortText = match sort_state.column {
+            ColumnType::Type => |item| item.file_type(),
+            ColumnType::Path => |item| item.path().to_string(),
+            _ => |item| item.name().to_string(),
+        };
+        
+        Some((key, text, sort_state.order == SortOrder::Descending))
+    }
+    
+    fn handle_sort_done(&mut self, result_ptr: usize) {
+        let result = unsafe { Box::from_raw(result_ptr as *mut SortResult) };
+        
+        if self.sort_task.as_ref().map(|sort_task| sort_task.generation) != Some(result.generation) {
+            log_debug("Ignoring results of a superseded sort");
+            return;
+        }
+        self.sort_task = None;
         unsafe {
-            // Restore default search to show all files
-            SetWindowTextW(self.search_edit, w!("*.png"));
-
-            self.calculate_layout();
-            update_scrollbar(self.list_view);
-            InvalidateRect(self.list_view, None, TRUE);
-            update_status_bar();
-
-            // Trigger a search to reload global data
-            handle_immediate_search();
+            let _ = KillTimer(self.main_window, SORT_PROGRESS_TIMER_ID);
         }
-    }
-
-    fn change_sort_order(&mut self, new_order: SortOrder) {
-        if let Some(ref mut sort_state) = self.sort_state {
-            // If we have an existing sort state, just change the order
-            sort_state.order = new_order;
-            
-            // Re-sort with the new order
-            self.apply_sort();
-        } else {
-            // If no sort state exists, create one with the default column (Name)
-            self.sort_state = Some(SortState {
-                column: ColumnType::Name,
-                order: new_order,
-            });
-            self.apply_sort();
+        
+        // A new search or filter may have replaced the rows while sorting
+        let unchanged = result.paths.len() == self.list_data.len()
+            && result.paths.iter().zip(&self.list_data).all(|(path, item)| *path == item.path());
+        if !unchanged {
+            log_debug("Discarding sort results for a list that has changed");
+            update_status_bar(self);
+            return;
         }
-    }
-
-    fn apply_sort(&mut self) {
-        if let Some(sort_state) = self.sort_state.clone() {
-            let column_type = sort_state.column;
-            let order = sort_state.order;
-            
-            // Perform the sort
-            match column_type {
-                ColumnType::Name => {
-                    if order == SortOrder::Ascending {
-                        self.list_data.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
-                    } else {
-                        self.list_data.sort_by(|a, b| b.name.to_lowercase().cmp(&a.name.to_lowercase()));
-                    }
-                }
-                ColumnType::Size => {
-                    // Load metadata for all items before sorting (only for visible items to keep performance)
-                    for item in &mut self.list_data {
-                        if item.size == 0 && item.modified_time == std::time::UNIX_EPOCH {
-                            item.load_metadata();
-                        }
-                    }
-                    
-                    if order == SortOrder::Ascending {
-                        self.list_data.sort_by(|a, b| a.size.cmp(&b.size));
-                    } else {
-                        self.list_data.sort_by(|a, b| b.size.cmp(&a.size));
-                    }
-                }
-                ColumnType::Type => {
-                    if order == SortOrder::Ascending {
-                        self.list_data.sort_by(|a, b| a.file_type.cmp(&b.file_type));
-                    } else {
-                        self.list_data.sort_by(|a, b| b.file_type.cmp(&a.file_type));
-                    }
-                }
-                ColumnType::Modified => {
-                    // Load metadata for all items before sorting
-                    for item in &mut self.list_data {
-                        if item.size == 0 && item.modified_time == std::time::UNIX_EPOCH {
-                            item.load_metadata();
-                        }
-                    }
-                    
-                    if order == SortOrder::Ascending {
-                        self.list_data.sort_by(|a, b| a.modified_time.cmp(&b.modified_time));
-                    } else {
-                        self.list_data.sort_by(|a, b| b.modified_time.cmp(&a.modified_time));
-                    }
-                }
-                ColumnType::Path => {
-                    if order == SortOrder::Ascending {
-                        self.list_data.sort_by(|a, b| a.path.to_lowercase().cmp(&b.path.to_lowercase()));
-                    } else {
-                        self.list_data.sort_by(|a, b| b.path.to_lowercase().cmp(&a.path.to_lowercase()));
-                    }
-                }
+        
+        let SortResult { paths, order, metadata, media, .. } = *result;
+        // A Duration or Bitrate sort has read every file already. The top
+        // rows go in last so they are the ones that stay cached.
+        if !media.is_empty() {
+            for &index in order.iter().rev() {
+                self.media_infos.put(paths[index].clone(), media[index]);
             }
-            
-            // Reset selection to first item
-            self.selected_index = if !self.list_data.is_empty() { Some(0) } else { None };
-            
-            // Recalculate layout
-            self.calculate_layout();
-            
-            println!("Applied sort by {:?} in {:?} order", column_type, order);
+        }
+        let mut rows: Vec<Option<FileResult>> = std::mem::take(&mut self.list_data)
+            .into_iter()
+            .zip(metadata)
+            .map(|(mut item, metadata)| {
+                if let (false, Some((size, modified_time))) = (item.metadata_loaded, metadata) {
+                    item.set_metadata(size, modified_time);
+                }
+                Some(item)
+            })
+            .collect();
+        self.list_data = order.into_iter().filter_map(|index| rows[index].take()).collect();
+        self.drive_groups = None;
+        
+        log_info(&format!("Background sort completed for {} items", self.list_data.len()));
+        
+        // Reset selection to first item
+        self.reset_selection();
+        self.calculate_layout();
+        
+        unsafe {
+            update_scrollbar(self);
+            InvalidateRect(self.list_view, None, TRUE);
+            update_status_bar(self);
         }
     }
 }
 
 fn main() -> Result<()> {
     unsafe {
-        init_logger();
-        log_debug("Application starting");
+        log_info("Application starting");
+        install_crash_handler();
+        start_watchdog();
+        
+        // Started by an update as the helper that installs it
+        if let Some((target, pid)) = apply_update_argument() {
+            if let Err(e) = apply_update(&target, pid) {
+                log_error(&format!("Failed to install the update: {}", e));
+                let message = format!("{}\n{}", get_strings().error_update_download, e);
+                MessageBoxW(None, PCWSTR::from_raw(to_wide(&message).as_ptr()), w!("Error"), MB_ICONERROR | MB_OK);
+            }
+            return Ok(());
+        }
+        
+        // Started elevated to repeat an action that was refused
+        if let Some(action) = elevated_action_argument() {
+            std::process::exit(run_elevated_action(action) as i32);
+        }
+        
+        // Started by the Explorer verb, the jump list or an es:// link while
+        // already running: let the running window do the work
+        let search_in = search_in_argument();
+        if let Some(ref folder) = search_in {
+            if forward_search_in(folder) {
+                log_info("Passed the folder to the running instance");
+                return Ok(());
+            }
+        }
+        let search = search_argument().or_else(url_search_argument);
+        if let Some(ref query) = search {
+            if forward_to_running_instance(COPYDATA_SEARCH, query) {
+                log_info("Passed the search to the running instance");
+                return Ok(());
+            }
+        }
+        let open_list = open_list_argument();
+        if let Some(ref list_path) = open_list {
+            if forward_to_running_instance(COPYDATA_OPEN_LIST, list_path) {
+                log_info("Passed the list file to the running instance");
+                return Ok(());
+            }
+        }
+        
+        remove_update_files();
         
         let instance = GetModuleHandleW(None)?;
         log_debug("Got module handle");
         
-        APP_STATE = Some(AppState::new());
+        let state = Box::new(AppState::new());
+        configure_logger(state.config.log_level, state.config.log_to_file);
         log_debug("Created app state");
         
         register_main_window_class(instance)?;
         register_list_view_class(instance)?;
+        register_stats_window_class(instance)?;
+        register_settings_window_class(instance)?;
+        register_input_box_class(instance)?;
+        register_about_box_class(instance)?;
+        register_duplicate_window_class(instance)?;
+        register_disk_usage_window_class(instance)?;
+        register_empty_folders_window_class(instance)?;
+        register_stats_dashboard_class(instance)?;
+        register_timeline_window_class(instance)?;
+        register_print_preview_class(instance)?;
+        register_open_history_window_class(instance)?;
+        register_syntax_help_class(instance)?;
+        register_image_viewer_window_class(instance)?;
+        register_quick_view_window_class(instance)?;
+        register_video_preview_window_class(instance)?;
+        register_archive_window_class(instance)?;
+        register_checksum_window_class(instance)?;
+        register_translation_editor_class(instance)?;
+        register_log_window_class(instance)?;
+        register_quick_search_class(instance)?;
+        register_launcher_class(instance)?;
+        register_column_chooser_class(instance)?;
         log_debug("Registered window classes");
         
+        let start_minimized = state.config.start_minimized && search_in.is_none() && search.is_none() && open_list.is_none();
+        let visible_style = if start_minimized { WINDOW_STYLE(0) } else { WS_VISIBLE };
+        
         let window = CreateWindowExW(
             WINDOW_EX_STYLE::default(),
             w!("EverythingLikeMainWindow"),
             w!("Everything-like File Browser"),
-            WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+            WS_OVERLAPPEDWINDOW | visible_style,
             CW_USEDEFAULT,
             CW_USEDEFAULT,
             1000,
@@ -1694,19 +6769,41 @@ fn main() -> Result<()> {
             None,
             None,
             instance,
-            None,
+            Some(Box::into_raw(state) as *const std::ffi::c_void),
         );
 
         if window.0 == 0 {
-            log_debug("Failed to create window");
+            log_error("Failed to create window");
             return Err(Error::from_win32());
         }
 
-        log_debug("Created main window");
+        log_info("Created main window");
 
-        ShowWindow(window, SW_SHOW);
-        UpdateWindow(window);
-        log_debug("Window shown and updated");
+        if start_minimized {
+            if let Some(state) = app_state(window) {
+                hide_to_tray(window, state);
+            }
+            log_info("Started minimized to the notification area");
+        } else {
+            ShowWindow(window, SW_SHOW);
+            UpdateWindow(window);
+            log_debug("Window shown and updated");
+        }
+        
+        if let (Some(folder), Some(state)) = (search_in, app_state(window)) {
+            state.search_in_folder(&folder);
+        }
+        if let (Some(query), Some(state)) = (search, app_state(window)) {
+            state.run_search(&query);
+        }
+        if let (Some(list_path), Some(state)) = (open_list, app_state(window)) {
+            open_file_list(window, state, &list_path);
+        }
+        if debug_console_argument() {
+            if let Some(state) = app_state(window) {
+                show_log_window(state.main_window, state.font);
+            }
+        }
 
         let mut message = MSG::default();
         while GetMessageW(&mut message, None, 0, 0).into() {
@@ -1714,7 +6811,7 @@ fn main() -> Result<()> {
             DispatchMessageW(&message);
         }
 
-        log_debug("Message loop ended");
+        log_info("Message loop ended");
         Ok(())
     }
 }
@@ -1771,29 +6868,237 @@ fn register_list_view_class(instance: HMODULE) -> Result<()> {
     }
 }
 
-fn create_menus(window: HWND) -> Result<()> {
-    recreate_menus_with_language(window)
-}
+fn register_stats_window_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(stats_window_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: CreateSolidBrush(COLORREF(0x00F0F0F0)),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeStatsWindow"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+fn create_menus(window: HWND, state: &AppState) -> Result<()> {
+    recreate_menus_with_language(window, state)
+}
+
+fn recreate_menus_with_language(window: HWND, state: &AppState) -> Result<()> {
+    unsafe {
+        // Destroy existing menu
+        let old_menu = GetMenu(window);
+        if !old_menu.is_invalid() {
+            DestroyMenu(old_menu);
+        }
+        
+        let hmenu = CreateMenu()?;
+        let strings = get_strings();
+        
+        // Create File submenu
+        let file_submenu = CreatePopupMenu()?;
+        
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_STRING,
+            ID_FILE_OPEN_LIST as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_open_list).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_STRING,
+            ID_FILE_PASTE_PATHS as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_paste_paths).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_STRING,
+            ID_FILE_MONITOR_FOLDER as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_monitor_folder).as_ptr()),
+        );
+        
+        // Create Recent Lists submenu
+        let recent_submenu = CreatePopupMenu()?;
+        
+        append_recent_list_items(recent_submenu, &state.config.recent_lists_for_menu());
+        
+        let _ = AppendMenuW(
+            recent_submenu,
+            MF_SEPARATOR,
+            0,
+            PCWSTR::null(),
+        );
+        
+        let _ = AppendMenuW(
+            recent_submenu,
+            MF_STRING,
+            ID_FILE_PIN_CURRENT_LIST as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_pin_current_list).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            recent_submenu,
+            MF_STRING,
+            ID_FILE_CLEAR_RECENT_LISTS as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_clear_recent_lists).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_STRING | MF_POPUP,
+            recent_submenu.0 as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_recent_lists).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_SEPARATOR,
+            0,
+            PCWSTR::null(),
+        );
+        
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_STRING,
+            ID_FILE_SAVE_LIST as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_save_list).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_STRING,
+            ID_FILE_EXPORT_LIST as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_export_list).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_STRING,
+            ID_FILE_EXPORT_SELECTION as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_export_selection).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_STRING,
+            ID_FILE_EXPORT_M3U as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_export_m3u).as_ptr()),
+        );
+        
+        // Create Contact Sheet submenu
+        let contact_sheet_submenu = CreatePopupMenu()?;
+        
+        let _ = AppendMenuW(
+            contact_sheet_submenu,
+            MF_STRING,
+            ID_FILE_EXPORT_CONTACT_SHEET as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_export_contact_sheet).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            contact_sheet_submenu,
+            MF_SEPARATOR,
+            0,
+            PCWSTR::null(),
+        );
+        
+        for (index, columns) in CONTACT_SHEET_COLUMN_CHOICES.iter().enumerate() {
+            let label = strings.contact_sheet_columns.format(*columns as u64);
+            let _ = AppendMenuW(
+                contact_sheet_submenu,
+                MF_STRING,
+                (ID_CONTACT_SHEET_COLUMNS_FIRST + index as i32) as usize,
+                PCWSTR::from_raw(to_wide(&label).as_ptr()),
+            );
+        }
+        
+        let _ = AppendMenuW(
+            contact_sheet_submenu,
+            MF_SEPARATOR,
+            0,
+            PCWSTR::null(),
+        );
+        
+        for (index, cell_size) in CONTACT_SHEET_CELL_SIZE_CHOICES.iter().enumerate() {
+            let label = format!("{} x {} px", cell_size, cell_size);
+            let _ = AppendMenuW(
+                contact_sheet_submenu,
+                MF_STRING,
+                (ID_CONTACT_SHEET_CELL_SIZE_FIRST + index as i32) as usize,
+                PCWSTR::from_raw(to_wide(&label).as_ptr()),
+            );
+        }
+        
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_STRING | MF_POPUP,
+            contact_sheet_submenu.0 as usize,
+            PCWSTR::from_raw(to_wide(&strings.menu_contact_sheet).as_ptr()),
+        );
+
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_SEPARATOR,
+            0,
+            PCWSTR::null(),
+        );
+
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_STRING,
+            ID_FILE_PRINT_PREVIEW as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_print_preview).as_ptr()),
+        );
+
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_STRING,
+            ID_FILE_PRINT as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_print).as_ptr()),
+        );
+
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_SEPARATOR,
+            0,
+            PCWSTR::null(),
+        );
+
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_STRING,
+            ID_FILE_LIST_STATISTICS as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_list_statistics).as_ptr()),
+        );
+
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_STRING,
+            ID_FILE_REMOVE_MISSING as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_remove_missing).as_ptr()),
+        );
 
-fn recreate_menus_with_language(window: HWND) -> Result<()> {
-    unsafe {
-        // Destroy existing menu
-        let old_menu = GetMenu(window);
-        if !old_menu.is_invalid() {
-            DestroyMenu(old_menu);
-        }
-        
-        let hmenu = CreateMenu()?;
-        let strings = get_strings();
-        
-        // Create File submenu
-        let file_submenu = CreatePopupMenu()?;
-        
         let _ = AppendMenuW(
             file_submenu,
             MF_STRING,
-            ID_FILE_OPEN_LIST as usize,
-            PCWSTR::from_raw(to_wide(&strings.file_open_list).as_ptr()),
+            ID_FILE_CLOSE_LIST as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_close_list).as_ptr()),
         );
         
         let _ = AppendMenuW(
@@ -1806,29 +7111,77 @@ fn recreate_menus_with_language(window: HWND) -> Result<()> {
         let _ = AppendMenuW(
             file_submenu,
             MF_STRING,
-            ID_FILE_SAVE_LIST as usize,
-            PCWSTR::from_raw(to_wide(&strings.file_save_list).as_ptr()),
+            ID_FILE_RESTORE_SESSION as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_restore_session).as_ptr()),
         );
         
         let _ = AppendMenuW(
             file_submenu,
             MF_STRING,
-            ID_FILE_EXPORT_LIST as usize,
-            PCWSTR::from_raw(to_wide(&strings.file_export_list).as_ptr()),
+            ID_FILE_SETTINGS as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_settings).as_ptr()),
         );
-
+        
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_STRING,
+            ID_FILE_EXPORT_SETTINGS as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_export_settings).as_ptr()),
+        );
+        
         let _ = AppendMenuW(
             file_submenu,
+            MF_STRING,
+            ID_FILE_IMPORT_SETTINGS as usize,
+            PCWSTR::from_raw(to_wide(&strings.file_import_settings).as_ptr()),
+        );
+        
+        // Create Profiles submenu
+        let profiles_submenu = CreatePopupMenu()?;
+        
+        append_profile_items(profiles_submenu, &state.config);
+        
+        let _ = AppendMenuW(
+            profiles_submenu,
             MF_SEPARATOR,
             0,
             PCWSTR::null(),
         );
-
+        
         let _ = AppendMenuW(
-            file_submenu,
+            profiles_submenu,
             MF_STRING,
-            ID_FILE_CLOSE_LIST as usize,
-            PCWSTR::from_raw(to_wide(&strings.file_close_list).as_ptr()),
+            ID_PROFILE_SAVE as usize,
+            PCWSTR::from_raw(to_wide(&strings.profile_save).as_ptr()),
+        );
+        
+        let delete_flags = if state.config.active_profile.is_some() {
+            MF_STRING
+        } else {
+            MF_STRING | MF_GRAYED
+        };
+        let _ = AppendMenuW(
+            profiles_submenu,
+            delete_flags,
+            ID_PROFILE_DELETE as usize,
+            PCWSTR::from_raw(to_wide(&strings.profile_delete).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_STRING | MF_POPUP,
+            profiles_submenu.0 as usize,
+            PCWSTR::from_raw(to_wide(&strings.menu_profiles).as_ptr()),
+        );
+        
+        // Remote Server submenu
+        let remote_submenu = CreatePopupMenu()?;
+        append_remote_server_items(remote_submenu, state);
+        let _ = AppendMenuW(
+            file_submenu,
+            MF_STRING | MF_POPUP,
+            remote_submenu.0 as usize,
+            PCWSTR::from_raw(to_wide(&strings.menu_remote_server).as_ptr()),
         );
         
         let _ = AppendMenuW(
@@ -1838,6 +7191,155 @@ fn recreate_menus_with_language(window: HWND) -> Result<()> {
             PCWSTR::from_raw(to_wide(&strings.menu_file).as_ptr()),
         );
         
+        // Create Bookmarks and Filters submenus
+        let bookmarks_submenu = CreatePopupMenu()?;
+        append_bookmark_items(bookmarks_submenu, state);
+        let _ = AppendMenuW(
+            hmenu,
+            MF_STRING | MF_POPUP,
+            bookmarks_submenu.0 as usize,
+            PCWSTR::from_raw(to_wide(&strings.menu_bookmarks).as_ptr()),
+        );
+        
+        let filters_submenu = CreatePopupMenu()?;
+        append_filter_items(filters_submenu, state);
+        let _ = AppendMenuW(
+            hmenu,
+            MF_STRING | MF_POPUP,
+            filters_submenu.0 as usize,
+            PCWSTR::from_raw(to_wide(&strings.menu_filters).as_ptr()),
+        );
+        
+        // Create Tools submenu
+        let tools_submenu = CreatePopupMenu()?;
+        append_tool_items(tools_submenu, &state.config.external_tools, state.showing_remote_results());
+        
+        let _ = AppendMenuW(
+            tools_submenu,
+            MF_SEPARATOR,
+            0,
+            PCWSTR::null(),
+        );
+        
+        let _ = AppendMenuW(
+            tools_submenu,
+            MF_STRING,
+            ID_TOOL_ADD as usize,
+            PCWSTR::from_raw(to_wide(&strings.tools_add).as_ptr()),
+        );
+        
+        let remove_submenu = CreatePopupMenu()?;
+        for (index, tool) in state.config.external_tools.iter().take(MAX_EXTERNAL_TOOLS).enumerate() {
+            let _ = AppendMenuW(
+                remove_submenu,
+                MF_STRING,
+                (ID_TOOL_REMOVE_FIRST + index as i32) as usize,
+                PCWSTR::from_raw(to_wide(&tool.name.replace('&', "&&")).as_ptr()),
+            );
+        }
+        let remove_flags = if state.config.external_tools.is_empty() {
+            MF_STRING | MF_POPUP | MF_GRAYED
+        } else {
+            MF_STRING | MF_POPUP
+        };
+        let _ = AppendMenuW(
+            tools_submenu,
+            remove_flags,
+            remove_submenu.0 as usize,
+            PCWSTR::from_raw(to_wide(&strings.tools_remove).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            tools_submenu,
+            MF_SEPARATOR,
+            0,
+            PCWSTR::null(),
+        );
+        
+        let _ = AppendMenuW(
+            tools_submenu,
+            MF_STRING,
+            ID_TOOL_FIND_DUPLICATES as usize,
+            PCWSTR::from_raw(to_wide(&strings.tools_find_duplicates).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            tools_submenu,
+            MF_STRING,
+            ID_TOOL_DISK_USAGE as usize,
+            PCWSTR::from_raw(to_wide(&strings.tools_disk_usage).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            tools_submenu,
+            MF_STRING,
+            ID_TOOL_EMPTY_FOLDERS as usize,
+            PCWSTR::from_raw(to_wide(&strings.tools_empty_folders).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            tools_submenu,
+            MF_STRING,
+            ID_TOOL_STATISTICS as usize,
+            PCWSTR::from_raw(to_wide(&strings.tools_statistics).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            tools_submenu,
+            MF_STRING,
+            ID_TOOL_TIMELINE as usize,
+            PCWSTR::from_raw(to_wide(&strings.tools_timeline).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            tools_submenu,
+            MF_SEPARATOR,
+            0,
+            PCWSTR::null(),
+        );
+        
+        let _ = AppendMenuW(
+            tools_submenu,
+            MF_STRING,
+            ID_TOOL_OPEN_HISTORY as usize,
+            PCWSTR::from_raw(to_wide(&strings.tools_open_history).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            tools_submenu,
+            MF_STRING,
+            ID_TOOL_RECORD_HISTORY as usize,
+            PCWSTR::from_raw(to_wide(&strings.tools_record_history).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            tools_submenu,
+            MF_SEPARATOR,
+            0,
+            PCWSTR::null(),
+        );
+        
+        let _ = AppendMenuW(
+            tools_submenu,
+            MF_STRING,
+            ID_TOOL_REBUILD_DATABASE as usize,
+            PCWSTR::from_raw(to_wide(&strings.tools_rebuild_database).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            tools_submenu,
+            MF_STRING,
+            ID_TOOL_UPDATE_FOLDER_INDEXES as usize,
+            PCWSTR::from_raw(to_wide(&strings.tools_update_folder_indexes).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            hmenu,
+            MF_STRING | MF_POPUP,
+            tools_submenu.0 as usize,
+            PCWSTR::from_raw(to_wide(&strings.menu_tools).as_ptr()),
+        );
+        
         // Create View submenu
         let view_submenu = CreatePopupMenu()?;
         
@@ -1869,6 +7371,48 @@ fn recreate_menus_with_language(window: HWND) -> Result<()> {
             PCWSTR::from_raw(to_wide(&strings.view_extra_large_icons).as_ptr()),
         );
         
+        let _ = AppendMenuW(
+            view_submenu,
+            MF_SEPARATOR,
+            0,
+            PCWSTR::null(),
+        );
+        
+        let _ = AppendMenuW(
+            view_submenu,
+            MF_STRING,
+            ID_VIEW_FOLDER_TREE as usize,
+            PCWSTR::from_raw(to_wide(&strings.view_folder_tree).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            view_submenu,
+            MF_STRING,
+            ID_VIEW_PREVIEW_PANE as usize,
+            PCWSTR::from_raw(to_wide(&strings.view_preview_pane).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            view_submenu,
+            MF_STRING,
+            ID_VIEW_IMAGE_VIEWER as usize,
+            PCWSTR::from_raw(to_wide(&strings.view_image_viewer).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            view_submenu,
+            MF_SEPARATOR,
+            0,
+            PCWSTR::null(),
+        );
+        
+        let _ = AppendMenuW(
+            view_submenu,
+            MF_STRING,
+            ID_VIEW_GROUP_BY_DRIVE as usize,
+            PCWSTR::from_raw(to_wide(&strings.view_group_by_drive).as_ptr()),
+        );
+        
         let _ = AppendMenuW(
             hmenu,
             MF_STRING | MF_POPUP,
@@ -1876,44 +7420,145 @@ fn recreate_menus_with_language(window: HWND) -> Result<()> {
             PCWSTR::from_raw(to_wide(&strings.menu_view).as_ptr()),
         );
         
-        // Create Columns submenu
-        let columns_submenu = CreatePopupMenu()?;
+        // Create Columns submenu
+        let columns_submenu = CreatePopupMenu()?;
+        
+        let _ = AppendMenuW(
+            columns_submenu,
+            MF_STRING,
+            ID_COLUMN_NAME as usize,
+            PCWSTR::from_raw(to_wide(&strings.column_name).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            columns_submenu,
+            MF_STRING,
+            ID_COLUMN_SIZE as usize,
+            PCWSTR::from_raw(to_wide(&strings.column_size).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            columns_submenu,
+            MF_STRING,
+            ID_COLUMN_TYPE as usize,
+            PCWSTR::from_raw(to_wide(&strings.column_type).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            columns_submenu,
+            MF_STRING,
+            ID_COLUMN_MODIFIED as usize,
+            PCWSTR::from_raw(to_wide(&strings.column_date_modified).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            columns_submenu,
+            MF_STRING,
+            ID_COLUMN_PATH as usize,
+            PCWSTR::from_raw(to_wide(&strings.column_path).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            columns_submenu,
+            MF_STRING,
+            ID_COLUMN_GIT_STATUS as usize,
+            PCWSTR::from_raw(to_wide(&strings.column_git).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            columns_submenu,
+            MF_STRING,
+            ID_COLUMN_TAGS as usize,
+            PCWSTR::from_raw(to_wide(&strings.column_tags).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            columns_submenu,
+            MF_STRING,
+            ID_COLUMN_HARD_LINKS as usize,
+            PCWSTR::from_raw(to_wide(&strings.column_hard_links).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            columns_submenu,
+            MF_STRING,
+            ID_COLUMN_STREAMS as usize,
+            PCWSTR::from_raw(to_wide(&strings.column_streams).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            columns_submenu,
+            MF_STRING,
+            ID_COLUMN_DURATION as usize,
+            PCWSTR::from_raw(to_wide(&strings.column_duration).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            columns_submenu,
+            MF_STRING,
+            ID_COLUMN_BITRATE as usize,
+            PCWSTR::from_raw(to_wide(&strings.column_bitrate).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            columns_submenu,
+            MF_STRING,
+            ID_COLUMN_NOTES as usize,
+            PCWSTR::from_raw(to_wide(&strings.column_notes).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            columns_submenu,
+            MF_STRING,
+            ID_COLUMN_RATING as usize,
+            PCWSTR::from_raw(to_wide(&strings.column_rating).as_ptr()),
+        );
+        
+        append_alignment_submenu(columns_submenu, &state.columns, &strings);
         
         let _ = AppendMenuW(
             columns_submenu,
             MF_STRING,
-            ID_COLUMN_NAME as usize,
-            PCWSTR::from_raw(to_wide(&strings.column_name).as_ptr()),
+            ID_FREEZE_NAME_COLUMN as usize,
+            PCWSTR::from_raw(to_wide(&strings.freeze_name_column).as_ptr()),
         );
         
         let _ = AppendMenuW(
             columns_submenu,
-            MF_STRING,
-            ID_COLUMN_SIZE as usize,
-            PCWSTR::from_raw(to_wide(&strings.column_size).as_ptr()),
+            MF_SEPARATOR,
+            0,
+            PCWSTR::null(),
         );
         
         let _ = AppendMenuW(
             columns_submenu,
             MF_STRING,
-            ID_COLUMN_TYPE as usize,
-            PCWSTR::from_raw(to_wide(&strings.column_type).as_ptr()),
+            ID_PATH_ELLIPSIS_END as usize,
+            PCWSTR::from_raw(to_wide(&strings.path_ellipsis_end).as_ptr()),
         );
         
         let _ = AppendMenuW(
             columns_submenu,
             MF_STRING,
-            ID_COLUMN_MODIFIED as usize,
-            PCWSTR::from_raw(to_wide(&strings.column_date_modified).as_ptr()),
+            ID_PATH_ELLIPSIS_MIDDLE as usize,
+            PCWSTR::from_raw(to_wide(&strings.path_ellipsis_middle).as_ptr()),
         );
         
         let _ = AppendMenuW(
             columns_submenu,
-            MF_STRING,
-            ID_COLUMN_PATH as usize,
-            PCWSTR::from_raw(to_wide(&strings.column_path).as_ptr()),
+            MF_SEPARATOR,
+            0,
+            PCWSTR::null(),
         );
         
+        for (id, label) in [
+            (ID_SIZE_FORMAT_AUTO, &strings.size_format_auto),
+            (ID_SIZE_FORMAT_KILOBYTES, &strings.size_format_kilobytes),
+            (ID_SIZE_FORMAT_BYTES, &strings.size_format_bytes),
+        ] {
+            let _ = AppendMenuW(columns_submenu, MF_STRING, id as usize, PCWSTR::from_raw(to_wide(label).as_ptr()));
+        }
+        
         let _ = AppendMenuW(
             hmenu,
             MF_STRING | MF_POPUP,
@@ -1924,18 +7569,34 @@ fn recreate_menus_with_language(window: HWND) -> Result<()> {
         // Create Language submenu
         let lang_submenu = CreatePopupMenu()?;
         
+        for (index, language) in Language::ALL.iter().enumerate() {
+            let _ = AppendMenuW(
+                lang_submenu,
+                MF_STRING,
+                (ID_LANG_FIRST + index as i32) as usize,
+                PCWSTR::from_raw(to_wide(language.display_name()).as_ptr()),
+            );
+        }
+        
+        let _ = AppendMenuW(
+            lang_submenu,
+            MF_SEPARATOR,
+            0,
+            PCWSTR::null(),
+        );
+        
         let _ = AppendMenuW(
             lang_submenu,
             MF_STRING,
-            ID_LANG_ENGLISH as usize,
-            PCWSTR::from_raw(to_wide(&strings.lang_english).as_ptr()),
+            ID_LANG_EDIT_TRANSLATIONS as usize,
+            PCWSTR::from_raw(to_wide(&strings.menu_edit_translations).as_ptr()),
         );
         
         let _ = AppendMenuW(
             lang_submenu,
             MF_STRING,
-            ID_LANG_CHINESE as usize,
-            PCWSTR::from_raw(to_wide(&strings.lang_chinese).as_ptr()),
+            ID_LANG_VALIDATE_TRANSLATIONS as usize,
+            PCWSTR::from_raw(to_wide(&strings.menu_validate_translations).as_ptr()),
         );
         
         let _ = AppendMenuW(
@@ -2006,6 +7667,27 @@ fn recreate_menus_with_language(window: HWND) -> Result<()> {
             PCWSTR::from_raw(to_wide(&strings.sort_descending).as_ptr()),
         );
         
+        let _ = AppendMenuW(
+            sort_submenu,
+            MF_SEPARATOR,
+            0,
+            PCWSTR::null(),
+        );
+        
+        let _ = AppendMenuW(
+            sort_submenu,
+            MF_STRING,
+            ID_SORT_LOCALE as usize,
+            PCWSTR::from_raw(to_wide(&strings.sort_locale).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            sort_submenu,
+            MF_STRING,
+            ID_SORT_RANK_BY_USE as usize,
+            PCWSTR::from_raw(to_wide(&strings.sort_rank_by_use).as_ptr()),
+        );
+        
         let _ = AppendMenuW(
             hmenu,
             MF_STRING | MF_POPUP,
@@ -2037,6 +7719,22 @@ fn recreate_menus_with_language(window: HWND) -> Result<()> {
             PCWSTR::from_raw(to_wide(&strings.thumb_visible_plus_500).as_ptr()),
         );
         
+        let _ = AppendMenuW(
+            thumb_submenu,
+            MF_SEPARATOR,
+            0,
+            PCWSTR::null(),
+        );
+        
+        for (id, label) in [
+            (ID_CAPTION_NAME, &strings.caption_name),
+            (ID_CAPTION_NAME_SIZE, &strings.caption_name_size),
+            (ID_CAPTION_NAME_DIMENSIONS, &strings.caption_name_dimensions),
+            (ID_CAPTION_NONE, &strings.caption_none),
+        ] {
+            let _ = AppendMenuW(thumb_submenu, MF_STRING, id as usize, PCWSTR::from_raw(to_wide(label).as_ptr()));
+        }
+        
         let _ = AppendMenuW(
             hmenu,
             MF_STRING | MF_POPUP,
@@ -2062,67 +7760,560 @@ fn recreate_menus_with_language(window: HWND) -> Result<()> {
         );
         
         let _ = AppendMenuW(
-            bg_submenu,
-            MF_SEPARATOR,
-            0,
-            PCWSTR::null(),
+            bg_submenu,
+            MF_SEPARATOR,
+            0,
+            PCWSTR::null(),
+        );
+        
+        let _ = AppendMenuW(
+            bg_submenu,
+            MF_STRING,
+            ID_BG_BLACK as usize,
+            PCWSTR::from_raw(to_wide(&strings.bg_black).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            bg_submenu,
+            MF_STRING,
+            ID_BG_WHITE as usize,
+            PCWSTR::from_raw(to_wide(&strings.bg_white).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            bg_submenu,
+            MF_STRING,
+            ID_BG_GRAY as usize,
+            PCWSTR::from_raw(to_wide(&strings.bg_gray).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            bg_submenu,
+            MF_STRING,
+            ID_BG_LIGHT_GRAY as usize,
+            PCWSTR::from_raw(to_wide(&strings.bg_light_gray).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            bg_submenu,
+            MF_STRING,
+            ID_BG_DARK_GRAY as usize,
+            PCWSTR::from_raw(to_wide(&strings.bg_dark_gray).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            hmenu,
+            MF_STRING | MF_POPUP,
+            bg_submenu.0 as usize,
+            PCWSTR::from_raw(to_wide(&strings.menu_thumbnail_background).as_ptr()),
+        );
+        
+        // Create Help submenu
+        let help_submenu = CreatePopupMenu()?;
+        
+        let _ = AppendMenuW(
+            help_submenu,
+            MF_STRING,
+            ID_HELP_ABOUT as usize,
+            PCWSTR::from_raw(to_wide(&strings.help_about).as_ptr()),
+        );
+        
+        let _ = AppendMenuW(
+            hmenu,
+            MF_STRING | MF_POPUP,
+            help_submenu.0 as usize,
+            PCWSTR::from_raw(to_wide(&strings.menu_help).as_ptr()),
+        );
+        
+        assign_menu_mnemonics(hmenu);
+        let _ = SetMenu(window, hmenu);
+        
+        // Set initial checkmarks based on loaded config and current view mode
+        update_thumbnail_menu_checkmarks(window, state.config.thumbnail_strategy);
+        update_background_menu_checkmarks(window, state.config.thumbnail_background);
+        update_view_menu_checkmarks(window, &state.view_mode);
+        update_column_menu_checkmarks(window, &state.columns);
+        update_language_menu_checkmarks(window, get_current_language());
+        update_sort_menu_checkmarks(window, &state.sort_state);
+        update_recent_list_menu_state(window, state);
+        update_list_menu_state(window, state);
+        update_contact_sheet_menu_state(window, state);
+        update_session_menu_state(window, state);
+        update_locale_sort_menu_state(window, state);
+        update_rank_menu_state(window, state);
+        update_folder_tree_menu_state(window, state);
+        update_preview_pane_menu_state(window, state);
+        if let Some(preview_pane) = state.preview_pane.as_ref() {
+            preview_pane.refresh_message();
+        }
+        update_image_viewer_menu_state(window, state);
+        update_path_ellipsis_menu_state(window, state);
+        update_caption_menu_state(window, state);
+        update_size_format_menu_state(window, state);
+        update_freeze_column_menu_state(window, state);
+        update_record_history_menu_state(window, state);
+        update_group_by_drive_menu_state(window, state);
+        
+        Ok(())
+    }
+}
+
+fn update_list_menu_state(window: HWND, state: &AppState) {
+    unsafe {
+        let hmenu = GetMenu(window);
+        if !hmenu.is_invalid() {
+            let remove_enabled = if state.is_list_mode && state.missing_count > 0 { MF_ENABLED } else { MF_GRAYED };
+            EnableMenuItem(hmenu, ID_FILE_REMOVE_MISSING as u32, remove_enabled);
+            
+            let statistics_enabled = if state.is_list_mode { MF_ENABLED } else { MF_GRAYED };
+            EnableMenuItem(hmenu, ID_FILE_LIST_STATISTICS as u32, statistics_enabled);
+        }
+    }
+}
+
+fn update_locale_sort_menu_state(window: HWND, state: &AppState) {
+    unsafe {
+        let hmenu = GetMenu(window);
+        if !hmenu.is_invalid() {
+            let check_state = if state.config.locale_sort { MF_CHECKED.0 } else { MF_UNCHECKED.0 };
+            CheckMenuItem(hmenu, ID_SORT_LOCALE as u32, check_state);
+        }
+    }
+}
+
+fn update_rank_menu_state(window: HWND, state: &AppState) {
+    unsafe {
+        let hmenu = GetMenu(window);
+        if !hmenu.is_invalid() {
+            let check_state = if state.config.rank_by_use { MF_CHECKED.0 } else { MF_UNCHECKED.0 };
+            CheckMenuItem(hmenu, ID_SORT_RANK_BY_USE as u32, check_state);
+        }
+    }
+}
+
+fn update_folder_tree_menu_state(window: HWND, state: &AppState) {
+    unsafe {
+        let hmenu = GetMenu(window);
+        if !hmenu.is_invalid() {
+            let check_state = if state.folder_tree.is_some() { MF_CHECKED.0 } else { MF_UNCHECKED.0 };
+            CheckMenuItem(hmenu, ID_VIEW_FOLDER_TREE as u32, check_state);
+        }
+    }
+}
+
+fn update_preview_pane_menu_state(window: HWND, state: &AppState) {
+    unsafe {
+        let hmenu = GetMenu(window);
+        if !hmenu.is_invalid() {
+            let check_state = if state.preview_pane.is_some() { MF_CHECKED.0 } else { MF_UNCHECKED.0 };
+            CheckMenuItem(hmenu, ID_VIEW_PREVIEW_PANE as u32, check_state);
+        }
+    }
+}
+
+fn update_record_history_menu_state(window: HWND, state: &AppState) {
+    unsafe {
+        let hmenu = GetMenu(window);
+        if !hmenu.is_invalid() {
+            let check_state = if state.config.record_open_history { MF_CHECKED.0 } else { MF_UNCHECKED.0 };
+            CheckMenuItem(hmenu, ID_TOOL_RECORD_HISTORY as u32, check_state);
+        }
+    }
+}
+
+fn update_group_by_drive_menu_state(window: HWND, state: &AppState) {
+    unsafe {
+        let hmenu = GetMenu(window);
+        if !hmenu.is_invalid() {
+            let check_state = if state.config.group_by_drive { MF_CHECKED.0 } else { MF_UNCHECKED.0 };
+            CheckMenuItem(hmenu, ID_VIEW_GROUP_BY_DRIVE as u32, check_state);
+        }
+    }
+}
+
+fn update_freeze_column_menu_state(window: HWND, state: &AppState) {
+    unsafe {
+        let hmenu = GetMenu(window);
+        if !hmenu.is_invalid() {
+            let check_state = if state.config.freeze_name_column { MF_CHECKED.0 } else { MF_UNCHECKED.0 };
+            CheckMenuItem(hmenu, ID_FREEZE_NAME_COLUMN as u32, check_state);
+        }
+    }
+}
+
+fn update_size_format_menu_state(window: HWND, state: &AppState) {
+    unsafe {
+        let hmenu = GetMenu(window);
+        if !hmenu.is_invalid() {
+            let checked = match state.config.size_format {
+                SizeFormat::Auto => ID_SIZE_FORMAT_AUTO,
+                SizeFormat::Kilobytes => ID_SIZE_FORMAT_KILOBYTES,
+                SizeFormat::Bytes => ID_SIZE_FORMAT_BYTES,
+            };
+            let _ = CheckMenuRadioItem(hmenu, ID_SIZE_FORMAT_AUTO as u32, ID_SIZE_FORMAT_BYTES as u32, checked as u32, MF_BYCOMMAND.0);
+        }
+    }
+}
+
+fn update_caption_menu_state(window: HWND, state: &AppState) {
+    unsafe {
+        let hmenu = GetMenu(window);
+        if !hmenu.is_invalid() {
+            let checked = match state.config.thumbnail_caption {
+                ThumbnailCaption::Name => ID_CAPTION_NAME,
+                ThumbnailCaption::NameAndSize => ID_CAPTION_NAME_SIZE,
+                ThumbnailCaption::NameAndDimensions => ID_CAPTION_NAME_DIMENSIONS,
+                ThumbnailCaption::Hidden => ID_CAPTION_NONE,
+            };
+            let _ = CheckMenuRadioItem(hmenu, ID_CAPTION_NAME as u32, ID_CAPTION_NONE as u32, checked as u32, MF_BYCOMMAND.0);
+        }
+    }
+}
+
+fn update_path_ellipsis_menu_state(window: HWND, state: &AppState) {
+    unsafe {
+        let hmenu = GetMenu(window);
+        if !hmenu.is_invalid() {
+            let checked = if state.config.path_ellipsis == PathEllipsis::Middle { ID_PATH_ELLIPSIS_MIDDLE } else { ID_PATH_ELLIPSIS_END };
+            let _ = CheckMenuRadioItem(hmenu, ID_PATH_ELLIPSIS_END as u32, ID_PATH_ELLIPSIS_MIDDLE as u32, checked as u32, MF_BYCOMMAND.0);
+        }
+    }
+}
+
+fn update_image_viewer_menu_state(window: HWND, state: &AppState) {
+    unsafe {
+        let hmenu = GetMenu(window);
+        if !hmenu.is_invalid() {
+            let check_state = if state.config.use_image_viewer { MF_CHECKED.0 } else { MF_UNCHECKED.0 };
+            CheckMenuItem(hmenu, ID_VIEW_IMAGE_VIEWER as u32, check_state);
+        }
+    }
+}
+
+fn update_session_menu_state(window: HWND, state: &AppState) {
+    unsafe {
+        let hmenu = GetMenu(window);
+        if !hmenu.is_invalid() {
+            let check_state = if state.config.restore_session { MF_CHECKED.0 } else { MF_UNCHECKED.0 };
+            CheckMenuItem(hmenu, ID_FILE_RESTORE_SESSION as u32, check_state);
+        }
+    }
+}
+
+fn update_contact_sheet_menu_state(window: HWND, state: &AppState) {
+    unsafe {
+        let hmenu = GetMenu(window);
+        if !hmenu.is_invalid() {
+            // Contact sheets are exported from the thumbnail views, one at a time
+            let can_export = state.view_mode != ViewMode::Details && !state.contact_sheet_running;
+            let export_enabled = if can_export { MF_ENABLED } else { MF_GRAYED };
+            EnableMenuItem(hmenu, ID_FILE_EXPORT_CONTACT_SHEET as u32, export_enabled);
+            
+            for (index, &columns) in CONTACT_SHEET_COLUMN_CHOICES.iter().enumerate() {
+                let check_state = if columns == state.config.contact_sheet_columns { MF_CHECKED.0 } else { MF_UNCHECKED.0 };
+                CheckMenuItem(hmenu, (ID_CONTACT_SHEET_COLUMNS_FIRST + index as i32) as u32, check_state);
+            }
+            
+            for (index, &cell_size) in CONTACT_SHEET_CELL_SIZE_CHOICES.iter().enumerate() {
+                let check_state = if cell_size == state.config.contact_sheet_cell_size { MF_CHECKED.0 } else { MF_UNCHECKED.0 };
+                CheckMenuItem(hmenu, (ID_CONTACT_SHEET_CELL_SIZE_FIRST + index as i32) as u32, check_state);
+            }
+        }
+    }
+}
+
+fn append_recent_list_items(hmenu: HMENU, entries: &[&RecentList]) {
+    unsafe {
+        if entries.is_empty() {
+            let strings = get_strings();
+            let _ = AppendMenuW(
+                hmenu,
+                MF_STRING | MF_GRAYED,
+                0,
+                PCWSTR::from_raw(to_wide(&strings.file_recent_empty).as_ptr()),
+            );
+            return;
+        }
+        
+        let max_entries = (ID_FILE_RECENT_LIST_LAST - ID_FILE_RECENT_LIST_FIRST + 1) as usize;
+        let mut previous_pinned = entries[0].pinned;
+        
+        for (index, entry) in entries.iter().take(max_entries).enumerate() {
+            // Separate pinned entries from the rest
+            if entry.pinned != previous_pinned {
+                let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+                previous_pinned = entry.pinned;
+            }
+            
+            // Number the first nine entries so they get keyboard accelerators
+            let path = entry.path.replace('&', "&&");
+            let label = if index < 9 {
+                format!("&{} {}", index + 1, path)
+            } else {
+                path
+            };
+            
+            let flags = if entry.pinned { MF_STRING | MF_CHECKED } else { MF_STRING };
+            let _ = AppendMenuW(
+                hmenu,
+                flags,
+                (ID_FILE_RECENT_LIST_FIRST + index as i32) as usize,
+                PCWSTR::from_raw(to_wide(&label).as_ptr()),
+            );
+        }
+    }
+}
+
+// Lists the saved profiles, with a check mark on the active one
+fn append_profile_items(hmenu: HMENU, config: &AppConfig) {
+    unsafe {
+        if config.profiles.is_empty() {
+            let strings = get_strings();
+            let _ = AppendMenuW(
+                hmenu,
+                MF_STRING | MF_GRAYED,
+                0,
+                PCWSTR::from_raw(to_wide(&strings.profile_none).as_ptr()),
+            );
+            return;
+        }
+        
+        for (index, profile) in config.profiles.iter().take(MAX_PROFILES).enumerate() {
+            let is_active = config.active_profile.as_deref().map_or(false, |active| active.eq_ignore_ascii_case(&profile.name));
+            let flags = if is_active { MF_STRING | MF_CHECKED } else { MF_STRING };
+            let _ = AppendMenuW(
+                hmenu,
+                flags,
+                (ID_PROFILE_FIRST + index as i32) as usize,
+                PCWSTR::from_raw(to_wide(&profile.name).as_ptr()),
+            );
+        }
+    }
+}
+
+fn append_remote_server_items(hmenu: HMENU, state: &AppState) {
+    unsafe {
+        let strings = get_strings();
+        let local_flags = if state.remote_server.is_none() { MF_STRING | MF_CHECKED } else { MF_STRING };
+        let _ = AppendMenuW(
+            hmenu,
+            local_flags,
+            ID_REMOTE_LOCAL as usize,
+            PCWSTR::from_raw(to_wide(&strings.remote_this_computer).as_ptr()),
         );
         
+        for (index, server) in state.config.remote_servers.iter().take(MAX_REMOTE_SERVERS).enumerate() {
+            let flags = if state.remote_server.as_ref() == Some(server) { MF_STRING | MF_CHECKED } else { MF_STRING };
+            let _ = AppendMenuW(
+                hmenu,
+                flags,
+                (ID_REMOTE_FIRST + index as i32) as usize,
+                PCWSTR::from_raw(to_wide(&server.display_name().replace('&', "&&")).as_ptr()),
+            );
+        }
+        
+        let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
         let _ = AppendMenuW(
-            bg_submenu,
+            hmenu,
             MF_STRING,
-            ID_BG_BLACK as usize,
-            PCWSTR::from_raw(to_wide(&strings.bg_black).as_ptr()),
+            ID_REMOTE_ADD as usize,
+            PCWSTR::from_raw(to_wide(&strings.remote_add_server).as_ptr()),
         );
         
+        let remove_flags = if state.remote_server.is_some() { MF_STRING } else { MF_STRING | MF_GRAYED };
         let _ = AppendMenuW(
-            bg_submenu,
-            MF_STRING,
-            ID_BG_WHITE as usize,
-            PCWSTR::from_raw(to_wide(&strings.bg_white).as_ptr()),
+            hmenu,
+            remove_flags,
+            ID_REMOTE_REMOVE as usize,
+            PCWSTR::from_raw(to_wide(&strings.remote_remove_server).as_ptr()),
         );
-        
+    }
+}
+
+fn append_bookmark_items(hmenu: HMENU, state: &AppState) {
+    unsafe {
+        let strings = get_strings();
         let _ = AppendMenuW(
-            bg_submenu,
+            hmenu,
             MF_STRING,
-            ID_BG_GRAY as usize,
-            PCWSTR::from_raw(to_wide(&strings.bg_gray).as_ptr()),
+            ID_IMPORT_EVERYTHING as usize,
+            PCWSTR::from_raw(to_wide(&strings.import_everything).as_ptr()),
         );
         
+        if !state.config.bookmarks.is_empty() {
+            let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+        }
+        for (index, bookmark) in state.config.bookmarks.iter().take(MAX_BOOKMARKS).enumerate() {
+            let _ = AppendMenuW(
+                hmenu,
+                MF_STRING,
+                (ID_BOOKMARK_FIRST + index as i32) as usize,
+                PCWSTR::from_raw(to_wide(&bookmark.name.replace('&', "&&")).as_ptr()),
+            );
+        }
+        
+        let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+        let exports_submenu = CreatePopupMenu().unwrap();
+        append_scheduled_export_items(exports_submenu, state);
         let _ = AppendMenuW(
-            bg_submenu,
-            MF_STRING,
-            ID_BG_LIGHT_GRAY as usize,
-            PCWSTR::from_raw(to_wide(&strings.bg_light_gray).as_ptr()),
+            hmenu,
+            MF_POPUP,
+            exports_submenu.0 as usize,
+            PCWSTR::from_raw(to_wide(&strings.scheduled_exports).as_ptr()),
         );
-        
+    }
+}
+
+// Schedule Current Search, then a submenu per export with its last run,
+// Run Now, the interval choices and Remove
+fn append_scheduled_export_items(hmenu: HMENU, state: &AppState) {
+    unsafe {
+        let strings = get_strings();
+        let add_flags = if state.config.scheduled_exports.len() < MAX_SCHEDULED_EXPORTS { MF_STRING } else { MF_STRING | MF_GRAYED };
         let _ = AppendMenuW(
-            bg_submenu,
-            MF_STRING,
-            ID_BG_DARK_GRAY as usize,
-            PCWSTR::from_raw(to_wide(&strings.bg_dark_gray).as_ptr()),
+            hmenu,
+            add_flags,
+            ID_SCHEDULED_EXPORT_ADD as usize,
+            PCWSTR::from_raw(to_wide(&strings.scheduled_export_add).as_ptr()),
         );
         
+        if !state.config.scheduled_exports.is_empty() {
+            let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+        }
+        for (index, export) in state.config.scheduled_exports.iter().take(MAX_SCHEDULED_EXPORTS).enumerate() {
+            let first_id = ID_SCHEDULED_EXPORT_FIRST + index as i32 * SCHEDULED_EXPORT_ID_STRIDE;
+            let running = state.running_exports.contains(&export.path);
+            let export_submenu = CreatePopupMenu().unwrap();
+            
+            let status = last_run_status(export, running, &strings);
+            let _ = AppendMenuW(export_submenu, MF_STRING | MF_GRAYED, 0, PCWSTR::from_raw(to_wide(&status.replace('&', "&&")).as_ptr()));
+            let _ = AppendMenuW(export_submenu, MF_STRING | MF_GRAYED, 0, PCWSTR::from_raw(to_wide(&export.path.replace('&', "&&")).as_ptr()));
+            let _ = AppendMenuW(export_submenu, MF_SEPARATOR, 0, PCWSTR::null());
+            let run_flags = if running { MF_STRING | MF_GRAYED } else { MF_STRING };
+            let _ = AppendMenuW(
+                export_submenu,
+                run_flags,
+                (first_id + SCHEDULED_EXPORT_RUN) as usize,
+                PCWSTR::from_raw(to_wide(&strings.scheduled_export_run_now).as_ptr()),
+            );
+            let _ = AppendMenuW(export_submenu, MF_SEPARATOR, 0, PCWSTR::null());
+            for (choice, &hours) in SCHEDULED_EXPORT_INTERVALS.iter().enumerate() {
+                let flags = if export.interval_hours == hours { MF_STRING | MF_CHECKED } else { MF_STRING };
+                let _ = AppendMenuW(
+                    export_submenu,
+                    flags,
+                    (first_id + SCHEDULED_EXPORT_INTERVAL_FIRST + choice as i32) as usize,
+                    PCWSTR::from_raw(to_wide(&interval_label(hours, &strings)).as_ptr()),
+                );
+            }
+            let _ = AppendMenuW(export_submenu, MF_SEPARATOR, 0, PCWSTR::null());
+            let _ = AppendMenuW(
+                export_submenu,
+                MF_STRING,
+                (first_id + SCHEDULED_EXPORT_REMOVE) as usize,
+                PCWSTR::from_raw(to_wide(&strings.scheduled_export_remove).as_ptr()),
+            );
+            
+            let _ = AppendMenuW(
+                hmenu,
+                MF_POPUP,
+                export_submenu.0 as usize,
+                PCWSTR::from_raw(to_wide(&export.name.replace('&', "&&")).as_ptr()),
+            );
+        }
+    }
+}
+
+// "No Filter", then the imported filters with the active one checked
+fn append_filter_items(hmenu: HMENU, state: &AppState) {
+    unsafe {
+        let strings = get_strings();
+        let none_flags = if state.active_filter.is_none() { MF_STRING | MF_CHECKED } else { MF_STRING };
         let _ = AppendMenuW(
             hmenu,
-            MF_STRING | MF_POPUP,
-            bg_submenu.0 as usize,
-            PCWSTR::from_raw(to_wide(&strings.menu_thumbnail_background).as_ptr()),
+            none_flags,
+            ID_FILTER_NONE as usize,
+            PCWSTR::from_raw(to_wide(&strings.filter_none).as_ptr()),
         );
         
-        let _ = SetMenu(window, hmenu);
+        for (index, filter) in state.config.search_filters.iter().take(MAX_SEARCH_FILTERS).enumerate() {
+            let flags = if state.active_filter.as_ref() == Some(filter) { MF_STRING | MF_CHECKED } else { MF_STRING };
+            let _ = AppendMenuW(
+                hmenu,
+                flags,
+                (ID_FILTER_FIRST + index as i32) as usize,
+                PCWSTR::from_raw(to_wide(&filter.name.replace('&', "&&")).as_ptr()),
+            );
+        }
         
-        // Set initial checkmarks based on loaded config and current view mode
-        if let Some(state) = &APP_STATE {
-            update_thumbnail_menu_checkmarks(window, state.config.thumbnail_strategy);
-            update_background_menu_checkmarks(window, state.config.thumbnail_background);
-            update_view_menu_checkmarks(window, &state.view_mode);
-            update_column_menu_checkmarks(window, &state.columns);
-            update_language_menu_checkmarks(window, get_current_language());
-            update_sort_menu_checkmarks(window, &state.sort_state);
+        // Checked while excluded; choosing one includes it again
+        if !state.config.excluded_folders.is_empty() {
+            let excluded_submenu = CreatePopupMenu().unwrap();
+            for (index, folder) in state.config.excluded_folders.iter().take(MAX_EXCLUDED_FOLDERS).enumerate() {
+                let _ = AppendMenuW(
+                    excluded_submenu,
+                    MF_STRING | MF_CHECKED,
+                    (ID_EXCLUDED_FOLDER_FIRST + index as i32) as usize,
+                    PCWSTR::from_raw(to_wide(&folder.replace('&', "&&")).as_ptr()),
+                );
+            }
+            let _ = AppendMenuW(excluded_submenu, MF_SEPARATOR, 0, PCWSTR::null());
+            let _ = AppendMenuW(
+                excluded_submenu,
+                MF_STRING,
+                ID_EXCLUDED_FOLDERS_CLEAR as usize,
+                PCWSTR::from_raw(to_wide(&strings.excluded_folders_clear).as_ptr()),
+            );
+            let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+            let _ = AppendMenuW(
+                hmenu,
+                MF_STRING | MF_POPUP,
+                excluded_submenu.0 as usize,
+                PCWSTR::from_raw(to_wide(&strings.menu_excluded_folders).as_ptr()),
+            );
         }
         
-        Ok(())
+        let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+        let _ = AppendMenuW(
+            hmenu,
+            MF_STRING,
+            ID_IMPORT_EVERYTHING as usize,
+            PCWSTR::from_raw(to_wide(&strings.import_everything).as_ptr()),
+        );
+    }
+}
+
+// One entry per tool; they act on local files only
+fn append_tool_items(hmenu: HMENU, tools: &[ExternalTool], remote: bool) {
+    unsafe {
+        let flags = if remote { MF_STRING | MF_GRAYED } else { MF_STRING };
+        for (index, tool) in tools.iter().take(MAX_EXTERNAL_TOOLS).enumerate() {
+            let _ = AppendMenuW(
+                hmenu,
+                flags,
+                (ID_TOOL_FIRST + index as i32) as usize,
+                PCWSTR::from_raw(to_wide(&tool.name.replace('&', "&&")).as_ptr()),
+            );
+        }
+    }
+}
+
+fn update_recent_list_menu_state(window: HWND, state: &AppState) {
+    unsafe {
+        let hmenu = GetMenu(window);
+        if !hmenu.is_invalid() {
+            // Pinning only applies while a list file is open
+            let pin_enabled = if state.current_list_path.is_some() { MF_ENABLED } else { MF_GRAYED };
+            EnableMenuItem(hmenu, ID_FILE_PIN_CURRENT_LIST as u32, pin_enabled);
+            
+            let is_pinned = state.current_list_path.as_ref().map_or(false, |list_path| {
+                state.config.recent_lists
+                    .iter()
+                    .any(|entry| entry.pinned && entry.path.eq_ignore_ascii_case(list_path))
+            });
+            let check_state = if is_pinned { MF_CHECKED.0 } else { MF_UNCHECKED.0 };
+            CheckMenuItem(hmenu, ID_FILE_PIN_CURRENT_LIST as u32, check_state);
+        }
     }
 }
 
@@ -2203,38 +8394,64 @@ fn update_column_menu_checkmarks(window: HWND, columns: &Vec<ColumnInfo>) {
     unsafe {
         let hmenu = GetMenu(window);
         if !hmenu.is_invalid() {
-            // Check columns based on their visibility
-            for column in columns {
-                let menu_id = match column.column_type {
-                    ColumnType::Name => ID_COLUMN_NAME,
-                    ColumnType::Size => ID_COLUMN_SIZE,
-                    ColumnType::Type => ID_COLUMN_TYPE,
-                    ColumnType::Modified => ID_COLUMN_MODIFIED,
-                    ColumnType::Path => ID_COLUMN_PATH,
-                };
-                
-                let check_state = if column.visible { MF_CHECKED.0 } else { MF_UNCHECKED.0 };
-                CheckMenuItem(hmenu, menu_id as u32, check_state);
-            }
+            check_column_items(hmenu, columns);
+        }
+    }
+}
+
+// Checks the ID_COLUMN_* items of the visible columns
+fn check_column_items(hmenu: HMENU, columns: &[ColumnInfo]) {
+    unsafe {
+        for column in columns {
+            let menu_id = match column.column_type {
+                ColumnType::Name => ID_COLUMN_NAME,
+                ColumnType::Size => ID_COLUMN_SIZE,
+                ColumnType::Type => ID_COLUMN_TYPE,
+                ColumnType::Modified => ID_COLUMN_MODIFIED,
+                ColumnType::Path => ID_COLUMN_PATH,
+                ColumnType::GitStatus => ID_COLUMN_GIT_STATUS,
+                ColumnType::Tags => ID_COLUMN_TAGS,
+                ColumnType::HardLinks => ID_COLUMN_HARD_LINKS,
+                ColumnType::Streams => ID_COLUMN_STREAMS,
+                ColumnType::Duration => ID_COLUMN_DURATION,
+                ColumnType::Bitrate => ID_COLUMN_BITRATE,
+                ColumnType::Notes => ID_COLUMN_NOTES,
+                ColumnType::Rating => ID_COLUMN_RATING,
+            };
+            
+            let check_state = if column.visible { MF_CHECKED.0 } else { MF_UNCHECKED.0 };
+            CheckMenuItem(hmenu, menu_id as u32, check_state);
+        }
+        
+        for (index, column) in columns.iter().enumerate() {
+            let check_state = if column.align_right { MF_CHECKED.0 } else { MF_UNCHECKED.0 };
+            CheckMenuItem(hmenu, (ID_ALIGN_RIGHT_FIRST + index as i32) as u32, check_state);
         }
     }
 }
 
+// Align Right submenu with an item for each column, after a separator.
+// check_column_items sets the checkmarks.
+unsafe fn append_alignment_submenu(menu: HMENU, columns: &[ColumnInfo], strings: &LanguageStrings) {
+    let Ok(submenu) = CreatePopupMenu() else {
+        return;
+    };
+    for (index, column) in columns.iter().enumerate() {
+        let label = to_wide(column.column_type.menu_label(strings));
+        let _ = AppendMenuW(submenu, MF_STRING, (ID_ALIGN_RIGHT_FIRST + index as i32) as usize, PCWSTR::from_raw(label.as_ptr()));
+    }
+    let _ = AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
+    let _ = AppendMenuW(menu, MF_STRING | MF_POPUP, submenu.0 as usize, PCWSTR::from_raw(to_wide(&strings.menu_align_right).as_ptr()));
+}
+
 fn update_language_menu_checkmarks(window: HWND, language: Language) {
     unsafe {
         let hmenu = GetMenu(window);
         if !hmenu.is_invalid() {
-            // Uncheck all items first
-            CheckMenuItem(hmenu, ID_LANG_ENGLISH as u32, MF_UNCHECKED.0);
-            CheckMenuItem(hmenu, ID_LANG_CHINESE as u32, MF_UNCHECKED.0);
-            
-            // Check the current language
-            let current_id = match language {
-                Language::English => ID_LANG_ENGLISH,
-                Language::Chinese => ID_LANG_CHINESE,
-            };
-            
-            CheckMenuItem(hmenu, current_id as u32, MF_CHECKED.0);
+            for (index, item) in Language::ALL.iter().enumerate() {
+                let check = if *item == language { MF_CHECKED } else { MF_UNCHECKED };
+                CheckMenuItem(hmenu, (ID_LANG_FIRST + index as i32) as u32, check.0);
+            }
         }
     }
 }
@@ -2243,64 +8460,226 @@ fn update_sort_menu_checkmarks(window: HWND, sort_state: &Option<SortState>) {
     unsafe {
         let hmenu = GetMenu(window);
         if !hmenu.is_invalid() {
-            // Uncheck all items first
-            CheckMenuItem(hmenu, ID_SORT_NAME as u32, MF_UNCHECKED.0);
-            CheckMenuItem(hmenu, ID_SORT_SIZE as u32, MF_UNCHECKED.0);
-            CheckMenuItem(hmenu, ID_SORT_TYPE as u32, MF_UNCHECKED.0);
-            CheckMenuItem(hmenu, ID_SORT_DATE as u32, MF_UNCHECKED.0);
-            CheckMenuItem(hmenu, ID_SORT_PATH as u32, MF_UNCHECKED.0);
-            CheckMenuItem(hmenu, ID_SORT_ASCENDING as u32, MF_UNCHECKED.0);
-            CheckMenuItem(hmenu, ID_SORT_DESCENDING as u32, MF_UNCHECKED.0);
+            check_sort_items(hmenu, sort_state);
+        }
+    }
+}
+
+// Checks the ID_SORT_* items of the current sort column and order
+fn check_sort_items(hmenu: HMENU, sort_state: &Option<SortState>) {
+    unsafe {
+        // Uncheck all items first
+        CheckMenuItem(hmenu, ID_SORT_NAME as u32, MF_UNCHECKED.0);
+        CheckMenuItem(hmenu, ID_SORT_SIZE as u32, MF_UNCHECKED.0);
+        CheckMenuItem(hmenu, ID_SORT_TYPE as u32, MF_UNCHECKED.0);
+        CheckMenuItem(hmenu, ID_SORT_DATE as u32, MF_UNCHECKED.0);
+        CheckMenuItem(hmenu, ID_SORT_PATH as u32, MF_UNCHECKED.0);
+        CheckMenuItem(hmenu, ID_SORT_ASCENDING as u32, MF_UNCHECKED.0);
+        CheckMenuItem(hmenu, ID_SORT_DESCENDING as u32, MF_UNCHECKED.0);
+        
+        // Check the current sort column and order if any
+        if let Some(state) = sort_state {
+            let current_id = match state.column {
+                ColumnType::Name => ID_SORT_NAME,
+                ColumnType::Size => ID_SORT_SIZE,
+                ColumnType::Type => ID_SORT_TYPE,
+                ColumnType::Modified => ID_SORT_DATE,
+                ColumnType::Path => ID_SORT_PATH,
+                // Never sorted by, see ColumnType::is_sortable
+                ColumnType::GitStatus | ColumnType::Tags | ColumnType::HardLinks | ColumnType::Streams | ColumnType::Notes | ColumnType::Rating => return,
+                // Sorted from the column header only
+                ColumnType::Duration | ColumnType::Bitrate => return,
+            };
+            
+            CheckMenuItem(hmenu, current_id as u32, MF_CHECKED.0);
             
-            // Check the current sort column and order if any
-            if let Some(state) = sort_state {
-                let current_id = match state.column {
-                    ColumnType::Name => ID_SORT_NAME,
-                    ColumnType::Size => ID_SORT_SIZE,
-                    ColumnType::Type => ID_SORT_TYPE,
-                    ColumnType::Modified => ID_SORT_DATE,
-                    ColumnType::Path => ID_SORT_PATH,
+            // Check the current sort order
+            match state.order {
+                SortOrder::Ascending => {
+                    CheckMenuItem(hmenu, ID_SORT_ASCENDING as u32, MF_CHECKED.0);
+                }
+                SortOrder::Descending => {
+                    CheckMenuItem(hmenu, ID_SORT_DESCENDING as u32, MF_CHECKED.0);
+                }
+                SortOrder::None => {
+                    // No order checkmark
+                }
+            }
+        }
+    }
+}
+
+// What Narrator, NVDA and other screen readers are told about the list
+// view, which draws everything itself
+impl AccessibleList for AppState {
+    fn list_name(&self) -> String {
+        get_strings().acc_results_list.clone()
+    }
+    
+    fn header_count(&self) -> usize {
+        if self.view_mode == ViewMode::Details {
+            self.get_visible_columns().len()
+        } else {
+            0
+        }
+    }
+    
+    fn header_name(&self, column: usize) -> String {
+        self.get_visible_columns()
+            .get(column)
+            .map(|column| column.column_type.display_name().to_string())
+            .unwrap_or_default()
+    }
+    
+    fn row_count(&self) -> usize {
+        self.list_data.len()
+    }
+    
+    fn row_name(&self, row: usize) -> String {
+        self.list_data.get(row).map(|item| item.name().to_string()).unwrap_or_default()
+    }
+    
+    fn row_description(&self, row: usize) -> String {
+        let Some(item) = self.list_data.get(row) else {
+            return String::new();
+        };
+        let strings = get_strings();
+        self.get_visible_columns()
+            .iter()
+            .filter(|column| column.column_type != ColumnType::Name)
+            .filter_map(|column| {
+                let text = self.cell_text(item, column.column_type, &strings);
+                (!text.is_empty()).then(|| format!("{}: {}", column.column_type.display_name(), text))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+    
+    fn child_rect(&self, child: AccessibleChild) -> Option<RECT> {
+        match child {
+            AccessibleChild::Header(column) => {
+                let columns = self.get_visible_columns();
+                let left = *self.column_lefts().get(column)?;
+                Some(RECT {
+                    left,
+                    top: 0,
+                    right: left + columns.get(column)?.width,
+                    bottom: HEADER_HEIGHT,
+                })
+            }
+            AccessibleChild::Row(row) => get_item_rect(row, self),
+        }
+    }
+    
+    fn child_at(&self, x: i32, y: i32) -> Option<AccessibleChild> {
+        if self.view_mode == ViewMode::Details && y < HEADER_HEIGHT {
+            self.get_column_at_x(x).map(AccessibleChild::Header)
+        } else {
+            self.get_item_at_point(x, y).map(AccessibleChild::Row)
+        }
+    }
+    
+    fn focused_row(&self) -> Option<usize> {
+        self.selected_index
+    }
+    
+    fn is_row_selected(&self, row: usize) -> bool {
+        self.is_selected(row)
+    }
+    
+    fn selected_count(&self) -> usize {
+        self.selected_items.len()
+    }
+    
+    fn select_row(&mut self, row: usize, flags: u32) {
+        if row >= self.list_data.len() {
+            return;
+        }
+        
+        let before = self.selection_snapshot();
+        if flags & SELFLAG_TAKESELECTION != 0 {
+            self.set_selection(row);
+        } else if flags & SELFLAG_EXTENDSELECTION != 0 {
+            self.extend_selection(self.selection_anchor.unwrap_or(row), row);
+        } else if flags & SELFLAG_ADDSELECTION != 0 {
+            self.selected_items.insert(row);
+        } else if flags & SELFLAG_REMOVESELECTION != 0 {
+            self.selected_items.remove(&row);
+        }
+        if flags & SELFLAG_TAKEFOCUS != 0 {
+            self.selected_index = Some(row);
+            self.ensure_selection_visible();
+            unsafe {
+                SetFocus(self.list_view);
+            }
+        }
+        
+        update_scrollbar(self);
+        self.invalidate_selection_change(&before);
+        update_status_bar(self);
+    }
+    
+    fn default_action(&self, child: AccessibleChild) -> Option<String> {
+        let strings = get_strings();
+        match child {
+            AccessibleChild::Header(column) => self.get_visible_columns()
+                .get(column)
+                .filter(|column| column.column_type.is_sortable())
+                .map(|_| strings.acc_sort.clone()),
+            AccessibleChild::Row(_) => Some(strings.acc_open.clone()),
+        }
+    }
+    
+    fn do_default_action(&mut self, child: AccessibleChild) {
+        match child {
+            AccessibleChild::Header(column) => {
+                let Some(column_type) = self.get_visible_columns().get(column).map(|column| column.column_type) else {
+                    return;
                 };
-                
-                CheckMenuItem(hmenu, current_id as u32, MF_CHECKED.0);
-                
-                // Check the current sort order
-                match state.order {
-                    SortOrder::Ascending => {
-                        CheckMenuItem(hmenu, ID_SORT_ASCENDING as u32, MF_CHECKED.0);
-                    }
-                    SortOrder::Descending => {
-                        CheckMenuItem(hmenu, ID_SORT_DESCENDING as u32, MF_CHECKED.0);
-                    }
-                    SortOrder::None => {
-                        // No order checkmark
-                    }
+                self.sort_by_column(column_type);
+                update_scrollbar(self);
+                unsafe {
+                    InvalidateRect(self.list_view, None, TRUE);
                 }
+                update_status_bar(self);
+            }
+            AccessibleChild::Row(row) => {
+                let before = self.selection_snapshot();
+                self.set_selection(row);
+                self.invalidate_selection_change(&before);
+                self.activate_selected();
             }
         }
     }
 }
 
+unsafe fn accessible_list(window: HWND) -> Option<&'static mut dyn AccessibleList> {
+    app_state(window).map(|state| state as &mut dyn AccessibleList)
+}
+
 extern "system" fn list_view_proc(
     window: HWND,
     message: u32,
     wparam: WPARAM,
     lparam: LPARAM,
 ) -> LRESULT {
+    let _timer = MessageTimer::start("list view", message);
     unsafe {
         match message {
             WM_CREATE => {
-                update_scrollbar(window);
+                if let Some(state) = app_state(window) {
+                    update_scrollbar(state);
+                }
                 LRESULT(0)
             }
             WM_SIZE => {
-                if let Some(state) = &mut APP_STATE {
+                if let Some(state) = app_state(window) {
                     let mut rect = RECT::default();
                     let _ = GetClientRect(window, &mut rect);
                     state.client_height = rect.bottom - rect.top;
                     state.client_width = rect.right - rect.left;
                     state.calculate_layout();
-                    update_scrollbar(window);
+                    update_scrollbar(state);
                     
                     // Post message to main window to recompute thumbnails
                     let _ = PostMessageW(GetParent(window), WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
@@ -2308,14 +8687,30 @@ extern "system" fn list_view_proc(
                 LRESULT(0)
             }
             WM_PAINT => {
-                paint_list_view(window);
+                match app_state(window) {
+                    Some(state) => {
+                        state.recompute_metadata_queue();
+                        state.recompute_git_status_queue();
+                        state.recompute_link_info_queue();
+                        state.recompute_media_info_queue();
+                        state.recompute_index_page_queue();
+                        sync_horizontal_scrollbar(state);
+                        paint_list_view(window, state);
+                    }
+                    None => {
+                        // Validate the region so WM_PAINT doesn't repeat
+                        let mut ps = PAINTSTRUCT::default();
+                        BeginPaint(window, &mut ps);
+                        EndPaint(window, &ps);
+                    }
+                }
                 LRESULT(0)
             }
             WM_LBUTTONDOWN => {
                 // Set focus to receive keyboard input
                 SetFocus(window);
                 
-                if let Some(state) = &mut APP_STATE {
+                if let Some(state) = app_state(window) {
                     let x = (lparam.0 & 0xFFFF) as i16 as i32;
                     let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
                     
@@ -2349,25 +8744,80 @@ extern "system" fn list_view_proc(
                                     state.sort_by_column(column_type);
                                     
                                     // Update UI
-                                    update_scrollbar(window);
+                                    update_scrollbar(state);
                                     InvalidateRect(window, None, TRUE);
-                                    update_status_bar();
+                                    update_status_bar(state);
                                 }
                             }
                         }
+                    } else if let Some(DetailsRow::Header(group)) = state.details_row_at(y) {
+                        // A drive header opens or closes its group
+                        state.toggle_drive_group(group);
+                    } else if let Some((item_index, stars)) = state.rating_stars_at(x, y).filter(|_| state.view_mode == ViewMode::Details) {
+                        state.rate_clicked(item_index, stars);
                     } else {
                         // Normal item selection
                     if let Some(item_index) = state.get_item_at_point(x, y) {
-                        state.set_selection(item_index);
-                        InvalidateRect(window, None, TRUE);
-                        update_status_bar();
+                        let ctrl_pressed = wparam.0 & 0x0008 != 0; // MK_CONTROL
+                        let shift_pressed = wparam.0 & 0x0004 != 0; // MK_SHIFT
+                        
+                        // The selection may be dragged to the sidebar to pin it,
+                        // or within an open list to reorder it
+                        let can_drag = state.folder_tree.is_some() || state.can_reorder_list();
+                        let clicked_in_selection = (can_drag && !ctrl_pressed && !shift_pressed
+                            && state.selected_items.len() > 1 && state.is_selected(item_index))
+                            .then_some(item_index);
+                        
+                        let before = state.selection_snapshot();
+                        match (state.selection_anchor, ctrl_pressed, shift_pressed) {
+                            // Kept whole in case it is dragged
+                            _ if clicked_in_selection.is_some() => state.selected_index = Some(item_index),
+                            (Some(anchor), _, true) => state.extend_selection(anchor, item_index),
+                            (_, true, false) => state.toggle_selection(item_index),
+                            _ => state.set_selection(item_index),
+                        }
+                        state.invalidate_selection_change(&before);
+                        update_status_bar(state);
+                        
+                        if can_drag {
+                            state.result_drag_state = Some(ResultDragState {
+                                start: POINT { x, y },
+                                is_dragging: false,
+                                reorder_gap: None,
+                                clicked_in_selection,
+                            });
+                            SetCapture(window);
+                        }
                         }
                     }
                 }
                 LRESULT(0)
             }
             WM_LBUTTONUP => {
-                if let Some(state) = &mut APP_STATE {
+                if let Some(state) = app_state(window) {
+                    if let Some(drag_state) = state.result_drag_state.take() {
+                        let _ = ReleaseCapture();
+                        let _ = KillTimer(window, REORDER_SCROLL_TIMER_ID);
+                        let mut cursor = POINT::default();
+                        let _ = GetCursorPos(&mut cursor);
+                        let dropped = state.folder_tree.as_ref().map_or(false, |folder_tree| folder_tree.contains_point(cursor));
+                        if !drag_state.is_dragging {
+                            if let Some(item_index) = drag_state.clicked_in_selection {
+                                let before = state.selection_snapshot();
+                                state.set_selection(item_index);
+                                state.invalidate_selection_change(&before);
+                                update_status_bar(state);
+                            }
+                        } else if dropped {
+                            state.pin_selected_results();
+                        } else if let Some(gap) = drag_state.reorder_gap {
+                            state.move_selected_entries_to(gap);
+                        }
+                        if drag_state.reorder_gap.is_some() {
+                            InvalidateRect(window, None, FALSE);
+                        }
+                    }
+                    
                     // End column resize if active
                     if let Some(ref drag_state) = state.column_drag_state {
                         if drag_state.is_dragging {
@@ -2380,10 +8830,30 @@ extern "system" fn list_view_proc(
                 LRESULT(0)
             }
             WM_MOUSEMOVE => {
-                if let Some(state) = &mut APP_STATE {
+                if let Some(state) = app_state(window) {
                     let x = (lparam.0 & 0xFFFF) as i16 as i32;
                     let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
                     
+                    // Dragging results: a normal cursor over the sidebar or,
+                    // with a marker where they would go, over an open list.
+                    // "No" anywhere else.
+                    if let Some(ref mut drag_state) = state.result_drag_state {
+                        if !drag_state.is_dragging {
+                            drag_state.is_dragging = (x - drag_state.start.x).abs() > GetSystemMetrics(SM_CXDRAG)
+                                || (y - drag_state.start.y).abs() > GetSystemMetrics(SM_CYDRAG);
+                        }
+                        if drag_state.is_dragging {
+                            let mut cursor = POINT::default();
+                            let _ = GetCursorPos(&mut cursor);
+                            let over_sidebar = state.folder_tree.as_ref().map_or(false, |folder_tree| folder_tree.contains_point(cursor));
+                            let over_list = x >= 0 && x < state.client_width && y >= 0 && y < state.client_height;
+                            let reorder_gap = (!over_sidebar && over_list && state.can_reorder_list()).then(|| state.reorder_gap_at(x, y));
+                            SetCursor(LoadCursorW(None, if over_sidebar || reorder_gap.is_some() { IDC_ARROW } else { IDC_NO }).unwrap_or_default());
+                            update_reorder_gap(window, state, reorder_gap);
+                        }
+                        return LRESULT(0);
+                    }
+                    
                     // Handle column resize dragging
                     let target_column_type = if let Some(ref drag_state) = state.column_drag_state {
                         if drag_state.is_dragging {
@@ -2429,16 +8899,31 @@ extern "system" fn list_view_proc(
                 }
                 LRESULT(0)
             }
+            WM_MBUTTONUP => {
+                // A middle click browses the result's folder in a new tab
+                if let Some(state) = app_state(window) {
+                    let x = (lparam.0 & 0xFFFF) as i16 as i32;
+                    let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
+                    
+                    if let Some(item_index) = state.get_item_at_point(x, y) {
+                        if !state.showing_remote_results() {
+                            state.browse_folder_in_new_tab(item_index);
+                        }
+                    }
+                }
+                LRESULT(0)
+            }
             WM_LBUTTONDBLCLK => {
-                if let Some(state) = &mut APP_STATE {
+                if let Some(state) = app_state(window) {
                     let x = (lparam.0 & 0xFFFF) as i16 as i32;
                     let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
                     
                     if let Some(item_index) = state.get_item_at_point(x, y) {
+                        let before = state.selection_snapshot();
                         state.set_selection(item_index);
-                        state.open_selected_file();
-                        InvalidateRect(window, None, TRUE);
-                        update_status_bar();
+                        state.activate_selected();
+                        state.invalidate_selection_change(&before);
+                        update_status_bar(state);
                     }
                 }
                 LRESULT(0)
@@ -2452,25 +8937,63 @@ extern "system" fn list_view_proc(
                 ClientToScreen(window, &mut pt);
                 
                 // Check if we clicked on a file
-                if let Some(state) = &mut APP_STATE {
+                if let Some(state) = app_state(window) {
                     if let Some(item_index) = state.get_item_at_point(x, y) {
                         // Right-clicked on a file - show file context menu
+                        let before = state.selection_snapshot();
                         state.set_selection(item_index);
-                        InvalidateRect(window, None, TRUE);
-                        update_status_bar();
-                        show_file_context_menu(GetParent(window), pt.x, pt.y, &state.list_data[item_index]);
+                        state.invalidate_selection_change(&before);
+                        update_status_bar(state);
+                        show_file_context_menu(GetParent(window), pt.x, pt.y, state);
+                    } else if state.view_mode == ViewMode::Details && y < HEADER_HEIGHT {
+                        let column = state.get_column_at_x(x).and_then(|index| state.get_visible_columns().get(index).map(|column| column.column_type));
+                        show_header_menu(GetParent(window), state, column, pt.x, pt.y);
                     } else {
                         // Right-clicked on empty space - show view context menu
-                show_context_menu(GetParent(window), pt.x, pt.y);
+                        show_context_menu(GetParent(window), state, pt.x, pt.y);
                     }
                 }
                 LRESULT(0)
             }
+            // Only sent for Shift+F10 and the Menu key, since right-clicks
+            // are handled above
+            WM_CONTEXTMENU => {
+                if let Some(state) = app_state(window) {
+                    show_keyboard_context_menu(window, state, GetKeyState(VK_CONTROL.0 as i32) < 0);
+                }
+                LRESULT(0)
+            }
             WM_KEYDOWN => {
-                if let Some(state) = &mut APP_STATE {
+                if let Some(state) = app_state(window) {
+                    let before = state.selection_snapshot();
                     let old_selected = state.selected_index;
+                    let anchor = state.selection_anchor;
+                    let shift_pressed = GetKeyState(VK_SHIFT.0 as i32) < 0;
+                    let ctrl_pressed = GetKeyState(VK_CONTROL.0 as i32) < 0;
                     
                     match wparam.0 as u32 {
+                        // Ctrl+Page Up/Down and Ctrl+Home/End scroll the view
+                        // without moving the selection, as does Space in the
+                        // icon views, so long lists can be skimmed
+                        0x21..=0x24 if ctrl_pressed => {
+                            let request = match wparam.0 {
+                                0x21 => 2, // SB_PAGEUP
+                                0x22 => 3, // SB_PAGEDOWN
+                                0x24 => 6, // SB_TOP
+                                _ => 7,    // SB_BOTTOM
+                            };
+                            handle_vertical_scroll(window, state, request, 0);
+                            return LRESULT(0);
+                        }
+                        0x20 if state.view_mode != ViewMode::Details => { // VK_SPACE, Shift+Space pages back
+                            handle_vertical_scroll(window, state, if shift_pressed { 2 } else { 3 }, 0);
+                            return LRESULT(0);
+                        }
+                        // Ctrl+Up/Down moves entries of an open list
+                        0x26 | 0x28 if ctrl_pressed && state.can_reorder_list() => {
+                            state.nudge_selected_entries(if wparam.0 == 0x26 { -1 } else { 1 });
+                            return LRESULT(0);
+                        }
                         0x26 => state.move_selection(-1),      // VK_UP
                         0x28 => state.move_selection(1),       // VK_DOWN
                         0x21 => { // VK_PRIOR (Page Up)
@@ -2492,29 +9015,134 @@ extern "system" fn list_view_proc(
                                 state.set_selection(0);
                             }
                         }
-                        0x23 => { // VK_END
-                            if !state.list_data.is_empty() {
-                                state.set_selection(state.list_data.len() - 1);
-                            }
+                        0x23 => { // VK_END
+                            if !state.list_data.is_empty() {
+                                state.set_selection(state.list_data.len() - 1);
+                            }
+                        }
+                        0x0D => { // VK_RETURN
+                            state.activate_selected();
+                        }
+                        0x72 => { // VK_F3
+                            state.quick_view_selected();
+                            return LRESULT(0);
+                        }
+                        0x73 => { // VK_F4
+                            state.preview_selected_video();
+                            return LRESULT(0);
+                        }
+                        0x09 => { // VK_TAB: on to the folder tree, or back to the search box
+                            match state.folder_tree {
+                                Some(ref folder_tree) => {
+                                    SetFocus(folder_tree.window);
+                                }
+                                None => focus_search_edit(state),
+                            }
+                            return LRESULT(0);
+                        }
+                        0x30..=0x35 if ctrl_pressed => { // Ctrl+0 to Ctrl+5 rate the selection
+                            state.rate_selected((wparam.0 - 0x30) as u8);
+                            return LRESULT(0);
+                        }
+                        0x41 if ctrl_pressed => { // Ctrl+A
+                            state.select_all();
+                        }
+                        0x56 if ctrl_pressed => { // Ctrl+V
+                            state.paste_paths_as_list();
+                            return LRESULT(0);
+                        }
+                        0x1B => { // VK_ESCAPE
+                            clear_search_or_hide(state);
+                            return LRESULT(0);
+                        }
+                        _ if is_focus_search_shortcut(wparam) => {
+                            focus_search_edit(state);
+                            return LRESULT(0);
                         }
-                        0x0D => { // VK_RETURN
-                            state.open_selected_file();
+                        _ if is_log_window_shortcut(wparam) => {
+                            toggle_log_window(state.main_window, state.font);
+                            return LRESULT(0);
+                        }
+                        _ if is_perf_hud_shortcut(wparam) => {
+                            toggle_perf_hud(state);
+                            return LRESULT(0);
                         }
                         _ => return DefWindowProcW(window, message, wparam, lparam),
                     }
                     
-                    if state.selected_index != old_selected {
-                        update_scrollbar(window);
-                        InvalidateRect(window, None, TRUE);
-                        update_status_bar();
+                    // Shift+navigation extends the selection from the anchor
+                    if shift_pressed && state.selected_index != old_selected {
+                        if let (Some(anchor), Some(current)) = (anchor, state.selected_index) {
+                            state.extend_selection(anchor, current);
+                        }
+                    }
+                    
+                    if state.selected_index != before.focused || state.selected_items != before.items {
+                        update_scrollbar(state);
+                        state.invalidate_selection_change(&before);
+                        update_status_bar(state);
                     }
                 }
                 LRESULT(0)
             }
+            // Alt+Enter shows the Explorer properties of the focused file
+            WM_SYSKEYDOWN if wparam.0 == 0x0D => {
+                if let Some(state) = app_state(window) {
+                    state.show_selected_properties();
+                }
+                LRESULT(0)
+            }
+            // Keeps the menu bar from beeping at Alt+Enter
+            WM_SYSCHAR if wparam.0 == 0x0D => LRESULT(0),
             WM_VSCROLL => {
                 let request = (wparam.0 & 0xFFFF) as u16;
                 let pos = ((wparam.0 >> 16) & 0xFFFF) as i16;
-                handle_vertical_scroll(window, request, pos);
+                if let Some(state) = app_state(window) {
+                    handle_vertical_scroll(window, state, request, pos);
+                }
+                LRESULT(0)
+            }
+            WM_HSCROLL => {
+                let request = (wparam.0 & 0xFFFF) as i32;
+                if let Some(state) = app_state(window) {
+                    handle_horizontal_scroll(window, state, request);
+                }
+                LRESULT(0)
+            }
+            // Scrolls while dragged entries are near the top or bottom
+            WM_TIMER if wparam.0 == REORDER_SCROLL_TIMER_ID => {
+                if let Some(state) = app_state(window) {
+                    let mut cursor = POINT::default();
+                    let _ = GetCursorPos(&mut cursor);
+                    let _ = ScreenToClient(window, &mut cursor);
+                    let top = if state.view_mode == ViewMode::Details { HEADER_HEIGHT } else { 0 };
+                    let request = if cursor.y < top + REORDER_SCROLL_MARGIN {
+                        Some(0) // SB_LINEUP
+                    } else if cursor.y >= state.client_height - REORDER_SCROLL_MARGIN {
+                        Some(1) // SB_LINEDOWN
+                    } else {
+                        None
+                    };
+                    if let Some(request) = request {
+                        let old_pos = state.scroll_pos;
+                        handle_vertical_scroll(window, state, request, 0);
+                        if state.scroll_pos != old_pos {
+                            let gap = state.reorder_gap_at(cursor.x, cursor.y);
+                            if let Some(drag_state) = state.result_drag_state.as_mut() {
+                                drag_state.reorder_gap = Some(gap);
+                            }
+                            InvalidateRect(window, None, FALSE);
+                        }
+                    }
+                }
+                LRESULT(0)
+            }
+            // Tilting the wheel scrolls the columns sideways
+            WM_MOUSEHWHEEL => {
+                let delta = ((wparam.0 >> 16) & 0xFFFF) as i16 as i32;
+                if let Some(state) = app_state(window) {
+                    scroll_columns(window, state, delta * HORIZONTAL_SCROLL_STEP / 120);
+                }
                 LRESULT(0)
             }
             WM_MOUSEWHEEL => {
@@ -2524,9 +9152,14 @@ extern "system" fn list_view_proc(
                 // Check if Ctrl key is pressed
                 let ctrl_pressed = GetKeyState(VK_CONTROL.0 as i32) < 0;
                 
-                if ctrl_pressed {
+                if GetKeyState(VK_SHIFT.0 as i32) < 0 && !ctrl_pressed {
+                    // Shift+Scroll: scroll the columns sideways
+                    if let Some(state) = app_state(window) {
+                        scroll_columns(window, state, -delta as i32 * HORIZONTAL_SCROLL_STEP);
+                    }
+                } else if ctrl_pressed {
                     // Ctrl+Scroll: Adjust zoom level (15 levels: 0-14)
-                    if let Some(state) = &mut APP_STATE {
+                    if let Some(state) = app_state(window) {
                         let current_zoom = state.zoom_level;
                         let new_zoom = if delta > 0 {
                             // Scroll up: increase zoom level (larger icons)
@@ -2538,40 +9171,71 @@ extern "system" fn list_view_proc(
                         
                         if new_zoom != current_zoom {
                             state.set_zoom_level(new_zoom);
-                            update_scrollbar(window);
+                            update_scrollbar(state);
                             InvalidateRect(window, None, TRUE);
                             
                             // Log zoom level for debugging
                             let icon_size = AppState::get_icon_size_from_zoom_level(new_zoom);
                             if new_zoom == 0 {
-                                println!("Zoom level: {} (Details view)", new_zoom);
+                                log_debug(&format!("Zoom level: {} (Details view)", new_zoom));
                             } else {
-                                println!("Zoom level: {} ({}px icons)", new_zoom, icon_size);
+                                log_debug(&format!("Zoom level: {} ({}px icons)", new_zoom, icon_size));
                             }
                         }
                     }
                 } else {
                     // Normal scroll: scroll the list
-                scroll_list(window, -delta as i32 * 3);
+                    if let Some(state) = app_state(window) {
+                        scroll_list(window, state, -delta as i32 * 3);
+                    }
                 }
                 LRESULT(0)
             }
-            WM_SETFOCUS => {
-                InvalidateRect(window, None, TRUE);
+            // Only the selection colour and the focus rectangle depend on focus
+            WM_SETFOCUS | WM_KILLFOCUS => {
+                if let Some(state) = app_state(window) {
+                    let mut items = state.selected_items.clone();
+                    items.extend(state.selected_index);
+                    state.invalidate_items(&items);
+                    if message == WM_SETFOCUS {
+                        notify_focus(window, state);
+                    }
+                }
                 LRESULT(0)
             }
-            WM_KILLFOCUS => {
-                InvalidateRect(window, None, TRUE);
+            // Screen readers get the rows and headers from an IAccessible
+            WM_GETOBJECT => {
+                handle_get_object(window, wparam, lparam, accessible_list)
+                    .unwrap_or_else(|| DefWindowProcW(window, message, wparam, lparam))
+            }
+            // paint_list_view fills the whole background itself
+            WM_ERASEBKGND => LRESULT(1),
+            _ if message == WM_ICON_READY => {
+                let cache_key = *Box::from_raw(wparam.0 as *mut String);
+                
+                // Repaint the visible rows that use this icon
+                if let Some(state) = app_state(window) {
+                    if state.view_mode == ViewMode::Details {
+                        let visible_end = (state.visible_start + state.visible_count + 1).min(state.list_data.len());
+                        for index in state.visible_start..visible_end {
+                            if icon_cache_key(state.list_data[index].path(), true) == cache_key {
+                                if let Some(rect) = get_item_rect(index, state) {
+                                    InvalidateRect(window, Some(&rect), FALSE);
+                                }
+                            }
+                        }
+                    }
+                }
                 LRESULT(0)
             }
             _ if message == WM_THUMBNAIL_READY => {
                 // Handle thumbnail completion
-                if let Some(state) = &mut APP_STATE {
+                if let Some(state) = app_state(window) {
                     let item_index = wparam.0;
                     let hbitmap = HBITMAP(lparam.0 as isize);
                     
                     if let Some(item) = state.list_data.get(item_index) {
-                        let cache_key = (item.path.clone(), state.selected_view_size);
+                        let cache_key = (item.path().to_string(), state.selected_view_size);
                         state.thumbnail_cache.put(cache_key, hbitmap);
                         
                         // Invalidate only the specific item's area
@@ -2591,7 +9255,7 @@ extern "system" fn list_view_proc(
 fn get_item_rect(item_index: usize, state: &AppState) -> Option<RECT> {
     match state.view_mode {
         ViewMode::Details => {
-            let y = item_index as i32 * state.item_height - state.scroll_pos;
+            let y = HEADER_HEIGHT + state.item_offset(item_index) - state.scroll_pos;
             if y >= -state.item_height && y < state.client_height + state.item_height {
                 Some(RECT {
                     left: 0,
@@ -2628,85 +9292,145 @@ fn get_item_rect(item_index: usize, state: &AppState) -> Option<RECT> {
     }
 }
 
-fn paint_list_view(window: HWND) {
-    log_debug("paint_list_view called");
+// Moves the marker of where dragged entries would go to `reorder_gap`, and
+// scrolls the view while the cursor is near its top or bottom
+unsafe fn update_reorder_gap(window: HWND, state: &mut AppState, reorder_gap: Option<usize>) {
+    let Some(drag_state) = state.result_drag_state.as_mut() else {
+        return;
+    };
+    if drag_state.reorder_gap == reorder_gap {
+        return;
+    }
+    let old_gap = std::mem::replace(&mut drag_state.reorder_gap, reorder_gap);
+    for gap in [old_gap, reorder_gap].into_iter().flatten() {
+        let mut marker = state.reorder_marker_rect(gap);
+        let _ = InflateRect(&mut marker, 1, 1);
+        InvalidateRect(window, Some(&marker), FALSE);
+    }
+    if reorder_gap.is_some() {
+        SetTimer(window, REORDER_SCROLL_TIMER_ID, 50, None);
+    } else {
+        let _ = KillTimer(window, REORDER_SCROLL_TIMER_ID);
+    }
+}
+
+fn paint_list_view(window: HWND, state: &mut AppState) {
+    log_trace("paint_list_view called");
     
+    let paint_start = Instant::now();
     unsafe {
         let mut ps = PAINTSTRUCT::default();
-        log_debug("About to call BeginPaint");
+        log_trace("About to call BeginPaint");
         let hdc = BeginPaint(window, &mut ps);
-        log_debug("BeginPaint completed");
+        log_trace("BeginPaint completed");
         
-        if let Some(state) = &APP_STATE {
-            log_debug(&format!("APP_STATE available for painting, list_data size: {}", state.list_data.len()));
-            
-            let mem_dc = CreateCompatibleDC(hdc);
-            let mut rect = RECT::default();
-            let _ = GetClientRect(window, &mut rect);
-            
-            log_debug("Created memory DC and got client rect");
-            
-            let bitmap = CreateCompatibleBitmap(hdc, rect.right - rect.left, rect.bottom - rect.top);
-            let old_bitmap = SelectObject(mem_dc, bitmap);
-            
-            log_debug("Created compatible bitmap");
-            
-            let bg_brush = CreateSolidBrush(COLORREF(0x00FFFFFF));
-            FillRect(mem_dc, &rect, bg_brush);
-            DeleteObject(bg_brush);
-            
-            SetBkMode(mem_dc, TRANSPARENT);
-            SelectObject(mem_dc, state.font);
-            
-            let has_focus = GetFocus() == window;
-            
-            log_debug(&format!("About to paint view mode: {:?}", state.view_mode));
-            
-            match state.view_mode {
-                ViewMode::Details => {
-                    log_debug("Calling paint_details_view");
-                    paint_details_view(mem_dc, &rect, state, has_focus);
-                    log_debug("paint_details_view completed");
-                }
-                _ => {
-                    log_debug("Calling paint_icon_view");
-                    paint_icon_view(mem_dc, &rect, state, has_focus);
-                    log_debug("paint_icon_view completed");
-                }
+        log_trace(&format!("Painting list view, list_data size: {}", state.list_data.len()));
+        
+        let mut rect = RECT::default();
+        let _ = GetClientRect(window, &mut rect);
+        let width = (rect.right - rect.left).max(1);
+        let height = (rect.bottom - rect.top).max(1);
+        
+        let size_changed = state.backbuffer.as_ref().map_or(true, |backbuffer| {
+            backbuffer.width != width || backbuffer.height != height
+        });
+        if size_changed {
+            log_debug(&format!("Creating {}x{} list view backbuffer", width, height));
+            state.backbuffer = Some(Backbuffer::new(hdc, width, height));
+        }
+        let mem_dc = state.backbuffer.as_ref().map_or(HDC(0), |backbuffer| backbuffer.dc);
+        
+        // The rest of the backbuffer still holds the last frame
+        let bg_brush = CreateSolidBrush(COLORREF(0x00FFFFFF));
+        FillRect(mem_dc, &ps.rcPaint, bg_brush);
+        DeleteObject(bg_brush);
+        
+        SetBkMode(mem_dc, TRANSPARENT);
+        SelectObject(mem_dc, state.font);
+        
+        let has_focus = GetFocus() == window;
+        
+        log_trace(&format!("About to paint view mode: {:?}", state.view_mode));
+        
+        match state.view_mode {
+            ViewMode::Details => {
+                log_trace("Calling paint_details_view");
+                paint_details_view(mem_dc, &rect, &ps.rcPaint, state, has_focus);
+                log_trace("paint_details_view completed");
             }
-            
-            log_debug("About to BitBlt to screen");
-            let _ = BitBlt(
-                hdc,
-                0, 0,
-                rect.right - rect.left,
-                rect.bottom - rect.top,
-                mem_dc,
-                0, 0,
-                SRCCOPY,
-            );
-            log_debug("BitBlt completed");
-            
-            SelectObject(mem_dc, old_bitmap);
-            DeleteObject(bitmap);
-            DeleteDC(mem_dc);
-            log_debug("Cleaned up GDI objects");
-        } else {
-            log_debug("ERROR: APP_STATE is None during painting");
+            _ => {
+                log_trace("Calling paint_icon_view");
+                paint_icon_view(mem_dc, &ps.rcPaint, state, has_focus);
+                log_trace("paint_icon_view completed");
+            }
+        }
+        
+        if let Some(gap) = state.result_drag_state.as_ref().and_then(|drag_state| drag_state.reorder_gap) {
+            let marker_brush = CreateSolidBrush(COLORREF(0x00000000));
+            FillRect(mem_dc, &state.reorder_marker_rect(gap), marker_brush);
+            DeleteObject(marker_brush);
+        }
+        
+        if state.perf_hud_visible && rects_intersect(&perf_hud_rect(state), &ps.rcPaint) {
+            paint_perf_hud(mem_dc, state);
         }
         
-        log_debug("About to call EndPaint");
+        // Only the invalidated part has been drawn
+        log_trace("About to BitBlt to screen");
+        let dirty = ps.rcPaint;
+        let _ = BitBlt(
+            hdc,
+            dirty.left, dirty.top,
+            dirty.right - dirty.left,
+            dirty.bottom - dirty.top,
+            mem_dc,
+            dirty.left, dirty.top,
+            SRCCOPY,
+        );
+        log_trace("BitBlt completed");
+        
+        log_trace("About to call EndPaint");
         EndPaint(window, &ps);
-        log_debug("paint_list_view completed successfully");
+        log_trace("paint_list_view completed successfully");
+    }
+    record_paint(paint_start.elapsed());
+}
+
+// Visible columns in the order they are drawn. A frozen Name column goes
+// last so it covers the columns scrolled underneath it.
+fn column_draw_order(count: usize, frozen: i32) -> Vec<usize> {
+    if frozen > 0 {
+        (1..count).chain(std::iter::once(0)).collect()
+    } else {
+        (0..count).collect()
+    }
+}
+
+// Keeps what is drawn from here on out of the frozen column's part of the
+// band from `top` to `bottom`. Returns the saved DC for restore_frozen_clip,
+// or None when no column is frozen.
+unsafe fn exclude_frozen_column(hdc: HDC, frozen: i32, top: i32, bottom: i32) -> Option<i32> {
+    if frozen <= 0 {
+        return None;
     }
+    let saved = SaveDC(hdc);
+    ExcludeClipRect(hdc, 0, top, frozen, bottom);
+    Some(saved)
 }
 
-fn paint_details_view(hdc: HDC, client_rect: &RECT, state: &AppState, has_focus: bool) {
+unsafe fn restore_frozen_clip(hdc: HDC, saved: &mut Option<i32>) {
+    if let Some(saved) = saved.take() {
+        let _ = RestoreDC(hdc, saved);
+    }
+}
+
+fn paint_details_view(hdc: HDC, client_rect: &RECT, paint_rect: &RECT, state: &AppState, has_focus: bool) {
     unsafe {
         let visible_columns = state.get_visible_columns();
         if visible_columns.is_empty() {
             return;
         }
+        let strings = get_strings();
         
         // Constants for icon display
         const ICON_SIZE: i32 = 16;
@@ -2733,8 +9457,16 @@ fn paint_details_view(hdc: HDC, client_rect: &RECT, state: &AppState, has_focus:
         LineTo(hdc, client_rect.right, HEADER_HEIGHT - 1);
         
         // Draw column headers and separators
-        let mut current_x = 0;
-        for (index, column) in visible_columns.iter().enumerate() {
+        let lefts = state.column_lefts();
+        let frozen = state.frozen_width();
+        let mut saved_dc = exclude_frozen_column(hdc, frozen, 0, HEADER_HEIGHT);
+        for index in column_draw_order(visible_columns.len(), frozen) {
+            let column = visible_columns[index];
+            let current_x = lefts[index];
+            if index == 0 {
+                restore_frozen_clip(hdc, &mut saved_dc);
+            }
+            
             // Column separator (except for first column)
             if index > 0 {
                 MoveToEx(hdc, current_x, 0, None);
@@ -2745,35 +9477,29 @@ fn paint_details_view(hdc: HDC, client_rect: &RECT, state: &AppState, has_focus:
             SetTextColor(hdc, COLORREF(0x00000000));
             SetBkMode(hdc, TRANSPARENT);
             
-            let header_text_with_sort = {
-                let base_text = column.column_type.display_name();
-                
-                // Add sort indicator if this column is sorted
-                if let Some(ref sort_state) = state.sort_state {
-                    if sort_state.column == column.column_type {
-                        match sort_state.order {
-                            SortOrder::Ascending => format!("{} ↑", base_text),
-                            SortOrder::Descending => format!("{} ↓", base_text),
-                            SortOrder::None => base_text.to_string(),
-                        }
-                    } else {
-                        base_text.to_string()
-                    }
-                } else {
-                    base_text.to_string()
-                }
-            };
+            let header_text_with_sort = state.column_header_text(column.column_type);
             
-            let header_text: Vec<u16> = header_text_with_sort.encode_utf16().collect();
-            // For the name column, offset text to account for icon space
-            let text_x = if index == 0 && visible_columns[0].column_type == ColumnType::Name {
-                current_x + TEXT_OFFSET + 5
+            let mut header_text: Vec<u16> = header_text_with_sort.encode_utf16().collect();
+            if column.align_right {
+                // Over the right edge of the values below it
+                let mut header_rect = RECT { left: current_x + 5, top: 0, right: current_x + column.width - 2, bottom: HEADER_HEIGHT };
+                DrawTextW(hdc, &mut header_text, &mut header_rect, DT_RIGHT | DT_VCENTER | DT_SINGLELINE | DT_END_ELLIPSIS);
             } else {
-                current_x + 5
-            };
-            TextOutW(hdc, text_x, 5, &header_text);
-            
-            current_x += column.width;
+                // For the name column, offset text to account for icon space
+                let text_x = if index == 0 && visible_columns[0].column_type == ColumnType::Name {
+                    current_x + TEXT_OFFSET + 5
+                } else {
+                    current_x + 5
+                };
+                TextOutW(hdc, text_x, 5, &header_text);
+            }
+        }
+        
+        // Edge of the frozen column, which the next header's separator
+        // scrolls away from
+        if frozen > 0 {
+            MoveToEx(hdc, frozen, 0, None);
+            LineTo(hdc, frozen, HEADER_HEIGHT);
         }
         
         SelectObject(hdc, old_pen);
@@ -2795,13 +9521,13 @@ fn paint_details_view(hdc: HDC, client_rect: &RECT, state: &AppState, has_focus:
             start_y = base_start_y + (items_above_header as i32 * state.item_height);
         }
         
-        for i in 0..state.visible_count {
-            let item_index = state.visible_start + i + first_item_offset;
-            if item_index >= state.list_data.len() {
+        let first_row = (state.scroll_pos / state.item_height) as usize;
+        let visible_rows = ((client_rect.bottom - content_top) / state.item_height + 2).max(0) as usize;
+        for i in 0..visible_rows {
+            let Some(row) = state.details_row(first_row + i + first_item_offset) else {
                 break;
-            }
+            };
             
-            let item = &state.list_data[item_index];
             let y = start_y + (i as i32 * state.item_height);
             
             // Double-check: ensure this item is not drawn above the header
@@ -2821,8 +9547,24 @@ fn paint_details_view(hdc: HDC, client_rect: &RECT, state: &AppState, has_focus:
                 bottom: y + state.item_height,
             };
             
+            // Rows outside the invalidated area are left as they are
+            if !rects_intersect(&item_rect, paint_rect) {
+                continue;
+            }
+            
+            let item_index = match row {
+                DetailsRow::Item(index) => index,
+                DetailsRow::Header(group) => {
+                    if let Some(group) = state.drive_groups.as_ref().and_then(|groups| groups.get(group)) {
+                        draw_drive_header(hdc, &item_rect, group, state, &strings);
+                    }
+                    continue;
+                }
+            };
+            let item = &state.list_data[item_index];
+            
             // Draw selection highlight
-            if Some(item_index) == state.selected_index {
+            if state.is_selected(item_index) {
                 let selection_color = if has_focus {
                     COLORREF(0x00316AC5) // Blue selection when focused
                 } else {
@@ -2843,35 +9585,37 @@ fn paint_details_view(hdc: HDC, client_rect: &RECT, state: &AppState, has_focus:
                 SetTextColor(hdc, COLORREF(0x00000000));
             }
             
+            // Rows of a page still being read stay blank
+            if item.is_placeholder() {
+                continue;
+            }
+            
+            // Grey out and strike through list entries whose file is missing
+            if item.missing {
+                if !state.is_selected(item_index) || !has_focus {
+                    SetTextColor(hdc, COLORREF(0x00A0A0A0));
+                }
+                SelectObject(hdc, state.strikeout_font);
+            } else {
+                SelectObject(hdc, state.font);
+            }
+            
             // Draw column data
-            let mut current_x = 0;
-            for (col_index, column) in visible_columns.iter().enumerate() {
-                let text = match column.column_type {
-                    ColumnType::Name => item.name.clone(),
-                    ColumnType::Size => {
-                        // Load metadata on demand for visible items
-                        let mut item_clone = item.clone();
-                        if item_clone.size == 0 && item_clone.modified_time == std::time::UNIX_EPOCH {
-                            item_clone.load_metadata();
-                        }
-                        item_clone.format_size()
-                    },
-                    ColumnType::Type => item.file_type.clone(),
-                    ColumnType::Modified => {
-                        // Load metadata on demand for visible items
-                        let mut item_clone = item.clone();
-                        if item_clone.size == 0 && item_clone.modified_time == std::time::UNIX_EPOCH {
-                            item_clone.load_metadata();
-                        }
-                        item_clone.format_modified_time()
-                    },
-                    ColumnType::Path => item.path.clone(),
-                };
+            let mut saved_dc = exclude_frozen_column(hdc, frozen, y, y + state.item_height);
+            for col_index in column_draw_order(visible_columns.len(), frozen) {
+                let column = visible_columns[col_index];
+                let current_x = lefts[col_index];
+                if col_index == 0 {
+                    restore_frozen_clip(hdc, &mut saved_dc);
+                }
+                let text = state.cell_text(item, column.column_type, &strings);
+                let align = if column.align_right { DT_RIGHT } else { DT_LEFT };
                 
                 // For the first column (Name), draw icon and adjust text position
                 if col_index == 0 && column.column_type == ColumnType::Name {
-                    // Get and draw file icon
-                    if let Some(icon) = get_file_icon(&item.path, true) { // true for small icon
+                    // Get and draw file icon; the generic icon stands in while it loads
+                    let icon = if item.is_folder { get_folder_icon(true) } else { get_file_icon(item.path(), true) }; // true for small icon
+                    if let Some(icon) = icon {
                         let icon_x = current_x + ICON_MARGIN;
                         let icon_y = y + (state.item_height - ICON_SIZE) / 2; // Center vertically
                         draw_icon(hdc, icon, icon_x, icon_y, ICON_SIZE);
@@ -2881,10 +9625,16 @@ fn paint_details_view(hdc: HDC, client_rect: &RECT, state: &AppState, has_focus:
                         let icon_y = y + (state.item_height - ICON_SIZE) / 2;
                         draw_icon(hdc, default_icon, icon_x, icon_y, ICON_SIZE);
                     }
+                    if is_offline(item.path()) {
+                        draw_offline_badge(hdc, current_x + ICON_MARGIN + ICON_SIZE, y + (state.item_height + ICON_SIZE) / 2);
+                    }
                     
-                    // Create clipping rect for text (offset by icon space)
+                    // Tag dots go between the icon and the name
+                    let dots_width = draw_tag_dots(hdc, &state.tags, item.path(), current_x + TEXT_OFFSET + 2, y + state.item_height / 2);
+                    
+                    // Create clipping rect for text (offset by icon and dot space)
                     let column_rect = RECT {
-                        left: current_x + TEXT_OFFSET + 2,
+                        left: current_x + TEXT_OFFSET + 2 + dots_width,
                         top: y,
                         right: current_x + column.width - 2,
                         bottom: y + state.item_height,
@@ -2894,8 +9644,11 @@ fn paint_details_view(hdc: HDC, client_rect: &RECT, state: &AppState, has_focus:
                     if !text.is_empty() {
                         let mut text_utf16: Vec<u16> = text.encode_utf16().collect();
                         let mut text_rect = column_rect;
-                        DrawTextW(hdc, &mut text_utf16, &mut text_rect, DT_LEFT | DT_VCENTER | DT_SINGLELINE | DT_END_ELLIPSIS);
+                        DrawTextW(hdc, &mut text_utf16, &mut text_rect, align | DT_VCENTER | DT_SINGLELINE | DT_END_ELLIPSIS);
                     }
+                } else if column.column_type == ColumnType::Rating {
+                    let rating = state.tags.rating_of(item.path());
+                    draw_rating_stars(hdc, current_x, y, column.width, state.item_height, rating);
                 } else {
                     // For other columns, normal text rendering
                     let column_rect = RECT {
@@ -2905,21 +9658,65 @@ fn paint_details_view(hdc: HDC, client_rect: &RECT, state: &AppState, has_focus:
                         bottom: y + state.item_height,
                     };
                     
+                    // Paths can be cut in the middle so their last folder stays visible
+                    let ellipsis = if column.column_type == ColumnType::Path && state.config.path_ellipsis == PathEllipsis::Middle {
+                        DT_PATH_ELLIPSIS
+                    } else {
+                        DT_END_ELLIPSIS
+                    };
+                    
                     // Draw text with clipping and ellipsis
                     if !text.is_empty() {
                         let mut text_utf16: Vec<u16> = text.encode_utf16().collect();
                         let mut text_rect = column_rect;
-                        DrawTextW(hdc, &mut text_utf16, &mut text_rect, DT_LEFT | DT_VCENTER | DT_SINGLELINE | DT_END_ELLIPSIS);
+                        DrawTextW(hdc, &mut text_utf16, &mut text_rect, align | DT_VCENTER | DT_SINGLELINE | ellipsis);
                     }
                 }
-                
-                current_x += column.width;
+            }
+            
+            // Marks the row the arrow keys and the Menu key act on
+            if has_focus && state.selected_index == Some(item_index) {
+                DrawFocusRect(hdc, &item_rect);
             }
         }
+        
+        // Edge of the frozen Name column while others scroll under it
+        if frozen > 0 && state.h_scroll_pos > 0 {
+            let edge_pen = CreatePen(PS_SOLID, 1, COLORREF(0x00C0C0C0));
+            let old_pen = SelectObject(hdc, edge_pen);
+            MoveToEx(hdc, frozen, HEADER_HEIGHT, None);
+            LineTo(hdc, frozen, client_rect.bottom);
+            SelectObject(hdc, old_pen);
+            DeleteObject(edge_pen);
+        }
+    }
+}
+
+// The header row of a drive group: an arrow showing whether it is open,
+// the volume and how many results it has and their total size
+fn draw_drive_header(hdc: HDC, rect: &RECT, group: &DriveGroup, state: &AppState, strings: &LanguageStrings) {
+    unsafe {
+        let header_brush = CreateSolidBrush(COLORREF(0x00EEE8E4));
+        FillRect(hdc, rect, header_brush);
+        DeleteObject(header_brush);
+        
+        let label = if group.label.is_empty() { strings.drive_group_other.clone() } else { group.label.clone() };
+        let text = fill(&strings.drive_group_header, &[
+            ("drive", &label),
+            ("count", &group.len.to_string()),
+            ("size", &format_size(group.total_size, state.config.size_format, strings)),
+        ]);
+        let arrow = if group.collapsed { "\u{25B6}" } else { "\u{25BC}" };
+        
+        SelectObject(hdc, state.font);
+        SetTextColor(hdc, COLORREF(0x00603010));
+        let mut text_utf16: Vec<u16> = format!("{}  {}", arrow, text).encode_utf16().collect();
+        let mut text_rect = RECT { left: rect.left + 6, ..*rect };
+        DrawTextW(hdc, &mut text_utf16, &mut text_rect, DT_LEFT | DT_VCENTER | DT_SINGLELINE | DT_END_ELLIPSIS);
     }
 }
 
-fn paint_icon_view(hdc: HDC, client_rect: &RECT, state: &AppState, has_focus: bool) {
+fn paint_icon_view(hdc: HDC, paint_rect: &RECT, state: &AppState, has_focus: bool) {
     unsafe {
         if state.grid_cols <= 0 || state.cell_size <= 0 {
             return;
@@ -2927,6 +9724,8 @@ fn paint_icon_view(hdc: HDC, client_rect: &RECT, state: &AppState, has_focus: bo
         
         let first_visible_row = state.scroll_pos / state.cell_size;
         let visible_rows = (state.client_height / state.cell_size) + 2;
+        let caption = state.config.thumbnail_caption;
+        let strings = get_strings();
         
         for row in first_visible_row..first_visible_row + visible_rows {
             for col in 0..state.grid_cols {
@@ -2951,8 +9750,13 @@ fn paint_icon_view(hdc: HDC, client_rect: &RECT, state: &AppState, has_focus: bo
                     bottom: y + state.cell_size,
                 };
                 
+                // Cells outside the invalidated area are left as they are
+                if !rects_intersect(&cell_rect, paint_rect) {
+                    continue;
+                }
+                
                 // Draw selection highlight
-                if Some(item_index) == state.selected_index {
+                if state.is_selected(item_index) {
                     let selection_color = if has_focus {
                         COLORREF(0x00316AC5)
                     } else {
@@ -2963,13 +9767,20 @@ fn paint_icon_view(hdc: HDC, client_rect: &RECT, state: &AppState, has_focus: bo
                     DeleteObject(selection_brush);
                 }
                 
+                if item.is_placeholder() {
+                    continue;
+                }
+                
                 // Draw thumbnail or placeholder
                 let thumbnail_size = state.selected_view_size;
                 let thumbnail_x = x + (state.cell_size - thumbnail_size as i32) / 2;
-                let thumbnail_y = y + 4;
+                // Without a caption the thumbnail sits in the middle of its cell
+                let thumbnail_y = if caption == ThumbnailCaption::Hidden { y + (state.cell_size - thumbnail_size as i32) / 2 } else { y + 4 };
                 
-                let cache_key = (item.path.clone(), thumbnail_size);
-                if let Some(&cached_bitmap) = state.thumbnail_cache.peek(&cache_key) {
+                let cache_key = (item.path().to_string(), thumbnail_size);
+                let cached = state.thumbnail_cache.peek(&cache_key).copied();
+                THUMBNAIL_LOOKUPS.record(cached.is_some());
+                if let Some(cached_bitmap) = cached {
                     // Draw cached thumbnail
                     draw_bitmap(hdc, cached_bitmap, thumbnail_x, thumbnail_y, thumbnail_size as i32);
                 } else {
@@ -2979,241 +9790,559 @@ fn paint_icon_view(hdc: HDC, client_rect: &RECT, state: &AppState, has_focus: bo
                     DeleteObject(placeholder);
                 }
                 
-                // Draw filename below thumbnail
-                let text_y = thumbnail_y + thumbnail_size as i32 + 4;
-                let text_rect = RECT {
-                    left: x + 2,
-                    top: text_y,
-                    right: x + state.cell_size - 2,
-                    bottom: y + state.cell_size - 2,
-                };
+                // Tag dots in the thumbnail's top left corner
+                draw_tag_dots(hdc, &state.tags, item.path(), thumbnail_x + 4, thumbnail_y + 4 + TAG_DOT_SIZE / 2);
+                if is_offline(item.path()) {
+                    draw_offline_badge(hdc, thumbnail_x + thumbnail_size as i32 - 2, thumbnail_y + thumbnail_size as i32 - 2);
+                }
                 
-                SetTextColor(hdc, if Some(item_index) == state.selected_index && has_focus {
-                    COLORREF(0x00FFFFFF)
-                } else {
-                    COLORREF(0x00000000)
-                });
+                if caption != ThumbnailCaption::Hidden {
+                    draw_thumbnail_caption(hdc, state, item_index, &cell_rect, thumbnail_y + thumbnail_size as i32 + 4, has_focus, &strings);
+                }
                 
-                let mut name_utf16: Vec<u16> = item.name.encode_utf16().collect();
-                let mut text_rect = text_rect;
-                DrawTextW(hdc, &mut name_utf16, &mut text_rect, DT_CENTER | DT_WORDBREAK | DT_END_ELLIPSIS);
+                if has_focus && state.selected_index == Some(item_index) {
+                    DrawFocusRect(hdc, &cell_rect);
+                }
+            }
+        }
+    }
+}
+
+// Draws the file name under a thumbnail from `text_y`, and its size or
+// dimensions on the cell's last line when the caption has them
+fn draw_thumbnail_caption(hdc: HDC, state: &AppState, item_index: usize, cell_rect: &RECT, text_y: i32, has_focus: bool, strings: &LanguageStrings) {
+    let item = &state.list_data[item_index];
+    unsafe {
+        let selected = state.is_selected(item_index) && has_focus;
+        SetTextColor(hdc, if selected {
+            COLORREF(0x00FFFFFF)
+        } else if item.missing {
+            COLORREF(0x00A0A0A0)
+        } else {
+            COLORREF(0x00000000)
+        });
+        SelectObject(hdc, if item.missing { state.strikeout_font } else { state.font });
+        
+        let detail = match state.config.thumbnail_caption {
+            ThumbnailCaption::NameAndSize => Some(item.format_size(state.config.size_format, strings)),
+            ThumbnailCaption::NameAndDimensions => Some(state.media_infos
+                .peek(item.path())
+                .and_then(|info| info.as_ref())
+                .map(|info| info.format_dimensions())
+                .unwrap_or_default()),
+            _ => None,
+        };
+        let name_bottom = cell_rect.bottom - 2 - if detail.is_some() { CAPTION_LINE_HEIGHT } else { 0 };
+        
+        let mut name_utf16: Vec<u16> = item.name().encode_utf16().collect();
+        let mut name_rect = RECT { left: cell_rect.left + 2, top: text_y, right: cell_rect.right - 2, bottom: name_bottom };
+        DrawTextW(hdc, &mut name_utf16, &mut name_rect, DT_CENTER | DT_WORDBREAK | DT_END_ELLIPSIS);
+        
+        if let Some(detail) = detail.filter(|detail| !detail.is_empty()) {
+            if !selected {
+                SetTextColor(hdc, COLORREF(0x00808080));
+            }
+            SelectObject(hdc, state.font);
+            let mut detail_utf16: Vec<u16> = detail.encode_utf16().collect();
+            let mut detail_rect = RECT { left: cell_rect.left + 2, top: name_bottom, right: cell_rect.right - 2, bottom: cell_rect.bottom - 2 };
+            DrawTextW(hdc, &mut detail_utf16, &mut detail_rect, DT_CENTER | DT_SINGLELINE | DT_END_ELLIPSIS);
+        }
+    }
+}
+
+// Height of each line of text under a thumbnail
+const CAPTION_LINE_HEIGHT: i32 = 20;
+// Size of the colored dot drawn for each tag of a file
+const TAG_DOT_SIZE: i32 = 8;
+// Width of each star of the Rating column, and the space before the first
+const RATING_STAR_WIDTH: i32 = 14;
+const RATING_STAR_LEFT: i32 = 4;
+const OFFLINE_BADGE_SIZE: i32 = 10;
+
+// Draws a dot per tag of `path` from `x`, centered on `center_y`, with a
+// white rim so they show on thumbnails too. Returns the width taken.
+fn draw_tag_dots(hdc: HDC, tags: &TagStore, path: &str, x: i32, center_y: i32) -> i32 {
+    let names = tags.tags_of(path);
+    if names.is_empty() {
+        return 0;
+    }
+    
+    unsafe {
+        let pen = CreatePen(PS_SOLID, 1, COLORREF(0x00FFFFFF));
+        let old_pen = SelectObject(hdc, pen);
+        for (index, name) in names.iter().enumerate() {
+            let brush = CreateSolidBrush(COLORREF(tags.color_of(name)));
+            let old_brush = SelectObject(hdc, brush);
+            let left = x + index as i32 * (TAG_DOT_SIZE + 2);
+            Ellipse(hdc, left, center_y - TAG_DOT_SIZE / 2, left + TAG_DOT_SIZE, center_y + TAG_DOT_SIZE / 2);
+            SelectObject(hdc, old_brush);
+            DeleteObject(brush);
+        }
+        SelectObject(hdc, old_pen);
+        DeleteObject(pen);
+    }
+    names.len() as i32 * (TAG_DOT_SIZE + 2)
+}
+
+// The stars of the Rating column, from the left edge of the cell at
+// `left`, `top`. The ones not given are drawn faintly so they can be clicked.
+fn draw_rating_stars(hdc: HDC, left: i32, top: i32, width: i32, height: i32, rating: u8) {
+    unsafe {
+        let old_color = GetTextColor(hdc);
+        let mut star: Vec<u16> = "★".encode_utf16().collect();
+        for index in 0..MAX_RATING {
+            let star_left = left + RATING_STAR_LEFT + index as i32 * RATING_STAR_WIDTH;
+            if star_left + RATING_STAR_WIDTH > left + width {
+                break;
+            }
+            SetTextColor(hdc, if index < rating { COLORREF(0x0000B4FF) } else { COLORREF(0x00D0D0D0) });
+            let mut star_rect = RECT { left: star_left, top, right: star_left + RATING_STAR_WIDTH, bottom: top + height };
+            DrawTextW(hdc, &mut star, &mut star_rect, DT_CENTER | DT_VCENTER | DT_SINGLELINE);
+        }
+        SetTextColor(hdc, old_color);
+    }
+}
+
+// Red dot with a white bar marking files on a share that didn't answer,
+// drawn with its bottom right corner at (right, bottom)
+fn draw_offline_badge(hdc: HDC, right: i32, bottom: i32) {
+    unsafe {
+        let pen = CreatePen(PS_SOLID, 1, COLORREF(0x00FFFFFF));
+        let brush = CreateSolidBrush(COLORREF(0x002020D0));
+        let old_pen = SelectObject(hdc, pen);
+        let old_brush = SelectObject(hdc, brush);
+        let (left, top) = (right - OFFLINE_BADGE_SIZE, bottom - OFFLINE_BADGE_SIZE);
+        Ellipse(hdc, left, top, right, bottom);
+        let middle = top + OFFLINE_BADGE_SIZE / 2;
+        let _ = MoveToEx(hdc, left + 3, middle, None);
+        let _ = LineTo(hdc, right - 3, middle);
+        SelectObject(hdc, old_brush);
+        SelectObject(hdc, old_pen);
+        DeleteObject(brush);
+        DeleteObject(pen);
+    }
+}
+
+fn draw_bitmap(hdc: HDC, bitmap: HBITMAP, x: i32, y: i32, size: i32) {
+    unsafe {
+        let bitmap_dc = CreateCompatibleDC(hdc);
+        let old_bitmap = SelectObject(bitmap_dc, bitmap);
+        
+        let _ = BitBlt(hdc, x, y, size, size, bitmap_dc, 0, 0, SRCCOPY);
+        
+        SelectObject(bitmap_dc, old_bitmap);
+        DeleteDC(bitmap_dc);
+    }
+}
+
+fn update_scrollbar(state: &AppState) {
+    unsafe {
+        log_trace("update_scrollbar called");
+        
+        log_trace(&format!("Setting scrollbar info: total_height={}, client_height={}, scroll_pos={}", 
+            state.total_height, state.client_height, state.scroll_pos));
+        
+        // Calculate the maximum scroll position
+        let max_scroll = (state.total_height - state.client_height).max(0);
+        
+        // Use a fixed scrollbar range (0-10000) for better Windows compatibility
+        const SCROLLBAR_RANGE: i32 = 10000;
+        let scrollbar_pos = if max_scroll > 0 {
+            ((state.scroll_pos as f64 / max_scroll as f64) * SCROLLBAR_RANGE as f64) as i32
+        } else {
+            0
+        };
+        
+        let scrollbar_page = if max_scroll > 0 {
+            ((state.client_height as f64 / state.total_height as f64) * SCROLLBAR_RANGE as f64) as u32
+        } else {
+            SCROLLBAR_RANGE as u32
+        };
+        
+        log_trace(&format!("Scrollbar mapping: actual_pos={}, scrollbar_pos={}, max_scroll={}, scrollbar_page={}", 
+            state.scroll_pos, scrollbar_pos, max_scroll, scrollbar_page));
+        
+        let si = SCROLLINFO {
+            cbSize: std::mem::size_of::<SCROLLINFO>() as u32,
+            fMask: SIF_RANGE | SIF_PAGE | SIF_POS,
+            nMin: 0,
+            nMax: SCROLLBAR_RANGE,
+            nPage: scrollbar_page.max(1),
+            nPos: scrollbar_pos.clamp(0, SCROLLBAR_RANGE),
+            nTrackPos: 0,
+        };
+        
+        SetScrollInfo(state.list_view, SB_VERT, &si, TRUE);
+        log_trace(&format!("Scrollbar updated: nMax={}, nPage={}, nPos={}", si.nMax, si.nPage, si.nPos));
+    }
+}
+
+fn handle_vertical_scroll(window: HWND, state: &mut AppState, request: u16, pos: i16) {
+    unsafe {
+        log_trace(&format!("handle_vertical_scroll called: request={}, pos={}, current_scroll_pos={}", 
+            request, pos, state.scroll_pos));
+            
+        let old_pos = state.scroll_pos;
+        let scroll_unit = match state.view_mode {
+            ViewMode::Details => state.item_height,
+            _ => state.cell_size,
+        };
+        
+        match request {
+            0 => {
+                log_trace("SB_LINEUP");
+                state.scroll_pos -= scroll_unit;
+            }
+            1 => {
+                log_trace("SB_LINEDOWN");
+                state.scroll_pos += scroll_unit;
+            }
+            2 => {
+                log_trace("SB_PAGEUP");
+                state.scroll_pos -= state.client_height;
+            }
+            3 => {
+                log_trace("SB_PAGEDOWN");
+                state.scroll_pos += state.client_height;
+            }
+            4 => { // SB_THUMBTRACK - user is dragging
+                // Check for Windows scrollbar position overflow (16-bit signed integer overflow)
+                if pos < 0 {
+                    log_trace(&format!("SB_THUMBTRACK: ignoring negative position {} (16-bit overflow), keeping current position {}", 
+                        pos, state.scroll_pos));
+                    // Keep current position, don't update
+                } else {
+                    log_trace(&format!("SB_THUMBTRACK: setting is_scrollbar_dragging=true, converting scrollbar_pos {} to actual position", pos));
+                    state.is_scrollbar_dragging = true;
+                    
+                    // Convert scrollbar position to actual scroll position
+                    const SCROLLBAR_RANGE: i32 = 10000;
+                    let max_scroll = (state.total_height - state.client_height).max(0);
+                    let actual_pos = if max_scroll > 0 && SCROLLBAR_RANGE > 0 {
+                        ((pos as f64 / SCROLLBAR_RANGE as f64) * max_scroll as f64) as i32
+                    } else {
+                        0
+                    };
+                    
+                    log_trace(&format!("SB_THUMBTRACK: scrollbar_pos={}, actual_pos={}, max_scroll={}", pos, actual_pos, max_scroll));
+                    state.scroll_pos = actual_pos;
+                }
+            }
+            5 => { // SB_THUMBPOSITION - user released drag
+                // Check for Windows scrollbar position overflow (16-bit signed integer overflow)
+                if pos < 0 {
+                    log_trace(&format!("SB_THUMBPOSITION: ignoring negative position {} (16-bit overflow), keeping current position {}", 
+                        pos, state.scroll_pos));
+                    // Keep current position, just set dragging to false
+                    state.is_scrollbar_dragging = false;
+                } else {
+                    log_trace(&format!("SB_THUMBPOSITION: setting is_scrollbar_dragging=false, converting scrollbar_pos {} to actual position", pos));
+                    state.is_scrollbar_dragging = false;
+                    
+                    // Convert scrollbar position to actual scroll position
+                    const SCROLLBAR_RANGE: i32 = 10000;
+                    let max_scroll = (state.total_height - state.client_height).max(0);
+                    let actual_pos = if max_scroll > 0 && SCROLLBAR_RANGE > 0 {
+                        ((pos as f64 / SCROLLBAR_RANGE as f64) * max_scroll as f64) as i32
+                    } else {
+                        0
+                    };
+                    
+                    log_trace(&format!("SB_THUMBPOSITION: scrollbar_pos={}, actual_pos={}, max_scroll={}", pos, actual_pos, max_scroll));
+                    state.scroll_pos = actual_pos;
+                }
+            }
+            6 => {
+                log_trace("SB_TOP");
+                state.scroll_pos = 0;
             }
+            7 => {
+                log_trace("SB_BOTTOM");
+                state.scroll_pos = state.total_height - state.client_height;
+            }
+            8 => {
+                log_trace("SB_ENDSCROLL: setting is_scrollbar_dragging=false");
+                // SB_ENDSCROLL - dragging ended, update scrollbar to synchronize
+                state.is_scrollbar_dragging = false;
+                update_scrollbar(state);
+                return;
+            }
+            _ => {
+                log_trace(&format!("Unknown scroll request: {}", request));
+                return;
+            }
+        }
+        
+        state.scroll_pos = state.scroll_pos.max(0).min(state.total_height - state.client_height);
+        log_trace(&format!("Clamped scroll_pos to: {}", state.scroll_pos));
+        
+        if state.scroll_pos != old_pos {
+            log_trace(&format!("Scroll position changed from {} to {}", old_pos, state.scroll_pos));
+            
+            // Only do minimal updates during dragging
+            if state.is_scrollbar_dragging {
+                log_trace("During dragging: minimal update (no scrollbar updates, no thumbnails)");
+                // During drag: only update visible range, no scrollbar updates, no thumbnails
+                state.calculate_layout();
+                scroll_view_contents(state, old_pos);
+            } else {
+                log_trace("Normal scrolling: full update");
+                // Normal scrolling: full update
+            state.calculate_layout();
+            update_scrollbar(state);
+            scroll_view_contents(state, old_pos);
+            
+            // Post message to recompute thumbnails
+            let _ = PostMessageW(GetParent(window), WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
+        }
+        } else {
+            log_trace("No scroll position change detected");
         }
+        
+        log_trace(&format!("handle_vertical_scroll completed: final_scroll_pos={}, is_dragging={}", 
+            state.scroll_pos, state.is_scrollbar_dragging));
     }
 }
 
-fn draw_bitmap(hdc: HDC, bitmap: HBITMAP, x: i32, y: i32, size: i32) {
+// Pixels the details columns move per scroll arrow click or wheel notch
+const HORIZONTAL_SCROLL_STEP: i32 = 40;
+
+// Shows the horizontal scrollbar while the details columns are wider than
+// the list, and keeps the scroll position within them. Only touches the
+// scrollbar when its range changed, since showing or hiding it resizes the
+// list.
+fn sync_horizontal_scrollbar(state: &mut AppState) {
+    let total = if state.view_mode == ViewMode::Details { state.columns_width() } else { 0 };
+    let max_scroll = (total - state.client_width).max(0);
+    let clamped = state.h_scroll_pos.clamp(0, max_scroll);
+    let range = (total, state.client_width);
+    if clamped == state.h_scroll_pos && range == state.h_scroll_range {
+        return;
+    }
+    state.h_scroll_pos = clamped;
+    state.h_scroll_range = range;
+    
+    let si = SCROLLINFO {
+        cbSize: std::mem::size_of::<SCROLLINFO>() as u32,
+        fMask: SIF_RANGE | SIF_PAGE | SIF_POS,
+        nMin: 0,
+        nMax: if max_scroll > 0 { total - 1 } else { 0 },
+        nPage: if max_scroll > 0 { state.client_width.max(1) as u32 } else { 0 },
+        nPos: clamped,
+        nTrackPos: 0,
+    };
     unsafe {
-        let bitmap_dc = CreateCompatibleDC(hdc);
-        let old_bitmap = SelectObject(bitmap_dc, bitmap);
-        
-        let _ = BitBlt(hdc, x, y, size, size, bitmap_dc, 0, 0, SRCCOPY);
-        
-        SelectObject(bitmap_dc, old_bitmap);
-        DeleteDC(bitmap_dc);
+        SetScrollInfo(state.list_view, SB_HORZ, &si, TRUE);
     }
 }
 
-fn update_scrollbar(window: HWND) {
-    unsafe {
-        log_debug("update_scrollbar called");
-        
-        if let Some(state) = &APP_STATE {
-            log_debug(&format!("Setting scrollbar info: total_height={}, client_height={}, scroll_pos={}", 
-                state.total_height, state.client_height, state.scroll_pos));
-            
-            // Calculate the maximum scroll position
-            let max_scroll = (state.total_height - state.client_height).max(0);
-            
-            // Use a fixed scrollbar range (0-10000) for better Windows compatibility
-            const SCROLLBAR_RANGE: i32 = 10000;
-            let scrollbar_pos = if max_scroll > 0 {
-                ((state.scroll_pos as f64 / max_scroll as f64) * SCROLLBAR_RANGE as f64) as i32
-            } else {
-                0
-            };
-            
-            let scrollbar_page = if max_scroll > 0 {
-                ((state.client_height as f64 / state.total_height as f64) * SCROLLBAR_RANGE as f64) as u32
-            } else {
-                SCROLLBAR_RANGE as u32
-            };
-            
-            log_debug(&format!("Scrollbar mapping: actual_pos={}, scrollbar_pos={}, max_scroll={}, scrollbar_page={}", 
-                state.scroll_pos, scrollbar_pos, max_scroll, scrollbar_page));
-            
-            let si = SCROLLINFO {
+fn handle_horizontal_scroll(window: HWND, state: &mut AppState, request: i32) {
+    let delta = match SCROLLBAR_COMMAND(request) {
+        SB_LINELEFT => -HORIZONTAL_SCROLL_STEP,
+        SB_LINERIGHT => HORIZONTAL_SCROLL_STEP,
+        SB_PAGELEFT => -state.client_width,
+        SB_PAGERIGHT => state.client_width,
+        SB_LEFT => -state.h_scroll_pos,
+        SB_RIGHT => i32::MAX / 2,
+        SB_THUMBTRACK | SB_THUMBPOSITION => {
+            // The 32-bit position; the one in WPARAM is cut to 16 bits
+            let mut si = SCROLLINFO {
                 cbSize: std::mem::size_of::<SCROLLINFO>() as u32,
-                fMask: SIF_RANGE | SIF_PAGE | SIF_POS,
-                nMin: 0,
-                nMax: SCROLLBAR_RANGE,
-                nPage: scrollbar_page.max(1),
-                nPos: scrollbar_pos.max(0).min(SCROLLBAR_RANGE),
-                nTrackPos: 0,
+                fMask: SIF_TRACKPOS,
+                ..Default::default()
             };
-            
-            SetScrollInfo(window, SB_VERT, &si, TRUE);
-            log_debug(&format!("Scrollbar updated: nMax={}, nPage={}, nPos={}", si.nMax, si.nPage, si.nPos));
-        } else {
-            log_debug("WARNING: update_scrollbar called but APP_STATE is None");
+            unsafe {
+                let _ = GetScrollInfo(window, SB_HORZ, &mut si);
+            }
+            si.nTrackPos - state.h_scroll_pos
         }
+        _ => return,
+    };
+    scroll_columns(window, state, delta);
+}
+
+// Moves the details columns sideways by `delta` pixels
+fn scroll_columns(window: HWND, state: &mut AppState, delta: i32) {
+    if state.view_mode != ViewMode::Details {
+        return;
+    }
+    let max_scroll = (state.columns_width() - state.client_width).max(0);
+    let new_pos = state.h_scroll_pos.saturating_add(delta).clamp(0, max_scroll);
+    if new_pos == state.h_scroll_pos {
+        return;
+    }
+    state.h_scroll_pos = new_pos;
+    unsafe {
+        SetScrollPos(window, SB_HORZ, new_pos, TRUE);
+        InvalidateRect(window, None, FALSE);
     }
 }
 
-fn handle_vertical_scroll(window: HWND, request: u16, pos: i16) {
+fn scroll_list(window: HWND, state: &mut AppState, lines: i32) {
     unsafe {
-        if let Some(state) = &mut APP_STATE {
-            log_debug(&format!("handle_vertical_scroll called: request={}, pos={}, current_scroll_pos={}", 
-                request, pos, state.scroll_pos));
-                
-            let old_pos = state.scroll_pos;
-            let scroll_unit = match state.view_mode {
-                ViewMode::Details => state.item_height,
-                _ => state.cell_size,
-            };
-            
-            match request {
-                0 => {
-                    log_debug("SB_LINEUP");
-                    state.scroll_pos -= scroll_unit;
-                }
-                1 => {
-                    log_debug("SB_LINEDOWN");
-                    state.scroll_pos += scroll_unit;
-                }
-                2 => {
-                    log_debug("SB_PAGEUP");
-                    state.scroll_pos -= state.client_height;
-                }
-                3 => {
-                    log_debug("SB_PAGEDOWN");
-                    state.scroll_pos += state.client_height;
-                }
-                4 => { // SB_THUMBTRACK - user is dragging
-                    // Check for Windows scrollbar position overflow (16-bit signed integer overflow)
-                    if pos < 0 {
-                        log_debug(&format!("SB_THUMBTRACK: ignoring negative position {} (16-bit overflow), keeping current position {}", 
-                            pos, state.scroll_pos));
-                        // Keep current position, don't update
-                    } else {
-                        log_debug(&format!("SB_THUMBTRACK: setting is_scrollbar_dragging=true, converting scrollbar_pos {} to actual position", pos));
-                        state.is_scrollbar_dragging = true;
-                        
-                        // Convert scrollbar position to actual scroll position
-                        const SCROLLBAR_RANGE: i32 = 10000;
-                        let max_scroll = (state.total_height - state.client_height).max(0);
-                        let actual_pos = if max_scroll > 0 && SCROLLBAR_RANGE > 0 {
-                            ((pos as f64 / SCROLLBAR_RANGE as f64) * max_scroll as f64) as i32
-                        } else {
-                            0
-                        };
-                        
-                        log_debug(&format!("SB_THUMBTRACK: scrollbar_pos={}, actual_pos={}, max_scroll={}", pos, actual_pos, max_scroll));
-                        state.scroll_pos = actual_pos;
-                    }
-                }
-                5 => { // SB_THUMBPOSITION - user released drag
-                    // Check for Windows scrollbar position overflow (16-bit signed integer overflow)
-                    if pos < 0 {
-                        log_debug(&format!("SB_THUMBPOSITION: ignoring negative position {} (16-bit overflow), keeping current position {}", 
-                            pos, state.scroll_pos));
-                        // Keep current position, just set dragging to false
-                        state.is_scrollbar_dragging = false;
-                    } else {
-                        log_debug(&format!("SB_THUMBPOSITION: setting is_scrollbar_dragging=false, converting scrollbar_pos {} to actual position", pos));
-                        state.is_scrollbar_dragging = false;
-                        
-                        // Convert scrollbar position to actual scroll position
-                        const SCROLLBAR_RANGE: i32 = 10000;
-                        let max_scroll = (state.total_height - state.client_height).max(0);
-                        let actual_pos = if max_scroll > 0 && SCROLLBAR_RANGE > 0 {
-                            ((pos as f64 / SCROLLBAR_RANGE as f64) * max_scroll as f64) as i32
-                        } else {
-                            0
-                        };
-                        
-                        log_debug(&format!("SB_THUMBPOSITION: scrollbar_pos={}, actual_pos={}, max_scroll={}", pos, actual_pos, max_scroll));
-                        state.scroll_pos = actual_pos;
-                    }
-                }
-                6 => {
-                    log_debug("SB_TOP");
-                    state.scroll_pos = 0;
-                }
-                7 => {
-                    log_debug("SB_BOTTOM");
-                    state.scroll_pos = state.total_height - state.client_height;
-                }
-                8 => {
-                    log_debug("SB_ENDSCROLL: setting is_scrollbar_dragging=false");
-                    // SB_ENDSCROLL - dragging ended, update scrollbar to synchronize
-                    state.is_scrollbar_dragging = false;
-                    update_scrollbar(window);
-                    return;
-                }
-                _ => {
-                    log_debug(&format!("Unknown scroll request: {}", request));
-                    return;
-                }
-            }
+        let old_pos = state.scroll_pos;
+        let scroll_unit = match state.view_mode {
+            ViewMode::Details => state.item_height,
+            _ => state.cell_size,
+        };
+        
+        state.scroll_pos += lines * scroll_unit;
+        state.scroll_pos = state.scroll_pos.max(0).min(state.total_height - state.client_height);
+        
+        if state.scroll_pos != old_pos {
+            state.calculate_layout();
+            update_scrollbar(state);
+            scroll_view_contents(state, old_pos);
             
-            state.scroll_pos = state.scroll_pos.max(0).min(state.total_height - state.client_height);
-            log_debug(&format!("Clamped scroll_pos to: {}", state.scroll_pos));
+            // Post message to recompute thumbnails
+            let _ = PostMessageW(GetParent(window), WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
+        }
+    }
+} 
+
+// Moves what is already on screen by the change in scroll position and
+// invalidates only the strip that scrolled into view. The details header
+// stays where it is.
+fn scroll_view_contents(state: &AppState, old_scroll_pos: i32) {
+    unsafe {
+        let delta = old_scroll_pos - state.scroll_pos;
+        let content_rect = RECT {
+            left: 0,
+            top: if state.view_mode == ViewMode::Details { HEADER_HEIGHT } else { 0 },
+            right: state.client_width,
+            bottom: state.client_height,
+        };
+        
+        if delta.abs() >= content_rect.bottom - content_rect.top {
+            InvalidateRect(state.list_view, Some(&content_rect), FALSE);
+        } else {
+            ScrollWindowEx(
+                state.list_view,
+                0,
+                delta,
+                Some(&content_rect),
+                Some(&content_rect),
+                None,
+                None,
+                SW_INVALIDATE,
+            );
             
-            if state.scroll_pos != old_pos {
-                log_debug(&format!("Scroll position changed from {} to {}", old_pos, state.scroll_pos));
-                
-                // Only do minimal updates during dragging
-                if state.is_scrollbar_dragging {
-                    log_debug("During dragging: minimal update (no scrollbar updates, no thumbnails)");
-                    // During drag: only update visible range, no scrollbar updates, no thumbnails
-                    state.calculate_layout();
-                    InvalidateRect(window, None, TRUE);
-                } else {
-                    log_debug("Normal scrolling: full update");
-                    // Normal scrolling: full update
-                state.calculate_layout();
-                update_scrollbar(window);
-                InvalidateRect(window, None, TRUE);
-                
-                // Post message to recompute thumbnails
-                let _ = PostMessageW(GetParent(window), WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
+            // The overlay was scrolled along with the rows
+            if state.perf_hud_visible {
+                let hud_rect = perf_hud_rect(state);
+                let moved_rect = RECT { top: hud_rect.top + delta, bottom: hud_rect.bottom + delta, ..hud_rect };
+                InvalidateRect(state.list_view, Some(&hud_rect), FALSE);
+                InvalidateRect(state.list_view, Some(&moved_rect), FALSE);
             }
-            } else {
-                log_debug("No scroll position change detected");
-            }
-            
-            log_debug(&format!("handle_vertical_scroll completed: final_scroll_pos={}, is_dragging={}", 
-                state.scroll_pos, state.is_scrollbar_dragging));
+        }
+    }
+}
+
+fn rects_intersect(a: &RECT, b: &RECT) -> bool {
+    a.left < b.right && b.left < a.right && a.top < b.bottom && b.top < a.bottom
+}
+
+// Ctrl+Shift+L opens or closes the diagnostics log window, which has no menu entry
+unsafe fn is_log_window_shortcut(wparam: WPARAM) -> bool {
+    wparam.0 == 0x4C // 'L'
+        && GetKeyState(VK_CONTROL.0 as i32) < 0
+        && GetKeyState(VK_SHIFT.0 as i32) < 0
+}
+
+// Ctrl+F or F6 puts the focus in the search box from anywhere
+unsafe fn is_focus_search_shortcut(wparam: WPARAM) -> bool {
+    (wparam.0 == 0x46 && GetKeyState(VK_CONTROL.0 as i32) < 0 && GetKeyState(VK_SHIFT.0 as i32) >= 0) // Ctrl+F
+        || wparam.0 == 0x75 // VK_F6
+}
+
+// Esc clears the query, or puts the window away once the query is empty:
+// to the notification area when closing does, minimized otherwise
+fn clear_search_or_hide(state: &mut AppState) {
+    unsafe {
+        if GetWindowTextLengthW(state.search_edit) > 0 {
+            // EN_CHANGE runs the empty search, or unfilters an open list
+            let _ = SetWindowTextW(state.search_edit, w!(""));
+            SetFocus(state.search_edit);
+        } else if state.config.close_to_tray {
+            hide_to_tray(state.main_window, state);
+        } else {
+            ShowWindow(state.main_window, SW_MINIMIZE);
+        }
+    }
+}
+
+// Ctrl+Shift+P toggles the performance HUD, which is also undocumented
+unsafe fn is_perf_hud_shortcut(wparam: WPARAM) -> bool {
+    wparam.0 == 0x50 // 'P'
+        && GetKeyState(VK_CONTROL.0 as i32) < 0
+        && GetKeyState(VK_SHIFT.0 as i32) < 0
+}
+
+fn toggle_perf_hud(state: &mut AppState) {
+    state.perf_hud_visible = !state.perf_hud_visible;
+    log_info(&format!("Performance HUD {}", if state.perf_hud_visible { "shown" } else { "hidden" }));
+    
+    unsafe {
+        if state.perf_hud_visible {
+            SetTimer(state.main_window, PERF_HUD_TIMER_ID, 500, None);
         } else {
-            log_debug("ERROR: handle_vertical_scroll called but APP_STATE is None");
+            let _ = KillTimer(state.main_window, PERF_HUD_TIMER_ID);
         }
+        InvalidateRect(state.list_view, Some(&perf_hud_rect(state)), FALSE);
+    }
+}
+
+fn perf_hud_rect(state: &AppState) -> RECT {
+    let top = if state.view_mode == ViewMode::Details { HEADER_HEIGHT } else { 0 } + PERF_HUD_MARGIN;
+    RECT {
+        left: state.client_width - PERF_HUD_WIDTH - PERF_HUD_MARGIN,
+        top,
+        right: state.client_width - PERF_HUD_MARGIN,
+        bottom: top + PERF_HUD_LINES * PERF_HUD_LINE_HEIGHT + PERF_HUD_MARGIN,
     }
 }
 
-fn scroll_list(window: HWND, lines: i32) {
+fn paint_perf_hud(hdc: HDC, state: &AppState) {
+    let mut queues = vec![
+        ("thumbnails", state.thumbnail_task_manager.as_ref().map_or(0, |manager| manager.get_queued_count())),
+        ("icons", pending_icon_count()),
+        ("metadata", state.metadata_loader.as_ref().map_or(0, |loader| loader.pending())),
+        ("git", state.git_status_loader.as_ref().map_or(0, |loader| loader.pending())),
+        ("links", state.link_info_loader.as_ref().map_or(0, |loader| loader.pending())),
+        ("media", state.media_info_loader.as_ref().map_or(0, |loader| loader.pending())),
+    ];
+    if let Some(ref task) = state.sort_task {
+        queues.push(("sort %", task.percent()));
+    }
+    
     unsafe {
-        if let Some(state) = &mut APP_STATE {
-            let old_pos = state.scroll_pos;
-            let scroll_unit = match state.view_mode {
-                ViewMode::Details => state.item_height,
-                _ => state.cell_size,
+        let rect = perf_hud_rect(state);
+        let background = CreateSolidBrush(COLORREF(0x00202020));
+        FillRect(hdc, &rect, background);
+        DeleteObject(background);
+        
+        SetTextColor(hdc, COLORREF(0x0080FF80));
+        for (line_index, line) in hud_lines(&queues).iter().enumerate() {
+            let top = rect.top + PERF_HUD_MARGIN / 2 + line_index as i32 * PERF_HUD_LINE_HEIGHT;
+            let mut line_rect = RECT {
+                left: rect.left + PERF_HUD_MARGIN,
+                top,
+                right: rect.right - PERF_HUD_MARGIN,
+                bottom: top + PERF_HUD_LINE_HEIGHT,
             };
-            
-            state.scroll_pos += lines * scroll_unit;
-            state.scroll_pos = state.scroll_pos.max(0).min(state.total_height - state.client_height);
-            
-            if state.scroll_pos != old_pos {
-                state.calculate_layout();
-                update_scrollbar(window);
-                InvalidateRect(window, None, TRUE);
-                
-                // Post message to recompute thumbnails
-                let _ = PostMessageW(GetParent(window), WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
-            }
+            let mut text_utf16: Vec<u16> = line.encode_utf16().collect();
+            DrawTextW(hdc, &mut text_utf16, &mut line_rect, DT_LEFT | DT_VCENTER | DT_SINGLELINE | DT_END_ELLIPSIS | DT_NOPREFIX);
         }
     }
-} 
+}
+
+// Tab from the list comes back to the whole query, like a dialog's edit
+fn focus_search_edit(state: &AppState) {
+    unsafe {
+        SendMessageW(state.search_edit, EM_SETSEL, WPARAM(0), LPARAM(-1));
+        SetFocus(state.search_edit);
+    }
+}
 
 extern "system" fn search_edit_proc(
     window: HWND,
@@ -3224,17 +10353,58 @@ extern "system" fn search_edit_proc(
     unsafe {
         match message {
             WM_KEYDOWN => {
+                if is_log_window_shortcut(wparam) {
+                    if let Some(state) = app_state(window) {
+                        toggle_log_window(state.main_window, state.font);
+                    }
+                    return LRESULT(0);
+                }
+                if is_perf_hud_shortcut(wparam) {
+                    if let Some(state) = app_state(window) {
+                        toggle_perf_hud(state);
+                    }
+                    return LRESULT(0);
+                }
+                if wparam.0 == 0x09 { // VK_TAB: on to the results
+                    if let Some(state) = app_state(window) {
+                        SetFocus(state.list_view);
+                    }
+                    return LRESULT(0);
+                }
+                if wparam.0 == 0x1B { // VK_ESCAPE
+                    if let Some(state) = app_state(window) {
+                        clear_search_or_hide(state);
+                    }
+                    return LRESULT(0);
+                }
+                if is_focus_search_shortcut(wparam) {
+                    if let Some(state) = app_state(window) {
+                        focus_search_edit(state);
+                    }
+                    return LRESULT(0);
+                }
                 if wparam.0 == 0x0D { // VK_RETURN (Enter key)
                     log_debug("Enter key pressed in search edit - triggering immediate search");
-                    handle_immediate_search();
+                    if let Some(state) = app_state(window) {
+                        handle_immediate_search(state);
+                        if !state.is_list_mode {
+                            let query = state.searched_query.clone();
+                            state.add_recent_search(&query);
+                        }
+                    }
                     return LRESULT(0);
                 }
             }
+            // The edit would beep at the tab that moved the focus away, and
+            // at the Esc that cleared it
+            WM_CHAR if wparam.0 == 0x09 || wparam.0 == 0x1B => return LRESULT(0),
             _ => {}
         }
         
-        // Call original window procedure for all other messages
-        if let Some(original_proc) = ORIGINAL_SEARCH_EDIT_PROC {
+        // Call original window procedure for all other messages; it is
+        // kept in the edit's GWLP_USERDATA when the control is subclassed
+        let original_proc: WNDPROC = std::mem::transmute(GetWindowLongPtrW(window, GWLP_USERDATA));
+        if original_proc.is_some() {
             CallWindowProcW(original_proc, window, message, wparam, lparam)
         } else {
             DefWindowProcW(window, message, wparam, lparam)
@@ -3242,22 +10412,230 @@ extern "system" fn search_edit_proc(
     }
 } 
 
+// Lists untranslated and unknown keys for every language file
+fn format_translation_report(reports: &[TranslationReport], strings: &LanguageStrings) -> String {
+    let mut report = String::new();
+    
+    for entry in reports {
+        report.push_str(&format!("{} ({})\r\n", entry.language.display_name(), entry.language.file_name()));
+        
+        if let Some(error) = &entry.error {
+            report.push_str(&format!("  {}\r\n", fill(&strings.validation_error, &[("error", error)])));
+        } else if entry.missing.is_empty() && entry.unknown.is_empty() {
+            report.push_str(&format!("  {}\r\n", strings.validation_complete));
+        }
+        
+        for (template, keys) in [(&strings.validation_missing, &entry.missing), (&strings.validation_unknown, &entry.unknown)] {
+            if keys.is_empty() {
+                continue;
+            }
+            report.push_str(&format!("  {}\r\n", fill(template, &[("count", &keys.len().to_string())])));
+            for key in keys {
+                report.push_str(&format!("    {}\r\n", key));
+            }
+        }
+        
+        report.push_str("\r\n");
+    }
+    
+    report
+}
+
+// Read-only text report with Copy and Close buttons
+fn show_report_window(parent: HWND, font: HFONT, title: &str, report: &str) {
+    unsafe {
+        let strings = get_strings();
+        let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+        
+        let window = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            PCWSTR::from_raw(to_wide(STATS_WINDOW_CLASS).as_ptr()),
+            PCWSTR::from_raw(to_wide(title).as_ptr()),
+            WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            640,
+            520,
+            parent,
+            None,
+            instance,
+            None,
+        );
+        
+        if window.0 == 0 {
+            log_error("Failed to create report window");
+            return;
+        }
+        
+        // Monospace font keeps the report columns aligned
+        let report_font = CreateFontW(
+            16, 0, 0, 0,
+            400,  // FW_NORMAL
+            0, 0, 0,
+            1,    // DEFAULT_CHARSET
+            0,    // OUT_DEFAULT_PRECIS
+            0,    // CLIP_DEFAULT_PRECIS
+            0,    // DEFAULT_QUALITY
+            49,   // FIXED_PITCH | FF_MODERN
+            w!("Consolas"),
+        );
+        SetWindowLongPtrW(window, GWLP_USERDATA, report_font.0);
+        
+        let text = CreateWindowExW(
+            WS_EX_CLIENTEDGE,
+            w!("EDIT"),
+            PCWSTR::from_raw(to_wide(report).as_ptr()),
+            WS_CHILD | WS_VISIBLE | WS_VSCROLL | WS_HSCROLL | WINDOW_STYLE((ES_MULTILINE | ES_READONLY | ES_AUTOVSCROLL | ES_AUTOHSCROLL) as u32),
+            0, 0, 0, 0,
+            window,
+            HMENU(ID_STATS_TEXT as isize),
+            instance,
+            None,
+        );
+        SendMessageW(text, WM_SETFONT, WPARAM(report_font.0 as usize), LPARAM(1));
+        
+        let buttons = [
+            (ID_STATS_COPY, &strings.stats_copy, BS_PUSHBUTTON),
+            (ID_STATS_CLOSE, &strings.stats_close, BS_DEFPUSHBUTTON),
+        ];
+        for (id, label, style) in buttons {
+            let button = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                w!("BUTTON"),
+                PCWSTR::from_raw(to_wide(label).as_ptr()),
+                WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(style as u32),
+                0, 0, 0, 0,
+                window,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(button, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        }
+        
+        let mut client_rect = RECT::default();
+        let _ = GetClientRect(window, &mut client_rect);
+        layout_statistics_window(window, client_rect.right, client_rect.bottom);
+    }
+}
+
+fn layout_statistics_window(window: HWND, width: i32, height: i32) {
+    unsafe {
+        let margin = 10;
+        let button_width = 90;
+        let button_height = 28;
+        let button_y = height - margin - button_height;
+        
+        let _ = SetWindowPos(
+            GetDlgItem(window, ID_STATS_TEXT),
+            None,
+            margin,
+            margin,
+            (width - margin * 2).max(0),
+            (button_y - margin * 2).max(0),
+            SWP_NOZORDER,
+        );
+        
+        let _ = SetWindowPos(
+            GetDlgItem(window, ID_STATS_CLOSE),
+            None,
+            width - margin - button_width,
+            button_y,
+            button_width,
+            button_height,
+            SWP_NOZORDER,
+        );
+        
+        let _ = SetWindowPos(
+            GetDlgItem(window, ID_STATS_COPY),
+            None,
+            width - (margin + button_width) * 2,
+            button_y,
+            button_width,
+            button_height,
+            SWP_NOZORDER,
+        );
+    }
+}
+
+extern "system" fn stats_window_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_SIZE => {
+                let width = (lparam.0 & 0xFFFF) as i32;
+                let height = ((lparam.0 >> 16) & 0xFFFF) as i32;
+                layout_statistics_window(window, width, height);
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                match (wparam.0 & 0xFFFF) as i32 {
+                    ID_STATS_COPY => {
+                        let text = GetDlgItem(window, ID_STATS_TEXT);
+                        let len = GetWindowTextLengthW(text);
+                        let mut buffer = vec![0u16; len as usize + 1];
+                        let copied = GetWindowTextW(text, &mut buffer);
+                        set_clipboard_text(window, &String::from_utf16_lossy(&buffer[..copied as usize]));
+                    }
+                    ID_STATS_CLOSE => {
+                        let _ = DestroyWindow(window);
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                let report_font = GetWindowLongPtrW(window, GWLP_USERDATA);
+                if report_font != 0 {
+                    DeleteObject(HFONT(report_font));
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
+
 extern "system" fn main_window_proc(
     window: HWND,
     message: u32,
     wparam: WPARAM,
     lparam: LPARAM,
 ) -> LRESULT {
+    let _timer = MessageTimer::start("main", message);
     unsafe {
         match message {
+            WM_NCCREATE => {
+                // Keep the boxed AppState passed to CreateWindowExW
+                let create_struct = lparam.0 as *const CREATESTRUCTW;
+                SetWindowLongPtrW(window, GWLP_USERDATA, (*create_struct).lpCreateParams as isize);
+                DefWindowProcW(window, message, wparam, lparam)
+            }
             WM_CREATE => {
-                if let Some(state) = &mut APP_STATE {
+                if let Some(state) = app_state(window) {
                     state.main_window = window;
                     
-                    state.font = CreateFontW(
+                    state.font = CreateFontW(
+                        16, 0, 0, 0,
+                        400,  // FW_NORMAL
+                        0, 0, 0,
+                        1,    // DEFAULT_CHARSET
+                        0,    // OUT_DEFAULT_PRECIS
+                        0,    // CLIP_DEFAULT_PRECIS
+                        0,    // DEFAULT_QUALITY
+                        0,    // DEFAULT_PITCH | FF_DONTCARE
+                        w!("Segoe UI"),
+                    );
+                    
+                    // Same face with strikeout, used for missing list entries
+                    state.strikeout_font = CreateFontW(
                         16, 0, 0, 0,
                         400,  // FW_NORMAL
-                        0, 0, 0,
+                        0, 0, 1,
                         1,    // DEFAULT_CHARSET
                         0,    // OUT_DEFAULT_PRECIS
                         0,    // CLIP_DEFAULT_PRECIS
@@ -3266,18 +10644,46 @@ extern "system" fn main_window_proc(
                         w!("Segoe UI"),
                     );
                     
-                    create_child_controls(window);
-                    let _ = create_menus(window);
+                    create_child_controls(window, state);
+                    start_icon_loader(state.list_view);
+                    network_paths::set_status_window(window);
+                    let _ = create_menus(window, state);
                     state.initialize_everything_sdk();
                     state.initialize_thumbnail_task_manager(state.list_view);
-                    update_status_bar();
+                    state.metadata_loader = Some(MetadataLoader::start(window, WM_METADATA_LOADED));
+                    state.git_status_loader = Some(GitStatusLoader::start(window, WM_GIT_STATUS_LOADED));
+                    state.link_info_loader = Some(LinkInfoLoader::start(window, WM_LINK_INFO_LOADED));
+                    state.media_info_loader = Some(MediaInfoLoader::start(window, WM_MEDIA_INFO_LOADED));
+                    state.restore_session();
+                    update_status_bar(state);
+                    
+                    if state.config.quick_search_hotkey && !state.set_quick_search_hotkey(true) {
+                        log_warn("Failed to register the quick search hotkey");
+                    }
+                    if state.config.launcher_hotkey && !state.set_launcher_hotkey(true) {
+                        log_warn("Failed to register the launcher hotkey");
+                    }
+                    if state.config.http_server {
+                        if let Err(e) = state.set_http_server(true, state.config.http_server_port) {
+                            log_warn(&format!("Failed to start the HTTP server: {}", e));
+                        }
+                    }
+                    if state.config.clipboard_watcher && !set_clipboard_listener(window, true) {
+                        log_warn("Failed to start watching the clipboard");
+                    }
+                    state.refresh_jump_list();
+                    state.check_for_updates_at_startup();
+                    state.run_due_exports(true);
+                    SetTimer(window, SCHEDULE_TIMER_ID, SCHEDULE_CHECK_INTERVAL_MS, None);
                 }
                 LRESULT(0)
             }
             WM_SIZE => {
                 let width = (lparam.0 & 0xFFFF) as i32;
                 let height = ((lparam.0 >> 16) & 0xFFFF) as i32;
-                resize_controls(width, height);
+                if let Some(state) = app_state(window) {
+                    resize_controls(state, width, height);
+                }
                 
                 // Post message to recompute thumbnails
                 let _ = PostMessageW(window, WM_RECOMPUTE_THUMBS, WPARAM(0), LPARAM(0));
@@ -3290,133 +10696,691 @@ extern "system" fn main_window_proc(
                 match control_id {
                     ID_SEARCH_EDIT => {
                         if notification == 0x0300 { // EN_CHANGE
-                            handle_search_change();
+                            if let Some(state) = app_state(window) {
+                                handle_search_change(state);
+                            }
+                        }
+                    }
+                    ID_SYNTAX_HELP_BUTTON => {
+                        if let Some(state) = app_state(window) {
+                            let mut rect = RECT::default();
+                            let _ = GetWindowRect(state.syntax_button, &mut rect);
+                            toggle_syntax_help(window, state.font, POINT { x: rect.right, y: rect.bottom });
+                        }
+                    }
+                    ID_VIEW_FOLDER_TREE => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_folder_tree();
+                        }
+                    }
+                    ID_VIEW_PREVIEW_PANE => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_preview_pane();
+                        }
+                    }
+                    ID_VIEW_IMAGE_VIEWER => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_image_viewer();
+                        }
+                    }
+                    ID_COLUMN_SIZE_TO_FIT => {
+                        if let Some(state) = app_state(window) {
+                            if let Some(column) = state.header_menu_column.take() {
+                                state.size_column_to_fit(column);
+                            }
+                        }
+                    }
+                    ID_FREEZE_NAME_COLUMN => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_freeze_name_column();
+                        }
+                    }
+                    ID_VIEW_GROUP_BY_DRIVE => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_group_by_drive();
+                        }
+                    }
+                    ID_COLUMN_RESET_LAYOUT => {
+                        if let Some(state) = app_state(window) {
+                            state.reset_column_layout();
+                        }
+                    }
+                    ID_COLUMN_MORE => {
+                        if let Some(state) = app_state(window) {
+                            state.choose_more_columns();
+                        }
+                    }
+                    ID_SIZE_FORMAT_AUTO => {
+                        if let Some(state) = app_state(window) {
+                            state.set_size_format(SizeFormat::Auto);
+                        }
+                    }
+                    ID_SIZE_FORMAT_KILOBYTES => {
+                        if let Some(state) = app_state(window) {
+                            state.set_size_format(SizeFormat::Kilobytes);
+                        }
+                    }
+                    ID_SIZE_FORMAT_BYTES => {
+                        if let Some(state) = app_state(window) {
+                            state.set_size_format(SizeFormat::Bytes);
+                        }
+                    }
+                    ID_PATH_ELLIPSIS_END => {
+                        if let Some(state) = app_state(window) {
+                            state.set_path_ellipsis(PathEllipsis::End);
+                        }
+                    }
+                    ID_PATH_ELLIPSIS_MIDDLE => {
+                        if let Some(state) = app_state(window) {
+                            state.set_path_ellipsis(PathEllipsis::Middle);
+                        }
+                    }
+                    ID_SIDEBAR_PIN_FOLDER | ID_SIDEBAR_UNPIN => {
+                        if let Some(state) = app_state(window) {
+                            match state.sidebar_menu_item.take() {
+                                Some(SidebarItem::Folder(path)) => state.pin_favorite(FavoriteKind::Folder, &path),
+                                Some(SidebarItem::Favorite(index, _)) => state.unpin_favorite(index),
+                                _ => {}
+                            }
+                        }
+                    }
+                    ID_SIDEBAR_PIN_SEARCH => {
+                        if let Some(state) = app_state(window) {
+                            let query = state.searched_query.clone();
+                            state.pin_favorite(FavoriteKind::Search, &query);
+                        }
+                    }
+                    ID_CLOSE_SEARCH_TAB => {
+                        if let Some(state) = app_state(window) {
+                            if let Some(index) = state.search_tab_menu_index.take() {
+                                state.close_search_tab(index);
+                            }
+                        }
+                    }
+                    ID_SIDEBAR_PIN_LIST => {
+                        if let Some(list_path) = app_state(window).and_then(|state| state.current_list_path.clone()) {
+                            if let Some(state) = app_state(window) {
+                                state.pin_favorite(FavoriteKind::List, &list_path);
+                            }
+                        }
+                    }
+                    ID_VIEW_DETAILS => {
+                        if let Some(state) = app_state(window) {
+                            state.set_view_mode(ViewMode::Details);
+                            update_scrollbar(state);
+                            InvalidateRect(state.list_view, None, TRUE);
+                        }
+                    }
+                    ID_VIEW_MEDIUM_ICONS => {
+                        if let Some(state) = app_state(window) {
+                            state.set_view_mode(ViewMode::MediumIcons);
+                            update_scrollbar(state);
+                            InvalidateRect(state.list_view, None, TRUE);
+                        }
+                    }
+                    ID_VIEW_LARGE_ICONS => {
+                        if let Some(state) = app_state(window) {
+                            state.set_view_mode(ViewMode::LargeIcons);
+                            update_scrollbar(state);
+                            InvalidateRect(state.list_view, None, TRUE);
+                        }
+                    }
+                    ID_VIEW_EXTRALARGE_ICONS => {
+                        if let Some(state) = app_state(window) {
+                            state.set_view_mode(ViewMode::ExtraLargeIcons);
+                            update_scrollbar(state);
+                            InvalidateRect(state.list_view, None, TRUE);
+                        }
+                    }
+                    ID_SORT_NAME => {
+                        if let Some(state) = app_state(window) {
+                            state.sort_by_column(ColumnType::Name);
+                            update_scrollbar(state);
+                            InvalidateRect(state.list_view, None, TRUE);
+                            update_status_bar(state);
+                            update_sort_menu_checkmarks(window, &state.sort_state);
+                        }
+                    }
+                    ID_SORT_SIZE => {
+                        if let Some(state) = app_state(window) {
+                            state.sort_by_column(ColumnType::Size);
+                            update_scrollbar(state);
+                            InvalidateRect(state.list_view, None, TRUE);
+                            update_status_bar(state);
+                            update_sort_menu_checkmarks(window, &state.sort_state);
+                        }
+                    }
+                    ID_SORT_TYPE => {
+                        if let Some(state) = app_state(window) {
+                            state.sort_by_column(ColumnType::Type);
+                            update_scrollbar(state);
+                            InvalidateRect(state.list_view, None, TRUE);
+                            update_status_bar(state);
+                            update_sort_menu_checkmarks(window, &state.sort_state);
+                        }
+                    }
+                    ID_SORT_DATE => {
+                        if let Some(state) = app_state(window) {
+                            state.sort_by_column(ColumnType::Modified);
+                            update_scrollbar(state);
+                            InvalidateRect(state.list_view, None, TRUE);
+                            update_status_bar(state);
+                            update_sort_menu_checkmarks(window, &state.sort_state);
+                        }
+                    }
+                    ID_SORT_PATH => {
+                        if let Some(state) = app_state(window) {
+                            state.sort_by_column(ColumnType::Path);
+                            update_scrollbar(state);
+                            InvalidateRect(state.list_view, None, TRUE);
+                            update_status_bar(state);
+                            update_sort_menu_checkmarks(window, &state.sort_state);
+                        }
+                    }
+                    ID_SORT_ASCENDING => {
+                        if let Some(state) = app_state(window) {
+                            state.change_sort_order(SortOrder::Ascending);
+                            update_scrollbar(state);
+                            InvalidateRect(state.list_view, None, TRUE);
+                            update_status_bar(state);
+                            update_sort_menu_checkmarks(window, &state.sort_state);
+                        }
+                    }
+                    ID_SORT_DESCENDING => {
+                        if let Some(state) = app_state(window) {
+                            state.change_sort_order(SortOrder::Descending);
+                            update_scrollbar(state);
+                            InvalidateRect(state.list_view, None, TRUE);
+                            update_status_bar(state);
+                            update_sort_menu_checkmarks(window, &state.sort_state);
+                        }
+                    }
+                    ID_SORT_LOCALE => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_locale_sort();
+                            update_scrollbar(state);
+                            InvalidateRect(state.list_view, None, TRUE);
+                            update_status_bar(state);
+                        }
+                    }
+                    ID_SORT_RANK_BY_USE => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_rank_by_use();
+                            update_scrollbar(state);
+                            InvalidateRect(state.list_view, None, TRUE);
+                            update_status_bar(state);
+                        }
+                    }
+                    ID_FILE_OPEN_LIST => {
+                        // Show file dialog to select file list
+                        if let Some(file_path) = show_open_file_dialog(window) {
+                            if let Some(state) = app_state(window) {
+                                open_file_list(window, state, &file_path);
+                            }
+                        }
+                    }
+                    ID_FILE_EXPORT_CONTACT_SHEET => {
+                        let filters = [("PNG Images (*.png)", "png")];
+                        if let Some(result) = show_save_file_dialog_with_filters(window, &get_strings().file_export_contact_sheet, "contact_sheet", &filters, 1, None) {
+                            if let Some(state) = app_state(window) {
+                                state.start_contact_sheet_export(result.path);
+                            }
+                        }
+                    }
+                    id if (ID_CONTACT_SHEET_COLUMNS_FIRST..ID_CONTACT_SHEET_COLUMNS_FIRST + CONTACT_SHEET_COLUMN_CHOICES.len() as i32).contains(&id) => {
+                        if let Some(state) = app_state(window) {
+                            let columns = CONTACT_SHEET_COLUMN_CHOICES[(id - ID_CONTACT_SHEET_COLUMNS_FIRST) as usize];
+                            let cell_size = state.config.contact_sheet_cell_size;
+                            state.set_contact_sheet_options(columns, cell_size);
+                        }
+                    }
+                    id if (ID_CONTACT_SHEET_CELL_SIZE_FIRST..ID_CONTACT_SHEET_CELL_SIZE_FIRST + CONTACT_SHEET_CELL_SIZE_CHOICES.len() as i32).contains(&id) => {
+                        if let Some(state) = app_state(window) {
+                            let columns = state.config.contact_sheet_columns;
+                            let cell_size = CONTACT_SHEET_CELL_SIZE_CHOICES[(id - ID_CONTACT_SHEET_CELL_SIZE_FIRST) as usize];
+                            state.set_contact_sheet_options(columns, cell_size);
+                        }
+                    }
+                    ID_FILE_PASTE_PATHS => {
+                        if let Some(state) = app_state(window) {
+                            state.paste_paths_as_list();
+                        }
+                    }
+                    ID_FILE_MONITOR_FOLDER => {
+                        let title = get_strings().monitor_folder_title;
+                        if let Some(folder) = show_folder_dialog(window, &title) {
+                            if let Some(state) = app_state(window) {
+                                state.monitor_folder(&folder);
+                            }
+                        }
+                    }
+                    ID_FILE_PRINT_PREVIEW => {
+                        if let Some(state) = app_state(window) {
+                            show_print_preview(window, state.font, state.results_print_table());
+                        }
+                    }
+                    ID_FILE_PRINT => {
+                        if let Some(state) = app_state(window) {
+                            print_table(window, &state.results_print_table());
+                        }
+                    }
+                    ID_FILE_LIST_STATISTICS => {
+                        if let Some(state) = app_state(window) {
+                            if state.is_list_mode {
+                                // Loading metadata for a large list can take a moment
+                                let old_cursor = SetCursor(LoadCursorW(None, IDC_WAIT).unwrap_or_default());
+                                let statistics = compute_statistics(&state.original_list_data);
+                                SetCursor(old_cursor);
+                                
+                                let report = statistics.to_report(&get_strings());
+                                show_report_window(window, state.font, &get_strings().stats_title, &report);
+                            }
+                        }
+                    }
+                    ID_FILE_REMOVE_MISSING => {
+                        if let Some(state) = app_state(window) {
+                            state.remove_missing_entries();
+                        }
+                    }
+                    ID_FILE_PIN_CURRENT_LIST => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_current_list_pin();
+                        }
+                    }
+                    ID_FILE_CLEAR_RECENT_LISTS => {
+                        if let Some(state) = app_state(window) {
+                            state.clear_recent_lists();
+                        }
+                    }
+                    ID_FILE_RESTORE_SESSION => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_restore_session();
+                        }
+                    }
+                    ID_FILE_SETTINGS => {
+                        if let Some(state) = app_state(window) {
+                            show_settings_dialog(window, &state.config, state.font);
+                        }
+                    }
+                    ID_LANG_EDIT_TRANSLATIONS => {
+                        if let Some(state) = app_state(window) {
+                            show_translation_editor(window, state.font);
+                        }
+                    }
+                    ID_LANG_VALIDATE_TRANSLATIONS => {
+                        if let Some(state) = app_state(window) {
+                            let strings = get_strings();
+                            let report = format_translation_report(&validate_translations(), &strings);
+                            show_report_window(window, state.font, &strings.validation_title, &report);
+                        }
+                    }
+                    ID_FILE_EXPORT_SETTINGS => {
+                        let filters = [("Settings Files (*.json)", "json")];
+                        let title = get_strings().file_export_settings;
+                        if let Some(result) = show_save_file_dialog_with_filters(window, &title, "everything_like_settings", &filters, 1, None) {
+                            if let Some(state) = app_state(window) {
+                                state.export_settings(&result.path);
+                            }
+                        }
+                    }
+                    ID_FILE_IMPORT_SETTINGS => {
+                        let filters = [("Settings Files (*.json)", "*.json"), ("All Files (*.*)", "*.*")];
+                        let title = get_strings().file_import_settings;
+                        if let Some(path) = show_open_file_dialog_with_filters(window, &title, &filters) {
+                            if let Some(state) = app_state(window) {
+                                state.import_settings(&path);
+                            }
+                        }
+                    }
+                    ID_TRAY_SHOW => {
+                        if let Some(state) = app_state(window) {
+                            restore_from_tray(window, state);
+                        }
+                    }
+                    ID_TRAY_EXIT => {
+                        let _ = DestroyWindow(window);
+                    }
+                    ID_OPEN_FILE => {
+                        if let Some(state) = app_state(window) {
+                            state.open_selected_file();
+                        }
+                    }
+                    ID_QUICK_VIEW => {
+                        if let Some(state) = app_state(window) {
+                            state.quick_view_selected();
+                        }
+                    }
+                    ID_PREVIEW_VIDEO => {
+                        if let Some(state) = app_state(window) {
+                            state.preview_selected_video();
+                        }
+                    }
+                    ID_BROWSE_ARCHIVE => {
+                        if let Some(state) = app_state(window) {
+                            if let Some(file) = state.selected_index.and_then(|index| state.list_data.get(index)) {
+                                show_archive_browser(window, state.font, file.path());
+                            }
+                        }
+                    }
+                    ID_VERIFY_CHECKSUMS => {
+                        if let Some(state) = app_state(window) {
+                            if let Some(file) = state.selected_index.and_then(|index| state.list_data.get(index)) {
+                                show_checksum_verifier(window, state.font, file.path());
+                            }
+                        }
+                    }
+                    ID_OPEN_FILE_LOCATION => {
+                        if let Some(state) = app_state(window) {
+                            state.open_selected_location();
+                        }
+                    }
+                    ID_BROWSE_FOLDER_IN_TAB => {
+                        if let Some(state) = app_state(window) {
+                            if let Some(index) = state.selected_index {
+                                state.browse_folder_in_new_tab(index);
+                            }
+                        }
+                    }
+                    ID_OPEN_CMD | ID_OPEN_POWERSHELL | ID_OPEN_WINDOWS_TERMINAL | ID_OPEN_WSL => {
+                        let terminal = match control_id {
+                            ID_OPEN_CMD => Terminal::CommandPrompt,
+                            ID_OPEN_POWERSHELL => Terminal::PowerShell,
+                            ID_OPEN_WSL => Terminal::Wsl,
+                            _ => Terminal::Wt,
+                        };
+                        if let Some(state) = app_state(window) {
+                            state.open_terminal_here(terminal);
+                        }
+                    }
+                    ID_COPY_WSL_PATH => {
+                        if let Some(state) = app_state(window) {
+                            state.copy_selected_as_wsl_paths();
+                        }
+                    }
+                    ID_TAG_NEW => {
+                        if let Some(state) = app_state(window) {
+                            state.add_new_tag();
+                        }
+                    }
+                    ID_TAG_CLEAR => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_tag(None);
+                        }
+                    }
+                    id if (ID_RATING_FIRST..=ID_RATING_FIRST + MAX_RATING as i32).contains(&id) => {
+                        if let Some(state) = app_state(window) {
+                            state.rate_selected((id - ID_RATING_FIRST) as u8);
+                        }
+                    }
+                    id if (ID_TAG_FIRST..ID_TAG_FIRST + MAX_TAGS as i32).contains(&id) => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_tag(Some((id - ID_TAG_FIRST) as usize));
+                        }
+                    }
+                    ID_EXCLUDE_FOLDER => {
+                        if let Some(state) = app_state(window) {
+                            state.exclude_selected_folders();
+                        }
+                    }
+                    ID_EXCLUDED_FOLDERS_CLEAR => {
+                        if let Some(state) = app_state(window) {
+                            state.include_excluded_folders(None);
+                        }
+                    }
+                    id if (ID_EXCLUDED_FOLDER_FIRST..ID_EXCLUDED_FOLDER_FIRST + MAX_EXCLUDED_FOLDERS as i32).contains(&id) => {
+                        if let Some(state) = app_state(window) {
+                            state.include_excluded_folders(Some((id - ID_EXCLUDED_FOLDER_FIRST) as usize));
+                        }
+                    }
+                    ID_COPY_AS_TABLE => {
+                        if let Some(state) = app_state(window) {
+                            state.copy_as_table();
+                        }
+                    }
+                    ID_EDIT_NOTE => {
+                        if let Some(state) = app_state(window) {
+                            state.edit_selected_note();
+                        }
+                    }
+                    ID_REMOVE_NOTE => {
+                        if let Some(state) = app_state(window) {
+                            state.set_selected_note("");
+                        }
+                    }
+                    ID_COPY_PATH | ID_COPY_NAME => {
+                        if let Some(state) = app_state(window) {
+                            state.copy_selected(control_id == ID_COPY_NAME);
+                        }
+                    }
+                    ID_DOWNLOAD_FILE => {
+                        let title = get_strings().download_folder_title;
+                        if let Some(folder) = show_folder_dialog(window, &title) {
+                            if let Some(state) = app_state(window) {
+                                state.download_selected(PathBuf::from(folder), false);
+                            }
+                        }
+                    }
+                    ID_REMOTE_LOCAL => {
+                        if let Some(state) = app_state(window) {
+                            state.disconnect_remote_server();
+                        }
+                    }
+                    ID_REMOTE_ADD => {
+                        if let Some(state) = app_state(window) {
+                            state.add_remote_server();
+                        }
+                    }
+                    ID_REMOTE_REMOVE => {
+                        if let Some(state) = app_state(window) {
+                            state.remove_current_remote_server();
+                        }
+                    }
+                    id if (ID_REMOTE_FIRST..ID_REMOTE_FIRST + MAX_REMOTE_SERVERS as i32).contains(&id) => {
+                        if let Some(state) = app_state(window) {
+                            state.connect_remote_server((id - ID_REMOTE_FIRST) as usize);
+                        }
+                    }
+                    ID_TOOL_ADD => {
+                        if let Some(state) = app_state(window) {
+                            state.add_external_tool();
+                        }
+                    }
+                    ID_TOOL_FIND_DUPLICATES => {
+                        if let Some(state) = app_state(window) {
+                            state.find_duplicates();
+                        }
+                    }
+                    ID_TOOL_DISK_USAGE => {
+                        if let Some(state) = app_state(window) {
+                            show_disk_usage(window, state.font, &state.selected_folder());
+                        }
+                    }
+                    ID_TOOL_EMPTY_FOLDERS => {
+                        if let Some(state) = app_state(window) {
+                            show_empty_folders(window, state.font, &state.selected_folder());
+                        }
+                    }
+                    ID_TOOL_STATISTICS => {
+                        if let Some(state) = app_state(window) {
+                            show_stats_dashboard(window, state.font, state.list_data.clone());
+                        }
+                    }
+                    ID_TOOL_OPEN_HISTORY => {
+                        if let Some(state) = app_state(window) {
+                            show_open_history(window, state.font, state.config.record_open_history);
+                        }
+                    }
+                    ID_TOOL_RECORD_HISTORY => {
+                        if let Some(state) = app_state(window) {
+                            state.set_record_open_history(!state.config.record_open_history);
+                        }
+                    }
+                    ID_TOOL_TIMELINE => {
+                        if let Some(state) = app_state(window) {
+                            show_timeline(window, state.font, state.list_data.clone());
+                        }
+                    }
+                    ID_TOOL_REBUILD_DATABASE => {
+                        if let Some(state) = app_state(window) {
+                            state.run_everything_command(true);
+                        }
+                    }
+                    ID_TOOL_UPDATE_FOLDER_INDEXES => {
+                        if let Some(state) = app_state(window) {
+                            state.run_everything_command(false);
+                        }
+                    }
+                    ID_COMPARE_CONTENTS => {
+                        if let Some(state) = app_state(window) {
+                            state.compare_selected_files();
+                        }
+                    }
+                    ID_COMPARE_IN_DIFF_TOOL => {
+                        if let Some(state) = app_state(window) {
+                            state.compare_in_diff_tool();
+                        }
+                    }
+                    id if (ID_TOOL_FIRST..ID_TOOL_FIRST + MAX_EXTERNAL_TOOLS as i32).contains(&id) => {
+                        if let Some(state) = app_state(window) {
+                            state.run_external_tool((id - ID_TOOL_FIRST) as usize);
+                        }
+                    }
+                    id if (ID_TOOL_REMOVE_FIRST..ID_TOOL_REMOVE_FIRST + MAX_EXTERNAL_TOOLS as i32).contains(&id) => {
+                        if let Some(state) = app_state(window) {
+                            state.remove_external_tool((id - ID_TOOL_REMOVE_FIRST) as usize);
                         }
                     }
-                    ID_VIEW_DETAILS => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.set_view_mode(ViewMode::Details);
-                            update_scrollbar(state.list_view);
-                            InvalidateRect(state.list_view, None, TRUE);
+                    ID_HELP_ABOUT => {
+                        if let Some(state) = app_state(window) {
+                            show_about_dialog(window, state.font);
                         }
                     }
-                    ID_VIEW_MEDIUM_ICONS => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.set_view_mode(ViewMode::MediumIcons);
-                            update_scrollbar(state.list_view);
-                            InvalidateRect(state.list_view, None, TRUE);
+                    ID_IMPORT_EVERYTHING => {
+                        if let Some(state) = app_state(window) {
+                            state.import_from_everything();
                         }
                     }
-                    ID_VIEW_LARGE_ICONS => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.set_view_mode(ViewMode::LargeIcons);
-                            update_scrollbar(state.list_view);
-                            InvalidateRect(state.list_view, None, TRUE);
+                    ID_SCHEDULED_EXPORT_ADD => {
+                        if let Some(state) = app_state(window) {
+                            state.add_scheduled_export();
                         }
                     }
-                    ID_VIEW_EXTRALARGE_ICONS => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.set_view_mode(ViewMode::ExtraLargeIcons);
-                            update_scrollbar(state.list_view);
-                            InvalidateRect(state.list_view, None, TRUE);
+                    id if (ID_SCHEDULED_EXPORT_FIRST..ID_SCHEDULED_EXPORT_FIRST + MAX_SCHEDULED_EXPORTS as i32 * SCHEDULED_EXPORT_ID_STRIDE).contains(&id) => {
+                        if let Some(state) = app_state(window) {
+                            let index = ((id - ID_SCHEDULED_EXPORT_FIRST) / SCHEDULED_EXPORT_ID_STRIDE) as usize;
+                            match (id - ID_SCHEDULED_EXPORT_FIRST) % SCHEDULED_EXPORT_ID_STRIDE {
+                                SCHEDULED_EXPORT_RUN => state.start_scheduled_export(index),
+                                SCHEDULED_EXPORT_REMOVE => state.remove_scheduled_export(index),
+                                action => {
+                                    if let Some(&hours) = SCHEDULED_EXPORT_INTERVALS.get((action - SCHEDULED_EXPORT_INTERVAL_FIRST) as usize) {
+                                        state.set_scheduled_export_interval(index, hours);
+                                    }
+                                }
+                            }
                         }
                     }
-                    ID_SORT_NAME => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.sort_by_column(ColumnType::Name);
-                            update_scrollbar(state.list_view);
-                            InvalidateRect(state.list_view, None, TRUE);
-                            update_status_bar();
-                            update_sort_menu_checkmarks(window, &state.sort_state);
+                    id if (ID_BOOKMARK_FIRST..ID_BOOKMARK_FIRST + MAX_BOOKMARKS as i32).contains(&id) => {
+                        if let Some(state) = app_state(window) {
+                            state.open_bookmark((id - ID_BOOKMARK_FIRST) as usize);
                         }
                     }
-                    ID_SORT_SIZE => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.sort_by_column(ColumnType::Size);
-                            update_scrollbar(state.list_view);
-                            InvalidateRect(state.list_view, None, TRUE);
-                            update_status_bar();
-                            update_sort_menu_checkmarks(window, &state.sort_state);
+                    ID_FILTER_NONE => {
+                        if let Some(state) = app_state(window) {
+                            state.set_search_filter(None);
                         }
                     }
-                    ID_SORT_TYPE => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.sort_by_column(ColumnType::Type);
-                            update_scrollbar(state.list_view);
-                            InvalidateRect(state.list_view, None, TRUE);
-                            update_status_bar();
-                            update_sort_menu_checkmarks(window, &state.sort_state);
+                    id if (ID_FILTER_FIRST..ID_FILTER_FIRST + MAX_SEARCH_FILTERS as i32).contains(&id) => {
+                        if let Some(state) = app_state(window) {
+                            state.set_search_filter(Some((id - ID_FILTER_FIRST) as usize));
                         }
                     }
-                    ID_SORT_DATE => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.sort_by_column(ColumnType::Modified);
-                            update_scrollbar(state.list_view);
-                            InvalidateRect(state.list_view, None, TRUE);
-                            update_status_bar();
-                            update_sort_menu_checkmarks(window, &state.sort_state);
+                    ID_TRAY_REVEAL_COPIED => {
+                        if let Some(state) = app_state(window) {
+                            state.reveal_copied_paths();
                         }
                     }
-                    ID_SORT_PATH => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.sort_by_column(ColumnType::Path);
-                            update_scrollbar(state.list_view);
-                            InvalidateRect(state.list_view, None, TRUE);
-                            update_status_bar();
-                            update_sort_menu_checkmarks(window, &state.sort_state);
+                    ID_TRAY_ADD_COPIED => {
+                        if let Some(state) = app_state(window) {
+                            restore_from_tray(window, state);
+                            let paths = std::mem::take(&mut state.copied_paths);
+                            state.add_paths_to_list(&paths);
                         }
                     }
-                    ID_SORT_ASCENDING => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.change_sort_order(SortOrder::Ascending);
-                            update_scrollbar(state.list_view);
-                            InvalidateRect(state.list_view, None, TRUE);
-                            update_status_bar();
-                            update_sort_menu_checkmarks(window, &state.sort_state);
+                    ID_TRAY_NEW_SEARCH => {
+                        if let Some(state) = app_state(window) {
+                            restore_from_tray(window, state);
+                            state.set_search_text("");
                         }
                     }
-                    ID_SORT_DESCENDING => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.change_sort_order(SortOrder::Descending);
-                            update_scrollbar(state.list_view);
-                            InvalidateRect(state.list_view, None, TRUE);
-                            update_status_bar();
-                            update_sort_menu_checkmarks(window, &state.sort_state);
+                    ID_TRAY_QUICK_SEARCH => {
+                        if let Some(state) = app_state(window) {
+                            show_quick_search(window, state.font);
                         }
                     }
-                    ID_FILE_OPEN_LIST => {
-                        // Show file dialog to select file list
-                        if let Some(file_path) = show_open_file_dialog(window) {
-                            if let Some(state) = &mut APP_STATE {
-                                match state.load_file_list(&file_path) {
-                                    Ok(_) => {
-                                        update_scrollbar(state.list_view);
-                                        InvalidateRect(state.list_view, None, TRUE);
-                                        update_status_bar();
-                                        println!("Successfully loaded file list: {}", file_path);
-                                    }
-                                    Err(e) => {
-                                        let message = format!("Failed to load file list: {}", e);
-                                        let message_wide: Vec<u16> = message.encode_utf16().chain(std::iter::once(0)).collect();
-                                        let title_wide: Vec<u16> = "Error".encode_utf16().chain(std::iter::once(0)).collect();
-                                        
-                                        MessageBoxW(
-                                            window,
-                                            PCWSTR::from_raw(message_wide.as_ptr()),
-                                            PCWSTR::from_raw(title_wide.as_ptr()),
-                                            MB_ICONERROR | MB_OK,
-                                        );
-                                    }
+                    ID_TRAY_LAUNCHER => {
+                        if let Some(state) = app_state(window) {
+                            toggle_launcher(window, state.font);
+                        }
+                    }
+                    id if (ID_TRAY_RECENT_FIRST..ID_TRAY_RECENT_FIRST + MAX_RECENT_SEARCHES as i32).contains(&id) => {
+                        if let Some(state) = app_state(window) {
+                            if let Some(query) = state.config.recent_searches.get((id - ID_TRAY_RECENT_FIRST) as usize).cloned() {
+                                state.run_search(&query);
+                            }
+                        }
+                    }
+                    ID_PROFILE_SAVE => {
+                        if let Some(state) = app_state(window) {
+                            state.save_current_profile();
+                        }
+                    }
+                    ID_PROFILE_DELETE => {
+                        if let Some(state) = app_state(window) {
+                            state.delete_active_profile();
+                        }
+                    }
+                    id if (ID_PROFILE_FIRST..ID_PROFILE_FIRST + MAX_PROFILES as i32).contains(&id) => {
+                        if let Some(state) = app_state(window) {
+                            state.switch_profile((id - ID_PROFILE_FIRST) as usize);
+                        }
+                    }
+                    id if (ID_FILE_RECENT_LIST_FIRST..=ID_FILE_RECENT_LIST_LAST).contains(&id) => {
+                        let index = (id - ID_FILE_RECENT_LIST_FIRST) as usize;
+                        let recent_path = app_state(window).and_then(|state| {
+                            state.config.recent_lists_for_menu().get(index).map(|entry| entry.path.clone())
+                        });
+                        
+                        if let (Some(list_path), Some(state)) = (recent_path, app_state(window)) {
+                            if std::path::Path::new(&list_path).exists() {
+                                open_file_list(window, state, &list_path);
+                            } else {
+                                // Forget lists that were deleted or moved
+                                state.config.remove_recent_list(&list_path);
+                                if let Err(e) = save_config(&state.config) {
+                                    log_error(&format!("Failed to save config: {}", e));
                                 }
+                                let _ = recreate_menus_with_language(window, state);
+                                state.refresh_jump_list();
+                                
+                                let strings = get_strings();
+                                let message = format!("{}\n{}", strings.error_recent_list_missing, list_path);
+                                MessageBoxW(
+                                    window,
+                                    PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                                    w!("Error"),
+                                    MB_ICONERROR | MB_OK,
+                                );
                             }
                         }
                     }
                     ID_FILE_SAVE_LIST => {
                         // Show save dialog with default filename
                         if let Some(save_path) = show_save_file_dialog(window, "file_list.csv") {
-                            if let Some(state) = &APP_STATE {
+                            if let Some(state) = app_state(window) {
                                 match state.save_file_list(&save_path) {
                                     Ok(_) => {
+                                        state.remember_list_file_version(&save_path);
+                                        
                                         let message = format!("File list saved to: {}", save_path);
                                         let message_wide: Vec<u16> = message.encode_utf16().chain(std::iter::once(0)).collect();
                                         let title_wide: Vec<u16> = "Success".encode_utf16().chain(std::iter::once(0)).collect();
@@ -3444,13 +11408,43 @@ extern "system" fn main_window_proc(
                             }
                         }
                     }
-                    ID_FILE_EXPORT_LIST => {
-                        // Show save dialog for simple export
-                        if let Some(export_path) = show_save_file_dialog(window, "simple_list.txt") {
-                            if let Some(state) = &APP_STATE {
-                                match state.export_simple_list(&export_path) {
+                    ID_FILE_EXPORT_LIST | ID_FILE_EXPORT_SELECTION | ID_FILE_EXPORT_M3U => {
+                        // Show save dialog; the chosen filter decides the export format
+                        let formats: &[ExportFormat] = if control_id == ID_FILE_EXPORT_M3U {
+                            &[ExportFormat::M3u]
+                        } else {
+                            &ExportFormat::ALL
+                        };
+                        let filters: Vec<(&str, &str)> = formats
+                            .iter()
+                            .map(|format| (format.filter_name(), format.extension()))
+                            .collect();
+                        
+                        // Offer restricting the export to the selection when there is one
+                        let strings = get_strings();
+                        let has_selection = app_state(window).map_or(false, |state| !state.selected_items.is_empty());
+                        let checkbox = if has_selection {
+                            Some((strings.export_selected_only.as_str(), control_id == ID_FILE_EXPORT_SELECTION))
+                        } else {
+                            None
+                        };
+                        
+                        if let Some(result) = show_save_file_dialog_with_filters(window, &strings.file_export_list, "file_list", &filters, 1, checkbox) {
+                            let export_path = result.path;
+                            
+                            // A typed extension wins over the selected filter
+                            let format = if control_id == ID_FILE_EXPORT_M3U {
+                                ExportFormat::M3u
+                            } else {
+                                ExportFormat::from_path(&export_path)
+                                    .or_else(|| ExportFormat::from_filter_index(result.filter_index))
+                                    .unwrap_or(ExportFormat::Text)
+                            };
+                            
+                            if let Some(state) = app_state(window) {
+                                match state.export_list(&export_path, format, result.checkbox_checked) {
                                     Ok(_) => {
-                                        let message = format!("Simple file list exported to: {}", export_path);
+                                        let message = format!("File list exported to: {}", export_path);
                                         let message_wide: Vec<u16> = message.encode_utf16().chain(std::iter::once(0)).collect();
                                         let title_wide: Vec<u16> = "Success".encode_utf16().chain(std::iter::once(0)).collect();
                                         
@@ -3488,20 +11482,20 @@ extern "system" fn main_window_proc(
                         );
 
                         if result == IDYES {
-                            if let Some(state) = &mut APP_STATE {
+                            if let Some(state) = app_state(window) {
                                 state.close_file_list();
                             }
                         }
                     }
                     // Language menu items
-                    ID_LANG_ENGLISH => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.set_language(Language::English);
+                    id if (ID_ALIGN_RIGHT_FIRST..=ID_ALIGN_RIGHT_LAST).contains(&id) => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_column_alignment((id - ID_ALIGN_RIGHT_FIRST) as usize);
                         }
                     }
-                    ID_LANG_CHINESE => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.set_language(Language::Chinese);
+                    id if (ID_LANG_FIRST..ID_LANG_FIRST + Language::ALL.len() as i32).contains(&id) => {
+                        if let Some(state) = app_state(window) {
+                            state.set_language(Language::ALL[(id - ID_LANG_FIRST) as usize]);
                         }
                     }
                     // Thumbnail strategy options
@@ -3516,118 +11510,544 @@ extern "system" fn main_window_proc(
                         );
                         
                         if result == IDYES {
-                            if let Some(state) = &mut APP_STATE {
+                            if let Some(state) = app_state(window) {
                                 state.set_thumbnail_strategy(ThumbnailStrategy::DefaultTopToBottom);
                             }
                         }
                         // If IDNO or user pressed Enter (default No), do nothing
                     }
                     ID_THUMB_VISIBLE => {
-                        if let Some(state) = &mut APP_STATE {
+                        if let Some(state) = app_state(window) {
                             state.set_thumbnail_strategy(ThumbnailStrategy::OnlyLoadVisible);
                         }
                     }
                     ID_THUMB_VISIBLE_PLUS_500 => {
-                        if let Some(state) = &mut APP_STATE {
+                        if let Some(state) = app_state(window) {
                             state.set_thumbnail_strategy(ThumbnailStrategy::LoadVisiblePlus500);
                         }
                     }
                     // Thumbnail background options
                     ID_BG_TRANSPARENT => {
-                        if let Some(state) = &mut APP_STATE {
+                        if let Some(state) = app_state(window) {
                             state.set_thumbnail_background(ThumbnailBackground::Transparent);
                         }
                     }
                     ID_BG_CHECKERBOARD => {
-                        if let Some(state) = &mut APP_STATE {
+                        if let Some(state) = app_state(window) {
                             state.set_thumbnail_background(ThumbnailBackground::Checkerboard);
                         }
                     }
                     ID_BG_BLACK => {
-                        if let Some(state) = &mut APP_STATE {
+                        if let Some(state) = app_state(window) {
                             state.set_thumbnail_background(ThumbnailBackground::Black);
                         }
                     }
                     ID_BG_WHITE => {
-                        if let Some(state) = &mut APP_STATE {
+                        if let Some(state) = app_state(window) {
                             state.set_thumbnail_background(ThumbnailBackground::White);
                         }
                     }
                     ID_BG_GRAY => {
-                        if let Some(state) = &mut APP_STATE {
+                        if let Some(state) = app_state(window) {
                             state.set_thumbnail_background(ThumbnailBackground::Gray);
                         }
                     }
                     ID_BG_LIGHT_GRAY => {
-                        if let Some(state) = &mut APP_STATE {
+                        if let Some(state) = app_state(window) {
                             state.set_thumbnail_background(ThumbnailBackground::LightGray);
                         }
                     }
                     ID_BG_DARK_GRAY => {
-                        if let Some(state) = &mut APP_STATE {
+                        if let Some(state) = app_state(window) {
                             state.set_thumbnail_background(ThumbnailBackground::DarkGray);
                         }
                     }
-                    // Column visibility toggles
-                    ID_COLUMN_NAME => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.toggle_column(ColumnType::Name);
-                        }
+                    ID_CAPTION_NAME | ID_CAPTION_NAME_SIZE | ID_CAPTION_NAME_DIMENSIONS | ID_CAPTION_NONE => {
+                        if let Some(state) = app_state(window) {
+                            state.set_thumbnail_caption(match control_id {
+                                ID_CAPTION_NAME_SIZE => ThumbnailCaption::NameAndSize,
+                                ID_CAPTION_NAME_DIMENSIONS => ThumbnailCaption::NameAndDimensions,
+                                ID_CAPTION_NONE => ThumbnailCaption::Hidden,
+                                _ => ThumbnailCaption::Name,
+                            });
+                        }
+                    }
+                    // Column visibility toggles
+                    ID_COLUMN_NAME => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_column(ColumnType::Name);
+                        }
+                    }
+                    ID_COLUMN_SIZE => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_column(ColumnType::Size);
+                        }
+                    }
+                    ID_COLUMN_TYPE => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_column(ColumnType::Type);
+                        }
+                    }
+                    ID_COLUMN_MODIFIED => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_column(ColumnType::Modified);
+                        }
+                    }
+                    ID_COLUMN_PATH => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_column(ColumnType::Path);
+                        }
+                    }
+                    ID_COLUMN_GIT_STATUS => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_column(ColumnType::GitStatus);
+                        }
+                    }
+                    ID_COLUMN_TAGS => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_column(ColumnType::Tags);
+                        }
+                    }
+                    ID_COLUMN_HARD_LINKS => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_column(ColumnType::HardLinks);
+                        }
+                    }
+                    ID_COLUMN_STREAMS => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_column(ColumnType::Streams);
+                        }
+                    }
+                    ID_COLUMN_DURATION => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_column(ColumnType::Duration);
+                        }
+                    }
+                    ID_COLUMN_BITRATE => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_column(ColumnType::Bitrate);
+                        }
+                    }
+                    ID_COLUMN_NOTES => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_column(ColumnType::Notes);
+                        }
+                    }
+                    ID_COLUMN_RATING => {
+                        if let Some(state) = app_state(window) {
+                            state.toggle_column(ColumnType::Rating);
+                        }
+                    }
+                    // Sort options
+                    ID_SORT_ASCENDING => {
+                        if let Some(state) = app_state(window) {
+                            state.change_sort_order(SortOrder::Ascending);
+                        }
+                    }
+                    ID_SORT_DESCENDING => {
+                        if let Some(state) = app_state(window) {
+                            state.change_sort_order(SortOrder::Descending);
+                        }
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_SEARCH_RESULTS => {
+                if let Some(state) = app_state(window) {
+                    log_debug("Received WM_SEARCH_RESULTS message");
+                    log_debug("App state is available, calling handle_search_results");
+                    state.handle_search_results(wparam.0);
+                    log_debug("handle_search_results completed");
+                } else {
+                    log_warn("WM_SEARCH_RESULTS received but the app state is missing");
+                    drop(Box::from_raw(wparam.0 as *mut SearchBatch));
+                }
+                LRESULT(0)
+            }
+            WM_SORT_DONE => {
+                if let Some(state) = app_state(window) {
+                    state.handle_sort_done(wparam.0);
+                } else {
+                    // Still free the result allocated by the sort thread
+                    drop(Box::from_raw(wparam.0 as *mut SortResult));
+                }
+                LRESULT(0)
+            }
+            WM_COMPARE_DONE => {
+                let result = Box::from_raw(wparam.0 as *mut CompareResult);
+                if let Some(state) = app_state(window) {
+                    state.handle_compare_done(*result);
+                }
+                LRESULT(0)
+            }
+            WM_METADATA_LOADED => {
+                if let Some(state) = app_state(window) {
+                    state.handle_metadata_loaded(wparam.0);
+                } else {
+                    // Still free the batch allocated by the loader thread
+                    drop(Box::from_raw(wparam.0 as *mut Vec<MetadataEntry>));
+                }
+                LRESULT(0)
+            }
+            WM_GIT_STATUS_LOADED => {
+                if let Some(state) = app_state(window) {
+                    state.handle_git_status_loaded(wparam.0);
+                } else {
+                    drop(Box::from_raw(wparam.0 as *mut Vec<GitStatusEntry>));
+                }
+                LRESULT(0)
+            }
+            WM_LINK_INFO_LOADED => {
+                if let Some(state) = app_state(window) {
+                    state.handle_link_info_loaded(wparam.0);
+                } else {
+                    drop(Box::from_raw(wparam.0 as *mut Vec<LinkInfoEntry>));
+                }
+                LRESULT(0)
+            }
+            WM_MEDIA_INFO_LOADED => {
+                if let Some(state) = app_state(window) {
+                    state.handle_media_info_loaded(wparam.0);
+                } else {
+                    drop(Box::from_raw(wparam.0 as *mut Vec<MediaInfoEntry>));
+                }
+                LRESULT(0)
+            }
+            WM_SHARE_STATUS_CHANGED => {
+                // Rows on the share get or lose their offline badge
+                if let Some(state) = app_state(window) {
+                    InvalidateRect(state.list_view, None, FALSE);
+                }
+                LRESULT(0)
+            }
+            WM_OVER_MEMORY_BUDGET => {
+                if let Some(state) = app_state(window) {
+                    state.confirm_over_memory_budget(wparam.0, lparam.0 as u64);
+                }
+                LRESULT(0)
+            }
+            WM_INDEX_PAGE => {
+                if let Some(state) = app_state(window) {
+                    state.handle_index_page(wparam.0);
+                } else {
+                    drop(Box::from_raw(wparam.0 as *mut IndexPage));
+                }
+                LRESULT(0)
+            }
+            WM_LIST_VALIDATED => {
+                if let Some(state) = app_state(window) {
+                    state.handle_list_validated(wparam.0 as isize);
+                } else {
+                    // Still free the results allocated by the validation thread
+                    drop(Box::from_raw(wparam.0 as *mut (Vec<String>, u64)));
+                }
+                LRESULT(0)
+            }
+            WM_CONTACT_SHEET_DONE => {
+                if let Some(state) = app_state(window) {
+                    state.handle_contact_sheet_done(wparam.0 as isize);
+                } else {
+                    drop(Box::from_raw(wparam.0 as *mut ContactSheetResult));
+                }
+                LRESULT(0)
+            }
+            WM_LIST_FILE_CHANGED => {
+                if let Some(state) = app_state(window) {
+                    state.handle_list_file_changed(wparam.0 as u64);
+                }
+                LRESULT(0)
+            }
+            WM_MONITORED_FOLDER_CHANGED => {
+                if let Some(state) = app_state(window) {
+                    state.handle_monitored_folder_changed(wparam.0 as isize);
+                } else {
+                    drop(Box::from_raw(wparam.0 as *mut (u64, Vec<FolderChange>)));
+                }
+                LRESULT(0)
+            }
+            WM_MONITORED_FOLDER_SCANNED => {
+                if let Some(state) = app_state(window) {
+                    state.handle_monitored_folder_scanned(wparam.0 as isize);
+                } else {
+                    drop(Box::from_raw(wparam.0 as *mut (u64, Vec<FileResult>)));
+                }
+                LRESULT(0)
+            }
+            WM_LOG_SETTINGS_CHANGED => {
+                // Keep the log window's choices for the next launch
+                if let Some(state) = app_state(window) {
+                    state.config.log_level = log_level();
+                    state.config.log_to_file = log_to_file();
+                    if let Err(e) = save_config(&state.config) {
+                        log_error(&format!("Failed to save config: {}", e));
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_TRANSLATIONS_CHANGED => {
+                // The language file was reloaded; rebuild everything that shows its text
+                if let Some(state) = app_state(window) {
+                    let _ = recreate_menus_with_language(window, state);
+                    InvalidateRect(state.list_view, None, TRUE);
+                    update_status_bar(state);
+                }
+                LRESULT(0)
+            }
+            WM_TRAY_ICON => {
+                match (lparam.0 & 0xFFFF) as u32 {
+                    WM_LBUTTONUP | WM_LBUTTONDBLCLK => {
+                        if let Some(state) = app_state(window) {
+                            restore_from_tray(window, state);
+                        }
+                    }
+                    WM_RBUTTONUP => {
+                        if let Some(state) = app_state(window) {
+                            show_tray_menu(window, state);
+                        }
+                    }
+                    NIN_BALLOONUSERCLICK => {
+                        if let Some(state) = app_state(window) {
+                            if !state.copied_paths.is_empty() {
+                                show_copied_paths_menu(window);
+                            }
+                            state.remove_balloon_tray_icon();
+                        }
+                    }
+                    NIN_BALLOONTIMEOUT => {
+                        if let Some(state) = app_state(window) {
+                            state.remove_balloon_tray_icon();
+                        }
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_ACTIVATE => {
+                if (wparam.0 & 0xFFFF) as u32 == WA_INACTIVE {
+                    if let Some(state) = app_state(window) {
+                        state.last_focus = GetFocus();
+                    }
+                }
+                DefWindowProcW(window, message, wparam, lparam)
+            }
+            // Activation focuses the frame itself, which takes no keys
+            WM_SETFOCUS => {
+                if let Some(state) = app_state(window) {
+                    if state.last_focus == state.list_view {
+                        SetFocus(state.list_view);
+                    } else if state.folder_tree.as_ref().map_or(false, |folder_tree| folder_tree.window == state.last_focus) {
+                        SetFocus(state.last_focus);
+                    } else {
+                        SetFocus(state.search_edit);
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_CLIPBOARDUPDATE => {
+                if let Some(state) = app_state(window) {
+                    if state.config.clipboard_watcher {
+                        state.offer_copied_paths();
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_COPYDATA => {
+                let data = &*(lparam.0 as *const COPYDATASTRUCT);
+                if ![COPYDATA_SEARCH_IN, COPYDATA_SEARCH, COPYDATA_OPEN_LIST].contains(&data.dwData) {
+                    return LRESULT(0);
+                }
+                
+                let units = std::slice::from_raw_parts(data.lpData as *const u16, data.cbData as usize / 2);
+                let text = String::from_utf16_lossy(units);
+                if let Some(state) = app_state(window) {
+                    match data.dwData {
+                        COPYDATA_SEARCH_IN => {
+                            restore_from_tray(window, state);
+                            state.search_in_folder(&text);
+                        }
+                        COPYDATA_SEARCH => state.run_search(&text),
+                        _ => {
+                            restore_from_tray(window, state);
+                            open_file_list(window, state, &text);
+                        }
+                    }
+                }
+                LRESULT(1)
+            }
+            WM_REMOTE_FAILED => {
+                let failure = Box::from_raw(wparam.0 as *mut (RemoteServer, String));
+                if let Some(state) = app_state(window) {
+                    state.handle_remote_failed(&failure.0, &failure.1);
+                }
+                LRESULT(0)
+            }
+            WM_DOWNLOAD_DONE => {
+                let done = Box::from_raw(wparam.0 as *mut DownloadDone);
+                if let Some(state) = app_state(window) {
+                    state.handle_download_done(*done);
+                }
+                LRESULT(0)
+            }
+            WM_FILES_RECYCLED => {
+                let deleted = Box::from_raw(wparam.0 as *mut Vec<String>);
+                if let Some(state) = app_state(window) {
+                    state.forget_deleted_files(&deleted);
+                }
+                LRESULT(0)
+            }
+            WM_SCHEDULED_EXPORT_DONE => {
+                let done = Box::from_raw(wparam.0 as *mut ScheduledExportDone);
+                if let Some(state) = app_state(window) {
+                    state.handle_scheduled_export_done(*done);
+                }
+                LRESULT(0)
+            }
+            WM_UPDATE_CHECK_DONE => {
+                let result = Box::from_raw(wparam.0 as *mut std::result::Result<Release, String>);
+                if let Some(state) = app_state(window) {
+                    state.handle_update_check_done(*result);
+                }
+                LRESULT(0)
+            }
+            WM_UPDATE_FOUND => {
+                let release = Box::from_raw(wparam.0 as *mut Release);
+                if let Some(state) = app_state(window) {
+                    state.offer_update(*release);
+                }
+                LRESULT(0)
+            }
+            WM_UPDATE_PROGRESS => {
+                if let Some(state) = app_state(window) {
+                    if state.update_progress.is_some() {
+                        state.update_progress = Some(wparam.0 as u32);
+                        update_status_bar(state);
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_UPDATE_DOWNLOADED => {
+                let result = Box::from_raw(wparam.0 as *mut std::result::Result<PathBuf, String>);
+                if let Some(state) = app_state(window) {
+                    state.handle_update_downloaded(*result);
+                }
+                LRESULT(0)
+            }
+            WM_QUICK_SEARCH => {
+                let query = Box::from_raw(wparam.0 as *mut String);
+                if let Some(state) = app_state(window) {
+                    state.run_search(&query);
+                }
+                LRESULT(0)
+            }
+            WM_HOTKEY => {
+                if wparam.0 as i32 == QUICK_SEARCH_HOTKEY_ID {
+                    if let Some(state) = app_state(window) {
+                        show_quick_search(window, state.font);
                     }
-                    ID_COLUMN_SIZE => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.toggle_column(ColumnType::Size);
-                        }
+                } else if wparam.0 as i32 == LAUNCHER_HOTKEY_ID {
+                    if let Some(state) = app_state(window) {
+                        toggle_launcher(window, state.font);
                     }
-                    ID_COLUMN_TYPE => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.toggle_column(ColumnType::Type);
-                        }
+                }
+                LRESULT(0)
+            }
+            WM_LAUNCHER_QUERY => {
+                let query = Box::from_raw(wparam.0 as *mut String);
+                if let Some(state) = app_state(window) {
+                    state.search_for_launcher(*query);
+                }
+                LRESULT(0)
+            }
+            WM_LAUNCHER_RESULTS => {
+                if let Some(state) = app_state(window) {
+                    state.handle_launcher_results(wparam.0);
+                } else {
+                    drop(Box::from_raw(wparam.0 as *mut SearchBatch));
+                }
+                LRESULT(0)
+            }
+            WM_LAUNCHER_OPEN | WM_OPEN_HISTORY_OPEN => {
+                let path = Box::from_raw(wparam.0 as *mut String);
+                if let Some(state) = app_state(window) {
+                    state.note_opened(&path);
+                }
+                open_file(window, &path);
+                LRESULT(0)
+            }
+            WM_SYNTAX_INSERT => {
+                let syntax = Box::from_raw(wparam.0 as *mut String);
+                if let Some(state) = app_state(window) {
+                    state.insert_search_syntax(&syntax);
+                }
+                LRESULT(0)
+            }
+            WM_OPEN_HISTORY_RECORD => {
+                if let Some(state) = app_state(window) {
+                    state.set_record_open_history(wparam.0 != 0);
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                if let Some(state) = app_state(window) {
+                    if state.config.close_to_tray {
+                        hide_to_tray(window, state);
+                        return LRESULT(0);
                     }
-                    ID_COLUMN_MODIFIED => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.toggle_column(ColumnType::Modified);
+                }
+                DefWindowProcW(window, message, wparam, lparam)
+            }
+            WM_APPLY_SETTINGS => {
+                let settings = Box::from_raw(wparam.0 as *mut AppConfig);
+                if let Some(state) = app_state(window) {
+                    state.apply_settings(*settings);
+                }
+                LRESULT(0)
+            }
+            WM_NOTIFY => {
+                let header = &*(lparam.0 as *const NMHDR);
+                if header.idFrom == ID_SEARCH_TABS as usize {
+                    if let Some(state) = app_state(window) {
+                        match header.code {
+                            TCN_SELCHANGE => {
+                                let index = SendMessageW(header.hwndFrom, TCM_GETCURSEL, WPARAM(0), LPARAM(0)).0;
+                                if index >= 0 {
+                                    state.select_search_tab(index as usize);
+                                }
+                            }
+                            NM_RCLICK => {
+                                show_search_tab_menu(window, state);
+                                return LRESULT(1);
+                            }
+                            _ => {}
                         }
                     }
-                    ID_COLUMN_PATH => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.toggle_column(ColumnType::Path);
+                }
+                if header.idFrom == ID_FOLDER_TREE as usize {
+                    if let Some(state) = app_state(window) {
+                        if header.code == TVN_KEYDOWN && { (*(lparam.0 as *const NMTVKEYDOWN)).wVKey } == 0x09 {
+                            // VK_TAB: back to the search box
+                            focus_search_edit(state);
+                            return LRESULT(1);
                         }
-                    }
-                    // Sort options
-                    ID_SORT_ASCENDING => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.change_sort_order(SortOrder::Ascending);
+                        if header.code == NM_RCLICK {
+                            show_sidebar_menu(window, state);
+                            return LRESULT(1);
                         }
-                    }
-                    ID_SORT_DESCENDING => {
-                        if let Some(state) = &mut APP_STATE {
-                            state.change_sort_order(SortOrder::Descending);
+                        if let Some(item) = state.folder_tree.as_mut().and_then(|folder_tree| folder_tree.handle_notify(header.code, lparam)) {
+                            state.open_sidebar_item(item);
                         }
                     }
-                    _ => {}
-                }
-                LRESULT(0)
-            }
-            WM_SEARCH_RESULTS => {
-                if let Some(state) = &mut APP_STATE {
-                    log_debug("Received WM_SEARCH_RESULTS message");
-                    let results_ptr = wparam.0 as isize;
-                    log_debug("APP_STATE is available, calling handle_search_results");
-                    state.handle_search_results(results_ptr);
-                    log_debug("handle_search_results completed");
-                } else {
-                    log_debug("WARNING: WM_SEARCH_RESULTS received but APP_STATE is None");
                 }
-                LRESULT(0)
+                DefWindowProcW(window, message, wparam, lparam)
             }
             WM_TIMER => {
                 let timer_id = wparam.0 as usize;
-                log_debug(&format!("Received WM_TIMER message with ID: {}", timer_id));
+                log_trace(&format!("Received WM_TIMER message with ID: {}", timer_id));
                 
                 if timer_id == SEARCH_TIMER_ID {
                     log_debug("Search timer expired, executing delayed search");
-                    if let Some(state) = &mut APP_STATE {
+                    if let Some(state) = app_state(window) {
                         // Kill the timer
                         let _ = KillTimer(state.main_window, SEARCH_TIMER_ID as usize);
                         state.search_timer_active = false;
@@ -3645,186 +12065,634 @@ extern "system" fn main_window_proc(
                         log_debug(&format!("Executing delayed search for: '{}'", search_text));
                         state.start_async_search(search_text);
                     }
+                } else if timer_id == SORT_PROGRESS_TIMER_ID {
+                    if let Some(state) = app_state(window) {
+                        update_status_bar(state);
+                    }
+                } else if timer_id == PERF_HUD_TIMER_ID {
+                    if let Some(state) = app_state(window) {
+                        InvalidateRect(state.list_view, Some(&perf_hud_rect(state)), FALSE);
+                    }
+                } else if timer_id == FOLDER_TREE_TIMER_ID {
+                    let _ = KillTimer(window, FOLDER_TREE_TIMER_ID);
+                    if let Some(state) = app_state(window) {
+                        state.reveal_selected_folder();
+                    }
+                } else if timer_id == PREVIEW_TIMER_ID {
+                    let _ = KillTimer(window, PREVIEW_TIMER_ID);
+                    if let Some(state) = app_state(window) {
+                        state.preview_selected_file();
+                    }
+                } else if timer_id == SCHEDULE_TIMER_ID {
+                    if let Some(state) = app_state(window) {
+                        state.run_due_exports(false);
+                    }
                 }
                 LRESULT(0)
             }
             WM_RECOMPUTE_THUMBS => {
-                log_debug("Received WM_RECOMPUTE_THUMBS message");
-                if let Some(state) = &APP_STATE {
-                    log_debug("APP_STATE is available, checking if scrollbar is being dragged");
+                log_trace("Received WM_RECOMPUTE_THUMBS message");
+                if let Some(state) = app_state(window) {
+                    log_trace("App state is available, checking if scrollbar is being dragged");
                     if !state.is_scrollbar_dragging {
-                        log_debug("Not dragging, calling recompute_thumbnail_queue");
+                        log_trace("Not dragging, calling recompute_thumbnail_queue");
                         state.recompute_thumbnail_queue();
-                        log_debug("recompute_thumbnail_queue completed");
+                        log_trace("recompute_thumbnail_queue completed");
                     } else {
-                        log_debug("Currently dragging scrollbar, skipping thumbnail recomputation");
+                        log_trace("Currently dragging scrollbar, skipping thumbnail recomputation");
                     }
                 } else {
-                    log_debug("WARNING: WM_RECOMPUTE_THUMBS received but APP_STATE is None");
+                    log_warn("WM_RECOMPUTE_THUMBS received but the app state is missing");
                 }
-                log_debug("WM_RECOMPUTE_THUMBS handler completed");
+                log_trace("WM_RECOMPUTE_THUMBS handler completed");
                 LRESULT(0)
             }
             WM_DESTROY => {
+                if let Some(state) = app_state(window) {
+                    state.save_on_exit();
+                    state.install_pending_update();
+                    if state.tray_icon_visible {
+                        remove_tray_icon(window);
+                    }
+                    if state.config.quick_search_hotkey {
+                        state.set_quick_search_hotkey(false);
+                    }
+                    if state.config.launcher_hotkey {
+                        state.set_launcher_hotkey(false);
+                    }
+                    // Handlers release their files while the pane is still there
+                    if let Some(preview_pane) = state.preview_pane.take() {
+                        preview_pane.destroy();
+                    }
+                }
                 PostQuitMessage(0);
                 LRESULT(0)
             }
+            WM_NCDESTROY => {
+                // Last message the window gets; free the state it owns
+                let state = SetWindowLongPtrW(window, GWLP_USERDATA, 0) as *mut AppState;
+                if !state.is_null() {
+                    drop(Box::from_raw(state));
+                }
+                DefWindowProcW(window, message, wparam, lparam)
+            }
             _ => DefWindowProcW(window, message, wparam, lparam),
         }
     }
 }
 
+fn open_file_list(window: HWND, state: &mut AppState, file_path: &str) {
+    unsafe {
+        match state.load_file_list(file_path) {
+            Ok(_) => {
+                update_scrollbar(state);
+                InvalidateRect(state.list_view, None, TRUE);
+                update_status_bar(state);
+                state.add_recent_list(file_path);
+                log_info(&format!("Successfully loaded file list: {}", file_path));
+            }
+            Err(e) => {
+                let message = format!("Failed to load file list: {}", e);
+                let message_wide: Vec<u16> = message.encode_utf16().chain(std::iter::once(0)).collect();
+                let title_wide: Vec<u16> = "Error".encode_utf16().chain(std::iter::once(0)).collect();
+                
+                MessageBoxW(
+                    window,
+                    PCWSTR::from_raw(message_wide.as_ptr()),
+                    PCWSTR::from_raw(title_wide.as_ptr()),
+                    MB_ICONERROR | MB_OK,
+                );
+            }
+        }
+    }
+}
+
 fn show_simple_file_input_dialog(_window: HWND, _title: &str) -> Option<String> {
     // For demonstration, return a default path
     Some("file_list.txt".to_string())
 }
 
-fn handle_immediate_search() {
+fn handle_immediate_search(state: &mut AppState) {
     unsafe {
-        if let Some(state) = &mut APP_STATE {
-            log_debug("handle_immediate_search called");
-            
-            // Kill existing timer if active
-            if state.search_timer_active {
-                let _ = KillTimer(state.main_window, SEARCH_TIMER_ID as usize);
-                state.search_timer_active = false;
-                log_debug("Killed existing search timer for immediate search");
+        log_debug("handle_immediate_search called");
+        
+        // Kill existing timer if active
+        if state.search_timer_active {
+            let _ = KillTimer(state.main_window, SEARCH_TIMER_ID);
+            state.search_timer_active = false;
+            log_debug("Killed existing search timer for immediate search");
+        }
+        
+        // Get current text from search edit control
+        let mut buffer: [u16; 1024] = [0; 1024];
+        let len = GetWindowTextW(state.search_edit, &mut buffer);
+        
+        let search_text = if len > 0 {
+            String::from_utf16_lossy(&buffer[..len as usize])
+        } else {
+            String::new()
+        };
+        
+        log_debug(&format!("Immediate search for: '{}'", search_text));
+        
+        // Start async search immediately
+        state.start_async_search(search_text);
+        
+        log_debug("handle_immediate_search completed");
+    }
+}
+
+fn update_status_bar(state: &AppState) {
+    unsafe {
+        log_trace("update_status_bar called");
+
+        log_trace(&format!("Status bar update: {} items total", state.list_data.len()));
+        let strings = get_strings();
+
+        let objects = strings.status_objects.format(state.list_data.len() as u64);
+        let status_text = if state.selected_items.len() > 1 {
+            let selected = strings.status_items_selected.format(state.selected_items.len() as u64);
+            // Folders count as nothing; their size isn't known
+            let total: u64 = state
+                .selected_items
+                .iter()
+                .filter_map(|&index| state.list_data.get(index))
+                .filter(|file| !file.is_folder)
+                .map(|file| file.size)
+                .sum();
+            if total > 0 {
+                format!("{} | {} ({})", objects, selected, format_size(total, state.config.size_format, &strings))
+            } else {
+                format!("{} | {}", objects, selected)
             }
-            
-            // Get current text from search edit control
-            let mut buffer: [u16; 1024] = [0; 1024];
-            let len = GetWindowTextW(state.search_edit, &mut buffer);
-            
-            let search_text = if len > 0 {
-                String::from_utf16_lossy(&buffer[..len as usize])
+        } else if let Some(selected) = state.selected_index {
+            if selected < state.list_data.len() {
+                let file = &state.list_data[selected];
+                // A remote path would be looked up on this machine
+                let file_info = if state.showing_remote_results() {
+                    format!("({})", file.format_size(state.config.size_format, &strings))
+                } else {
+                    get_file_info(file.path(), state.config.size_format, &strings)
+                };
+                let selected_file = fill(&strings.status_selected_file, &[("name", file.name()), ("info", &file_info)]);
+
+                format!("{} | {}", objects, selected_file)
+            } else {
+                objects
+            }
+        } else {
+            objects
+        };
+
+        // Add list name if in list mode
+        let final_status = if state.is_list_mode {
+            let list_status = if let Some(ref list_name) = state.current_list_name {
+                format!("{} | {}", status_text, fill(&strings.status_list, &[("name", list_name)]))
             } else {
-                String::new()
+                format!("{} | {}", status_text, strings.status_list_mode)
             };
             
-            log_debug(&format!("Immediate search for: '{}'", search_text));
+            if state.missing_count > 0 {
+                format!("{} | {}", list_status, strings.status_missing.format(state.missing_count as u64))
+            } else {
+                list_status
+            }
+        } else if let Some(ref server) = state.remote_server {
+            format!("{} | {}", status_text, fill(&strings.status_remote, &[("name", &server.display_name())]))
+        } else {
+            status_text
+        };
+        
+        let final_status = match state.active_filter {
+            Some(ref filter) if !state.is_list_mode => {
+                format!("{} | {}", final_status, fill(&strings.status_filter, &[("name", &filter.name)]))
+            }
+            _ => final_status,
+        };
+        
+        let final_status = match state.search_scope {
+            Some(ref folder) if !state.is_list_mode => {
+                format!("{} | {}", final_status, fill(&strings.status_scope, &[("path", folder)]))
+            }
+            _ => final_status,
+        };
+        
+        // Progress of a sort running in the background
+        let final_status = match state.sort_task {
+            Some(ref sort_task) => {
+                let percent = sort_task.percent().to_string();
+                format!("{} | {}", final_status, fill(&strings.status_sorting, &[("percent", &percent)]))
+            }
+            None => final_status,
+        };
+        
+        let final_status = match state.update_progress {
+            Some(percent) => {
+                format!("{} | {}", final_status, fill(&strings.status_update_download, &[("percent", &percent.to_string())]))
+            }
+            None => final_status,
+        };
+        
+        // Roughly what the results and thumbnails hold on to
+        let memory = results_bytes(&state.list_data) + thumbnail_cache_bytes(&state.thumbnail_cache);
+        let final_status = format!(
+            "{} | {}",
+            final_status,
+            fill(&strings.status_memory, &[("size", &format_size(memory, SizeFormat::Auto, &strings))])
+        );
+
+        log_trace(&format!("Setting status text: '{}'", final_status));
+        let status_utf16: Vec<u16> = final_status.encode_utf16().chain(std::iter::once(0)).collect();
+        let _ = SetWindowTextW(state.status_bar, PCWSTR::from_raw(status_utf16.as_ptr()));
+        log_trace("update_status_bar completed successfully");
+    }
+}
+
+fn get_file_info(path: &str, size_format: SizeFormat, strings: &LanguageStrings) -> String {
+    match fs::metadata(path) {
+        Ok(metadata) => format!("({})", format_size(metadata.len(), size_format, strings)),
+        Err(_) => String::new(),
+    }
+}
+
+// Opens `path` with its associated program. When that is refused for lack
+// of rights, the user can have it opened as administrator instead.
+fn open_file(window: HWND, path: &str) {
+    let result = shell_open(path);
+    if result > 32 {
+        return;
+    }
+    
+    log_warn(&format!("Failed to open {} (error {})", path, result));
+    if is_access_denied(result as u32) && confirm_elevated_retry(window, &[path.to_string()]) {
+        open_elevated(path);
+    }
+}
+
+// The shell's Properties dialog for `path`
+fn show_file_properties(window: HWND, path: &str) {
+    unsafe {
+        let path_utf16 = to_wide(&shell_path(path));
+        let mut info = SHELLEXECUTEINFOW {
+            cbSize: std::mem::size_of::<SHELLEXECUTEINFOW>() as u32,
+            fMask: SEE_MASK_INVOKEIDLIST,
+            hwnd: window,
+            lpVerb: w!("properties"),
+            lpFile: PCWSTR::from_raw(path_utf16.as_ptr()),
+            nShow: SW_SHOWNORMAL.0,
+            ..Default::default()
+        };
+        if ShellExecuteExW(&mut info).is_err() {
+            log_warn(&format!("Failed to show properties of {}", path));
+        }
+    }
+}
+
+// Remote results can't be shown in Explorer, so they get Download instead
+fn show_file_context_menu(window: HWND, x: i32, y: i32, state: &AppState) {
+    unsafe {
+        let hmenu = CreatePopupMenu().unwrap();
+        let strings = get_strings();
+        let remote = state.showing_remote_results();
+        let tools = &state.config.external_tools;
+        
+        let _ = AppendMenuW(hmenu, MF_STRING, ID_OPEN_FILE as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.ctx_open).as_ptr()));
+        
+        if !remote {
+            let _ = AppendMenuW(hmenu, MF_STRING, ID_QUICK_VIEW as usize, 
+                               PCWSTR::from_raw(to_wide(&strings.ctx_quick_view).as_ptr()));
+            let selected = state.selected_index.and_then(|index| state.list_data.get(index));
+            if selected.is_some_and(|file| is_video(file.extension())) {
+                let _ = AppendMenuW(hmenu, MF_STRING, ID_PREVIEW_VIDEO as usize, 
+                                   PCWSTR::from_raw(to_wide(&strings.ctx_preview_video).as_ptr()));
+            }
+            if selected.is_some_and(|file| is_archive(file.extension())) {
+                let _ = AppendMenuW(hmenu, MF_STRING, ID_BROWSE_ARCHIVE as usize, 
+                                   PCWSTR::from_raw(to_wide(&strings.ctx_browse_archive).as_ptr()));
+            }
+            if selected.is_some_and(|file| is_checksum_manifest(file.extension())) {
+                let _ = AppendMenuW(hmenu, MF_STRING, ID_VERIFY_CHECKSUMS as usize, 
+                                   PCWSTR::from_raw(to_wide(&strings.ctx_verify_checksums).as_ptr()));
+            }
+            if state.compared_pair().is_some() {
+                let compare_submenu = CreatePopupMenu().unwrap();
+                let _ = AppendMenuW(compare_submenu, MF_STRING, ID_COMPARE_CONTENTS as usize, 
+                                   PCWSTR::from_raw(to_wide(&strings.compare_contents).as_ptr()));
+                let _ = AppendMenuW(compare_submenu, MF_STRING, ID_COMPARE_IN_DIFF_TOOL as usize, 
+                                   PCWSTR::from_raw(to_wide(&strings.compare_in_diff_tool).as_ptr()));
+                let _ = AppendMenuW(hmenu, MF_STRING | MF_POPUP, compare_submenu.0 as usize, 
+                                   PCWSTR::from_raw(to_wide(&strings.ctx_compare).as_ptr()));
+            }
+        }
+        
+        let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+        
+        if remote {
+            let _ = AppendMenuW(hmenu, MF_STRING, ID_DOWNLOAD_FILE as usize, 
+                               PCWSTR::from_raw(to_wide(&strings.ctx_download).as_ptr()));
+        } else {
+            let _ = AppendMenuW(hmenu, MF_STRING, ID_OPEN_FILE_LOCATION as usize, 
+                               PCWSTR::from_raw(to_wide(&strings.ctx_open_location).as_ptr()));
+            let _ = AppendMenuW(hmenu, MF_STRING, ID_BROWSE_FOLDER_IN_TAB as usize, 
+                               PCWSTR::from_raw(to_wide(&strings.ctx_browse_folder_in_tab).as_ptr()));
             
-            // Start async search immediately
-            state.start_async_search(search_text);
+            let terminal_submenu = CreatePopupMenu().unwrap();
+            let _ = AppendMenuW(terminal_submenu, MF_STRING, ID_OPEN_CMD as usize, 
+                               PCWSTR::from_raw(to_wide(&strings.terminal_cmd).as_ptr()));
+            let _ = AppendMenuW(terminal_submenu, MF_STRING, ID_OPEN_POWERSHELL as usize, 
+                               PCWSTR::from_raw(to_wide(&strings.terminal_powershell).as_ptr()));
+            let _ = AppendMenuW(terminal_submenu, MF_STRING, ID_OPEN_WINDOWS_TERMINAL as usize, 
+                               PCWSTR::from_raw(to_wide(&strings.terminal_windows_terminal).as_ptr()));
+            let _ = AppendMenuW(terminal_submenu, MF_STRING, ID_OPEN_WSL as usize, 
+                               PCWSTR::from_raw(to_wide(&strings.terminal_wsl).as_ptr()));
+            let _ = AppendMenuW(hmenu, MF_STRING | MF_POPUP, terminal_submenu.0 as usize, 
+                               PCWSTR::from_raw(to_wide(&strings.ctx_open_terminal).as_ptr()));
+        }
+        
+        let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+        
+        let _ = AppendMenuW(hmenu, MF_STRING, ID_COPY_PATH as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.ctx_copy_path).as_ptr()));
+        
+        let _ = AppendMenuW(hmenu, MF_STRING, ID_COPY_NAME as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.ctx_copy_name).as_ptr()));
+        
+        let _ = AppendMenuW(hmenu, MF_STRING, ID_COPY_AS_TABLE as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.ctx_copy_as_table).as_ptr()));
+        
+        if !remote {
+            let _ = AppendMenuW(hmenu, MF_STRING, ID_COPY_WSL_PATH as usize, 
+                               PCWSTR::from_raw(to_wide(&strings.ctx_copy_wsl_path).as_ptr()));
             
-            log_debug("handle_immediate_search completed");
-        } else {
-            log_debug("WARNING: handle_immediate_search called but APP_STATE is None");
+            let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+            let tags_submenu = CreatePopupMenu().unwrap();
+            append_tag_items(tags_submenu, state);
+            let _ = AppendMenuW(hmenu, MF_STRING | MF_POPUP, tags_submenu.0 as usize, 
+                               PCWSTR::from_raw(to_wide(&strings.ctx_tags).as_ptr()));
+            let rating_submenu = CreatePopupMenu().unwrap();
+            append_rating_items(rating_submenu, state);
+            let _ = AppendMenuW(hmenu, MF_STRING | MF_POPUP, rating_submenu.0 as usize, 
+                               PCWSTR::from_raw(to_wide(&strings.ctx_rating).as_ptr()));
+            if state.is_list_mode {
+                let any_noted = state.get_selected_results().iter().any(|file| state.list_notes.note_of(file.path()).is_some());
+                let remove_flags = if any_noted { MF_STRING } else { MF_STRING | MF_GRAYED };
+                let _ = AppendMenuW(hmenu, MF_STRING, ID_EDIT_NOTE as usize, 
+                                   PCWSTR::from_raw(to_wide(&strings.ctx_edit_note).as_ptr()));
+                let _ = AppendMenuW(hmenu, remove_flags, ID_REMOVE_NOTE as usize, 
+                                   PCWSTR::from_raw(to_wide(&strings.ctx_remove_note).as_ptr()));
+            }
+            let _ = AppendMenuW(hmenu, MF_STRING, ID_EXCLUDE_FOLDER as usize, 
+                               PCWSTR::from_raw(to_wide(&strings.ctx_exclude_folder).as_ptr()));
+        }
+        
+        if !tools.is_empty() && !remote {
+            let tools_submenu = CreatePopupMenu().unwrap();
+            append_tool_items(tools_submenu, tools, remote);
+            let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+            let _ = AppendMenuW(hmenu, MF_STRING | MF_POPUP, tools_submenu.0 as usize, 
+                               PCWSTR::from_raw(to_wide(&strings.menu_tools).as_ptr()));
         }
+        
+        assign_menu_mnemonics(hmenu);
+        let _ = TrackPopupMenu(
+            hmenu, 
+            TPM_RIGHTALIGN | TPM_TOPALIGN, 
+            x, y, 0, 
+            window, 
+            None
+        );
+        
+        let _ = DestroyMenu(hmenu);
     }
 }
 
-fn update_status_bar() {
+// The existing tags, checked when every selected result has the tag, then
+// New Tag and Remove All Tags
+fn append_tag_items(hmenu: HMENU, state: &AppState) {
     unsafe {
-        log_debug("update_status_bar called");
+        let strings = get_strings();
+        let selected = state.get_selected_results();
+        
+        for (index, tag) in state.tags.tags().iter().take(MAX_TAGS).enumerate() {
+            let tagged = !selected.is_empty() && selected.iter().all(|file| {
+                state.tags.tags_of(file.path()).iter().any(|name| name.eq_ignore_ascii_case(&tag.name))
+            });
+            let flags = if tagged { MF_STRING | MF_CHECKED } else { MF_STRING };
+            let _ = AppendMenuW(
+                hmenu,
+                flags,
+                (ID_TAG_FIRST + index as i32) as usize,
+                PCWSTR::from_raw(to_wide(&tag.name.replace('&', "&&")).as_ptr()),
+            );
+        }
+        if !state.tags.tags().is_empty() {
+            let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+        }
+        
+        let _ = AppendMenuW(hmenu, MF_STRING, ID_TAG_NEW as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.tags_new).as_ptr()));
+        let any_tagged = selected.iter().any(|file| !state.tags.tags_of(file.path()).is_empty());
+        let clear_flags = if any_tagged { MF_STRING } else { MF_STRING | MF_GRAYED };
+        let _ = AppendMenuW(hmenu, clear_flags, ID_TAG_CLEAR as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.tags_clear).as_ptr()));
+    }
+}
 
-        if let Some(state) = &APP_STATE {
-            log_debug(&format!("Status bar update: {} items total", state.list_data.len()));
-            let strings = get_strings();
+// No Rating and one to MAX_RATING stars, the rating every selected result
+// has checked
+fn append_rating_items(hmenu: HMENU, state: &AppState) {
+    unsafe {
+        let selected = state.get_selected_results();
+        let shared = selected.first().map(|file| state.tags.rating_of(file.path()))
+            .filter(|&rating| selected.iter().all(|file| state.tags.rating_of(file.path()) == rating));
+        for rating in 0..=MAX_RATING {
+            let label = if rating == 0 { get_strings().rating_none } else { "★".repeat(rating as usize) };
+            let flags = if shared == Some(rating) { MF_STRING | MF_CHECKED } else { MF_STRING };
+            let _ = AppendMenuW(hmenu, flags, (ID_RATING_FIRST + rating as i32) as usize, PCWSTR::from_raw(to_wide(&label).as_ptr()));
+        }
+    }
+}
 
-            let status_text = if let Some(selected) = state.selected_index {
-                if selected < state.list_data.len() {
-                    let file = &state.list_data[selected];
-                    let file_info = get_file_info(&file.path);
-
-                    format!("{} {} | {}: {} {}",
-                        state.list_data.len(),
-                        strings.status_objects,
-                        strings.status_selected,
-                        file.name,
-                        file_info
-                    )
-                } else {
-                    format!("{} {}", state.list_data.len(), strings.status_objects)
-                }
-            } else {
-                format!("{} {}", state.list_data.len(), strings.status_objects)
-            };
+// Hides the main window, leaving only the notification area icon
+fn hide_to_tray(window: HWND, state: &mut AppState) {
+    unsafe {
+        if !state.tray_icon_visible {
+            state.tray_icon_visible = add_tray_icon(window, "Everything-like File Browser");
+        }
+        
+        // Without an icon there would be no way back to the window
+        if state.tray_icon_visible {
+            ShowWindow(window, SW_HIDE);
+        } else {
+            ShowWindow(window, SW_SHOWMINIMIZED);
+        }
+    }
+}
 
-            // Add list name if in list mode
-            let final_status = if state.is_list_mode {
-                if let Some(ref list_name) = state.current_list_name {
-                    format!("{} | List: {}", status_text, list_name)
-                } else {
-                    format!("{} | List Mode", status_text)
-                }
-            } else {
-                status_text
-            };
+fn restore_from_tray(window: HWND, state: &mut AppState) {
+    unsafe {
+        ShowWindow(window, SW_SHOW);
+        ShowWindow(window, SW_RESTORE);
+        let _ = SetForegroundWindow(window);
+        
+        if state.tray_icon_visible {
+            remove_tray_icon(window);
+            state.tray_icon_visible = false;
+        }
+    }
+}
 
-            log_debug(&format!("Setting status text: '{}'", final_status));
-            let status_utf16: Vec<u16> = final_status.encode_utf16().chain(std::iter::once(0)).collect();
-            let _ = SetWindowTextW(state.status_bar, PCWSTR::from_raw(status_utf16.as_ptr()));
-            log_debug("update_status_bar completed successfully");
-        } else {
-            log_debug("WARNING: update_status_bar called but APP_STATE is None");
+fn show_tray_menu(window: HWND, state: &AppState) {
+    unsafe {
+        let hmenu = CreatePopupMenu().unwrap();
+        let strings = get_strings();
+        
+        let _ = AppendMenuW(hmenu, MF_STRING, ID_TRAY_SHOW as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.tray_show).as_ptr()));
+        let _ = SetMenuDefaultItem(hmenu, ID_TRAY_SHOW as u32, 0);
+        
+        let _ = AppendMenuW(hmenu, MF_STRING, ID_TRAY_NEW_SEARCH as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.tray_new_search).as_ptr()));
+        let _ = AppendMenuW(hmenu, MF_STRING, ID_TRAY_QUICK_SEARCH as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.tray_quick_search).as_ptr()));
+        let _ = AppendMenuW(hmenu, MF_STRING, ID_TRAY_LAUNCHER as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.tray_launcher).as_ptr()));
+        
+        let recent_submenu = CreatePopupMenu().unwrap();
+        if state.config.recent_searches.is_empty() {
+            let _ = AppendMenuW(recent_submenu, MF_STRING | MF_GRAYED, 0, 
+                               PCWSTR::from_raw(to_wide(&strings.tray_no_recent_searches).as_ptr()));
         }
+        for (i, query) in state.config.recent_searches.iter().take(MAX_RECENT_SEARCHES).enumerate() {
+            // A single & would underline the next character
+            let label = query.replace('&', "&&");
+            let _ = AppendMenuW(recent_submenu, MF_STRING, (ID_TRAY_RECENT_FIRST + i as i32) as usize, 
+                               PCWSTR::from_raw(to_wide(&label).as_ptr()));
+        }
+        let _ = AppendMenuW(hmenu, MF_STRING | MF_POPUP, recent_submenu.0 as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.tray_recent_searches).as_ptr()));
+        
+        let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+        
+        let _ = AppendMenuW(hmenu, MF_STRING, ID_TRAY_EXIT as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.tray_exit).as_ptr()));
+        
+        let mut cursor = POINT::default();
+        let _ = GetCursorPos(&mut cursor);
+        
+        // Required so the menu closes when clicking elsewhere
+        let _ = SetForegroundWindow(window);
+        
+        assign_menu_mnemonics(hmenu);
+        let _ = TrackPopupMenu(
+            hmenu, 
+            TPM_RIGHTALIGN | TPM_BOTTOMALIGN, 
+            cursor.x, cursor.y, 0, 
+            window, 
+            None
+        );
+        
+        let _ = DestroyMenu(hmenu);
     }
 }
 
-fn get_file_info(path: &str) -> String {
-    match fs::metadata(path) {
-        Ok(metadata) => {
-            let size = metadata.len();
-            let size_str = if size > 1024 * 1024 * 1024 {
-                format!("({:.1} GB)", size as f64 / (1024.0 * 1024.0 * 1024.0))
-            } else if size > 1024 * 1024 {
-                format!("({:.1} MB)", size as f64 / (1024.0 * 1024.0))
-            } else if size > 1024 {
-                format!("({:.1} KB)", size as f64 / 1024.0)
-            } else {
-                format!("({} bytes)", size)
-            };
-            size_str
+// Right-click menu of the sidebar: pin the folder or unpin the favorite
+// under the cursor, or pin what the results show now
+fn show_search_tab_menu(window: HWND, state: &mut AppState) {
+    unsafe {
+        let mut cursor = POINT::default();
+        let _ = GetCursorPos(&mut cursor);
+        
+        state.search_tab_menu_index = state.search_tabs.as_ref().and_then(|search_tabs| search_tabs.tab_at(cursor));
+        if state.search_tab_menu_index.is_none() {
+            return;
         }
-        Err(_) => String::new(),
+        
+        let hmenu = CreatePopupMenu().unwrap();
+        let strings = get_strings();
+        let _ = AppendMenuW(hmenu, MF_STRING, ID_CLOSE_SEARCH_TAB as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.close_search_tab).as_ptr()));
+        
+        assign_menu_mnemonics(hmenu);
+        let _ = TrackPopupMenu(
+            hmenu, 
+            TPM_LEFTALIGN | TPM_TOPALIGN, 
+            cursor.x, cursor.y, 0, 
+            window, 
+            None
+        );
+        
+        let _ = DestroyMenu(hmenu);
     }
 }
 
-fn open_file(path: &str) {
+fn show_sidebar_menu(window: HWND, state: &mut AppState) {
     unsafe {
-        let path_utf16: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
-        let operation = w!("open");
+        let hmenu = CreatePopupMenu().unwrap();
+        let strings = get_strings();
         
-        let result = ShellExecuteW(
-            None,
-            operation,
-            PCWSTR::from_raw(path_utf16.as_ptr()),
-            None,
-            None,
-            SW_SHOWNORMAL,
+        state.sidebar_menu_item = state.folder_tree.as_ref().and_then(|folder_tree| folder_tree.item_at_cursor());
+        match state.sidebar_menu_item {
+            Some(SidebarItem::Folder(ref path)) if !path.is_empty() => {
+                let _ = AppendMenuW(hmenu, MF_STRING, ID_SIDEBAR_PIN_FOLDER as usize, 
+                                   PCWSTR::from_raw(to_wide(&strings.sidebar_pin_folder).as_ptr()));
+                let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+            }
+            Some(SidebarItem::Favorite(..)) => {
+                let _ = AppendMenuW(hmenu, MF_STRING, ID_SIDEBAR_UNPIN as usize, 
+                                   PCWSTR::from_raw(to_wide(&strings.sidebar_unpin).as_ptr()));
+                let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+            }
+            _ => {}
+        }
+        
+        let search_flags = if state.is_list_mode || state.searched_query.trim().is_empty() { MF_STRING | MF_GRAYED } else { MF_STRING };
+        let _ = AppendMenuW(hmenu, search_flags, ID_SIDEBAR_PIN_SEARCH as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.sidebar_pin_search).as_ptr()));
+        let list_flags = if state.is_list_mode && state.current_list_path.is_some() { MF_STRING } else { MF_STRING | MF_GRAYED };
+        let _ = AppendMenuW(hmenu, list_flags, ID_SIDEBAR_PIN_LIST as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.sidebar_pin_list).as_ptr()));
+        
+        let mut cursor = POINT::default();
+        let _ = GetCursorPos(&mut cursor);
+        
+        assign_menu_mnemonics(hmenu);
+        let _ = TrackPopupMenu(
+            hmenu, 
+            TPM_LEFTALIGN | TPM_TOPALIGN, 
+            cursor.x, cursor.y, 0, 
+            window, 
+            None
         );
         
-        if result.0 <= 32 {
-            println!("Failed to open file: {}", path);
-        }
+        let _ = DestroyMenu(hmenu);
     }
 }
 
-fn show_file_context_menu(window: HWND, x: i32, y: i32, _file: &FileResult) {
+// Shown when the clipboard watcher's notification is clicked
+fn show_copied_paths_menu(window: HWND) {
     unsafe {
         let hmenu = CreatePopupMenu().unwrap();
         let strings = get_strings();
         
-        let _ = AppendMenuW(hmenu, MF_STRING, ID_OPEN_FILE as usize, 
-                           PCWSTR::from_raw(to_wide(&strings.ctx_open).as_ptr()));
-        
-        let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
-        
-        let _ = AppendMenuW(hmenu, MF_STRING, ID_OPEN_FILE_LOCATION as usize, 
-                           PCWSTR::from_raw(to_wide(&strings.ctx_open_location).as_ptr()));
-        
-        let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+        let _ = AppendMenuW(hmenu, MF_STRING, ID_TRAY_REVEAL_COPIED as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.clipboard_watch_reveal).as_ptr()));
+        let _ = SetMenuDefaultItem(hmenu, ID_TRAY_REVEAL_COPIED as u32, 0);
+        let _ = AppendMenuW(hmenu, MF_STRING, ID_TRAY_ADD_COPIED as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.clipboard_watch_add_to_list).as_ptr()));
         
-        let _ = AppendMenuW(hmenu, MF_STRING, ID_COPY_PATH as usize, 
-                           PCWSTR::from_raw(to_wide(&strings.ctx_copy_path).as_ptr()));
+        let mut cursor = POINT::default();
+        let _ = GetCursorPos(&mut cursor);
         
-        let _ = AppendMenuW(hmenu, MF_STRING, ID_COPY_NAME as usize, 
-                           PCWSTR::from_raw(to_wide(&strings.ctx_copy_name).as_ptr()));
+        // Required so the menu closes when clicking elsewhere
+        let _ = SetForegroundWindow(window);
         
+        assign_menu_mnemonics(hmenu);
         let _ = TrackPopupMenu(
             hmenu, 
-            TPM_RIGHTALIGN | TPM_TOPALIGN, 
-            x, y, 0, 
+            TPM_RIGHTALIGN | TPM_BOTTOMALIGN, 
+            cursor.x, cursor.y, 0, 
             window, 
             None
         );
@@ -3833,7 +12701,7 @@ fn show_file_context_menu(window: HWND, x: i32, y: i32, _file: &FileResult) {
     }
 }
 
-fn show_context_menu(window: HWND, x: i32, y: i32) {
+fn show_context_menu(window: HWND, state: &AppState, x: i32, y: i32) {
     unsafe {
         let hmenu = CreatePopupMenu().unwrap();
         let strings = get_strings();
@@ -3848,16 +12716,15 @@ fn show_context_menu(window: HWND, x: i32, y: i32) {
                            PCWSTR::from_raw(to_wide(&strings.view_extra_large_icons).as_ptr()));
         
         // Check current view mode
-        if let Some(state) = &APP_STATE {
-            let current_id = match state.view_mode {
-                ViewMode::Details => ID_VIEW_DETAILS,
-                ViewMode::MediumIcons => ID_VIEW_MEDIUM_ICONS,
-                ViewMode::LargeIcons => ID_VIEW_LARGE_ICONS,
-                ViewMode::ExtraLargeIcons => ID_VIEW_EXTRALARGE_ICONS,
-            };
-            let _ = CheckMenuItem(hmenu, current_id as u32, MF_CHECKED.0);
-        }
+        let current_id = match state.view_mode {
+            ViewMode::Details => ID_VIEW_DETAILS,
+            ViewMode::MediumIcons => ID_VIEW_MEDIUM_ICONS,
+            ViewMode::LargeIcons => ID_VIEW_LARGE_ICONS,
+            ViewMode::ExtraLargeIcons => ID_VIEW_EXTRALARGE_ICONS,
+        };
+        let _ = CheckMenuItem(hmenu, current_id as u32, MF_CHECKED.0);
         
+        assign_menu_mnemonics(hmenu);
         let _ = TrackPopupMenu(
             hmenu, 
             TPM_RIGHTALIGN | TPM_TOPALIGN, 
@@ -3870,171 +12737,407 @@ fn show_context_menu(window: HWND, x: i32, y: i32) {
     }
 }
 
-fn create_child_controls(parent: HWND) {
+// Column and sorting choices for the details view headers, opened by
+// right-clicking a header or with Ctrl and the Menu key. `column` is the
+// header the menu is about, for Size Column to Fit.
+fn show_header_menu(window: HWND, state: &mut AppState, column: Option<ColumnType>, x: i32, y: i32) {
     unsafe {
-        if let Some(state) = &mut APP_STATE {
-            let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
-            
-            // Create search edit box
-            state.search_edit = CreateWindowExW(
-                WS_EX_CLIENTEDGE,
-                w!("EDIT"),
-                w!("*.png"),
-                WS_CHILD | WS_VISIBLE | WS_BORDER,
-                10, 10, 980, 25,
-                parent,
-                HMENU(ID_SEARCH_EDIT as isize),
-                instance,
-                None,
-            );
+        let hmenu = CreatePopupMenu().unwrap();
+        let strings = get_strings();
+        state.header_menu_column = column;
+        
+        for (id, label) in [
+            (ID_COLUMN_NAME, &strings.column_name),
+            (ID_COLUMN_SIZE, &strings.column_size),
+            (ID_COLUMN_TYPE, &strings.column_type),
+            (ID_COLUMN_MODIFIED, &strings.column_date_modified),
+            (ID_COLUMN_PATH, &strings.column_path),
+            (ID_COLUMN_GIT_STATUS, &strings.column_git),
+            (ID_COLUMN_TAGS, &strings.column_tags),
+            (ID_COLUMN_HARD_LINKS, &strings.column_hard_links),
+            (ID_COLUMN_STREAMS, &strings.column_streams),
+            (ID_COLUMN_DURATION, &strings.column_duration),
+            (ID_COLUMN_BITRATE, &strings.column_bitrate),
+            (ID_COLUMN_NOTES, &strings.column_notes),
+            (ID_COLUMN_RATING, &strings.column_rating),
+        ] {
+            let _ = AppendMenuW(hmenu, MF_STRING, id as usize, PCWSTR::from_raw(to_wide(label).as_ptr()));
+        }
+        let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+        let fit_flags = if column.is_some() { MF_STRING } else { MF_STRING | MF_GRAYED };
+        let _ = AppendMenuW(hmenu, fit_flags, ID_COLUMN_SIZE_TO_FIT as usize,
+                           PCWSTR::from_raw(to_wide(&strings.column_size_to_fit).as_ptr()));
+        let _ = AppendMenuW(hmenu, MF_STRING, ID_COLUMN_RESET_LAYOUT as usize,
+                           PCWSTR::from_raw(to_wide(&strings.column_reset_layout).as_ptr()));
+        let _ = AppendMenuW(hmenu, MF_STRING, ID_COLUMN_MORE as usize,
+                           PCWSTR::from_raw(to_wide(&strings.column_more).as_ptr()));
+        append_alignment_submenu(hmenu, &state.columns, &strings);
+        check_column_items(hmenu, &state.columns);
+        
+        let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+        for (id, label) in [
+            (ID_SORT_NAME, &strings.sort_name),
+            (ID_SORT_SIZE, &strings.sort_size),
+            (ID_SORT_TYPE, &strings.sort_type),
+            (ID_SORT_DATE, &strings.sort_date),
+            (ID_SORT_PATH, &strings.sort_path),
+        ] {
+            let _ = AppendMenuW(hmenu, MF_STRING, id as usize, PCWSTR::from_raw(to_wide(label).as_ptr()));
+        }
+        let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+        let _ = AppendMenuW(hmenu, MF_STRING, ID_SORT_ASCENDING as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.sort_ascending).as_ptr()));
+        let _ = AppendMenuW(hmenu, MF_STRING, ID_SORT_DESCENDING as usize, 
+                           PCWSTR::from_raw(to_wide(&strings.sort_descending).as_ptr()));
+        check_sort_items(hmenu, &state.sort_state);
+        
+        assign_menu_mnemonics(hmenu);
+        let _ = TrackPopupMenu(
+            hmenu, 
+            TPM_LEFTALIGN | TPM_TOPALIGN, 
+            x, y, 0, 
+            window, 
+            None
+        );
+        
+        let _ = DestroyMenu(hmenu);
+    }
+}
 
-            SendMessageW(state.search_edit, WM_SETFONT, WPARAM(state.font.0 as usize), LPARAM(1));
-
-            // Subclass the search edit to handle Enter key
-            ORIGINAL_SEARCH_EDIT_PROC = Some(std::mem::transmute(SetWindowLongPtrW(
-                state.search_edit,
-                GWLP_WNDPROC,
-                search_edit_proc as usize as isize,
-            )));
-
-            // Create custom list view
-            state.list_view = CreateWindowExW(
-                WS_EX_CLIENTEDGE,
-                w!("EverythingLikeListView"),
-                w!(""),
-                WS_CHILD | WS_VISIBLE | WS_VSCROLL | WS_TABSTOP,
-                10, 45, 980, 600,
-                parent,
-                HMENU(ID_LIST_VIEW as isize),
-                instance,
-                None,
-            );
+// Shift+F10 or the Menu key in the list: the file menu below the focused
+// row, or the header menu with Ctrl held or nothing to act on. Menus from
+// the keyboard open at the part of the list they are about instead of at
+// the mouse.
+fn show_keyboard_context_menu(list_view: HWND, state: &mut AppState, ctrl_pressed: bool) {
+    unsafe {
+        let window = GetParent(list_view);
+        let to_screen = |x: i32, y: i32| {
+            let mut point = POINT { x, y };
+            ClientToScreen(list_view, &mut point);
+            point
+        };
+        
+        if state.view_mode == ViewMode::Details && (ctrl_pressed || state.list_data.is_empty()) {
+            // Below the header of the sort column, where the menu's
+            // choices show
+            let sorted = state.sort_state.as_ref().and_then(|sort| {
+                state.get_visible_columns().iter().position(|column| column.column_type == sort.column)
+            });
+            let left = state
+                .child_rect(AccessibleChild::Header(sorted.unwrap_or(0)))
+                .map_or(0, |rect| rect.left);
+            let point = to_screen(left, HEADER_HEIGHT);
+            let column = state.get_visible_columns().get(sorted.unwrap_or(0)).map(|column| column.column_type);
+            show_header_menu(window, state, column, point.x, point.y);
+            return;
+        }
+        
+        let before = state.selection_snapshot();
+        state.ensure_selection_visible();
+        if state.scroll_pos != before.scroll_pos {
+            update_scrollbar(state);
+            state.invalidate_selection_change(&before);
+        }
+        match state.selected_index.and_then(|index| get_item_rect(index, state)) {
+            Some(rect) => {
+                // The file menu is right-aligned, so end it at the name
+                let right = match state.view_mode {
+                    ViewMode::Details => state.get_visible_columns().first().map_or(rect.right, |column| column.width.min(rect.right)),
+                    _ => rect.right,
+                };
+                let point = to_screen(right, rect.bottom);
+                show_file_context_menu(window, point.x, point.y, state);
+            }
+            None => {
+                let point = to_screen(state.client_width / 2, state.client_height / 2);
+                show_context_menu(window, state, point.x, point.y);
+            }
+        }
+    }
+}
 
-            // Create status bar
-            state.status_bar = CreateWindowExW(
-                WINDOW_EX_STYLE::default(),
-                STATUSCLASSNAMEW,
-                w!(""),
-                WS_CHILD | WS_VISIBLE,
-                0, 0, 0, 0,
-                parent,
-                HMENU(ID_STATUS_BAR as isize),
-                instance,
-                None,
-            );
+fn create_child_controls(parent: HWND, state: &mut AppState) {
+    unsafe {
+        let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+        
+        // Create search edit box
+        state.search_edit = CreateWindowExW(
+            WS_EX_CLIENTEDGE,
+            w!("EDIT"),
+            PCWSTR::from_raw(to_wide(&state.config.default_search).as_ptr()),
+            WS_CHILD | WS_VISIBLE | WS_BORDER,
+            10, 10, 980, 25,
+            parent,
+            HMENU(ID_SEARCH_EDIT as isize),
+            instance,
+            None,
+        );
+
+        SendMessageW(state.search_edit, WM_SETFONT, WPARAM(state.font.0 as usize), LPARAM(1));
+
+        // Subclass the search edit to handle Enter key
+        let original_proc = SetWindowLongPtrW(
+            state.search_edit,
+            GWLP_WNDPROC,
+            search_edit_proc as usize as isize,
+        );
+        SetWindowLongPtrW(state.search_edit, GWLP_USERDATA, original_proc);
+
+        enable_path_completion(state.search_edit);
+
+        state.syntax_button = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            w!("BUTTON"),
+            w!("?"),
+            WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32),
+            0, 10, SYNTAX_BUTTON_WIDTH, 25,
+            parent,
+            HMENU(ID_SYNTAX_HELP_BUTTON as isize),
+            instance,
+            None,
+        );
+        SendMessageW(state.syntax_button, WM_SETFONT, WPARAM(state.font.0 as usize), LPARAM(1));
+
+        state.search_tabs = Some(SearchTabs::create(parent, ID_SEARCH_TABS, state.font));
+
+        // Create custom list view
+        state.list_view = CreateWindowExW(
+            WS_EX_CLIENTEDGE,
+            w!("EverythingLikeListView"),
+            w!(""),
+            WS_CHILD | WS_VISIBLE | WS_VSCROLL | WS_HSCROLL | WS_TABSTOP,
+            10, 45, 980, 600,
+            parent,
+            HMENU(ID_LIST_VIEW as isize),
+            instance,
+            None,
+        );
+
+        // Create status bar
+        state.status_bar = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            STATUSCLASSNAMEW,
+            w!(""),
+            WS_CHILD | WS_VISIBLE,
+            0, 0, 0, 0,
+            parent,
+            HMENU(ID_STATUS_BAR as isize),
+            instance,
+            None,
+        );
 
-            SendMessageW(state.status_bar, WM_SETFONT, WPARAM(state.font.0 as usize), LPARAM(1));
+        SendMessageW(state.status_bar, WM_SETFONT, WPARAM(state.font.0 as usize), LPARAM(1));
+        
+        if state.config.show_folder_tree {
+            state.folder_tree = Some(FolderTree::create(parent, state.font, ID_FOLDER_TREE, &state.config.favorites));
+        }
+        
+        if state.config.show_preview_pane {
+            state.preview_pane = Some(PreviewPane::create(parent, state.font, ID_PREVIEW_PANE));
         }
     }
 }
 
-fn resize_controls(width: i32, height: i32) {
+// Completes folder names in the search box while the query is a path, such
+// as C:\Users\..., so deep folders are quick to type. The shell only offers
+// completions for text that starts like a path, so other queries are left
+// alone.
+fn enable_path_completion(edit: HWND) {
     unsafe {
-        if let Some(state) = &mut APP_STATE {
-            let margin = 10;
-            let edit_height = 25;
-            let status_height = 25;
-            let gap = 10;
-            
-            // Resize search edit
-            let _ = SetWindowPos(
-                state.search_edit,
-                None,
-                margin,
-                margin,
-                width - 2 * margin,
-                edit_height,
-                SWP_NOZORDER,
-            );
-            
-            // Resize status bar (it auto-sizes its height)
-            let _ = SetWindowPos(
-                state.status_bar,
-                None,
-                0,
-                height - status_height,
-                width,
-                status_height,
-                SWP_NOZORDER,
-            );
-            
-            // Resize list view
-            let list_y = margin + edit_height + gap;
-            let list_height = height - list_y - status_height - margin;
-            
-            let _ = SetWindowPos(
-                state.list_view,
-                None,
-                margin,
-                list_y,
-                width - 2 * margin,
-                list_height,
-                SWP_NOZORDER,
-            );
-            
-            // Update client dimensions and recalculate layout
-            state.client_width = width - 2 * margin;
-            state.client_height = list_height;
-            state.calculate_layout();
-            update_scrollbar(state.list_view);
+        use windows::Win32::System::Com::{CoInitializeEx, COINIT_APARTMENTTHREADED};
+        use windows::Win32::UI::Shell::{
+            SHAutoComplete, SHACF_AUTOAPPEND_FORCE_OFF, SHACF_AUTOSUGGEST_FORCE_ON, SHACF_FILESYS_DIRS,
+        };
+        
+        // The completion list is a COM object living on this thread
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+        if let Err(e) = SHAutoComplete(edit, SHACF_FILESYS_DIRS | SHACF_AUTOSUGGEST_FORCE_ON | SHACF_AUTOAPPEND_FORCE_OFF) {
+            log_warn(&format!("Path completion is unavailable: {}", e));
         }
     }
 }
 
-fn handle_search_change() {
+// Width of the "?" button right of the search box
+const SYNTAX_BUTTON_WIDTH: i32 = 25;
+
+fn resize_controls(state: &mut AppState, width: i32, height: i32) {
     unsafe {
-        if let Some(state) = &mut APP_STATE {
-            log_debug("handle_search_change called");
-            
-            // Get text from search edit control
-            let mut buffer: [u16; 1024] = [0; 1024];
-            let len = GetWindowTextW(state.search_edit, &mut buffer);
-            
-            let search_text = if len > 0 {
-                String::from_utf16_lossy(&buffer[..len as usize])
+        let margin = 10;
+        let edit_height = 25;
+        let status_height = 25;
+        let gap = 10;
+        
+        // Resize search edit, leaving room for the syntax button
+        let edit_width = width - 2 * margin - SYNTAX_BUTTON_WIDTH - 4;
+        let _ = SetWindowPos(
+            state.search_edit,
+            None,
+            margin,
+            margin,
+            edit_width,
+            edit_height,
+            SWP_NOZORDER,
+        );
+        let _ = SetWindowPos(
+            state.syntax_button,
+            None,
+            margin + edit_width + 4,
+            margin,
+            SYNTAX_BUTTON_WIDTH,
+            edit_height,
+            SWP_NOZORDER,
+        );
+        
+        // Resize status bar (it auto-sizes its height)
+        let _ = SetWindowPos(
+            state.status_bar,
+            None,
+            0,
+            height - status_height,
+            width,
+            status_height,
+            SWP_NOZORDER,
+        );
+        
+        // The tabs go above the results while more than one is open
+        let mut list_y = margin + edit_height + gap;
+        if let Some(ref search_tabs) = state.search_tabs {
+            if search_tabs.is_shown() {
+                let _ = SetWindowPos(
+                    search_tabs.strip,
+                    None,
+                    margin,
+                    list_y,
+                    width - 2 * margin,
+                    TAB_STRIP_HEIGHT,
+                    SWP_NOZORDER | SWP_SHOWWINDOW,
+                );
+                list_y += TAB_STRIP_HEIGHT;
             } else {
-                String::new()
-            };
-            
-            log_debug(&format!("Search text changed: '{}'", search_text));
-            
-            // Store the pending search query
-            state.pending_search_query = search_text.clone();
-            
-            // Check if we're in list mode
-            if state.is_list_mode {
-                // For list mode, search locally without delay
-                log_debug("List mode detected, performing local search");
-                state.search_local_list(&search_text);
-                log_debug("handle_search_change completed (list mode)");
-                return;
+                ShowWindow(search_tabs.strip, SW_HIDE);
             }
-            
-            // Kill existing timer if active
-            if state.search_timer_active {
-                KillTimer(state.main_window, SEARCH_TIMER_ID as usize);
-                state.search_timer_active = false;
-                log_debug("Killed existing search timer");
+        }
+        
+        // Resize list view, right of the folder tree when it is shown
+        let list_height = height - list_y - status_height - margin;
+        let list_x = match state.folder_tree {
+            Some(ref folder_tree) => {
+                let tree_width = FOLDER_TREE_WIDTH.min(width / 3);
+                let _ = SetWindowPos(
+                    folder_tree.window,
+                    None,
+                    margin,
+                    list_y,
+                    tree_width,
+                    list_height,
+                    SWP_NOZORDER,
+                );
+                margin + tree_width + gap
             }
-            
-            // Start new timer (500ms delay)
-            if SetTimer(state.main_window, SEARCH_TIMER_ID as usize, 500, None) != 0 {
-                state.search_timer_active = true;
-                log_debug("Started new search timer (500ms)");
-            } else {
-                log_debug("ERROR: Failed to set search timer");
-                // Fallback to immediate search if timer fails
-                state.start_async_search(state.pending_search_query.clone());
+            None => margin,
+        };
+        
+        // and left of the preview pane
+        let list_right = match state.preview_pane {
+            Some(ref preview_pane) => {
+                let pane_width = PREVIEW_PANE_WIDTH.min(width / 3);
+                let _ = SetWindowPos(
+                    preview_pane.window,
+                    None,
+                    width - margin - pane_width,
+                    list_y,
+                    pane_width,
+                    list_height,
+                    SWP_NOZORDER,
+                );
+                preview_pane.resize();
+                width - margin - pane_width - gap
             }
-            
-            log_debug("handle_search_change completed");
+            None => width - margin,
+        };
+        
+        let _ = SetWindowPos(
+            state.list_view,
+            None,
+            list_x,
+            list_y,
+            list_right - list_x,
+            list_height,
+            SWP_NOZORDER,
+        );
+        
+        // Update client dimensions and recalculate layout
+        state.client_width = list_right - list_x;
+        state.client_height = list_height;
+        state.calculate_layout();
+        update_scrollbar(state);
+    }
+}
+
+fn handle_search_change(state: &mut AppState) {
+    unsafe {
+        log_debug("handle_search_change called");
+        
+        // Get text from search edit control
+        let mut buffer: [u16; 1024] = [0; 1024];
+        let len = GetWindowTextW(state.search_edit, &mut buffer);
+        
+        let search_text = if len > 0 {
+            String::from_utf16_lossy(&buffer[..len as usize])
+        } else {
+            String::new()
+        };
+        
+        log_debug(&format!("Search text changed: '{}'", search_text));
+        
+        // Store the pending search query
+        state.pending_search_query = search_text.clone();
+        
+        // Check if we're in list mode
+        if state.is_list_mode {
+            // For list mode, search locally without delay
+            log_debug("List mode detected, performing local search");
+            state.search_local_list(&search_text);
+            log_debug("handle_search_change completed (list mode)");
+            return;
+        }
+        
+        // Kill existing timer if active
+        if state.search_timer_active {
+            let _ = KillTimer(state.main_window, SEARCH_TIMER_ID);
+            state.search_timer_active = false;
+            log_debug("Killed existing search timer");
+        }
+        
+        // Start new timer (configurable delay, 500ms by default)
+        if SetTimer(state.main_window, SEARCH_TIMER_ID, state.config.search_delay_ms, None) != 0 {
+            state.search_timer_active = true;
+            log_debug(&format!("Started new search timer ({}ms)", state.config.search_delay_ms));
         } else {
-            log_debug("WARNING: handle_search_change called but APP_STATE is None");
+            log_error("Failed to set search timer");
+            // Fallback to immediate search if timer fails
+            state.start_async_search(state.pending_search_query.clone());
         }
+        
+        log_debug("handle_search_change completed");
     }
 }
 
 fn show_open_file_dialog(window: HWND) -> Option<String> {
+    let filters = [
+        ("Everything File Lists (*.efu)", "*.efu"),
+        ("CSV Files (*.csv)", "*.csv"),
+        ("Text Files (*.txt)", "*.txt"),
+        ("Playlists (*.m3u;*.m3u8)", "*.m3u;*.m3u8"),
+        ("All Files (*.*)", "*.*"),
+    ];
+    
+    // Default to .efu files
+    show_open_file_dialog_with_filters(window, &get_strings().file_open_list, &filters)
+}
+
+// Filters are (display name, pattern) pairs; the first one is selected
+fn show_open_file_dialog_with_filters(window: HWND, title: &str, filters: &[(&str, &str)]) -> Option<String> {
     unsafe {
         use windows::Win32::System::Com::*;
         use windows::Win32::UI::Shell::*;
@@ -4058,24 +13161,19 @@ fn show_open_file_dialog(window: HWND) -> Option<String> {
         };
         
         // Set title
-        let strings = get_strings();
-        let title_utf16: Vec<u16> = strings.file_open_list.encode_utf16().chain(std::iter::once(0)).collect();
+        let title_utf16: Vec<u16> = title.encode_utf16().chain(std::iter::once(0)).collect();
         let _ = file_dialog.SetTitle(PCWSTR::from_raw(title_utf16.as_ptr()));
         
         // Create persistent storage for filter strings
-        let filter_names: Vec<Vec<u16>> = vec![
-            "Everything File Lists (*.efu)".encode_utf16().chain(std::iter::once(0)).collect(),
-            "CSV Files (*.csv)".encode_utf16().chain(std::iter::once(0)).collect(),
-            "Text Files (*.txt)".encode_utf16().chain(std::iter::once(0)).collect(),
-            "All Files (*.*)".encode_utf16().chain(std::iter::once(0)).collect(),
-        ];
+        let filter_names: Vec<Vec<u16>> = filters
+            .iter()
+            .map(|(name, _)| name.encode_utf16().chain(std::iter::once(0)).collect())
+            .collect();
         
-        let filter_specs: Vec<Vec<u16>> = vec![
-            "*.efu".encode_utf16().chain(std::iter::once(0)).collect(),
-            "*.csv".encode_utf16().chain(std::iter::once(0)).collect(),
-            "*.txt".encode_utf16().chain(std::iter::once(0)).collect(),
-            "*.*".encode_utf16().chain(std::iter::once(0)).collect(),
-        ];
+        let filter_specs: Vec<Vec<u16>> = filters
+            .iter()
+            .map(|(_, spec)| spec.encode_utf16().chain(std::iter::once(0)).collect())
+            .collect();
         
         let filter_structs: Vec<COMDLG_FILTERSPEC> = filter_names.iter().zip(filter_specs.iter()).map(|(name, spec)| {
             COMDLG_FILTERSPEC {
@@ -4085,7 +13183,7 @@ fn show_open_file_dialog(window: HWND) -> Option<String> {
         }).collect();
         
         let _ = file_dialog.SetFileTypes(&filter_structs);
-        let _ = file_dialog.SetFileTypeIndex(1); // Default to .efu files
+        let _ = file_dialog.SetFileTypeIndex(1);
         
         // Show the dialog
         if file_dialog.Show(window).is_ok() {
@@ -4108,7 +13206,82 @@ fn show_open_file_dialog(window: HWND) -> Option<String> {
     }
 }
 
+fn show_folder_dialog(window: HWND, title: &str) -> Option<String> {
+    unsafe {
+        use windows::Win32::System::Com::*;
+        use windows::Win32::UI::Shell::*;
+        
+        if CoInitializeEx(None, COINIT_APARTMENTTHREADED | COINIT_DISABLE_OLE1DDE).is_err() {
+            return None;
+        }
+        
+        let file_dialog: IFileOpenDialog = match CoCreateInstance(
+            &FileOpenDialog,
+            None,
+            CLSCTX_INPROC_SERVER,
+        ) {
+            Ok(dialog) => dialog,
+            Err(_) => {
+                CoUninitialize();
+                return None;
+            }
+        };
+        
+        let title_utf16: Vec<u16> = title.encode_utf16().chain(std::iter::once(0)).collect();
+        let _ = file_dialog.SetTitle(PCWSTR::from_raw(title_utf16.as_ptr()));
+        if let Ok(options) = file_dialog.GetOptions() {
+            let _ = file_dialog.SetOptions(options | FOS_PICKFOLDERS);
+        }
+        
+        let mut folder = None;
+        if file_dialog.Show(window).is_ok() {
+            if let Ok(item) = file_dialog.GetResult() {
+                if let Ok(path_bstr) = item.GetDisplayName(SIGDN_FILESYSPATH) {
+                    folder = Some(String::from_utf16_lossy(
+                        std::slice::from_raw_parts(
+                            path_bstr.as_ptr(), 
+                            wcslen(path_bstr.as_ptr())
+                        )
+                    ));
+                }
+            }
+        }
+        
+        CoUninitialize();
+        folder
+    }
+}
+
 fn show_save_file_dialog(window: HWND, default_name: &str) -> Option<String> {
+    let filters = [
+        ("CSV Files (*.csv)", "csv"),
+        ("Everything File Lists (*.efu)", "efu"),
+        ("Text Files (*.txt)", "txt"),
+        ("All Files (*.*)", "*"),
+    ];
+    
+    // Default to CSV files for saving
+    show_save_file_dialog_with_filters(window, &get_strings().file_save_list, default_name, &filters, 1, None).map(|result| result.path)
+}
+
+struct SaveDialogResult {
+    path: String,
+    filter_index: u32, // 1-based, as passed to SetFileTypes
+    checkbox_checked: bool,
+}
+
+// Dialog control ID for the optional check box
+const SAVE_DIALOG_CHECKBOX_ID: u32 = 100;
+
+// Filters are (display name, extension) pairs; checkbox is an optional (label, initially checked) pair
+fn show_save_file_dialog_with_filters(
+    window: HWND,
+    title: &str,
+    default_name: &str,
+    filters: &[(&str, &str)],
+    default_index: u32,
+    checkbox: Option<(&str, bool)>,
+) -> Option<SaveDialogResult> {
     unsafe {
         use windows::Win32::System::Com::*;
         use windows::Win32::UI::Shell::*;
@@ -4136,24 +13309,19 @@ fn show_save_file_dialog(window: HWND, default_name: &str) -> Option<String> {
         let _ = file_dialog.SetFileName(PCWSTR::from_raw(filename_utf16.as_ptr()));
         
         // Set title
-        let strings = get_strings();
-        let title_utf16: Vec<u16> = strings.file_save_list.encode_utf16().chain(std::iter::once(0)).collect();
+        let title_utf16: Vec<u16> = title.encode_utf16().chain(std::iter::once(0)).collect();
         let _ = file_dialog.SetTitle(PCWSTR::from_raw(title_utf16.as_ptr()));
         
         // Set file type filters
-        let filter_names: Vec<Vec<u16>> = vec![
-            "CSV Files (*.csv)".encode_utf16().chain(std::iter::once(0)).collect(),
-            "Everything File Lists (*.efu)".encode_utf16().chain(std::iter::once(0)).collect(),
-            "Text Files (*.txt)".encode_utf16().chain(std::iter::once(0)).collect(),
-            "All Files (*.*)".encode_utf16().chain(std::iter::once(0)).collect(),
-        ];
+        let filter_names: Vec<Vec<u16>> = filters
+            .iter()
+            .map(|(name, _)| name.encode_utf16().chain(std::iter::once(0)).collect())
+            .collect();
         
-        let filter_specs: Vec<Vec<u16>> = vec![
-            "*.csv".encode_utf16().chain(std::iter::once(0)).collect(),
-            "*.efu".encode_utf16().chain(std::iter::once(0)).collect(),
-            "*.txt".encode_utf16().chain(std::iter::once(0)).collect(),
-            "*.*".encode_utf16().chain(std::iter::once(0)).collect(),
-        ];
+        let filter_specs: Vec<Vec<u16>> = filters
+            .iter()
+            .map(|(_, extension)| format!("*.{}", extension).encode_utf16().chain(std::iter::once(0)).collect())
+            .collect();
         
         let filter_structs: Vec<COMDLG_FILTERSPEC> = filter_names.iter().zip(filter_specs.iter()).map(|(name, spec)| {
             COMDLG_FILTERSPEC {
@@ -4163,10 +13331,33 @@ fn show_save_file_dialog(window: HWND, default_name: &str) -> Option<String> {
         }).collect();
         
         let _ = file_dialog.SetFileTypes(&filter_structs);
-        let _ = file_dialog.SetFileTypeIndex(1); // Default to CSV files for saving
+        let _ = file_dialog.SetFileTypeIndex(default_index);
+        
+        // Append the extension of the selected filter when the user types none
+        if let Some((_, extension)) = filters.get((default_index as usize).saturating_sub(1)) {
+            let extension_utf16: Vec<u16> = extension.encode_utf16().chain(std::iter::once(0)).collect();
+            let _ = file_dialog.SetDefaultExtension(PCWSTR::from_raw(extension_utf16.as_ptr()));
+        }
+        
+        // Add the optional check box below the file name
+        let customize: Option<IFileDialogCustomize> = match checkbox {
+            Some((label, checked)) => file_dialog.cast::<IFileDialogCustomize>().ok().and_then(|customize| {
+                let label_utf16: Vec<u16> = label.encode_utf16().chain(std::iter::once(0)).collect();
+                customize
+                    .AddCheckButton(SAVE_DIALOG_CHECKBOX_ID, PCWSTR::from_raw(label_utf16.as_ptr()), BOOL::from(checked))
+                    .ok()
+                    .map(|_| customize)
+            }),
+            None => None,
+        };
         
         // Show the dialog
         if file_dialog.Show(window).is_ok() {
+            let filter_index = file_dialog.GetFileTypeIndex().unwrap_or(default_index);
+            let checkbox_checked = customize
+                .as_ref()
+                .and_then(|customize| customize.GetCheckButtonState(SAVE_DIALOG_CHECKBOX_ID).ok())
+                .map_or(false, |state| state.as_bool());
             if let Ok(item) = file_dialog.GetResult() {
                 if let Ok(path_bstr) = item.GetDisplayName(SIGDN_FILESYSPATH) {
                     let path_str = String::from_utf16_lossy(
@@ -4176,7 +13367,11 @@ fn show_save_file_dialog(window: HWND, default_name: &str) -> Option<String> {
                         )
                     );
                     CoUninitialize();
-                    return Some(path_str);
+                    return Some(SaveDialogResult {
+                        path: path_str,
+                        filter_index,
+                        checkbox_checked,
+                    });
                 }
             }
         }
@@ -0,0 +1,131 @@
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::*,
+        Storage::EnhancedStorage::{
+            PKEY_Audio_EncodingBitrate, PKEY_Image_HorizontalSize, PKEY_Image_VerticalSize, PKEY_Media_Duration,
+            PKEY_Video_EncodingBitrate, PKEY_Video_FrameHeight, PKEY_Video_FrameWidth, PKEY_Video_TotalBitrate,
+        },
+        System::Com::{CoInitializeEx, CoUninitialize, COINIT_MULTITHREADED},
+        UI::Shell::{IShellItem2, SHCreateItemFromParsingName},
+    },
+};
+use std::path::Path;
+use crate::batch_loader::BatchLoader;
+use crate::image_viewer::is_viewable_image;
+use crate::long_paths::shell_path;
+use crate::network_paths::throttled;
+use crate::thumbnail::to_wide;
+use crate::video_preview::is_video;
+
+// Results are posted in small batches so the first rows fill in quickly
+const RESULT_BATCH_SIZE: usize = 16;
+
+// The shell's media durations are in 100 ns units
+const TICKS_PER_SECOND: u64 = 10_000_000;
+
+const AUDIO_EXTENSIONS: [&str; 12] = [
+    "mp3", "m4a", "aac", "flac", "wav", "wma", "ogg", "oga", "opus", "aiff", "aif", "ape",
+];
+
+// Files the shell can read a duration or bitrate from
+pub fn is_media(extension: &str) -> bool {
+    is_video(extension) || AUDIO_EXTENSIONS.iter().any(|known| known.eq_ignore_ascii_case(extension))
+}
+
+// Files the shell can read a width and height from
+pub fn has_dimensions(extension: &str) -> bool {
+    is_video(extension) || is_viewable_image(extension)
+}
+
+// Length and bitrate of an audio or video file, and the size of a video
+// or image, any of which the file's property handler may leave out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MediaInfo {
+    // In 100 ns units
+    pub duration: Option<u64>,
+    // In bits per second
+    pub bitrate: Option<u32>,
+    // Width and height in pixels
+    pub dimensions: Option<(u32, u32)>,
+}
+
+impl MediaInfo {
+    // h:mm:ss, or m:ss for anything shorter than an hour
+    pub fn format_duration(&self) -> String {
+        let Some(duration) = self.duration else {
+            return String::new();
+        };
+        let seconds = duration / TICKS_PER_SECOND;
+        let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+        if hours > 0 {
+            format!("{}:{:02}:{:02}", hours, minutes, seconds)
+        } else {
+            format!("{}:{:02}", minutes, seconds)
+        }
+    }
+
+    pub fn format_bitrate(&self) -> String {
+        self.bitrate.map(|bitrate| format!("{} kbps", bitrate / 1000)).unwrap_or_default()
+    }
+
+    pub fn format_dimensions(&self) -> String {
+        self.dimensions.map(|(width, height)| format!("{} × {}", width, height)).unwrap_or_default()
+    }
+}
+
+// Details of one row. None when the file has no media properties.
+pub struct MediaInfoEntry {
+    pub path: String,
+    pub info: Option<MediaInfo>,
+}
+
+// Reads durations and bitrates for the rows on screen on a worker thread
+// and posts `message` with a boxed Vec<MediaInfoEntry> in WPARAM for each
+// batch
+pub type MediaInfoLoader = BatchLoader<String, MediaInfoEntry>;
+
+impl MediaInfoLoader {
+    pub fn start(window: HWND, message: u32) -> Self {
+        BatchLoader::spawn(window, message, RESULT_BATCH_SIZE, |path: &String| MediaInfoEntry {
+            path: path.clone(),
+            info: throttled(path, || read_media_info(path)).flatten(),
+        })
+    }
+}
+
+// Reads the duration, bitrate and dimensions through the file's property handler.
+// Callable from any thread, including the sort's thread pool.
+pub fn read_media_info(path: &str) -> Option<MediaInfo> {
+    let extension = Path::new(path).extension().and_then(|extension| extension.to_str()).unwrap_or("");
+    if !is_media(extension) && !has_dimensions(extension) {
+        return None;
+    }
+
+    let path_wide = to_wide(&shell_path(path));
+    unsafe {
+        let com_initialized = CoInitializeEx(None, COINIT_MULTITHREADED).is_ok();
+        let info = SHCreateItemFromParsingName::<_, _, IShellItem2>(PCWSTR::from_raw(path_wide.as_ptr()), None)
+            .ok()
+            .map(|item| {
+                // Videos report the bitrate of all their streams together
+                let bitrate = [PKEY_Video_TotalBitrate, PKEY_Video_EncodingBitrate, PKEY_Audio_EncodingBitrate]
+                    .iter()
+                    .find_map(|key| item.GetUInt32(key).ok().filter(|&bitrate| bitrate > 0));
+                let dimensions = [(PKEY_Image_HorizontalSize, PKEY_Image_VerticalSize), (PKEY_Video_FrameWidth, PKEY_Video_FrameHeight)]
+                    .iter()
+                    .find_map(|(width, height)| item.GetUInt32(width).ok().zip(item.GetUInt32(height).ok()))
+                    .filter(|&(width, height)| width > 0 && height > 0);
+                MediaInfo {
+                    duration: item.GetUInt64(&PKEY_Media_Duration).ok().filter(|&duration| duration > 0),
+                    bitrate,
+                    dimensions,
+                }
+            })
+            .filter(|info| info.duration.is_some() || info.bitrate.is_some() || info.dimensions.is_some());
+        if com_initialized {
+            CoUninitialize();
+        }
+        info
+    }
+}
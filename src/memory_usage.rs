@@ -0,0 +1,41 @@
+use crate::everything_sdk::FileResult;
+use lru::LruCache;
+use windows::Win32::Graphics::Gdi::HBITMAP;
+
+// What one result is taken to use before it is read: the row itself and a
+// path of typical length
+pub const RESULT_BYTES_ESTIMATE: u64 = std::mem::size_of::<FileResult>() as u64 + 120;
+
+// Memory `count` results are expected to take
+pub fn predicted_results_bytes(count: usize) -> u64 {
+    count as u64 * RESULT_BYTES_ESTIMATE
+}
+
+// How many of `count` results fit in `budget` bytes; all of them without one
+pub fn results_within_budget(count: usize, budget: Option<u64>) -> usize {
+    match budget {
+        Some(budget) if predicted_results_bytes(count) > budget => (budget / RESULT_BYTES_ESTIMATE) as usize,
+        _ => count,
+    }
+}
+
+// The budget in bytes for a setting in megabytes, 0 meaning none
+pub fn budget_bytes(megabytes: u32) -> Option<u64> {
+    (megabytes > 0).then(|| megabytes as u64 * 1024 * 1024)
+}
+
+// Memory the result rows take, their paths included
+pub fn results_bytes(results: &Vec<FileResult>) -> u64 {
+    let rows = results.capacity() * std::mem::size_of::<FileResult>();
+    let paths: usize = results.iter().map(|result| result.path().len()).sum();
+    (rows + paths) as u64
+}
+
+// Memory the cached thumbnails take, as 32-bit bitmaps of the size they
+// were made for
+pub fn thumbnail_cache_bytes(cache: &LruCache<(String, u32), HBITMAP>) -> u64 {
+    cache
+        .iter()
+        .map(|((path, size), _)| *size as u64 * *size as u64 * 4 + path.len() as u64)
+        .sum()
+}
@@ -0,0 +1,160 @@
+use windows::{
+    core::PWSTR,
+    Win32::{
+        Foundation::*,
+        UI::WindowsAndMessaging::*,
+    },
+};
+
+// Letters for labels that have none of their own to underline, in the
+// order they are tried
+const SUFFIX_KEYS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+// Gives every item of the menu and of its submenus an Alt-key mnemonic,
+// keeping the ones a label already marks with '&'. The letters are picked
+// per menu so no two items share one: the start of a word if one is free,
+// then any free letter. Labels without usable letters, like Chinese ones,
+// get a "(&X)" suffix instead.
+pub fn assign_menu_mnemonics(hmenu: HMENU) {
+    let count = unsafe { GetMenuItemCount(hmenu) };
+    let mut labels: Vec<(u32, String)> = Vec::new();
+    for position in 0..count.max(0) as u32 {
+        let (label, submenu) = match menu_item_label(hmenu, position) {
+            Some(item) => item,
+            None => continue,
+        };
+        if !submenu.is_invalid() {
+            assign_menu_mnemonics(submenu);
+        }
+        labels.push((position, label));
+    }
+
+    let mut used: Vec<char> = labels.iter().filter_map(|(_, label)| existing_mnemonic(label)).collect();
+    for (position, label) in labels {
+        if existing_mnemonic(&label).is_some() {
+            continue;
+        }
+        if let Some(marked) = with_mnemonic(&label, &mut used) {
+            set_menu_item_label(hmenu, position, &marked);
+        }
+    }
+}
+
+// The text of a string item and its submenu, if any. None for separators
+// and other items without text.
+fn menu_item_label(hmenu: HMENU, position: u32) -> Option<(String, HMENU)> {
+    unsafe {
+        let mut info = MENUITEMINFOW {
+            cbSize: std::mem::size_of::<MENUITEMINFOW>() as u32,
+            fMask: MIIM_FTYPE | MIIM_STRING | MIIM_SUBMENU,
+            ..Default::default()
+        };
+        GetMenuItemInfoW(hmenu, position, TRUE, &mut info).ok()?;
+        if info.fType.0 & (MFT_SEPARATOR.0 | MFT_BITMAP.0 | MFT_OWNERDRAW.0) != 0 || info.cch == 0 {
+            return None;
+        }
+
+        // The first call only returned the length
+        let mut buffer = vec![0u16; info.cch as usize + 1];
+        info.fMask = MIIM_STRING;
+        info.dwTypeData = PWSTR(buffer.as_mut_ptr());
+        info.cch = buffer.len() as u32;
+        GetMenuItemInfoW(hmenu, position, TRUE, &mut info).ok()?;
+
+        let label = String::from_utf16_lossy(&buffer[..info.cch as usize]);
+        Some((label, info.hSubMenu))
+    }
+}
+
+fn set_menu_item_label(hmenu: HMENU, position: u32, label: &str) {
+    unsafe {
+        let mut wide: Vec<u16> = label.encode_utf16().chain(std::iter::once(0)).collect();
+        let info = MENUITEMINFOW {
+            cbSize: std::mem::size_of::<MENUITEMINFOW>() as u32,
+            fMask: MIIM_STRING,
+            dwTypeData: PWSTR(wide.as_mut_ptr()),
+            ..Default::default()
+        };
+        let _ = SetMenuItemInfoW(hmenu, position, TRUE, &info);
+    }
+}
+
+// The key a label already underlines. "&&" is a literal '&', not a mark.
+fn existing_mnemonic(label: &str) -> Option<char> {
+    let text = label.split('\t').next().unwrap_or(label);
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '&' {
+            match chars.next() {
+                Some('&') | None => {}
+                Some(key) => return Some(key_of(key)),
+            }
+        }
+    }
+    None
+}
+
+// The label with a free key marked, which is then added to `used`. The
+// part after a tab is the shortcut text and is left alone.
+fn with_mnemonic(label: &str, used: &mut Vec<char>) -> Option<String> {
+    let (text, shortcut) = match label.split_once('\t') {
+        Some((text, shortcut)) => (text, Some(shortcut)),
+        None => (label, None),
+    };
+    let chars: Vec<char> = text.chars().collect();
+
+    // Positions that can be marked, skipping the escaped "&&"
+    let mut candidates: Vec<(usize, bool)> = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        if c == '&' {
+            index += 2;
+            continue;
+        }
+        if is_mnemonic_char(c) {
+            let word_start = index == 0 || !chars[index - 1].is_alphanumeric();
+            candidates.push((index, word_start));
+        }
+        index += 1;
+    }
+
+    let free = |&&(index, _): &&(usize, bool)| !used.contains(&key_of(chars[index]));
+    let chosen = candidates
+        .iter()
+        .filter(|(_, word_start)| *word_start)
+        .find(free)
+        .or_else(|| candidates.iter().find(free))
+        .map(|&(index, _)| index);
+
+    let mut marked = match chosen {
+        Some(index) => {
+            used.push(key_of(chars[index]));
+            let mut marked: String = chars[..index].iter().collect();
+            marked.push('&');
+            marked.extend(&chars[index..]);
+            marked
+        }
+        None => {
+            let key = SUFFIX_KEYS.chars().find(|key| !used.contains(&key_of(*key)))?;
+            used.push(key_of(key));
+            format!("{}(&{})", text.trim_end(), key)
+        }
+    };
+
+    if let Some(shortcut) = shortcut {
+        marked.push('\t');
+        marked.push_str(shortcut);
+    }
+    Some(marked)
+}
+
+// Letters of cased scripts and digits; a keyboard has no key for a
+// Chinese character or a kana
+fn is_mnemonic_char(c: char) -> bool {
+    c.is_ascii_digit() || c.is_lowercase() || c.is_uppercase()
+}
+
+fn key_of(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
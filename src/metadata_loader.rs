@@ -0,0 +1,47 @@
+use windows::Win32::Foundation::HWND;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::batch_loader::BatchLoader;
+use crate::everything_sdk::count_folder_items;
+use crate::network_paths::throttled;
+
+// Results are posted in small batches so the first rows fill in quickly
+// even when a whole page is waiting on a slow network drive
+const RESULT_BATCH_SIZE: usize = 32;
+
+// Size and modified time of one row, read on the worker thread. The path is
+// sent back so the UI can tell whether the row still holds the same file.
+pub struct MetadataEntry {
+    pub index: usize,
+    pub path: String,
+    pub size: u64,
+    pub modified_time: SystemTime,
+    // Number of entries when the row is a folder
+    pub folder_items: Option<u32>,
+}
+
+// Stats files for the rows around the viewport on a worker thread and posts
+// `message` with a boxed Vec<MetadataEntry> in WPARAM for each batch
+pub type MetadataLoader = BatchLoader<(usize, String), MetadataEntry>;
+
+impl MetadataLoader {
+    pub fn start(window: HWND, message: u32) -> Self {
+        BatchLoader::spawn(window, message, RESULT_BATCH_SIZE, |(index, path): &(usize, String)| {
+            // Rows on an offline share come back empty
+            let read = throttled(path, || match std::fs::metadata(path) {
+                Ok(metadata) if metadata.is_dir() => {
+                    (0, metadata.modified().unwrap_or(UNIX_EPOCH), Some(count_folder_items(path)))
+                }
+                Ok(metadata) => (metadata.len(), metadata.modified().unwrap_or(UNIX_EPOCH), None),
+                Err(_) => (0, UNIX_EPOCH, None),
+            });
+            let (size, modified_time, folder_items) = read.unwrap_or((0, UNIX_EPOCH, None));
+            MetadataEntry {
+                index: *index,
+                path: path.clone(),
+                size,
+                modified_time,
+                folder_items,
+            }
+        })
+    }
+}
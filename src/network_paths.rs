@@ -0,0 +1,224 @@
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::*,
+        Storage::FileSystem::GetDriveTypeW,
+        UI::WindowsAndMessaging::{PostMessageW, WM_USER},
+    },
+};
+use std::collections::HashMap;
+use std::sync::{mpsc, Condvar, Mutex, atomic::{AtomicIsize, Ordering}};
+use std::time::{Duration, Instant};
+use crate::logger::{log_info, log_warn};
+use crate::thumbnail::to_wide;
+
+// Posted to the window given to set_status_window, without parameters,
+// when a share goes offline or comes back
+pub const WM_SHARE_STATUS_CHANGED: u32 = WM_USER + 141;
+
+// Workers reading from one share at the same time. The rest wait their
+// turn instead of each stalling on the same slow server.
+const ROOT_CONCURRENCY: usize = 2;
+
+// How long a share gets to answer before it counts as offline. Probes run
+// on the workers, never on the UI thread, so this can be generous.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(8);
+
+// Offline shares are skipped for this long before they are tried again
+const OFFLINE_RETRY: Duration = Duration::from_secs(60);
+
+// GetDriveTypeW's answer for mapped network drives
+const DRIVE_REMOTE: u32 = 4;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Reachability {
+    // Not tried yet, or a read took so long it should be tried again
+    Unknown,
+    Online,
+    // Didn't answer at the time
+    Offline(Instant),
+}
+
+struct RootState {
+    reachability: Reachability,
+    // Workers reading from the share right now
+    active: usize,
+    // A worker is checking whether the share answers
+    probing: bool,
+}
+
+// Shares seen so far, keyed by network_root
+static ROOTS: Mutex<Option<HashMap<String, RootState>>> = Mutex::new(None);
+static ROOTS_CHANGED: Condvar = Condvar::new();
+
+// Whether each drive letter is a mapped network drive, once asked
+static REMOTE_DRIVES: Mutex<[Option<bool>; 26]> = Mutex::new([None; 26]);
+
+static STATUS_WINDOW: AtomicIsize = AtomicIsize::new(0);
+
+// Window told about shares going offline or coming back
+pub fn set_status_window(window: HWND) {
+    STATUS_WINDOW.store(window.0, Ordering::Relaxed);
+}
+
+// The share `path` is on, as \\server\share in lowercase, or the letter of
+// a mapped network drive as "Z:". None for local paths.
+pub fn network_root(path: &str) -> Option<String> {
+    let unc = path
+        .strip_prefix(r"\\?\UNC\")
+        .or_else(|| path.strip_prefix(r"\\").filter(|rest| !rest.starts_with(['?', '.'])));
+    if let Some(rest) = unc {
+        let mut parts = rest.split(['\\', '/']).filter(|part| !part.is_empty());
+        let server = parts.next()?;
+        return Some(match parts.next() {
+            Some(share) => format!(r"\\{}\{}", server, share).to_lowercase(),
+            None => format!(r"\\{}", server).to_lowercase(),
+        });
+    }
+
+    let letter = path.chars().next().filter(|c| c.is_ascii_alphabetic() && path[1..].starts_with(':'))?;
+    is_remote_drive(letter).then(|| format!("{}:", letter.to_ascii_uppercase()))
+}
+
+pub fn is_network_path(path: &str) -> bool {
+    network_root(path).is_some()
+}
+
+fn is_remote_drive(letter: char) -> bool {
+    let index = (letter.to_ascii_uppercase() as u8 - b'A') as usize;
+    let Ok(mut drives) = REMOTE_DRIVES.lock() else {
+        return false;
+    };
+    *drives[index].get_or_insert_with(|| {
+        let root = to_wide(&format!("{}:\\", letter));
+        unsafe { GetDriveTypeW(PCWSTR::from_raw(root.as_ptr())) == DRIVE_REMOTE }
+    })
+}
+
+// Whether the share `path` is on didn't answer the last time it was tried.
+// Cheap enough to ask while painting.
+pub fn is_offline(path: &str) -> bool {
+    let Some(root) = network_root(path) else {
+        return false;
+    };
+    ROOTS.lock().is_ok_and(|roots| {
+        roots
+            .as_ref()
+            .and_then(|roots| roots.get(&root))
+            .is_some_and(|state| matches!(state.reachability, Reachability::Offline(_)))
+    })
+}
+
+// Runs `read`, which touches the file at `path`. Local paths run right
+// away. Files on a network share wait until fewer than ROOT_CONCURRENCY
+// reads of that share are running, and the share is tried with a timeout
+// before the first one. Returns None without running `read` while the
+// share is offline.
+pub fn throttled<T>(path: &str, read: impl FnOnce() -> T) -> Option<T> {
+    let Some(root) = network_root(path) else {
+        return Some(read());
+    };
+    if !acquire(&root) {
+        return None;
+    }
+
+    let started = Instant::now();
+    let result = read();
+    release(&root, started.elapsed() >= PROBE_TIMEOUT);
+    Some(result)
+}
+
+// Waits for a free slot on `root`. False when the share is offline.
+fn acquire(root: &str) -> bool {
+    let Ok(mut roots) = ROOTS.lock() else {
+        return true;
+    };
+    loop {
+        let state = roots.get_or_insert_with(HashMap::new).entry(root.to_string()).or_insert(RootState {
+            reachability: Reachability::Unknown,
+            active: 0,
+            probing: false,
+        });
+
+        let needs_probe = match state.reachability {
+            Reachability::Unknown => true,
+            Reachability::Online => false,
+            Reachability::Offline(since) if since.elapsed() < OFFLINE_RETRY => return false,
+            Reachability::Offline(_) => true,
+        };
+
+        if !needs_probe && state.active < ROOT_CONCURRENCY {
+            state.active += 1;
+            return true;
+        }
+
+        if needs_probe && !state.probing {
+            state.probing = true;
+            let was_offline = matches!(state.reachability, Reachability::Offline(_));
+            drop(roots);
+
+            let online = probe(root);
+
+            roots = match ROOTS.lock() {
+                Ok(roots) => roots,
+                Err(_) => return online,
+            };
+            if let Some(state) = roots.as_mut().and_then(|roots| roots.get_mut(root)) {
+                state.probing = false;
+                state.reachability = if online { Reachability::Online } else { Reachability::Offline(Instant::now()) };
+            }
+            ROOTS_CHANGED.notify_all();
+
+            if online == was_offline {
+                if online {
+                    log_info(&format!("{} is reachable again", root));
+                } else {
+                    log_warn(&format!("{} is not reachable, skipping it for {} seconds", root, OFFLINE_RETRY.as_secs()));
+                }
+                notify_status_window();
+            }
+            continue;
+        }
+
+        // Another worker is probing the share or using its slots
+        roots = match ROOTS_CHANGED.wait(roots) {
+            Ok(roots) => roots,
+            Err(_) => return true,
+        };
+    }
+}
+
+// Frees the slot taken by acquire. A read that took as long as a probe
+// may mean the share went away, so it is tried again before the next one.
+fn release(root: &str, slow: bool) {
+    if let Ok(mut roots) = ROOTS.lock() {
+        if let Some(state) = roots.as_mut().and_then(|roots| roots.get_mut(root)) {
+            state.active = state.active.saturating_sub(1);
+            if slow && state.reachability == Reachability::Online {
+                state.reachability = Reachability::Unknown;
+            }
+        }
+    }
+    ROOTS_CHANGED.notify_all();
+}
+
+// Whether the share answers within PROBE_TIMEOUT. The check runs on its
+// own thread, which is left behind if Windows is still waiting on the
+// server when the time is up.
+fn probe(root: &str) -> bool {
+    let (sender, receiver) = mpsc::channel();
+    let path = format!("{}\\", root);
+    std::thread::spawn(move || {
+        let _ = sender.send(std::fs::metadata(&path).is_ok());
+    });
+    receiver.recv_timeout(PROBE_TIMEOUT).unwrap_or(false)
+}
+
+fn notify_status_window() {
+    let window = STATUS_WINDOW.load(Ordering::Relaxed);
+    if window != 0 {
+        unsafe {
+            let _ = PostMessageW(HWND(window), WM_SHARE_STATUS_CHANGED, WPARAM(0), LPARAM(0));
+        }
+    }
+}
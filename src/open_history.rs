@@ -0,0 +1,421 @@
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{HBRUSH, HFONT, COLOR_BTNFACE},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Controls::*,
+            Input::KeyboardAndMouse::EnableWindow,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::config::get_config_dir;
+use crate::lang::{fill, get_strings};
+use crate::logger::{log_error, log_info, log_warn};
+use crate::scheduled_export::unix_now;
+use crate::thumbnail::to_wide;
+
+// Posted to the owner when recording is switched on or off from the
+// history window, with 1 in WPARAM to record
+pub const WM_OPEN_HISTORY_RECORD: u32 = WM_USER + 143;
+
+// Posted to the owner with a boxed String in WPARAM to open that file
+pub const WM_OPEN_HISTORY_OPEN: u32 = WM_USER + 144;
+
+const OPEN_HISTORY_WINDOW_CLASS: &str = "EverythingLikeOpenHistory";
+
+const OPEN_HISTORY_WINDOW_WIDTH: i32 = 820;
+const OPEN_HISTORY_WINDOW_HEIGHT: i32 = 560;
+const MARGIN: i32 = 10;
+const BUTTON_WIDTH: i32 = 80;
+const BUTTON_HEIGHT: i32 = 26;
+
+// Control IDs
+const ID_OPEN_HISTORY_FILTER: i32 = 9861;
+const ID_OPEN_HISTORY_LIST: i32 = 9862;
+const ID_OPEN_HISTORY_STATUS: i32 = 9863;
+const ID_OPEN_HISTORY_RECORD: i32 = 9864;
+const ID_OPEN_HISTORY_CLEAR: i32 = 9865;
+const ID_OPEN_HISTORY_CLOSE: i32 = 9866;
+
+// List columns
+const COLUMN_OPENED: i32 = 0;
+const COLUMN_NAME: i32 = 1;
+const COLUMN_FOLDER: i32 = 2;
+
+// One line of open_history.jsonl
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenedFile {
+    // Unix time the file was opened
+    pub time: u64,
+    pub path: String,
+}
+
+impl OpenedFile {
+    fn opened_text(&self) -> String {
+        Local
+            .timestamp_opt(self.time as i64, 0)
+            .single()
+            .map_or(String::new(), |time| time.format("%Y-%m-%d %H:%M").to_string())
+    }
+}
+
+// Files opened from the app, one JSON object per line, next to config.json
+fn history_path() -> Option<PathBuf> {
+    get_config_dir().ok().map(|dir| dir.join("open_history.jsonl"))
+}
+
+// Adds `path` to the history as opened now
+pub fn record_opened(path: &str) {
+    let Some(history) = history_path() else {
+        return;
+    };
+    let entry = OpenedFile { time: unix_now(), path: path.to_string() };
+    let result = serde_json::to_string(&entry)
+        .map_err(|e| e.to_string())
+        .and_then(|line| {
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&history)
+                .and_then(|mut file| writeln!(file, "{}", line))
+                .map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        log_warn(&format!("Failed to add {} to the open history: {}", path, e));
+    }
+}
+
+// The history, latest first. Lines that can't be read are skipped.
+fn read_history() -> Vec<OpenedFile> {
+    let Some(text) = history_path().and_then(|history| std::fs::read_to_string(history).ok()) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<OpenedFile> = text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    entries.reverse();
+    entries
+}
+
+fn clear_history() -> std::io::Result<()> {
+    match history_path() {
+        Some(history) if history.exists() => std::fs::remove_file(history),
+        _ => Ok(()),
+    }
+}
+
+struct OpenHistory {
+    owner: HWND,
+    list: HWND,
+    entries: Vec<OpenedFile>,
+    // Index into `entries` of each row, after filtering
+    rows: Vec<usize>,
+}
+
+// The window's OpenHistory, kept in GWLP_USERDATA
+unsafe fn open_history(window: HWND) -> Option<&'static mut OpenHistory> {
+    let history = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut OpenHistory;
+    history.as_mut()
+}
+
+pub fn register_open_history_window_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(open_history_window_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as isize),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeOpenHistory"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// Tools → Open History: the files opened from the app, latest first,
+// narrowed down by what is typed in the filter box. `recording` is whether
+// opened files are being added to it.
+pub fn show_open_history(owner: HWND, font: HFONT, recording: bool) {
+    unsafe {
+        let strings = get_strings();
+        let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+
+        let window = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            PCWSTR::from_raw(to_wide(OPEN_HISTORY_WINDOW_CLASS).as_ptr()),
+            PCWSTR::from_raw(to_wide(&strings.open_history_title).as_ptr()),
+            WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX | WS_VISIBLE,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            OPEN_HISTORY_WINDOW_WIDTH,
+            OPEN_HISTORY_WINDOW_HEIGHT,
+            owner,
+            None,
+            instance,
+            None,
+        );
+
+        if window.0 == 0 {
+            log_error("Failed to create open history window");
+            return;
+        }
+
+        let mut client_rect = RECT::default();
+        let _ = GetClientRect(window, &mut client_rect);
+        let width = client_rect.right - MARGIN * 2;
+        let label_width = 70;
+        let status_y = MARGIN * 2 + BUTTON_HEIGHT;
+        let list_y = status_y + 20 + MARGIN;
+        let button_y = client_rect.bottom - MARGIN - BUTTON_HEIGHT;
+
+        let list = CreateWindowExW(
+            WS_EX_CLIENTEDGE,
+            WC_LISTVIEWW,
+            PCWSTR::null(),
+            WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(LVS_REPORT | LVS_SINGLESEL | LVS_SHOWSELALWAYS),
+            MARGIN,
+            list_y,
+            width,
+            button_y - MARGIN - list_y,
+            window,
+            HMENU(ID_OPEN_HISTORY_LIST as isize),
+            instance,
+            None,
+        );
+        SendMessageW(list, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        SendMessageW(list, LVM_SETEXTENDEDLISTVIEWSTYLE, WPARAM(0), LPARAM((LVS_EX_FULLROWSELECT | LVS_EX_DOUBLEBUFFER) as isize));
+
+        let columns = [
+            (&strings.open_history_opened, 130),
+            (&strings.column_name, 220),
+            (&strings.column_path, width - 374),
+        ];
+        for (index, (name, column_width)) in columns.iter().enumerate() {
+            let mut name_wide = to_wide(name);
+            let column = LVCOLUMNW {
+                mask: LVCF_TEXT | LVCF_WIDTH,
+                cx: *column_width,
+                pszText: PWSTR::from_raw(name_wide.as_mut_ptr()),
+                ..Default::default()
+            };
+            SendMessageW(list, LVM_INSERTCOLUMNW, WPARAM(index), LPARAM(&column as *const _ as isize));
+        }
+
+        let record_width = 240;
+        let clear_width = 120;
+        let controls = [
+            (w!("STATIC"), strings.open_history_filter.clone(), WINDOW_STYLE(0), WINDOW_EX_STYLE::default(), -1, MARGIN, MARGIN + 4, label_width, 20),
+            (w!("EDIT"), String::new(), WS_TABSTOP | WINDOW_STYLE(ES_AUTOHSCROLL as u32), WS_EX_CLIENTEDGE, ID_OPEN_HISTORY_FILTER, MARGIN + label_width, MARGIN, width - label_width, BUTTON_HEIGHT),
+            (w!("STATIC"), String::new(), WINDOW_STYLE(0), WINDOW_EX_STYLE::default(), ID_OPEN_HISTORY_STATUS, MARGIN, status_y, width, 20),
+            (w!("BUTTON"), strings.open_history_record.clone(), WS_TABSTOP | WINDOW_STYLE(BS_AUTOCHECKBOX as u32), WINDOW_EX_STYLE::default(), ID_OPEN_HISTORY_RECORD, MARGIN, button_y, record_width, BUTTON_HEIGHT),
+            (w!("BUTTON"), strings.open_history_clear.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), WINDOW_EX_STYLE::default(), ID_OPEN_HISTORY_CLEAR, MARGIN * 2 + record_width, button_y, clear_width, BUTTON_HEIGHT),
+            (w!("BUTTON"), strings.stats_close.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), WINDOW_EX_STYLE::default(), ID_OPEN_HISTORY_CLOSE, client_rect.right - MARGIN - BUTTON_WIDTH, button_y, BUTTON_WIDTH, BUTTON_HEIGHT),
+        ];
+        for (class, text, style, ex_style, id, x, y, width, height) in controls {
+            let control = CreateWindowExW(
+                ex_style,
+                class,
+                PCWSTR::from_raw(to_wide(&text).as_ptr()),
+                WS_CHILD | WS_VISIBLE | style,
+                x, y, width, height,
+                window,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(control, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        }
+        let record_state = if recording { BST_CHECKED } else { BST_UNCHECKED };
+        SendMessageW(GetDlgItem(window, ID_OPEN_HISTORY_RECORD), BM_SETCHECK, WPARAM(record_state.0 as usize), LPARAM(0));
+
+        let entries = read_history();
+        log_info(&format!("Showing the open history of {} files", entries.len()));
+        let history = Box::new(OpenHistory { owner, list, entries, rows: Vec::new() });
+        SetWindowLongPtrW(window, GWLP_USERDATA, Box::into_raw(history) as isize);
+
+        fill_list(window);
+    }
+}
+
+unsafe fn set_status(window: HWND, text: &str) {
+    let _ = SetWindowTextW(GetDlgItem(window, ID_OPEN_HISTORY_STATUS), PCWSTR::from_raw(to_wide(text).as_ptr()));
+}
+
+unsafe fn filter_text(window: HWND) -> String {
+    let edit = GetDlgItem(window, ID_OPEN_HISTORY_FILTER);
+    let mut buffer = vec![0u16; GetWindowTextLengthW(edit) as usize + 1];
+    let copied = GetWindowTextW(edit, &mut buffer);
+    String::from_utf16_lossy(&buffer[..copied as usize]).trim().to_lowercase()
+}
+
+unsafe fn set_cell(list: HWND, row: usize, column: i32, text: &str) {
+    let mut text_wide = to_wide(text);
+    let item = LVITEMW {
+        iSubItem: column,
+        pszText: PWSTR::from_raw(text_wide.as_mut_ptr()),
+        ..Default::default()
+    };
+    SendMessageW(list, LVM_SETITEMTEXTW, WPARAM(row), LPARAM(&item as *const _ as isize));
+}
+
+// Lists the entries whose path or time holds every word of the filter, so
+// "2026-10-13 report" finds the report opened that day
+unsafe fn fill_list(window: HWND) {
+    let Some(history) = open_history(window) else {
+        return;
+    };
+    let strings = get_strings();
+    let filter = filter_text(window);
+    let words: Vec<&str> = filter.split_whitespace().collect();
+
+    history.rows = history
+        .entries
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| {
+            let text = format!("{} {}", entry.opened_text(), entry.path.to_lowercase());
+            words.iter().all(|word| text.contains(word))
+        })
+        .map(|(index, _)| index)
+        .collect();
+
+    SendMessageW(history.list, WM_SETREDRAW, WPARAM(0), LPARAM(0));
+    SendMessageW(history.list, LVM_DELETEALLITEMS, WPARAM(0), LPARAM(0));
+    for (row, &index) in history.rows.iter().enumerate() {
+        let entry = &history.entries[index];
+        let mut opened_wide = to_wide(&entry.opened_text());
+        let item = LVITEMW {
+            mask: LVIF_TEXT,
+            iItem: row as i32,
+            iSubItem: COLUMN_OPENED,
+            pszText: PWSTR::from_raw(opened_wide.as_mut_ptr()),
+            ..Default::default()
+        };
+        SendMessageW(history.list, LVM_INSERTITEMW, WPARAM(0), LPARAM(&item as *const _ as isize));
+        let path = std::path::Path::new(&entry.path);
+        let name = path.file_name().map_or(entry.path.clone(), |name| name.to_string_lossy().to_string());
+        set_cell(history.list, row, COLUMN_NAME, &name);
+        set_cell(history.list, row, COLUMN_FOLDER, &path.parent().map_or(String::new(), |folder| folder.to_string_lossy().to_string()));
+    }
+    SendMessageW(history.list, WM_SETREDRAW, WPARAM(1), LPARAM(0));
+
+    EnableWindow(GetDlgItem(window, ID_OPEN_HISTORY_CLEAR), BOOL::from(!history.entries.is_empty()));
+    if history.entries.is_empty() {
+        set_status(window, &strings.open_history_empty);
+    } else {
+        set_status(window, &fill(&strings.open_history_summary, &[
+            ("shown", &history.rows.len().to_string()),
+            ("count", &history.entries.len().to_string()),
+        ]));
+    }
+}
+
+unsafe fn clear(window: HWND) {
+    let strings = get_strings();
+    let answer = MessageBoxW(
+        window,
+        PCWSTR::from_raw(to_wide(&strings.open_history_confirm_clear).as_ptr()),
+        PCWSTR::from_raw(to_wide(&strings.confirm_title).as_ptr()),
+        MB_ICONQUESTION | MB_YESNO | MB_DEFBUTTON2,
+    );
+    if answer != IDYES {
+        return;
+    }
+
+    match clear_history() {
+        Ok(()) => {
+            log_info("Cleared the open history");
+            if let Some(history) = open_history(window) {
+                history.entries.clear();
+            }
+        }
+        Err(e) => log_warn(&format!("Failed to clear the open history: {}", e)),
+    }
+    fill_list(window);
+}
+
+// Has the owner open the file of `row`, as if opened from the results
+unsafe fn open_row(window: HWND, row: usize) {
+    let Some(history) = open_history(window) else {
+        return;
+    };
+    let Some(entry) = history.rows.get(row).and_then(|&index| history.entries.get(index)) else {
+        return;
+    };
+    let path_ptr = Box::into_raw(Box::new(entry.path.clone())) as usize;
+    if PostMessageW(history.owner, WM_OPEN_HISTORY_OPEN, WPARAM(path_ptr), LPARAM(0)).is_err() {
+        drop(Box::from_raw(path_ptr as *mut String));
+    }
+}
+
+extern "system" fn open_history_window_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_NOTIFY => {
+                let header = &*(lparam.0 as *const NMHDR);
+                if header.idFrom == ID_OPEN_HISTORY_LIST as usize && header.code == NM_DBLCLK {
+                    let activate = &*(lparam.0 as *const NMITEMACTIVATE);
+                    if activate.iItem >= 0 {
+                        open_row(window, activate.iItem as usize);
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                let notification = ((wparam.0 >> 16) & 0xFFFF) as u32;
+                match (wparam.0 & 0xFFFF) as i32 {
+                    ID_OPEN_HISTORY_FILTER if notification == EN_CHANGE => fill_list(window),
+                    ID_OPEN_HISTORY_RECORD => {
+                        if let Some(history) = open_history(window) {
+                            let checked = SendMessageW(GetDlgItem(window, ID_OPEN_HISTORY_RECORD), BM_GETCHECK, WPARAM(0), LPARAM(0)).0 == BST_CHECKED.0 as isize;
+                            let _ = PostMessageW(history.owner, WM_OPEN_HISTORY_RECORD, WPARAM(usize::from(checked)), LPARAM(0));
+                        }
+                    }
+                    ID_OPEN_HISTORY_CLEAR => clear(window),
+                    ID_OPEN_HISTORY_CLOSE => {
+                        let _ = DestroyWindow(window);
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(window);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                let history = SetWindowLongPtrW(window, GWLP_USERDATA, 0) as *mut OpenHistory;
+                if !history.is_null() {
+                    drop(Box::from_raw(history));
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
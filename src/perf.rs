@@ -0,0 +1,284 @@
+use windows::Win32::UI::WindowsAndMessaging::*;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock, atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering}};
+use std::time::{Duration, Instant};
+use crate::logger::{log_debug, log_warn};
+
+// Longer than this on the UI thread and the window visibly freezes
+const STALL_THRESHOLD: Duration = Duration::from_millis(100);
+const WATCHDOG_INTERVAL: Duration = Duration::from_millis(50);
+// The HUD shows the slowest message of this window
+const SLOWEST_WINDOW: Duration = Duration::from_secs(5);
+
+// Shared between the UI thread and the watchdog. Times are milliseconds
+// since EPOCH so they fit in an atomic.
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+static LAST_ACTIVITY_MS: AtomicU64 = AtomicU64::new(0);
+static MESSAGE_DEPTH: AtomicU32 = AtomicU32::new(0);
+static CURRENT_MESSAGE: AtomicU32 = AtomicU32::new(0);
+static STALL_REPORTED: AtomicBool = AtomicBool::new(false);
+// Set by WM_ENTERIDLE: a menu or message box is waiting for input, which
+// is not a stall even though no message is being handled
+static MODAL_IDLE: AtomicBool = AtomicBool::new(false);
+static MODAL_LOOPS: AtomicU64 = AtomicU64::new(0);
+
+struct PerfStats {
+    paints: VecDeque<Instant>,
+    last_paint: Duration,
+    slowest: Option<(&'static str, u32, Duration, Instant)>,
+    stalls: u64,
+    // Duration of each search stage the last time it ran, in pipeline order
+    stages: Vec<(&'static str, Duration)>,
+}
+
+static STATS: Mutex<PerfStats> = Mutex::new(PerfStats {
+    paints: VecDeque::new(),
+    last_paint: Duration::ZERO,
+    slowest: None,
+    stalls: 0,
+    stages: Vec::new(),
+});
+
+thread_local! {
+    // Time spent in nested messages, one entry per MessageTimer on the
+    // stack, so each message is charged only for its own work
+    static NESTED_TIME: RefCell<Vec<Duration>> = const { RefCell::new(Vec::new()) };
+}
+
+pub struct CacheCounter {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CacheCounter {
+    const fn new() -> Self {
+        Self {
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn describe(&self) -> String {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let total = hits + self.misses.load(Ordering::Relaxed);
+        if total == 0 {
+            return "-".to_string();
+        }
+        format!("{:.1}% of {}", hits as f64 * 100.0 / total as f64, total)
+    }
+}
+
+pub static ICON_LOOKUPS: CacheCounter = CacheCounter::new();
+pub static THUMBNAIL_LOOKUPS: CacheCounter = CacheCounter::new();
+
+fn now_ms() -> u64 {
+    EPOCH.get_or_init(Instant::now).elapsed().as_millis() as u64
+}
+
+fn touch() {
+    LAST_ACTIVITY_MS.store(now_ms(), Ordering::Relaxed);
+    STALL_REPORTED.store(false, Ordering::Relaxed);
+}
+
+// Starts the thread that notices a message which is still running, since
+// the timer below can only report once the message has returned
+pub fn start_watchdog() {
+    touch();
+    std::thread::spawn(|| loop {
+        std::thread::sleep(WATCHDOG_INTERVAL);
+
+        if MESSAGE_DEPTH.load(Ordering::Relaxed) == 0 || MODAL_IDLE.load(Ordering::Relaxed) {
+            continue;
+        }
+        let busy_ms = now_ms().saturating_sub(LAST_ACTIVITY_MS.load(Ordering::Relaxed));
+        if busy_ms > STALL_THRESHOLD.as_millis() as u64 && !STALL_REPORTED.swap(true, Ordering::Relaxed) {
+            log_warn(&format!(
+                "UI thread has not responded for {} ms, still handling {}",
+                busy_ms,
+                message_name(CURRENT_MESSAGE.load(Ordering::Relaxed)),
+            ));
+        }
+    });
+}
+
+// Measures one window message on the UI thread; create it at the top of
+// a window procedure and let it drop on return
+pub struct MessageTimer {
+    window: &'static str,
+    message: u32,
+    start: Instant,
+    modal_loops: u64,
+}
+
+impl MessageTimer {
+    pub fn start(window: &'static str, message: u32) -> Self {
+        if message == WM_ENTERIDLE {
+            MODAL_IDLE.store(true, Ordering::Relaxed);
+            MODAL_LOOPS.fetch_add(1, Ordering::Relaxed);
+        } else {
+            MODAL_IDLE.store(false, Ordering::Relaxed);
+        }
+
+        MESSAGE_DEPTH.fetch_add(1, Ordering::Relaxed);
+        CURRENT_MESSAGE.store(message, Ordering::Relaxed);
+        touch();
+        NESTED_TIME.with(|nested| nested.borrow_mut().push(Duration::ZERO));
+
+        Self {
+            window,
+            message,
+            start: Instant::now(),
+            modal_loops: MODAL_LOOPS.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Drop for MessageTimer {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        let own_time = NESTED_TIME.with(|nested| {
+            let mut nested = nested.borrow_mut();
+            let children = nested.pop().unwrap_or_default();
+            if let Some(parent) = nested.last_mut() {
+                *parent += elapsed;
+            }
+            elapsed.saturating_sub(children)
+        });
+
+        MESSAGE_DEPTH.fetch_sub(1, Ordering::Relaxed);
+        touch();
+
+        if own_time <= STALL_THRESHOLD {
+            return;
+        }
+
+        let name = message_name(self.message);
+        if MODAL_LOOPS.load(Ordering::Relaxed) != self.modal_loops {
+            // A menu or message box was open; the time is the user's
+            log_debug(&format!("{} in {} window ran a modal loop for {} ms", name, self.window, own_time.as_millis()));
+            return;
+        }
+
+        log_warn(&format!("UI thread stalled for {} ms handling {} in {} window", own_time.as_millis(), name, self.window));
+        if let Ok(mut stats) = STATS.lock() {
+            stats.stalls += 1;
+            let now = Instant::now();
+            let replace = stats.slowest.map_or(true, |(_, _, slowest, at)| {
+                own_time > slowest || now.duration_since(at) > SLOWEST_WINDOW
+            });
+            if replace {
+                stats.slowest = Some((self.window, self.message, own_time, now));
+            }
+        }
+    }
+}
+
+// Times one stage of the search pipeline; it is logged and kept for the HUD
+// when dropped
+pub struct StageTimer {
+    name: &'static str,
+    start: Instant,
+}
+
+pub fn time_stage(name: &'static str) -> StageTimer {
+    StageTimer {
+        name,
+        start: Instant::now(),
+    }
+}
+
+impl Drop for StageTimer {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        log_debug(&format!("Search stage '{}' took {} ms", self.name, elapsed.as_millis()));
+        if let Ok(mut stats) = STATS.lock() {
+            match stats.stages.iter_mut().find(|(name, _)| *name == self.name) {
+                Some(stage) => stage.1 = elapsed,
+                None => stats.stages.push((self.name, elapsed)),
+            }
+        }
+    }
+}
+
+pub fn record_paint(duration: Duration) {
+    if let Ok(mut stats) = STATS.lock() {
+        let now = Instant::now();
+        while stats.paints.front().is_some_and(|&at| now.duration_since(at) > Duration::from_secs(1)) {
+            stats.paints.pop_front();
+        }
+        stats.paints.push_back(now);
+        stats.last_paint = duration;
+    }
+}
+
+// Text for the performance HUD. `queues` are the depths of the work queues
+// the caller owns, as (label, count).
+pub fn hud_lines(queues: &[(&str, usize)]) -> Vec<String> {
+    let Ok(stats) = STATS.lock() else {
+        return Vec::new();
+    };
+
+    let now = Instant::now();
+    let fps = stats.paints.iter().filter(|&&at| now.duration_since(at) <= Duration::from_secs(1)).count();
+    let slowest = match stats.slowest {
+        Some((window, message, duration, at)) if now.duration_since(at) <= SLOWEST_WINDOW => {
+            format!("{} ms ({} in {})", duration.as_millis(), message_name(message), window)
+        }
+        _ => "-".to_string(),
+    };
+    let queues = queues
+        .iter()
+        .map(|(label, count)| format!("{} {}", label, count))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let stages = if stats.stages.is_empty() {
+        "-".to_string()
+    } else {
+        stats.stages
+            .iter()
+            .map(|(name, duration)| format!("{} {} ms", name, duration.as_millis()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    vec![
+        format!("FPS: {}   last paint: {:.1} ms", fps, stats.last_paint.as_secs_f64() * 1000.0),
+        format!("Slowest message: {}   stalls: {}", slowest, stats.stalls),
+        format!("Queues: {}", queues),
+        format!("Icon cache: {}", ICON_LOOKUPS.describe()),
+        format!("Thumbnail cache: {}", THUMBNAIL_LOOKUPS.describe()),
+        format!("Search: {}", stages),
+    ]
+}
+
+fn message_name(message: u32) -> String {
+    let name = match message {
+        WM_CREATE => "WM_CREATE",
+        WM_SIZE => "WM_SIZE",
+        WM_PAINT => "WM_PAINT",
+        WM_TIMER => "WM_TIMER",
+        WM_COMMAND => "WM_COMMAND",
+        WM_NOTIFY => "WM_NOTIFY",
+        WM_KEYDOWN => "WM_KEYDOWN",
+        WM_CHAR => "WM_CHAR",
+        WM_VSCROLL => "WM_VSCROLL",
+        WM_MOUSEWHEEL => "WM_MOUSEWHEEL",
+        WM_MOUSEMOVE => "WM_MOUSEMOVE",
+        WM_LBUTTONDOWN => "WM_LBUTTONDOWN",
+        WM_LBUTTONUP => "WM_LBUTTONUP",
+        WM_LBUTTONDBLCLK => "WM_LBUTTONDBLCLK",
+        WM_RBUTTONUP => "WM_RBUTTONUP",
+        WM_CONTEXTMENU => "WM_CONTEXTMENU",
+        WM_CLOSE => "WM_CLOSE",
+        WM_DESTROY => "WM_DESTROY",
+        _ if (WM_USER..WM_APP).contains(&message) => return format!("WM_USER+{}", message - WM_USER),
+        _ => return format!("message 0x{:04X}", message),
+    };
+    name.to_string()
+}
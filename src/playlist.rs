@@ -0,0 +1,98 @@
+use crate::everything_sdk::FileResult;
+use std::path::{Component, Path, PathBuf};
+
+pub fn is_playlist_path(path: &str) -> bool {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    extension == "m3u" || extension == "m3u8"
+}
+
+// Returns the entries of an M3U/M3U8 playlist as absolute paths.
+// Relative entries are resolved against the playlist's own folder;
+// comment/#EXT lines and non-file URLs are skipped.
+pub fn parse_playlist(content: &str, playlist_path: &str) -> Vec<String> {
+    let base_dir = Path::new(playlist_path).parent().unwrap_or(Path::new(""));
+    let mut paths = Vec::new();
+
+    for line in content.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let entry = if let Some(file_url) = line.strip_prefix("file:///") {
+            percent_decode(file_url)
+        } else if line.contains("://") {
+            // Streams and other remote entries can't be shown as files
+            continue;
+        } else {
+            line.to_string()
+        };
+
+        let entry = entry.replace('/', "\\");
+        let entry_path = Path::new(&entry);
+        let resolved = if entry_path.is_absolute() || entry.starts_with("\\\\") {
+            PathBuf::from(&entry)
+        } else {
+            base_dir.join(entry_path)
+        };
+
+        paths.push(normalize_path(&resolved).to_string_lossy().to_string());
+    }
+
+    paths
+}
+
+// Extended M3U, written as UTF-8 so it is valid for both .m3u and .m3u8
+pub fn format_m3u(items: &[FileResult]) -> String {
+    let mut content = String::from("#EXTM3U\n");
+    for item in items {
+        let title = Path::new(item.name())
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| item.name().to_string());
+
+        content.push_str(&format!("#EXTINF:-1,{}\n{}\n", title, item.path()));
+    }
+    content
+}
+
+// Removes "." and ".." components without touching the file system
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(value) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(value);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
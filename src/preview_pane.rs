@@ -0,0 +1,208 @@
+use std::path::Path;
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::HFONT,
+        System::{
+            Com::*,
+            LibraryLoader::GetModuleHandleW,
+            SystemServices::{SS_CENTER, SS_CENTERIMAGE},
+        },
+        UI::{
+            Shell::{
+                PropertiesSystem::{IInitializeWithFile, IInitializeWithStream},
+                AssocQueryStringW, IPreviewHandler, SHCreateStreamOnFileEx, ASSOCF_INIT_DEFAULTTOSTAR, ASSOCSTR_SHELLEXTENSION,
+            },
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::lang::get_strings;
+use crate::logger::{log_debug, log_warn};
+use crate::long_paths::{extended_path, shell_path};
+use crate::thumbnail::to_wide;
+
+// The shell extension key preview handlers are registered under
+const PREVIEW_HANDLER_INTERFACE: PCWSTR = w!("{8895b1c6-b41f-4c1c-a562-0d564250836f}");
+
+// Pane right of the results that shows the selected file with the preview
+// handler registered for its type, the same one Explorer's preview pane
+// uses. Handlers run out of process in the system's preview surrogate, so
+// one that crashes or hangs doesn't take the window down with it.
+pub struct PreviewPane {
+    pub window: HWND,
+    handler: Option<IPreviewHandler>,
+    path: Option<String>,
+    com_initialized: bool,
+}
+
+impl PreviewPane {
+    pub fn create(parent: HWND, font: HFONT, id: i32) -> Self {
+        let window = unsafe {
+            let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+            // A static control shows the message while there is no preview;
+            // the handler's window covers it otherwise
+            let window = CreateWindowExW(
+                WS_EX_CLIENTEDGE,
+                w!("STATIC"),
+                PCWSTR::from_raw(to_wide(&get_strings().preview_select_file).as_ptr()),
+                WS_CHILD | WS_VISIBLE | WS_CLIPCHILDREN | WINDOW_STYLE(SS_CENTER.0 | SS_CENTERIMAGE.0),
+                0, 0, 0, 0,
+                parent,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(window, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+            window
+        };
+
+        // Handlers are COM objects; this keeps COM up on the UI thread for
+        // as long as the pane is there
+        let com_initialized = unsafe { CoInitializeEx(None, COINIT_APARTMENTTHREADED | COINIT_DISABLE_OLE1DDE).is_ok() };
+
+        PreviewPane { window, handler: None, path: None, com_initialized }
+    }
+
+    // Previews `path`, or shows the placeholder for None. Nothing happens
+    // when the file is already shown.
+    pub fn show_file(&mut self, path: Option<&str>) {
+        if self.path.as_deref() == path {
+            return;
+        }
+        self.unload();
+        self.path = path.map(str::to_string);
+
+        let strings = get_strings();
+        let Some(path) = path else {
+            self.set_message(&strings.preview_select_file);
+            return;
+        };
+
+        match unsafe { self.load(path) } {
+            Ok(handler) => self.handler = Some(handler),
+            Err(e) => {
+                log_debug(&format!("No preview for {}: {}", path, e));
+                self.set_message(&strings.preview_unavailable);
+            }
+        }
+    }
+
+    unsafe fn load(&self, path: &str) -> Result<IPreviewHandler> {
+        let extension = Path::new(path).extension().and_then(|extension| extension.to_str()).unwrap_or("");
+        let clsid = preview_handler_clsid(extension).ok_or_else(|| Error::from(E_NOINTERFACE))?;
+        let handler: IPreviewHandler = CoCreateInstance(&clsid, None, CLSCTX_LOCAL_SERVER)?;
+
+        // Streams work across the process boundary; handlers that only
+        // take a path get the path
+        if let Ok(initialize) = handler.cast::<IInitializeWithStream>() {
+            let path_wide = to_wide(&extended_path(path));
+            let stream = SHCreateStreamOnFileEx(
+                PCWSTR::from_raw(path_wide.as_ptr()),
+                (STGM_READ | STGM_SHARE_DENY_NONE).0,
+                0,
+                FALSE,
+                None,
+            )?;
+            initialize.Initialize(&stream, STGM_READ.0)?;
+        } else {
+            let path_wide = to_wide(&shell_path(path));
+            handler.cast::<IInitializeWithFile>()?.Initialize(PCWSTR::from_raw(path_wide.as_ptr()), STGM_READ.0)?;
+        }
+
+        self.set_message("");
+        let rect = self.client_rect();
+        handler.SetWindow(self.window, &rect)?;
+        if let Err(e) = handler.DoPreview() {
+            let _ = handler.Unload();
+            return Err(e);
+        }
+        Ok(handler)
+    }
+
+    // Lets the handler release the file and its window
+    fn unload(&mut self) {
+        if let Some(handler) = self.handler.take() {
+            unsafe {
+                if let Err(e) = handler.Unload() {
+                    log_warn(&format!("Failed to unload the preview handler: {}", e));
+                }
+            }
+        }
+    }
+
+    // Tells the handler the pane's new size after the window was moved
+    pub fn resize(&self) {
+        if let Some(handler) = self.handler.as_ref() {
+            let rect = self.client_rect();
+            unsafe {
+                let _ = handler.SetRect(&rect);
+            }
+        }
+    }
+
+    // The placeholder text and the language it is in
+    pub fn refresh_message(&self) {
+        if self.handler.is_none() {
+            let strings = get_strings();
+            self.set_message(if self.path.is_some() { &strings.preview_unavailable } else { &strings.preview_select_file });
+        }
+    }
+
+    fn set_message(&self, text: &str) {
+        unsafe {
+            let _ = SetWindowTextW(self.window, PCWSTR::from_raw(to_wide(text).as_ptr()));
+        }
+    }
+
+    fn client_rect(&self) -> RECT {
+        let mut rect = RECT::default();
+        unsafe {
+            let _ = GetClientRect(self.window, &mut rect);
+        }
+        rect
+    }
+
+    pub fn destroy(mut self) {
+        self.unload();
+        unsafe {
+            let _ = DestroyWindow(self.window);
+        }
+    }
+}
+
+impl Drop for PreviewPane {
+    fn drop(&mut self) {
+        // The handler has to go before COM does
+        self.unload();
+        if self.com_initialized {
+            unsafe {
+                CoUninitialize();
+            }
+        }
+    }
+}
+
+// The class of the preview handler registered for `extension`, if any
+fn preview_handler_clsid(extension: &str) -> Option<GUID> {
+    if extension.is_empty() {
+        return None;
+    }
+    let association = to_wide(&format!(".{}", extension));
+    let mut buffer = [0u16; 64];
+    let mut length = buffer.len() as u32;
+    unsafe {
+        AssocQueryStringW(
+            ASSOCF_INIT_DEFAULTTOSTAR,
+            ASSOCSTR_SHELLEXTENSION,
+            PCWSTR::from_raw(association.as_ptr()),
+            PREVIEW_HANDLER_INTERFACE,
+            PWSTR::from_raw(buffer.as_mut_ptr()),
+            &mut length,
+        )
+        .ok()
+        .ok()?;
+        CLSIDFromString(PCWSTR::from_raw(buffer.as_ptr())).ok()
+    }
+}
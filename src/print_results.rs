@@ -0,0 +1,445 @@
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::*,
+        Storage::Xps::{EndDoc, EndPage, StartDocW, StartPage, DOCINFOW},
+        System::{
+            LibraryLoader::GetModuleHandleW,
+            SystemServices::{SS_CENTER, SS_CENTERIMAGE},
+        },
+        UI::{
+            Controls::Dialogs::*,
+            Input::KeyboardAndMouse::EnableWindow,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::lang::{fill, get_strings};
+use crate::logger::{log_error, log_info, log_warn};
+use crate::thumbnail::to_wide;
+
+const PRINT_PREVIEW_CLASS: &str = "EverythingLikePrintPreview";
+
+const PREVIEW_WIDTH: i32 = 760;
+const PREVIEW_HEIGHT: i32 = 900;
+const MARGIN: i32 = 10;
+const BUTTON_WIDTH: i32 = 90;
+const BUTTON_HEIGHT: i32 = 26;
+const TOOLBAR_HEIGHT: i32 = BUTTON_HEIGHT + MARGIN * 2;
+
+// Control IDs
+const ID_PREVIEW_PREVIOUS: i32 = 9911;
+const ID_PREVIEW_NEXT: i32 = 9912;
+const ID_PREVIEW_PAGE: i32 = 9913;
+const ID_PREVIEW_PRINT: i32 = 9914;
+const ID_PREVIEW_CLOSE: i32 = 9915;
+
+// Text size on paper, in points
+const FONT_POINTS: i32 = 9;
+
+// A page the preview is drawn on when there is no printer to measure: A4
+// at 300 dpi
+const FALLBACK_PAGE: PageSize = PageSize { width: 2480, height: 3508, dpi_x: 300, dpi_y: 300 };
+
+pub struct PrintColumn {
+    pub name: String,
+    // On screen, in pixels; the columns share the paper's width alike
+    pub width: i32,
+    pub align_right: bool,
+}
+
+// The results as they are shown: the visible columns, in the current sort
+pub struct PrintTable {
+    pub title: String,
+    pub columns: Vec<PrintColumn>,
+    pub rows: Vec<Vec<String>>,
+}
+
+// The printable area of a page in device units, and the device's resolution
+#[derive(Clone, Copy)]
+struct PageSize {
+    width: i32,
+    height: i32,
+    dpi_x: i32,
+    dpi_y: i32,
+}
+
+impl PageSize {
+    unsafe fn of(hdc: HDC) -> Self {
+        Self {
+            width: GetDeviceCaps(hdc, HORZRES),
+            height: GetDeviceCaps(hdc, VERTRES),
+            dpi_x: GetDeviceCaps(hdc, LOGPIXELSX),
+            dpi_y: GetDeviceCaps(hdc, LOGPIXELSY),
+        }
+    }
+
+    fn margin_x(&self) -> i32 {
+        self.dpi_x / 2
+    }
+
+    fn margin_y(&self) -> i32 {
+        self.dpi_y / 2
+    }
+
+    fn line_height(&self) -> i32 {
+        // The font's height with a little room between the lines
+        FONT_POINTS * self.dpi_y / 72 * 4 / 3
+    }
+
+    // Title, column headers and a gap above the rows, the page number below
+    fn rows_per_page(&self) -> usize {
+        let rows_height = self.height - self.margin_y() * 2 - self.line_height() * 5;
+        (rows_height / self.line_height()).max(1) as usize
+    }
+
+    fn page_count(&self, table: &PrintTable) -> usize {
+        table.rows.len().div_ceil(self.rows_per_page()).max(1)
+    }
+}
+
+// Draws page `page` of `table`, counted from 0, in the units of `size`
+unsafe fn draw_page(hdc: HDC, size: PageSize, table: &PrintTable, page: usize) {
+    let strings = get_strings();
+    let font_height = -(FONT_POINTS * size.dpi_y / 72);
+    let font = CreateFontW(font_height, 0, 0, 0, FW_NORMAL.0 as i32, 0, 0, 0, DEFAULT_CHARSET.0 as u32, 0, 0, 0, 0, w!("Segoe UI"));
+    let bold_font = CreateFontW(font_height, 0, 0, 0, FW_BOLD.0 as i32, 0, 0, 0, DEFAULT_CHARSET.0 as u32, 0, 0, 0, 0, w!("Segoe UI"));
+    let old_font = SelectObject(hdc, bold_font);
+    SetBkMode(hdc, TRANSPARENT);
+    SetTextColor(hdc, COLORREF(0x00000000));
+
+    let left = size.margin_x();
+    let right = size.width - size.margin_x();
+    let line_height = size.line_height();
+    let padding = size.dpi_x / 20;
+    let mut y = size.margin_y();
+
+    let draw = |text: &str, left: i32, right: i32, y: i32, format: DRAW_TEXT_FORMAT| {
+        let mut text_wide: Vec<u16> = text.encode_utf16().collect();
+        let mut rect = RECT { left, top: y, right, bottom: y + line_height };
+        DrawTextW(hdc, &mut text_wide, &mut rect, format | DT_VCENTER | DT_SINGLELINE | DT_NOPREFIX | DT_END_ELLIPSIS);
+    };
+
+    draw(&table.title, left, right, y, DT_LEFT);
+    y += line_height * 2;
+
+    // Columns as wide, relative to each other, as on screen
+    let total_width: i32 = table.columns.iter().map(|column| column.width.max(1)).sum::<i32>().max(1);
+    let mut lefts = Vec::with_capacity(table.columns.len() + 1);
+    let mut x = left;
+    for column in &table.columns {
+        lefts.push(x);
+        x += ((right - left) as i64 * column.width.max(1) as i64 / total_width as i64) as i32;
+    }
+    lefts.push(right);
+
+    for (index, column) in table.columns.iter().enumerate() {
+        let format = if column.align_right { DT_RIGHT } else { DT_LEFT };
+        draw(&column.name, lefts[index] + padding, lefts[index + 1] - padding, y, format);
+    }
+    y += line_height;
+    let pen = CreatePen(PS_SOLID, (size.dpi_y / 150).max(1), COLORREF(0x00000000));
+    let old_pen = SelectObject(hdc, pen);
+    MoveToEx(hdc, left, y, None);
+    LineTo(hdc, right, y);
+    SelectObject(hdc, old_pen);
+    DeleteObject(pen);
+
+    SelectObject(hdc, font);
+    let rows_per_page = size.rows_per_page();
+    let first = page * rows_per_page;
+    for row in table.rows.iter().skip(first).take(rows_per_page) {
+        for (index, (cell, column)) in row.iter().zip(&table.columns).enumerate() {
+            let format = if column.align_right { DT_RIGHT } else { DT_LEFT };
+            draw(cell, lefts[index] + padding, lefts[index + 1] - padding, y, format);
+        }
+        y += line_height;
+    }
+
+    let page_number = fill(&strings.print_page_number, &[
+        ("page", &(page + 1).to_string()),
+        ("pages", &size.page_count(table).to_string()),
+    ]);
+    draw(&page_number, left, right, size.height - size.margin_y() - line_height, DT_CENTER);
+
+    SelectObject(hdc, old_font);
+    DeleteObject(font);
+    DeleteObject(bold_font);
+}
+
+// The default printer's page, for the preview to match what will print
+fn default_page_size() -> PageSize {
+    unsafe {
+        let mut dialog = PRINTDLGW {
+            lStructSize: std::mem::size_of::<PRINTDLGW>() as u32,
+            Flags: PD_RETURNDEFAULT | PD_RETURNDC,
+            ..Default::default()
+        };
+        if !PrintDlgW(&mut dialog).as_bool() || dialog.hDC.is_invalid() {
+            return FALLBACK_PAGE;
+        }
+        let size = PageSize::of(dialog.hDC);
+        release_print_dialog(&dialog);
+        if size.width > 0 && size.height > 0 && size.dpi_x > 0 && size.dpi_y > 0 { size } else { FALLBACK_PAGE }
+    }
+}
+
+unsafe fn release_print_dialog(dialog: &PRINTDLGW) {
+    if !dialog.hDC.is_invalid() {
+        DeleteDC(dialog.hDC);
+    }
+    if !dialog.hDevMode.is_invalid() {
+        let _ = GlobalFree(dialog.hDevMode);
+    }
+    if !dialog.hDevNames.is_invalid() {
+        let _ = GlobalFree(dialog.hDevNames);
+    }
+}
+
+// File → Print: asks for the printer and prints every page of `table`
+pub fn print_table(owner: HWND, table: &PrintTable) {
+    unsafe {
+        let mut dialog = PRINTDLGW {
+            lStructSize: std::mem::size_of::<PRINTDLGW>() as u32,
+            hwndOwner: owner,
+            Flags: PD_RETURNDC | PD_NOPAGENUMS | PD_NOSELECTION | PD_USEDEVMODECOPIESANDCOLLATE,
+            ..Default::default()
+        };
+        if !PrintDlgW(&mut dialog).as_bool() {
+            return;
+        }
+        if dialog.hDC.is_invalid() {
+            release_print_dialog(&dialog);
+            return;
+        }
+
+        let hdc = dialog.hDC;
+        let size = PageSize::of(hdc);
+        let document_name = to_wide(&table.title);
+        let document = DOCINFOW {
+            cbSize: std::mem::size_of::<DOCINFOW>() as i32,
+            lpszDocName: PCWSTR::from_raw(document_name.as_ptr()),
+            ..Default::default()
+        };
+
+        let pages = size.page_count(table);
+        if StartDocW(hdc, &document) > 0 {
+            for page in 0..pages {
+                if StartPage(hdc) <= 0 {
+                    break;
+                }
+                draw_page(hdc, size, table, page);
+                if EndPage(hdc) <= 0 {
+                    break;
+                }
+            }
+            EndDoc(hdc);
+            log_info(&format!("Printed {} results on {} pages", table.rows.len(), pages));
+        } else {
+            log_warn("The printer didn't start the document");
+        }
+        release_print_dialog(&dialog);
+    }
+}
+
+struct PrintPreview {
+    table: PrintTable,
+    size: PageSize,
+    page: usize,
+}
+
+// The window's PrintPreview, kept in GWLP_USERDATA
+unsafe fn print_preview(window: HWND) -> Option<&'static mut PrintPreview> {
+    let preview = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut PrintPreview;
+    preview.as_mut()
+}
+
+pub fn register_print_preview_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(print_preview_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH((COLOR_APPWORKSPACE.0 + 1) as isize),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikePrintPreview"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// File → Print Preview: the pages of `table` as the default printer will
+// print them, one at a time, with a button to print them
+pub fn show_print_preview(owner: HWND, font: HFONT, table: PrintTable) {
+    unsafe {
+        let strings = get_strings();
+        let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+
+        let window = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            PCWSTR::from_raw(to_wide(PRINT_PREVIEW_CLASS).as_ptr()),
+            PCWSTR::from_raw(to_wide(&strings.print_preview_title).as_ptr()),
+            WS_OVERLAPPEDWINDOW | WS_CLIPCHILDREN | WS_VISIBLE,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            PREVIEW_WIDTH,
+            PREVIEW_HEIGHT,
+            owner,
+            None,
+            instance,
+            None,
+        );
+
+        if window.0 == 0 {
+            log_error("Failed to create print preview window");
+            return;
+        }
+
+        let mut client_rect = RECT::default();
+        let _ = GetClientRect(window, &mut client_rect);
+
+        let controls = [
+            (w!("BUTTON"), strings.print_preview_previous.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), ID_PREVIEW_PREVIOUS, MARGIN, BUTTON_WIDTH),
+            (w!("BUTTON"), strings.print_preview_next.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), ID_PREVIEW_NEXT, MARGIN * 2 + BUTTON_WIDTH, BUTTON_WIDTH),
+            (w!("STATIC"), String::new(), WINDOW_STYLE(SS_CENTER.0 | SS_CENTERIMAGE.0), ID_PREVIEW_PAGE, MARGIN * 3 + BUTTON_WIDTH * 2, BUTTON_WIDTH * 2),
+            (w!("BUTTON"), strings.print_preview_print.clone(), WS_TABSTOP | WINDOW_STYLE(BS_DEFPUSHBUTTON as u32), ID_PREVIEW_PRINT, MARGIN * 4 + BUTTON_WIDTH * 4, BUTTON_WIDTH),
+            (w!("BUTTON"), strings.print_preview_close.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), ID_PREVIEW_CLOSE, MARGIN * 5 + BUTTON_WIDTH * 5, BUTTON_WIDTH),
+        ];
+        for (class, text, style, id, x, width) in controls {
+            let control = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                class,
+                PCWSTR::from_raw(to_wide(&text).as_ptr()),
+                WS_CHILD | WS_VISIBLE | style,
+                x, MARGIN, width, BUTTON_HEIGHT,
+                window,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(control, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        }
+
+        let preview = Box::new(PrintPreview { table, size: default_page_size(), page: 0 });
+        SetWindowLongPtrW(window, GWLP_USERDATA, Box::into_raw(preview) as isize);
+        show_page(window, 0);
+    }
+}
+
+// Moves the preview to `page` and updates the page counter and buttons
+unsafe fn show_page(window: HWND, page: usize) {
+    let Some(preview) = print_preview(window) else {
+        return;
+    };
+    let pages = preview.size.page_count(&preview.table);
+    preview.page = page.min(pages - 1);
+
+    let label = fill(&get_strings().print_page_number, &[
+        ("page", &(preview.page + 1).to_string()),
+        ("pages", &pages.to_string()),
+    ]);
+    let _ = SetWindowTextW(GetDlgItem(window, ID_PREVIEW_PAGE), PCWSTR::from_raw(to_wide(&label).as_ptr()));
+    EnableWindow(GetDlgItem(window, ID_PREVIEW_PREVIOUS), preview.page > 0);
+    EnableWindow(GetDlgItem(window, ID_PREVIEW_NEXT), preview.page + 1 < pages);
+    InvalidateRect(window, None, TRUE);
+}
+
+// The page scaled to fit below the buttons, a sheet of white on grey
+unsafe fn paint_preview(window: HWND, hdc: HDC, preview: &PrintPreview) {
+    let mut client_rect = RECT::default();
+    let _ = GetClientRect(window, &mut client_rect);
+    let toolbar_rect = RECT { bottom: TOOLBAR_HEIGHT, ..client_rect };
+    FillRect(hdc, &toolbar_rect, GetSysColorBrush(COLOR_BTNFACE));
+
+    let available_width = client_rect.right - MARGIN * 2;
+    let available_height = client_rect.bottom - TOOLBAR_HEIGHT - MARGIN * 2;
+    if available_width <= 0 || available_height <= 0 {
+        return;
+    }
+    let size = preview.size;
+    let (width, height) = if available_width as i64 * size.height as i64 > available_height as i64 * size.width as i64 {
+        ((available_height as i64 * size.width as i64 / size.height as i64) as i32, available_height)
+    } else {
+        (available_width, (available_width as i64 * size.height as i64 / size.width as i64) as i32)
+    };
+    let sheet = RECT {
+        left: (client_rect.right - width) / 2,
+        top: TOOLBAR_HEIGHT + MARGIN,
+        right: (client_rect.right - width) / 2 + width,
+        bottom: TOOLBAR_HEIGHT + MARGIN + height,
+    };
+    FillRect(hdc, &sheet, HBRUSH(GetStockObject(WHITE_BRUSH).0));
+
+    let saved = SaveDC(hdc);
+    SetMapMode(hdc, MM_ANISOTROPIC);
+    SetWindowExtEx(hdc, size.width, size.height, None);
+    SetViewportExtEx(hdc, width, height, None);
+    SetViewportOrgEx(hdc, sheet.left, sheet.top, None);
+    draw_page(hdc, size, &preview.table, preview.page);
+    RestoreDC(hdc, saved);
+}
+
+extern "system" fn print_preview_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_PAINT => {
+                let mut ps = PAINTSTRUCT::default();
+                let hdc = BeginPaint(window, &mut ps);
+                if let Some(preview) = print_preview(window) {
+                    paint_preview(window, hdc, preview);
+                }
+                EndPaint(window, &ps);
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                let control_id = (wparam.0 & 0xFFFF) as i32;
+                let page = print_preview(window).map_or(0, |preview| preview.page);
+                match control_id {
+                    ID_PREVIEW_PREVIOUS => show_page(window, page.saturating_sub(1)),
+                    ID_PREVIEW_NEXT => show_page(window, page + 1),
+                    ID_PREVIEW_PRINT => {
+                        if let Some(preview) = print_preview(window) {
+                            print_table(window, &preview.table);
+                        }
+                    }
+                    ID_PREVIEW_CLOSE => {
+                        let _ = DestroyWindow(window);
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(window);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                let preview = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut PrintPreview;
+                if !preview.is_null() {
+                    SetWindowLongPtrW(window, GWLP_USERDATA, 0);
+                    drop(Box::from_raw(preview));
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::*,
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Input::KeyboardAndMouse::*,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::logger::log_error;
+use crate::thumbnail::to_wide;
+
+// Sent to the owner window when Enter is pressed in the popup; WPARAM is
+// the query as a boxed String
+pub const WM_QUICK_SEARCH: u32 = WM_USER + 112;
+
+const QUICK_SEARCH_CLASS: &str = "EverythingLikeQuickSearch";
+
+const POPUP_WIDTH: i32 = 560;
+const POPUP_HEIGHT: i32 = 38;
+const MARGIN: i32 = 6;
+
+const ID_QUICK_SEARCH_TEXT: i32 = 9701;
+
+struct QuickSearch {
+    edit: HWND,
+    owner: HWND,
+}
+
+// The window's QuickSearch, kept in GWLP_USERDATA
+unsafe fn quick_search(window: HWND) -> Option<&'static mut QuickSearch> {
+    let quick_search = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut QuickSearch;
+    quick_search.as_mut()
+}
+
+pub fn register_quick_search_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(quick_search_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH((COLOR_WINDOW.0 + 1) as isize),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeQuickSearch"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// Compact search bar near the top of the screen the cursor is on, opened
+// from the tray menu or the global hotkey. Escape or clicking elsewhere
+// hides it again.
+pub fn show_quick_search(owner: HWND, font: HFONT) {
+    unsafe {
+        // The popup is created on first use and hidden, not destroyed, afterwards
+        let existing = FindWindowW(PCWSTR::from_raw(to_wide(QUICK_SEARCH_CLASS).as_ptr()), None);
+        let window = if existing.0 != 0 {
+            existing
+        } else {
+            match create_quick_search(owner, font) {
+                Some(window) => window,
+                None => return,
+            }
+        };
+        let Some(edit) = quick_search(window).map(|quick_search| quick_search.edit) else {
+            return;
+        };
+
+        let mut cursor = POINT::default();
+        let _ = GetCursorPos(&mut cursor);
+        let mut monitor_info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        let _ = GetMonitorInfoW(MonitorFromPoint(cursor, MONITOR_DEFAULTTONEAREST), &mut monitor_info);
+        let work_area = monitor_info.rcWork;
+        let x = work_area.left + (work_area.right - work_area.left - POPUP_WIDTH) / 2;
+        let y = work_area.top + (work_area.bottom - work_area.top) / 5;
+
+        let _ = SetWindowPos(window, HWND_TOPMOST, x, y, POPUP_WIDTH, POPUP_HEIGHT, SWP_SHOWWINDOW);
+        let _ = SetWindowTextW(edit, w!(""));
+        let _ = SetForegroundWindow(window);
+        SetFocus(edit);
+    }
+}
+
+unsafe fn create_quick_search(owner: HWND, font: HFONT) -> Option<HWND> {
+    let instance = HINSTANCE(GetModuleHandleW(None).ok()?.0);
+
+    let window = CreateWindowExW(
+        WS_EX_TOOLWINDOW | WS_EX_TOPMOST,
+        PCWSTR::from_raw(to_wide(QUICK_SEARCH_CLASS).as_ptr()),
+        w!(""),
+        WS_POPUP | WS_BORDER,
+        0,
+        0,
+        POPUP_WIDTH,
+        POPUP_HEIGHT,
+        None,
+        None,
+        instance,
+        None,
+    );
+    if window.0 == 0 {
+        log_error("Failed to create quick search popup");
+        return None;
+    }
+
+    let mut client_rect = RECT::default();
+    let _ = GetClientRect(window, &mut client_rect);
+    let edit = CreateWindowExW(
+        WS_EX_CLIENTEDGE,
+        w!("EDIT"),
+        w!(""),
+        WS_CHILD | WS_VISIBLE | WINDOW_STYLE(ES_AUTOHSCROLL as u32),
+        MARGIN,
+        MARGIN,
+        client_rect.right - MARGIN * 2,
+        client_rect.bottom - MARGIN * 2,
+        window,
+        HMENU(ID_QUICK_SEARCH_TEXT as isize),
+        instance,
+        None,
+    );
+    SendMessageW(edit, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+
+    // Enter and Escape need handling in the edit itself; the original
+    // procedure is kept in its GWLP_USERDATA
+    let original_proc = SetWindowLongPtrW(edit, GWLP_WNDPROC, quick_search_edit_proc as *const () as isize);
+    SetWindowLongPtrW(edit, GWLP_USERDATA, original_proc);
+
+    let quick_search = Box::new(QuickSearch { edit, owner });
+    SetWindowLongPtrW(window, GWLP_USERDATA, Box::into_raw(quick_search) as isize);
+    Some(window)
+}
+
+extern "system" fn quick_search_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_ACTIVATE => {
+                if (wparam.0 & 0xFFFF) as u32 == WA_INACTIVE {
+                    ShowWindow(window, SW_HIDE);
+                }
+                LRESULT(0)
+            }
+            WM_SETFOCUS => {
+                if let Some(quick_search) = quick_search(window) {
+                    SetFocus(quick_search.edit);
+                }
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                let quick_search = SetWindowLongPtrW(window, GWLP_USERDATA, 0) as *mut QuickSearch;
+                if !quick_search.is_null() {
+                    drop(Box::from_raw(quick_search));
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
+
+extern "system" fn quick_search_edit_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_KEYDOWN if wparam.0 == VK_RETURN.0 as usize => {
+                submit_quick_search(GetParent(window));
+                return LRESULT(0);
+            }
+            WM_KEYDOWN if wparam.0 == VK_ESCAPE.0 as usize => {
+                ShowWindow(GetParent(window), SW_HIDE);
+                return LRESULT(0);
+            }
+            // Swallow the characters of Enter and Escape so the edit doesn't beep
+            WM_CHAR if wparam.0 == 0x0D || wparam.0 == 0x1B => {
+                return LRESULT(0);
+            }
+            _ => {}
+        }
+
+        let original_proc: WNDPROC = std::mem::transmute(GetWindowLongPtrW(window, GWLP_USERDATA));
+        if original_proc.is_some() {
+            CallWindowProcW(original_proc, window, message, wparam, lparam)
+        } else {
+            DefWindowProcW(window, message, wparam, lparam)
+        }
+    }
+}
+
+unsafe fn submit_quick_search(window: HWND) {
+    let Some(quick_search) = quick_search(window) else {
+        return;
+    };
+
+    let mut buffer: [u16; 1024] = [0; 1024];
+    let len = GetWindowTextW(quick_search.edit, &mut buffer);
+    let query = String::from_utf16_lossy(&buffer[..len.max(0) as usize]).trim().to_string();
+
+    ShowWindow(window, SW_HIDE);
+    if query.is_empty() {
+        return;
+    }
+
+    let query_ptr = Box::into_raw(Box::new(query)) as usize;
+    if PostMessageW(quick_search.owner, WM_QUICK_SEARCH, WPARAM(query_ptr), LPARAM(0)).is_err() {
+        drop(Box::from_raw(query_ptr as *mut String));
+    }
+}
@@ -0,0 +1,714 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Globalization::{MultiByteToWideChar, CP_ACP, MULTI_BYTE_TO_WIDE_CHAR_FLAGS},
+        Graphics::Gdi::*,
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Controls::SetScrollInfo,
+            Input::KeyboardAndMouse::*,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::lang::{fill, get_strings};
+use crate::logger::{log_error, log_warn};
+use crate::menu_mnemonics::assign_menu_mnemonics;
+use crate::thumbnail::to_wide;
+
+const QUICK_VIEW_WINDOW_CLASS: &str = "EverythingLikeQuickView";
+
+const QUICK_VIEW_WINDOW_WIDTH: i32 = 900;
+const QUICK_VIEW_WINDOW_HEIGHT: i32 = 650;
+const TEXT_MARGIN: i32 = 4;
+
+// Context menu
+const ID_QUICK_VIEW_TEXT: i32 = 9881;
+const ID_QUICK_VIEW_HEX: i32 = 9882;
+const ID_QUICK_VIEW_UTF8: i32 = 9883;
+const ID_QUICK_VIEW_UTF16LE: i32 = 9884;
+const ID_QUICK_VIEW_UTF16BE: i32 = 9885;
+const ID_QUICK_VIEW_ANSI: i32 = 9886;
+
+// Bytes read at a time while looking for line ends
+const CHUNK_SIZE: usize = 64 * 1024;
+// Longer lines are broken, so a file without line ends still scrolls
+const MAX_LINE_BYTES: u64 = 8192;
+// Bytes looked at to pick the encoding
+const SAMPLE_SIZE: usize = 64 * 1024;
+const HEX_ROW_BYTES: u64 = 16;
+const TAB_WIDTH: usize = 4;
+// Scroll bar positions; files can be larger than an i32 scroll range
+const SCROLL_RANGE: i32 = 10000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Ansi,
+}
+
+impl TextEncoding {
+    fn label(self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => "UTF-8",
+            TextEncoding::Utf16Le => "UTF-16 LE",
+            TextEncoding::Utf16Be => "UTF-16 BE",
+            TextEncoding::Ansi => "ANSI",
+        }
+    }
+
+    // Bytes per code unit, which is also the size of a line end
+    fn unit(self) -> u64 {
+        match self {
+            TextEncoding::Utf16Le | TextEncoding::Utf16Be => 2,
+            _ => 1,
+        }
+    }
+
+    fn byte_order_mark(self) -> &'static [u8] {
+        match self {
+            TextEncoding::Utf8 => &[0xEF, 0xBB, 0xBF],
+            TextEncoding::Utf16Le => &[0xFF, 0xFE],
+            TextEncoding::Utf16Be => &[0xFE, 0xFF],
+            TextEncoding::Ansi => &[],
+        }
+    }
+
+    fn is_line_end(self, bytes: &[u8], index: usize) -> bool {
+        match self {
+            TextEncoding::Utf16Le => bytes[index] == b'\n' && bytes[index + 1] == 0,
+            TextEncoding::Utf16Be => bytes[index] == 0 && bytes[index + 1] == b'\n',
+            _ => bytes[index] == b'\n',
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> Vec<u16> {
+        match self {
+            TextEncoding::Utf8 => String::from_utf8_lossy(bytes).encode_utf16().collect(),
+            TextEncoding::Utf16Le => bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect(),
+            TextEncoding::Utf16Be => bytes.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect(),
+            TextEncoding::Ansi => unsafe {
+                if bytes.is_empty() {
+                    return Vec::new();
+                }
+                let length = MultiByteToWideChar(CP_ACP, MULTI_BYTE_TO_WIDE_CHAR_FLAGS(0), bytes, None);
+                let mut wide = vec![0u16; length.max(0) as usize];
+                MultiByteToWideChar(CP_ACP, MULTI_BYTE_TO_WIDE_CHAR_FLAGS(0), bytes, Some(&mut wide));
+                wide
+            },
+        }
+    }
+}
+
+// Picks the encoding from the start of the file: a byte order mark, else
+// UTF-16 when NULs sit on one side of the code units, else UTF-8 when the
+// sample is valid UTF-8, else the ANSI code page. The flag is set for files
+// that look binary, which open in hex mode.
+fn detect_encoding(sample: &[u8]) -> (TextEncoding, bool) {
+    for encoding in [TextEncoding::Utf8, TextEncoding::Utf16Le, TextEncoding::Utf16Be] {
+        if sample.starts_with(encoding.byte_order_mark()) {
+            return (encoding, false);
+        }
+    }
+
+    let even_nuls = sample.iter().step_by(2).filter(|&&byte| byte == 0).count();
+    let odd_nuls = sample.iter().skip(1).step_by(2).filter(|&&byte| byte == 0).count();
+    let pairs = (sample.len() / 2).max(1);
+    if even_nuls + odd_nuls > 0 {
+        if odd_nuls * 2 > pairs && even_nuls * 20 < pairs {
+            return (TextEncoding::Utf16Le, false);
+        }
+        if even_nuls * 2 > pairs && odd_nuls * 20 < pairs {
+            return (TextEncoding::Utf16Be, false);
+        }
+        return (TextEncoding::Utf8, true);
+    }
+
+    match std::str::from_utf8(sample) {
+        Ok(_) => (TextEncoding::Utf8, false),
+        // A character cut off by the end of the sample is still UTF-8
+        Err(e) if e.error_len().is_none() => (TextEncoding::Utf8, false),
+        Err(_) => (TextEncoding::Ansi, false),
+    }
+}
+
+struct QuickView {
+    path: String,
+    file: Option<File>,
+    // Why the file couldn't be opened
+    error: Option<String>,
+    size: u64,
+    encoding: TextEncoding,
+    hex: bool,
+    // First byte shown: the start of a line in text mode, of a row in hex mode
+    top: u64,
+    // Characters scrolled off the left edge
+    left: i32,
+    font: HFONT,
+    char_width: i32,
+    line_height: i32,
+}
+
+impl QuickView {
+    // Where the text starts, after a byte order mark of the chosen encoding
+    fn data_start(&mut self) -> u64 {
+        let mark = self.encoding.byte_order_mark();
+        if !mark.is_empty() && self.read_at(0, mark.len()) == mark {
+            mark.len() as u64
+        } else {
+            0
+        }
+    }
+
+    fn read_at(&mut self, offset: u64, length: usize) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        if let Some(file) = self.file.as_mut() {
+            if file.seek(SeekFrom::Start(offset)).is_ok() {
+                let _ = file.by_ref().take(length as u64).read_to_end(&mut buffer);
+            }
+        }
+        buffer
+    }
+
+    // Up to `count` lines from `offset`, each with the offset it starts at
+    fn read_lines(&mut self, offset: u64, count: usize) -> Vec<(u64, Vec<u16>)> {
+        let unit = self.encoding.unit() as usize;
+        let mut lines = Vec::new();
+        let mut buffer = Vec::new();
+        let mut buffer_start = offset;
+        let mut position = 0usize;
+
+        while lines.len() < count {
+            let at_end = buffer_start + buffer.len() as u64 >= self.size;
+            let limit = (position + MAX_LINE_BYTES as usize).min(buffer.len());
+            let line_end = (position..limit.saturating_sub(unit - 1))
+                .step_by(unit)
+                .find(|&index| self.encoding.is_line_end(&buffer, index));
+
+            let (end, next) = match line_end {
+                Some(index) => (index, index + unit),
+                None if position + (MAX_LINE_BYTES as usize) < buffer.len() => {
+                    let mut end = position + MAX_LINE_BYTES as usize;
+                    // Don't break a UTF-8 character in two
+                    if self.encoding == TextEncoding::Utf8 {
+                        while end > position + 1 && buffer[end] & 0xC0 == 0x80 {
+                            end -= 1;
+                        }
+                    }
+                    (end, end)
+                }
+                None if at_end => {
+                    if position < buffer.len() {
+                        lines.push((buffer_start + position as u64, self.clean_line(&buffer[position..])));
+                    }
+                    break;
+                }
+                None => {
+                    // Drop what has been split already and read on
+                    buffer.drain(..position);
+                    buffer_start += position as u64;
+                    position = 0;
+                    let more = self.read_at(buffer_start + buffer.len() as u64, CHUNK_SIZE);
+                    if more.is_empty() {
+                        self.size = buffer_start + buffer.len() as u64;
+                    }
+                    buffer.extend_from_slice(&more);
+                    continue;
+                }
+            };
+
+            lines.push((buffer_start + position as u64, self.clean_line(&buffer[position..end])));
+            position = next;
+        }
+        lines
+    }
+
+    // The line as drawn: without its carriage return, tabs expanded and
+    // other control characters shown as dots
+    fn clean_line(&self, bytes: &[u8]) -> Vec<u16> {
+        let mut text = self.encoding.decode(bytes);
+        if text.last() == Some(&(b'\r' as u16)) {
+            text.pop();
+        }
+        let mut line = Vec::with_capacity(text.len());
+        for unit in text {
+            match unit {
+                0x09 => {
+                    let spaces = TAB_WIDTH - line.len() % TAB_WIDTH;
+                    line.extend(std::iter::repeat_n(b' ' as u16, spaces));
+                }
+                0x00..=0x1F => line.push(0x00B7),
+                _ => line.push(unit),
+            }
+        }
+        line
+    }
+
+    // Start of the line that holds `offset`
+    fn line_start_at(&mut self, offset: u64) -> u64 {
+        let data_start = self.data_start();
+        let unit = self.encoding.unit();
+        let offset = offset.min(self.size);
+        if offset <= data_start {
+            return data_start;
+        }
+        let offset = offset - (offset - data_start) % unit;
+        let from = offset.saturating_sub(MAX_LINE_BYTES).max(data_start);
+        let from = from + (from - data_start) % unit;
+        let buffer = self.read_at(from, (offset - from) as usize);
+
+        let mut index = buffer.len() as isize - unit as isize;
+        while index >= 0 {
+            if self.encoding.is_line_end(&buffer, index as usize) {
+                return from + index as u64 + unit;
+            }
+            index -= unit as isize;
+        }
+        from
+    }
+
+    fn visible_rows(&self, window: HWND) -> usize {
+        let mut rect = RECT::default();
+        unsafe {
+            let _ = GetClientRect(window, &mut rect);
+        }
+        ((rect.bottom - TEXT_MARGIN * 2) / self.line_height.max(1)).max(1) as usize
+    }
+
+    // Offset of the top line that shows the end of the file at the bottom
+    fn last_page_top(&mut self, rows: usize) -> u64 {
+        if self.hex {
+            let total_rows = self.size.div_ceil(HEX_ROW_BYTES);
+            return total_rows.saturating_sub(rows as u64) * HEX_ROW_BYTES;
+        }
+        let mut top = self.line_start_at(self.size);
+        // A file ending in a line end has no line after it
+        if top == self.size && top > self.data_start() {
+            top = self.line_start_at(top - self.encoding.unit());
+        }
+        for _ in 1..rows {
+            if top <= self.data_start() {
+                break;
+            }
+            top = self.line_start_at(top - self.encoding.unit());
+        }
+        top
+    }
+
+    fn scroll_lines(&mut self, window: HWND, delta: i64) {
+        let rows = self.visible_rows(window);
+        if self.hex {
+            let top = self.top as i64 + delta * HEX_ROW_BYTES as i64;
+            self.top = (top.max(0) as u64).min(self.last_page_top(rows));
+            return;
+        }
+
+        if delta > 0 {
+            let lines = self.read_lines(self.top, delta as usize + 1);
+            if let Some(&(start, _)) = lines.get(delta as usize).or(lines.last()) {
+                self.top = start.min(self.last_page_top(rows));
+            }
+        } else {
+            for _ in 0..-delta {
+                if self.top <= self.data_start() {
+                    break;
+                }
+                self.top = self.line_start_at(self.top - self.encoding.unit());
+            }
+        }
+    }
+
+    // Moves to a fraction of the file, as the scroll bar thumb asks
+    fn scroll_to_fraction(&mut self, window: HWND, position: i32) {
+        let rows = self.visible_rows(window);
+        let offset = (self.size as f64 * position as f64 / SCROLL_RANGE as f64) as u64;
+        self.top = if self.hex {
+            (offset - offset % HEX_ROW_BYTES).min(self.last_page_top(rows))
+        } else {
+            self.line_start_at(offset).min(self.last_page_top(rows))
+        };
+    }
+
+    fn set_mode(&mut self, hex: bool, encoding: TextEncoding) {
+        self.hex = hex;
+        self.encoding = encoding;
+        self.top = if hex {
+            self.top - self.top % HEX_ROW_BYTES
+        } else {
+            let top = self.top;
+            self.line_start_at(top)
+        };
+    }
+}
+
+// The window's QuickView, kept in GWLP_USERDATA
+unsafe fn quick_view(window: HWND) -> Option<&'static mut QuickView> {
+    let view = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut QuickView;
+    view.as_mut()
+}
+
+pub fn register_quick_view_window_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(quick_view_window_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_IBEAM)?,
+            hbrBackground: HBRUSH(0),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeQuickView"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// F3: shows `path` as text, or as hex when it looks binary, in the quick
+// view window. Only the lines on screen are read, so large files open at
+// once. Pressing F3 again on another result reuses the window.
+pub fn show_quick_view(owner: HWND, path: &str) {
+    unsafe {
+        let class_wide = to_wide(QUICK_VIEW_WINDOW_CLASS);
+        let mut window = FindWindowW(PCWSTR::from_raw(class_wide.as_ptr()), PCWSTR::null());
+
+        if window.0 == 0 {
+            let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+            window = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                PCWSTR::from_raw(class_wide.as_ptr()),
+                PCWSTR::null(),
+                WS_OVERLAPPEDWINDOW | WS_VSCROLL | WS_VISIBLE,
+                CW_USEDEFAULT,
+                CW_USEDEFAULT,
+                QUICK_VIEW_WINDOW_WIDTH,
+                QUICK_VIEW_WINDOW_HEIGHT,
+                owner,
+                None,
+                instance,
+                None,
+            );
+
+            if window.0 == 0 {
+                log_error("Failed to create quick view window");
+                return;
+            }
+
+            // Monospace font keeps the hex columns aligned
+            let font = CreateFontW(
+                16, 0, 0, 0,
+                400,  // FW_NORMAL
+                0, 0, 0,
+                1,    // DEFAULT_CHARSET
+                0,    // OUT_DEFAULT_PRECIS
+                0,    // CLIP_DEFAULT_PRECIS
+                0,    // DEFAULT_QUALITY
+                49,   // FIXED_PITCH | FF_MODERN
+                w!("Consolas"),
+            );
+            let hdc = GetDC(window);
+            let old_font = SelectObject(hdc, font);
+            let mut metrics = TEXTMETRICW::default();
+            GetTextMetricsW(hdc, &mut metrics);
+            SelectObject(hdc, old_font);
+            ReleaseDC(window, hdc);
+
+            let view = Box::new(QuickView {
+                path: String::new(),
+                file: None,
+                error: None,
+                size: 0,
+                encoding: TextEncoding::Utf8,
+                hex: false,
+                top: 0,
+                left: 0,
+                font,
+                char_width: metrics.tmAveCharWidth.max(1),
+                line_height: metrics.tmHeight.max(1),
+            });
+            SetWindowLongPtrW(window, GWLP_USERDATA, Box::into_raw(view) as isize);
+        } else if IsIconic(window).as_bool() {
+            let _ = ShowWindow(window, SW_RESTORE);
+        }
+
+        if let Some(view) = quick_view(window) {
+            view.path = path.to_string();
+            view.top = 0;
+            view.left = 0;
+            match File::open(path) {
+                Ok(file) => {
+                    view.size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+                    view.file = Some(file);
+                    view.error = None;
+                    let sample = view.read_at(0, SAMPLE_SIZE);
+                    let (encoding, binary) = detect_encoding(&sample);
+                    view.set_mode(binary, encoding);
+                }
+                Err(e) => {
+                    log_warn(&format!("Quick view can't open {}: {}", path, e));
+                    view.size = 0;
+                    view.file = None;
+                    view.error = Some(e.to_string());
+                }
+            }
+        }
+        update_view(window);
+        let _ = SetForegroundWindow(window);
+    }
+}
+
+unsafe fn update_title(window: HWND, view: &QuickView) {
+    let name = std::path::Path::new(&view.path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(&view.path);
+    let mode = if view.hex { get_strings().quick_view_hex.clone() } else { view.encoding.label().to_string() };
+    let title = fill(&get_strings().quick_view_title, &[("name", name), ("mode", &mode)]);
+    let _ = SetWindowTextW(window, PCWSTR::from_raw(to_wide(&title).as_ptr()));
+}
+
+// Refreshes the title, scroll bar and contents after a scroll or mode change
+unsafe fn update_view(window: HWND) {
+    let Some(view) = quick_view(window) else { return };
+    update_title(window, view);
+
+    let rows = view.visible_rows(window) as u64;
+    let shown = if view.hex { rows * HEX_ROW_BYTES } else { rows * 80 * view.encoding.unit() };
+    let size = view.size.max(1) as f64;
+    let scroll_info = SCROLLINFO {
+        cbSize: std::mem::size_of::<SCROLLINFO>() as u32,
+        fMask: SIF_RANGE | SIF_PAGE | SIF_POS | SIF_DISABLENOSCROLL,
+        nMin: 0,
+        nMax: SCROLL_RANGE,
+        nPage: ((shown as f64 / size * SCROLL_RANGE as f64) as u32).clamp(1, SCROLL_RANGE as u32 + 1),
+        nPos: (view.top as f64 / size * SCROLL_RANGE as f64) as i32,
+        nTrackPos: 0,
+    };
+    SetScrollInfo(window, SB_VERT, &scroll_info, TRUE);
+    InvalidateRect(window, None, FALSE);
+}
+
+// One hex row: offset, 16 bytes in hex and the same bytes as characters
+fn hex_row(offset: u64, bytes: &[u8]) -> Vec<u16> {
+    let mut row = format!("{:08X}  ", offset);
+    for column in 0..HEX_ROW_BYTES as usize {
+        match bytes.get(column) {
+            Some(byte) => row.push_str(&format!("{:02X} ", byte)),
+            None => row.push_str("   "),
+        }
+        if column == 7 {
+            row.push(' ');
+        }
+    }
+    row.push(' ');
+    row.extend(bytes.iter().map(|&byte| if (0x20..0x7F).contains(&byte) { byte as char } else { '.' }));
+    row.encode_utf16().collect()
+}
+
+// Drawn into a memory bitmap first so scrolling doesn't flicker
+unsafe fn paint(window: HWND) {
+    let mut paint_struct = PAINTSTRUCT::default();
+    let hdc = BeginPaint(window, &mut paint_struct);
+    let mut client_rect = RECT::default();
+    let _ = GetClientRect(window, &mut client_rect);
+    let (width, height) = (client_rect.right.max(1), client_rect.bottom.max(1));
+
+    let memory_dc = CreateCompatibleDC(hdc);
+    let bitmap = CreateCompatibleBitmap(hdc, width, height);
+    let old_bitmap = SelectObject(memory_dc, bitmap);
+    FillRect(memory_dc, &client_rect, GetSysColorBrush(COLOR_WINDOW));
+
+    if let Some(view) = quick_view(window) {
+        let old_font = SelectObject(memory_dc, view.font);
+        SetBkMode(memory_dc, TRANSPARENT);
+        SetTextColor(memory_dc, COLORREF(GetSysColor(COLOR_WINDOWTEXT)));
+
+        let rows = view.visible_rows(window) + 1;
+        let lines: Vec<Vec<u16>> = if let Some(error) = &view.error {
+            let message = fill(&get_strings().quick_view_error, &[("error", error)]);
+            vec![message.encode_utf16().collect()]
+        } else if view.hex {
+            let bytes = view.read_at(view.top, rows * HEX_ROW_BYTES as usize);
+            bytes
+                .chunks(HEX_ROW_BYTES as usize)
+                .enumerate()
+                .map(|(row, chunk)| hex_row(view.top + row as u64 * HEX_ROW_BYTES, chunk))
+                .collect()
+        } else {
+            view.read_lines(view.top, rows).into_iter().map(|(_, line)| line).collect()
+        };
+
+        let x = TEXT_MARGIN - view.left * view.char_width;
+        for (row, line) in lines.iter().enumerate() {
+            let y = TEXT_MARGIN + row as i32 * view.line_height;
+            let _ = TextOutW(memory_dc, x, y, line);
+        }
+        SelectObject(memory_dc, old_font);
+    }
+
+    let _ = BitBlt(hdc, 0, 0, width, height, memory_dc, 0, 0, SRCCOPY);
+
+    SelectObject(memory_dc, old_bitmap);
+    DeleteObject(bitmap);
+    DeleteDC(memory_dc);
+    EndPaint(window, &paint_struct);
+}
+
+unsafe fn show_context_menu(window: HWND, x: i32, y: i32) {
+    let Some(view) = quick_view(window) else { return };
+    let strings = get_strings();
+    let menu = CreatePopupMenu().unwrap();
+
+    let items = [
+        (ID_QUICK_VIEW_TEXT, strings.quick_view_text.clone(), !view.hex),
+        (ID_QUICK_VIEW_HEX, strings.quick_view_hex.clone(), view.hex),
+        (0, String::new(), false),
+        (ID_QUICK_VIEW_UTF8, TextEncoding::Utf8.label().to_string(), view.encoding == TextEncoding::Utf8),
+        (ID_QUICK_VIEW_UTF16LE, TextEncoding::Utf16Le.label().to_string(), view.encoding == TextEncoding::Utf16Le),
+        (ID_QUICK_VIEW_UTF16BE, TextEncoding::Utf16Be.label().to_string(), view.encoding == TextEncoding::Utf16Be),
+        (ID_QUICK_VIEW_ANSI, TextEncoding::Ansi.label().to_string(), view.encoding == TextEncoding::Ansi),
+    ];
+    for (id, label, checked) in items {
+        if id == 0 {
+            let _ = AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
+            continue;
+        }
+        let flags = if checked { MF_STRING | MF_CHECKED } else { MF_STRING };
+        let _ = AppendMenuW(menu, flags, id as usize, PCWSTR::from_raw(to_wide(&label).as_ptr()));
+    }
+
+    assign_menu_mnemonics(menu);
+    let _ = TrackPopupMenu(menu, TPM_RIGHTBUTTON, x, y, 0, window, None);
+    let _ = DestroyMenu(menu);
+}
+
+extern "system" fn quick_view_window_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_PAINT => {
+                paint(window);
+                LRESULT(0)
+            }
+            WM_ERASEBKGND => LRESULT(1),
+            WM_SIZE => {
+                update_view(window);
+                LRESULT(0)
+            }
+            WM_KEYDOWN => {
+                let Some(view) = quick_view(window) else { return LRESULT(0) };
+                let rows = view.visible_rows(window) as i64;
+                match VIRTUAL_KEY(wparam.0 as u16) {
+                    VK_UP => view.scroll_lines(window, -1),
+                    VK_DOWN => view.scroll_lines(window, 1),
+                    VK_PRIOR => view.scroll_lines(window, -(rows - 1).max(1)),
+                    VK_NEXT | VK_SPACE => view.scroll_lines(window, (rows - 1).max(1)),
+                    VK_HOME => view.top = if view.hex { 0 } else { view.data_start() },
+                    VK_END => view.top = view.last_page_top(rows as usize),
+                    VK_LEFT => view.left = (view.left - 4).max(0),
+                    VK_RIGHT => view.left += 4,
+                    VK_H => {
+                        let encoding = view.encoding;
+                        view.set_mode(!view.hex, encoding);
+                    }
+                    VK_ESCAPE | VK_F3 => {
+                        let _ = DestroyWindow(window);
+                        return LRESULT(0);
+                    }
+                    _ => return DefWindowProcW(window, message, wparam, lparam),
+                }
+                update_view(window);
+                LRESULT(0)
+            }
+            WM_MOUSEWHEEL => {
+                if let Some(view) = quick_view(window) {
+                    let delta = ((wparam.0 >> 16) & 0xFFFF) as i16 as i64;
+                    view.scroll_lines(window, -delta / 120 * 3);
+                    update_view(window);
+                }
+                LRESULT(0)
+            }
+            WM_VSCROLL => {
+                if let Some(view) = quick_view(window) {
+                    let rows = view.visible_rows(window) as i64;
+                    match SCROLLBAR_COMMAND((wparam.0 & 0xFFFF) as i32) {
+                        SB_LINEUP => view.scroll_lines(window, -1),
+                        SB_LINEDOWN => view.scroll_lines(window, 1),
+                        SB_PAGEUP => view.scroll_lines(window, -(rows - 1).max(1)),
+                        SB_PAGEDOWN => view.scroll_lines(window, (rows - 1).max(1)),
+                        SB_THUMBTRACK | SB_THUMBPOSITION => {
+                            let mut scroll_info = SCROLLINFO {
+                                cbSize: std::mem::size_of::<SCROLLINFO>() as u32,
+                                fMask: SIF_TRACKPOS,
+                                ..Default::default()
+                            };
+                            let _ = GetScrollInfo(window, SB_VERT, &mut scroll_info);
+                            view.scroll_to_fraction(window, scroll_info.nTrackPos);
+                        }
+                        _ => {}
+                    }
+                    update_view(window);
+                }
+                LRESULT(0)
+            }
+            WM_CONTEXTMENU => {
+                let (mut x, mut y) = ((lparam.0 & 0xFFFF) as i16 as i32, ((lparam.0 >> 16) & 0xFFFF) as i16 as i32);
+                // From the keyboard: open at the top left corner
+                if x == -1 && y == -1 {
+                    let mut point = POINT { x: TEXT_MARGIN, y: TEXT_MARGIN };
+                    let _ = ClientToScreen(window, &mut point);
+                    (x, y) = (point.x, point.y);
+                }
+                show_context_menu(window, x, y);
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                if let Some(view) = quick_view(window) {
+                    let (hex, encoding) = match (wparam.0 & 0xFFFF) as i32 {
+                        ID_QUICK_VIEW_TEXT => (false, view.encoding),
+                        ID_QUICK_VIEW_HEX => (true, view.encoding),
+                        ID_QUICK_VIEW_UTF8 => (false, TextEncoding::Utf8),
+                        ID_QUICK_VIEW_UTF16LE => (false, TextEncoding::Utf16Le),
+                        ID_QUICK_VIEW_UTF16BE => (false, TextEncoding::Utf16Be),
+                        ID_QUICK_VIEW_ANSI => (false, TextEncoding::Ansi),
+                        _ => return DefWindowProcW(window, message, wparam, lparam),
+                    };
+                    view.set_mode(hex, encoding);
+                    update_view(window);
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(window);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                let view = SetWindowLongPtrW(window, GWLP_USERDATA, 0) as *mut QuickView;
+                if !view.is_null() {
+                    let view = Box::from_raw(view);
+                    DeleteObject(view.font);
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::path::Path;
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::*,
+        UI::{
+            Shell::{SHFileOperationW, SHFILEOPSTRUCTW, FO_DELETE, FOF_ALLOWUNDO, FOF_NOCONFIRMATION},
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::elevation::{confirm_elevated_retry, is_access_denied, recycle_elevated, show_error};
+use crate::lang::get_strings;
+use crate::logger::log_info;
+use crate::long_paths::shell_path;
+
+// Sent to the main window with a boxed Vec<String> in WPARAM: files or
+// folders a tool moved to the Recycle Bin, so they can leave the results
+pub const WM_FILES_RECYCLED: u32 = WM_USER + 123;
+
+// SHFileOperation's code for a source it wasn't allowed to touch
+const DE_ACCESSDENIEDSRC: i32 = 0x78;
+
+// How a SHFileOperation call ended
+pub struct RecycleResult {
+    code: i32,
+    aborted: bool,
+}
+
+impl RecycleResult {
+    // Something went wrong other than the user cancelling
+    pub fn failed(&self) -> bool {
+        self.code != 0 && !self.aborted
+    }
+
+    // What went wrong was a lack of rights
+    pub fn access_denied(&self) -> bool {
+        self.failed() && (self.code == DE_ACCESSDENIEDSRC || is_access_denied(self.code as u32))
+    }
+}
+
+// Moves `paths` to the Recycle Bin and returns the ones that are gone.
+// The caller has already asked for confirmation. When rights were lacking
+// the user is offered to try again as administrator, and what that moves
+// comes to `window` later with WM_ELEVATED_RECYCLE_DONE; otherwise what
+// couldn't be moved is reported in a message box unless the user cancelled.
+pub fn move_to_recycle_bin(window: HWND, paths: Vec<String>) -> Vec<String> {
+    let mut result = recycle_paths(window, &paths);
+
+    // Some paths may have gone before an error or a cancel
+    let (deleted, mut failed): (Vec<String>, Vec<String>) = paths.into_iter().partition(|path| !Path::new(path).exists());
+    if !failed.is_empty() && result.access_denied() && confirm_elevated_retry(window, &failed) {
+        // The helper reports its own errors
+        recycle_elevated(window, std::mem::take(&mut failed));
+        result.aborted = true;
+    }
+
+    log_info(&format!("Moved {} items to the Recycle Bin, {} failed", deleted.len(), failed.len()));
+    if !failed.is_empty() && result.failed() {
+        show_error(window, &get_strings().error_delete_files, &failed);
+    }
+    deleted
+}
+
+// One SHFileOperation moving `paths` to the Recycle Bin, without any
+// reporting
+pub fn recycle_paths(window: HWND, paths: &[String]) -> RecycleResult {
+    // SHFileOperation takes the paths as one double-null-terminated buffer
+    let mut from: Vec<u16> = Vec::new();
+    for path in paths {
+        from.extend(shell_path(path).encode_utf16());
+        from.push(0);
+    }
+    from.push(0);
+    let mut operation = SHFILEOPSTRUCTW {
+        hwnd: window,
+        wFunc: FO_DELETE,
+        pFrom: PCWSTR::from_raw(from.as_ptr()),
+        fFlags: (FOF_ALLOWUNDO.0 | FOF_NOCONFIRMATION.0) as u16,
+        ..Default::default()
+    };
+    let code = unsafe { SHFileOperationW(&mut operation) };
+    RecycleResult { code, aborted: operation.fAnyOperationsAborted.as_bool() }
+}
+
+// Tells the main window which paths are gone
+pub fn notify_recycled(main_window: HWND, deleted: Vec<String>) {
+    if deleted.is_empty() {
+        return;
+    }
+    let deleted_ptr = Box::into_raw(Box::new(deleted)) as usize;
+    unsafe {
+        SendMessageW(main_window, WM_FILES_RECYCLED, WPARAM(deleted_ptr), LPARAM(0));
+    }
+}
@@ -0,0 +1,174 @@
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::config::get_config_dir;
+use crate::logger::{log_info, log_warn};
+use crate::scheduled_export::unix_now;
+
+// How quickly a run stops counting as recent: half as much after this long
+const RECENCY_HALF_LIFE_SECS: f64 = 14.0 * 24.0 * 3600.0;
+
+// Weights of the three parts of a ranking score. A good name match
+// outranks a few runs, but a file run every day beats a slightly better
+// match nobody opens.
+const MATCH_WEIGHT: f64 = 2.0;
+const FREQUENCY_WEIGHT: f64 = 1.0;
+const RECENCY_WEIGHT: f64 = 1.5;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RunStats {
+    pub count: u32,
+    // Unix time of the last run
+    pub last_run: u64,
+}
+
+// How often and how recently files were opened from the results, kept in
+// run_history.db next to config.json
+pub struct RunHistory {
+    connection: Connection,
+    // Keyed by lowercased path. Shared with running sorts, which get a
+    // snapshot that later runs don't change.
+    runs: Arc<HashMap<String, RunStats>>,
+}
+
+impl RunHistory {
+    // Opens the database, creating it on first use. Without one, runs
+    // still count for this session but aren't saved.
+    pub fn open() -> Self {
+        let connection = get_config_dir()
+            .map_err(|e| e.to_string())
+            .and_then(|dir| Connection::open(dir.join("run_history.db")).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                log_warn(&format!("Failed to open the run history, runs won't be saved: {}", e));
+                Connection::open_in_memory().expect("in-memory database")
+            });
+
+        let mut history = RunHistory { connection, runs: Arc::new(HashMap::new()) };
+        if let Err(e) = history.load() {
+            log_warn(&format!("Failed to read the run history: {}", e));
+        }
+        log_info(&format!("Loaded the run history of {} files", history.runs.len()));
+        history
+    }
+
+    fn load(&mut self) -> rusqlite::Result<()> {
+        self.connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS runs (
+                 path TEXT PRIMARY KEY COLLATE NOCASE,
+                 count INTEGER NOT NULL,
+                 last_run INTEGER NOT NULL
+             );",
+        )?;
+
+        let mut statement = self.connection.prepare("SELECT path, count, last_run FROM runs")?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, RunStats { count: row.get(1)?, last_run: row.get(2)? }))
+        })?;
+        let mut runs = HashMap::new();
+        for row in rows {
+            let (path, stats) = row?;
+            runs.insert(path.to_lowercase(), stats);
+        }
+        self.runs = Arc::new(runs);
+        Ok(())
+    }
+
+    // Counts one more run of `path`, now
+    pub fn record(&mut self, path: &str) {
+        let now = unix_now();
+        let stats = Arc::make_mut(&mut self.runs)
+            .entry(path.to_lowercase())
+            .or_insert(RunStats { count: 0, last_run: 0 });
+        stats.count += 1;
+        stats.last_run = now;
+
+        let saved = self.connection.execute(
+            "INSERT INTO runs (path, count, last_run) VALUES (?1, 1, ?2)
+             ON CONFLICT(path) DO UPDATE SET count = count + 1, last_run = ?2",
+            params![path, now],
+        );
+        if let Err(e) = saved {
+            log_warn(&format!("Failed to save the run of {}: {}", path, e));
+        }
+    }
+
+    // Scores results for `query` against the runs so far
+    pub fn ranker(&self, query: &str) -> Ranker {
+        Ranker {
+            runs: self.runs.clone(),
+            terms: name_terms(query),
+            now: unix_now(),
+        }
+    }
+}
+
+// The words of a query that are matched against names. Functions such as
+// ext: or size:, exclusions and wildcards are left to Everything.
+fn name_terms(query: &str) -> Vec<String> {
+    query
+        .split_whitespace()
+        .filter(|term| !term.contains(':') && !term.starts_with(['-', '!']))
+        .map(|term| term.trim_matches('"').replace(['*', '?'], "").to_lowercase())
+        .filter(|term| !term.is_empty())
+        .collect()
+}
+
+// Orders results launcher-style: by how well the name matches the query,
+// how often the file was run and how recently
+pub struct Ranker {
+    runs: Arc<HashMap<String, RunStats>>,
+    terms: Vec<String>,
+    now: u64,
+}
+
+impl std::fmt::Debug for Ranker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Ranker").field("terms", &self.terms).field("runs", &self.runs.len()).finish()
+    }
+}
+
+impl Ranker {
+    // Higher is better
+    pub fn score(&self, path: &str, name: &str) -> f64 {
+        let (frequency, recency) = match self.runs.get(&path.to_lowercase()) {
+            Some(stats) => {
+                let age = self.now.saturating_sub(stats.last_run) as f64;
+                ((stats.count as f64).ln_1p(), 0.5f64.powf(age / RECENCY_HALF_LIFE_SECS))
+            }
+            None => (0.0, 0.0),
+        };
+        MATCH_WEIGHT * self.match_quality(name) + FREQUENCY_WEIGHT * frequency + RECENCY_WEIGHT * recency
+    }
+
+    // 1 for a name that is exactly the query, down to 0.25 for one that
+    // only contains it somewhere, averaged over the words of the query
+    fn match_quality(&self, name: &str) -> f64 {
+        if self.terms.is_empty() {
+            return 0.0;
+        }
+        let name = name.to_lowercase();
+        let stem = name.rsplit_once('.').map_or(name.as_str(), |(stem, _)| stem);
+        let total: f64 = self
+            .terms
+            .iter()
+            .map(|term| {
+                if stem == term || name == *term {
+                    1.0
+                } else if name.starts_with(term.as_str()) {
+                    0.75
+                } else if name
+                    .match_indices(term.as_str())
+                    .any(|(index, _)| !name[..index].ends_with(char::is_alphanumeric))
+                {
+                    // At the start of a word, as in "my-report" for "rep"
+                    0.5
+                } else if name.contains(term.as_str()) {
+                    0.25
+                } else {
+                    0.0
+                }
+            })
+            .sum();
+        total / self.terms.len() as f64
+    }
+}
@@ -0,0 +1,106 @@
+use rayon::prelude::*;
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, WPARAM},
+    UI::WindowsAndMessaging::PostMessageW,
+};
+use crate::config::ScheduledExport;
+use crate::everything_sdk::{EverythingSDK, FileResult};
+use crate::export::{export_results, ExportFormat};
+use crate::lang::{fill, LanguageStrings};
+use crate::logger::{log_info, log_warn};
+
+// How often the main window looks for exports that are due
+pub const SCHEDULE_CHECK_INTERVAL_MS: u32 = 60 * 1000;
+
+// Posted back to the window once a run has finished
+pub struct ScheduledExportDone {
+    // Identifies the export; entries can be removed or reordered meanwhile
+    pub path: String,
+    pub finished: u64,
+    pub result: Result<usize, String>,
+}
+
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+// Startup runs every export set to run at startup, and the interval ones
+// that came due while the app was closed
+pub fn is_due(export: &ScheduledExport, now: u64, startup: bool) -> bool {
+    if export.interval_hours == 0 {
+        return startup;
+    }
+    now.saturating_sub(export.last_run) >= export.interval_hours as u64 * 3600
+}
+
+// Runs `query` through Everything on a worker thread, writes every result to
+// `export.path` and posts a boxed ScheduledExportDone as `message`'s WPARAM
+pub fn run_scheduled_export(window: HWND, message: u32, export: &ScheduledExport, query: String) {
+    let path = export.path.clone();
+    let format = match ExportFormat::from_path(&path) {
+        Some(ExportFormat::Efu) => ExportFormat::Efu,
+        _ => ExportFormat::Csv,
+    };
+    log_info(&format!("Running scheduled export {} to {}", export.name, path));
+
+    std::thread::spawn(move || {
+        let result = EverythingSDK::new()
+            .and_then(|sdk| {
+                let mut files = Vec::new();
+                sdk.search_files_in_batches(&query, usize::MAX, |batch, _| {
+                    files.extend(batch);
+                    true
+                })?;
+                Ok(files)
+            })
+            .map_err(|e| e.to_string())
+            .and_then(|mut files: Vec<FileResult>| {
+                files.par_iter_mut().for_each(|file| file.load_metadata());
+                export_results(&files, format, &path, None).map_err(|e| e.to_string())?;
+                Ok(files.len())
+            });
+
+        match &result {
+            Ok(count) => log_info(&format!("Scheduled export wrote {} files to {}", count, path)),
+            Err(e) => log_warn(&format!("Scheduled export to {} failed: {}", path, e)),
+        }
+
+        let done = Box::into_raw(Box::new(ScheduledExportDone { path, finished: unix_now(), result })) as usize;
+        unsafe {
+            if PostMessageW(window, message, WPARAM(done), LPARAM(0)).is_err() {
+                drop(Box::from_raw(done as *mut ScheduledExportDone));
+            }
+        }
+    });
+}
+
+// The interval as shown in the menu
+pub fn interval_label(hours: u32, strings: &LanguageStrings) -> String {
+    match hours {
+        0 => strings.scheduled_export_at_startup.clone(),
+        1 => strings.scheduled_export_hourly.clone(),
+        24 => strings.scheduled_export_daily.clone(),
+        168 => strings.scheduled_export_weekly.clone(),
+        hours => fill(&strings.scheduled_export_every_hours, &[("hours", &hours.to_string())]),
+    }
+}
+
+// The grayed status line of an export's submenu
+pub fn last_run_status(export: &ScheduledExport, running: bool, strings: &LanguageStrings) -> String {
+    if running {
+        return strings.scheduled_export_running.clone();
+    }
+    if export.last_run == 0 {
+        return strings.scheduled_export_never_run.clone();
+    }
+
+    let finished = std::time::UNIX_EPOCH + std::time::Duration::from_secs(export.last_run);
+    let time = chrono::DateTime::<chrono::Local>::from(finished).format("%Y-%m-%d %H:%M").to_string();
+    if export.last_error.is_empty() {
+        fill(&strings.scheduled_export_last_run, &[("time", &time), ("count", &export.last_count.to_string())])
+    } else {
+        fill(&strings.scheduled_export_last_failed, &[("time", &time), ("error", &export.last_error)])
+    }
+}
@@ -0,0 +1,193 @@
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{ScreenToClient, HFONT},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Controls::*,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::lang::get_strings;
+use crate::shell_verb::browsed_folder;
+use crate::thumbnail::to_wide;
+
+// Height of the strip of tabs above the results
+pub const TAB_STRIP_HEIGHT: i32 = 26;
+
+// Tab titles longer than this many characters are cut short
+const MAX_TAB_TITLE: usize = 32;
+
+// Searches kept open side by side. Only the active one is shown; switching
+// tabs puts the other one's query back in the search box. The strip is
+// hidden while there is a single tab.
+pub struct SearchTabs {
+    pub strip: HWND,
+    queries: Vec<String>,
+    active: usize,
+}
+
+impl SearchTabs {
+    pub fn create(parent: HWND, id: i32, font: HFONT) -> Self {
+        unsafe {
+            let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+            let strip = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                WC_TABCONTROLW,
+                PCWSTR::null(),
+                WS_CHILD | WS_CLIPSIBLINGS | WINDOW_STYLE(TCS_FOCUSNEVER),
+                0,
+                0,
+                0,
+                TAB_STRIP_HEIGHT,
+                parent,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(strip, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+
+            let tabs = Self {
+                strip,
+                queries: vec![String::new()],
+                active: 0,
+            };
+            tabs.insert_tab(0);
+            tabs
+        }
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.queries.len() > 1
+    }
+
+    // What each tab searches for, in strip order
+    pub fn queries(&self) -> &[String] {
+        &self.queries
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    // Replaces every tab with one per query, e.g. from the saved session.
+    // An empty list leaves the tabs as they are.
+    pub fn restore(&mut self, queries: Vec<String>, active: usize) {
+        if queries.is_empty() {
+            return;
+        }
+        unsafe {
+            SendMessageW(self.strip, TCM_DELETEALLITEMS, WPARAM(0), LPARAM(0));
+        }
+        self.queries = queries;
+        self.active = active.min(self.queries.len() - 1);
+        for index in 0..self.queries.len() {
+            self.insert_tab(index);
+        }
+        self.show_active();
+    }
+
+    // Keeps `query` as what the active tab searches for
+    pub fn set_active_query(&mut self, query: &str) {
+        if self.queries[self.active] != query {
+            self.queries[self.active] = query.to_string();
+            self.set_title(self.active);
+        }
+    }
+
+    // Adds a tab for `query` after the others and makes it the active one
+    pub fn open(&mut self, query: &str) {
+        self.queries.push(query.to_string());
+        self.active = self.queries.len() - 1;
+        self.insert_tab(self.active);
+        self.show_active();
+    }
+
+    // Makes tab `index` the active one. Returns its query when that changed
+    // the active tab.
+    pub fn select(&mut self, index: usize) -> Option<String> {
+        if index >= self.queries.len() || index == self.active {
+            return None;
+        }
+        self.active = index;
+        self.show_active();
+        Some(self.queries[index].clone())
+    }
+
+    // Closes tab `index`, the last one staying open. Returns the query of
+    // the tab that becomes active when the active one was closed.
+    pub fn close(&mut self, index: usize) -> Option<String> {
+        if index >= self.queries.len() || self.queries.len() == 1 {
+            return None;
+        }
+        self.queries.remove(index);
+        unsafe {
+            SendMessageW(self.strip, TCM_DELETEITEM, WPARAM(index), LPARAM(0));
+        }
+        let closed_active = index == self.active;
+        if index < self.active || self.active == self.queries.len() {
+            self.active -= 1;
+        }
+        self.show_active();
+        closed_active.then(|| self.queries[self.active].clone())
+    }
+
+    // The tab under `point`, in screen coordinates
+    pub fn tab_at(&self, point: POINT) -> Option<usize> {
+        let mut hit = TCHITTESTINFO { pt: point, flags: TCHT_NOWHERE };
+        unsafe {
+            let _ = ScreenToClient(self.strip, &mut hit.pt);
+            let index = SendMessageW(self.strip, TCM_HITTEST, WPARAM(0), LPARAM(&mut hit as *mut _ as isize)).0;
+            (index >= 0).then_some(index as usize)
+        }
+    }
+
+    fn show_active(&self) {
+        unsafe {
+            SendMessageW(self.strip, TCM_SETCURSEL, WPARAM(self.active), LPARAM(0));
+        }
+    }
+
+    fn insert_tab(&self, index: usize) {
+        let mut title = to_wide(&tab_title(&self.queries[index]));
+        let item = TCITEMW {
+            mask: TCIF_TEXT,
+            pszText: PWSTR::from_raw(title.as_mut_ptr()),
+            ..Default::default()
+        };
+        unsafe {
+            SendMessageW(self.strip, TCM_INSERTITEMW, WPARAM(index), LPARAM(&item as *const _ as isize));
+        }
+    }
+
+    fn set_title(&self, index: usize) {
+        let mut title = to_wide(&tab_title(&self.queries[index]));
+        let item = TCITEMW {
+            mask: TCIF_TEXT,
+            pszText: PWSTR::from_raw(title.as_mut_ptr()),
+            ..Default::default()
+        };
+        unsafe {
+            SendMessageW(self.strip, TCM_SETITEMW, WPARAM(index), LPARAM(&item as *const _ as isize));
+        }
+    }
+}
+
+// What a tab is called: the folder it browses, or its query
+fn tab_title(query: &str) -> String {
+    let title = match browsed_folder(query) {
+        Some(folder) => {
+            let trimmed = folder.trim_end_matches('\\');
+            trimmed.rsplit('\\').next().filter(|name| !name.is_empty() && *name != trimmed).unwrap_or(folder).to_string()
+        }
+        None if query.trim().is_empty() => get_strings().tab_new_search.clone(),
+        None => query.trim().to_string(),
+    };
+    if title.chars().count() > MAX_TAB_TITLE {
+        format!("{}…", title.chars().take(MAX_TAB_TITLE - 1).collect::<String>())
+    } else {
+        title
+    }
+}
@@ -0,0 +1,619 @@
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{HBRUSH, HFONT, COLOR_BTNFACE},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Controls::*,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::config::{
+    AppConfig, LanguageCode, ThumbnailBackground, ThumbnailCaption, ThumbnailStrategy,
+    CONTACT_SHEET_CELL_SIZE_CHOICES, CONTACT_SHEET_COLUMN_CHOICES,
+    HTTP_SERVER_PORT_RANGE, MAX_RESULTS_RANGE, MEMORY_BUDGET_RANGE, SEARCH_DELAY_RANGE, THUMBNAIL_CACHE_SIZE_RANGE, THUMBNAIL_THREADS_RANGE,
+};
+use crate::lang::{get_strings, Language};
+use crate::logger::log_error;
+use crate::thumbnail::to_wide;
+
+// Sent to the owner window when settings are applied. WPARAM holds a
+// Box<AppConfig> with the edited values; the owner takes ownership of it.
+pub const WM_APPLY_SETTINGS: u32 = WM_USER + 105;
+
+const SETTINGS_WINDOW_CLASS: &str = "EverythingLikeSettingsWindow";
+
+const DIALOG_WIDTH: i32 = 480;
+const DIALOG_HEIGHT: i32 = 400;
+const MARGIN: i32 = 10;
+const ROW_HEIGHT: i32 = 34;
+const LABEL_WIDTH: i32 = 200;
+const BUTTON_WIDTH: i32 = 80;
+const BUTTON_HEIGHT: i32 = 26;
+
+// Control IDs
+const ID_SETTINGS_TAB: i32 = 9201;
+const ID_SETTINGS_OK: i32 = 9202;
+const ID_SETTINGS_CANCEL: i32 = 9203;
+const ID_SETTINGS_APPLY: i32 = 9204;
+const ID_SETTINGS_RESTORE_SESSION: i32 = 9210;
+const ID_SETTINGS_SEARCH_DELAY: i32 = 9211;
+const ID_SETTINGS_MAX_RESULTS: i32 = 9212;
+const ID_SETTINGS_BACKGROUND: i32 = 9213;
+const ID_SETTINGS_STRATEGY: i32 = 9214;
+const ID_SETTINGS_SHEET_COLUMNS: i32 = 9215;
+const ID_SETTINGS_SHEET_CELL_SIZE: i32 = 9216;
+const ID_SETTINGS_LANGUAGE: i32 = 9217;
+const ID_SETTINGS_THREADS: i32 = 9218;
+const ID_SETTINGS_CACHE_SIZE: i32 = 9219;
+const ID_SETTINGS_START_MINIMIZED: i32 = 9220;
+const ID_SETTINGS_START_WITH_WINDOWS: i32 = 9221;
+const ID_SETTINGS_DEFAULT_SEARCH: i32 = 9222;
+const ID_SETTINGS_LOCALE_SORT: i32 = 9223;
+const ID_SETTINGS_EXPLORER_VERB: i32 = 9224;
+const ID_SETTINGS_CLOSE_TO_TRAY: i32 = 9225;
+const ID_SETTINGS_QUICK_SEARCH_HOTKEY: i32 = 9226;
+const ID_SETTINGS_HTTP_SERVER: i32 = 9227;
+const ID_SETTINGS_HTTP_PORT: i32 = 9228;
+const ID_SETTINGS_URL_PROTOCOL: i32 = 9229;
+const ID_SETTINGS_TERMINAL_PROFILE: i32 = 9230;
+const ID_SETTINGS_WSL_DISTRO: i32 = 9231;
+const ID_SETTINGS_EVERYTHING_BOOKMARKS: i32 = 9232;
+const ID_SETTINGS_EVERYTHING_FILTERS: i32 = 9233;
+const ID_SETTINGS_CLIPBOARD_WATCHER: i32 = 9234;
+const ID_SETTINGS_AUTO_UPDATE: i32 = 9235;
+const ID_SETTINGS_LAUNCHER_HOTKEY: i32 = 9236;
+const ID_SETTINGS_SKIP_NETWORK_THUMBNAILS: i32 = 9237;
+const ID_SETTINGS_MEMORY_BUDGET: i32 = 9238;
+const ID_SETTINGS_CAPTION: i32 = 9239;
+
+// Combo box entries, in display order
+const STRATEGIES: [ThumbnailStrategy; 3] = [
+    ThumbnailStrategy::DefaultTopToBottom,
+    ThumbnailStrategy::OnlyLoadVisible,
+    ThumbnailStrategy::LoadVisiblePlus500,
+];
+const BACKGROUNDS: [ThumbnailBackground; 7] = [
+    ThumbnailBackground::Transparent,
+    ThumbnailBackground::Checkerboard,
+    ThumbnailBackground::Black,
+    ThumbnailBackground::White,
+    ThumbnailBackground::Gray,
+    ThumbnailBackground::LightGray,
+    ThumbnailBackground::DarkGray,
+];
+const CAPTIONS: [ThumbnailCaption; 4] = [
+    ThumbnailCaption::Name,
+    ThumbnailCaption::NameAndSize,
+    ThumbnailCaption::NameAndDimensions,
+    ThumbnailCaption::Hidden,
+];
+
+struct SettingsDialog {
+    owner: HWND,
+    tab: HWND,
+    // Controls shown on each tab, labels included
+    pages: Vec<Vec<HWND>>,
+    // Values the dialog was opened with; fields without a control pass through unchanged
+    config: AppConfig,
+}
+
+// The window's SettingsDialog, kept in GWLP_USERDATA
+unsafe fn settings_dialog(window: HWND) -> Option<&'static mut SettingsDialog> {
+    let dialog = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut SettingsDialog;
+    dialog.as_mut()
+}
+
+// Where a control goes in the dialog's client area
+struct Bounds {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+// Lays out one tab's controls top to bottom and remembers them for show/hide
+struct PageBuilder {
+    window: HWND,
+    instance: HINSTANCE,
+    font: HFONT,
+    area: RECT,
+    row: i32,
+    controls: Vec<HWND>,
+}
+
+impl PageBuilder {
+    fn new(window: HWND, instance: HINSTANCE, font: HFONT, area: RECT) -> Self {
+        Self {
+            window,
+            instance,
+            font,
+            area,
+            row: 0,
+            controls: Vec::new(),
+        }
+    }
+
+    fn row_y(&self) -> i32 {
+        self.area.top + MARGIN + self.row * ROW_HEIGHT
+    }
+
+    unsafe fn create(&mut self, class: PCWSTR, text: &str, style: WINDOW_STYLE, ex_style: WINDOW_EX_STYLE, id: i32, bounds: Bounds) -> HWND {
+        let control = CreateWindowExW(
+            ex_style,
+            class,
+            PCWSTR::from_raw(to_wide(text).as_ptr()),
+            WS_CHILD | style,
+            bounds.x, bounds.y, bounds.width, bounds.height,
+            self.window,
+            HMENU(id as isize),
+            self.instance,
+            None,
+        );
+        SendMessageW(control, WM_SETFONT, WPARAM(self.font.0 as usize), LPARAM(1));
+        self.controls.push(control);
+        control
+    }
+
+    unsafe fn add_label(&mut self, text: &str) {
+        let y = self.row_y() + 3;
+        self.create(w!("STATIC"), text, WINDOW_STYLE(0), WINDOW_EX_STYLE::default(), -1, Bounds { x: self.area.left + MARGIN, y, width: LABEL_WIDTH, height: 22 });
+    }
+
+    fn control_x(&self) -> i32 {
+        self.area.left + MARGIN + LABEL_WIDTH + MARGIN
+    }
+
+    fn control_width(&self) -> i32 {
+        (self.area.right - MARGIN - self.control_x()).max(0)
+    }
+
+    unsafe fn add_checkbox(&mut self, id: i32, text: &str, checked: bool) {
+        let (x, y, width) = (self.area.left + MARGIN, self.row_y(), self.area.right - self.area.left - MARGIN * 2);
+        let checkbox = self.create(w!("BUTTON"), text, WS_TABSTOP | WINDOW_STYLE(BS_AUTOCHECKBOX as u32), WINDOW_EX_STYLE::default(), id, Bounds { x, y, width, height: 24 });
+        if checked {
+            SendMessageW(checkbox, BM_SETCHECK, WPARAM(BST_CHECKED.0 as usize), LPARAM(0));
+        }
+        self.row += 1;
+    }
+
+    unsafe fn add_number(&mut self, id: i32, label: &str, value: u32) {
+        self.add_label(label);
+        let (x, y, width) = (self.control_x(), self.row_y(), self.control_width());
+        self.create(w!("EDIT"), &value.to_string(), WS_TABSTOP | WINDOW_STYLE((ES_NUMBER | ES_AUTOHSCROLL) as u32), WS_EX_CLIENTEDGE, id, Bounds { x, y, width, height: 24 });
+        self.row += 1;
+    }
+
+    // Full-width explanatory text on its own row
+    unsafe fn add_note(&mut self, text: &str) {
+        let (x, y, width) = (self.area.left + MARGIN, self.row_y(), self.area.right - self.area.left - MARGIN * 2);
+        self.create(w!("STATIC"), text, WINDOW_STYLE(0), WINDOW_EX_STYLE::default(), -1, Bounds { x, y, width, height: 40 });
+        self.row += 1;
+    }
+
+    unsafe fn add_text(&mut self, id: i32, label: &str, value: &str) {
+        self.add_label(label);
+        let (x, y, width) = (self.control_x(), self.row_y(), self.control_width());
+        self.create(w!("EDIT"), value, WS_TABSTOP | WINDOW_STYLE(ES_AUTOHSCROLL as u32), WS_EX_CLIENTEDGE, id, Bounds { x, y, width, height: 24 });
+        self.row += 1;
+    }
+
+    unsafe fn add_choice(&mut self, id: i32, label: &str, items: &[String], selected: usize) {
+        self.add_label(label);
+        // The height includes the drop-down list
+        let (x, y, width) = (self.control_x(), self.row_y(), self.control_width());
+        let combo = self.create(w!("COMBOBOX"), "", WS_TABSTOP | WS_VSCROLL | WINDOW_STYLE(CBS_DROPDOWNLIST as u32), WINDOW_EX_STYLE::default(), id, Bounds { x, y, width, height: 200 });
+        for item in items {
+            SendMessageW(combo, CB_ADDSTRING, WPARAM(0), LPARAM(to_wide(item).as_ptr() as isize));
+        }
+        SendMessageW(combo, CB_SETCURSEL, WPARAM(selected), LPARAM(0));
+        self.row += 1;
+    }
+}
+
+pub fn register_settings_window_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(settings_window_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            // COLOR_BTNFACE + 1, so the pages match the tab control
+            hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as isize),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeSettingsWindow"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+pub fn show_settings_dialog(owner: HWND, config: &AppConfig, font: HFONT) {
+    unsafe {
+        // Only one settings window is open at a time
+        let existing = FindWindowW(PCWSTR::from_raw(to_wide(SETTINGS_WINDOW_CLASS).as_ptr()), None);
+        if existing.0 != 0 {
+            let _ = SetForegroundWindow(existing);
+            return;
+        }
+
+        let init = INITCOMMONCONTROLSEX {
+            dwSize: std::mem::size_of::<INITCOMMONCONTROLSEX>() as u32,
+            dwICC: ICC_TAB_CLASSES | ICC_STANDARD_CLASSES,
+        };
+        let _ = InitCommonControlsEx(&init);
+
+        let strings = get_strings();
+        let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+
+        // Center over the main window
+        let mut owner_rect = RECT::default();
+        let _ = GetWindowRect(owner, &mut owner_rect);
+        let x = owner_rect.left + ((owner_rect.right - owner_rect.left) - DIALOG_WIDTH) / 2;
+        let y = owner_rect.top + ((owner_rect.bottom - owner_rect.top) - DIALOG_HEIGHT) / 2;
+
+        let window = CreateWindowExW(
+            WS_EX_DLGMODALFRAME,
+            PCWSTR::from_raw(to_wide(SETTINGS_WINDOW_CLASS).as_ptr()),
+            PCWSTR::from_raw(to_wide(&strings.settings_title).as_ptr()),
+            WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_VISIBLE,
+            x.max(0),
+            y.max(0),
+            DIALOG_WIDTH,
+            DIALOG_HEIGHT,
+            owner,
+            None,
+            instance,
+            None,
+        );
+
+        if window.0 == 0 {
+            log_error("Failed to create settings window");
+            return;
+        }
+
+        let mut client_rect = RECT::default();
+        let _ = GetClientRect(window, &mut client_rect);
+        let button_y = client_rect.bottom - MARGIN - BUTTON_HEIGHT;
+
+        let tab = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            WC_TABCONTROLW,
+            PCWSTR::null(),
+            WS_CHILD | WS_VISIBLE | WS_CLIPSIBLINGS | WS_TABSTOP,
+            MARGIN,
+            MARGIN,
+            client_rect.right - MARGIN * 2,
+            button_y - MARGIN * 2,
+            window,
+            HMENU(ID_SETTINGS_TAB as isize),
+            instance,
+            None,
+        );
+        SendMessageW(tab, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+
+        let tab_names = [
+            &strings.settings_tab_general,
+            &strings.settings_tab_search,
+            &strings.settings_tab_view,
+            &strings.settings_tab_thumbnails,
+            &strings.settings_tab_language,
+            &strings.settings_tab_advanced,
+        ];
+        for (index, name) in tab_names.iter().enumerate() {
+            let mut name_wide = to_wide(name);
+            let item = TCITEMW {
+                mask: TCIF_TEXT,
+                pszText: PWSTR::from_raw(name_wide.as_mut_ptr()),
+                ..Default::default()
+            };
+            SendMessageW(tab, TCM_INSERTITEMW, WPARAM(index), LPARAM(&item as *const _ as isize));
+        }
+
+        // Page area in dialog coordinates, below the tab headers
+        let mut area = RECT {
+            left: 0,
+            top: 0,
+            right: client_rect.right - MARGIN * 2,
+            bottom: button_y - MARGIN * 2,
+        };
+        SendMessageW(tab, TCM_ADJUSTRECT, WPARAM(0), LPARAM(&mut area as *mut _ as isize));
+        area.left += MARGIN;
+        area.right += MARGIN;
+        area.top += MARGIN;
+        area.bottom += MARGIN;
+
+        let mut pages = Vec::new();
+
+        // General
+        let mut page = PageBuilder::new(window, instance, font, area);
+        page.add_checkbox(ID_SETTINGS_RESTORE_SESSION, &strings.settings_restore_session, config.restore_session);
+        page.add_checkbox(ID_SETTINGS_START_MINIMIZED, &strings.settings_start_minimized, config.start_minimized);
+        page.add_checkbox(ID_SETTINGS_START_WITH_WINDOWS, &strings.settings_start_with_windows, config.start_with_windows);
+        page.add_checkbox(ID_SETTINGS_EXPLORER_VERB, &strings.settings_explorer_verb, config.explorer_verb);
+        page.add_checkbox(ID_SETTINGS_URL_PROTOCOL, &strings.settings_url_protocol, config.url_protocol);
+        page.add_checkbox(ID_SETTINGS_CLOSE_TO_TRAY, &strings.settings_close_to_tray, config.close_to_tray);
+        page.add_checkbox(ID_SETTINGS_QUICK_SEARCH_HOTKEY, &strings.settings_quick_search_hotkey, config.quick_search_hotkey);
+        page.add_checkbox(ID_SETTINGS_CLIPBOARD_WATCHER, &strings.settings_clipboard_watcher, config.clipboard_watcher);
+        page.add_checkbox(ID_SETTINGS_AUTO_UPDATE, &strings.settings_auto_update, config.auto_check_updates);
+        pages.push(page.controls);
+
+        // Search
+        let mut page = PageBuilder::new(window, instance, font, area);
+        page.add_number(ID_SETTINGS_SEARCH_DELAY, &strings.settings_search_delay, config.search_delay_ms);
+        page.add_number(ID_SETTINGS_MAX_RESULTS, &strings.settings_max_results, config.max_results);
+        page.add_number(ID_SETTINGS_MEMORY_BUDGET, &strings.settings_memory_budget, config.memory_budget_mb);
+        page.add_text(ID_SETTINGS_DEFAULT_SEARCH, &strings.settings_default_search, &config.default_search);
+        page.add_note(&strings.settings_default_search_hint);
+        page.add_text(ID_SETTINGS_EVERYTHING_BOOKMARKS, &strings.settings_everything_bookmarks, &config.everything_bookmarks_path);
+        page.add_text(ID_SETTINGS_EVERYTHING_FILTERS, &strings.settings_everything_filters, &config.everything_filters_path);
+        page.add_note(&strings.settings_everything_import_hint);
+        page.add_checkbox(ID_SETTINGS_LAUNCHER_HOTKEY, &strings.settings_launcher_hotkey, config.launcher_hotkey);
+        pages.push(page.controls);
+
+        // View
+        let mut page = PageBuilder::new(window, instance, font, area);
+        let background_names: Vec<String> = [
+            &strings.bg_transparent,
+            &strings.bg_checkerboard,
+            &strings.bg_black,
+            &strings.bg_white,
+            &strings.bg_gray,
+            &strings.bg_light_gray,
+            &strings.bg_dark_gray,
+        ].iter().map(|name| name.to_string()).collect();
+        let background_index = BACKGROUNDS.iter().position(|&b| b == config.thumbnail_background).unwrap_or(0);
+        page.add_choice(ID_SETTINGS_BACKGROUND, &strings.settings_background, &background_names, background_index);
+        page.add_checkbox(ID_SETTINGS_LOCALE_SORT, &strings.sort_locale, config.locale_sort);
+        pages.push(page.controls);
+
+        // Thumbnails
+        let mut page = PageBuilder::new(window, instance, font, area);
+        let strategy_names = vec![
+            strings.thumb_default.clone(),
+            strings.thumb_visible.clone(),
+            strings.thumb_visible_plus_500.clone(),
+        ];
+        let strategy_index = STRATEGIES.iter().position(|&s| s == config.thumbnail_strategy).unwrap_or(0);
+        page.add_choice(ID_SETTINGS_STRATEGY, &strings.settings_strategy, &strategy_names, strategy_index);
+        page.add_checkbox(ID_SETTINGS_SKIP_NETWORK_THUMBNAILS, &strings.settings_skip_network_thumbnails, config.skip_network_thumbnails);
+        let caption_names = vec![
+            strings.caption_name.clone(),
+            strings.caption_name_size.clone(),
+            strings.caption_name_dimensions.clone(),
+            strings.caption_none.clone(),
+        ];
+        let caption_index = CAPTIONS.iter().position(|&c| c == config.thumbnail_caption).unwrap_or(0);
+        page.add_choice(ID_SETTINGS_CAPTION, &strings.settings_caption, &caption_names, caption_index);
+
+        let column_names: Vec<String> = CONTACT_SHEET_COLUMN_CHOICES.iter().map(|c| c.to_string()).collect();
+        let column_index = CONTACT_SHEET_COLUMN_CHOICES.iter().position(|&c| c == config.contact_sheet_columns).unwrap_or(0);
+        page.add_choice(ID_SETTINGS_SHEET_COLUMNS, &strings.settings_sheet_columns, &column_names, column_index);
+
+        let cell_size_names: Vec<String> = CONTACT_SHEET_CELL_SIZE_CHOICES.iter().map(|s| format!("{} x {} px", s, s)).collect();
+        let cell_size_index = CONTACT_SHEET_CELL_SIZE_CHOICES.iter().position(|&s| s == config.contact_sheet_cell_size).unwrap_or(0);
+        page.add_choice(ID_SETTINGS_SHEET_CELL_SIZE, &strings.settings_sheet_cell_size, &cell_size_names, cell_size_index);
+        pages.push(page.controls);
+
+        // Language
+        let mut page = PageBuilder::new(window, instance, font, area);
+        let language_names: Vec<String> = Language::ALL.iter().map(|l| l.display_name().to_string()).collect();
+        let language_index = Language::ALL.iter().position(|l| l.to_code() == config.language.to_string()).unwrap_or(0);
+        page.add_choice(ID_SETTINGS_LANGUAGE, &strings.settings_language, &language_names, language_index);
+        pages.push(page.controls);
+
+        // Advanced
+        let mut page = PageBuilder::new(window, instance, font, area);
+        page.add_number(ID_SETTINGS_THREADS, &strings.settings_threads, config.thumbnail_threads);
+        page.add_number(ID_SETTINGS_CACHE_SIZE, &strings.settings_cache_size, config.thumbnail_cache_size);
+        page.add_checkbox(ID_SETTINGS_HTTP_SERVER, &strings.settings_http_server, config.http_server);
+        page.add_number(ID_SETTINGS_HTTP_PORT, &strings.settings_http_port, config.http_server_port);
+        page.add_text(ID_SETTINGS_WSL_DISTRO, &strings.settings_wsl_distro, &config.wsl_distro);
+        page.add_text(ID_SETTINGS_TERMINAL_PROFILE, &strings.settings_terminal_profile, &config.terminal_profile);
+        page.add_note(&strings.settings_terminal_profile_hint);
+        pages.push(page.controls);
+
+        // Page controls are siblings of the tab control and must stay above it
+        let _ = SetWindowPos(tab, HWND_BOTTOM, 0, 0, 0, 0, SWP_NOMOVE | SWP_NOSIZE);
+
+        let buttons = [
+            (ID_SETTINGS_OK, &strings.settings_ok, BS_DEFPUSHBUTTON, 3),
+            (ID_SETTINGS_CANCEL, &strings.settings_cancel, BS_PUSHBUTTON, 2),
+            (ID_SETTINGS_APPLY, &strings.settings_apply, BS_PUSHBUTTON, 1),
+        ];
+        for (id, label, style, slot) in buttons {
+            let button = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                w!("BUTTON"),
+                PCWSTR::from_raw(to_wide(label).as_ptr()),
+                WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(style as u32),
+                client_rect.right - (MARGIN + BUTTON_WIDTH) * slot,
+                button_y,
+                BUTTON_WIDTH,
+                BUTTON_HEIGHT,
+                window,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(button, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        }
+
+        let dialog = Box::new(SettingsDialog {
+            owner,
+            tab,
+            pages,
+            config: config.clone(),
+        });
+        SetWindowLongPtrW(window, GWLP_USERDATA, Box::into_raw(dialog) as isize);
+
+        show_page(window, 0);
+    }
+}
+
+unsafe fn show_page(window: HWND, index: usize) {
+    if let Some(dialog) = settings_dialog(window) {
+        for (page_index, controls) in dialog.pages.iter().enumerate() {
+            let command = if page_index == index { SW_SHOW } else { SW_HIDE };
+            for &control in controls {
+                ShowWindow(control, command);
+            }
+        }
+    }
+}
+
+unsafe fn read_checkbox(window: HWND, id: i32) -> bool {
+    SendMessageW(GetDlgItem(window, id), BM_GETCHECK, WPARAM(0), LPARAM(0)).0 == BST_CHECKED.0 as isize
+}
+
+unsafe fn read_choice(window: HWND, id: i32) -> Option<usize> {
+    let index = SendMessageW(GetDlgItem(window, id), CB_GETCURSEL, WPARAM(0), LPARAM(0)).0;
+    if index >= 0 { Some(index as usize) } else { None }
+}
+
+// Falls back to the current value when the field is empty or not a number
+unsafe fn read_number(window: HWND, id: i32, current: u32, range: (u32, u32)) -> u32 {
+    let mut buffer: [u16; 32] = [0; 32];
+    let len = GetWindowTextW(GetDlgItem(window, id), &mut buffer);
+    String::from_utf16_lossy(&buffer[..len.max(0) as usize])
+        .trim()
+        .parse::<u32>()
+        .map(|value| value.clamp(range.0, range.1))
+        .unwrap_or(current)
+}
+
+unsafe fn read_text(window: HWND, id: i32) -> String {
+    let mut buffer: [u16; 260] = [0; 260];
+    let len = GetWindowTextW(GetDlgItem(window, id), &mut buffer);
+    String::from_utf16_lossy(&buffer[..len.max(0) as usize]).trim().to_string()
+}
+
+unsafe fn read_settings(window: HWND, base: &AppConfig) -> AppConfig {
+    let mut config = base.clone();
+
+    config.restore_session = read_checkbox(window, ID_SETTINGS_RESTORE_SESSION);
+    config.start_minimized = read_checkbox(window, ID_SETTINGS_START_MINIMIZED);
+    config.start_with_windows = read_checkbox(window, ID_SETTINGS_START_WITH_WINDOWS);
+    config.explorer_verb = read_checkbox(window, ID_SETTINGS_EXPLORER_VERB);
+    config.url_protocol = read_checkbox(window, ID_SETTINGS_URL_PROTOCOL);
+    config.close_to_tray = read_checkbox(window, ID_SETTINGS_CLOSE_TO_TRAY);
+    config.quick_search_hotkey = read_checkbox(window, ID_SETTINGS_QUICK_SEARCH_HOTKEY);
+    config.launcher_hotkey = read_checkbox(window, ID_SETTINGS_LAUNCHER_HOTKEY);
+    config.skip_network_thumbnails = read_checkbox(window, ID_SETTINGS_SKIP_NETWORK_THUMBNAILS);
+    config.clipboard_watcher = read_checkbox(window, ID_SETTINGS_CLIPBOARD_WATCHER);
+    config.auto_check_updates = read_checkbox(window, ID_SETTINGS_AUTO_UPDATE);
+    config.locale_sort = read_checkbox(window, ID_SETTINGS_LOCALE_SORT);
+    config.http_server = read_checkbox(window, ID_SETTINGS_HTTP_SERVER);
+
+    config.search_delay_ms = read_number(window, ID_SETTINGS_SEARCH_DELAY, base.search_delay_ms, SEARCH_DELAY_RANGE);
+    config.max_results = read_number(window, ID_SETTINGS_MAX_RESULTS, base.max_results, MAX_RESULTS_RANGE);
+    config.memory_budget_mb = read_number(window, ID_SETTINGS_MEMORY_BUDGET, base.memory_budget_mb, MEMORY_BUDGET_RANGE);
+    config.default_search = read_text(window, ID_SETTINGS_DEFAULT_SEARCH);
+    config.terminal_profile = read_text(window, ID_SETTINGS_TERMINAL_PROFILE);
+    config.wsl_distro = read_text(window, ID_SETTINGS_WSL_DISTRO);
+    config.everything_bookmarks_path = read_text(window, ID_SETTINGS_EVERYTHING_BOOKMARKS);
+    config.everything_filters_path = read_text(window, ID_SETTINGS_EVERYTHING_FILTERS);
+    config.thumbnail_threads = read_number(window, ID_SETTINGS_THREADS, base.thumbnail_threads, THUMBNAIL_THREADS_RANGE);
+    config.thumbnail_cache_size = read_number(window, ID_SETTINGS_CACHE_SIZE, base.thumbnail_cache_size, THUMBNAIL_CACHE_SIZE_RANGE);
+    config.http_server_port = read_number(window, ID_SETTINGS_HTTP_PORT, base.http_server_port, HTTP_SERVER_PORT_RANGE);
+
+    if let Some(index) = read_choice(window, ID_SETTINGS_BACKGROUND) {
+        config.thumbnail_background = BACKGROUNDS[index];
+    }
+    if let Some(index) = read_choice(window, ID_SETTINGS_STRATEGY) {
+        config.thumbnail_strategy = STRATEGIES[index];
+    }
+    if let Some(index) = read_choice(window, ID_SETTINGS_CAPTION) {
+        config.thumbnail_caption = CAPTIONS[index];
+    }
+    if let Some(index) = read_choice(window, ID_SETTINGS_SHEET_COLUMNS) {
+        config.contact_sheet_columns = CONTACT_SHEET_COLUMN_CHOICES[index];
+    }
+    if let Some(index) = read_choice(window, ID_SETTINGS_SHEET_CELL_SIZE) {
+        config.contact_sheet_cell_size = CONTACT_SHEET_CELL_SIZE_CHOICES[index];
+    }
+    if let Some(index) = read_choice(window, ID_SETTINGS_LANGUAGE) {
+        config.language = LanguageCode::from_string(Language::ALL[index].to_code());
+    }
+
+    config
+}
+
+unsafe fn apply_settings(window: HWND) {
+    if let Some(dialog) = settings_dialog(window) {
+        let config = read_settings(window, &dialog.config);
+        dialog.config = config.clone();
+
+        // Show clamped values so the fields match what was applied
+        for (id, value) in [
+            (ID_SETTINGS_SEARCH_DELAY, config.search_delay_ms),
+            (ID_SETTINGS_MAX_RESULTS, config.max_results),
+            (ID_SETTINGS_MEMORY_BUDGET, config.memory_budget_mb),
+            (ID_SETTINGS_THREADS, config.thumbnail_threads),
+            (ID_SETTINGS_CACHE_SIZE, config.thumbnail_cache_size),
+        ] {
+            let _ = SetWindowTextW(GetDlgItem(window, id), PCWSTR::from_raw(to_wide(&value.to_string()).as_ptr()));
+        }
+
+        let config_ptr = Box::into_raw(Box::new(config)) as usize;
+        SendMessageW(dialog.owner, WM_APPLY_SETTINGS, WPARAM(config_ptr), LPARAM(0));
+    }
+}
+
+extern "system" fn settings_window_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_NOTIFY => {
+                let header = &*(lparam.0 as *const NMHDR);
+                if header.code == TCN_SELCHANGE {
+                    if let Some(dialog) = settings_dialog(window) {
+                        let index = SendMessageW(dialog.tab, TCM_GETCURSEL, WPARAM(0), LPARAM(0)).0;
+                        show_page(window, index.max(0) as usize);
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                match (wparam.0 & 0xFFFF) as i32 {
+                    ID_SETTINGS_OK => {
+                        apply_settings(window);
+                        let _ = DestroyWindow(window);
+                    }
+                    ID_SETTINGS_CANCEL => {
+                        let _ = DestroyWindow(window);
+                    }
+                    ID_SETTINGS_APPLY => {
+                        apply_settings(window);
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                let dialog = SetWindowLongPtrW(window, GWLP_USERDATA, 0) as *mut SettingsDialog;
+                if !dialog.is_null() {
+                    drop(Box::from_raw(dialog));
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use windows::{
+    core::{w, PCWSTR},
+    Win32::{
+        Foundation::*,
+        System::{
+            DataExchange::COPYDATASTRUCT,
+            Registry::*,
+        },
+        UI::WindowsAndMessaging::*,
+    },
+};
+use crate::logger::log_info;
+use crate::thumbnail::to_wide;
+
+// Folders, folder backgrounds and drives each have their own shell key
+const VERB_KEYS: [&str; 3] = [
+    "Software\\Classes\\Directory\\shell\\EverythingLike.SearchHere",
+    "Software\\Classes\\Directory\\Background\\shell\\EverythingLike.SearchHere",
+    "Software\\Classes\\Drive\\shell\\EverythingLike.SearchHere",
+];
+
+// Command line argument the verb starts the app with
+const SEARCH_IN_ARG: &str = "--search-in";
+
+// dwData of the WM_COPYDATA that hands a folder to the running instance;
+// lpData is the folder as UTF-16 without a terminator
+pub const COPYDATA_SEARCH_IN: usize = 1;
+
+// True when the "Search here" verb is registered for the current user
+pub fn is_explorer_verb_registered() -> bool {
+    unsafe {
+        let key_wide = to_wide(&format!("{}\\command", VERB_KEYS[0]));
+        let mut key = HKEY::default();
+        if RegOpenKeyExW(HKEY_CURRENT_USER, PCWSTR::from_raw(key_wide.as_ptr()), 0, KEY_QUERY_VALUE, &mut key).is_err() {
+            return false;
+        }
+        let _ = RegCloseKey(key);
+        true
+    }
+}
+
+// Adds or removes the verb on folders, folder backgrounds and drives. It
+// runs the current executable with --search-in and the folder.
+pub fn set_explorer_verb(enabled: bool, label: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if enabled {
+        let exe_path = std::env::current_exe()?;
+        let icon = format!("\"{}\",0", exe_path.display());
+        let command = format!("\"{}\" {} \"%V\"", exe_path.display(), SEARCH_IN_ARG);
+        for verb_key in VERB_KEYS {
+            unsafe {
+                let key = create_key(verb_key)?;
+                let result = set_string(key, None, label).and_then(|_| set_string(key, Some("Icon"), &icon));
+                let _ = RegCloseKey(key);
+                result?;
+
+                let key = create_key(&format!("{}\\command", verb_key))?;
+                let result = set_string(key, None, &command);
+                let _ = RegCloseKey(key);
+                result?;
+            }
+        }
+    } else {
+        for verb_key in VERB_KEYS {
+            let key_wide = to_wide(verb_key);
+            unsafe {
+                match RegDeleteTreeW(HKEY_CURRENT_USER, PCWSTR::from_raw(key_wide.as_ptr())) {
+                    // Already gone is what we wanted
+                    Err(e) if e.code() == ERROR_FILE_NOT_FOUND.to_hresult() => {}
+                    other => other?,
+                }
+            }
+        }
+    }
+
+    log_info(&format!("Explorer verb {}", if enabled { "registered" } else { "removed" }));
+    Ok(())
+}
+
+pub unsafe fn create_key(path: &str) -> windows::core::Result<HKEY> {
+    let path_wide = to_wide(path);
+    let mut key = HKEY::default();
+    RegCreateKeyExW(
+        HKEY_CURRENT_USER,
+        PCWSTR::from_raw(path_wide.as_ptr()),
+        0,
+        PCWSTR::null(),
+        REG_OPTION_NON_VOLATILE,
+        KEY_SET_VALUE,
+        None,
+        &mut key,
+        None,
+    )?;
+    Ok(key)
+}
+
+pub unsafe fn set_string(key: HKEY, name: Option<&str>, value: &str) -> windows::core::Result<()> {
+    let name_wide = name.map(to_wide);
+    let name_ptr = name_wide.as_ref().map_or(PCWSTR::null(), |name| PCWSTR::from_raw(name.as_ptr()));
+    let value_wide = to_wide(value);
+    let data = std::slice::from_raw_parts(value_wide.as_ptr() as *const u8, value_wide.len() * 2);
+    RegSetValueExW(key, name_ptr, 0, REG_SZ, Some(data))
+}
+
+// The folder passed with --search-in, if the app was started by the verb
+pub fn search_in_argument() -> Option<String> {
+    path_argument_value(SEARCH_IN_ARG)
+}
+
+// The non-empty value following `name` on the command line
+pub fn argument_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == name {
+            return args.next().filter(|value| !value.is_empty());
+        }
+    }
+    None
+}
+
+// Quotes one argument so the C runtime's command line parsing gives it back
+// unchanged: backslashes only need doubling when a quote follows them
+pub fn quote_argument(argument: &str) -> String {
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in argument.chars() {
+        if c == '\\' {
+            backslashes += 1;
+            continue;
+        }
+        let escaped = if c == '"' { backslashes * 2 + 1 } else { backslashes };
+        quoted.push_str(&"\\".repeat(escaped));
+        quoted.push(c);
+        backslashes = 0;
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+// The value following `name` when the shell passed it quoted, as with
+// "%V" and "%1". A drive root comes as "C:\", where the command line rules
+// read the backslash as escaping the closing quote and leave C:" instead;
+// the backslash is put back.
+pub fn path_argument_value(name: &str) -> Option<String> {
+    argument_value(name).map(|value| match value.strip_suffix('"') {
+        Some(path) => format!("{}\\", path),
+        None => value,
+    })
+}
+
+// Hands the folder to an instance that is already running. Returns false
+// when there is none, so this process should start up itself.
+pub fn forward_search_in(folder: &str) -> bool {
+    forward_to_running_instance(COPYDATA_SEARCH_IN, folder)
+}
+
+// Sends `text` to the running instance as a WM_COPYDATA of type `kind`
+pub fn forward_to_running_instance(kind: usize, text: &str) -> bool {
+    unsafe {
+        let window = FindWindowW(w!("EverythingLikeMainWindow"), None);
+        if window.0 == 0 {
+            return false;
+        }
+
+        // The shell gave this process the foreground; pass it on so the
+        // running window may come to the front
+        let mut process_id = 0;
+        GetWindowThreadProcessId(window, Some(&mut process_id));
+        let _ = AllowSetForegroundWindow(process_id);
+
+        let text_wide: Vec<u16> = text.encode_utf16().collect();
+        let data = COPYDATASTRUCT {
+            dwData: kind,
+            cbData: (text_wide.len() * 2) as u32,
+            lpData: text_wide.as_ptr() as *mut std::ffi::c_void,
+        };
+        SendMessageW(window, WM_COPYDATA, WPARAM(0), LPARAM(&data as *const _ as isize));
+        true
+    }
+}
+
+// Everything matches the whole path when a term contains a backslash, so a
+// quoted folder with a trailing backslash limits the search to that folder.
+// The trailing space leaves room to type the rest of the query.
+pub fn scoped_search_query(folder: &str) -> String {
+    let folder = folder.trim_end_matches(['\\', '/', '"']);
+    format!("\"{}\\\" ", folder)
+}
+
+// The opposite of scoped_search_query: leaves everything inside `folder`
+// out of the results
+pub fn excluded_search_query(folder: &str) -> String {
+    format!("!{}", scoped_search_query(folder))
+}
+
+// Everything's parent: lists what is directly inside `folder`, the way a
+// file browser shows it
+pub fn browse_folder_query(folder: &str) -> String {
+    format!("parent:\"{}\"", folder)
+}
+
+// The folder a query made by browse_folder_query lists
+pub fn browsed_folder(query: &str) -> Option<&str> {
+    query.trim().strip_prefix("parent:\"")?.strip_suffix('"').filter(|folder| !folder.contains('"'))
+}
@@ -0,0 +1,350 @@
+use windows::Win32::{
+    Foundation::*,
+    UI::WindowsAndMessaging::PostMessageW,
+};
+use rayon::prelude::*;
+use std::cmp::Ordering as CmpOrdering;
+use std::sync::{Arc, atomic::{AtomicBool, AtomicUsize, Ordering}};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::collation::compare_by_locale;
+use crate::drive_groups::drive_sort_key;
+use crate::everything_sdk::FileResult;
+use crate::media_info::{read_media_info, MediaInfo};
+use crate::run_history::Ranker;
+use crate::thumbnail::to_wide;
+
+// The text of a result that is compared for a column, and that breaks ties
+pub type SortText = fn(&FileResult) -> String;
+
+#[derive(Debug)]
+pub enum SortKey {
+    // Plain text comparison; Name and Path ignore case, Type doesn't
+    Text { ignore_case: bool },
+    // Text compared with the collation of a locale such as "zh-CN"
+    Locale(String),
+    Size,
+    Modified,
+    // Read from each file's property handler, which is slower still
+    Duration,
+    Bitrate,
+    // Best match first by name, run count and recency; the text breaks ties
+    Rank(Ranker),
+    // The order the results were found in, for grouping by drive alone
+    Unsorted,
+}
+
+impl SortKey {
+    fn reads_media(&self) -> bool {
+        matches!(self, SortKey::Duration | SortKey::Bitrate)
+    }
+}
+
+// The number a Duration or Bitrate sort compares; files without one go first
+fn media_value(key: &SortKey, info: Option<MediaInfo>) -> u64 {
+    match key {
+        SortKey::Duration => info.and_then(|info| info.duration).unwrap_or(0),
+        SortKey::Bitrate => info.and_then(|info| info.bitrate).map_or(0, |bitrate| bitrate as u64),
+        _ => 0,
+    }
+}
+
+// What the worker needs from one row. The metadata is None when the row
+// hasn't been stat'ed yet.
+pub struct SortItem {
+    pub path: String,
+    pub text: String,
+    pub metadata: Option<(u64, SystemTime)>,
+}
+
+pub struct SortResult {
+    pub generation: u64,
+    // The paths the order was computed for, used to check that the list
+    // hasn't changed in the meantime
+    pub paths: Vec<String>,
+    // New position -> old index
+    pub order: Vec<usize>,
+    pub metadata: Vec<Option<(u64, SystemTime)>>,
+    // Media details read for a Duration or Bitrate sort, otherwise empty
+    pub media: Vec<Option<MediaInfo>>,
+}
+
+// A sort running on a background thread. The result is posted as a boxed
+// SortResult in WPARAM of `message`; a cancelled sort posts nothing.
+pub struct SortTask {
+    pub generation: u64,
+    total: usize,
+    progress: Arc<AtomicUsize>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl SortTask {
+    // With `by_drive` the results of each drive come together, in drive
+    // order, ahead of `key`
+    pub fn start(window: HWND, message: u32, generation: u64, items: Vec<SortItem>, key: SortKey, descending: bool, by_drive: bool) -> Self {
+        let total = items.len();
+        let progress = Arc::new(AtomicUsize::new(0));
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let thread_progress = progress.clone();
+        let thread_cancelled = cancelled.clone();
+
+        std::thread::spawn(move || {
+            let mut paths = Vec::with_capacity(total);
+            let mut texts = Vec::with_capacity(total);
+            let mut metadata = Vec::with_capacity(total);
+            for item in items {
+                paths.push(item.path);
+                texts.push(item.text);
+                metadata.push(item.metadata);
+            }
+
+            // Size and date sorts need every file stat'ed first; this is the
+            // slow part, so it is what the progress counts
+            if matches!(key, SortKey::Size | SortKey::Modified) {
+                metadata.par_iter_mut().zip(paths.par_iter()).for_each(|(entry, path)| {
+                    if entry.is_none() && !thread_cancelled.load(Ordering::Relaxed) {
+                        *entry = Some(match std::fs::metadata(path) {
+                            Ok(file) => (file.len(), file.modified().unwrap_or(UNIX_EPOCH)),
+                            Err(_) => (0, UNIX_EPOCH),
+                        });
+                    }
+                    thread_progress.fetch_add(1, Ordering::Relaxed);
+                });
+            }
+
+            let media: Vec<Option<MediaInfo>> = if key.reads_media() {
+                paths
+                    .par_iter()
+                    .map(|path| {
+                        let info = if thread_cancelled.load(Ordering::Relaxed) { None } else { read_media_info(path) };
+                        thread_progress.fetch_add(1, Ordering::Relaxed);
+                        info
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+
+            if thread_cancelled.load(Ordering::Relaxed) {
+                return;
+            }
+
+            let mut order: Vec<usize> = (0..paths.len()).collect();
+            match key {
+                SortKey::Text { ignore_case: true } => {
+                    let keys: Vec<String> = texts.par_iter().map(|text| text.to_lowercase()).collect();
+                    order.par_sort_by(|&a, &b| keys[a].cmp(&keys[b]));
+                }
+                SortKey::Text { ignore_case: false } => {
+                    order.par_sort_by(|&a, &b| texts[a].cmp(&texts[b]));
+                }
+                SortKey::Locale(locale) => {
+                    let locale = to_wide(&locale);
+                    let keys: Vec<Vec<u16>> = texts.par_iter().map(|text| text.encode_utf16().collect()).collect();
+                    order.par_sort_by(|&a, &b| compare_by_locale(&locale, &keys[a], &keys[b]));
+                }
+                SortKey::Size => {
+                    let size = |index: usize| metadata[index].map_or(0, |(size, _)| size);
+                    order.par_sort_by(|&a, &b| size(a).cmp(&size(b)));
+                }
+                SortKey::Modified => {
+                    let modified = |index: usize| metadata[index].map_or(UNIX_EPOCH, |(_, modified)| modified);
+                    order.par_sort_by(|&a, &b| modified(a).cmp(&modified(b)));
+                }
+                SortKey::Duration | SortKey::Bitrate => {
+                    let values: Vec<u64> = media.iter().map(|info| media_value(&key, *info)).collect();
+                    order.par_sort_by(|&a, &b| values[a].cmp(&values[b]));
+                }
+                SortKey::Rank(ref ranker) => {
+                    let keys: Vec<(f64, String)> = paths
+                        .par_iter()
+                        .zip(texts.par_iter())
+                        .map(|(path, text)| (ranker.score(path, text), text.to_lowercase()))
+                        .collect();
+                    order.par_sort_by(|&a, &b| compare_rank((keys[a].0, &keys[a].1), (keys[b].0, &keys[b].1)));
+                }
+                SortKey::Unsorted => {}
+            }
+            if descending {
+                order.reverse();
+            }
+            // Stable, so each drive keeps the order of the key
+            if by_drive {
+                let drives: Vec<String> = paths.par_iter().map(|path| drive_sort_key(path)).collect();
+                order.par_sort_by(|&a, &b| drives[a].cmp(&drives[b]));
+            }
+
+            if thread_cancelled.load(Ordering::Relaxed) {
+                return;
+            }
+
+            let result_ptr = Box::into_raw(Box::new(SortResult {
+                generation,
+                paths,
+                order,
+                metadata,
+                media,
+            })) as usize;
+            unsafe {
+                if PostMessageW(window, message, WPARAM(result_ptr), LPARAM(0)).is_err() {
+                    drop(Box::from_raw(result_ptr as *mut SortResult));
+                }
+            }
+        });
+
+        Self {
+            generation,
+            total,
+            progress,
+            cancelled,
+        }
+    }
+
+    // Share of the files stat'ed or read so far. Text sorts have nothing to stat
+    // and stay at 0 until they finish.
+    pub fn percent(&self) -> usize {
+        if self.total == 0 {
+            return 0;
+        }
+        (self.progress.load(Ordering::Relaxed) * 100 / self.total).min(99)
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for SortTask {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+#[derive(Debug)]
+enum SortValue {
+    Text(String),
+    Wide(Vec<u16>),
+    Size(u64),
+    Modified(SystemTime),
+    Number(u64),
+    Rank(f64, String),
+}
+
+// Higher scores first, equal ones by name
+fn compare_rank(a: (f64, &str), b: (f64, &str)) -> CmpOrdering {
+    b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1))
+}
+
+// Keeps search results that arrive in batches in sort order. Each batch is
+// sorted on the search thread and merged into the keys seen so far; the
+// positions its items land at are returned so the UI can merge the rows
+// without comparing anything.
+#[derive(Debug)]
+pub struct StreamSorter {
+    key: SortKey,
+    text: SortText,
+    descending: bool,
+    by_drive: bool,
+    locale: Vec<u16>,
+    // The drive of each row, empty unless grouping by drive, and its value
+    keys: Vec<(String, SortValue)>,
+}
+
+impl StreamSorter {
+    pub fn new(key: SortKey, text: SortText, descending: bool, by_drive: bool) -> Self {
+        let locale = match &key {
+            SortKey::Locale(locale) => to_wide(locale),
+            _ => Vec::new(),
+        };
+        Self {
+            key,
+            text,
+            descending,
+            by_drive,
+            locale,
+            keys: Vec::new(),
+        }
+    }
+
+    // Sorts `items` and returns, for each of them in order, its index in the
+    // list once merged with all earlier batches. Equal items stay behind the
+    // ones already shown so the view doesn't shuffle.
+    pub fn insert_batch(&mut self, items: &mut Vec<FileResult>) -> Vec<usize> {
+        if matches!(self.key, SortKey::Size | SortKey::Modified) {
+            items.par_iter_mut().filter(|item| !item.metadata_loaded).for_each(|item| {
+                match std::fs::metadata(item.path()) {
+                    Ok(file) => item.set_metadata(file.len(), file.modified().unwrap_or(UNIX_EPOCH)),
+                    Err(_) => item.set_metadata(0, UNIX_EPOCH),
+                }
+            });
+        }
+
+        let mut batch: Vec<((String, SortValue), FileResult)> = if self.key.reads_media() {
+            let values: Vec<SortValue> = items
+                .par_iter()
+                .map(|item| SortValue::Number(media_value(&self.key, read_media_info(item.path()))))
+                .collect();
+            values.into_iter().zip(items.drain(..)).map(|(value, item)| ((self.drive(&item), value), item)).collect()
+        } else {
+            items.drain(..).map(|item| ((self.drive(&item), self.value(&item)), item)).collect()
+        };
+        batch.sort_by(|a, b| self.compare(&a.0, &b.0));
+
+        let mut merged = Vec::with_capacity(self.keys.len() + batch.len());
+        let mut positions = Vec::with_capacity(batch.len());
+        let mut existing = std::mem::take(&mut self.keys).into_iter().peekable();
+        for (value, item) in batch {
+            while let Some(key) = existing.next_if(|key| self.compare(key, &value) != CmpOrdering::Greater) {
+                merged.push(key);
+            }
+            positions.push(merged.len());
+            merged.push(value);
+            items.push(item);
+        }
+        merged.extend(existing);
+        self.keys = merged;
+
+        positions
+    }
+
+    fn drive(&self, item: &FileResult) -> String {
+        if self.by_drive { drive_sort_key(item.path()) } else { String::new() }
+    }
+
+    fn value(&self, item: &FileResult) -> SortValue {
+        match &self.key {
+            SortKey::Text { ignore_case: true } => SortValue::Text((self.text)(item).to_lowercase()),
+            SortKey::Text { ignore_case: false } => SortValue::Text((self.text)(item)),
+            SortKey::Locale(_) => SortValue::Wide((self.text)(item).encode_utf16().collect()),
+            SortKey::Size => SortValue::Size(item.size),
+            SortKey::Modified => SortValue::Modified(item.modified_time),
+            // Read for the whole batch at once in insert_batch
+            SortKey::Duration | SortKey::Bitrate => SortValue::Number(0),
+            SortKey::Rank(ranker) => {
+                let text = (self.text)(item);
+                SortValue::Rank(ranker.score(item.path(), &text), text.to_lowercase())
+            }
+            SortKey::Unsorted => SortValue::Number(0),
+        }
+    }
+
+    // Drive first, in ascending order whichever way the value goes
+    fn compare(&self, a: &(String, SortValue), b: &(String, SortValue)) -> CmpOrdering {
+        a.0.cmp(&b.0).then_with(|| self.compare_values(&a.1, &b.1))
+    }
+
+    fn compare_values(&self, a: &SortValue, b: &SortValue) -> CmpOrdering {
+        let ordering = match (a, b) {
+            (SortValue::Text(a), SortValue::Text(b)) => a.cmp(b),
+            (SortValue::Wide(a), SortValue::Wide(b)) => compare_by_locale(&self.locale, a, b),
+            (SortValue::Size(a), SortValue::Size(b)) => a.cmp(b),
+            (SortValue::Modified(a), SortValue::Modified(b)) => a.cmp(b),
+            (SortValue::Number(a), SortValue::Number(b)) => a.cmp(b),
+            (SortValue::Rank(a_score, a_name), SortValue::Rank(b_score, b_name)) => {
+                compare_rank((*a_score, a_name), (*b_score, b_name))
+            }
+            _ => CmpOrdering::Equal,
+        };
+        if self.descending { ordering.reverse() } else { ordering }
+    }
+}
@@ -0,0 +1,403 @@
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::*,
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Controls::*,
+            Input::KeyboardAndMouse::EnableWindow,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::clipboard::set_clipboard_text;
+use crate::everything_sdk::FileResult;
+use crate::lang::{fill, get_strings};
+use crate::list_stats::{compute_statistics, format_bytes, size_bucket_label, ListStatistics};
+use crate::logger::{log_error, log_info, log_warn};
+use crate::thumbnail::to_wide;
+
+// Posted by the worker with a boxed ListStatistics in WPARAM
+const WM_STATISTICS_COMPUTED: u32 = WM_USER + 127;
+
+const STATS_DASHBOARD_CLASS: &str = "EverythingLikeStatsDashboard";
+
+const DASHBOARD_WIDTH: i32 = 980;
+const DASHBOARD_HEIGHT: i32 = 640;
+const MARGIN: i32 = 10;
+const BUTTON_WIDTH: i32 = 110;
+const BUTTON_HEIGHT: i32 = 26;
+const LIST_WIDTH: i32 = 380;
+const LINE_HEIGHT: i32 = 18;
+
+// Control IDs
+const ID_DASHBOARD_SUMMARY: i32 = 9861;
+const ID_DASHBOARD_EXTENSIONS: i32 = 9862;
+const ID_DASHBOARD_COPY: i32 = 9863;
+const ID_DASHBOARD_EXPORT: i32 = 9864;
+const ID_DASHBOARD_CLOSE: i32 = 9865;
+
+// Extension list columns
+const COLUMN_FILES: i32 = 1;
+const COLUMN_SIZE: i32 = 2;
+const COLUMN_SHARE: i32 = 3;
+
+// Same light blue as the Biggest Files bars
+const BAR_COLOR: COLORREF = COLORREF(0x00F0C080);
+
+struct Dashboard {
+    font: HFONT,
+    list: HWND,
+    statistics: Option<ListStatistics>,
+}
+
+// The window's Dashboard, kept in GWLP_USERDATA
+unsafe fn dashboard(window: HWND) -> Option<&'static mut Dashboard> {
+    let dashboard = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut Dashboard;
+    dashboard.as_mut()
+}
+
+pub fn register_stats_dashboard_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(stats_dashboard_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as isize),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeStatsDashboard"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// Tools → Results Statistics: counts and sizes per extension, a size
+// histogram and a modified-date timeline for `items`. Sizes and dates are
+// read from disk on a worker thread where the results lack them.
+pub fn show_stats_dashboard(owner: HWND, font: HFONT, items: Vec<FileResult>) {
+    unsafe {
+        let strings = get_strings();
+        let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+
+        let window = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            PCWSTR::from_raw(to_wide(STATS_DASHBOARD_CLASS).as_ptr()),
+            PCWSTR::from_raw(to_wide(&strings.stats_dashboard_title).as_ptr()),
+            WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX | WS_CLIPCHILDREN | WS_VISIBLE,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            DASHBOARD_WIDTH,
+            DASHBOARD_HEIGHT,
+            owner,
+            None,
+            instance,
+            None,
+        );
+
+        if window.0 == 0 {
+            log_error("Failed to create statistics window");
+            return;
+        }
+
+        let mut client_rect = RECT::default();
+        let _ = GetClientRect(window, &mut client_rect);
+        let list_y = MARGIN * 2 + LINE_HEIGHT;
+        let button_y = client_rect.bottom - MARGIN - BUTTON_HEIGHT;
+
+        let list = CreateWindowExW(
+            WS_EX_CLIENTEDGE,
+            WC_LISTVIEWW,
+            PCWSTR::null(),
+            WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(LVS_REPORT | LVS_SINGLESEL),
+            MARGIN,
+            list_y,
+            LIST_WIDTH,
+            button_y - MARGIN - list_y,
+            window,
+            HMENU(ID_DASHBOARD_EXTENSIONS as isize),
+            instance,
+            None,
+        );
+        SendMessageW(list, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        SendMessageW(list, LVM_SETEXTENDEDLISTVIEWSTYLE, WPARAM(0), LPARAM((LVS_EX_FULLROWSELECT | LVS_EX_GRIDLINES) as isize));
+
+        let columns = [
+            (&strings.stats_column_extension, LVCFMT_LEFT, LIST_WIDTH - 260),
+            (&strings.stats_column_files, LVCFMT_RIGHT, 70),
+            (&strings.column_size, LVCFMT_RIGHT, 90),
+            (&strings.stats_column_share, LVCFMT_RIGHT, 75),
+        ];
+        for (index, (name, format, column_width)) in columns.iter().enumerate() {
+            let mut name_wide = to_wide(name);
+            let column = LVCOLUMNW {
+                mask: LVCF_TEXT | LVCF_WIDTH | LVCF_FMT,
+                fmt: *format,
+                cx: *column_width,
+                pszText: PWSTR::from_raw(name_wide.as_mut_ptr()),
+                ..Default::default()
+            };
+            SendMessageW(list, LVM_INSERTCOLUMNW, WPARAM(index), LPARAM(&column as *const _ as isize));
+        }
+
+        let summary = fill(&strings.stats_analyzing, &[("count", &items.len().to_string())]);
+        let controls = [
+            (w!("STATIC"), summary, WINDOW_STYLE(0), ID_DASHBOARD_SUMMARY, MARGIN, MARGIN, client_rect.right - MARGIN * 2, LINE_HEIGHT),
+            (w!("BUTTON"), strings.stats_copy.clone(), WS_TABSTOP | WS_DISABLED | WINDOW_STYLE(BS_PUSHBUTTON as u32), ID_DASHBOARD_COPY, MARGIN, button_y, BUTTON_WIDTH, BUTTON_HEIGHT),
+            (w!("BUTTON"), strings.stats_export_csv.clone(), WS_TABSTOP | WS_DISABLED | WINDOW_STYLE(BS_PUSHBUTTON as u32), ID_DASHBOARD_EXPORT, MARGIN * 2 + BUTTON_WIDTH, button_y, BUTTON_WIDTH, BUTTON_HEIGHT),
+            (w!("BUTTON"), strings.stats_close.clone(), WS_TABSTOP | WINDOW_STYLE(BS_DEFPUSHBUTTON as u32), ID_DASHBOARD_CLOSE, client_rect.right - MARGIN - BUTTON_WIDTH, button_y, BUTTON_WIDTH, BUTTON_HEIGHT),
+        ];
+        for (class, text, style, id, x, y, width, height) in controls {
+            let control = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                class,
+                PCWSTR::from_raw(to_wide(&text).as_ptr()),
+                WS_CHILD | WS_VISIBLE | style,
+                x, y, width, height,
+                window,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(control, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        }
+
+        let dashboard = Box::new(Dashboard { font, list, statistics: None });
+        SetWindowLongPtrW(window, GWLP_USERDATA, Box::into_raw(dashboard) as isize);
+
+        log_info(&format!("Computing statistics for {} results", items.len()));
+        std::thread::spawn(move || {
+            let statistics = Box::new(compute_statistics(&items));
+            let statistics_ptr = Box::into_raw(statistics) as usize;
+            if PostMessageW(window, WM_STATISTICS_COMPUTED, WPARAM(statistics_ptr), LPARAM(0)).is_err() {
+                // The window is gone, so nobody will free the statistics
+                drop(Box::from_raw(statistics_ptr as *mut ListStatistics));
+            }
+        });
+    }
+}
+
+unsafe fn set_cell(list: HWND, row: usize, column: i32, text: &str) {
+    let mut text_wide = to_wide(text);
+    let item = LVITEMW {
+        iSubItem: column,
+        pszText: PWSTR::from_raw(text_wide.as_mut_ptr()),
+        ..Default::default()
+    };
+    SendMessageW(list, LVM_SETITEMTEXTW, WPARAM(row), LPARAM(&item as *const _ as isize));
+}
+
+unsafe fn show_statistics(window: HWND, statistics: ListStatistics) {
+    let Some(dashboard) = dashboard(window) else {
+        return;
+    };
+    let strings = get_strings();
+
+    for (row, (extension, count, size)) in statistics.extensions.iter().enumerate() {
+        let label = if extension.is_empty() { strings.stats_no_extension.clone() } else { format!(".{}", extension) };
+        let mut label_wide = to_wide(&label);
+        let item = LVITEMW {
+            mask: LVIF_TEXT,
+            iItem: row as i32,
+            pszText: PWSTR::from_raw(label_wide.as_mut_ptr()),
+            ..Default::default()
+        };
+        SendMessageW(dashboard.list, LVM_INSERTITEMW, WPARAM(0), LPARAM(&item as *const _ as isize));
+        set_cell(dashboard.list, row, COLUMN_FILES, &count.to_string());
+        set_cell(dashboard.list, row, COLUMN_SIZE, &format_bytes(*size, &strings));
+        let share = *size as f64 * 100.0 / statistics.total_size.max(1) as f64;
+        set_cell(dashboard.list, row, COLUMN_SHARE, &format!("{:.1}%", share));
+    }
+
+    let summary = fill(&strings.stats_summary, &[
+        ("count", &statistics.total_files.to_string()),
+        ("size", &format_bytes(statistics.total_size, &strings)),
+        ("missing", &statistics.missing_files.to_string()),
+    ]);
+    let _ = SetWindowTextW(GetDlgItem(window, ID_DASHBOARD_SUMMARY), PCWSTR::from_raw(to_wide(&summary).as_ptr()));
+    EnableWindow(GetDlgItem(window, ID_DASHBOARD_COPY), TRUE);
+    EnableWindow(GetDlgItem(window, ID_DASHBOARD_EXPORT), TRUE);
+
+    dashboard.statistics = Some(statistics);
+    InvalidateRect(window, None, TRUE);
+}
+
+unsafe fn draw_text(hdc: HDC, text: &str, rect: &mut RECT, format: DRAW_TEXT_FORMAT) {
+    let mut text: Vec<u16> = text.encode_utf16().collect();
+    DrawTextW(hdc, &mut text, rect, format | DT_SINGLELINE | DT_VCENTER);
+}
+
+// Draws a titled bar chart into `area`: one bar per entry, as tall as its
+// file count compared with the largest, with the count above it and the
+// label below. Labels are skipped evenly when they would overlap.
+unsafe fn draw_bar_chart(hdc: HDC, area: RECT, title: &str, entries: &[(String, usize, u64)]) {
+    FillRect(hdc, &area, GetSysColorBrush(COLOR_WINDOW));
+    FrameRect(hdc, &area, GetSysColorBrush(COLOR_BTNSHADOW));
+
+    let mut title_rect = RECT { left: area.left + 8, top: area.top + 6, right: area.right - 8, bottom: area.top + 6 + LINE_HEIGHT };
+    draw_text(hdc, title, &mut title_rect, DT_LEFT);
+    if entries.is_empty() {
+        return;
+    }
+
+    // Leave a line above the tallest bar for its count
+    let plot = RECT {
+        left: area.left + 8,
+        top: title_rect.bottom + LINE_HEIGHT,
+        right: area.right - 8,
+        bottom: area.bottom - 6 - LINE_HEIGHT,
+    };
+    let slot = ((plot.right - plot.left) / entries.len() as i32).max(1);
+    let largest = entries.iter().map(|entry| entry.1).max().unwrap_or(0).max(1);
+
+    let label_width = entries
+        .iter()
+        .map(|(label, _, _)| {
+            let mut size = SIZE::default();
+            GetTextExtentPoint32W(hdc, &label.encode_utf16().collect::<Vec<u16>>(), &mut size);
+            size.cx
+        })
+        .max()
+        .unwrap_or(0)
+        + 8;
+    let label_step = ((label_width + slot - 1) / slot).max(1) as usize;
+
+    let bar_brush = CreateSolidBrush(BAR_COLOR);
+    for (index, (label, count, _)) in entries.iter().enumerate() {
+        let left = plot.left + slot * index as i32;
+        let height = ((plot.bottom - plot.top) as i64 * *count as i64 / largest as i64) as i32;
+        let bar = RECT { left: left + slot / 8, top: plot.bottom - height, right: left + slot - slot / 8, bottom: plot.bottom };
+        if height > 0 {
+            FillRect(hdc, &bar, bar_brush);
+        }
+
+        if index % label_step == 0 {
+            let center = left + slot / 2;
+            let mut label_rect = RECT { left: center - label_width / 2, top: plot.bottom + 2, right: center + label_width / 2, bottom: area.bottom - 4 };
+            draw_text(hdc, label, &mut label_rect, DT_CENTER);
+            if *count > 0 {
+                let mut count_rect = RECT { left: label_rect.left, top: bar.top - LINE_HEIGHT, right: label_rect.right, bottom: bar.top };
+                draw_text(hdc, &count.to_string(), &mut count_rect, DT_CENTER);
+            }
+        }
+    }
+    DeleteObject(bar_brush);
+}
+
+// The size histogram above the timeline, right of the extension list
+unsafe fn paint_charts(window: HWND, hdc: HDC) {
+    let Some(dashboard) = dashboard(window) else {
+        return;
+    };
+    let Some(statistics) = &dashboard.statistics else {
+        return;
+    };
+    let strings = get_strings();
+
+    let mut client_rect = RECT::default();
+    let _ = GetClientRect(window, &mut client_rect);
+    let left = MARGIN * 2 + LIST_WIDTH;
+    let top = MARGIN * 2 + LINE_HEIGHT;
+    let bottom = client_rect.bottom - MARGIN * 2 - BUTTON_HEIGHT;
+    let middle = (top + bottom) / 2;
+
+    let old_font = SelectObject(hdc, dashboard.font);
+    SetBkMode(hdc, TRANSPARENT);
+    SetTextColor(hdc, COLORREF(GetSysColor(COLOR_WINDOWTEXT)));
+
+    let sizes: Vec<(String, usize, u64)> = statistics
+        .size_buckets
+        .iter()
+        .enumerate()
+        .map(|(bucket, (count, size))| (size_bucket_label(bucket, &strings), *count, *size))
+        .collect();
+    let histogram_area = RECT { left, top, right: client_rect.right - MARGIN, bottom: middle - MARGIN / 2 };
+    draw_bar_chart(hdc, histogram_area, &strings.stats_by_size, &sizes);
+
+    let (by_month, timeline) = statistics.timeline();
+    let title = if by_month { &strings.stats_by_month } else { &strings.stats_by_year };
+    let timeline_area = RECT { left, top: middle + MARGIN / 2, right: client_rect.right - MARGIN, bottom };
+    draw_bar_chart(hdc, timeline_area, title, &timeline);
+
+    SelectObject(hdc, old_font);
+}
+
+unsafe fn export_csv(window: HWND) {
+    let Some(statistics) = dashboard(window).and_then(|dashboard| dashboard.statistics.as_ref()) else {
+        return;
+    };
+    let strings = get_strings();
+    let filters = [("CSV Files (*.csv)", "csv")];
+    let Some(result) = crate::show_save_file_dialog_with_filters(window, &strings.stats_export_csv, "statistics", &filters, 1, None) else {
+        return;
+    };
+
+    if let Err(e) = std::fs::write(&result.path, statistics.to_csv()) {
+        log_warn(&format!("Failed to export statistics to {}: {}", result.path, e));
+        let message = fill(&strings.error_stats_export, &[("error", &e.to_string())]);
+        MessageBoxW(window, PCWSTR::from_raw(to_wide(&message).as_ptr()), w!("Error"), MB_ICONERROR | MB_OK);
+    }
+}
+
+extern "system" fn stats_dashboard_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_STATISTICS_COMPUTED => {
+                let statistics = *Box::from_raw(wparam.0 as *mut ListStatistics);
+                show_statistics(window, statistics);
+                LRESULT(0)
+            }
+            WM_PAINT => {
+                let mut paint = PAINTSTRUCT::default();
+                let hdc = BeginPaint(window, &mut paint);
+                paint_charts(window, hdc);
+                EndPaint(window, &paint);
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                match (wparam.0 & 0xFFFF) as i32 {
+                    ID_DASHBOARD_COPY => {
+                        if let Some(statistics) = dashboard(window).and_then(|dashboard| dashboard.statistics.as_ref()) {
+                            set_clipboard_text(window, &statistics.to_report(&get_strings()));
+                        }
+                    }
+                    ID_DASHBOARD_EXPORT => export_csv(window),
+                    ID_DASHBOARD_CLOSE => {
+                        let _ = DestroyWindow(window);
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(window);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                let dashboard = SetWindowLongPtrW(window, GWLP_USERDATA, 0) as *mut Dashboard;
+                if !dashboard.is_null() {
+                    drop(Box::from_raw(dashboard));
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
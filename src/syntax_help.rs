@@ -0,0 +1,262 @@
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{HBRUSH, HFONT, COLOR_BTNFACE},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Controls::*,
+            Input::KeyboardAndMouse::{SetFocus, VK_ESCAPE},
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::lang::{get_strings, LanguageStrings};
+use crate::logger::log_error;
+use crate::thumbnail::to_wide;
+
+// Posted to the owner with a boxed String in WPARAM to put in the search box
+pub const WM_SYNTAX_INSERT: u32 = WM_USER + 145;
+
+const SYNTAX_HELP_WINDOW_CLASS: &str = "EverythingLikeSyntaxHelp";
+
+const SYNTAX_HELP_WINDOW_WIDTH: i32 = 640;
+const SYNTAX_HELP_WINDOW_HEIGHT: i32 = 420;
+
+// Control IDs
+const ID_SYNTAX_LIST: i32 = 9871;
+
+// List columns
+const COLUMN_SYNTAX: i32 = 0;
+const COLUMN_EXAMPLE: i32 = 1;
+const COLUMN_DESCRIPTION: i32 = 2;
+
+// (text inserted, example, description) of each operator, in list order
+fn syntax_entries(strings: &LanguageStrings) -> Vec<(&'static str, &'static str, &str)> {
+    vec![
+        ("ext:", "ext:jpg;png", &strings.syntax_ext),
+        ("size:", "size:>10mb", &strings.syntax_size),
+        ("dm:", "dm:lastweek", &strings.syntax_dm),
+        ("dc:", "dc:2024", &strings.syntax_dc),
+        ("*", "report*.docx", &strings.syntax_star),
+        ("?", "IMG_????.jpg", &strings.syntax_question),
+        ("regex:", "regex:^\\d+\\.txt$", &strings.syntax_regex),
+        ("path:", "path:projects\\src", &strings.syntax_path),
+        ("file:", "file:notes", &strings.syntax_file),
+        ("folder:", "folder:backup", &strings.syntax_folder),
+        ("\"\"", "\"annual report\"", &strings.syntax_quotes),
+        ("|", "jpg | png", &strings.syntax_or),
+        ("!", "!temp", &strings.syntax_not),
+        ("<>", "<jpg | png> holiday", &strings.syntax_group),
+        ("case:", "case:README", &strings.syntax_case),
+        ("ww:", "ww:log", &strings.syntax_whole_word),
+    ]
+}
+
+struct SyntaxHelp {
+    owner: HWND,
+    list: HWND,
+}
+
+// The window's SyntaxHelp, kept in GWLP_USERDATA
+unsafe fn syntax_help(window: HWND) -> Option<&'static mut SyntaxHelp> {
+    let syntax_help = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut SyntaxHelp;
+    syntax_help.as_mut()
+}
+
+pub fn register_syntax_help_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(syntax_help_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as isize),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeSyntaxHelp"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// The "?" button beside the search box: a cheat sheet of the search syntax
+// below `anchor`, in screen coordinates. Clicking an operator puts it in the
+// search box; the sheet stays open until closed. Clicking the button again
+// closes it.
+pub fn toggle_syntax_help(owner: HWND, font: HFONT, anchor: POINT) {
+    unsafe {
+        // Only one cheat sheet is open at a time
+        let existing = FindWindowW(PCWSTR::from_raw(to_wide(SYNTAX_HELP_WINDOW_CLASS).as_ptr()), None);
+        if existing.0 != 0 {
+            let _ = DestroyWindow(existing);
+            return;
+        }
+
+        let strings = get_strings();
+        let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+
+        // Right-aligned with the button, kept on the screen
+        let screen_width = GetSystemMetrics(SM_CXSCREEN);
+        let x = (anchor.x - SYNTAX_HELP_WINDOW_WIDTH).clamp(0, (screen_width - SYNTAX_HELP_WINDOW_WIDTH).max(0));
+
+        let window = CreateWindowExW(
+            WS_EX_TOOLWINDOW,
+            PCWSTR::from_raw(to_wide(SYNTAX_HELP_WINDOW_CLASS).as_ptr()),
+            PCWSTR::from_raw(to_wide(&strings.syntax_help_title).as_ptr()),
+            WS_POPUP | WS_CAPTION | WS_SYSMENU | WS_VISIBLE,
+            x,
+            anchor.y,
+            SYNTAX_HELP_WINDOW_WIDTH,
+            SYNTAX_HELP_WINDOW_HEIGHT,
+            owner,
+            None,
+            instance,
+            None,
+        );
+
+        if window.0 == 0 {
+            log_error("Failed to create syntax help window");
+            return;
+        }
+
+        let mut client_rect = RECT::default();
+        let _ = GetClientRect(window, &mut client_rect);
+
+        let list = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            WC_LISTVIEWW,
+            PCWSTR::null(),
+            WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(LVS_REPORT | LVS_SINGLESEL | LVS_SHOWSELALWAYS),
+            0,
+            0,
+            client_rect.right,
+            client_rect.bottom,
+            window,
+            HMENU(ID_SYNTAX_LIST as isize),
+            instance,
+            None,
+        );
+        SendMessageW(list, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        SendMessageW(list, LVM_SETEXTENDEDLISTVIEWSTYLE, WPARAM(0), LPARAM((LVS_EX_FULLROWSELECT | LVS_EX_DOUBLEBUFFER) as isize));
+
+        let columns = [
+            (&strings.syntax_help_operator, 80),
+            (&strings.syntax_help_example, 170),
+            (&strings.syntax_help_description, client_rect.right - 274),
+        ];
+        for (index, (name, column_width)) in columns.iter().enumerate() {
+            let mut name_wide = to_wide(name);
+            let column = LVCOLUMNW {
+                mask: LVCF_TEXT | LVCF_WIDTH,
+                cx: *column_width,
+                pszText: PWSTR::from_raw(name_wide.as_mut_ptr()),
+                ..Default::default()
+            };
+            SendMessageW(list, LVM_INSERTCOLUMNW, WPARAM(index), LPARAM(&column as *const _ as isize));
+        }
+
+        for (row, (syntax, example, description)) in syntax_entries(&strings).into_iter().enumerate() {
+            let mut syntax_wide = to_wide(syntax);
+            let item = LVITEMW {
+                mask: LVIF_TEXT,
+                iItem: row as i32,
+                iSubItem: COLUMN_SYNTAX,
+                pszText: PWSTR::from_raw(syntax_wide.as_mut_ptr()),
+                ..Default::default()
+            };
+            SendMessageW(list, LVM_INSERTITEMW, WPARAM(0), LPARAM(&item as *const _ as isize));
+            set_cell(list, row, COLUMN_EXAMPLE, example);
+            set_cell(list, row, COLUMN_DESCRIPTION, description);
+        }
+
+        let syntax_help = Box::new(SyntaxHelp { owner, list });
+        SetWindowLongPtrW(window, GWLP_USERDATA, Box::into_raw(syntax_help) as isize);
+        let _ = SetFocus(list);
+    }
+}
+
+unsafe fn set_cell(list: HWND, row: usize, column: i32, text: &str) {
+    let mut text_wide = to_wide(text);
+    let item = LVITEMW {
+        iSubItem: column,
+        pszText: PWSTR::from_raw(text_wide.as_mut_ptr()),
+        ..Default::default()
+    };
+    SendMessageW(list, LVM_SETITEMTEXTW, WPARAM(row), LPARAM(&item as *const _ as isize));
+}
+
+// Has the owner put the operator of `row` in the search box
+unsafe fn insert_row(window: HWND, row: usize) {
+    let syntax = syntax_entries(&get_strings()).get(row).map(|(syntax, _, _)| *syntax);
+    let (Some(syntax_help), Some(syntax)) = (syntax_help(window), syntax) else {
+        return;
+    };
+    let syntax_ptr = Box::into_raw(Box::new(syntax.to_string())) as usize;
+    if PostMessageW(syntax_help.owner, WM_SYNTAX_INSERT, WPARAM(syntax_ptr), LPARAM(0)).is_err() {
+        drop(Box::from_raw(syntax_ptr as *mut String));
+    }
+}
+
+extern "system" fn syntax_help_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_NOTIFY => {
+                let header = &*(lparam.0 as *const NMHDR);
+                if header.idFrom == ID_SYNTAX_LIST as usize {
+                    match header.code {
+                        NM_CLICK => {
+                            let activate = &*(lparam.0 as *const NMITEMACTIVATE);
+                            if activate.iItem >= 0 {
+                                insert_row(window, activate.iItem as usize);
+                            }
+                        }
+                        NM_RETURN => {
+                            if let Some(syntax_help) = syntax_help(window) {
+                                let row = SendMessageW(syntax_help.list, LVM_GETNEXTITEM, WPARAM(usize::MAX), LPARAM(LVNI_SELECTED as isize)).0;
+                                if row >= 0 {
+                                    insert_row(window, row as usize);
+                                }
+                            }
+                        }
+                        LVN_KEYDOWN => {
+                            let key = &*(lparam.0 as *const NMLVKEYDOWN);
+                            if key.wVKey == VK_ESCAPE.0 {
+                                let _ = DestroyWindow(window);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(window);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                let syntax_help = SetWindowLongPtrW(window, GWLP_USERDATA, 0) as *mut SyntaxHelp;
+                if !syntax_help.is_null() {
+                    drop(Box::from_raw(syntax_help));
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
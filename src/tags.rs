@@ -0,0 +1,223 @@
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use crate::config::get_config_dir;
+use crate::logger::{log_info, log_warn};
+
+// Colors of the label dots (0x00BBGGRR), handed out to new tags in turn:
+// red, orange, yellow, green, blue, purple and gray
+pub const TAG_COLORS: [u32; 7] = [
+    0x003C3CE0,
+    0x00288CF0,
+    0x0020C8E8,
+    0x0050B040,
+    0x00D08030,
+    0x00B05090,
+    0x00909090,
+];
+
+// Maximum number of tags offered in the Tags menu
+pub const MAX_TAGS: usize = 30;
+
+// Stars a file can be given
+pub const MAX_RATING: u8 = 5;
+
+#[derive(Clone)]
+pub struct Tag {
+    pub name: String,
+    pub color: u32,
+}
+
+// Tags and star ratings given to files, kept in tags.db next to
+// config.json. Everything is also held in memory, since the list looks tags
+// up for every row it draws.
+pub struct TagStore {
+    connection: Connection,
+    tags: Vec<Tag>,
+    // (path as tagged, tag names) per lowercased path
+    files: HashMap<String, (String, Vec<String>)>,
+    // (path as rated, stars) per lowercased path
+    ratings: HashMap<String, (String, u8)>,
+}
+
+impl TagStore {
+    // Opens the database, creating it on first use. Without one, tags
+    // still work for this run but aren't saved.
+    pub fn open() -> Self {
+        let connection = get_config_dir()
+            .map_err(|e| e.to_string())
+            .and_then(|dir| Connection::open(dir.join("tags.db")).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                log_warn(&format!("Failed to open the tag database, tags won't be saved: {}", e));
+                Connection::open_in_memory().expect("in-memory database")
+            });
+
+        let mut store = TagStore { connection, tags: Vec::new(), files: HashMap::new(), ratings: HashMap::new() };
+        if let Err(e) = store.load() {
+            log_warn(&format!("Failed to read tags: {}", e));
+        }
+        log_info(&format!("Loaded {} tags on {} files and {} ratings", store.tags.len(), store.files.len(), store.ratings.len()));
+        store
+    }
+
+    // A copy of the tags and ratings for a search on another thread.
+    // Changes made to it aren't saved.
+    pub fn snapshot(&self) -> Self {
+        TagStore {
+            connection: Connection::open_in_memory().expect("in-memory database"),
+            tags: self.tags.clone(),
+            files: self.files.clone(),
+            ratings: self.ratings.clone(),
+        }
+    }
+
+    fn load(&mut self) -> rusqlite::Result<()> {
+        self.connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS tags (name TEXT PRIMARY KEY COLLATE NOCASE, color INTEGER NOT NULL);
+             CREATE TABLE IF NOT EXISTS file_tags (
+                 path TEXT NOT NULL COLLATE NOCASE,
+                 tag TEXT NOT NULL COLLATE NOCASE,
+                 PRIMARY KEY (path, tag)
+             );
+             CREATE TABLE IF NOT EXISTS ratings (path TEXT PRIMARY KEY COLLATE NOCASE, rating INTEGER NOT NULL);",
+        )?;
+
+        let mut statement = self.connection.prepare("SELECT name, color FROM tags ORDER BY rowid")?;
+        self.tags = statement
+            .query_map([], |row| Ok(Tag { name: row.get(0)?, color: row.get(1)? }))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let mut statement = self.connection.prepare("SELECT path, tag FROM file_tags ORDER BY rowid")?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        for row in rows {
+            let (path, tag) = row?;
+            self.files
+                .entry(path.to_lowercase())
+                .or_insert_with(|| (path.clone(), Vec::new()))
+                .1
+                .push(tag);
+        }
+
+        let mut statement = self.connection.prepare("SELECT path, rating FROM ratings")?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, u8>(1)?)))?;
+        for row in rows {
+            let (path, rating) = row?;
+            self.ratings.insert(path.to_lowercase(), (path, rating.min(MAX_RATING)));
+        }
+        Ok(())
+    }
+
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    pub fn tags_of(&self, path: &str) -> &[String] {
+        self.files.get(&path.to_lowercase()).map_or(&[], |(_, tags)| tags.as_slice())
+    }
+
+    pub fn color_of(&self, name: &str) -> u32 {
+        self.tags
+            .iter()
+            .find(|tag| tag.name.eq_ignore_ascii_case(name))
+            .map_or(TAG_COLORS[TAG_COLORS.len() - 1], |tag| tag.color)
+    }
+
+    // Stars given to `path`, 0 when it has none
+    pub fn rating_of(&self, path: &str) -> u8 {
+        self.ratings.get(&path.to_lowercase()).map_or(0, |(_, rating)| *rating)
+    }
+
+    // Every tagged or rated path, as it was first tagged or rated
+    pub fn tagged_or_rated_paths(&self) -> Vec<String> {
+        let rated_only = self.ratings.iter().filter(|(key, _)| !self.files.contains_key(*key));
+        self.files
+            .values()
+            .map(|(path, _)| path.clone())
+            .chain(rated_only.map(|(_, (path, _))| path.clone()))
+            .collect()
+    }
+
+    // Gives `paths` the stars; 0 takes their rating away
+    pub fn set_rating(&mut self, paths: &[String], rating: u8) -> Result<(), String> {
+        let rating = rating.min(MAX_RATING);
+        let transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+        for path in paths {
+            if rating == 0 {
+                transaction.execute("DELETE FROM ratings WHERE path = ?1", params![path])
+            } else {
+                transaction.execute("INSERT OR REPLACE INTO ratings (path, rating) VALUES (?1, ?2)", params![path, rating])
+            }
+            .map_err(|e| e.to_string())?;
+        }
+        transaction.commit().map_err(|e| e.to_string())?;
+
+        for path in paths {
+            if rating == 0 {
+                self.ratings.remove(&path.to_lowercase());
+            } else {
+                self.ratings.insert(path.to_lowercase(), (path.clone(), rating));
+            }
+        }
+        Ok(())
+    }
+
+    // Gives `paths` the tag, creating it with the next color if it is new
+    pub fn add_tag(&mut self, paths: &[String], name: &str) -> Result<(), String> {
+        let name = match self.tags.iter().find(|tag| tag.name.eq_ignore_ascii_case(name)) {
+            Some(tag) => tag.name.clone(),
+            None => {
+                let color = TAG_COLORS[self.tags.len() % TAG_COLORS.len()];
+                self.connection
+                    .execute("INSERT INTO tags (name, color) VALUES (?1, ?2)", params![name, color])
+                    .map_err(|e| e.to_string())?;
+                self.tags.push(Tag { name: name.to_string(), color });
+                name.to_string()
+            }
+        };
+
+        let transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+        for path in paths {
+            transaction
+                .execute("INSERT OR IGNORE INTO file_tags (path, tag) VALUES (?1, ?2)", params![path, name])
+                .map_err(|e| e.to_string())?;
+        }
+        transaction.commit().map_err(|e| e.to_string())?;
+
+        for path in paths {
+            let (_, tags) = self.files.entry(path.to_lowercase()).or_insert_with(|| (path.clone(), Vec::new()));
+            if !tags.iter().any(|tag| tag.eq_ignore_ascii_case(&name)) {
+                tags.push(name.clone());
+            }
+        }
+        Ok(())
+    }
+
+    // Takes the tag off `paths`, or every tag when `name` is None. A tag no
+    // file carries any more is forgotten.
+    pub fn remove_tag(&mut self, paths: &[String], name: Option<&str>) -> Result<(), String> {
+        let transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+        for path in paths {
+            match name {
+                Some(name) => transaction.execute("DELETE FROM file_tags WHERE path = ?1 AND tag = ?2", params![path, name]),
+                None => transaction.execute("DELETE FROM file_tags WHERE path = ?1", params![path]),
+            }
+            .map_err(|e| e.to_string())?;
+        }
+        transaction
+            .execute("DELETE FROM tags WHERE name NOT IN (SELECT tag FROM file_tags)", [])
+            .map_err(|e| e.to_string())?;
+        transaction.commit().map_err(|e| e.to_string())?;
+
+        for path in paths {
+            let key = path.to_lowercase();
+            if let Some((_, tags)) = self.files.get_mut(&key) {
+                tags.retain(|tag| name.map_or(false, |name| !tag.eq_ignore_ascii_case(name)));
+                if tags.is_empty() {
+                    self.files.remove(&key);
+                }
+            }
+        }
+        let files = &self.files;
+        self.tags.retain(|tag| files.values().any(|(_, tags)| tags.iter().any(|name| name.eq_ignore_ascii_case(&tag.name))));
+        Ok(())
+    }
+}
@@ -14,6 +14,9 @@ use rayon::ThreadPool;
 use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
 use std::collections::{HashSet, HashMap};
 use crate::config::{ThumbnailStrategy, ThumbnailBackground};
+use crate::logger::log_debug;
+use crate::long_paths::shell_path;
+use crate::network_paths::{is_network_path, throttled};
 
 // Custom messages for thumbnail system
 pub const WM_THUMBNAIL_READY: u32 = 0x0400 + 2; // WM_APP + 2
@@ -37,9 +40,9 @@ pub struct ThumbnailTaskManager {
 }
 
 impl ThumbnailTaskManager {
-    pub fn new(window_handle: HWND) -> Self {
+    pub fn new(window_handle: HWND, num_threads: usize) -> Self {
         let thread_pool = rayon::ThreadPoolBuilder::new()
-            .num_threads(4) // Use 4 background threads for thumbnail generation
+            .num_threads(num_threads.max(1)) // Background threads for thumbnail generation
             .build()
             .expect("Failed to create thread pool");
 
@@ -52,7 +55,7 @@ impl ThumbnailTaskManager {
     }
 
     pub fn cancel_all_tasks(&self) {
-        println!("Cancelling all thumbnail tasks");
+        log_debug("Cancelling all thumbnail tasks");
         
         // Cancel all existing tasks
         if let Ok(tokens) = self.cancellation_tokens.lock() {
@@ -130,8 +133,11 @@ impl ThumbnailTaskManager {
                 return;
             }
             
-            // Generate thumbnail
-            if let Some(thumbnail) = get_shell_thumbnail(&request_clone.file_path, request_clone.size, request_clone.background) {
+            // Generate thumbnail; files on an offline share keep the placeholder
+            let thumbnail = throttled(&request_clone.file_path, || {
+                get_shell_thumbnail(&request_clone.file_path, request_clone.size, request_clone.background)
+            });
+            if let Some(thumbnail) = thumbnail.flatten() {
                 // Check cancellation again before posting result
                 if !request_clone.cancellation_token.load(Ordering::Relaxed) {
                     unsafe {
@@ -177,6 +183,7 @@ impl ThumbnailTaskManager {
         total_items: usize,
         list_data: &[crate::everything_sdk::FileResult],
         selected_view_size: u32,
+        skip_network: bool,
     ) {
         // Compute desired set based on strategy
         let desired_set: HashSet<usize> = match strategy {
@@ -213,11 +220,15 @@ impl ThumbnailTaskManager {
 
         // Queue new tasks for desired items not already queued
         for &index in &desired_set {
-            if !current_queued.contains(&index) && index < list_data.len() {
+            // Rows of a page still being read are queued once it is in
+            if !current_queued.contains(&index) && index < list_data.len() && !list_data[index].is_placeholder() {
+                if skip_network && is_network_path(list_data[index].path()) {
+                    continue;
+                }
                 let cancellation_token = Arc::new(AtomicBool::new(false));
                 let request = ThumbnailRequest {
                     item_index: index,
-                    file_path: list_data[index].path.clone(),
+                    file_path: list_data[index].path().to_string(),
                     size: selected_view_size,
                     background: background,
                     cancellation_token,
@@ -226,12 +237,12 @@ impl ThumbnailTaskManager {
             }
         }
 
-        println!(
+        log_debug(&format!(
             "Recomputed thumbnail queue - Strategy: {:?}, Desired: {}, Currently queued: {}",
             strategy,
             desired_set.len(),
             self.get_queued_count()
-        );
+        ));
     }
 
     pub fn get_queued_count(&self) -> usize {
@@ -246,7 +257,7 @@ impl ThumbnailTaskManager {
 pub fn get_shell_thumbnail(path: &str, size: u32, background: ThumbnailBackground) -> Option<HBITMAP> {
     unsafe {
         // Convert path to wide string
-        let path_wide: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
+        let path_wide = to_wide(&shell_path(path));
         
         // Create shell item from path
         let shell_item: IShellItem = match SHCreateItemFromParsingName(
@@ -0,0 +1,501 @@
+use std::cmp::Reverse;
+use std::time::{SystemTime, UNIX_EPOCH};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate};
+use rayon::prelude::*;
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{InvalidateRect, COLOR_BTNFACE, HBRUSH, HFONT},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Controls::*,
+            Shell::ShellExecuteW,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::everything_sdk::FileResult;
+use crate::lang::{fill, get_strings, LanguageStrings};
+use crate::list_stats::format_bytes;
+use crate::logger::{log_error, log_info};
+use crate::thumbnail::to_wide;
+
+// Posted by the worker with a boxed Vec<TimelineEntry> in WPARAM
+const WM_TIMELINE_LOADED: u32 = WM_USER + 128;
+
+const TIMELINE_WINDOW_CLASS: &str = "EverythingLikeTimeline";
+
+const TIMELINE_WINDOW_WIDTH: i32 = 900;
+const TIMELINE_WINDOW_HEIGHT: i32 = 600;
+const MARGIN: i32 = 10;
+const BUTTON_WIDTH: i32 = 80;
+const BUTTON_HEIGHT: i32 = 26;
+const JUMP_LIST_WIDTH: i32 = 180;
+
+// Control IDs
+const ID_TIMELINE_DATE: i32 = 9871;
+const ID_TIMELINE_STATUS: i32 = 9872;
+const ID_TIMELINE_JUMP: i32 = 9873;
+const ID_TIMELINE_LIST: i32 = 9874;
+const ID_TIMELINE_CLOSE: i32 = 9875;
+
+// List view columns
+const COLUMN_FOLDER: i32 = 1;
+const COLUMN_DATE: i32 = 2;
+const COLUMN_SIZE: i32 = 3;
+
+// A result with both of its dates, read from disk
+struct TimelineEntry {
+    path: String,
+    size: u64,
+    is_folder: bool,
+    modified: Option<SystemTime>,
+    created: Option<SystemTime>,
+}
+
+// The groups, newest first. Years count down, so the older a year the
+// later it comes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum DateBucket {
+    Today,
+    Yesterday,
+    ThisWeek,
+    ThisMonth,
+    Year(Reverse<i32>),
+    Unknown,
+}
+
+impl DateBucket {
+    fn of(time: Option<SystemTime>, today: NaiveDate) -> Self {
+        let Some(date) = time.map(|time| DateTime::<Local>::from(time).date_naive()) else {
+            return DateBucket::Unknown;
+        };
+        let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+
+        // Dates in the future count as today
+        if date >= today {
+            DateBucket::Today
+        } else if date == today - Duration::days(1) {
+            DateBucket::Yesterday
+        } else if date >= monday {
+            DateBucket::ThisWeek
+        } else if date.year() == today.year() && date.month() == today.month() {
+            DateBucket::ThisMonth
+        } else {
+            DateBucket::Year(Reverse(date.year()))
+        }
+    }
+
+    fn label(self, strings: &LanguageStrings) -> String {
+        match self {
+            DateBucket::Today => strings.time_today.clone(),
+            DateBucket::Yesterday => strings.time_yesterday.clone(),
+            DateBucket::ThisWeek => strings.timeline_this_week.clone(),
+            DateBucket::ThisMonth => strings.timeline_this_month.clone(),
+            DateBucket::Year(Reverse(year)) => year.to_string(),
+            DateBucket::Unknown => strings.timeline_unknown.clone(),
+        }
+    }
+}
+
+struct Timeline {
+    list: HWND,
+    jump: HWND,
+    entries: Vec<TimelineEntry>,
+    // Entry indexes per bucket, newest first, in list order
+    groups: Vec<(DateBucket, Vec<usize>)>,
+    // Entry index of each list row
+    rows: Vec<usize>,
+}
+
+// The window's Timeline, kept in GWLP_USERDATA
+unsafe fn timeline(window: HWND) -> Option<&'static mut Timeline> {
+    let timeline = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut Timeline;
+    timeline.as_mut()
+}
+
+pub fn register_timeline_window_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(timeline_window_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as isize),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeTimeline"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// Tools → Timeline: `items` grouped into Today, Yesterday, This week, This
+// month and then one group per year, by modified or created date. The list
+// on the left jumps to a group. Dates are read on a worker thread.
+pub fn show_timeline(owner: HWND, font: HFONT, items: Vec<FileResult>) {
+    unsafe {
+        let strings = get_strings();
+        let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+
+        let window = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            PCWSTR::from_raw(to_wide(TIMELINE_WINDOW_CLASS).as_ptr()),
+            PCWSTR::from_raw(to_wide(&strings.timeline_title).as_ptr()),
+            WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX | WS_VISIBLE,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            TIMELINE_WINDOW_WIDTH,
+            TIMELINE_WINDOW_HEIGHT,
+            owner,
+            None,
+            instance,
+            None,
+        );
+
+        if window.0 == 0 {
+            log_error("Failed to create timeline window");
+            return;
+        }
+
+        let mut client_rect = RECT::default();
+        let _ = GetClientRect(window, &mut client_rect);
+        let label_width = 60;
+        let combo_width = 200;
+        let status_x = MARGIN * 2 + label_width + combo_width;
+        let list_x = MARGIN * 2 + JUMP_LIST_WIDTH;
+        let list_y = MARGIN * 2 + BUTTON_HEIGHT;
+        let list_width = client_rect.right - MARGIN - list_x;
+        let button_y = client_rect.bottom - MARGIN - BUTTON_HEIGHT;
+
+        let list = CreateWindowExW(
+            WS_EX_CLIENTEDGE,
+            WC_LISTVIEWW,
+            PCWSTR::null(),
+            WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(LVS_REPORT | LVS_SINGLESEL | LVS_SHOWSELALWAYS),
+            list_x,
+            list_y,
+            list_width,
+            button_y - MARGIN - list_y,
+            window,
+            HMENU(ID_TIMELINE_LIST as isize),
+            instance,
+            None,
+        );
+        SendMessageW(list, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        SendMessageW(list, LVM_SETEXTENDEDLISTVIEWSTYLE, WPARAM(0), LPARAM((LVS_EX_FULLROWSELECT | LVS_EX_DOUBLEBUFFER) as isize));
+        SendMessageW(list, LVM_ENABLEGROUPVIEW, WPARAM(1), LPARAM(0));
+
+        let columns = [
+            (&strings.column_name, 220),
+            (&strings.column_path, list_width - 480),
+            (&strings.timeline_modified, 140),
+            (&strings.column_size, 90),
+        ];
+        for (index, (name, column_width)) in columns.iter().enumerate() {
+            let mut name_wide = to_wide(name);
+            let column = LVCOLUMNW {
+                mask: LVCF_TEXT | LVCF_WIDTH,
+                cx: *column_width,
+                pszText: PWSTR::from_raw(name_wide.as_mut_ptr()),
+                ..Default::default()
+            };
+            SendMessageW(list, LVM_INSERTCOLUMNW, WPARAM(index), LPARAM(&column as *const _ as isize));
+        }
+
+        let loading = fill(&strings.timeline_loading, &[("count", &items.len().to_string())]);
+        let controls = [
+            (w!("STATIC"), strings.timeline_date.clone(), WINDOW_STYLE(0), WINDOW_EX_STYLE::default(), -1, MARGIN, MARGIN + 4, label_width, 20),
+            (w!("COMBOBOX"), String::new(), WS_TABSTOP | WS_VSCROLL | WINDOW_STYLE(CBS_DROPDOWNLIST as u32), WINDOW_EX_STYLE::default(), ID_TIMELINE_DATE, MARGIN + label_width, MARGIN, combo_width, 200),
+            (w!("STATIC"), loading, WINDOW_STYLE(0), WINDOW_EX_STYLE::default(), ID_TIMELINE_STATUS, status_x, MARGIN + 4, client_rect.right - MARGIN - status_x, 20),
+            (w!("LISTBOX"), String::new(), WS_TABSTOP | WS_VSCROLL | WINDOW_STYLE((LBS_NOTIFY | LBS_NOINTEGRALHEIGHT) as u32), WS_EX_CLIENTEDGE, ID_TIMELINE_JUMP, MARGIN, list_y, JUMP_LIST_WIDTH, button_y - MARGIN - list_y),
+            (w!("BUTTON"), strings.stats_close.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), WINDOW_EX_STYLE::default(), ID_TIMELINE_CLOSE, client_rect.right - MARGIN - BUTTON_WIDTH, button_y, BUTTON_WIDTH, BUTTON_HEIGHT),
+        ];
+        for (class, text, style, ex_style, id, x, y, width, height) in controls {
+            let control = CreateWindowExW(
+                ex_style,
+                class,
+                PCWSTR::from_raw(to_wide(&text).as_ptr()),
+                WS_CHILD | WS_VISIBLE | style,
+                x, y, width, height,
+                window,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(control, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        }
+
+        let combo = GetDlgItem(window, ID_TIMELINE_DATE);
+        for label in [&strings.timeline_modified, &strings.timeline_created] {
+            SendMessageW(combo, CB_ADDSTRING, WPARAM(0), LPARAM(to_wide(label).as_ptr() as isize));
+        }
+        SendMessageW(combo, CB_SETCURSEL, WPARAM(0), LPARAM(0));
+
+        let timeline = Box::new(Timeline {
+            list,
+            jump: GetDlgItem(window, ID_TIMELINE_JUMP),
+            entries: Vec::new(),
+            groups: Vec::new(),
+            rows: Vec::new(),
+        });
+        SetWindowLongPtrW(window, GWLP_USERDATA, Box::into_raw(timeline) as isize);
+
+        log_info(&format!("Reading dates of {} results for the timeline", items.len()));
+        std::thread::spawn(move || {
+            let entries = Box::new(load_entries(&items));
+            let entries_ptr = Box::into_raw(entries) as usize;
+            if PostMessageW(window, WM_TIMELINE_LOADED, WPARAM(entries_ptr), LPARAM(0)).is_err() {
+                // The window is gone, so nobody will free the entries
+                drop(Box::from_raw(entries_ptr as *mut Vec<TimelineEntry>));
+            }
+        });
+    }
+}
+
+// Reads both dates of every result. Results that can't be read, such as
+// remote ones, keep the modified time they came with.
+fn load_entries(items: &[FileResult]) -> Vec<TimelineEntry> {
+    let known = |time: SystemTime| (time != UNIX_EPOCH).then_some(time);
+    items
+        .par_iter()
+        .map(|item| match std::fs::metadata(item.path()) {
+            Ok(metadata) => TimelineEntry {
+                path: item.path().to_string(),
+                size: metadata.len(),
+                is_folder: metadata.is_dir(),
+                modified: metadata.modified().ok().and_then(known),
+                created: metadata.created().ok().and_then(known),
+            },
+            Err(_) => TimelineEntry {
+                path: item.path().to_string(),
+                size: item.size,
+                is_folder: false,
+                modified: known(item.modified_time),
+                created: None,
+            },
+        })
+        .collect()
+}
+
+// Sorts the entries into buckets by the chosen date, newest first in each
+fn group_entries(entries: &[TimelineEntry], by_created: bool) -> Vec<(DateBucket, Vec<usize>)> {
+    let date = |entry: &TimelineEntry| if by_created { entry.created } else { entry.modified };
+    let today = Local::now().date_naive();
+
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by_key(|&index| Reverse(date(&entries[index])));
+
+    let mut groups: Vec<(DateBucket, Vec<usize>)> = Vec::new();
+    for index in order {
+        let bucket = DateBucket::of(date(&entries[index]), today);
+        match groups.iter_mut().find(|(existing, _)| *existing == bucket) {
+            Some((_, members)) => members.push(index),
+            None => groups.push((bucket, vec![index])),
+        }
+    }
+    groups.sort_by_key(|(bucket, _)| *bucket);
+    groups
+}
+
+unsafe fn set_cell(list: HWND, row: usize, column: i32, text: &str) {
+    let mut text_wide = to_wide(text);
+    let item = LVITEMW {
+        iSubItem: column,
+        pszText: PWSTR::from_raw(text_wide.as_mut_ptr()),
+        ..Default::default()
+    };
+    SendMessageW(list, LVM_SETITEMTEXTW, WPARAM(row), LPARAM(&item as *const _ as isize));
+}
+
+// Fills the list and the jump list with the groups for the chosen date
+unsafe fn show_groups(window: HWND) {
+    let Some(timeline) = timeline(window) else {
+        return;
+    };
+    let strings = get_strings();
+    let by_created = SendMessageW(GetDlgItem(window, ID_TIMELINE_DATE), CB_GETCURSEL, WPARAM(0), LPARAM(0)).0 == 1;
+    timeline.groups = group_entries(&timeline.entries, by_created);
+    timeline.rows.clear();
+
+    SendMessageW(timeline.list, WM_SETREDRAW, WPARAM(0), LPARAM(0));
+    SendMessageW(timeline.list, LVM_DELETEALLITEMS, WPARAM(0), LPARAM(0));
+    SendMessageW(timeline.list, LVM_REMOVEALLGROUPS, WPARAM(0), LPARAM(0));
+    SendMessageW(timeline.jump, LB_RESETCONTENT, WPARAM(0), LPARAM(0));
+
+    let mut date_label = to_wide(if by_created { &strings.timeline_created } else { &strings.timeline_modified });
+    let column = LVCOLUMNW {
+        mask: LVCF_TEXT,
+        pszText: PWSTR::from_raw(date_label.as_mut_ptr()),
+        ..Default::default()
+    };
+    SendMessageW(timeline.list, LVM_SETCOLUMNW, WPARAM(COLUMN_DATE as usize), LPARAM(&column as *const _ as isize));
+
+    for (group_id, (bucket, members)) in timeline.groups.iter().enumerate() {
+        let header = fill(&strings.timeline_group, &[("name", &bucket.label(&strings)), ("count", &members.len().to_string())]);
+        let mut header_wide = to_wide(&header);
+        let group = LVGROUP {
+            cbSize: std::mem::size_of::<LVGROUP>() as u32,
+            mask: LVGF_HEADER | LVGF_GROUPID | LVGF_STATE,
+            pszHeader: PWSTR::from_raw(header_wide.as_mut_ptr()),
+            iGroupId: group_id as i32,
+            stateMask: LVGS_COLLAPSIBLE,
+            state: LVGS_COLLAPSIBLE,
+            ..Default::default()
+        };
+        SendMessageW(timeline.list, LVM_INSERTGROUP, WPARAM(usize::MAX), LPARAM(&group as *const _ as isize));
+        SendMessageW(timeline.jump, LB_ADDSTRING, WPARAM(0), LPARAM(header_wide.as_ptr() as isize));
+
+        for &index in members {
+            let entry = &timeline.entries[index];
+            let path = std::path::Path::new(&entry.path);
+            let name = path.file_name().map_or(entry.path.clone(), |name| name.to_string_lossy().to_string());
+            let row = timeline.rows.len();
+            let mut name_wide = to_wide(&name);
+            let item = LVITEMW {
+                mask: LVIF_TEXT | LVIF_GROUPID,
+                iItem: row as i32,
+                pszText: PWSTR::from_raw(name_wide.as_mut_ptr()),
+                iGroupId: group_id as i32,
+                ..Default::default()
+            };
+            SendMessageW(timeline.list, LVM_INSERTITEMW, WPARAM(0), LPARAM(&item as *const _ as isize));
+            set_cell(timeline.list, row, COLUMN_FOLDER, &path.parent().map_or(String::new(), |folder| folder.to_string_lossy().to_string()));
+            let date = if by_created { entry.created } else { entry.modified };
+            if let Some(date) = date {
+                set_cell(timeline.list, row, COLUMN_DATE, &DateTime::<Local>::from(date).format("%Y-%m-%d %H:%M").to_string());
+            }
+            if !entry.is_folder {
+                set_cell(timeline.list, row, COLUMN_SIZE, &format_bytes(entry.size, &strings));
+            }
+            timeline.rows.push(index);
+        }
+    }
+
+    SendMessageW(timeline.list, WM_SETREDRAW, WPARAM(1), LPARAM(0));
+    InvalidateRect(timeline.list, None, TRUE);
+
+    let summary = fill(&strings.timeline_summary, &[
+        ("count", &timeline.entries.len().to_string()),
+        ("groups", &timeline.groups.len().to_string()),
+    ]);
+    let _ = SetWindowTextW(GetDlgItem(window, ID_TIMELINE_STATUS), PCWSTR::from_raw(to_wide(&summary).as_ptr()));
+}
+
+// Scrolls the list so the group's header is at the top and selects its
+// first row
+unsafe fn jump_to_group(window: HWND, group_id: usize) {
+    let Some(timeline) = timeline(window) else {
+        return;
+    };
+    if group_id >= timeline.groups.len() {
+        return;
+    }
+
+    // LVM_GETGROUPRECT reads which part of the group to measure from `top`
+    let mut group_rect = RECT { top: LVGGR_HEADER as i32, ..Default::default() };
+    SendMessageW(timeline.list, LVM_GETGROUPRECT, WPARAM(group_id), LPARAM(&mut group_rect as *mut _ as isize));
+    let mut header_rect = RECT::default();
+    let header = HWND(SendMessageW(timeline.list, LVM_GETHEADER, WPARAM(0), LPARAM(0)).0);
+    let _ = GetWindowRect(header, &mut header_rect);
+    SendMessageW(timeline.list, LVM_SCROLL, WPARAM(0), LPARAM((group_rect.top - (header_rect.bottom - header_rect.top)) as isize));
+
+    let first_row = timeline.groups[..group_id].iter().map(|(_, members)| members.len()).sum::<usize>();
+    let state = LVITEMW {
+        stateMask: LIST_VIEW_ITEM_STATE_FLAGS(LVIS_SELECTED.0 | LVIS_FOCUSED.0),
+        state: LIST_VIEW_ITEM_STATE_FLAGS(LVIS_SELECTED.0 | LVIS_FOCUSED.0),
+        ..Default::default()
+    };
+    SendMessageW(timeline.list, LVM_SETITEMSTATE, WPARAM(first_row), LPARAM(&state as *const _ as isize));
+}
+
+// Shows the row's file in Explorer
+unsafe fn open_row(window: HWND, row: usize) {
+    let Some(timeline) = timeline(window) else {
+        return;
+    };
+    let Some(entry) = timeline.rows.get(row).and_then(|&index| timeline.entries.get(index)) else {
+        return;
+    };
+
+    let arguments = format!("/select,\"{}\"", entry.path);
+    ShellExecuteW(
+        None,
+        w!("open"),
+        w!("explorer.exe"),
+        PCWSTR::from_raw(to_wide(&arguments).as_ptr()),
+        None,
+        SW_SHOWNORMAL,
+    );
+}
+
+extern "system" fn timeline_window_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_TIMELINE_LOADED => {
+                let entries = *Box::from_raw(wparam.0 as *mut Vec<TimelineEntry>);
+                if let Some(timeline) = timeline(window) {
+                    timeline.entries = entries;
+                    show_groups(window);
+                }
+                LRESULT(0)
+            }
+            WM_NOTIFY => {
+                let header = &*(lparam.0 as *const NMHDR);
+                if header.idFrom == ID_TIMELINE_LIST as usize && header.code == NM_DBLCLK {
+                    let activate = &*(lparam.0 as *const NMITEMACTIVATE);
+                    if activate.iItem >= 0 {
+                        open_row(window, activate.iItem as usize);
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                let id = (wparam.0 & 0xFFFF) as i32;
+                let notification = ((wparam.0 >> 16) & 0xFFFF) as u32;
+                match id {
+                    ID_TIMELINE_DATE if notification == CBN_SELCHANGE => show_groups(window),
+                    ID_TIMELINE_JUMP if notification == LBN_SELCHANGE => {
+                        let index = SendMessageW(GetDlgItem(window, ID_TIMELINE_JUMP), LB_GETCURSEL, WPARAM(0), LPARAM(0)).0;
+                        if index >= 0 {
+                            jump_to_group(window, index as usize);
+                        }
+                    }
+                    ID_TIMELINE_CLOSE => {
+                        let _ = DestroyWindow(window);
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(window);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                let timeline = SetWindowLongPtrW(window, GWLP_USERDATA, 0) as *mut Timeline;
+                if !timeline.is_null() {
+                    drop(Box::from_raw(timeline));
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
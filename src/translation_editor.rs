@@ -0,0 +1,379 @@
+use std::collections::BTreeSet;
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{HBRUSH, HFONT, COLOR_BTNFACE},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Controls::*,
+            Input::KeyboardAndMouse::EnableWindow,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::lang::{get_current_language, get_strings, get_translation_entries, save_translations, TranslationEntry};
+use crate::logger::log_error;
+use crate::thumbnail::to_wide;
+
+// Sent to the owner window after translations were saved and reloaded,
+// so menus and labels can be rebuilt with the new text
+pub const WM_TRANSLATIONS_CHANGED: u32 = WM_USER + 107;
+
+const EDITOR_WINDOW_CLASS: &str = "EverythingLikeTranslationEditor";
+
+const EDITOR_WIDTH: i32 = 760;
+const EDITOR_HEIGHT: i32 = 540;
+const MARGIN: i32 = 10;
+const BUTTON_WIDTH: i32 = 80;
+const BUTTON_HEIGHT: i32 = 26;
+
+// Control IDs
+const ID_TRANSLATION_LIST: i32 = 9501;
+const ID_TRANSLATION_EDIT: i32 = 9502;
+const ID_TRANSLATION_SAVE: i32 = 9503;
+const ID_TRANSLATION_CLOSE: i32 = 9504;
+
+// List view columns
+const COLUMN_TRANSLATION: i32 = 2;
+
+struct TranslationEditor {
+    owner: HWND,
+    list: HWND,
+    edit: HWND,
+    entries: Vec<TranslationEntry>,
+    // Row shown in the edit box
+    selected: Option<usize>,
+    // Keys edited since the last save
+    changed: BTreeSet<String>,
+    // Set while the edit box is filled from code, so EN_CHANGE is ignored
+    updating: bool,
+}
+
+// The window's TranslationEditor, kept in GWLP_USERDATA
+unsafe fn translation_editor(window: HWND) -> Option<&'static mut TranslationEditor> {
+    let editor = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut TranslationEditor;
+    editor.as_mut()
+}
+
+// Line breaks are edited as \n and \r, like in the language files
+fn to_display(value: &str) -> String {
+    value.replace('\r', "\\r").replace('\n', "\\n")
+}
+
+fn from_display(text: &str) -> String {
+    text.replace("\\r", "\r").replace("\\n", "\n")
+}
+
+pub fn register_translation_editor_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(translation_editor_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as isize),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeTranslationEditor"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+pub fn show_translation_editor(owner: HWND, font: HFONT) {
+    unsafe {
+        // Only one editor window is open at a time
+        let existing = FindWindowW(PCWSTR::from_raw(to_wide(EDITOR_WINDOW_CLASS).as_ptr()), None);
+        if existing.0 != 0 {
+            let _ = SetForegroundWindow(existing);
+            return;
+        }
+
+        let init = INITCOMMONCONTROLSEX {
+            dwSize: std::mem::size_of::<INITCOMMONCONTROLSEX>() as u32,
+            dwICC: ICC_LISTVIEW_CLASSES | ICC_STANDARD_CLASSES,
+        };
+        let _ = InitCommonControlsEx(&init);
+
+        let strings = get_strings();
+        let language = get_current_language();
+        let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+
+        // Center over the main window
+        let mut owner_rect = RECT::default();
+        let _ = GetWindowRect(owner, &mut owner_rect);
+        let x = owner_rect.left + ((owner_rect.right - owner_rect.left) - EDITOR_WIDTH) / 2;
+        let y = owner_rect.top + ((owner_rect.bottom - owner_rect.top) - EDITOR_HEIGHT) / 2;
+
+        let title = format!("{} - {} ({})", strings.translation_editor_title, language.display_name(), language.file_name());
+        let window = CreateWindowExW(
+            WS_EX_DLGMODALFRAME,
+            PCWSTR::from_raw(to_wide(EDITOR_WINDOW_CLASS).as_ptr()),
+            PCWSTR::from_raw(to_wide(&title).as_ptr()),
+            WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_VISIBLE,
+            x.max(0),
+            y.max(0),
+            EDITOR_WIDTH,
+            EDITOR_HEIGHT,
+            owner,
+            None,
+            instance,
+            None,
+        );
+
+        if window.0 == 0 {
+            log_error("Failed to create translation editor window");
+            return;
+        }
+
+        let mut client_rect = RECT::default();
+        let _ = GetClientRect(window, &mut client_rect);
+        let width = client_rect.right - MARGIN * 2;
+        let button_y = client_rect.bottom - MARGIN - BUTTON_HEIGHT;
+        let edit_y = button_y - MARGIN - 24;
+        let label_y = edit_y - 22;
+        let list_height = label_y - MARGIN * 2;
+
+        let list = CreateWindowExW(
+            WS_EX_CLIENTEDGE,
+            WC_LISTVIEWW,
+            PCWSTR::null(),
+            WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(LVS_REPORT | LVS_SINGLESEL | LVS_SHOWSELALWAYS),
+            MARGIN,
+            MARGIN,
+            width,
+            list_height,
+            window,
+            HMENU(ID_TRANSLATION_LIST as isize),
+            instance,
+            None,
+        );
+        SendMessageW(list, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        SendMessageW(list, LVM_SETEXTENDEDLISTVIEWSTYLE, WPARAM(0), LPARAM((LVS_EX_FULLROWSELECT | LVS_EX_GRIDLINES) as isize));
+
+        let column_width = (width - 24) / 3;
+        for (index, name) in [&strings.translation_key, &strings.translation_english, &strings.translation_value].iter().enumerate() {
+            let mut name_wide = to_wide(name);
+            let column = LVCOLUMNW {
+                mask: LVCF_TEXT | LVCF_WIDTH,
+                cx: column_width,
+                pszText: PWSTR::from_raw(name_wide.as_mut_ptr()),
+                ..Default::default()
+            };
+            SendMessageW(list, LVM_INSERTCOLUMNW, WPARAM(index), LPARAM(&column as *const _ as isize));
+        }
+
+        let entries = get_translation_entries();
+        for (row, entry) in entries.iter().enumerate() {
+            let mut key_wide = to_wide(&entry.key);
+            let item = LVITEMW {
+                mask: LVIF_TEXT,
+                iItem: row as i32,
+                pszText: PWSTR::from_raw(key_wide.as_mut_ptr()),
+                ..Default::default()
+            };
+            SendMessageW(list, LVM_INSERTITEMW, WPARAM(0), LPARAM(&item as *const _ as isize));
+            set_cell(list, row, 1, &entry.english);
+            set_cell(list, row, COLUMN_TRANSLATION, &entry.translation);
+        }
+
+        let controls = [
+            (w!("STATIC"), strings.translation_edit_label.clone(), WINDOW_STYLE(0), WINDOW_EX_STYLE::default(), -1, MARGIN, label_y, width, 20),
+            (w!("EDIT"), String::new(), WS_TABSTOP | WS_DISABLED | WINDOW_STYLE(ES_AUTOHSCROLL as u32), WS_EX_CLIENTEDGE, ID_TRANSLATION_EDIT, MARGIN, edit_y, width, 24),
+            (w!("BUTTON"), strings.translation_save.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), WINDOW_EX_STYLE::default(), ID_TRANSLATION_SAVE, client_rect.right - (MARGIN + BUTTON_WIDTH) * 2, button_y, BUTTON_WIDTH, BUTTON_HEIGHT),
+            (w!("BUTTON"), strings.stats_close.clone(), WS_TABSTOP | WINDOW_STYLE(BS_PUSHBUTTON as u32), WINDOW_EX_STYLE::default(), ID_TRANSLATION_CLOSE, client_rect.right - (MARGIN + BUTTON_WIDTH), button_y, BUTTON_WIDTH, BUTTON_HEIGHT),
+        ];
+        for (class, text, style, ex_style, id, x, y, width, height) in controls {
+            let control = CreateWindowExW(
+                ex_style,
+                class,
+                PCWSTR::from_raw(to_wide(&text).as_ptr()),
+                WS_CHILD | WS_VISIBLE | style,
+                x, y, width, height,
+                window,
+                HMENU(id as isize),
+                instance,
+                None,
+            );
+            SendMessageW(control, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        }
+
+        let editor = Box::new(TranslationEditor {
+            owner,
+            list,
+            edit: GetDlgItem(window, ID_TRANSLATION_EDIT),
+            entries,
+            selected: None,
+            changed: BTreeSet::new(),
+            updating: false,
+        });
+        SetWindowLongPtrW(window, GWLP_USERDATA, Box::into_raw(editor) as isize);
+    }
+}
+
+unsafe fn set_cell(list: HWND, row: usize, column: i32, text: &str) {
+    let mut text_wide = to_wide(&to_display(text));
+    let item = LVITEMW {
+        iSubItem: column,
+        pszText: PWSTR::from_raw(text_wide.as_mut_ptr()),
+        ..Default::default()
+    };
+    SendMessageW(list, LVM_SETITEMTEXTW, WPARAM(row), LPARAM(&item as *const _ as isize));
+}
+
+unsafe fn select_row(window: HWND, row: usize) {
+    if let Some(editor) = translation_editor(window) {
+        if let Some(entry) = editor.entries.get(row) {
+            editor.selected = Some(row);
+
+            editor.updating = true;
+            let _ = SetWindowTextW(editor.edit, PCWSTR::from_raw(to_wide(&to_display(&entry.translation)).as_ptr()));
+            editor.updating = false;
+            EnableWindow(editor.edit, TRUE);
+        }
+    }
+}
+
+unsafe fn update_translation(window: HWND) {
+    if let Some(editor) = translation_editor(window) {
+        let row = match editor.selected {
+            Some(row) if !editor.updating => row,
+            _ => return,
+        };
+
+        let length = GetWindowTextLengthW(editor.edit);
+        let mut buffer = vec![0u16; length.max(0) as usize + 1];
+        let read = GetWindowTextW(editor.edit, &mut buffer);
+        let value = from_display(&String::from_utf16_lossy(&buffer[..read.max(0) as usize]));
+
+        let entry = &mut editor.entries[row];
+        entry.translation = value;
+        editor.changed.insert(entry.key.clone());
+        set_cell(editor.list, row, COLUMN_TRANSLATION, &entry.translation);
+    }
+}
+
+// Returns false when saving failed and the editor should stay open
+unsafe fn save_changes(window: HWND) -> bool {
+    if let Some(editor) = translation_editor(window) {
+        if editor.changed.is_empty() {
+            return true;
+        }
+
+        let changes: Vec<(String, String)> = editor.entries
+            .iter()
+            .filter(|entry| editor.changed.contains(&entry.key))
+            .map(|entry| (entry.key.clone(), entry.translation.clone()))
+            .collect();
+
+        match save_translations(&changes) {
+            Ok(_) => {
+                editor.changed.clear();
+                SendMessageW(editor.owner, WM_TRANSLATIONS_CHANGED, WPARAM(0), LPARAM(0));
+                true
+            }
+            Err(e) => {
+                let strings = get_strings();
+                let message = format!("{}\n{}", strings.error_save_translations, e);
+                MessageBoxW(
+                    window,
+                    PCWSTR::from_raw(to_wide(&message).as_ptr()),
+                    w!("Error"),
+                    MB_ICONERROR | MB_OK,
+                );
+                false
+            }
+        }
+    } else {
+        true
+    }
+}
+
+// Asks before throwing away unsaved edits; returns true when the window may close
+unsafe fn confirm_close(window: HWND) -> bool {
+    let has_changes = translation_editor(window).is_some_and(|editor| !editor.changed.is_empty());
+    if !has_changes {
+        return true;
+    }
+
+    let strings = get_strings();
+    let result = MessageBoxW(
+        window,
+        PCWSTR::from_raw(to_wide(&strings.translation_unsaved).as_ptr()),
+        PCWSTR::from_raw(to_wide(&strings.confirm_title).as_ptr()),
+        MB_YESNOCANCEL | MB_ICONQUESTION,
+    );
+    match result {
+        IDYES => save_changes(window),
+        IDNO => true,
+        _ => false,
+    }
+}
+
+extern "system" fn translation_editor_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_NOTIFY => {
+                let header = &*(lparam.0 as *const NMHDR);
+                if header.idFrom == ID_TRANSLATION_LIST as usize && header.code == LVN_ITEMCHANGED {
+                    let change = &*(lparam.0 as *const NMLISTVIEW);
+                    let now_selected = change.uNewState & LVIS_SELECTED.0 != 0;
+                    let was_selected = change.uOldState & LVIS_SELECTED.0 != 0;
+                    if now_selected && !was_selected && change.iItem >= 0 {
+                        select_row(window, change.iItem as usize);
+                    }
+                }
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                let id = (wparam.0 & 0xFFFF) as i32;
+                let notification = ((wparam.0 >> 16) & 0xFFFF) as u32;
+                match id {
+                    ID_TRANSLATION_EDIT if notification == EN_CHANGE => {
+                        update_translation(window);
+                    }
+                    ID_TRANSLATION_SAVE => {
+                        save_changes(window);
+                    }
+                    ID_TRANSLATION_CLOSE => {
+                        if confirm_close(window) {
+                            let _ = DestroyWindow(window);
+                        }
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                if confirm_close(window) {
+                    let _ = DestroyWindow(window);
+                }
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                let editor = SetWindowLongPtrW(window, GWLP_USERDATA, 0) as *mut TranslationEditor;
+                if !editor.is_null() {
+                    drop(Box::from_raw(editor));
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
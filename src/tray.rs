@@ -0,0 +1,65 @@
+use windows::Win32::{
+    Foundation::*,
+    UI::{
+        Shell::*,
+        WindowsAndMessaging::*,
+    },
+};
+
+// Sent to the owner window for mouse events on the tray icon;
+// the mouse message is in the low word of LPARAM
+pub const WM_TRAY_ICON: u32 = WM_USER + 106;
+
+const TRAY_ICON_ID: u32 = 1;
+
+pub fn add_tray_icon(window: HWND, tooltip: &str) -> bool {
+    unsafe {
+        let mut data = notify_icon_data(window);
+        data.uFlags = NIF_MESSAGE | NIF_ICON | NIF_TIP;
+        data.uCallbackMessage = WM_TRAY_ICON;
+        data.hIcon = LoadIconW(None, IDI_APPLICATION).unwrap_or_default();
+
+        // The tip is a fixed-size, null-terminated buffer
+        for (slot, unit) in data.szTip.iter_mut().zip(tooltip.encode_utf16().take(127)) {
+            *slot = unit;
+        }
+
+        Shell_NotifyIconW(NIM_ADD, &data).as_bool()
+    }
+}
+
+// Shows a notification from the tray icon, which must have been added.
+// Clicking it sends NIN_BALLOONUSERCLICK with WM_TRAY_ICON.
+pub fn show_tray_balloon(window: HWND, title: &str, text: &str) -> bool {
+    unsafe {
+        let mut data = notify_icon_data(window);
+        data.uFlags = NIF_INFO;
+        data.dwInfoFlags = NIIF_INFO;
+
+        // Both are fixed-size, null-terminated buffers
+        for (slot, unit) in data.szInfoTitle.iter_mut().zip(title.encode_utf16().take(63)) {
+            *slot = unit;
+        }
+        for (slot, unit) in data.szInfo.iter_mut().zip(text.encode_utf16().take(255)) {
+            *slot = unit;
+        }
+
+        Shell_NotifyIconW(NIM_MODIFY, &data).as_bool()
+    }
+}
+
+pub fn remove_tray_icon(window: HWND) {
+    unsafe {
+        let data = notify_icon_data(window);
+        let _ = Shell_NotifyIconW(NIM_DELETE, &data);
+    }
+}
+
+fn notify_icon_data(window: HWND) -> NOTIFYICONDATAW {
+    NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+        hWnd: window,
+        uID: TRAY_ICON_ID,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,118 @@
+use windows::Win32::{
+    Foundation::*,
+    UI::WindowsAndMessaging::PostMessageW,
+};
+use crate::https_client::https_get;
+
+// GitHub's description of the newest published release
+const LATEST_RELEASE_API: &str = "https://api.github.com/repos/onlyclxy/EverythingLike/releases/latest";
+
+// Where to send people when the reply has no page of its own
+pub const RELEASES_PAGE: &str = "https://github.com/onlyclxy/EverythingLike/releases";
+
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub struct Release {
+    // The tag without its "v" prefix, e.g. "0.2.0"
+    pub version: String,
+    pub page_url: String,
+    // The executable to install, when the release has one
+    pub asset: Option<ReleaseAsset>,
+}
+
+pub struct ReleaseAsset {
+    pub name: String,
+    pub url: String,
+    // Lowercase hex from GitHub's "digest" field, if it gave one
+    pub sha256: Option<String>,
+    // A "<name>.sha256" or SHA256SUMS file published next to it
+    pub checksum_url: Option<String>,
+}
+
+impl Release {
+    pub fn is_newer(&self) -> bool {
+        is_newer_version(&self.version, CURRENT_VERSION)
+    }
+}
+
+pub fn fetch_latest_release() -> Result<Release, String> {
+    let body = https_get(LATEST_RELEASE_API, "application/vnd.github+json")?;
+    let reply: serde_json::Value = serde_json::from_slice(&body).map_err(|e| e.to_string())?;
+
+    let tag = reply["tag_name"].as_str().ok_or("The reply names no release")?;
+    Ok(Release {
+        version: tag.trim_start_matches(['v', 'V']).to_string(),
+        page_url: reply["html_url"].as_str().unwrap_or(RELEASES_PAGE).to_string(),
+        asset: find_executable_asset(&reply["assets"]),
+    })
+}
+
+// The release's .exe, preferring one named after the app
+fn find_executable_asset(assets: &serde_json::Value) -> Option<ReleaseAsset> {
+    let assets = assets.as_array()?;
+    let name_of = |asset: &serde_json::Value| asset["name"].as_str().unwrap_or("").to_lowercase();
+
+    let executables: Vec<&serde_json::Value> = assets.iter().filter(|asset| name_of(asset).ends_with(".exe")).collect();
+    let executable = executables
+        .iter()
+        .find(|asset| {
+            let name = name_of(asset);
+            name.contains("everything-like") || name.contains("everythinglike")
+        })
+        .or_else(|| executables.first())?;
+
+    let name = executable["name"].as_str()?.to_string();
+    let checksum_names = [format!("{}.sha256", name.to_lowercase()), "sha256sums".to_string(), "sha256sums.txt".to_string()];
+    let checksum_url = checksum_names.iter().find_map(|checksum_name| {
+        assets
+            .iter()
+            .find(|asset| name_of(asset) == *checksum_name)
+            .and_then(|asset| asset["browser_download_url"].as_str())
+            .map(|url| url.to_string())
+    });
+
+    Some(ReleaseAsset {
+        url: executable["browser_download_url"].as_str()?.to_string(),
+        sha256: executable["digest"]
+            .as_str()
+            .and_then(|digest| digest.strip_prefix("sha256:"))
+            .map(|hex| hex.to_lowercase()),
+        checksum_url,
+        name,
+    })
+}
+
+// Fetches the latest release on a worker thread and posts `message` with a
+// boxed Result<Release, String> in WPARAM
+pub fn check_for_updates(window: HWND, message: u32) {
+    std::thread::spawn(move || {
+        let result = Box::new(fetch_latest_release());
+        let result_ptr = Box::into_raw(result) as usize;
+        unsafe {
+            if PostMessageW(window, message, WPARAM(result_ptr), LPARAM(0)).is_err() {
+                // The window is gone, so nobody will free the result
+                drop(Box::from_raw(result_ptr as *mut Result<Release, String>));
+            }
+        }
+    });
+}
+
+// Compares dotted versions number by number, so "0.10.0" is newer than
+// "0.9.1". A suffix such as "-beta" after a number is ignored.
+pub fn is_newer_version(candidate: &str, current: &str) -> bool {
+    let numbers = |version: &str| -> Vec<u64> {
+        version
+            .split('.')
+            .map(|part| {
+                let digits: String = part.chars().take_while(|c| c.is_ascii_digit()).collect();
+                digits.parse().unwrap_or(0)
+            })
+            .collect()
+    };
+    let (candidate, current) = (numbers(candidate), numbers(current));
+    let length = candidate.len().max(current.len());
+    let padded = |numbers: &[u64]| -> Vec<u64> {
+        (0..length).map(|i| numbers.get(i).copied().unwrap_or(0)).collect()
+    };
+    padded(&candidate) > padded(&current)
+}
@@ -0,0 +1,209 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use windows::{
+    core::{w, PCWSTR},
+    Win32::{
+        Foundation::*,
+        System::Threading::{OpenProcess, WaitForSingleObject, PROCESS_SYNCHRONIZE},
+        UI::{
+            Shell::ShellExecuteW,
+            WindowsAndMessaging::{PostMessageW, SHOW_WINDOW_CMD, SW_HIDE, SW_SHOWNORMAL},
+        },
+    },
+};
+use crate::file_hash::{Sha256, to_hex};
+use crate::https_client::{https_download, https_get};
+use crate::logger::{log_info, log_warn};
+use crate::shell_verb::{argument_value, quote_argument};
+use crate::thumbnail::to_wide;
+use crate::update_check::ReleaseAsset;
+
+// The downloaded executable is started with these to replace the
+// installed one once the running app has exited
+const APPLY_UPDATE_ARG: &str = "--apply-update";
+const UPDATE_PID_ARG: &str = "--update-pid";
+
+// How long the helper waits for the app to exit, then to be able to
+// overwrite its executable
+const EXIT_TIMEOUT_MS: u32 = 30_000;
+const REPLACE_ATTEMPTS: u32 = 20;
+const REPLACE_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+fn update_folder() -> PathBuf {
+    std::env::temp_dir().join("EverythingLike").join("update")
+}
+
+// Downloads the release executable on a worker thread. `progress_message`
+// is posted with the percentage in WPARAM while it arrives, then
+// `done_message` with a boxed Result<PathBuf, String>: the verified file,
+// or why it couldn't be had.
+pub fn start_update_download(window: HWND, asset: ReleaseAsset, progress_message: u32, done_message: u32) {
+    std::thread::spawn(move || {
+        let result = download_update(&asset, |percent| unsafe {
+            let _ = PostMessageW(window, progress_message, WPARAM(percent as usize), LPARAM(0));
+        });
+        match &result {
+            Ok(path) => log_info(&format!("Downloaded and verified update {}", path.display())),
+            Err(e) => log_warn(&format!("Update download failed: {}", e)),
+        }
+
+        let result_ptr = Box::into_raw(Box::new(result)) as usize;
+        unsafe {
+            if PostMessageW(window, done_message, WPARAM(result_ptr), LPARAM(0)).is_err() {
+                // The window is gone, so nobody will free the result
+                drop(Box::from_raw(result_ptr as *mut Result<PathBuf, String>));
+            }
+        }
+    });
+}
+
+// The file is only kept when its SHA-256 matches the one the release
+// publishes; a release without one can't be installed this way
+fn download_update<F: FnMut(u32)>(asset: &ReleaseAsset, mut on_progress: F) -> Result<PathBuf, String> {
+    let expected = match (&asset.sha256, &asset.checksum_url) {
+        (Some(sha256), _) => sha256.clone(),
+        (None, Some(checksum_url)) => {
+            let checksums = https_get(checksum_url, "text/plain")?;
+            parse_checksum(&String::from_utf8_lossy(&checksums), &asset.name)
+                .ok_or_else(|| format!("No SHA-256 checksum for {} in {}", asset.name, checksum_url))?
+        }
+        (None, None) => return Err(format!("The release publishes no SHA-256 checksum for {}", asset.name)),
+    };
+
+    let folder = update_folder();
+    let _ = std::fs::remove_dir_all(&folder);
+    std::fs::create_dir_all(&folder).map_err(|e| e.to_string())?;
+
+    // Only the file name is used, so the asset can't name a path elsewhere
+    let file_name = Path::new(&asset.name).file_name().ok_or("The release asset has no file name")?;
+    let path = folder.join(file_name);
+
+    let mut hasher = Sha256::new()?;
+    let mut last_percent = None;
+    https_download(&asset.url, &path, |data, written, total| {
+        hasher.update(data)?;
+        if let Some(total) = total.filter(|&total| total > 0) {
+            let percent = (written.min(total) * 100 / total) as u32;
+            if last_percent != Some(percent) {
+                last_percent = Some(percent);
+                on_progress(percent);
+            }
+        }
+        Ok(())
+    })?;
+
+    let actual = to_hex(&hasher.finish()?);
+    if !actual.eq_ignore_ascii_case(&expected) {
+        let _ = std::fs::remove_file(&path);
+        return Err(format!("Checksum mismatch for {}: expected {}, got {}", asset.name, expected, actual));
+    }
+    Ok(path)
+}
+
+// Finds the digest for `name` in a "<hex>  <name>" list, or takes the only
+// digest of a "<name>.sha256" file
+fn parse_checksum(text: &str, name: &str) -> Option<String> {
+    let is_digest = |token: &str| token.len() == 64 && token.chars().all(|c| c.is_ascii_hexdigit());
+    let mut lone_digest = None;
+    for line in text.lines() {
+        let mut tokens = line.split_whitespace();
+        let Some(digest) = tokens.next().filter(|token| is_digest(token)) else {
+            continue;
+        };
+        match tokens.next() {
+            // "*" marks a binary-mode entry
+            Some(file) if file.trim_start_matches('*').eq_ignore_ascii_case(name) => {
+                return Some(digest.to_lowercase());
+            }
+            Some(_) => {}
+            None => lone_digest = Some(digest.to_lowercase()),
+        }
+    }
+    lone_digest
+}
+
+// Starts the downloaded executable as the helper that swaps it in once
+// this process has exited
+pub fn launch_update_helper(update_exe: &Path) -> Result<(), String> {
+    let current_exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let arguments = format!(
+        "{} {} {} {}",
+        APPLY_UPDATE_ARG,
+        quote_argument(&current_exe.to_string_lossy()),
+        UPDATE_PID_ARG,
+        std::process::id()
+    );
+    // Hidden, so its console doesn't flash up
+    launch(update_exe, &arguments, SW_HIDE)?;
+    log_info(&format!("Started the update helper {}", update_exe.display()));
+    Ok(())
+}
+
+// The executable to replace and the process to wait for, when this
+// process was started as the update helper
+pub fn apply_update_argument() -> Option<(PathBuf, u32)> {
+    let target = argument_value(APPLY_UPDATE_ARG)?;
+    let pid = argument_value(UPDATE_PID_ARG).and_then(|pid| pid.parse().ok()).unwrap_or(0);
+    Some((PathBuf::from(target), pid))
+}
+
+// Runs as the helper: waits for the old app to exit, copies this
+// executable over it and starts the result
+pub fn apply_update(target: &Path, pid: u32) -> Result<(), String> {
+    unsafe {
+        if let Ok(process) = OpenProcess(PROCESS_SYNCHRONIZE, FALSE, pid) {
+            WaitForSingleObject(process, EXIT_TIMEOUT_MS);
+            let _ = CloseHandle(process);
+        }
+    }
+
+    let source = std::env::current_exe().map_err(|e| e.to_string())?;
+    let mut attempt = 0;
+    loop {
+        // The old executable can stay locked for a moment after its exit
+        match std::fs::copy(&source, target) {
+            Ok(_) => break,
+            Err(e) if attempt + 1 < REPLACE_ATTEMPTS => {
+                attempt += 1;
+                log_warn(&format!("Replacing {} failed, retrying: {}", target.display(), e));
+                std::thread::sleep(REPLACE_RETRY_DELAY);
+            }
+            Err(e) => return Err(format!("Could not replace {}: {}", target.display(), e)),
+        }
+    }
+
+    log_info(&format!("Updated {}", target.display()));
+    launch(target, "", SW_SHOWNORMAL)
+}
+
+fn launch(program: &Path, arguments: &str, show: SHOW_WINDOW_CMD) -> Result<(), String> {
+    let program_wide = to_wide(&program.to_string_lossy());
+    let arguments_wide = to_wide(arguments);
+    let result = unsafe {
+        ShellExecuteW(
+            None,
+            w!("open"),
+            PCWSTR::from_raw(program_wide.as_ptr()),
+            PCWSTR::from_raw(arguments_wide.as_ptr()),
+            PCWSTR::null(),
+            show,
+        )
+    };
+
+    // Values up to 32 are errors
+    if result.0 <= 32 {
+        return Err(format!("Could not start {} (error {})", program.display(), result.0));
+    }
+    Ok(())
+}
+
+// Removes what an earlier update left behind. The helper may still be
+// exiting when the updated app starts, in which case this is tried again
+// on the next start.
+pub fn remove_update_files() {
+    let folder = update_folder();
+    let running_from_folder = std::env::current_exe().is_ok_and(|exe| exe.starts_with(&folder));
+    if !running_from_folder && folder.exists() {
+        let _ = std::fs::remove_dir_all(&folder);
+    }
+}
@@ -0,0 +1,91 @@
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::ERROR_FILE_NOT_FOUND,
+        System::Registry::*,
+    },
+};
+use crate::http_server::percent_decode;
+use crate::logger::log_info;
+use crate::shell_verb::{create_key, path_argument_value, set_string};
+use crate::thumbnail::to_wide;
+
+// Registering a scheme under the user's classes needs no elevation
+const PROTOCOL_KEY: &str = "Software\\Classes\\es";
+const SCHEME: &str = "es:";
+
+// Command line argument the protocol handler starts the app with
+const URL_ARG: &str = "--url";
+
+// True when es:// links open this app for the current user
+pub fn is_url_protocol_registered() -> bool {
+    unsafe {
+        let key_wide = to_wide(&format!("{}\\shell\\open\\command", PROTOCOL_KEY));
+        let mut key = HKEY::default();
+        if RegOpenKeyExW(HKEY_CURRENT_USER, PCWSTR::from_raw(key_wide.as_ptr()), 0, KEY_QUERY_VALUE, &mut key).is_err() {
+            return false;
+        }
+        let _ = RegCloseKey(key);
+        true
+    }
+}
+
+// Adds or removes the es:// scheme. Links run the current executable with
+// --url and the whole link.
+pub fn set_url_protocol(enabled: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if enabled {
+        let exe_path = std::env::current_exe()?;
+        let icon = format!("\"{}\",0", exe_path.display());
+        let command = format!("\"{}\" {} \"%1\"", exe_path.display(), URL_ARG);
+        unsafe {
+            // The empty "URL Protocol" value is what marks the key as a scheme
+            let key = create_key(PROTOCOL_KEY)?;
+            let result = set_string(key, None, "URL:EverythingLike Search")
+                .and_then(|_| set_string(key, Some("URL Protocol"), ""));
+            let _ = RegCloseKey(key);
+            result?;
+
+            let key = create_key(&format!("{}\\DefaultIcon", PROTOCOL_KEY))?;
+            let result = set_string(key, None, &icon);
+            let _ = RegCloseKey(key);
+            result?;
+
+            let key = create_key(&format!("{}\\shell\\open\\command", PROTOCOL_KEY))?;
+            let result = set_string(key, None, &command);
+            let _ = RegCloseKey(key);
+            result?;
+        }
+    } else {
+        let key_wide = to_wide(PROTOCOL_KEY);
+        unsafe {
+            match RegDeleteTreeW(HKEY_CURRENT_USER, PCWSTR::from_raw(key_wide.as_ptr())) {
+                // Already gone is what we wanted
+                Err(e) if e.code() == ERROR_FILE_NOT_FOUND.to_hresult() => {}
+                other => other?,
+            }
+        }
+    }
+
+    log_info(&format!("es:// protocol {}", if enabled { "registered" } else { "removed" }));
+    Ok(())
+}
+
+// The query of the es:// link passed with --url. A link ending in a
+// backslash loses it to the quoting of "%1" like a drive root does.
+pub fn url_search_argument() -> Option<String> {
+    path_argument_value(URL_ARG).and_then(|url| query_from_url(&url))
+}
+
+// "es://ext:pdf%20report" -> "ext:pdf report". Browsers may drop the
+// slashes or add one at the end, so both are optional.
+fn query_from_url(url: &str) -> Option<String> {
+    let scheme = url.get(..SCHEME.len())?;
+    if !scheme.eq_ignore_ascii_case(SCHEME) {
+        return None;
+    }
+    let query = url[SCHEME.len()..].trim_start_matches('/').trim_end_matches('/');
+
+    // A "+" in a link is part of the query, not an encoded space
+    let query = percent_decode(&query.replace('+', "%2B"));
+    Some(query.trim().to_string()).filter(|query| !query.is_empty())
+}
@@ -0,0 +1,607 @@
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::time::{Duration, Instant};
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::*,
+        Media::MediaFoundation::*,
+        System::{
+            Com::{CoInitializeEx, CoUninitialize, COINIT_MULTITHREADED, StructuredStorage::PROPVARIANT},
+            LibraryLoader::GetModuleHandleW,
+            Variant::VT_I8,
+        },
+        UI::{
+            Input::KeyboardAndMouse::*,
+            WindowsAndMessaging::*,
+        },
+    },
+};
+use crate::lang::{fill, get_strings};
+use crate::logger::{log_error, log_info, log_warn};
+use crate::thumbnail::to_wide;
+
+// Posted by the player with a boxed PlayerEvent in WPARAM
+const WM_VIDEO_EVENT: u32 = WM_USER + 130;
+
+const VIDEO_PREVIEW_WINDOW_CLASS: &str = "EverythingLikeVideoPreview";
+
+// Frames are scaled down to this width on the player thread
+const PREVIEW_WIDTH: i32 = 480;
+const DEFAULT_PREVIEW_HEIGHT: i32 = 270;
+
+// Media Foundation times are in 100 ns units
+const TICKS_PER_SECOND: i64 = 10_000_000;
+// Used for stepping back when the stream doesn't give its frame rate
+const DEFAULT_FRAME_TICKS: i64 = TICKS_PER_SECOND / 30;
+
+const VIDEO_EXTENSIONS: [&str; 14] = [
+    "mp4", "m4v", "mov", "wmv", "asf", "avi", "mkv", "webm", "mpg", "mpeg",
+    "ts", "m2ts", "3gp", "3g2",
+];
+
+pub fn is_video(extension: &str) -> bool {
+    VIDEO_EXTENSIONS.iter().any(|known| known.eq_ignore_ascii_case(extension))
+}
+
+enum PlayerCommand {
+    TogglePause,
+    // Pauses and shows the next (+1) or previous (-1) frame
+    Step(i32),
+}
+
+// A scaled frame, 32bpp BGR top-down
+struct VideoFrame {
+    width: i32,
+    height: i32,
+    pixels: Vec<u8>,
+    // Presentation time in 100 ns units
+    time: i64,
+}
+
+enum PlayerEvent {
+    Opened { width: i32, height: i32, duration: i64 },
+    Frame(VideoFrame),
+    Paused(bool),
+    Failed(String),
+}
+
+struct VideoPreview {
+    path: String,
+    // Dropping it tells the player thread to stop
+    commands: Sender<PlayerCommand>,
+    frame: Option<VideoFrame>,
+    duration: i64,
+    paused: bool,
+    error: Option<String>,
+}
+
+// The window's VideoPreview, kept in GWLP_USERDATA
+unsafe fn video_preview(window: HWND) -> Option<&'static mut VideoPreview> {
+    let preview = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut VideoPreview;
+    preview.as_mut()
+}
+
+pub fn register_video_preview_window_class(instance: HMODULE) -> Result<()> {
+    unsafe {
+        let window_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(video_preview_window_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance.into(),
+            hIcon: HICON(0),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH(0),
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: w!("EverythingLikeVideoPreview"),
+            hIconSm: HICON(0),
+        };
+
+        let atom = RegisterClassExW(&window_class);
+        if atom == 0 {
+            return Err(Error::from_win32());
+        }
+
+        Ok(())
+    }
+}
+
+// F4: plays `path` muted and scaled down in a small popup at `anchor`
+// (screen coordinates), replacing a preview that is already open. Space
+// pauses, Left/Right step a frame and Esc closes.
+pub fn show_video_preview(owner: HWND, path: &str, anchor: POINT) {
+    unsafe {
+        let class_wide = to_wide(VIDEO_PREVIEW_WINDOW_CLASS);
+        let existing = FindWindowW(PCWSTR::from_raw(class_wide.as_ptr()), PCWSTR::null());
+        if existing.0 != 0 {
+            let _ = DestroyWindow(existing);
+        }
+
+        let style = WS_POPUP | WS_CAPTION | WS_SYSMENU;
+        let ex_style = WS_EX_TOOLWINDOW;
+        let mut window_rect = RECT { left: 0, top: 0, right: PREVIEW_WIDTH, bottom: DEFAULT_PREVIEW_HEIGHT };
+        let _ = AdjustWindowRectEx(&mut window_rect, style, FALSE, ex_style);
+        let (x, y) = fit_on_monitor(anchor, window_rect.right - window_rect.left, window_rect.bottom - window_rect.top);
+
+        let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+        let window = CreateWindowExW(
+            ex_style,
+            PCWSTR::from_raw(class_wide.as_ptr()),
+            PCWSTR::null(),
+            style | WS_VISIBLE,
+            x,
+            y,
+            window_rect.right - window_rect.left,
+            window_rect.bottom - window_rect.top,
+            owner,
+            None,
+            instance,
+            None,
+        );
+
+        if window.0 == 0 {
+            log_error("Failed to create video preview window");
+            return;
+        }
+
+        let (commands, receiver) = channel();
+        let preview = Box::new(VideoPreview {
+            path: path.to_string(),
+            commands,
+            frame: None,
+            duration: 0,
+            paused: false,
+            error: None,
+        });
+        SetWindowLongPtrW(window, GWLP_USERDATA, Box::into_raw(preview) as isize);
+        update_title(window);
+
+        log_info(&format!("Previewing video {}", path));
+        let path = path.to_string();
+        std::thread::spawn(move || run_player(window, &path, receiver));
+        let _ = SetForegroundWindow(window);
+    }
+}
+
+// Top left corner for a window of the given size at `anchor`, moved so it
+// stays within the anchor's monitor
+unsafe fn fit_on_monitor(anchor: POINT, width: i32, height: i32) -> (i32, i32) {
+    let mut monitor_info = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    let _ = GetMonitorInfoW(MonitorFromPoint(anchor, MONITOR_DEFAULTTONEAREST), &mut monitor_info);
+    let work_area = monitor_info.rcWork;
+    (
+        anchor.x.min(work_area.right - width).max(work_area.left),
+        anchor.y.min(work_area.bottom - height).max(work_area.top),
+    )
+}
+
+// Hands an event to the window. False once the window is gone, which is
+// the player's cue to stop.
+fn post_event(window: HWND, event: PlayerEvent) -> bool {
+    let event_ptr = Box::into_raw(Box::new(event)) as usize;
+    unsafe {
+        if PostMessageW(window, WM_VIDEO_EVENT, WPARAM(event_ptr), LPARAM(0)).is_err() {
+            drop(Box::from_raw(event_ptr as *mut PlayerEvent));
+            return false;
+        }
+    }
+    true
+}
+
+fn run_player(window: HWND, path: &str, commands: Receiver<PlayerCommand>) {
+    unsafe {
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        if let Err(e) = MFStartup(MF_VERSION, MFSTARTUP_LITE) {
+            post_event(window, PlayerEvent::Failed(e.message().to_string()));
+            CoUninitialize();
+            return;
+        }
+
+        if let Err(e) = Player::open(path).and_then(|player| player.run(window, &commands)) {
+            log_warn(&format!("Failed to play {}: {}", path, e));
+            post_event(window, PlayerEvent::Failed(e.message().to_string()));
+        }
+
+        let _ = MFShutdown();
+        CoUninitialize();
+    }
+}
+
+struct Player {
+    reader: IMFSourceReader,
+    width: i32,
+    height: i32,
+    stride: i32,
+    frame_ticks: i64,
+    duration: i64,
+    // A frame read past while stepping back, handed out next
+    ahead: Option<VideoFrame>,
+}
+
+impl Player {
+    // A source reader that decodes the first video stream to RGB32. The
+    // audio streams are never selected, so the preview is silent.
+    unsafe fn open(path: &str) -> Result<Self> {
+        let mut attributes = None;
+        MFCreateAttributes(&mut attributes, 1)?;
+        let attributes = attributes.ok_or_else(|| Error::from(E_FAIL))?;
+        attributes.SetUINT32(&MF_SOURCE_READER_ENABLE_VIDEO_PROCESSING, 1)?;
+
+        let path_wide = to_wide(path);
+        let reader = MFCreateSourceReaderFromURL(PCWSTR::from_raw(path_wide.as_ptr()), &attributes)?;
+        reader.SetStreamSelection(MF_SOURCE_READER_ALL_STREAMS.0 as u32, FALSE)?;
+        reader.SetStreamSelection(MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32, TRUE)?;
+
+        let media_type = MFCreateMediaType()?;
+        media_type.SetGUID(&MF_MT_MAJOR_TYPE, &MFMediaType_Video)?;
+        media_type.SetGUID(&MF_MT_SUBTYPE, &MFVideoFormat_RGB32)?;
+        reader.SetCurrentMediaType(MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32, None, &media_type)?;
+
+        let duration = reader
+            .GetPresentationAttribute(MF_SOURCE_READER_MEDIASOURCE.0 as u32, &MF_PD_DURATION)
+            .map_or(0, |value| value.Anonymous.Anonymous.Anonymous.hVal);
+
+        let mut player = Player { reader, width: 0, height: 0, stride: 0, frame_ticks: DEFAULT_FRAME_TICKS, duration, ahead: None };
+        player.read_format()?;
+        Ok(player)
+    }
+
+    // Frame size, row stride and frame rate of the decoded stream
+    unsafe fn read_format(&mut self) -> Result<()> {
+        let media_type = self.reader.GetCurrentMediaType(MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32)?;
+        let frame_size = media_type.GetUINT64(&MF_MT_FRAME_SIZE)?;
+        self.width = (frame_size >> 32) as i32;
+        self.height = frame_size as u32 as i32;
+        // Negative for bottom-up rows
+        self.stride = media_type.GetUINT32(&MF_MT_DEFAULT_STRIDE).map_or(self.width * 4, |stride| stride as i32);
+        if let Ok(frame_rate) = media_type.GetUINT64(&MF_MT_FRAME_RATE) {
+            let (numerator, denominator) = ((frame_rate >> 32) as i64, frame_rate as u32 as i64);
+            if numerator > 0 {
+                self.frame_ticks = TICKS_PER_SECOND * denominator / numerator;
+            }
+        }
+        Ok(())
+    }
+
+    fn preview_size(&self) -> (i32, i32) {
+        let width = self.width.min(PREVIEW_WIDTH).max(1);
+        let height = (self.height as i64 * width as i64 / self.width.max(1) as i64).max(1) as i32;
+        (width, height)
+    }
+
+    // The next frame, scaled down, or None at the end of the stream
+    unsafe fn read_frame(&mut self) -> Result<Option<VideoFrame>> {
+        if let Some(frame) = self.ahead.take() {
+            return Ok(Some(frame));
+        }
+        loop {
+            let mut flags = 0u32;
+            let mut time = 0i64;
+            let mut sample = None;
+            self.reader.ReadSample(
+                MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32,
+                0,
+                None,
+                Some(&mut flags),
+                Some(&mut time),
+                Some(&mut sample),
+            )?;
+            if flags & MF_SOURCE_READERF_ENDOFSTREAM.0 as u32 != 0 {
+                return Ok(None);
+            }
+            if flags & MF_SOURCE_READERF_CURRENTMEDIATYPECHANGED.0 as u32 != 0 {
+                self.read_format()?;
+            }
+            let Some(sample) = sample else { continue };
+
+            let buffer = sample.ConvertToContiguousBuffer()?;
+            let mut data = std::ptr::null_mut();
+            let mut length = 0u32;
+            buffer.Lock(&mut data, None, Some(&mut length))?;
+            let source = std::slice::from_raw_parts(data, length as usize);
+            let frame = self.scale(source, time);
+            let _ = buffer.Unlock();
+            return Ok(Some(frame));
+        }
+    }
+
+    // Nearest-neighbour scaling is plenty for a preview this size
+    fn scale(&self, source: &[u8], time: i64) -> VideoFrame {
+        let (width, height) = self.preview_size();
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        let row_bytes = self.stride.unsigned_abs() as usize;
+        for y in 0..height {
+            let source_y = (y as i64 * self.height as i64 / height as i64) as usize;
+            let source_row = if self.stride < 0 { self.height as usize - 1 - source_y } else { source_y };
+            for x in 0..width {
+                let source_x = (x as i64 * self.width as i64 / width as i64) as usize;
+                let from = source_row * row_bytes + source_x * 4;
+                let to = ((y * width + x) * 4) as usize;
+                if let Some(pixel) = source.get(from..from + 4) {
+                    pixels[to..to + 4].copy_from_slice(pixel);
+                }
+            }
+        }
+        VideoFrame { width, height, pixels, time }
+    }
+
+    unsafe fn seek(&mut self, time: i64) -> Result<()> {
+        let mut position = PROPVARIANT::default();
+        (*position.Anonymous.Anonymous).vt = VT_I8;
+        (*position.Anonymous.Anonymous).Anonymous.hVal = time.max(0);
+        self.ahead = None;
+        self.reader.SetCurrentPosition(&GUID::zeroed(), &position)
+    }
+
+    // Seeking lands on the key frame before `time`, so decode on from there
+    // to the frame showing at `time`
+    unsafe fn frame_at(&mut self, time: i64) -> Result<Option<VideoFrame>> {
+        self.seek(time)?;
+        let mut found = None;
+        while let Some(frame) = self.read_frame()? {
+            if frame.time > time + self.frame_ticks / 2 {
+                if found.is_none() {
+                    return Ok(Some(frame));
+                }
+                self.ahead = Some(frame);
+                break;
+            }
+            found = Some(frame);
+        }
+        Ok(found)
+    }
+
+    // Plays until the window closes, looping at the end. Frames are sent
+    // when they are due, measured from the first frame after a pause.
+    unsafe fn run(mut self, window: HWND, commands: &Receiver<PlayerCommand>) -> Result<()> {
+        let (width, height) = self.preview_size();
+        if !post_event(window, PlayerEvent::Opened { width, height, duration: self.duration }) {
+            return Ok(());
+        }
+
+        let mut playing = true;
+        let mut pending = None;
+        let mut clock: Option<(Instant, i64)> = None;
+        let mut last_time = 0i64;
+
+        loop {
+            let command = match pending.take() {
+                Some(command) => Some(command),
+                None if playing => match commands.try_recv() {
+                    Ok(command) => Some(command),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => return Ok(()),
+                },
+                None => match commands.recv() {
+                    Ok(command) => Some(command),
+                    Err(_) => return Ok(()),
+                },
+            };
+
+            match command {
+                Some(PlayerCommand::TogglePause) => {
+                    playing = !playing;
+                    clock = None;
+                    if !post_event(window, PlayerEvent::Paused(!playing)) {
+                        return Ok(());
+                    }
+                    continue;
+                }
+                Some(PlayerCommand::Step(direction)) => {
+                    if playing {
+                        playing = false;
+                        if !post_event(window, PlayerEvent::Paused(true)) {
+                            return Ok(());
+                        }
+                    }
+                    let frame = if direction < 0 {
+                        self.frame_at(last_time - self.frame_ticks)?
+                    } else {
+                        self.read_frame()?
+                    };
+                    if let Some(frame) = frame {
+                        last_time = frame.time;
+                        if !post_event(window, PlayerEvent::Frame(frame)) {
+                            return Ok(());
+                        }
+                    }
+                    continue;
+                }
+                None => {}
+            }
+
+            let Some(frame) = self.read_frame()? else {
+                self.seek(0)?;
+                clock = None;
+                continue;
+            };
+
+            let (start, start_time) = *clock.get_or_insert((Instant::now(), frame.time));
+            let due = start + Duration::from_micros(((frame.time - start_time).max(0) / 10) as u64);
+            let now = Instant::now();
+            if due > now {
+                match commands.recv_timeout(due - now) {
+                    Ok(command) => pending = Some(command),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => return Ok(()),
+                }
+            }
+
+            last_time = frame.time;
+            if !post_event(window, PlayerEvent::Frame(frame)) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// m:ss, or h:mm:ss for an hour or more
+fn format_time(ticks: i64) -> String {
+    let seconds = ticks.max(0) / TICKS_PER_SECOND;
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+unsafe fn update_title(window: HWND) {
+    let Some(preview) = video_preview(window) else { return };
+    let strings = get_strings();
+    let name = std::path::Path::new(&preview.path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(&preview.path);
+    let position = preview.frame.as_ref().map_or(0, |frame| frame.time);
+    let mut title = fill(
+        &strings.video_preview_title,
+        &[("name", name), ("position", &format_time(position)), ("duration", &format_time(preview.duration))],
+    );
+    if preview.paused {
+        title = fill(&strings.video_preview_paused, &[("title", &title)]);
+    }
+    let _ = SetWindowTextW(window, PCWSTR::from_raw(to_wide(&title).as_ptr()));
+}
+
+// Sizes the client area to the frame, keeping the window on its monitor
+unsafe fn fit_to_video(window: HWND, width: i32, height: i32) {
+    let style = WINDOW_STYLE(GetWindowLongW(window, GWL_STYLE) as u32);
+    let ex_style = WINDOW_EX_STYLE(GetWindowLongW(window, GWL_EXSTYLE) as u32);
+    let mut window_rect = RECT { left: 0, top: 0, right: width, bottom: height };
+    let _ = AdjustWindowRectEx(&mut window_rect, style, FALSE, ex_style);
+    let (window_width, window_height) = (window_rect.right - window_rect.left, window_rect.bottom - window_rect.top);
+
+    let mut current = RECT::default();
+    let _ = GetWindowRect(window, &mut current);
+    let (x, y) = fit_on_monitor(POINT { x: current.left, y: current.top }, window_width, window_height);
+    let _ = SetWindowPos(window, None, x, y, window_width, window_height, SWP_NOZORDER | SWP_NOACTIVATE);
+}
+
+unsafe fn paint(window: HWND) {
+    let mut paint_struct = PAINTSTRUCT::default();
+    let hdc = BeginPaint(window, &mut paint_struct);
+    let mut client_rect = RECT::default();
+    let _ = GetClientRect(window, &mut client_rect);
+
+    match video_preview(window) {
+        Some(VideoPreview { frame: Some(frame), .. }) => {
+            let bitmap_info = BITMAPINFO {
+                bmiHeader: BITMAPINFOHEADER {
+                    biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                    biWidth: frame.width,
+                    biHeight: -frame.height,
+                    biPlanes: 1,
+                    biBitCount: 32,
+                    biCompression: BI_RGB.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            StretchDIBits(
+                hdc,
+                0, 0, client_rect.right, client_rect.bottom,
+                0, 0, frame.width, frame.height,
+                Some(frame.pixels.as_ptr() as *const std::ffi::c_void),
+                &bitmap_info,
+                DIB_RGB_COLORS,
+                SRCCOPY,
+            );
+        }
+        preview => {
+            FillRect(hdc, &client_rect, HBRUSH(GetStockObject(BLACK_BRUSH).0));
+            let strings = get_strings();
+            let message = match preview.and_then(|preview| preview.error.as_ref()) {
+                Some(error) => fill(&strings.video_preview_failed, &[("error", error)]),
+                None => strings.image_viewer_loading.clone(),
+            };
+            let mut text: Vec<u16> = message.encode_utf16().collect();
+            let mut text_rect = RECT { left: 8, right: client_rect.right - 8, ..client_rect };
+            SetBkMode(hdc, TRANSPARENT);
+            SetTextColor(hdc, COLORREF(0x00C0C0C0));
+            let old_font = SelectObject(hdc, GetStockObject(DEFAULT_GUI_FONT));
+            DrawTextW(hdc, &mut text, &mut text_rect, DT_CENTER | DT_VCENTER | DT_SINGLELINE | DT_END_ELLIPSIS | DT_NOPREFIX);
+            SelectObject(hdc, old_font);
+        }
+    }
+
+    EndPaint(window, &paint_struct);
+}
+
+extern "system" fn video_preview_window_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match message {
+            WM_VIDEO_EVENT => {
+                let event = *Box::from_raw(wparam.0 as *mut PlayerEvent);
+                if let Some(preview) = video_preview(window) {
+                    match event {
+                        PlayerEvent::Opened { width, height, duration } => {
+                            preview.duration = duration;
+                            fit_to_video(window, width, height);
+                        }
+                        PlayerEvent::Frame(frame) => preview.frame = Some(frame),
+                        PlayerEvent::Paused(paused) => preview.paused = paused,
+                        PlayerEvent::Failed(error) => preview.error = Some(error),
+                    }
+                    update_title(window);
+                    InvalidateRect(window, None, FALSE);
+                }
+                LRESULT(0)
+            }
+            WM_PAINT => {
+                paint(window);
+                LRESULT(0)
+            }
+            WM_ERASEBKGND => LRESULT(1),
+            WM_KEYDOWN => {
+                let Some(preview) = video_preview(window) else { return LRESULT(0) };
+                let command = match VIRTUAL_KEY(wparam.0 as u16) {
+                    VK_SPACE => PlayerCommand::TogglePause,
+                    VK_RIGHT | VK_OEM_PERIOD => PlayerCommand::Step(1),
+                    VK_LEFT | VK_OEM_COMMA => PlayerCommand::Step(-1),
+                    VK_ESCAPE | VK_F4 => {
+                        let _ = DestroyWindow(window);
+                        return LRESULT(0);
+                    }
+                    _ => return DefWindowProcW(window, message, wparam, lparam),
+                };
+                let _ = preview.commands.send(command);
+                LRESULT(0)
+            }
+            WM_LBUTTONUP => {
+                if let Some(preview) = video_preview(window) {
+                    let _ = preview.commands.send(PlayerCommand::TogglePause);
+                }
+                LRESULT(0)
+            }
+            WM_ACTIVATE => {
+                // Like a tooltip, the preview goes away once the user moves on
+                if (wparam.0 & 0xFFFF) as u32 == WA_INACTIVE {
+                    let _ = PostMessageW(window, WM_CLOSE, WPARAM(0), LPARAM(0));
+                }
+                DefWindowProcW(window, message, wparam, lparam)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(window);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                let preview = SetWindowLongPtrW(window, GWLP_USERDATA, 0) as *mut VideoPreview;
+                if !preview.is_null() {
+                    drop(Box::from_raw(preview));
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}